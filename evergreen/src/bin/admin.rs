@@ -0,0 +1,407 @@
+use eg::common::user;
+use eg::idl;
+use eg::result::EgResult;
+use eg::Editor;
+use eg::EgValue;
+use evergreen as eg;
+use std::fs;
+use yaml_rust::{Yaml, YamlLoader};
+
+const HELP_TEXT: &str = r#"
+Provision org units, workstations, staff users, and SIP accounts
+from a YAML file.
+
+Objects are matched on their identifying field (org unit shortname,
+workstation name, usrname, SIP username).  Missing objects are
+created and existing objects are updated to match the file, so the
+same file may be applied repeatedly.
+
+All changes are applied in a single transaction.
+
+./eg-admin --file provision.yml
+
+Options
+
+    --file <path>
+        Required.  YAML provisioning file.
+
+    --dry-run
+        Apply the changes, then roll them back.
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+
+File Format
+
+    # ID of the staff account recorded as the creator/editor.
+    staff-account: 1
+
+    org-units:
+      - shortname: BR4
+        name: Example Branch 4
+        parent: SYS2        # shortname
+        ou-type: 3
+        email: br4@example.org
+        phone: 555-1234
+
+    workstations:
+      - name: BR4-circ-01
+        owning-lib: BR4
+
+    staff-users:
+      - usrname: br4circ
+        password: demo123
+        first-given-name: Branch
+        family-name: Circulator
+        home-ou: BR4
+        profile: 5          # permission group ID
+        ident-type: 3
+        barcode: 99999300001
+        email: br4circ@example.org
+        work-ous: [BR4]
+
+    sip-accounts:
+      - sip-username: br4sip
+        sip-password: sip-pass
+        usrname: br4circ
+        setting-group: 1
+        workstation: BR4-circ-01
+        enabled: true
+"#;
+
+/// Default ident type ("Other") for new users.
+const DEFAULT_IDENT_TYPE: i64 = 3;
+
+struct Provisioner {
+    editor: Editor,
+    created: usize,
+    updated: usize,
+}
+
+impl Provisioner {
+    /// Find the object matching `key`, creating it from `key` + `values`
+    /// if it does not exist, or updating any fields whose values differ.
+    fn apply(&mut self, classname: &str, key: EgValue, values: EgValue) -> EgResult<EgValue> {
+        let label = format!("{classname} {}", key.dump());
+
+        let mut obj = match self.editor.search(classname, key.clone())?.pop() {
+            Some(o) => o,
+            None => {
+                let mut obj = key;
+                for (k, v) in values.entries() {
+                    obj[k] = v.clone();
+                }
+
+                println!("Creating {label}");
+                self.created += 1;
+
+                let obj = EgValue::create(classname, obj)?;
+                return self.editor.create(obj);
+            }
+        };
+
+        let mut changed = false;
+
+        for (k, v) in values.entries() {
+            if obj[k].to_string() != v.to_string() {
+                obj[k] = v.clone();
+                changed = true;
+            }
+        }
+
+        if changed {
+            println!("Updating {label}");
+            self.updated += 1;
+            self.editor.update(obj.clone())?;
+        }
+
+        Ok(obj)
+    }
+
+    fn org_id(&mut self, shortname: &str) -> EgResult<i64> {
+        match self
+            .editor
+            .search("aou", eg::hash! {"shortname": shortname})?
+            .pop()
+        {
+            Some(o) => o.id(),
+            None => Err(format!("No such org unit: {shortname}").into()),
+        }
+    }
+
+    fn org_units(&mut self, list: &Yaml) -> EgResult<()> {
+        for y in list.as_vec().unwrap_or(&Vec::new()) {
+            let shortname = required_str(y, "shortname")?;
+
+            let mut values = eg::hash! {
+                "name": required_str(y, "name")?,
+                "ou_type": required_int(y, "ou-type")?,
+            };
+
+            if let Some(parent) = y["parent"].as_str() {
+                values["parent_ou"] = self.org_id(parent)?.into();
+            }
+
+            copy_optional(y, &mut values, &[("email", "email"), ("phone", "phone")]);
+
+            self.apply("aou", eg::hash! {"shortname": shortname}, values)?;
+        }
+
+        Ok(())
+    }
+
+    fn workstations(&mut self, list: &Yaml) -> EgResult<()> {
+        for y in list.as_vec().unwrap_or(&Vec::new()) {
+            let name = required_str(y, "name")?;
+            let owning_lib = self.org_id(required_str(y, "owning-lib")?)?;
+
+            self.apply(
+                "aws",
+                eg::hash! {"name": name},
+                eg::hash! {"owning_lib": owning_lib},
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn staff_users(&mut self, list: &Yaml) -> EgResult<()> {
+        for y in list.as_vec().unwrap_or(&Vec::new()) {
+            let usrname = required_str(y, "usrname")?;
+            let password = required_str(y, "password")?;
+
+            let mut values = eg::hash! {
+                "first_given_name": required_str(y, "first-given-name")?,
+                "family_name": required_str(y, "family-name")?,
+                "home_ou": self.org_id(required_str(y, "home-ou")?)?,
+                "profile": required_int(y, "profile")?,
+                "ident_type": y["ident-type"].as_i64().unwrap_or(DEFAULT_IDENT_TYPE),
+            };
+
+            copy_optional(y, &mut values, &[("email", "email")]);
+
+            let key = eg::hash! {"usrname": usrname, "deleted": "f"};
+
+            let exists = !self.editor.search("au", key.clone())?.is_empty();
+
+            if !exists {
+                // New users get their main password on create.
+                values["passwd"] = password.into();
+            }
+
+            let mut au = self.apply("au", key, values)?;
+            let user_id = au.id()?;
+
+            if exists
+                && !user::verify_migrated_password(&mut self.editor, user_id, password, false)?
+            {
+                println!("Updating password for {usrname}");
+
+                user::change_password(&mut self.editor, user_id, password)?;
+            }
+
+            // Numeric barcodes are parsed by YAML as integers.
+            let barcode = match &y["barcode"] {
+                Yaml::Integer(i) => Some(i.to_string()),
+                other => other.as_str().map(|s| s.to_string()),
+            };
+
+            if let Some(barcode) = barcode {
+                let card = self.apply(
+                    "ac",
+                    eg::hash! {"barcode": barcode},
+                    eg::hash! {"usr": user_id, "active": "t"},
+                )?;
+
+                if au["card"].as_int() != Some(card.id()?) {
+                    au["card"] = card["id"].clone();
+                    self.editor.update(au)?;
+                }
+            }
+
+            for org in y["work-ous"].as_vec().unwrap_or(&Vec::new()) {
+                let sn = org
+                    .as_str()
+                    .ok_or_else(|| format!("Invalid work-ous value for {usrname}"))?;
+
+                let work_ou = self.org_id(sn)?;
+
+                self.apply(
+                    "puwoum",
+                    eg::hash! {"usr": user_id, "work_ou": work_ou},
+                    eg::hash! {},
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sip_accounts(&mut self, list: &Yaml) -> EgResult<()> {
+        for y in list.as_vec().unwrap_or(&Vec::new()) {
+            let sip_username = required_str(y, "sip-username")?;
+            let sip_password = required_str(y, "sip-password")?;
+            let usrname = required_str(y, "usrname")?;
+
+            let user_id = match self
+                .editor
+                .search("au", eg::hash! {"usrname": usrname, "deleted": "f"})?
+                .pop()
+            {
+                Some(u) => u.id()?,
+                None => return Err(format!("No such user: {usrname}").into()),
+            };
+
+            // SIP logins are verified against the user's sip2 password.
+            if !user::verify_password(&mut self.editor, user_id, sip_password, "sip2")? {
+                println!("Setting SIP password for {sip_username}");
+
+                let query = eg::hash! {"from": ["actor.set_passwd", user_id, "sip2", sip_password]};
+                self.editor.json_query(query)?;
+            }
+
+            let mut values = eg::hash! {
+                "usr": user_id,
+                "setting_group": required_int(y, "setting-group")?,
+                "enabled": yaml_bool(&y["enabled"], true),
+                "sip_password": self.sip_password_id(user_id)?,
+            };
+
+            if let Some(name) = y["workstation"].as_str() {
+                let ws = self
+                    .editor
+                    .search("aws", eg::hash! {"name": name})?
+                    .pop()
+                    .ok_or_else(|| format!("No such workstation: {name}"))?;
+
+                values["workstation"] = ws["id"].clone();
+            }
+
+            self.apply("sipacc", eg::hash! {"sip_username": sip_username}, values)?;
+        }
+
+        Ok(())
+    }
+
+    /// ID of the sip2 password row for a user.
+    ///
+    /// The password class is taken from the IDL link on the SIP
+    /// account's sip_password field.
+    fn sip_password_id(&mut self, user_id: i64) -> EgResult<i64> {
+        let class = idl::get_class("sipacc")?;
+
+        let pw_class = class
+            .links()
+            .get("sip_password")
+            .map(|l| l.class().to_string())
+            .ok_or("IDL has no link for sipacc.sip_password")?;
+
+        let query = eg::hash! {"usr": user_id, "passwd_type": "sip2"};
+
+        match self.editor.search(&pw_class, query)?.pop() {
+            Some(pw) => pw.id(),
+            None => Err(format!("No sip2 password found for user {user_id}").into()),
+        }
+    }
+}
+
+fn required_str<'a>(y: &'a Yaml, key: &str) -> EgResult<&'a str> {
+    y[key]
+        .as_str()
+        .ok_or_else(|| format!("'{key}' value required: {y:?}").into())
+}
+
+fn required_int(y: &Yaml, key: &str) -> EgResult<i64> {
+    y[key]
+        .as_i64()
+        .ok_or_else(|| format!("Numeric '{key}' value required: {y:?}").into())
+}
+
+/// Evergreen-style "t"/"f" boolean from a YAML value.
+fn yaml_bool(y: &Yaml, default: bool) -> &'static str {
+    if y.as_bool().unwrap_or(default) {
+        "t"
+    } else {
+        "f"
+    }
+}
+
+/// Copy optional string values from YAML keys to object fields.
+fn copy_optional(y: &Yaml, values: &mut EgValue, keys: &[(&str, &str)]) {
+    for (key, field) in keys {
+        if let Some(v) = y[*key].as_str() {
+            values[*field] = v.into();
+        }
+    }
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optopt("", "file", "", "");
+    options.optflag("", "dry-run", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let filename = params.opt_str("file").ok_or("--file required")?;
+
+    let yaml_text =
+        fs::read_to_string(&filename).map_err(|e| format!("Cannot read {filename}: {e}"))?;
+
+    let docs = YamlLoader::load_from_str(&yaml_text)
+        .map_err(|e| format!("Cannot parse {filename}: {e}"))?;
+
+    let doc = docs.first().ok_or_else(|| format!("{filename} is empty"))?;
+
+    let staff_id = required_int(doc, "staff-account")?;
+
+    let client = eg::init::Builder::new().skip_host_settings().build()?;
+
+    let mut editor = Editor::new(&client);
+
+    let staff = editor
+        .retrieve("au", staff_id)?
+        .ok_or_else(|| format!("No such staff account: {staff_id}"))?;
+
+    editor.give_requestor(staff);
+    editor.xact_begin()?;
+
+    let mut prov = Provisioner {
+        editor,
+        created: 0,
+        updated: 0,
+    };
+
+    let result = prov
+        .org_units(&doc["org-units"])
+        .and_then(|_| prov.workstations(&doc["workstations"]))
+        .and_then(|_| prov.staff_users(&doc["staff-users"]))
+        .and_then(|_| prov.sip_accounts(&doc["sip-accounts"]));
+
+    if let Err(e) = result {
+        prov.editor.rollback()?;
+        return Err(e);
+    }
+
+    println!(
+        "Created {} and updated {} objects",
+        prov.created, prov.updated
+    );
+
+    if params.opt_present("dry-run") {
+        println!("Dry run; rolling back changes");
+        prov.editor.rollback()
+    } else {
+        prov.editor.commit()
+    }
+}
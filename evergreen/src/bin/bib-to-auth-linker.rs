@@ -170,7 +170,7 @@ impl BibLinker {
         let mut controlled_fields: Vec<ControlledField> = Vec::new();
 
         for bib_field in bib_fields {
-            let bib_tag = bib_field["tag"].as_str().unwrap();
+            let bib_tag = bib_field.str_at("tag")?;
 
             if !linkable_tag_prefixes.contains(&&bib_tag[..1]) {
                 continue;
@@ -178,14 +178,14 @@ impl BibLinker {
 
             let authority_field = &bib_field["authority_field"];
 
-            let auth_tag = authority_field["tag"].as_str().unwrap();
+            let auth_tag = authority_field.str_at("tag")?;
 
             // Ignore authority 18X fields
             if auth_tag[..2].eq("18") {
                 continue;
             }
 
-            let sf_string = authority_field["sf_list"].as_str().unwrap();
+            let sf_string = authority_field.str_at("sf_list")?;
             let mut subfields: Vec<String> = Vec::new();
 
             for sf in sf_string.split("") {
@@ -230,10 +230,10 @@ impl BibLinker {
         // matchy-ness
         for auth_id in auth_ids {
             for leader in maybe_leaders.iter() {
-                if leader["record"].as_i64().unwrap() == auth_id {
+                if leader.int_at("record")? == auth_id {
                     leaders.push(AuthLeader {
-                        auth_id: leader["record"].as_i64().unwrap(),
-                        value: leader["value"].as_str().unwrap().to_string(),
+                        auth_id,
+                        value: leader.str_at("value")?.to_string(),
                     });
                     break;
                 }
@@ -361,9 +361,9 @@ impl BibLinker {
 
     fn update_bib_record(&mut self, mut bre: EgValue, record: &marc::Record) -> Result<(), String> {
         let xml = record.to_xml()?;
-        let bre_id = bre["id"].as_i64().unwrap();
+        let bre_id = bre.int_at("id")?;
 
-        if bre["marc"].as_str().unwrap() == xml {
+        if bre.str_at("marc")? == xml {
             log::debug!("Skipping update of record {bre_id} -- no changes made");
             return Ok(());
         }
@@ -457,7 +457,7 @@ impl BibLinker {
             };
 
             for rec in recs {
-                auth_ids.push(rec["id"].as_i64().unwrap());
+                auth_ids.push(rec.int_at("id")?);
             }
 
             if searches.pop().is_none() {
@@ -498,11 +498,11 @@ impl BibLinker {
                 }
             };
 
-            if bre["deleted"].as_str().unwrap() == "t" {
+            if bre.bool_at("deleted")? {
                 continue;
             }
 
-            let xml = bre["marc"].as_str().unwrap();
+            let xml = bre.str_at("marc")?;
 
             let mut record = match marc::Record::from_xml(xml).next() {
                 Some(r) => r?,
@@ -0,0 +1,161 @@
+//! Cancel expired hold shelf holds and print a pick list.
+use eg::common::holdshelf::{self, ClearShelfOptions, PickListEntry};
+use eg::common::org;
+use eg::result::EgResult;
+use eg::{Editor, EgValue};
+use evergreen as eg;
+
+const HELP_TEXT: &str = r#"
+Cancel holds whose hold shelf expire time has passed and print a pick
+list of the items staff should pull from the shelf, along with what
+should happen to each item next:
+
+    hold     - Item fills another hold at the same library.
+    transit  - Item goes to another library to fill a hold or return home.
+    reshelve - Item goes back to the shelves.
+
+Each org unit is processed in its own transaction.
+
+./eg-clear-hold-shelf --org BR1 --org BR2 --notify
+
+Options
+
+    --org <id-or-shortname>
+        Required.  Clear the hold shelf at this org unit.  May be
+        repeated.
+
+    --descendants
+        Also clear the hold shelves of each org unit's descendants.
+
+    --notify
+        Create Action/Trigger events (hook
+        hold_request.cancel.expire_holds_shelf) for each canceled hold.
+
+    --dry-run
+        Build the pick list, then roll back the cancellations.
+
+    --json
+        Print the pick list as JSON, one line per org unit.
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+"#;
+
+fn org_id(editor: &mut Editor, value: &str) -> EgResult<i64> {
+    match value.parse::<i64>() {
+        Ok(id) => Ok(id),
+        Err(_) => org::by_shortname(editor, value)?.id(),
+    }
+}
+
+fn print_pick_list(org_id: i64, entries: &[PickListEntry]) {
+    println!(
+        "Hold shelf pick list for org unit {org_id}: {} item(s)\n",
+        entries.len()
+    );
+
+    if entries.is_empty() {
+        return;
+    }
+
+    println!(
+        "{:<16} {:<40} {:<20} {:<10} {:>6}  {:<24}",
+        "BARCODE", "TITLE", "CALL NUMBER", "ACTION", "DEST", "EXPIRED"
+    );
+
+    for entry in entries {
+        let title: String = entry
+            .title
+            .as_deref()
+            .unwrap_or("")
+            .chars()
+            .take(40)
+            .collect();
+        let dest = entry.dest_lib.map(|d| d.to_string()).unwrap_or_default();
+
+        println!(
+            "{:<16} {:<40} {:<20} {:<10} {:>6}  {:<24}",
+            entry.barcode,
+            title,
+            entry.call_number.as_deref().unwrap_or(""),
+            entry.action,
+            dest,
+            entry.shelf_expire_time,
+        );
+    }
+
+    println!();
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optmulti("", "org", "", "");
+    options.optflag("", "descendants", "");
+    options.optflag("", "notify", "");
+    options.optflag("", "dry-run", "");
+    options.optflag("", "json", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let orgs = params.opt_strs("org");
+    if orgs.is_empty() {
+        return Err("One or more --org values required".into());
+    }
+
+    let clear_ops = ClearShelfOptions {
+        descendants: params.opt_present("descendants"),
+        notify: params.opt_present("notify"),
+    };
+
+    let dry_run = params.opt_present("dry-run");
+    let json = params.opt_present("json");
+
+    let client = eg::init::Builder::new().skip_host_settings().build()?;
+    let mut editor = Editor::new(&client);
+
+    for org in orgs.iter() {
+        let org_id = org_id(&mut editor, org)?;
+
+        let clear = |e: &mut Editor| holdshelf::clear_shelf(e, org_id, &clear_ops);
+
+        let entries = if dry_run {
+            editor.in_transaction_rollback(clear)?
+        } else {
+            editor.in_transaction(clear)?
+        };
+
+        if json {
+            let mut list = EgValue::new_array();
+            for entry in entries.iter() {
+                list.push(entry.to_eg_value())?;
+            }
+
+            let report = eg::hash! {
+                "org_unit": org_id,
+                "dry_run": dry_run,
+                "pick_list": list,
+            };
+
+            println!("{}", report.dump());
+        } else {
+            print_pick_list(org_id, &entries);
+        }
+    }
+
+    if dry_run && !json {
+        println!("Dry run; hold cancellations were rolled back");
+    }
+
+    Ok(())
+}
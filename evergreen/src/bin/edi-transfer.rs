@@ -0,0 +1,470 @@
+//! Deliver EDI orders to vendors and pick up vendor EDI messages via SFTP.
+use eg::common::acq::edi::Interchange;
+use eg::common::acq::invoice::Invoice;
+use eg::common::acq::{EDI_STATUS_COMPLETE, EDI_STATUS_NEW, EDI_STATUS_TRANS_ERROR};
+use eg::{Editor, EgResult, EgValue};
+use evergreen as eg;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+const HELP_TEXT: &str = r#"
+Deliver pending EDI ORDERS messages (acq.edi_message rows with status
+"new") to vendors and pick up new vendor messages (e.g. INVOIC, ORDRSP)
+from each EDI account's inbound directory.
+
+Files are transferred with the system sftp command in batch mode, so
+each EDI account host must accept key-based authentication for the
+account username.  Passwords are not supported.
+
+Picked up files are stored as new acq.edi_message rows.  Files already
+stored for an account are not picked up again.
+
+./eg-edi-transfer --interval 600
+
+Options
+
+    --account <id>
+        Only process this EDI account.  May be repeated.
+
+    --push-only
+        Deliver orders without picking up vendor messages.
+
+    --pickup-only
+        Pick up vendor messages without delivering orders.
+
+    --interval <seconds>
+        Repeat every this many seconds instead of exiting after one
+        pass.
+
+    --dry-run
+        Report what would be transferred without transferring files
+        or changing the database.
+
+    --sftp <path>
+        Path to the sftp command.  Defaults to "sftp".
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+"#;
+
+struct Transfer {
+    editor: Editor,
+    sftp: String,
+    dry_run: bool,
+}
+
+impl Transfer {
+    /// EDI accounts for active providers.
+    fn accounts(&mut self, ids: &[i64]) -> EgResult<Vec<EgValue>> {
+        let query = if ids.is_empty() {
+            eg::hash! {"id": {"!=": eg::NULL}}
+        } else {
+            eg::hash! {"id": ids}
+        };
+
+        let flesh = eg::hash! {"flesh": 1, "flesh_fields": {"acqedi": ["provider"]}};
+
+        let accounts = self.editor.search_with_ops("acqedi", query, flesh)?;
+
+        Ok(accounts
+            .into_iter()
+            .filter(|a| a["provider"]["active"].boolish())
+            .collect())
+    }
+
+    /// Run a set of sftp commands against the account's host.
+    ///
+    /// Returns the command output.
+    fn sftp(&self, account: &EgValue, commands: &[String]) -> EgResult<String> {
+        let host = account["host"].str()?;
+        let host = host.strip_prefix("sftp://").unwrap_or(host);
+
+        let mut command = Command::new(&self.sftp);
+        command.args(["-b", "-", "-o", "BatchMode=yes"]);
+
+        let host = match host.split_once(':') {
+            Some((h, port)) => {
+                command.args(["-P", port]);
+                h
+            }
+            None => host,
+        };
+
+        let dest = match account["username"].as_str() {
+            Some(user) => format!("{user}@{host}"),
+            None => host.to_string(),
+        };
+
+        let mut child = command
+            .arg(&dest)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Cannot run {}: {e}", self.sftp))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all((commands.join("\n") + "\n").as_bytes())
+                .map_err(|e| format!("Error sending sftp commands: {e}"))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Error running sftp: {e}"))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "sftp to {dest} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Local temp file for a transfer.  `name` must be a plain file
+    /// name; see remote_name().
+    fn local_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("eg-edi-{}-{name}", std::process::id()))
+    }
+
+    /// Remote file name for an outbound ORDERS message.
+    fn order_file_name(message: &EgValue) -> EgResult<String> {
+        Ok(format!(
+            "order_{}_{}.epo",
+            message["purchase_order"].as_int().unwrap_or(0),
+            message.id()?
+        ))
+    }
+
+    /// Extract the file name from a line of remote directory listing.
+    ///
+    /// Returns None for names we cannot safely quote in an sftp
+    /// command or use as part of a local file name, i.e. names
+    /// containing quotes, backslashes, control characters, or "..".
+    fn remote_name(line: &str) -> Option<&str> {
+        let name = line.trim().rsplit('/').next()?;
+
+        if name.is_empty()
+            || name == "."
+            || name.contains("..")
+            || name
+                .chars()
+                .any(|c| c == '"' || c == '\'' || c == '\\' || c.is_control())
+        {
+            return None;
+        }
+
+        Some(name)
+    }
+
+    /// Values for a new acq.edi_message for a picked up file.
+    fn message_values(account_id: i64, remote_file: &str, edi: &str) -> EgValue {
+        let mut values = eg::hash! {
+            "account": account_id,
+            "remote_file": remote_file,
+            "status": EDI_STATUS_NEW,
+            "edi": edi,
+        };
+
+        match Interchange::parse(edi) {
+            Ok(ic) => {
+                if let Some(msg) = ic.messages().first() {
+                    values["message_type"] = EgValue::from(msg.message_type());
+                }
+            }
+            Err(e) => {
+                eprintln!("Cannot parse {remote_file}: {e}");
+                values["status"] = EgValue::from(EDI_STATUS_TRANS_ERROR);
+                values["error"] = EgValue::from(e.to_string());
+                values["error_time"] = EgValue::from("now");
+            }
+        }
+
+        values
+    }
+
+    /// Deliver the account's pending ORDERS messages.
+    fn push(&mut self, account: &EgValue) -> EgResult<usize> {
+        let query = eg::hash! {
+            "account": account.id()?,
+            "message_type": "ORDERS",
+            "status": EDI_STATUS_NEW,
+        };
+
+        let messages = self.editor.search("acqedim", query)?;
+        let dir = account["path"].as_str().unwrap_or(".");
+        let mut count = 0;
+
+        for mut message in messages {
+            let name = Transfer::order_file_name(&message)?;
+            let remote_file = format!("{}/{name}", dir.trim_end_matches('/'));
+
+            println!("Delivering {remote_file} to {}", account["host"]);
+
+            if self.dry_run {
+                continue;
+            }
+
+            let local = Transfer::local_file(&name);
+
+            fs::write(&local, message["edi"].str()?)
+                .map_err(|e| format!("Cannot write {local:?}: {e}"))?;
+
+            let result = self.sftp(
+                account,
+                &[format!("put \"{}\" \"{remote_file}\"", local.display())],
+            );
+
+            fs::remove_file(&local).ok();
+            result?;
+
+            message["remote_file"] = EgValue::from(remote_file);
+            message["process_time"] = EgValue::from("now");
+            message["status"] = EgValue::from(EDI_STATUS_COMPLETE);
+
+            self.editor.in_transaction(|e| e.update(message))?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Store new files from the account's inbound directory.
+    fn pickup(&mut self, account: &EgValue) -> EgResult<usize> {
+        let Some(dir) = account["in_dir"].as_str() else {
+            return Ok(0);
+        };
+
+        let dir = dir.trim_end_matches('/');
+        let listing = self.sftp(account, &[format!("ls -1 \"{dir}\"")])?;
+
+        let mut count = 0;
+
+        // Batch mode echoes each command, prefixed with "sftp>".
+        for line in listing.lines().filter(|l| !l.starts_with("sftp>")) {
+            let Some(name) = Transfer::remote_name(line) else {
+                if !line.trim().is_empty() {
+                    eprintln!("Skipping unsafe remote file name: {line:?}");
+                }
+                continue;
+            };
+
+            let remote_file = format!("{dir}/{name}");
+
+            let query = eg::hash! {"account": account.id()?, "remote_file": remote_file.as_str()};
+            if !self.editor.search("acqedim", query)?.is_empty() {
+                continue;
+            }
+
+            println!("Picking up {remote_file} from {}", account["host"]);
+
+            if self.dry_run {
+                continue;
+            }
+
+            let local = Transfer::local_file(name);
+
+            let result = self
+                .sftp(
+                    account,
+                    &[format!("get \"{remote_file}\" \"{}\"", local.display())],
+                )
+                .and_then(|_| {
+                    fs::read_to_string(&local)
+                        .map_err(|e| format!("Cannot read {local:?}: {e}").into())
+                });
+
+            fs::remove_file(&local).ok();
+
+            self.store_message(account, &remote_file, &result?)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Create an acq.edi_message for a picked up file.
+    fn store_message(&mut self, account: &EgValue, remote_file: &str, edi: &str) -> EgResult<()> {
+        let values = Transfer::message_values(account.id()?, remote_file, edi);
+
+        if let Ok(ic) = Interchange::parse(edi) {
+            for invoice in Invoice::from_interchange(&ic)? {
+                println!(
+                    "Invoice {} with {} line(s) totaling {}",
+                    invoice.invoice_ident,
+                    invoice.lines.len(),
+                    invoice.total.map(|t| t.to_string()).unwrap_or_default()
+                );
+            }
+        }
+
+        let message = EgValue::create("acqedim", values)?;
+
+        self.editor.in_transaction(|e| e.create(message))?;
+
+        Ok(())
+    }
+
+    fn process_account(&mut self, account: &EgValue, push: bool, pickup: bool) -> EgResult<()> {
+        let mut activity = 0;
+
+        if push {
+            activity += self.push(account)?;
+        }
+
+        if pickup {
+            activity += self.pickup(account)?;
+        }
+
+        if activity > 0 && !self.dry_run {
+            let mut account = account.clone();
+            account.deflesh()?;
+            account["last_activity"] = EgValue::from("now");
+
+            self.editor.in_transaction(|e| e.update(account))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optmulti("", "account", "", "");
+    options.optflag("", "push-only", "");
+    options.optflag("", "pickup-only", "");
+    options.optopt("", "interval", "", "");
+    options.optflag("", "dry-run", "");
+    options.optopt("", "sftp", "", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let mut account_ids = Vec::new();
+    for id in params.opt_strs("account") {
+        account_ids.push(
+            id.parse::<i64>()
+                .map_err(|e| format!("Invalid --account value '{id}': {e}"))?,
+        );
+    }
+
+    let interval = match params.opt_str("interval") {
+        Some(i) => Some(
+            i.parse::<u64>()
+                .map_err(|e| format!("Invalid --interval value '{i}': {e}"))?,
+        ),
+        None => None,
+    };
+
+    let push = !params.opt_present("pickup-only");
+    let pickup = !params.opt_present("push-only");
+
+    let client = eg::init()?;
+
+    let mut transfer = Transfer {
+        editor: Editor::new(&client),
+        sftp: params.opt_str("sftp").unwrap_or("sftp".to_string()),
+        dry_run: params.opt_present("dry-run"),
+    };
+
+    loop {
+        for account in transfer.accounts(&account_ids)? {
+            if let Err(e) = transfer.process_account(&account, push, pickup) {
+                // Keep going so one unreachable vendor does not
+                // hold up the rest.
+                eprintln!("EDI account {} failed: {e}", account["id"]);
+                log::error!("EDI account {} failed: {e}", account["id"]);
+            }
+        }
+
+        match interval {
+            Some(secs) => thread::sleep(Duration::from_secs(secs)),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_file_names() {
+        let message = eg::hash! {"id": 17, "purchase_order": 42};
+        assert_eq!(
+            Transfer::order_file_name(&message).unwrap(),
+            "order_42_17.epo"
+        );
+
+        let message = eg::hash! {"id": 17};
+        assert_eq!(
+            Transfer::order_file_name(&message).unwrap(),
+            "order_0_17.epo"
+        );
+
+        assert!(Transfer::order_file_name(&eg::hash! {}).is_err());
+    }
+
+    #[test]
+    fn remote_names() {
+        assert_eq!(Transfer::remote_name("in/invoice.edi"), Some("invoice.edi"));
+        assert_eq!(
+            Transfer::remote_name("  ordrsp_1.edi "),
+            Some("ordrsp_1.edi")
+        );
+
+        assert_eq!(Transfer::remote_name(""), None);
+        assert_eq!(Transfer::remote_name("in/"), None);
+        assert_eq!(Transfer::remote_name("in/.."), None);
+        assert_eq!(Transfer::remote_name("in/."), None);
+        assert_eq!(Transfer::remote_name("a..b"), None);
+        assert_eq!(Transfer::remote_name("in/x\" y.edi"), None);
+        assert_eq!(Transfer::remote_name("it's.edi"), None);
+        assert_eq!(Transfer::remote_name("bad\\name"), None);
+        assert_eq!(Transfer::remote_name("bad\x07name"), None);
+    }
+
+    #[test]
+    fn local_files_stay_in_temp_dir() {
+        let local = Transfer::local_file("invoice.edi");
+        assert_eq!(local.parent(), Some(env::temp_dir().as_path()));
+    }
+
+    #[test]
+    fn message_values() {
+        let edi = "UNA:+.? 'UNB+UNOC:3+7654321:31B+1234567:31B+240102:1200+55'\
+            UNH+1+INVOIC:D:96A:UN'BGM+380+INV-9+9'UNT+3+1'UNZ+1+55'";
+
+        let values = Transfer::message_values(3, "in/inv.edi", edi);
+        assert_eq!(values["account"].as_int(), Some(3));
+        assert_eq!(values["remote_file"].as_str(), Some("in/inv.edi"));
+        assert_eq!(values["status"].as_str(), Some(EDI_STATUS_NEW));
+        assert_eq!(values["message_type"].as_str(), Some("INVOIC"));
+        assert!(values["error"].is_null());
+
+        let values = Transfer::message_values(3, "in/junk.edi", "not edi");
+        assert_eq!(values["status"].as_str(), Some(EDI_STATUS_TRANS_ERROR));
+        assert!(values["error"].is_string());
+        assert!(values["message_type"].is_null());
+    }
+}
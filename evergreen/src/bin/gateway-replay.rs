@@ -0,0 +1,280 @@
+//! Replay API calls recorded by eg-http-gateway against another gateway.
+use eg::EgResult;
+use evergreen as eg;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+
+const HELP_TEXT: &str = r#"
+Replay API calls recorded by eg-http-gateway (see EG_HTTP_GATEWAY_RECORD_DIR)
+against another gateway, e.g. to reproduce a production problem on a test
+system.
+
+Recordings are replayed in the order they were recorded.  Calls whose
+params were redacted when recorded (protected or sensitive calls) are
+skipped.  For each call, the recorded and replayed HTTP status are
+reported, along with whether the response payloads match.
+
+./eg-gateway-replay --url http://localhost/osrf-gateway-v1 \
+    --authtoken 0123456789abcdef0123456789abcdef /var/tmp/gateway-recordings
+
+Options
+
+    --url <url>
+        Gateway URL to send calls to.  Only plain HTTP is supported.
+
+    --authtoken <token>
+        Authtoken used in place of the authtokens scrubbed from the
+        recorded calls.
+
+    --delay <milliseconds>
+        Pause this long between calls.  Defaults to 0.
+
+    --verbose
+        Print the replayed response for calls whose response differs
+        from the recording.
+
+Any remaining arguments are recording files or directories of
+recording files.
+"#;
+
+/// Must match the placeholder used by eg-http-gateway.
+const AUTHTOKEN_PLACEHOLDER: &str = "__AUTHTOKEN__";
+
+/// How long to wait for a replayed call to complete.
+const READ_TIMEOUT: u64 = 300;
+
+struct Replayer {
+    url: Url,
+    authtoken: Option<String>,
+    verbose: bool,
+}
+
+impl Replayer {
+    /// Replay one recording.  Returns true if the replayed response
+    /// matches the recorded response.
+    fn replay(&self, path: &PathBuf) -> EgResult<bool> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {path:?}: {e}"))?;
+        let rec = json::parse(&text).map_err(|e| format!("Invalid recording {path:?}: {e}"))?;
+
+        let service = rec["service"].as_str().unwrap_or("");
+        let method = rec["method"].as_str().unwrap_or("");
+
+        if rec["redacted"].as_bool().unwrap_or(false) {
+            println!("{} {method} SKIPPED (redacted)", file_name(path));
+            return Ok(true);
+        }
+
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("service", service);
+        form.append_pair("method", method);
+
+        if let Some(format) = rec["format"].as_str() {
+            form.append_pair("format", format);
+        }
+
+        if let Some(locale) = rec["locale"].as_str() {
+            form.append_pair("locale", locale);
+        }
+
+        if let Some(level) = rec["api_level"].as_u8() {
+            form.append_pair("api_level", &level.to_string());
+        }
+
+        for param in rec["params"].members() {
+            let mut param = param.clone();
+            if let Some(token) = self.authtoken.as_deref() {
+                replace_placeholder(&mut param, token);
+            }
+            form.append_pair("param", &param.dump());
+        }
+
+        let start = Instant::now();
+        let (status, body) = self.post(&form.finish())?;
+        let duration = start.elapsed().as_secs_f64();
+
+        let response = json::parse(&body).unwrap_or(json::JsonValue::Null);
+
+        let matches = rec["status"].as_u16() == Some(status)
+            && rec["response"]["payload"] == response["payload"];
+
+        println!(
+            "{} {method} status={}/{status} time={duration:.3}s {}",
+            file_name(path),
+            rec["status"],
+            if matches { "MATCH" } else { "DIFF" }
+        );
+
+        if !matches && self.verbose {
+            println!("{body}");
+        }
+
+        Ok(matches)
+    }
+
+    /// POST a form-encoded body to the gateway.
+    ///
+    /// Returns the HTTP status and the response body.
+    fn post(&self, body: &str) -> EgResult<(u16, String)> {
+        let host = self.url.host_str().ok_or("Gateway URL has no host")?;
+        let port = self.url.port_or_known_default().unwrap_or(80);
+
+        let mut stream = TcpStream::connect((host, port))
+            .map_err(|e| format!("Cannot connect to {host}:{port}: {e}"))?;
+
+        stream
+            .set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT)))
+            .map_err(|e| format!("Cannot set read timeout: {e}"))?;
+
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {host}\r\n\
+            Content-Type: application/x-www-form-urlencoded\r\n\
+            Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.url.path(),
+            body.len()
+        );
+
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("Error sending request: {e}"))?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .map_err(|e| format!("Error reading response: {e}"))?;
+
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or("Incomplete HTTP response")?;
+
+        // e.g. "HTTP/1.1 200 OK"
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse::<u16>().ok())
+            .ok_or_else(|| format!("Invalid HTTP status line: {head}"))?;
+
+        Ok((status, body.to_string()))
+    }
+}
+
+fn file_name(path: &PathBuf) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn replace_placeholder(value: &mut json::JsonValue, token: &str) {
+    if value.as_str() == Some(AUTHTOKEN_PLACEHOLDER) {
+        *value = token.into();
+    } else if value.is_array() {
+        for member in value.members_mut() {
+            replace_placeholder(member, token);
+        }
+    } else if value.is_object() {
+        for (_, member) in value.entries_mut() {
+            replace_placeholder(member, token);
+        }
+    }
+}
+
+/// Expand directories into their recording files, sorted by name,
+/// which sorts them in the order they were recorded.
+fn recording_files(args: &[String]) -> EgResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for arg in args {
+        let path = PathBuf::from(arg);
+
+        if path.is_dir() {
+            let mut dir_files: Vec<PathBuf> = fs::read_dir(&path)
+                .map_err(|e| format!("Cannot read directory {path:?}: {e}"))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|e| e == "json"))
+                .collect();
+
+            dir_files.sort();
+            files.append(&mut dir_files);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optflag("", "verbose", "");
+    options.optopt("", "url", "", "");
+    options.optopt("", "authtoken", "", "");
+    options.optopt("", "delay", "", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let url = params.opt_str("url").ok_or("--url is required")?;
+    let url = Url::parse(&url).map_err(|e| format!("Invalid --url '{url}': {e}"))?;
+
+    if url.scheme() != "http" {
+        return Err("Only http:// gateway URLs are supported".into());
+    }
+
+    let delay = match params.opt_str("delay") {
+        Some(d) => d
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid --delay value '{d}': {e}"))?,
+        None => 0,
+    };
+
+    let files = recording_files(&params.free)?;
+
+    if files.is_empty() {
+        return Err("No recordings provided".into());
+    }
+
+    let replayer = Replayer {
+        url,
+        authtoken: params.opt_str("authtoken"),
+        verbose: params.opt_present("verbose"),
+    };
+
+    let mut diffs = 0;
+    let mut errors = 0;
+
+    for (idx, path) in files.iter().enumerate() {
+        if idx > 0 && delay > 0 {
+            thread::sleep(Duration::from_millis(delay));
+        }
+
+        match replayer.replay(path) {
+            Ok(true) => {}
+            Ok(false) => diffs += 1,
+            Err(e) => {
+                eprintln!("{} ERROR {e}", file_name(path));
+                errors += 1;
+            }
+        }
+    }
+
+    println!(
+        "Replayed {} calls: {diffs} differed, {errors} failed",
+        files.len()
+    );
+
+    Ok(())
+}
@@ -1,4 +1,3 @@
-use eg::init::InitOptions;
 use eg::osrf::session::MultiSession;
 use eg::result::EgResult;
 use eg::util;
@@ -115,10 +114,7 @@ fn main() -> EgResult<()> {
         sleep = v.parse::<u64>().unwrap_or(0);
     }
 
-    let mut init_ops = InitOptions::new();
-    init_ops.skip_host_settings = true; // we don't need it.
-
-    let client = eg::init::with_options(&init_ops)?;
+    let client = eg::init::Builder::new().skip_host_settings().build()?;
 
     let mut multi_ses = MultiSession::new(client.clone(), "open-ils.rs-hold-targeter");
 
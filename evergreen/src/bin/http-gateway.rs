@@ -3,13 +3,20 @@ use eg::date;
 use eg::idl;
 use eg::osrf::conf;
 use eg::osrf::logging::Logger;
+use eg::osrf::message::MessageStatus;
 use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
+use ipnet::IpNet;
 use std::any::Any;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
+use std::fs;
 use std::io::{Read, Write};
-use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use url::Url;
 
 const BUFSIZE: usize = 1024;
@@ -17,17 +24,85 @@ const DEFAULT_PORT: u16 = 9682;
 const DEFAULT_ADDRESS: &str = "127.0.0.1";
 const DUMMY_BASE_URL: &str = "http://localhost";
 const HTTP_CONTENT_TYPE: &str = "Content-Type: text/json";
+const HTTP_CONTENT_TYPE_JS: &str = "Content-Type: application/javascript";
 
-/// Max time we'll wait for a reply from an OpenSRF request.
+/// Response header containing the request's log trace, which is also
+/// relayed to OpenSRF as the osrf_xid of every message it generates.
+const REQUEST_ID_HEADER: &str = "X-Request-ID";
+
+/// Requests whose path ends with this value receive the IDL class
+/// definitions instead of being relayed to OpenSRF.
+const CLASSES_PATH: &str = "/classes";
+
+/// Requests whose path ends with this value contain a list of API
+/// calls to relay in parallel.
+const BATCH_PATH: &str = "/batch";
+
+/// Requests whose path ends with this value relay the responses of a
+/// single API call as a long-lived stream of server-sent events.
+const EVENTS_PATH: &str = "/events";
+
+/// While an events request is waiting on responses, send a comment
+/// to the client this often (seconds) to keep the connection alive
+/// and to notice when the client goes away.
+const SSE_KEEPALIVE_INTERVAL: i32 = 15;
+
+/// Maximum number of API calls allowed in a single batch request.
+const MAX_BATCH_CALLS: usize = 25;
+
+/// Variable the legacy IDL2js output assigns the class definitions to.
+const PRELOAD_VARIABLE: &str = "_preload_fieldmapper_IDL";
+
+/// Default max time we'll wait for a reply from an OpenSRF request,
+/// unless overridden with EG_HTTP_GATEWAY_RELAY_TIMEOUT.
 /// Keep this value large and assume the proxy (eg. nginx) we sit
 /// behind had sane read/write timeouts
 const OSRF_RELAY_TIMEOUT: i32 = 300;
 const GATEWAY_POLL_TIMEOUT: u64 = 5;
 
+/// Recorded values which look like authtokens are replaced with this
+/// so recordings can be replayed with a token for another system.
+const AUTHTOKEN_PLACEHOLDER: &str = "__AUTHTOKEN__";
+
+/// Recorded values of SENSITIVE_FIELDS are replaced with this.
+const SENSITIVE_REDACTED: &str = "REDACTED";
+
+/// Hash keys and IDL fields whose values are never recorded.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "passwd",
+    "password",
+    "first_given_name",
+    "second_given_name",
+    "family_name",
+    "pref_first_given_name",
+    "pref_second_given_name",
+    "pref_family_name",
+    "name_keywords",
+    "dob",
+    "email",
+    "day_phone",
+    "evening_phone",
+    "other_phone",
+    "ident_value",
+    "ident_value2",
+    "street1",
+    "street2",
+    "post_code",
+];
+
+/// Ingress value applied to relayed requests unless overridden
+/// with EG_HTTP_GATEWAY_INGRESS.
+const DEFAULT_INGRESS: &str = "gateway-v1";
+
 struct GatewayRequest {
     stream: TcpStream,
     address: SocketAddr,
+    /// Address of the client, which differs from the peer address
+    /// when the request arrives via a trusted proxy.
+    client_ip: IpAddr,
     start_time: date::EgDate,
+    /// Unique ID for this request, used as our log trace.
+    request_id: String,
 }
 
 impl GatewayRequest {
@@ -50,6 +125,12 @@ struct ParsedGatewayRequest {
     method: Option<eg::osrf::message::MethodCall>,
     format: idl::DataFormat,
     http_method: String,
+    /// Caller asked that the params be kept out of the logs.
+    sensitive: bool,
+    /// Locale services should use for translated strings.
+    locale: Option<String>,
+    /// API level requested by the caller.
+    api_level: Option<u8>,
 }
 
 /// Just the stuff we need.
@@ -60,9 +141,138 @@ struct ParsedHttpRequest {
     body: Option<String>,
 }
 
+/// How long to wait on API calls to complete, in seconds.
+struct RelayTimeouts {
+    default: i32,
+    /// Method name or prefix (ending in '*') paired with its timeout.
+    methods: Vec<(String, i32)>,
+}
+
+impl RelayTimeouts {
+    /// Timeout for the named method.  When several patterns match,
+    /// the longest (most specific) one wins.
+    fn for_method(&self, method: &str) -> i32 {
+        self.methods
+            .iter()
+            .filter(|(pattern, _)| match pattern.strip_suffix('*') {
+                Some(prefix) => method.starts_with(prefix),
+                None => method == pattern,
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, timeout)| *timeout)
+            .unwrap_or(self.default)
+    }
+}
+
+/// HTTP methods we answer.
+const ALLOWED_METHODS: &str = "GET, HEAD, POST, OPTIONS";
+
+/// Reasons an API call could not be relayed or answered, each of
+/// which maps onto an HTTP status.
+#[derive(Debug)]
+enum RelayError {
+    /// The request could not be read or parsed.
+    BadRequest(String),
+    /// OpenSRF replied with a failure status.
+    Status(MessageStatus, String),
+    /// No complete response arrived within the relay timeout.
+    Timeout,
+    /// We could not talk to the bus or make sense of a reply.
+    Transport(String),
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BadRequest(m) => write!(f, "Bad request: {m}"),
+            Self::Status(s, label) => write!(f, "OpenSRF replied {} {label}", *s as isize),
+            Self::Timeout => write!(f, "Timed out waiting for OpenSRF to complete the call"),
+            Self::Transport(m) => write!(f, "Transport error: {m}"),
+        }
+    }
+}
+
+impl RelayError {
+    fn http_status(&self) -> u16 {
+        match self {
+            Self::BadRequest(_) => 400,
+            Self::Timeout => 504,
+            Self::Transport(_) => 502,
+            Self::Status(status, _) => match status {
+                MessageStatus::MethodNotFound | MessageStatus::ServiceNotFound => 404,
+                MessageStatus::Forbidden | MessageStatus::NotAllowed => 403,
+                MessageStatus::Unauthorized => 401,
+                MessageStatus::Timeout => 408,
+                MessageStatus::BadRequest | MessageStatus::Expfailed => 400,
+                MessageStatus::ServiceUnavailable => 503,
+                _ => 502,
+            },
+        }
+    }
+
+    /// Structured error for response bodies.
+    fn to_eg_value(&self) -> EgValue {
+        let (code, osrf_status) = match self {
+            Self::BadRequest(_) => ("BAD_REQUEST", EgValue::Null),
+            Self::Timeout => ("RELAY_TIMEOUT", EgValue::Null),
+            Self::Transport(_) => ("TRANSPORT_ERROR", EgValue::Null),
+            Self::Status(s, _) => ("OSRF_STATUS", EgValue::from(*s as i64)),
+        };
+
+        eg::hash! {
+            code: code,
+            osrf_status: osrf_status,
+            message: self.to_string(),
+        }
+    }
+
+    /// Response body for a failed call.
+    fn to_response(&self) -> EgValue {
+        eg::hash! {
+            status: self.http_status(),
+            payload: [],
+            error: self.to_eg_value(),
+        }
+    }
+}
+
+impl From<eg::EgError> for RelayError {
+    fn from(e: eg::EgError) -> Self {
+        RelayError::Transport(e.to_string())
+    }
+}
+
+/// Reason phrase for the HTTP status codes we return.
+fn status_line(status: u16) -> String {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Bad Gateway",
+    };
+
+    format!("HTTP/1.1 {status} {reason}")
+}
+
+/// IDL fields to remove from hash-formatted responses, keyed on classname.
+type SuppressFields = HashMap<String, Vec<String>>;
+
 struct GatewayHandler {
     bus: Option<eg::osrf::bus::Bus>,
     partial_buffer: Option<String>,
+    suppress_fields: Arc<SuppressFields>,
+    ingress: String,
+    trusted_proxies: Arc<Vec<IpNet>>,
+    timeouts: Arc<RelayTimeouts>,
+    /// Directory where API calls and their responses are recorded.
+    record_dir: Option<Arc<PathBuf>>,
 }
 
 impl GatewayHandler {
@@ -74,17 +284,31 @@ impl GatewayHandler {
     }
 
     fn handle_request(&mut self, request: &mut GatewayRequest) -> EgResult<()> {
-        // For now we asssume any error is the result of a bad request.
-        // We could make the various read/parsers return something
-        // more meaningful to separate, e.g., 4XX and 5XX errors.
-        let mut response = eg::hash! {
-            status: 400,
-            payload: [],
-        };
-
         let mut http_req = None;
+        let mut recording = None;
 
-        match self.read_request(request) {
+        let result = match self.read_request(request) {
+            Ok(htreq) if htreq.method == "OPTIONS" => {
+                return Self::write_response(request, 204, "OPTIONS", HTTP_CONTENT_TYPE, "");
+            }
+            Ok(htreq) if !matches!(htreq.method.as_str(), "GET" | "HEAD" | "POST") => {
+                // Refuse e.g. PUT and DELETE before relaying anything.
+                log::warn!(
+                    "[{}] Refusing HTTP method {}",
+                    request.client_ip,
+                    htreq.method
+                );
+                return Self::write_response(request, 405, &htreq.method, HTTP_CONTENT_TYPE, "");
+            }
+            Ok(htreq) if Self::is_classes_request(&htreq) => {
+                return self.handle_classes_request(request, htreq);
+            }
+            Ok(htreq) if Self::is_batch_request(&htreq) => {
+                return self.handle_batch_request(request, htreq);
+            }
+            Ok(htreq) if Self::is_events_request(&htreq) => {
+                return self.handle_events_request(request, htreq);
+            }
             Ok(htreq) => match self.parse_request(htreq) {
                 Ok(hreq) => {
                     http_req = Some(hreq);
@@ -93,38 +317,74 @@ impl GatewayHandler {
                     // request exits early on a failure.
                     self.log_request(request, http_req.as_ref().unwrap());
 
-                    match self.relay_to_osrf(http_req.as_mut().unwrap()) {
-                        Ok(list) => {
-                            response["payload"] = EgValue::Array(list);
-                            response["status"] = EgValue::from(200);
-                        }
-                        Err(e) => log::error!("relay_to_osrf() failed: {e}"),
-                    }
+                    // Capture the params before they are sent away.
+                    recording = self.start_recording(http_req.as_ref().unwrap());
+
+                    self.relay_to_osrf(http_req.as_mut().unwrap())
                 }
-                Err(e) => log::error!("parse_request() failed: {e}"),
+                Err(e) => Err(RelayError::BadRequest(e.to_string())),
             },
-            Err(e) => log::error!("read_request() failed: {e}"),
-        }
-
-        let data = response.dump();
-        let length = format!("Content-Length: {}", data.as_bytes().len());
+            Err(e) => Err(RelayError::BadRequest(e.to_string())),
+        };
 
-        let leader = if response["status"] == EgValue::Number(200.into()) {
-            "HTTP/1.1 200 OK"
-        } else {
-            "HTTP/1.1 400 Bad Request"
+        let response = match result {
+            Ok(list) => eg::hash! {
+                status: 200,
+                payload: EgValue::Array(list),
+            },
+            Err(e) => {
+                log::error!("[{}] Request failed: {e}", request.client_ip);
+                e.to_response()
+            }
         };
 
+        if let Some(rec) = recording {
+            self.save_recording(request, rec, &response);
+        }
+
         // It's possible http_req failed to parse successfully
         let http_method = match http_req.as_ref() {
             Some(req) => req.http_method.as_str(),
             None => "GET",
         };
 
+        Self::write_response(
+            request,
+            response["status"].as_u16().unwrap_or(500),
+            http_method,
+            HTTP_CONTENT_TYPE,
+            &response.dump(),
+        )
+    }
+
+    /// Write the HTTP response to the client and log the request duration.
+    fn write_response(
+        request: &mut GatewayRequest,
+        status: u16,
+        http_method: &str,
+        content_type: &str,
+        data: &str,
+    ) -> EgResult<()> {
+        let length = format!("Content-Length: {}", data.as_bytes().len());
+        let request_id = format!("{REQUEST_ID_HEADER}: {}", request.request_id);
+        let allow = format!("Allow: {ALLOWED_METHODS}");
+
+        let leader = status_line(status);
+
         let response = match http_method {
-            "HEAD" => format!("{leader}\r\n{HTTP_CONTENT_TYPE}\r\n{length}\r\n\r\n"),
-            "GET" | "POST" => format!("{leader}\r\n{HTTP_CONTENT_TYPE}\r\n{length}\r\n\r\n{data}"),
-            _ => "HTTP/1.1 405 Method Not Allowed\r\n".to_string(),
+            // HEAD responses describe the GET response without the body.
+            "HEAD" => format!("{leader}\r\n{content_type}\r\n{length}\r\n{request_id}\r\n\r\n"),
+            "GET" | "POST" => {
+                format!("{leader}\r\n{content_type}\r\n{length}\r\n{request_id}\r\n\r\n{data}")
+            }
+            "OPTIONS" => {
+                let leader = status_line(204);
+                format!("{leader}\r\n{allow}\r\n{request_id}\r\n\r\n")
+            }
+            _ => {
+                let leader = status_line(405);
+                format!("{leader}\r\n{allow}\r\nContent-Length: 0\r\n{request_id}\r\n\r\n")
+            }
         };
 
         if let Err(e) = request.stream.write_all(response.as_bytes()) {
@@ -134,44 +394,547 @@ impl GatewayHandler {
         let duration = date::now() - request.start_time;
         let millis = (duration.num_milliseconds() as f64) / 1000.0;
 
-        log::debug!("[{}] Request duration: {:.3}s", request.address, millis);
+        log::debug!("[{}] Request duration: {:.3}s", request.client_ip, millis);
 
         Ok(())
     }
 
-    fn relay_to_osrf(&mut self, request: &mut ParsedGatewayRequest) -> EgResult<Vec<EgValue>> {
-        let recipient = eg::osrf::addr::BusAddress::for_bare_service(&request.service);
+    /// True if the caller is requesting IDL class definitions.
+    fn is_classes_request(http_req: &ParsedHttpRequest) -> bool {
+        let path = http_req.path.split('?').next().unwrap_or("");
+        path.trim_end_matches('/').ends_with(CLASSES_PATH)
+    }
 
-        // Send every request to the router on our gateway domain.
-        let router = eg::osrf::addr::BusAddress::for_router(
-            conf::config().gateway().unwrap().router_name(),
-            conf::config().gateway().unwrap().domain().name(),
+    /// Respond with the IDL class definitions in the format produced
+    /// by the legacy fieldmapper IDL2js handler.
+    ///
+    /// Supported parameters:
+    ///
+    /// * `class` - Limit the response to this class.  May be repeated.
+    /// * `format=js` - Return a JavaScript snippet which assigns the
+    ///   class definitions to `_preload_fieldmapper_IDL` instead of
+    ///   plain JSON.
+    fn handle_classes_request(
+        &mut self,
+        request: &mut GatewayRequest,
+        http_req: ParsedHttpRequest,
+    ) -> EgResult<()> {
+        let url_params = match http_req.body.as_ref() {
+            Some(b) => format!("{}?{}", DUMMY_BASE_URL, b),
+            None => format!("{}{}", DUMMY_BASE_URL, &http_req.path),
+        };
+
+        let mut classes = Vec::new();
+        let mut as_js = false;
+
+        match Url::parse(&url_params) {
+            Ok(parsed_url) => {
+                for (k, v) in parsed_url.query_pairs() {
+                    match k.as_ref() {
+                        "class" => classes.push(v.to_string()),
+                        "format" => as_js = v.as_ref() == "js",
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Error parsing request params: {e}");
+                let data = RelayError::BadRequest(e.to_string()).to_response().dump();
+                return Self::write_response(
+                    request,
+                    400,
+                    &http_req.method,
+                    HTTP_CONTENT_TYPE,
+                    &data,
+                );
+            }
+        }
+
+        log::info!(
+            "ACT:[{}] IDL classes requested: {}",
+            request.client_ip,
+            if classes.is_empty() {
+                "all".to_string()
+            } else {
+                classes.join(",")
+            }
         );
 
-        // Avoid cloning the method which could be a big pile o' JSON.
-        // We know method is non-None here.
-        let method = request.method.take().unwrap();
+        let idl_classes = Self::idl_classes(&classes);
 
-        let tm = eg::osrf::message::TransportMessage::with_body(
-            recipient.as_str(),
-            self.bus().address().as_str(),
-            &eg::util::random_number(16), // thread
-            eg::osrf::message::Message::new(
-                eg::osrf::message::MessageType::Request,
-                1, // thread trace
-                eg::osrf::message::Payload::Method(method),
-            ),
+        if as_js {
+            let data = format!("var {PRELOAD_VARIABLE} = {};", idl_classes.dump());
+            Self::write_response(request, 200, &http_req.method, HTTP_CONTENT_TYPE_JS, &data)
+        } else {
+            let data = eg::hash! {status: 200, payload: [idl_classes]}.dump();
+            Self::write_response(request, 200, &http_req.method, HTTP_CONTENT_TYPE, &data)
+        }
+    }
+
+    /// True if the caller is sending a batch of API calls.
+    fn is_batch_request(http_req: &ParsedHttpRequest) -> bool {
+        let path = http_req.path.split('?').next().unwrap_or("");
+        path.trim_end_matches('/').ends_with(BATCH_PATH)
+    }
+
+    /// Relay a batch of API calls to OpenSRF in parallel.
+    ///
+    /// The POST body is a JSON array of calls, each of the form
+    /// {"service": "...", "method": "...", "params": [...]}, plus an
+    /// optional "sensitive": true to keep the params out of the logs.
+    /// The response payload contains one {"status": ..., "payload": [...]}
+    /// entry per call, in the order the calls were sent.
+    ///
+    /// The `format`, `locale`, and `api_level` URL parameters apply to
+    /// all calls in the batch.
+    fn handle_batch_request(
+        &mut self,
+        request: &mut GatewayRequest,
+        http_req: ParsedHttpRequest,
+    ) -> EgResult<()> {
+        let http_method = http_req.method.to_string();
+
+        let result = match self.parse_batch_request(http_req) {
+            Ok(mut calls) => {
+                for call in calls.iter() {
+                    self.log_request(request, call);
+                }
+
+                self.relay_batch_to_osrf(&mut calls)
+                    .map_err(|e| RelayError::Transport(e.to_string()))
+            }
+            Err(e) => Err(RelayError::BadRequest(e.to_string())),
+        };
+
+        let response = match result {
+            Ok(list) => eg::hash! {
+                status: 200,
+                payload: EgValue::Array(list),
+            },
+            Err(e) => {
+                log::error!("[{}] Batch request failed: {e}", request.client_ip);
+                e.to_response()
+            }
+        };
+
+        Self::write_response(
+            request,
+            response["status"].as_u16().unwrap_or(500),
+            &http_method,
+            HTTP_CONTENT_TYPE,
+            &response.dump(),
+        )
+    }
+
+    /// True if the caller wants responses as server-sent events.
+    fn is_events_request(http_req: &ParsedHttpRequest) -> bool {
+        let path = http_req.path.split('?').next().unwrap_or("");
+        path.trim_end_matches('/').ends_with(EVENTS_PATH)
+    }
+
+    /// Relay an API call to OpenSRF and forward each response to the
+    /// client as a server-sent event as soon as it arrives.
+    ///
+    /// Parameters are the same as for standard requests.  Events are:
+    ///
+    /// * `response` - One API response value.
+    /// * `progress` - The server reported progress on a long-running
+    ///   call.  Data is {"progress": n, "total": m, "message": "..."},
+    ///   where total and message are optional.
+    /// * `complete` - The call completed.  No more events will follow.
+    /// * `error` - The call failed.  Data is the structured error.
+    ///
+    /// There is no overall time limit on the call, so the worker
+    /// handling it remains busy until the call completes or the
+    /// client disconnects.
+    fn handle_events_request(
+        &mut self,
+        request: &mut GatewayRequest,
+        http_req: ParsedHttpRequest,
+    ) -> EgResult<()> {
+        let http_method = http_req.method.to_string();
+
+        let mut call = match self.parse_request(http_req) {
+            Ok(c) => c,
+            Err(e) => {
+                let err = RelayError::BadRequest(e.to_string());
+                log::error!("[{}] Events request failed: {err}", request.client_ip);
+                let data = err.to_response().dump();
+                return Self::write_response(request, 400, &http_method, HTTP_CONTENT_TYPE, &data);
+            }
+        };
+
+        self.log_request(request, &call);
+
+        let thread = eg::util::random_number(16);
+
+        if let Err(e) = self.send_to_osrf(&mut call, &thread) {
+            let err = RelayError::from(e);
+            log::error!("[{}] Events request failed: {err}", request.client_ip);
+            let data = err.to_response().dump();
+            return Self::write_response(
+                request,
+                err.http_status(),
+                &http_method,
+                HTTP_CONTENT_TYPE,
+                &data,
+            );
+        }
+
+        let leader = format!(
+            "{}\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+            X-Accel-Buffering: no\r\n{REQUEST_ID_HEADER}: {}\r\n\r\n",
+            status_line(200),
+            request.request_id
         );
 
-        self.bus().send_to(tm, router.as_str())?;
+        let mut connected = Self::write_event(request, &leader).is_ok();
+        let mut event_id = 0;
+
+        while connected {
+            let tm = match self.bus().recv(SSE_KEEPALIVE_INTERVAL, None) {
+                Ok(Some(tm)) => tm,
+                Ok(None) => {
+                    connected = Self::write_event(request, ": keepalive\n\n").is_ok();
+                    continue;
+                }
+                Err(e) => {
+                    let data = RelayError::from(e).to_eg_value().dump();
+                    Self::write_event(request, &format!("event: error\ndata: {data}\n\n")).ok();
+                    break;
+                }
+            };
+
+            if tm.thread() != thread {
+                log::warn!("Discarding reply for unknown thread {}", tm.thread());
+                continue;
+            }
+
+            for msg in tm.body().iter() {
+                if let eg::osrf::message::Payload::Status(stat) = msg.payload() {
+                    if let Some(progress) = eg::osrf::message::Progress::from_status(stat) {
+                        let event = format!(
+                            "event: progress\ndata: {}\n\n",
+                            progress.to_eg_value().dump()
+                        );
+                        connected = connected && Self::write_event(request, &event).is_ok();
+                    }
+                }
+            }
+
+            let mut complete = false;
+
+            match self.extract_osrf_responses(&call.format, &mut complete, tm) {
+                Ok(replies) => {
+                    for reply in replies {
+                        event_id += 1;
+                        let event = format!(
+                            "id: {event_id}\nevent: response\ndata: {}\n\n",
+                            reply.dump()
+                        );
+                        connected = connected && Self::write_event(request, &event).is_ok();
+                    }
+                }
+                Err(e) => {
+                    log::error!("[{}] Events request failed: {e}", request.client_ip);
+                    let data = e.to_eg_value().dump();
+                    Self::write_event(request, &format!("event: error\ndata: {data}\n\n")).ok();
+                    break;
+                }
+            }
+
+            if complete {
+                Self::write_event(request, "event: complete\ndata: {}\n\n").ok();
+                break;
+            }
+        }
+
+        if !connected {
+            log::info!(
+                "[{}] Client disconnected from events stream",
+                request.client_ip
+            );
+
+            // The call may keep responding after we stop listening.
+            // Move to a fresh bus address so stray responses don't
+            // end up in the replies to our next request.
+            self.partial_buffer = None;
+            self.bus().clear_bus()?;
+            self.bus().generate_address();
+        }
+
+        let duration = date::now() - request.start_time;
+        let millis = (duration.num_milliseconds() as f64) / 1000.0;
+
+        log::debug!("[{}] Request duration: {:.3}s", request.client_ip, millis);
+
+        Ok(())
+    }
+
+    /// Write a chunk of an event stream to the client.
+    ///
+    /// Returns Err if the client has disconnected.
+    fn write_event(request: &mut GatewayRequest, text: &str) -> EgResult<()> {
+        request
+            .stream
+            .write_all(text.as_bytes())
+            .and_then(|_| request.stream.flush())
+            .map_err(|e| format!("Error writing to client: {e}").into())
+    }
+
+    /// Translate the JSON body of a batch request into a list of
+    /// ParsedGatewayRequest's.
+    fn parse_batch_request(
+        &self,
+        http_req: ParsedHttpRequest,
+    ) -> EgResult<Vec<ParsedGatewayRequest>> {
+        let body = http_req
+            .body
+            .as_ref()
+            .ok_or("Batch requests must be sent via POST")?;
+
+        let url = format!("{}{}", DUMMY_BASE_URL, &http_req.path);
+
+        let parsed_url =
+            Url::parse(&url).map_err(|e| format!("Error parsing request params: {e}"))?;
+
+        let mut format = idl::DataFormat::Fieldmapper;
+        let mut locale = None;
+        let mut api_level = None;
+
+        for (k, v) in parsed_url.query_pairs() {
+            match k.as_ref() {
+                "format" => format = v.as_ref().into(),
+                "locale" => locale = Some(parse_locale(&v)?),
+                "api_level" => api_level = Some(parse_api_level(&v)?),
+                _ => {}
+            }
+        }
+
+        let calls = json::parse(body).map_err(|e| format!("Cannot parse batch request: {e}"))?;
+
+        if !calls.is_array() || calls.is_empty() {
+            return Err("Batch request must be a non-empty array of calls".into());
+        }
+
+        if calls.len() > MAX_BATCH_CALLS {
+            return Err(format!(
+                "Batch request contains {} calls; max is {MAX_BATCH_CALLS}",
+                calls.len()
+            )
+            .into());
+        }
+
+        let mut requests = Vec::new();
+
+        for call in calls.members() {
+            let service = call["service"]
+                .as_str()
+                .ok_or_else(|| format!("Batch call has no service name: {call}"))?;
+
+            let method = call["method"]
+                .as_str()
+                .ok_or_else(|| format!("Batch call has no method name: {call}"))?;
+
+            let mut params = Vec::new();
+
+            for param in call["params"].members() {
+                params.push(Self::parse_param(&format, param.clone())?);
+            }
+
+            requests.push(ParsedGatewayRequest {
+                format: format.clone(),
+                service: service.to_string(),
+                method: Some(eg::osrf::message::MethodCall::new(method, params)),
+                http_method: http_req.method.to_string(),
+                sensitive: call["sensitive"].as_bool() == Some(true),
+                locale: locale.clone(),
+                api_level,
+            });
+        }
+
+        Ok(requests)
+    }
+
+    /// Send every call in the batch, then collect the replies for
+    /// each as they arrive.
+    ///
+    /// Returns one status + payload entry per call, plus an "error"
+    /// for failed calls.  A call which fails does not prevent the
+    /// others from completing.
+    fn relay_batch_to_osrf(
+        &mut self,
+        calls: &mut [ParsedGatewayRequest],
+    ) -> EgResult<Vec<EgValue>> {
+        let mut entries = Vec::new();
+        let mut threads = Vec::new();
+        let mut timers = Vec::new();
+        let mut partials: Vec<Option<String>> = Vec::new();
+
+        for call in calls.iter_mut() {
+            let thread = eg::util::random_number(16);
+
+            timers.push(eg::util::Timer::new(self.call_timeout(call)));
+
+            if let Err(e) = self.send_to_osrf(call, &thread) {
+                log::error!("Error relaying batch call: {e}");
+                entries.push(RelayError::from(e).to_response());
+                threads.push(None);
+            } else {
+                entries.push(eg::hash! {status: 200, payload: []});
+                threads.push(Some(thread));
+            }
+
+            partials.push(None);
+        }
+
+        loop {
+            // Calls which have run out of time are abandoned.
+            for (index, thread) in threads.iter_mut().enumerate() {
+                if thread.is_some() && timers[index].done() {
+                    entries[index] = RelayError::Timeout.to_response();
+                    *thread = None;
+                }
+            }
+
+            // Wait no longer than the call with the least time left.
+            let Some(remaining) = threads
+                .iter()
+                .zip(timers.iter())
+                .filter(|(thread, _)| thread.is_some())
+                .map(|(_, timer)| timer.remaining())
+                .min()
+            else {
+                break; // All calls are complete.
+            };
+
+            let tm = match self.bus().recv(remaining, None)? {
+                Some(r) => r,
+                None => continue,
+            };
+
+            let index = match threads
+                .iter()
+                .position(|t| t.as_deref() == Some(tm.thread()))
+            {
+                Some(i) => i,
+                None => {
+                    log::warn!("Discarding reply for unknown thread {}", tm.thread());
+                    continue;
+                }
+            };
+
+            // Partial messages are buffered per call.
+            std::mem::swap(&mut self.partial_buffer, &mut partials[index]);
+
+            let mut complete = false;
+            let result = self.extract_osrf_responses(&calls[index].format, &mut complete, tm);
+
+            std::mem::swap(&mut self.partial_buffer, &mut partials[index]);
+
+            match result {
+                Ok(batch) => {
+                    for reply in batch {
+                        entries[index]["payload"].push(reply)?;
+                    }
+                }
+                Err(e) => {
+                    log::error!("Batch call failed: {e}");
+                    entries[index] = e.to_response();
+                    complete = true;
+                }
+            }
+
+            if complete {
+                threads[index] = None;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Build a hash of IDL class definitions keyed on class name.
+    ///
+    /// If `classnames` is empty, all classes are included.  Unknown
+    /// class names are ignored.
+    fn idl_classes(classnames: &[String]) -> EgValue {
+        let mut hash = EgValue::new_object();
+
+        for class in idl::parser().classes().values() {
+            let classname = class.classname();
+
+            if !classnames.is_empty() && !classnames.iter().any(|c| c == classname) {
+                continue;
+            }
+
+            let mut fields: Vec<&idl::Field> = class.fields().values().collect();
+            fields.sort_by_key(|f| f.array_pos());
+
+            let mut field_list = Vec::new();
+
+            for field in fields {
+                let mut fhash = eg::hash! {
+                    name: field.name(),
+                    label: field.label(),
+                    datatype: field.datatype().to_string(),
+                    virtual: field.is_virtual(),
+                    i18n: field.i18n(),
+                    array_position: field.array_pos(),
+                };
+
+                if let Some(link) = class.links().get(field.name()) {
+                    fhash["class"] = EgValue::from(link.class());
+                    fhash["reltype"] = EgValue::from(link.reltype().to_string());
+                    fhash["key"] = EgValue::from(link.key());
+                    if let Some(map) = link.map() {
+                        fhash["map"] = EgValue::from(map);
+                    }
+                }
+
+                field_list.push(fhash);
+            }
+
+            let controller: Vec<EgValue> = class
+                .controller()
+                .unwrap_or("")
+                .split_whitespace()
+                .map(EgValue::from)
+                .collect();
+
+            hash[classname] = eg::hash! {
+                name: classname,
+                label: class.label(),
+                table: class.tablename(),
+                pkey: class.pkey(),
+                fieldmapper: class.fieldmapper(),
+                controller: controller,
+                virtual: class.is_virtual(),
+                fields: field_list,
+            };
+        }
+
+        hash
+    }
+
+    fn relay_to_osrf(
+        &mut self,
+        request: &mut ParsedGatewayRequest,
+    ) -> Result<Vec<EgValue>, RelayError> {
+        let timer = eg::util::Timer::new(self.call_timeout(request));
+
+        self.send_to_osrf(request, &eg::util::random_number(16))?;
 
         let mut replies: Vec<EgValue> = Vec::new();
 
         loop {
+            if timer.done() {
+                return Err(RelayError::Timeout);
+            }
+
             // A request can result in any number of response messages.
-            let tm = match self.bus().recv(OSRF_RELAY_TIMEOUT, None)? {
+            let tm = match self.bus().recv(timer.remaining(), None)? {
                 Some(r) => r,
-                None => return Ok(replies), // Timeout
+                None => return Err(RelayError::Timeout),
             };
 
             let mut complete = false;
@@ -186,6 +949,55 @@ impl GatewayHandler {
         }
     }
 
+    /// Relay timeout for an API call which has not yet been sent.
+    fn call_timeout(&self, request: &ParsedGatewayRequest) -> i32 {
+        match request.method.as_ref() {
+            Some(m) => self.timeouts.for_method(m.method()),
+            None => self.timeouts.default,
+        }
+    }
+
+    /// Send an API call to OpenSRF within the provided thread.
+    fn send_to_osrf(&mut self, request: &mut ParsedGatewayRequest, thread: &str) -> EgResult<()> {
+        let recipient = eg::osrf::addr::BusAddress::for_bare_service(&request.service);
+
+        // Send every request to the router on our gateway domain.
+        let router = eg::osrf::addr::BusAddress::for_router(
+            conf::config().gateway().unwrap().router_name(),
+            conf::config().gateway().unwrap().domain().name(),
+        );
+
+        // Avoid cloning the method which could be a big pile o' JSON.
+        // We know method is non-None here.
+        let method = request.method.take().unwrap();
+
+        let mut msg = eg::osrf::message::Message::new(
+            eg::osrf::message::MessageType::Request,
+            1, // thread trace
+            eg::osrf::message::Payload::Method(method),
+        );
+
+        msg.set_ingress(&self.ingress);
+        msg.set_sensitive(request.sensitive);
+
+        if let Some(locale) = request.locale.as_deref() {
+            msg.set_locale(locale);
+        }
+
+        if let Some(level) = request.api_level {
+            msg.set_api_level(level);
+        }
+
+        let tm = eg::osrf::message::TransportMessage::with_body(
+            recipient.as_str(),
+            self.bus().address().as_str(),
+            thread,
+            msg,
+        );
+
+        self.bus().send_to(tm, router.as_str())
+    }
+
     /// Extract API response values from each response message body.
     ///
     /// Returns Err if we receive an unexpected status/response value.
@@ -194,7 +1006,7 @@ impl GatewayHandler {
         format: &idl::DataFormat,
         complete: &mut bool,
         mut tm: eg::osrf::message::TransportMessage,
-    ) -> EgResult<Vec<EgValue>> {
+    ) -> Result<Vec<EgValue>, RelayError> {
         let mut replies: Vec<EgValue> = Vec::new();
 
         for mut resp in tm.body_mut().drain(..) {
@@ -235,14 +1047,16 @@ impl GatewayHandler {
                     }
 
                     // Parse the collected chunks as a the final JSON value.
-                    content = EgValue::parse(&buf)
-                        .map_err(|e| format!("Error reconstituting partial message: {e}"))?;
+                    content = EgValue::parse(&buf).map_err(|e| {
+                        RelayError::Transport(format!("Error reconstituting partial message: {e}"))
+                    })?;
                 }
 
                 if format.is_hash() {
                     // JSON replies arrive from opensrf as Fieldmapper-encoded
                     // objects.  Decode them into flat hashes for the caller.
-                    content.to_classed_hash();
+                    // Sensitive fields are dropped along the way.
+                    content.to_classed_hash_suppressed(&self.suppress_fields);
 
                     if format == &idl::DataFormat::Hash {
                         // If the caller specifically requests the Hash
@@ -261,7 +1075,9 @@ impl GatewayHandler {
                     | eg::osrf::message::MessageStatus::Continue => {
                         // Keep reading in case there's more data in the message.
                     }
-                    _ => return Err(stat.clone().into_json_value().dump().into()),
+                    s => {
+                        return Err(RelayError::Status(*s, stat.status_label().to_string()));
+                    }
                 }
             }
         }
@@ -281,6 +1097,8 @@ impl GatewayHandler {
         let mut parsed_req = None;
         let mut content_length = 0;
         let mut chars: Vec<u8> = Vec::new();
+        let mut forwarded_for = None;
+        let mut real_ip = None;
 
         loop {
             // Pull a chunk of bytes from the stream and see what we can
@@ -328,15 +1146,30 @@ impl GatewayHandler {
                 header_byte_count = res.unwrap();
 
                 for header in req.headers.iter() {
-                    if header.name.to_lowercase().as_str() == "content-length" {
-                        let len = String::from_utf8_lossy(header.value);
-                        if let Ok(size) = len.parse::<usize>() {
-                            content_length = size;
-                            break;
+                    match header.name.to_lowercase().as_str() {
+                        "content-length" => {
+                            let len = String::from_utf8_lossy(header.value);
+                            if let Ok(size) = len.parse::<usize>() {
+                                content_length = size;
+                            }
                         }
+                        "x-forwarded-for" => {
+                            forwarded_for = Some(String::from_utf8_lossy(header.value).to_string())
+                        }
+                        "x-real-ip" => {
+                            real_ip = Some(String::from_utf8_lossy(header.value).to_string())
+                        }
+                        _ => {}
                     }
                 }
 
+                request.client_ip = eg::util::forwarded_client_ip(
+                    request.address.ip(),
+                    forwarded_for.as_deref(),
+                    real_ip.as_deref(),
+                    &self.trusted_proxies,
+                );
+
                 let method = req
                     .method
                     .map(|v| v.to_string())
@@ -411,6 +1244,9 @@ impl GatewayHandler {
         let mut service: Option<String> = None;
         let mut params: Vec<EgValue> = Vec::new();
         let mut format = idl::DataFormat::Fieldmapper;
+        let mut sensitive = false;
+        let mut locale = None;
+        let mut api_level = None;
 
         // First see if the caller requested a format so we can
         // apply the needed changes while parsing the data below.
@@ -424,22 +1260,14 @@ impl GatewayHandler {
             match k.as_ref() {
                 "method" => method = Some(v.to_string()),
                 "service" => service = Some(v.to_string()),
+                "sensitive" => sensitive = matches!(v.as_ref(), "1" | "true"),
+                "locale" => locale = Some(parse_locale(&v)?),
+                "api_level" => api_level = Some(parse_api_level(&v)?),
                 "param" => {
                     let jval = json::parse(&v)
                         .map_err(|e| format!("Cannot parse parameter: {e} : {v}"))?;
 
-                    let val;
-                    if format.is_hash() {
-                        // Caller is sending flat-hash parameters.
-                        // Translate them into Fieldmapper parameters
-                        // before relaying them to opensrf.
-                        val = EgValue::from_classed_json_hash(jval)?;
-                    } else {
-                        // Caller is sending array-based Fieldmapper IDL value.
-                        val = EgValue::from_json_value(jval)?;
-                    }
-
-                    params.push(val);
+                    params.push(Self::parse_param(&format, jval)?);
                 }
                 _ => {} // ignore other stuff
             }
@@ -458,9 +1286,94 @@ impl GatewayHandler {
             service,
             method: Some(osrf_method),
             http_method: http_req.method.to_string(),
+            sensitive,
+            locale,
+            api_level,
+        })
+    }
+
+    /// Translate a JSON API parameter into an EgValue.
+    fn parse_param(format: &idl::DataFormat, jval: json::JsonValue) -> EgResult<EgValue> {
+        if format.is_hash() {
+            // Caller is sending flat-hash parameters.
+            // Translate them into Fieldmapper parameters
+            // before relaying them to opensrf.
+            EgValue::from_classed_json_hash(jval)
+        } else {
+            // Caller is sending array-based Fieldmapper IDL value.
+            EgValue::from_json_value(jval)
+        }
+    }
+
+    /// Start a recording of an API call, when recording is enabled.
+    ///
+    /// Params for protected (log_protect) and sensitive calls are not
+    /// recorded.  Values that look like authtokens are replaced with a
+    /// placeholder and values of SENSITIVE_FIELDS (names, addresses,
+    /// passwords, etc.) are redacted.
+    fn start_recording(&self, req: &ParsedGatewayRequest) -> Option<EgValue> {
+        self.record_dir.as_ref()?;
+
+        let method = req.method.as_ref()?;
+
+        let redacted = req.sensitive
+            || conf::config()
+                .log_protect()
+                .iter()
+                .any(|m| method.method().starts_with(m));
+
+        let params = if redacted {
+            EgValue::Null
+        } else {
+            let mut params = EgValue::new_array();
+            for param in method.params() {
+                let mut param = param.clone();
+                if req.format.is_hash() {
+                    param.to_classed_hash();
+                }
+                scrub_recorded(&mut param);
+                params.push(param).ok()?;
+            }
+            params
+        };
+
+        Some(eg::hash! {
+            "time": date::to_iso(&date::now()),
+            "service": req.service.as_str(),
+            "method": method.method(),
+            "format": format_name(&req.format),
+            "locale": req.locale.as_deref(),
+            "api_level": req.api_level.map(i64::from),
+            "redacted": redacted,
+            "params": params,
         })
     }
 
+    /// Add the response to a recording and write it to the record
+    /// directory, one file per API call.  Failures are logged and
+    /// otherwise ignored.
+    fn save_recording(&self, request: &GatewayRequest, mut rec: EgValue, response: &EgValue) {
+        let Some(dir) = self.record_dir.as_ref() else {
+            return;
+        };
+
+        rec["request_id"] = request.request_id.as_str().into();
+        rec["status"] = response["status"].clone();
+
+        if !rec["redacted"].boolish() {
+            let mut response = response.clone();
+            scrub_recorded(&mut response);
+            rec["response"] = response;
+        }
+
+        let millis = (date::epoch_secs() * 1000.0) as u64;
+        let path = dir.join(format!("{millis}-{}.json", request.request_id));
+
+        if let Err(e) = fs::write(&path, rec.dump()) {
+            log::error!("Cannot write gateway recording {path:?}: {e}");
+        }
+    }
+
     fn log_request(&self, request: &GatewayRequest, req: &ParsedGatewayRequest) {
         let method = req.method.as_ref().unwrap();
 
@@ -468,11 +1381,13 @@ impl GatewayHandler {
             method.method(),
             method.params(),
             conf::config().log_protect(),
+            req.sensitive,
         );
 
         log::info!(
-            "ACT:[{}] {} {} {}",
-            request.address,
+            "ACT:[{}] [{}] {} {} {}",
+            request.client_ip,
+            self.ingress,
             req.service,
             method.method(),
             log_params
@@ -481,7 +1396,7 @@ impl GatewayHandler {
         // Also log as INFO e.g. gateway.xx.log
         log::info!(
             "[{}] {} {} {}",
-            request.address,
+            request.client_ip,
             req.service,
             method.method(),
             log_params
@@ -505,7 +1420,12 @@ impl mptc::RequestHandler for GatewayHandler {
     fn process(&mut self, mut request: Box<dyn mptc::Request>) -> Result<(), String> {
         let request = GatewayRequest::downcast(&mut request);
 
-        log::debug!("[{}] Gateway request received", request.address);
+        // Every new request gets its own log trace.  The trace is
+        // thread-local, so it's created here in the worker thread.
+        Logger::mk_log_trace();
+        request.request_id = Logger::get_log_trace();
+
+        log::debug!("[{}] Gateway request received", request.client_ip);
 
         let result = self.handle_request(request);
 
@@ -522,16 +1442,37 @@ impl mptc::RequestHandler for GatewayHandler {
 
 struct GatewayStream {
     listener: TcpListener,
+    suppress_fields: Arc<SuppressFields>,
+    ingress: String,
+    /// Proxies whose X-Forwarded-For / X-Real-IP headers we believe.
+    trusted_proxies: Arc<Vec<IpNet>>,
+    timeouts: Arc<RelayTimeouts>,
+    record_dir: Option<Arc<PathBuf>>,
 }
 
 impl GatewayStream {
-    fn new(address: &str, port: u16) -> EgResult<Self> {
+    fn new(
+        address: &str,
+        port: u16,
+        suppress_fields: SuppressFields,
+        ingress: &str,
+        trusted_proxies: Vec<IpNet>,
+        timeouts: RelayTimeouts,
+        record_dir: Option<PathBuf>,
+    ) -> EgResult<Self> {
         log::info!("EG Gateway listening at {address}:{port}");
 
         let listener = eg::util::tcp_listener(address, port, GATEWAY_POLL_TIMEOUT)
             .map_err(|e| format!("Cannot listen for connections on {address}:{port} {e}"))?;
 
-        let stream = GatewayStream { listener };
+        let stream = GatewayStream {
+            listener,
+            suppress_fields: Arc::new(suppress_fields),
+            ingress: ingress.to_string(),
+            trusted_proxies: Arc::new(trusted_proxies),
+            timeouts: Arc::new(timeouts),
+            record_dir: record_dir.map(Arc::new),
+        };
 
         Ok(stream)
     }
@@ -549,13 +1490,12 @@ impl mptc::RequestStream for GatewayStream {
             },
         };
 
-        // Every new request gets its own log trace.
-        Logger::mk_log_trace();
-
         let request = GatewayRequest {
             stream,
             address,
+            client_ip: address.ip(),
             start_time: date::now(),
+            request_id: String::new(),
         };
 
         Ok(Some(Box::new(request)))
@@ -565,6 +1505,11 @@ impl mptc::RequestStream for GatewayStream {
         let handler = GatewayHandler {
             bus: None,
             partial_buffer: None,
+            suppress_fields: self.suppress_fields.clone(),
+            ingress: self.ingress.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+            timeouts: self.timeouts.clone(),
+            record_dir: self.record_dir.clone(),
         };
 
         Box::new(handler)
@@ -589,22 +1534,20 @@ fn main() {
         _ => DEFAULT_PORT,
     };
 
-    let init_ops = eg::init::InitOptions {
-        // As a gateway, we generally won't have access to the host
-        // settings, since that's typically on a private domain.
-        skip_host_settings: true,
-
-        // Skip logging so we can use the loging config in
-        // the gateway() config instead.
-        skip_logging: true,
-        appname: Some(String::from("http-gateway")),
-    };
-
     // Connect to OpenSRF, parse the IDL
     // NOTE: Since we are not fetching host settings, we use
     // the default IDL path unless it's overridden with the
     // EG_IDL_FILE environment variable.
-    eg::init::with_options(&init_ops).expect("Evergreen init");
+    eg::init::Builder::new()
+        .appname("http-gateway")
+        // As a gateway, we generally won't have access to the host
+        // settings, since that's typically on a private domain.
+        .skip_host_settings()
+        // Skip logging so we can use the loging config in
+        // the gateway() config instead.
+        .skip_logging()
+        .build()
+        .expect("Evergreen init");
 
     // Setup logging with the gateway config
     let gateway_conf = conf::config().gateway().expect("Gateway config Required");
@@ -614,7 +1557,49 @@ fn main() {
         .init()
         .expect("Logger Init");
 
-    let stream = GatewayStream::new(&address, port).expect("Build stream");
+    let suppress_fields = match env::var("EG_HTTP_GATEWAY_SUPPRESS_FIELDS") {
+        Ok(v) => parse_suppress_fields(&v).expect("Invalid suppress fields"),
+        _ => HashMap::new(),
+    };
+
+    let ingress = env::var("EG_HTTP_GATEWAY_INGRESS").unwrap_or(DEFAULT_INGRESS.to_string());
+
+    // Comma-separated addresses / CIDR ranges, e.g. "127.0.0.1,10.0.0.0/8"
+    let trusted_proxies = match env::var("EG_HTTP_GATEWAY_TRUSTED_PROXIES") {
+        Ok(v) => eg::util::parse_ip_ranges(&v).expect("Invalid trusted proxies"),
+        _ => Vec::new(),
+    };
+
+    let default_timeout = match env::var("EG_HTTP_GATEWAY_RELAY_TIMEOUT") {
+        Ok(v) => v.parse::<i32>().expect("Invalid relay timeout"),
+        _ => OSRF_RELAY_TIMEOUT,
+    };
+
+    let timeouts = RelayTimeouts {
+        default: default_timeout,
+        methods: match env::var("EG_HTTP_GATEWAY_METHOD_TIMEOUTS") {
+            Ok(v) => parse_method_timeouts(&v).expect("Invalid method timeouts"),
+            _ => Vec::new(),
+        },
+    };
+
+    // Record API calls and their responses for replay with
+    // eg-gateway-replay.  Recordings may contain patron data.
+    let record_dir = match env::var("EG_HTTP_GATEWAY_RECORD_DIR") {
+        Ok(v) => Some(init_record_dir(&v).expect("Invalid record directory")),
+        _ => None,
+    };
+
+    let stream = GatewayStream::new(
+        &address,
+        port,
+        suppress_fields,
+        &ingress,
+        trusted_proxies,
+        timeouts,
+        record_dir,
+    )
+    .expect("Build stream");
     let mut server = mptc::Server::new(Box::new(stream));
 
     if let Ok(n) = env::var("EG_HTTP_GATEWAY_MAX_WORKERS") {
@@ -631,3 +1616,119 @@ fn main() {
 
     server.run();
 }
+
+/// Parse a list of "class.field" values, separated by spaces and/or
+/// commas, into a set of fields to suppress per IDL class.
+///
+/// E.g. EG_HTTP_GATEWAY_SUPPRESS_FIELDS="au.passwd au.email"
+fn parse_suppress_fields(value: &str) -> EgResult<SuppressFields> {
+    let mut suppress = SuppressFields::new();
+
+    for part in value.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let (classname, field) = part
+            .split_once('.')
+            .ok_or_else(|| format!("Suppress field '{part}' is not of the form class.field"))?;
+
+        let class = idl::get_class(classname)?;
+
+        if !class.has_field(field) {
+            return Err(format!("IDL class '{classname}' has no field named '{field}'").into());
+        }
+
+        log::info!("Suppressing field {classname}.{field} in hash responses");
+
+        suppress
+            .entry(classname.to_string())
+            .or_default()
+            .push(field.to_string());
+    }
+
+    Ok(suppress)
+}
+
+/// Parse a list of "method=seconds" values, separated by spaces and/or
+/// commas.  Method names ending in '*' apply to all methods starting
+/// with the preceding text.
+///
+/// E.g. EG_HTTP_GATEWAY_METHOD_TIMEOUTS="open-ils.reporter.*=1800 open-ils.pcrud.search.*=60"
+fn parse_method_timeouts(value: &str) -> EgResult<Vec<(String, i32)>> {
+    let mut timeouts = Vec::new();
+
+    for part in value.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let (method, seconds) = part
+            .split_once('=')
+            .ok_or_else(|| format!("Method timeout '{part}' is not of the form method=seconds"))?;
+
+        let seconds = seconds
+            .parse::<i32>()
+            .ok()
+            .filter(|s| *s > 0)
+            .ok_or_else(|| format!("Invalid timeout for method {method}: {seconds}"))?;
+
+        log::info!("Relay timeout for {method} is {seconds}s");
+
+        timeouts.push((method.to_string(), seconds));
+    }
+
+    Ok(timeouts)
+}
+
+/// Create the recording directory if needed.
+fn init_record_dir(value: &str) -> EgResult<PathBuf> {
+    let dir = Path::new(value);
+
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {dir:?}: {e}"))?;
+
+    log::info!("Recording API calls to {dir:?}");
+
+    Ok(dir.to_path_buf())
+}
+
+/// Locale query parameter, e.g. locale=fr-CA.
+fn parse_locale(value: &str) -> EgResult<String> {
+    if eg::osrf::message::is_valid_locale(value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("Invalid locale: '{value}'").into())
+    }
+}
+
+/// api_level query parameter, e.g. api_level=2.
+fn parse_api_level(value: &str) -> EgResult<u8> {
+    value
+        .parse::<u8>()
+        .map_err(|e| format!("Invalid api_level '{value}': {e}").into())
+}
+
+/// Name of a data format as passed in the "format" request param.
+fn format_name(format: &idl::DataFormat) -> &'static str {
+    match format {
+        idl::DataFormat::Fieldmapper => "fieldmapper",
+        idl::DataFormat::Hash => "hash",
+        idl::DataFormat::HashFull => "hashfull",
+    }
+}
+
+/// Replace any string that looks like an authtoken (32 hex characters)
+/// with a placeholder and redact the values of SENSITIVE_FIELDS.
+fn scrub_recorded(value: &mut EgValue) {
+    if let Some(s) = value.as_str() {
+        if s.len() == 32 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            *value = AUTHTOKEN_PLACEHOLDER.into();
+        }
+    } else if value.is_array() {
+        for member in value.members_mut() {
+            scrub_recorded(member);
+        }
+    } else if value.is_object() {
+        for (key, member) in value.entries_mut() {
+            if SENSITIVE_FIELDS.contains(&key) {
+                if !member.is_null() {
+                    *member = SENSITIVE_REDACTED.into();
+                }
+            } else {
+                scrub_recorded(member);
+            }
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! Launch and supervise multiple Evergreen components as one node.
+use eg::result::EgResult;
+use evergreen as eg;
+use mptc::signals::SignalTracker;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+use yaml_rust::{Yaml, YamlLoader};
+
+const HELP_TEXT: &str = r#"
+Launch the components of an Evergreen node -- router, gateways, SIP
+mediator, services, etc. -- as child processes and restart them when
+they exit, according to each component's restart policy.
+
+Useful for small sites and containers where running each component
+as its own system service is more trouble than it's worth.
+
+./eg-node --config /usr/local/etc/eg-node.yml
+
+Options
+
+    --config <path>
+        Node config file.  Defaults to /usr/local/etc/eg-node.yml
+
+Signals
+
+    SIGTERM - Forwarded to all components, which should exit quickly.
+    SIGINT  - Forwarded to all components, which should exit gracefully.
+    SIGHUP  - Forwarded to all components, which should reload.
+    SIGUSR1 - Forwarded to all components.  Thread pool servers (e.g.
+              eg-http-gateway) replace their workers one at a time.
+    SIGUSR2 - Forwarded to all components.  Thread pool servers log
+              the state of each worker.
+
+    Components still running after the shutdown timeout are killed.
+
+Config File
+
+    # Seconds to wait for components to exit at shutdown.
+    shutdown-timeout: 30
+
+    # Environment variables applied to every component.
+    env:
+      OSRF_CONFIG: /openils/conf/opensrf_core.xml
+
+    components:
+      - name: router
+        # Commands without a path are found in the same directory
+        # as eg-node, then in the PATH.
+        command: eg-router
+
+        # always     - Restart whenever the component exits.
+        # on-failure - Restart when the component exits with an error.
+        # never      - Never restart.
+        restart: always
+
+        # Seconds to wait before restarting.  Defaults to 2.
+        restart-delay: 2
+
+        # Stop restarting after this many restarts in a row.  Any
+        # component which runs for 60 seconds is no longer considered
+        # to be restarting in a row.  Defaults to unlimited.
+        max-restarts: 10
+
+      - name: http-gateway
+        command: eg-http-gateway
+        env:
+          EG_HTTP_GATEWAY_PORT: 9682
+
+      - name: sip2-mediator
+        command: eg-sip2-mediator
+        args: ["--config", "/usr/local/etc/eg-sip2-mediator.yml"]
+
+      - name: rs-circ
+        command: eg-service-rs-circ
+        # Disabled components are not started.
+        enabled: false
+"#;
+
+const DEFAULT_CONFIG_FILE: &str = "/usr/local/etc/eg-node.yml";
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+const DEFAULT_RESTART_DELAY: u64 = 2;
+
+/// How often we check on our components.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Components which run at least this long have their consecutive
+/// restart count reset.
+const STABLE_RUN_TIME: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RestartPolicy {
+    Always,
+    OnFailure,
+    Never,
+}
+
+impl TryFrom<&str> for RestartPolicy {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "always" => Ok(Self::Always),
+            "on-failure" => Ok(Self::OnFailure),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("Invalid restart policy: {s}")),
+        }
+    }
+}
+
+struct Component {
+    name: String,
+    command: PathBuf,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    restart: RestartPolicy,
+    restart_delay: Duration,
+    max_restarts: Option<u32>,
+
+    child: Option<Child>,
+    started: Option<Instant>,
+    restart_at: Option<Instant>,
+    restarts: u32,
+}
+
+impl Component {
+    fn from_yaml(y: &Yaml, node_env: &[(String, String)]) -> EgResult<Component> {
+        let name = y["name"]
+            .as_str()
+            .ok_or_else(|| format!("Component 'name' required: {y:?}"))?;
+
+        let command = y["command"]
+            .as_str()
+            .ok_or_else(|| format!("Component {name} 'command' required"))?;
+
+        let restart = match y["restart"].as_str() {
+            Some(r) => RestartPolicy::try_from(r).map_err(|e| format!("{name}: {e}"))?,
+            None => RestartPolicy::Always,
+        };
+
+        let restart_delay =
+            yaml_uint(&y["restart-delay"], name, "restart-delay")?.unwrap_or(DEFAULT_RESTART_DELAY);
+
+        let mut args = Vec::new();
+        for arg in y["args"].as_vec().unwrap_or(&Vec::new()) {
+            args.push(yaml_string(arg).ok_or_else(|| format!("{name}: invalid arg {arg:?}"))?);
+        }
+
+        let mut env = node_env.to_vec();
+        env.append(&mut env_vars(&y["env"])?);
+
+        Ok(Component {
+            name: name.to_string(),
+            command: find_command(command),
+            args,
+            env,
+            restart,
+            restart_delay: Duration::from_secs(restart_delay),
+            max_restarts: yaml_uint(&y["max-restarts"], name, "max-restarts")?,
+            child: None,
+            started: None,
+            restart_at: None,
+            restarts: 0,
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// True if the component is running or waiting to be restarted.
+    fn is_active(&self) -> bool {
+        self.child.is_some() || self.restart_at.is_some()
+    }
+
+    fn start(&mut self) {
+        log::info!("Starting {} ({:?})", self.name, self.command);
+
+        let result = Command::new(&self.command)
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .spawn();
+
+        match result {
+            Ok(child) => {
+                log::info!("{} started with pid {}", self.name, child.id());
+                self.child = Some(child);
+                self.started = Some(Instant::now());
+            }
+            Err(e) => {
+                log::error!("Cannot start {}: {e}", self.name);
+                self.schedule_restart(false);
+            }
+        }
+    }
+
+    /// See if our component has exited and restart it if needed.
+    fn check(&mut self) {
+        if let Some(restart_at) = self.restart_at {
+            if Instant::now() >= restart_at {
+                self.restart_at = None;
+                self.start();
+            }
+            return;
+        }
+
+        let Some(child) = self.child.as_mut() else {
+            return;
+        };
+
+        let status = match child.try_wait() {
+            Ok(Some(s)) => s,
+            Ok(None) => return, // Still running
+            Err(e) => {
+                log::error!("Cannot check status of {}: {e}", self.name);
+                return;
+            }
+        };
+
+        self.child = None;
+
+        log::warn!("{} exited with {status}", self.name);
+
+        if self.started.is_some_and(|s| s.elapsed() >= STABLE_RUN_TIME) {
+            self.restarts = 0;
+        }
+
+        self.schedule_restart(status.success());
+    }
+
+    fn schedule_restart(&mut self, exited_ok: bool) {
+        let restart = match self.restart {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => !exited_ok,
+            RestartPolicy::Never => false,
+        };
+
+        if !restart {
+            log::info!("Not restarting {} per its restart policy", self.name);
+            return;
+        }
+
+        if let Some(max) = self.max_restarts {
+            if self.restarts >= max {
+                log::error!("{} restarted {max} times in a row; giving up", self.name);
+                return;
+            }
+        }
+
+        self.restarts += 1;
+        self.restart_at = Some(Instant::now() + self.restart_delay);
+
+        log::info!(
+            "Restarting {} in {} seconds",
+            self.name,
+            self.restart_delay.as_secs()
+        );
+    }
+
+    /// Send a signal (e.g. "TERM") to our running component.
+    fn signal(&self, signal: &str) {
+        let Some(child) = self.child.as_ref() else {
+            return;
+        };
+
+        let result = Command::new("kill")
+            .args(["-s", signal, &child.id().to_string()])
+            .status();
+
+        if let Err(e) = result {
+            log::error!("Cannot send SIG{signal} to {}: {e}", self.name);
+        }
+    }
+}
+
+struct Node {
+    components: Vec<Component>,
+    shutdown_timeout: Duration,
+    signals: SignalTracker,
+}
+
+impl Node {
+    fn from_file(path: &str) -> EgResult<Node> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {path}: {e}"))?;
+
+        let docs = YamlLoader::load_from_str(&text)
+            .map_err(|e| format!("Cannot parse YAML file {path}: {e}"))?;
+
+        let doc = docs.first().ok_or("Node config is empty")?;
+
+        let node_env = env_vars(&doc["env"])?;
+
+        let mut components = Vec::new();
+        for y in doc["components"].as_vec().unwrap_or(&Vec::new()) {
+            if !y["enabled"].as_bool().unwrap_or(true) {
+                continue;
+            }
+            components.push(Component::from_yaml(y, &node_env)?);
+        }
+
+        if components.is_empty() {
+            return Err("Node config contains no enabled components".into());
+        }
+
+        let shutdown_timeout = yaml_uint(&doc["shutdown-timeout"], "node", "shutdown-timeout")?
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+
+        let mut signals = SignalTracker::new();
+        signals.track_graceful_shutdown();
+        signals.track_fast_shutdown();
+        signals.track_reload();
+        signals.track_restart();
+        signals.track_dump_state();
+
+        Ok(Node {
+            components,
+            shutdown_timeout: Duration::from_secs(shutdown_timeout),
+            signals,
+        })
+    }
+
+    fn run(&mut self) {
+        for component in self.components.iter_mut() {
+            component.start();
+        }
+
+        while !self.signals.any_shutdown_requested() {
+            if self.signals.reload_requested() {
+                self.signals.handle_reload_requested();
+                self.signal_all("HUP");
+            }
+
+            if self.signals.restart_requested() {
+                self.signals.handle_restart_requested();
+                self.signal_all("USR1");
+            }
+
+            if self.signals.dump_state_requested() {
+                self.signals.handle_dump_state_requested();
+                self.signal_all("USR2");
+            }
+
+            for component in self.components.iter_mut() {
+                component.check();
+            }
+
+            if !self.components.iter().any(|c| c.is_active()) {
+                log::info!("No components left running; exiting");
+                return;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        self.shutdown();
+    }
+
+    fn signal_all(&self, signal: &str) {
+        for component in self.components.iter() {
+            component.signal(signal);
+        }
+    }
+
+    fn shutdown(&mut self) {
+        if self.signals.fast_shutdown_requested() {
+            log::info!("Fast shutdown requested");
+            self.signal_all("TERM");
+        } else {
+            log::info!("Graceful shutdown requested");
+            self.signal_all("INT");
+        }
+
+        let start = Instant::now();
+
+        while start.elapsed() < self.shutdown_timeout {
+            for component in self.components.iter_mut() {
+                if let Some(child) = component.child.as_mut() {
+                    if let Ok(Some(status)) = child.try_wait() {
+                        log::info!("{} exited with {status}", component.name);
+                        component.child = None;
+                    }
+                }
+            }
+
+            if !self.components.iter().any(|c| c.is_running()) {
+                return;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        for component in self.components.iter_mut() {
+            if let Some(mut child) = component.child.take() {
+                log::warn!("Killing {} after shutdown timeout", component.name);
+                child.kill().ok();
+                child.wait().ok();
+            }
+        }
+    }
+}
+
+/// Optional non-negative integer setting.
+///
+/// Returns an Err if the value is present but is not an integer that
+/// fits in a T, e.g. a negative number.
+fn yaml_uint<T: TryFrom<i64>>(y: &Yaml, owner: &str, key: &str) -> EgResult<Option<T>> {
+    if y.is_badvalue() || y.is_null() {
+        return Ok(None);
+    }
+
+    match y.as_i64().and_then(|v| T::try_from(v).ok()) {
+        Some(v) => Ok(Some(v)),
+        None => Err(format!("{owner}: invalid {key} {y:?}").into()),
+    }
+}
+
+/// Numbers and booleans are allowed where strings are expected, since
+/// YAML parses unquoted values like ports as numbers.
+fn yaml_string(y: &Yaml) -> Option<String> {
+    match y {
+        Yaml::String(s) => Some(s.to_string()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Real(r) => Some(r.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn env_vars(y: &Yaml) -> EgResult<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+
+    let Some(hash) = y.as_hash() else {
+        return Ok(vars);
+    };
+
+    for (k, v) in hash.iter() {
+        let (Some(key), Some(value)) = (k.as_str(), yaml_string(v)) else {
+            return Err(format!("Invalid env value: {k:?} => {v:?}").into());
+        };
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+/// Commands without a path are preferably found alongside our own
+/// binary, so a node runs the components it was installed with.
+fn find_command(command: &str) -> PathBuf {
+    let path = PathBuf::from(command);
+
+    if path.components().count() > 1 {
+        return path;
+    }
+
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+    {
+        let sibling = dir.join(command);
+        if sibling.is_file() {
+            return sibling;
+        }
+    }
+
+    path
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optopt("", "config", "", "");
+
+    let args: Vec<String> = env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let config = params
+        .opt_str("config")
+        .unwrap_or(DEFAULT_CONFIG_FILE.to_string());
+
+    // Logs like every other component, using the logging config from
+    // opensrf_core.xml.  This also verifies the bus is reachable before
+    // we launch any components.
+    eg::init::Builder::new()
+        .appname("eg-node")
+        .skip_host_settings()
+        .skip_idl()
+        .build()?;
+
+    let mut node = Node::from_file(&config)?;
+
+    node.run();
+
+    Ok(())
+}
@@ -0,0 +1,440 @@
+//! Overview of OpenSRF services, workers, and bus queues across domains.
+use eg::date;
+use eg::osrf::addr::BusAddress;
+use eg::osrf::message::{Message, MessageType, MethodCall, Payload, TransportMessage};
+use eg::osrf::worker::ControlCommand;
+use eg::result::EgResult;
+use eg::util;
+use eg::Client;
+use eg::EgValue;
+use evergreen as eg;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+const HELP_TEXT: &str = r#"
+Print an overview of the services registered with our router,
+including services registered on remote domains.
+
+For each service instance, reports how many API calls the router has
+routed to it, its worker counts, its memory use (RSS), the number of
+requests waiting in its bus queue, and when it last answered a status
+request.
+
+For each domain, reports the number of bus keys and how many of those
+have been flagged as stale (given an expire time) by eg-buswatch.
+
+./eg-osrf-stats --watch 5
+
+Options
+
+    --service <name>
+        Only report on this service.
+
+    --json
+        Print the overview as JSON, one line per report.
+
+    --watch <seconds>
+        Refresh the overview every this many seconds until killed.
+
+    --timeout <seconds>
+        How long to wait for service instances to answer status
+        requests.  Defaults to 3.
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+"#;
+
+const DEFAULT_TIMEOUT: i32 = 3;
+
+/// Clear the terminal and move the cursor to the top left.
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[H";
+
+struct OsrfStats {
+    client: Client,
+    service: Option<String>,
+    timeout: i32,
+
+    /// When each instance last answered a status request, keyed on
+    /// the bus address of the instance.
+    last_seen: HashMap<String, String>,
+}
+
+impl OsrfStats {
+    /// Build the overview from the router summary, the bus, and
+    /// status requests sent to each service instance.
+    fn collect(&mut self) -> EgResult<EgValue> {
+        let mut summary = self
+            .client
+            .send_recv_one("router", "opensrf.router.info.summarize", None)?
+            .ok_or("Router returned no summary")?;
+
+        let mut router_domains = vec![summary["primary_domain"].take()];
+        for domain in summary["remote_domains"].members_mut() {
+            router_domains.push(domain.take());
+        }
+
+        let thread = util::random_number(16);
+        let mut domains = EgValue::new_array();
+
+        for router_domain in router_domains {
+            let domain = self.collect_domain(router_domain, &thread)?;
+            domains.push(domain)?;
+        }
+
+        let expected = domains
+            .members()
+            .flat_map(|d| d["services"].members())
+            .map(|s| s["instances"].len())
+            .sum();
+
+        let mut acks = self.collect_acks(&thread, expected)?;
+
+        for domain in domains.members_mut() {
+            for service in domain["services"].members_mut() {
+                for instance in service["instances"].members_mut() {
+                    let address = instance["address"].str()?.to_string();
+
+                    if let Some(mut ack) = acks.remove(&address) {
+                        instance["workers"] = ack["worker_count"].take();
+                        instance["active"] = ack["active"].take();
+                        instance["idle"] = ack["idle"].take();
+                        instance["quiesced"] = ack["quiesced"].take();
+                        instance["spawned"] = ack["spawned"].take();
+                        instance["reaped"] = ack["reaped"].take();
+                        instance["rss_bytes"] = ack["rss_bytes"].take();
+                        instance["cpu_secs"] = ack["cpu_secs"].take();
+                    }
+
+                    if let Some(seen) = self.last_seen.get(&address) {
+                        instance["last_seen"] = seen.as_str().into();
+                    }
+                }
+            }
+        }
+
+        Ok(domains)
+    }
+
+    /// Collect stats for one domain and send a status request to
+    /// each of its service instances.
+    fn collect_domain(&mut self, mut router_domain: EgValue, thread: &str) -> EgResult<EgValue> {
+        let name = router_domain["domain"].str()?.to_string();
+
+        let (keys, stale) = match self.bus_key_counts(&name) {
+            Ok((k, s)) => (EgValue::from(k), EgValue::from(s)),
+            Err(e) => {
+                log::warn!("Cannot read bus keys for domain {name}: {e}");
+                (EgValue::Null, EgValue::Null)
+            }
+        };
+
+        let mut domain = eg::hash! {
+            "domain": name.as_str(),
+            "route_count": router_domain["route_count"].take(),
+            "bus_keys": keys,
+            "stale_keys": stale,
+            "services": [],
+        };
+
+        for mut router_service in router_domain["services"].members_mut().map(|s| s.take()) {
+            let service_name = router_service["name"].str()?.to_string();
+
+            if let Some(wanted) = self.service.as_deref() {
+                if wanted != service_name {
+                    continue;
+                }
+            }
+
+            let mut service = eg::hash! {
+                "name": service_name.as_str(),
+                "route_count": router_service["route_count"].take(),
+                "instances": [],
+            };
+
+            for router_instance in router_service["instances"].members() {
+                let address = router_instance["address"].str()?;
+                let listen_address = router_instance["listen_address"].str()?;
+
+                let queue = match self.queue_depth(&name, listen_address) {
+                    Ok(q) => EgValue::from(q),
+                    Err(e) => {
+                        log::warn!("Cannot read queue depth for {listen_address}: {e}");
+                        EgValue::Null
+                    }
+                };
+
+                if let Err(e) = self.send_status_request(address, thread) {
+                    log::warn!("Cannot send status request to {address}: {e}");
+                }
+
+                service["instances"].push(eg::hash! {
+                    "address": address,
+                    "register_time": router_instance["register_time"].clone(),
+                    "route_count": router_instance["route_count"].clone(),
+                    "queue": queue,
+                    "workers": EgValue::Null,
+                    "active": EgValue::Null,
+                    "idle": EgValue::Null,
+                    "quiesced": EgValue::Null,
+                    "spawned": EgValue::Null,
+                    "reaped": EgValue::Null,
+                    "rss_bytes": EgValue::Null,
+                    "cpu_secs": EgValue::Null,
+                    "last_seen": EgValue::Null,
+                })?;
+            }
+
+            domain["services"].push(service)?;
+        }
+
+        Ok(domain)
+    }
+
+    /// Returns the number of opensrf keys on the domain's bus and
+    /// how many of them have been given an expire time by eg-buswatch.
+    fn bus_key_counts(&mut self, domain: &str) -> EgResult<(usize, usize)> {
+        let mut client = self.client.singleton().borrow_mut();
+        let bus = client.get_domain_bus(domain)?;
+
+        let keys = bus.keys("opensrf:*")?;
+
+        let mut stale = 0;
+        for key in keys.iter() {
+            if bus.ttl(key)? > -1 {
+                stale += 1;
+            }
+        }
+
+        Ok((keys.len(), stale))
+    }
+
+    /// Number of requests waiting to be picked up by a worker.
+    fn queue_depth(&mut self, domain: &str, listen_address: &str) -> EgResult<i32> {
+        self.client
+            .singleton()
+            .borrow_mut()
+            .get_domain_bus(domain)?
+            .llen(listen_address)
+    }
+
+    fn send_status_request(&mut self, address: &str, thread: &str) -> EgResult<()> {
+        let instance = BusAddress::from_str(address)?;
+
+        let tmsg = TransportMessage::with_body(
+            instance.as_str(),
+            self.client.address().as_str(),
+            thread,
+            Message::new(
+                MessageType::Request,
+                1,
+                Payload::Method(MethodCall::new(
+                    &ControlCommand::Status.method(),
+                    Vec::new(),
+                )),
+            ),
+        );
+
+        self.client
+            .singleton()
+            .borrow_mut()
+            .get_domain_bus(instance.domain())?
+            .send(tmsg)
+    }
+
+    /// Collect status replies until every instance has answered or
+    /// we time out, keyed on the bus address of each instance.
+    fn collect_acks(
+        &mut self,
+        thread: &str,
+        expected: usize,
+    ) -> EgResult<HashMap<String, EgValue>> {
+        let mut acks = HashMap::new();
+        let timer = util::Timer::new(self.timeout);
+
+        while acks.len() < expected && !timer.done() {
+            let recv_result = self
+                .client
+                .singleton()
+                .borrow_mut()
+                .bus_mut()
+                .recv(1, None)?;
+
+            let Some(mut tmsg) = recv_result else {
+                continue;
+            };
+
+            if tmsg.thread() != thread {
+                continue;
+            }
+
+            for mut msg in tmsg.body_mut().drain(..) {
+                let Payload::Result(mut result) = msg.take_payload() else {
+                    continue;
+                };
+
+                let ack = result.take_content();
+
+                if let Some(address) = ack["address"].as_str() {
+                    let now = date::to_iso(&date::now());
+                    self.last_seen.insert(address.to_string(), now);
+                    acks.insert(address.to_string(), ack);
+                }
+            }
+        }
+
+        Ok(acks)
+    }
+}
+
+/// Display a value as a table cell.
+fn cell(value: &EgValue) -> String {
+    if value.is_null() {
+        "-".to_string()
+    } else if let Some(s) = value.as_str() {
+        s.to_string()
+    } else {
+        value.dump()
+    }
+}
+
+/// Display a byte count in megabytes.
+fn megabytes(value: &EgValue) -> String {
+    match value.as_usize() {
+        Some(b) => (b / (1024 * 1024)).to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Display an instance by its host and process ID.
+fn instance_label(address: &str) -> String {
+    BusAddress::from_str(address)
+        .ok()
+        .and_then(|a| a.remainder().map(|r| r.to_string()))
+        .map(|r| r.split(':').take(2).collect::<Vec<&str>>().join(":"))
+        .unwrap_or_else(|| address.to_string())
+}
+
+fn print_table(domains: &EgValue) {
+    for domain in domains.members() {
+        println!(
+            "Domain {}  routed={}  bus-keys={}  stale-keys={}\n",
+            cell(&domain["domain"]),
+            cell(&domain["route_count"]),
+            cell(&domain["bus_keys"]),
+            cell(&domain["stale_keys"]),
+        );
+
+        println!(
+            "{:<32} {:<28} {:>7} {:>7} {:>6} {:>5} {:>5} {:>6}  {:<8} {:<24} {:<24}",
+            "SERVICE",
+            "INSTANCE",
+            "ROUTED",
+            "WORKERS",
+            "ACTIVE",
+            "IDLE",
+            "QUEUE",
+            "RSS-MB",
+            "STATE",
+            "REGISTERED",
+            "LAST SEEN",
+        );
+
+        for service in domain["services"].members() {
+            for instance in service["instances"].members() {
+                let state = match instance["quiesced"].as_bool() {
+                    Some(true) => "quiesced",
+                    Some(false) => "ok",
+                    None => "no reply",
+                };
+
+                println!(
+                    "{:<32} {:<28} {:>7} {:>7} {:>6} {:>5} {:>5} {:>6}  {:<8} {:<24} {:<24}",
+                    cell(&service["name"]),
+                    instance_label(instance["address"].as_str().unwrap_or("")),
+                    cell(&instance["route_count"]),
+                    cell(&instance["workers"]),
+                    cell(&instance["active"]),
+                    cell(&instance["idle"]),
+                    cell(&instance["queue"]),
+                    megabytes(&instance["rss_bytes"]),
+                    state,
+                    cell(&instance["register_time"]),
+                    cell(&instance["last_seen"]),
+                );
+            }
+        }
+
+        println!();
+    }
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optflag("", "json", "");
+    options.optopt("", "service", "", "");
+    options.optopt("", "watch", "", "");
+    options.optopt("", "timeout", "", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let timeout = match params.opt_str("timeout") {
+        Some(t) => t
+            .parse::<i32>()
+            .map_err(|e| format!("Invalid --timeout value '{t}': {e}"))?,
+        None => DEFAULT_TIMEOUT,
+    };
+
+    let watch = match params.opt_str("watch") {
+        Some(w) => Some(
+            w.parse::<u64>()
+                .map_err(|e| format!("Invalid --watch value '{w}': {e}"))?,
+        ),
+        None => None,
+    };
+
+    let json = params.opt_present("json");
+
+    let client = eg::init::Builder::new()
+        .skip_host_settings()
+        .skip_idl()
+        .build()?;
+
+    let mut stats = OsrfStats {
+        client,
+        timeout,
+        service: params.opt_str("service"),
+        last_seen: HashMap::new(),
+    };
+
+    loop {
+        let domains = stats.collect()?;
+
+        if json {
+            println!("{}", domains.dump());
+        } else {
+            if watch.is_some() {
+                print!("{CLEAR_SCREEN}");
+            }
+            print_table(&domains);
+        }
+
+        match watch {
+            Some(secs) => thread::sleep(Duration::from_secs(secs)),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
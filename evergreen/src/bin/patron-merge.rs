@@ -0,0 +1,124 @@
+use eg::common::user::{self, MergeOptions};
+use eg::result::EgResult;
+use evergreen as eg;
+
+const HELP_TEXT: &str = r#"
+Merge one or more patron accounts into a lead account.
+
+Circulations, holds, billings, notes, etc. are moved to the lead
+account and the merged accounts are removed.  A private note
+recording the merge is added to the lead account.
+
+./eg-patron-merge --staff-account 1 --lead-user 123 --user 456 --user 789
+
+Options
+
+    --staff-account <user-id>
+        Required.  ID of the staff account recorded as performing
+        the merge.
+
+    --lead-user <user-id>
+        Required.  ID of the account which absorbs the merged accounts.
+
+    --user <user-id>
+        Required.  ID of an account to merge into the lead account.
+        May be repeated.
+
+    --delete-addresses
+        Delete the addresses of merged accounts instead of moving
+        them to the lead account.
+
+    --delete-cards
+        Delete the cards of merged accounts instead of moving them
+        to the lead account.
+
+    --deactivate-cards
+        Deactivate the cards of merged accounts as they are moved.
+
+    --dry-run
+        Perform the merge, then roll back the changes.
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+"#;
+
+fn parse_id(name: &str, value: &str) -> EgResult<i64> {
+    value
+        .parse::<i64>()
+        .map_err(|e| format!("Invalid --{name} value '{value}': {e}").into())
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optopt("", "staff-account", "", "");
+    options.optopt("", "lead-user", "", "");
+    options.optmulti("", "user", "", "");
+    options.optflag("", "delete-addresses", "");
+    options.optflag("", "delete-cards", "");
+    options.optflag("", "deactivate-cards", "");
+    options.optflag("", "dry-run", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let staff_id = match params.opt_str("staff-account") {
+        Some(v) => parse_id("staff-account", &v)?,
+        None => return Err("--staff-account required".into()),
+    };
+
+    let lead_id = match params.opt_str("lead-user") {
+        Some(v) => parse_id("lead-user", &v)?,
+        None => return Err("--lead-user required".into()),
+    };
+
+    let mut user_ids = Vec::new();
+    for v in params.opt_strs("user") {
+        user_ids.push(parse_id("user", &v)?);
+    }
+
+    if user_ids.is_empty() {
+        return Err("One or more --user values required".into());
+    }
+
+    let merge_ops = MergeOptions {
+        delete_addresses: params.opt_present("delete-addresses"),
+        delete_cards: params.opt_present("delete-cards"),
+        deactivate_cards: params.opt_present("deactivate-cards"),
+    };
+
+    let client = eg::init::Builder::new().skip_host_settings().build()?;
+    let mut editor = eg::Editor::new(&client);
+
+    let staff = editor
+        .retrieve("au", staff_id)?
+        .ok_or_else(|| format!("No such staff account: {staff_id}"))?;
+
+    editor.give_requestor(staff);
+
+    editor.xact_begin()?;
+
+    if let Err(e) = user::merge_users(&mut editor, lead_id, &user_ids, &merge_ops) {
+        editor.rollback()?;
+        return Err(e);
+    }
+
+    if params.opt_present("dry-run") {
+        println!("Dry run; rolling back merge");
+        editor.rollback()?;
+    } else {
+        editor.commit()?;
+        println!("Merged {} user(s) into user {lead_id}", user_ids.len());
+    }
+
+    Ok(())
+}
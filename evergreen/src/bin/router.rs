@@ -828,13 +828,12 @@ impl Router {
 
 fn main() {
     // Prefer router-specific logging to the default client logging
-    let init_ops = init::InitOptions {
-        skip_logging: true,
-        skip_host_settings: true,
-        appname: Some(String::from("router")),
-    };
-
-    init::with_options(&init_ops).unwrap();
+    init::Builder::new()
+        .appname("router")
+        .skip_logging()
+        .skip_host_settings()
+        .build()
+        .unwrap();
 
     let config = conf::config();
 
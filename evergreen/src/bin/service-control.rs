@@ -0,0 +1,188 @@
+use eg::osrf::addr::BusAddress;
+use eg::osrf::message::{Message, MessageType, MethodCall, Payload, TransportMessage};
+use eg::osrf::worker::ControlCommand;
+use eg::result::EgResult;
+use eg::util;
+use eg::Client;
+use eg::EgValue;
+use evergreen as eg;
+use std::collections::HashSet;
+
+const HELP_TEXT: &str = r#"
+Broadcast a control command to every worker of a running service
+and report each acknowledgement.
+
+Service instances are found via the router on our domain, including
+instances registered on remote domains.
+
+./eg-service-control --service open-ils.rs-actor --command reload
+
+Options
+
+    --service <name>
+        Required.  Service name.
+
+    --command <reload|quiesce|resume|stats|status>
+        Required.
+
+        reload  - Workers reload their settings between sessions.
+        quiesce - The service un-registers from its routers so no new
+                  requests are routed to it.  Workers remain running.
+        resume  - The service re-registers with its routers.
+        stats   - Workers report request counts and start times.
+        status  - Servers report worker counts without involving
+                  their workers.
+
+    --timeout <seconds>
+        Stop waiting for acknowledgements after this many seconds.
+        Workers acknowledge between sessions, so busy workers may
+        take a while to reply.  Defaults to 15.
+
+    Standard OpenSRF environment variables (e.g. OSRF_CONFIG) are
+    also supported.
+"#;
+
+const DEFAULT_TIMEOUT: i32 = 15;
+
+/// Bus addresses of every registered instance of a service, pulled
+/// from the router summary.
+fn find_instances(client: &Client, service: &str) -> EgResult<Vec<String>> {
+    let summary = client
+        .send_recv_one("router", "opensrf.router.info.summarize", None)?
+        .ok_or("Router returned no summary")?;
+
+    let mut domains = vec![&summary["primary_domain"]];
+    domains.extend(summary["remote_domains"].members());
+
+    let mut instances = Vec::new();
+
+    for domain in domains {
+        for svc in domain["services"].members() {
+            if svc["name"].as_str() != Some(service) {
+                continue;
+            }
+            for instance in svc["instances"].members() {
+                if let Some(addr) = instance["address"].as_str() {
+                    instances.push(addr.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(instances)
+}
+
+fn main() -> EgResult<()> {
+    let mut options = getopts::Options::new();
+
+    options.optflag("", "help", "Show this message");
+    options.optopt("", "service", "", "");
+    options.optopt("", "command", "", "");
+    options.optopt("", "timeout", "", "");
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let params = options
+        .parse(&args[1..])
+        .map_err(|e| format!("Error parsing params: {e}"))?;
+
+    if params.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let service = params.opt_str("service").ok_or("--service required")?;
+
+    let command = params.opt_str("command").ok_or("--command required")?;
+    let command = ControlCommand::from_method(&format!(
+        "{}{command}",
+        eg::osrf::worker::CONTROL_METHOD_PREFIX
+    ))
+    .ok_or_else(|| format!("Invalid --command: {command}"))?;
+
+    let timeout = match params.opt_str("timeout") {
+        Some(t) => t
+            .parse::<i32>()
+            .map_err(|e| format!("Invalid --timeout value '{t}': {e}"))?,
+        None => DEFAULT_TIMEOUT,
+    };
+
+    let client = eg::init::Builder::new()
+        .skip_host_settings()
+        .skip_idl()
+        .build()?;
+
+    let instances = find_instances(&client, &service)?;
+
+    if instances.is_empty() {
+        return Err(format!("No registered instances of {service}").into());
+    }
+
+    let thread = util::random_number(16);
+
+    let mut expected: HashSet<String> = HashSet::new();
+
+    for addr in instances {
+        let instance = BusAddress::from_str(&addr)?;
+
+        let tmsg = TransportMessage::with_body(
+            instance.as_str(),
+            client.address().as_str(),
+            &thread,
+            Message::new(
+                MessageType::Request,
+                1,
+                Payload::Method(MethodCall::new(&command.method(), Vec::new())),
+            ),
+        );
+
+        client
+            .singleton()
+            .borrow_mut()
+            .get_domain_bus(instance.domain())?
+            .send(tmsg)?;
+
+        expected.insert(addr);
+    }
+
+    // We expect one acknowledgement from each instance, which tells
+    // us how many worker acknowledgements will follow.
+    let timer = util::Timer::new(timeout);
+    let mut missing: usize = expected.len();
+
+    while missing > 0 && !timer.done() {
+        let recv_result = client.singleton().borrow_mut().bus_mut().recv(1, None)?;
+
+        let Some(mut tmsg) = recv_result else {
+            continue;
+        };
+
+        if tmsg.thread() != thread {
+            continue;
+        }
+
+        for mut msg in tmsg.body_mut().drain(..) {
+            let Payload::Result(mut result) = msg.take_payload() else {
+                continue;
+            };
+
+            let ack: EgValue = result.take_content();
+
+            // Only instance acks carry the instance bus address.
+            if let Some(addr) = ack["address"].as_str() {
+                if expected.contains(addr) {
+                    missing += ack["workers"].as_usize().unwrap_or(0);
+                }
+            }
+
+            println!("{}", ack.dump());
+            missing -= 1;
+        }
+    }
+
+    if missing > 0 {
+        return Err(format!("Timed out waiting on {missing} acknowledgement(s)").into());
+    }
+
+    Ok(())
+}
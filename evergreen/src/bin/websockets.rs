@@ -1,24 +1,25 @@
 use eg::idl;
 use eg::osrf::addr::BusAddress;
-use eg::osrf::bus::Bus;
+use eg::osrf::bus::{Bus, BusReceiver, BusSender};
 use eg::osrf::conf;
 use eg::osrf::logging::Logger;
 use eg::osrf::message;
 use eg::Client;
 use eg::EgResult;
 use evergreen as eg;
+use ipnet::IpNet;
 use std::any::Any;
 use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::fmt;
 use std::net::TcpListener;
-use std::net::{SocketAddr, TcpStream};
+use std::net::{IpAddr, TcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tungstenite as ws;
 use ws::protocol::Message as WebSocketMessage;
 use ws::protocol::WebSocket;
@@ -34,7 +35,9 @@ const MAX_THREAD_SIZE: usize = 256;
 /// e.g. nginx, so this is more of a backstop.
 const MAX_MESSAGE_SIZE: usize = 10485760; // ~10M
 
-const WEBSOCKET_INGRESS: &str = "ws-translator-v3";
+/// Ingress value applied to relayed messages unless overridden
+/// with EG_WEBSOCKETS_INGRESS.
+const DEFAULT_INGRESS: &str = "ws-translator-v3";
 
 const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1";
 
@@ -50,6 +53,138 @@ const MAX_BACKLOG_SIZE: usize = 1000;
 
 const SIG_POLL_INTERVAL: u64 = 3;
 
+/// Key of the top-level object in heartbeat messages sent to clients.
+const HEARTBEAT_KEY: &str = "heartbeat";
+
+/// Top-level key of inbound messages requesting the session's own
+/// statistics, which are returned under the same key.  These are
+/// answered by the translator and never relayed to OpenSRF.
+const STATUS_KEY: &str = "translator_status";
+
+/// Top-level key of inbound messages asking the translator to
+/// DISCONNECT the stateful OpenSRF session named by the message's
+/// "thread" value.  The reply uses the same key.  Like status
+/// requests, these are never relayed to OpenSRF as-is.
+const DISCONNECT_KEY: &str = "disconnect_session";
+
+/// Key of the top-level object in advisory messages telling the
+/// client that its requests are being queued (throttle=true) or
+/// that the queue has drained (throttle=false).
+const BACKPRESSURE_KEY: &str = "backpressure";
+
+/// Handshake header carrying the shared secret when EG_WEBSOCKETS_SECRET
+/// is set, unless overridden with EG_WEBSOCKETS_SECRET_HEADER.
+const DEFAULT_SECRET_HEADER: &str = "X-EG-Websocket-Secret";
+
+/// Rules applied to the HTTP request which opens each websocket
+/// connection.  Requests which fail to comply are rejected with a 403
+/// before the connection is upgraded.
+#[derive(Debug, Clone, Default)]
+struct AccessControl {
+    /// Origin header values allowed to connect, e.g.
+    /// "https://staff.example.org".  Entries starting with "*." match
+    /// any subdomain, e.g. "*.example.org".  Empty means any origin,
+    /// including none.
+    allowed_origins: Vec<String>,
+
+    /// Name of the header carrying the shared secret.
+    secret_header: String,
+
+    /// When set, connections must provide this value in the secret header.
+    secret: Option<String>,
+
+    /// Proxies whose X-Forwarded-For / X-Real-IP headers we believe.
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl AccessControl {
+    fn from_env() -> Self {
+        let allowed_origins = env::var("EG_WEBSOCKETS_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|o| o.trim().trim_end_matches('/').to_lowercase())
+            .filter(|o| !o.is_empty())
+            .collect();
+
+        AccessControl {
+            allowed_origins,
+            secret_header: env::var("EG_WEBSOCKETS_SECRET_HEADER")
+                .unwrap_or(DEFAULT_SECRET_HEADER.to_string()),
+            secret: env::var("EG_WEBSOCKETS_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            // Comma-separated addresses / CIDR ranges, e.g. "127.0.0.1,10.0.0.0/8"
+            trusted_proxies: match env::var("EG_WEBSOCKETS_TRUSTED_PROXIES") {
+                Ok(v) => eg::util::parse_ip_ranges(&v).expect("Invalid trusted proxies"),
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    /// Address of the client, which differs from the peer address
+    /// when the connection arrives via a trusted proxy.
+    fn client_ip(&self, peer_ip: IpAddr, request: &ws::handshake::server::Request) -> IpAddr {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+
+        eg::util::forwarded_client_ip(
+            peer_ip,
+            header("X-Forwarded-For"),
+            header("X-Real-IP"),
+            &self.trusted_proxies,
+        )
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/').to_lowercase();
+
+        self.allowed_origins.iter().any(|allowed| {
+            if let Some(domain) = allowed.strip_prefix("*.") {
+                // Compare on the host portion of the origin, ignoring
+                // the scheme and port.
+                let host = origin.split("://").last().unwrap_or("");
+                let host = host.split(':').next().unwrap_or("");
+                host.ends_with(&format!(".{domain}"))
+            } else {
+                *allowed == origin
+            }
+        })
+    }
+
+    /// Compare secrets without bailing at the first mismatched byte,
+    /// so response timing reveals nothing about the secret.
+    fn secret_matches(secret: &str, value: &str) -> bool {
+        secret.len() == value.len()
+            && secret
+                .bytes()
+                .zip(value.bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+
+    /// Returns Err with the reason the handshake request is not allowed.
+    fn check(&self, request: &ws::handshake::server::Request) -> Result<(), String> {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+
+        if !self.allowed_origins.is_empty() {
+            match header("Origin") {
+                Some(origin) if self.origin_allowed(origin) => {}
+                Some(origin) => return Err(format!("origin not allowed: {origin}")),
+                None => return Err("no Origin header".to_string()),
+            }
+        }
+
+        if let Some(secret) = self.secret.as_deref() {
+            match header(&self.secret_header) {
+                Some(value) if Self::secret_matches(secret, value) => {}
+                Some(_) => return Err(format!("invalid {} header", self.secret_header)),
+                None => return Err(format!("no {} header", self.secret_header)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /* Server spawns a new client session per connection.
  *
  * Each client session is composed of 3 threads: Inbound, Main, and Outbound.
@@ -85,7 +220,7 @@ struct SessionInbound {
     shutdown_session: Arc<AtomicBool>,
 
     /// Websocket client address.
-    client_ip: SocketAddr,
+    client_ip: IpAddr,
 }
 
 impl fmt::Display for SessionInbound {
@@ -151,13 +286,13 @@ struct SessionOutbound {
 
     /// Pulls messages from the OpenSRF bus for delivery back to the
     /// websocket client.
-    osrf_receiver: Bus,
+    osrf_receiver: BusReceiver,
 
     /// Cleanup and exit if true.
     shutdown_session: Arc<AtomicBool>,
 
     /// Websocket client address.
-    client_ip: SocketAddr,
+    client_ip: IpAddr,
 }
 
 impl fmt::Display for SessionOutbound {
@@ -174,7 +309,7 @@ impl SessionOutbound {
                 break;
             }
 
-            let msg = match self.osrf_receiver.recv(SIG_POLL_INTERVAL as i32, None) {
+            let msg = match self.osrf_receiver.recv(SIG_POLL_INTERVAL as i32) {
                 Ok(op) => match op {
                     Some(tm) => {
                         log::debug!("{self} received message from: {}", tm.from());
@@ -212,10 +347,10 @@ struct Session {
     sender: WebSocket<TcpStream>,
 
     /// Relays request to the OpenSRF bus.
-    osrf_sender: Bus,
+    osrf_sender: BusSender,
 
     /// Websocket client address.
-    client_ip: SocketAddr,
+    client_ip: IpAddr,
 
     /// Cleanup and exit if true.
     shutdown_session: Arc<AtomicBool>,
@@ -246,6 +381,29 @@ struct Session {
     format: Option<idl::DataFormat>,
 
     shutdown: Arc<AtomicBool>,
+
+    /// If set, send a heartbeat message to the client after this
+    /// much time passes without any other message being sent.
+    heartbeat_interval: Option<Duration>,
+
+    /// When we last sent a message to the websocket client.
+    last_sent: Instant,
+
+    /// When the session started.
+    started: Instant,
+
+    /// Number of API requests relayed to OpenSRF.
+    reqs_relayed: usize,
+
+    /// Ingress applied to all messages relayed to OpenSRF.
+    ingress: String,
+
+    /// If true, tell the client when we start and stop queueing
+    /// its requests.
+    signal_backpressure: bool,
+
+    /// True if we have told the client to throttle its requests.
+    throttled: bool,
 }
 
 impl fmt::Display for Session {
@@ -255,12 +413,25 @@ impl fmt::Display for Session {
 }
 
 impl Session {
-    fn run(stream: TcpStream, max_parallel: usize, shutdown: Arc<AtomicBool>) -> EgResult<()> {
-        let client_ip = stream
+    fn run(
+        stream: TcpStream,
+        max_parallel: usize,
+        heartbeat_interval: Option<Duration>,
+        shutdown: Arc<AtomicBool>,
+        ingress: &str,
+        signal_backpressure: bool,
+        access: &AccessControl,
+    ) -> EgResult<()> {
+        let peer_ip = stream
             .peer_addr()
-            .map_err(|e| format!("Could not determine client IP address: {e}"))?;
+            .map_err(|e| format!("Could not determine client IP address: {e}"))?
+            .ip();
 
-        log::debug!("Starting new session for {client_ip}");
+        log::debug!("Starting new session for {peer_ip}");
+
+        // Replaced with the forwarded client address, if any, during
+        // the handshake.
+        let mut client_ip = peer_ip;
 
         // Split the TcpStream into a read/write pair so each endpoint
         // can be managed within its own thread.
@@ -269,9 +440,29 @@ impl Session {
             .try_clone()
             .map_err(|e| format!("Fatal error splitting client streams: {e}"))?;
 
+        // Vet the handshake request before upgrading the connection.
+        let check_access = |request: &ws::handshake::server::Request,
+                            response: ws::handshake::server::Response| {
+            client_ip = access.client_ip(peer_ip, request);
+
+            match access.check(request) {
+                Ok(()) => Ok(response),
+                Err(reason) => {
+                    log::warn!("Rejecting websocket connection from {client_ip}: {reason}");
+
+                    let mut error = ws::handshake::server::ErrorResponse::new(Some(
+                        "Connection not allowed".to_string(),
+                    ));
+                    *error.status_mut() = ws::http::StatusCode::FORBIDDEN;
+
+                    Err(error)
+                }
+            }
+        };
+
         // Wrap each endpoint in a WebSocket container.
-        let receiver =
-            ws::accept(instream).map_err(|e| format!("Error accepting new connection: {}", e))?;
+        let receiver = ws::accept_hdr(instream, check_access)
+            .map_err(|e| format!("Error accepting new connection: {}", e))?;
 
         let sender = WebSocket::from_raw_socket(outstream, ws::protocol::Role::Server, None);
 
@@ -280,20 +471,12 @@ impl Session {
         let gateway = conf::config().gateway();
         let busconf = gateway.as_ref().unwrap(); // previously verified
 
-        let osrf_sender = Bus::new(busconf)?;
-        let mut osrf_receiver = Bus::new(busconf)?;
-
-        // The main Session thread has an OpenSRF bus connection that
-        // only ever calls send() / send_to() -- never recv().  The
-        // Outbound thread, which listens for response on the OpenSRF
-        // bus has a bus connection that only ever calls recv().  (Note
-        // the lower-level Bus API never mingles send/receive actions).
-        // In this, we have a split-brain bus connections that won't
-        // step each other's toes.
-        //
-        // It also means the bus receiver must have the same bus address
-        // as the sender so it can act as its receiver.
-        osrf_receiver.set_address(osrf_sender.address());
+        // The main Session thread only ever sends on the OpenSRF bus.
+        // The Outbound thread, which listens for responses on the
+        // OpenSRF bus, only ever receives.  Splitting the bus gives
+        // each its own connection, so they won't step on each other's
+        // toes, while sharing one bus address.
+        let (osrf_sender, osrf_receiver) = Bus::new(busconf)?.split()?;
 
         let shutdown_session = Arc::new(AtomicBool::new(false));
 
@@ -320,6 +503,13 @@ impl Session {
             format: None,
             shutdown,
             shutdown_session,
+            heartbeat_interval,
+            last_sent: Instant::now(),
+            started: Instant::now(),
+            reqs_relayed: 0,
+            ingress: ingress.to_string(),
+            signal_backpressure,
+            throttled: false,
             osrf_sessions: HashMap::new(),
             request_queue: VecDeque::new(),
         };
@@ -394,6 +584,11 @@ impl Session {
                 return;
             }
 
+            if let Err(e) = self.send_heartbeat() {
+                log::error!("{self} Error sending heartbeat: {e}");
+                return;
+            }
+
             let recv_result = self
                 .to_main_rx
                 .recv_timeout(Duration::from_secs(SIG_POLL_INTERVAL));
@@ -442,7 +637,115 @@ impl Session {
                 log::error!("{self} Error processing inbound message: {e}");
                 return;
             }
+
+            if let Err(e) = self.send_backpressure() {
+                log::error!("{self} Error sending backpressure message: {e}");
+                return;
+            }
+        }
+    }
+
+    /// Tell the client when its requests start to be queued because
+    /// we have reached max_parallel, and again once the queue drains,
+    /// so it may throttle its own request bursts.
+    fn send_backpressure(&mut self) -> Result<(), String> {
+        if !self.signal_backpressure {
+            return Ok(());
+        }
+
+        let throttle = if self.throttled {
+            // Stay throttled until all queued requests are relayed
+            // and we have room for more.
+            !self.request_queue.is_empty() || self.reqs_in_flight >= self.max_parallel
+        } else {
+            self.reqs_in_flight >= self.max_parallel
+        };
+
+        if throttle == self.throttled {
+            return Ok(());
         }
+
+        self.throttled = throttle;
+
+        let mut stats = self.stats();
+        stats["throttle"] = throttle.into();
+        stats["max_parallel"] = self.max_parallel.into();
+
+        let mut obj = json::JsonValue::new_object();
+        obj[BACKPRESSURE_KEY] = stats;
+
+        log::debug!("{self} sending backpressure throttle={throttle}");
+
+        self.write_to_client(WebSocketMessage::Text(obj.dump()))
+    }
+
+    /// Send a heartbeat message to the client if heartbeats are enabled
+    /// and we have not sent the client anything within the heartbeat
+    /// interval.
+    ///
+    /// Heartbeats let clients detect a dead translator and keep
+    /// idle connections open through proxies with short timeouts.
+    fn send_heartbeat(&mut self) -> Result<(), String> {
+        let interval = match self.heartbeat_interval {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+
+        if self.last_sent.elapsed() < interval {
+            return Ok(());
+        }
+
+        let mut obj = json::JsonValue::new_object();
+        obj[HEARTBEAT_KEY] = self.stats();
+
+        log::trace!("{self} sending heartbeat");
+
+        self.write_to_client(WebSocketMessage::Text(obj.dump()))
+    }
+
+    /// Translator status and statistics for this session.
+    fn stats(&self) -> json::JsonValue {
+        let status = if self.shutdown.load(Ordering::Relaxed) {
+            "shutting_down"
+        } else {
+            "ok"
+        };
+
+        json::object! {
+            status: status,
+            reqs_relayed: self.reqs_relayed,
+            reqs_in_flight: self.reqs_in_flight,
+            backlog: self.request_queue.len(),
+            uptime: self.started.elapsed().as_secs(),
+            ingress: self.ingress.as_str(),
+        }
+    }
+
+    /// Respond to a client's request for session statistics.
+    ///
+    /// The request's "thread" value, if any, is returned in the
+    /// response so the client can match it to the request.
+    fn send_stats(&mut self, mut request: json::JsonValue) -> Result<(), String> {
+        let mut obj = json::JsonValue::new_object();
+
+        obj[STATUS_KEY] = self.stats();
+        obj["thread"] = request["thread"].take();
+
+        log::debug!("{self} sending session stats");
+
+        self.write_to_client(WebSocketMessage::Text(obj.dump()))
+    }
+
+    /// Write a message to the websocket client, tracking when
+    /// we last did so.
+    fn write_to_client(&mut self, msg: WebSocketMessage) -> Result<(), String> {
+        self.sender
+            .write_message(msg)
+            .map_err(|e| format!("{self} Error writing to websocket client: {e}"))?;
+
+        self.last_sent = Instant::now();
+
+        Ok(())
     }
 
     /// handle_inbound_message tosses inbound messages onto a queue.
@@ -479,6 +782,14 @@ impl Session {
 
                 if tlen >= MAX_MESSAGE_SIZE {
                     log::error!("{self} Dropping huge websocket message size={tlen}");
+                } else if let Some(request) = Session::parse_control_request(&text, STATUS_KEY) {
+                    // Status requests skip the queue.
+                    self.send_stats(request)?;
+                } else if let Some(request) = Session::parse_control_request(&text, DISCONNECT_KEY)
+                {
+                    // As do disconnect requests, since the session they
+                    // target may be what's clogging the queue.
+                    self.disconnect_session(request)?;
                 } else if self.request_queue.len() >= MAX_BACKLOG_SIZE {
                     // Client is getting out of handle.  Let them go.
                     return Err(format!(
@@ -493,10 +804,7 @@ impl Session {
                 Ok(false)
             }
             WebSocketMessage::Ping(text) => {
-                let message = WebSocketMessage::Pong(text);
-                self.sender
-                    .write_message(message)
-                    .map_err(|e| format!("{self} Error sending Pong to client: {e}"))?;
+                self.write_to_client(WebSocketMessage::Pong(text))?;
                 Ok(false)
             }
             WebSocketMessage::Close(_) => {
@@ -510,6 +818,65 @@ impl Session {
         }
     }
 
+    /// Returns the parsed message if the text is a translator control
+    /// request with the provided top-level key.
+    fn parse_control_request(text: &str, key: &str) -> Option<json::JsonValue> {
+        // Avoid parsing every request twice.
+        if !text.contains(key) {
+            return None;
+        }
+
+        match json::parse(text) {
+            Ok(v) if v.has_key(key) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Force a DISCONNECT of a stateful OpenSRF session on behalf of
+    /// the client, e.g. one abandoned by a UI reload, so the service
+    /// worker is released now instead of at its keepalive timeout.
+    ///
+    /// The reply reports whether the session was connected.
+    fn disconnect_session(&mut self, mut request: json::JsonValue) -> Result<(), String> {
+        let thread = request["thread"].take();
+
+        let Some(thread_str) = thread.as_str() else {
+            log::warn!("{self} disconnect request has no 'thread' key");
+            return Ok(());
+        };
+
+        let disconnected = match self.osrf_sessions.remove(thread_str) {
+            Some(worker) => {
+                log::info!("{self} client requested DISCONNECT of session {thread_str}");
+
+                let msg = message::Message::new(
+                    message::MessageType::Disconnect,
+                    1, // thread trace
+                    message::Payload::NoPayload,
+                );
+
+                let tm = message::TransportMessage::with_body(
+                    &worker,
+                    self.osrf_sender.address().as_str(),
+                    thread_str,
+                    msg,
+                );
+
+                self.osrf_sender.send(tm)?;
+
+                true
+            }
+            None => false,
+        };
+
+        let mut obj = json::JsonValue::new_object();
+
+        obj[DISCONNECT_KEY] = json::object! {disconnected: disconnected};
+        obj["thread"] = thread;
+
+        self.write_to_client(WebSocketMessage::Text(obj.dump()))
+    }
+
     /// Wrap a websocket request in an OpenSRF transport message and
     /// put on the OpenSRF bus for delivery.
     fn relay_to_osrf(&mut self, json_text: &str) -> Result<(), String> {
@@ -523,7 +890,11 @@ impl Session {
         if let Some(xid) = log_xid.as_str() {
             Logger::set_log_trace(xid);
         } else {
+            // Suffix the generated trace with our request count so
+            // requests arriving within the same millisecond still get
+            // unique IDs.  The ID is echoed to the client in each reply.
             Logger::mk_log_trace();
+            Logger::set_log_trace(format!("{}-{}", Logger::get_log_trace(), self.reqs_relayed));
         };
 
         let thread = thread
@@ -591,7 +962,7 @@ impl Session {
             // require the IDL.  The IDL is required for HASH-ifying
             // inputs and outputs.
             let mut msg = message::Message::from_json_value(msg_json, false)?;
-            msg.set_ingress(WEBSOCKET_INGRESS);
+            msg.set_ingress(&self.ingress);
 
             match msg.mtype() {
                 message::MessageType::Connect => {
@@ -600,6 +971,7 @@ impl Session {
                 }
                 message::MessageType::Request => {
                     self.reqs_in_flight += 1;
+                    self.reqs_relayed += 1;
 
                     // Inbound requests using a hash format need to be
                     // turned into Fieldmapper objects before they
@@ -729,11 +1101,7 @@ impl Session {
 
         log::trace!("{self} replying with message: {msg_json}");
 
-        let msg = WebSocketMessage::Text(msg_json);
-
-        self.sender
-            .write_message(msg)
-            .map_err(|e| format!("{self} Error sending response to websocket client: {e}"))
+        self.write_to_client(WebSocketMessage::Text(msg_json))
     }
 
     /// Log an API call, honoring the log-protect configs.
@@ -747,11 +1115,13 @@ impl Session {
             request.method(),
             request.params(),
             conf::config().log_protect(),
+            msg.sensitive(),
         );
 
         log::info!(
-            "ACT:[{}] {} {} {}",
+            "ACT:[{}] [{}] {} {} {}",
             self.client_ip,
+            self.ingress,
             service,
             request.method(),
             log_params
@@ -792,7 +1162,11 @@ impl mptc::Request for WebsocketRequest {
 
 struct WebsocketHandler {
     max_parallel: usize,
+    heartbeat_interval: Option<Duration>,
     shutdown: Arc<AtomicBool>,
+    ingress: String,
+    signal_backpressure: bool,
+    access: AccessControl,
 }
 
 impl mptc::RequestHandler for WebsocketHandler {
@@ -814,7 +1188,15 @@ impl mptc::RequestHandler for WebsocketHandler {
 
         let shutdown = self.shutdown.clone();
 
-        if let Err(e) = Session::run(stream, self.max_parallel, shutdown) {
+        if let Err(e) = Session::run(
+            stream,
+            self.max_parallel,
+            self.heartbeat_interval,
+            shutdown,
+            &self.ingress,
+            self.signal_backpressure,
+            &self.access,
+        ) {
             log::error!("Websocket session ended with error: {e}");
         }
 
@@ -831,6 +1213,18 @@ struct WebsocketStream {
     /// are queued for delivery and relayed as soon as possible.
     max_parallel: usize,
 
+    /// Send heartbeat messages to idle clients at this interval.
+    heartbeat_interval: Option<Duration>,
+
+    /// Ingress applied to all messages relayed to OpenSRF.
+    ingress: String,
+
+    /// Tell clients when their requests start and stop being queued.
+    signal_backpressure: bool,
+
+    /// Origin and shared secret checks applied to new connections.
+    access: AccessControl,
+
     /// Set to true of the mptc::Server tells us it's time to shutdown.
     ///
     /// Read by our Sessions
@@ -838,7 +1232,16 @@ struct WebsocketStream {
 }
 
 impl WebsocketStream {
-    fn new(client: Client, address: &str, port: u16, max_parallel: usize) -> Result<Self, String> {
+    fn new(
+        client: Client,
+        address: &str,
+        port: u16,
+        max_parallel: usize,
+        heartbeat_interval: Option<Duration>,
+        ingress: &str,
+        signal_backpressure: bool,
+        access: AccessControl,
+    ) -> Result<Self, String> {
         log::info!("EG Websocket listening at {address}:{port}");
 
         let listener = eg::util::tcp_listener(address, port, SIG_POLL_INTERVAL)
@@ -848,6 +1251,10 @@ impl WebsocketStream {
             listener,
             client,
             max_parallel,
+            heartbeat_interval,
+            ingress: ingress.to_string(),
+            signal_backpressure,
+            access,
             shutdown: Arc::new(AtomicBool::new(false)),
         };
 
@@ -878,6 +1285,10 @@ impl mptc::RequestStream for WebsocketStream {
         let handler = WebsocketHandler {
             shutdown: self.shutdown.clone(),
             max_parallel: self.max_parallel,
+            heartbeat_interval: self.heartbeat_interval,
+            ingress: self.ingress.clone(),
+            signal_backpressure: self.signal_backpressure,
+            access: self.access.clone(),
         };
 
         Box::new(handler)
@@ -902,22 +1313,20 @@ impl mptc::RequestStream for WebsocketStream {
 }
 
 fn main() {
-    let init_ops = eg::init::InitOptions {
-        // As a gateway, we generally won't have access to the host
-        // settings, since that's typically on a private domain.
-        skip_host_settings: true,
-
-        // Skip logging so we can use the logging config in
-        // the gateway() config instead.
-        skip_logging: true,
-        appname: Some(String::from("http-gateway")),
-    };
-
     // Connect to OpenSRF, parse the IDL
     // NOTE: Since we are not fetching host settings, we use
     // the default IDL path unless it's overridden with the
     // EG_IDL_FILE environment variable.
-    let client = eg::init::with_options(&init_ops).expect("Evergreen init");
+    let client = eg::init::Builder::new()
+        .appname("http-gateway")
+        // As a gateway, we generally won't have access to the host
+        // settings, since that's typically on a private domain.
+        .skip_host_settings()
+        // Skip logging so we can use the logging config in
+        // the gateway() config instead.
+        .skip_logging()
+        .build()
+        .expect("Evergreen init");
 
     // Setup logging with the gateway config
     let gateway_conf = conf::config().gateway().expect("Gateway config required");
@@ -939,7 +1348,41 @@ fn main() {
 
     let address = env::var("EG_WEBSOCKETS_ADDRESS").unwrap_or(DEFAULT_LISTEN_ADDRESS.to_string());
 
-    let stream = WebsocketStream::new(client, &address, port, max_parallel).expect("Build stream");
+    // Heartbeats are disabled unless a non-zero interval is provided.
+    let heartbeat_interval = match env::var("EG_WEBSOCKETS_HEARTBEAT_INTERVAL") {
+        Ok(v) => match v.parse::<u64>().expect("Invalid heartbeat interval") {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        },
+        _ => None,
+    };
+
+    let ingress = env::var("EG_WEBSOCKETS_INGRESS").unwrap_or(DEFAULT_INGRESS.to_string());
+
+    // Backpressure messages are only sent to clients when requested.
+    let signal_backpressure = matches!(
+        env::var("EG_WEBSOCKETS_BACKPRESSURE").as_deref(),
+        Ok("true") | Ok("1")
+    );
+
+    // Limit which web pages and clients may open connections.
+    let access = AccessControl::from_env();
+
+    if access.allowed_origins.is_empty() && access.secret.is_none() {
+        log::warn!("Accepting websocket connections from any origin");
+    }
+
+    let stream = WebsocketStream::new(
+        client,
+        &address,
+        port,
+        max_parallel,
+        heartbeat_interval,
+        &ingress,
+        signal_backpressure,
+        access,
+    )
+    .expect("Build stream");
 
     let mut server = mptc::Server::new(Box::new(stream));
 
@@ -0,0 +1,441 @@
+//! EDIFACT syntax: segments, messages, and interchanges.
+//!
+//! Only the default UNOC delimiters are written.  Parsing honors the
+//! delimiters declared in a UNA service string advice segment.
+use crate as eg;
+use eg::date;
+use eg::EgResult;
+use std::fmt;
+
+/// Default version for messages we create, e.g. ORDERS:D:96A:UN
+const DEFAULT_VERSION: &[&str] = &["D", "96A", "UN"];
+
+/// Characters which separate the parts of an EDIFACT interchange.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delimiters {
+    pub component: char,
+    pub element: char,
+    pub decimal: char,
+    pub release: char,
+    pub segment: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Delimiters {
+            component: ':',
+            element: '+',
+            decimal: '.',
+            release: '?',
+            segment: '\'',
+        }
+    }
+}
+
+impl Delimiters {
+    /// Read the delimiters from a UNA segment, e.g. "UNA:+.? '".
+    fn from_una(una: &str) -> EgResult<Self> {
+        let chars: Vec<char> = una.chars().skip(3).collect();
+
+        if chars.len() < 6 {
+            return Err(format!("Invalid UNA segment: {una}").into());
+        }
+
+        Ok(Delimiters {
+            component: chars[0],
+            element: chars[1],
+            decimal: chars[2],
+            release: chars[3],
+            // chars[4] is reserved.
+            segment: chars[5],
+        })
+    }
+
+    fn is_special(&self, c: char) -> bool {
+        c == self.component || c == self.element || c == self.release || c == self.segment
+    }
+
+    /// Prefix delimiter characters within a value with the release
+    /// character.
+    fn escape(&self, value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+
+        for c in value.chars() {
+            if self.is_special(c) {
+                escaped.push(self.release);
+            }
+            escaped.push(c);
+        }
+
+        escaped
+    }
+
+    /// Split on a delimiter, ignoring released delimiters.  Release
+    /// characters are retained so the parts may be split further.
+    fn split(&self, text: &str, delimiter: char) -> Vec<String> {
+        let mut parts = vec![String::new()];
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            if c == self.release {
+                let part = parts.last_mut().unwrap();
+                part.push(c);
+                if let Some(next) = chars.next() {
+                    part.push(next);
+                }
+            } else if c == delimiter {
+                parts.push(String::new());
+            } else {
+                parts.last_mut().unwrap().push(c);
+            }
+        }
+
+        parts
+    }
+
+    /// Remove release characters from a value.
+    fn unescape(&self, value: &str) -> String {
+        let mut unescaped = String::with_capacity(value.len());
+        let mut chars = value.chars();
+
+        while let Some(c) = chars.next() {
+            if c == self.release {
+                if let Some(next) = chars.next() {
+                    unescaped.push(next);
+                }
+            } else {
+                unescaped.push(c);
+            }
+        }
+
+        unescaped
+    }
+}
+
+/// One EDIFACT segment, e.g. QTY+21:5
+///
+/// Each data element is a list of components.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    tag: String,
+    elements: Vec<Vec<String>>,
+}
+
+impl Segment {
+    pub fn new(tag: &str) -> Self {
+        Segment {
+            tag: tag.to_string(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Append a data element composed of the provided components.
+    ///
+    /// ```
+    /// use evergreen::common::acq::edi::Segment;
+    ///
+    /// let seg = Segment::new("QTY").element(&["21", "5"]);
+    /// assert_eq!(seg.to_string(), "QTY+21:5'");
+    /// assert_eq!(seg.value(0, 1), Some("5"));
+    /// ```
+    pub fn element(mut self, components: &[&str]) -> Self {
+        self.elements
+            .push(components.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn elements(&self) -> &Vec<Vec<String>> {
+        &self.elements
+    }
+
+    /// Value of a component within a data element, if present and
+    /// non-empty.
+    pub fn value(&self, element: usize, component: usize) -> Option<&str> {
+        self.elements
+            .get(element)
+            .and_then(|e| e.get(component))
+            .map(|c| c.as_str())
+            .filter(|c| !c.is_empty())
+    }
+
+    fn to_edi(&self, delims: &Delimiters) -> String {
+        let mut edi = self.tag.clone();
+
+        for element in self.elements.iter() {
+            edi.push(delims.element);
+
+            let components: Vec<String> = element.iter().map(|c| delims.escape(c)).collect();
+            edi += &components.join(&delims.component.to_string());
+        }
+
+        edi.push(delims.segment);
+        edi
+    }
+
+    fn from_edi(text: &str, delims: &Delimiters) -> Self {
+        let mut parts = delims.split(text, delims.element).into_iter();
+
+        let tag = parts.next().unwrap_or_default();
+
+        let elements = parts
+            .map(|e| {
+                delims
+                    .split(&e, delims.component)
+                    .iter()
+                    .map(|c| delims.unescape(c))
+                    .collect()
+            })
+            .collect();
+
+        Segment { tag, elements }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_edi(&Delimiters::default()))
+    }
+}
+
+/// One message within an interchange, minus its UNH/UNT envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// e.g. ORDERS, INVOIC
+    message_type: String,
+    /// Message reference number, unique within the interchange.
+    reference: String,
+    /// e.g. ["D", "96A", "UN"]
+    version: Vec<String>,
+    segments: Vec<Segment>,
+}
+
+impl Message {
+    pub fn new(message_type: &str, reference: &str) -> Self {
+        Message {
+            message_type: message_type.to_string(),
+            reference: reference.to_string(),
+            version: DEFAULT_VERSION.iter().map(|v| v.to_string()).collect(),
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn message_type(&self) -> &str {
+        &self.message_type
+    }
+
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    pub fn segments(&self) -> &Vec<Segment> {
+        &self.segments
+    }
+
+    pub fn add_segment(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// First segment with the provided tag.
+    pub fn segment(&self, tag: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.tag == tag)
+    }
+}
+
+/// An EDIFACT interchange (UNB .. UNZ) containing one or more messages.
+///
+/// ```
+/// use evergreen::common::acq::edi::{Interchange, Message, Segment};
+///
+/// let mut msg = Message::new("ORDERS", "1");
+/// msg.add_segment(Segment::new("BGM").element(&["220"]).element(&["PO?42"]));
+///
+/// let mut ic = Interchange::new("1234567", "31B", "7654321", "31B", "100");
+/// ic.add_message(msg);
+///
+/// let edi = ic.to_edi();
+/// assert!(edi.contains("BGM+220+PO??42'"));
+///
+/// let parsed = Interchange::parse(&edi).unwrap();
+/// assert_eq!(parsed.sender(), "1234567");
+/// assert_eq!(parsed.messages()[0].segment("BGM").unwrap().value(1, 0), Some("PO?42"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interchange {
+    sender: String,
+    sender_qualifier: String,
+    recipient: String,
+    recipient_qualifier: String,
+    control_ref: String,
+    messages: Vec<Message>,
+}
+
+impl Interchange {
+    /// Qualifiers identify the code list of the sender and recipient
+    /// IDs, e.g. "31B" for SAN, "14" for EAN.
+    pub fn new(
+        sender: &str,
+        sender_qualifier: &str,
+        recipient: &str,
+        recipient_qualifier: &str,
+        control_ref: &str,
+    ) -> Self {
+        Interchange {
+            sender: sender.to_string(),
+            sender_qualifier: sender_qualifier.to_string(),
+            recipient: recipient.to_string(),
+            recipient_qualifier: recipient_qualifier.to_string(),
+            control_ref: control_ref.to_string(),
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn sender(&self) -> &str {
+        &self.sender
+    }
+
+    pub fn recipient(&self) -> &str {
+        &self.recipient
+    }
+
+    pub fn control_ref(&self) -> &str {
+        &self.control_ref
+    }
+
+    pub fn messages(&self) -> &Vec<Message> {
+        &self.messages
+    }
+
+    pub fn add_message(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// EDIFACT text for the full interchange, one segment per line.
+    pub fn to_edi(&self) -> String {
+        let delims = Delimiters::default();
+        let now = date::now_local();
+
+        let mut segments = vec![Segment::new("UNB")
+            .element(&["UNOC", "3"])
+            .element(&[&self.sender, &self.sender_qualifier])
+            .element(&[&self.recipient, &self.recipient_qualifier])
+            .element(&[
+                &now.format("%y%m%d").to_string(),
+                &now.format("%H%M").to_string(),
+            ])
+            .element(&[&self.control_ref])];
+
+        for msg in self.messages.iter() {
+            let mut version = vec![msg.message_type.as_str()];
+            version.extend(msg.version.iter().map(|v| v.as_str()));
+
+            segments.push(
+                Segment::new("UNH")
+                    .element(&[&msg.reference])
+                    .element(&version),
+            );
+
+            segments.extend(msg.segments.iter().cloned());
+
+            // UNT counts the UNH and UNT segments too.
+            segments.push(
+                Segment::new("UNT")
+                    .element(&[&(msg.segments.len() + 2).to_string()])
+                    .element(&[&msg.reference]),
+            );
+        }
+
+        segments.push(
+            Segment::new("UNZ")
+                .element(&[&self.messages.len().to_string()])
+                .element(&[&self.control_ref]),
+        );
+
+        let mut edi = format!(
+            "UNA{}{}{}{} {}\n",
+            delims.component, delims.element, delims.decimal, delims.release, delims.segment
+        );
+
+        for seg in segments.iter() {
+            edi += &seg.to_edi(&delims);
+            edi.push('\n');
+        }
+
+        edi
+    }
+
+    /// Parse EDIFACT text into an interchange.
+    pub fn parse(text: &str) -> EgResult<Interchange> {
+        let mut text = text.trim_start();
+
+        let delims = if text.starts_with("UNA") {
+            let una: String = text.chars().take(9).collect();
+            let delims = Delimiters::from_una(&una)?;
+            text = &text[una.len()..];
+            delims
+        } else {
+            Delimiters::default()
+        };
+
+        let mut interchange: Option<Interchange> = None;
+        let mut message: Option<Message> = None;
+
+        for seg_text in delims.split(text, delims.segment) {
+            // Segments are often separated by newlines for legibility.
+            let seg_text = seg_text.trim();
+            if seg_text.is_empty() {
+                continue;
+            }
+
+            let seg = Segment::from_edi(seg_text, &delims);
+
+            match seg.tag() {
+                "UNB" => {
+                    interchange = Some(Interchange::new(
+                        seg.value(1, 0).unwrap_or(""),
+                        seg.value(1, 1).unwrap_or(""),
+                        seg.value(2, 0).unwrap_or(""),
+                        seg.value(2, 1).unwrap_or(""),
+                        seg.value(4, 0).unwrap_or(""),
+                    ));
+                }
+                "UNH" => {
+                    let header = seg.elements().get(1).cloned().unwrap_or_default();
+
+                    message = Some(Message {
+                        message_type: header.first().cloned().unwrap_or_default(),
+                        reference: seg.value(0, 0).unwrap_or("").to_string(),
+                        version: header.into_iter().skip(1).collect(),
+                        segments: Vec::new(),
+                    });
+                }
+                "UNT" => {
+                    let msg = message
+                        .take()
+                        .ok_or("EDI message trailer without a header")?;
+
+                    interchange
+                        .as_mut()
+                        .ok_or("EDI message outside of an interchange")?
+                        .add_message(msg);
+                }
+                "UNZ" => break,
+                _ => {
+                    message
+                        .as_mut()
+                        .ok_or_else(|| format!("EDI segment outside of a message: {seg_text}"))?
+                        .add_segment(seg);
+                }
+            }
+        }
+
+        if message.is_some() {
+            return Err("EDI message has no trailer".into());
+        }
+
+        interchange.ok_or_else(|| "No EDI interchange found".into())
+    }
+}
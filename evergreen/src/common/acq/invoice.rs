@@ -0,0 +1,167 @@
+//! EDI INVOIC messages.
+use crate as eg;
+use eg::common::acq::edi::{Interchange, Message, Segment};
+use eg::EgResult;
+use eg::EgValue;
+
+/// One invoiced lineitem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoiceLine {
+    pub line_number: Option<String>,
+    /// Item identifier, typically an ISBN.
+    pub ident: Option<String>,
+    pub quantity: Option<i64>,
+    /// Line total.
+    pub amount: Option<f64>,
+    /// From the "po/lineitem" reference sent with the order.
+    pub po_id: Option<i64>,
+    pub lineitem_id: Option<i64>,
+}
+
+impl InvoiceLine {
+    fn new(lin: &Segment) -> Self {
+        InvoiceLine {
+            line_number: lin.value(0, 0).map(|v| v.to_string()),
+            ident: lin.value(2, 0).map(|v| v.to_string()),
+            quantity: None,
+            amount: None,
+            po_id: None,
+            lineitem_id: None,
+        }
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        eg::hash! {
+            "line_number": self.line_number.as_deref(),
+            "ident": self.ident.as_deref(),
+            "quantity": self.quantity,
+            "amount": self.amount,
+            "po": self.po_id,
+            "lineitem": self.lineitem_id,
+        }
+    }
+}
+
+/// Invoice data read from an INVOIC message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invoice {
+    /// Vendor's invoice number.
+    pub invoice_ident: String,
+    /// YYYYMMDD
+    pub invoice_date: Option<String>,
+    pub currency: Option<String>,
+    /// Total amount due.
+    pub total: Option<f64>,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl Invoice {
+    /// Read an invoice from an INVOIC message.
+    ///
+    /// ```
+    /// use evergreen::common::acq::edi::Interchange;
+    /// use evergreen::common::acq::invoice::Invoice;
+    ///
+    /// let edi = "UNA:+.? 'UNB+UNOC:3+7654321:31B+1234567:31B+240102:1200+55'\
+    ///     UNH+1+INVOIC:D:96A:UN'BGM+380+INV-9+9'DTM+137:20240102:102'\
+    ///     LIN+1++9780306406157:EN'QTY+47:2'MOA+203:25.50'RFF+LI:42/1001'\
+    ///     UNS+S'MOA+9:25.50'UNT+10+1'UNZ+1+55'";
+    ///
+    /// let ic = Interchange::parse(edi).unwrap();
+    /// let invoice = Invoice::from_message(&ic.messages()[0]).unwrap();
+    ///
+    /// assert_eq!(invoice.invoice_ident, "INV-9");
+    /// assert_eq!(invoice.total, Some(25.50));
+    /// assert_eq!(invoice.lines[0].quantity, Some(2));
+    /// assert_eq!(invoice.lines[0].lineitem_id, Some(1001));
+    /// ```
+    pub fn from_message(msg: &Message) -> EgResult<Invoice> {
+        if msg.message_type() != "INVOIC" {
+            return Err(format!("Not an INVOIC message: {}", msg.message_type()).into());
+        }
+
+        let invoice_ident = msg
+            .segment("BGM")
+            .and_then(|s| s.value(1, 0))
+            .ok_or("INVOIC message has no invoice number")?;
+
+        let mut invoice = Invoice {
+            invoice_ident: invoice_ident.to_string(),
+            invoice_date: None,
+            currency: None,
+            total: None,
+            lines: Vec::new(),
+        };
+
+        // Segments following LIN describe that line until the UNS
+        // section separator.
+        let mut line: Option<InvoiceLine> = None;
+        let mut summary = false;
+
+        for seg in msg.segments() {
+            match (seg.tag(), seg.value(0, 0)) {
+                ("LIN", _) => {
+                    invoice.lines.extend(line.take());
+                    line = Some(InvoiceLine::new(seg));
+                }
+                ("UNS", _) => {
+                    invoice.lines.extend(line.take());
+                    summary = true;
+                }
+                ("DTM", Some("137")) if line.is_none() && !summary => {
+                    invoice.invoice_date = seg.value(0, 1).map(|v| v.to_string());
+                }
+                ("CUX", _) if line.is_none() => {
+                    invoice.currency = seg.value(0, 1).map(|v| v.to_string());
+                }
+                ("QTY", Some("47")) => {
+                    if let Some(l) = line.as_mut() {
+                        l.quantity = seg.value(0, 1).and_then(|v| v.parse().ok());
+                    }
+                }
+                ("MOA", Some("203")) => {
+                    if let Some(l) = line.as_mut() {
+                        l.amount = seg.value(0, 1).and_then(|v| v.parse().ok());
+                    }
+                }
+                ("MOA", Some("9")) if summary => {
+                    invoice.total = seg.value(0, 1).and_then(|v| v.parse().ok());
+                }
+                ("RFF", Some("LI")) => {
+                    if let (Some(l), Some(value)) = (line.as_mut(), seg.value(0, 1)) {
+                        let mut parts = value.split('/').map(|p| p.trim().parse::<i64>().ok());
+                        l.po_id = parts.next().flatten();
+                        l.lineitem_id = parts.next().flatten();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        invoice.lines.extend(line.take());
+
+        Ok(invoice)
+    }
+
+    /// Read all invoices within an interchange.
+    pub fn from_interchange(interchange: &Interchange) -> EgResult<Vec<Invoice>> {
+        interchange
+            .messages()
+            .iter()
+            .filter(|m| m.message_type() == "INVOIC")
+            .map(Invoice::from_message)
+            .collect()
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        let lines: Vec<EgValue> = self.lines.iter().map(|l| l.to_eg_value()).collect();
+
+        eg::hash! {
+            "invoice_ident": self.invoice_ident.as_str(),
+            "invoice_date": self.invoice_date.as_deref(),
+            "currency": self.currency.as_deref(),
+            "total": self.total,
+            "lines": lines,
+        }
+    }
+}
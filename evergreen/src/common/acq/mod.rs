@@ -0,0 +1,14 @@
+//! Acquisitions: EDI order and invoice messages.
+//!
+//! This covers translating between EDIFACT messages and Evergreen
+//! purchase order data.  Message transport is handled by
+//! eg-edi-transfer.
+pub mod edi;
+pub mod invoice;
+pub mod orders;
+
+/// acq.edi_message.status values.
+pub const EDI_STATUS_NEW: &str = "new";
+pub const EDI_STATUS_COMPLETE: &str = "complete";
+pub const EDI_STATUS_TRANS_ERROR: &str = "trans_error";
+pub const EDI_STATUS_PROC_ERROR: &str = "proc_error";
@@ -0,0 +1,186 @@
+//! EDI ORDERS messages for purchase orders.
+use crate as eg;
+use eg::common::acq::edi::{Interchange, Message, Segment};
+use eg::common::acq::EDI_STATUS_NEW;
+use eg::date;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+
+/// Qualifier for Standard Address Number party IDs.
+const SAN_QUALIFIER: &str = "31B";
+
+/// Lineitem attribute value by name, e.g. "isbn".
+fn lineitem_attr<'a>(lineitem: &'a EgValue, name: &str) -> Option<&'a str> {
+    lineitem["attributes"]
+        .members()
+        .find(|a| a["attr_name"].as_str() == Some(name))
+        .and_then(|a| a["attr_value"].as_str())
+}
+
+/// Purchase order fleshed with everything needed to build its order.
+fn fleshed_po(editor: &mut Editor, po_id: i64) -> EgResult<EgValue> {
+    let flesh = eg::hash! {
+        "flesh": 2,
+        "flesh_fields": {
+            "acqpo": ["provider", "lineitems"],
+            "acqpro": ["edi_default"],
+            "jub": ["attributes", "lineitem_details"],
+        }
+    };
+
+    editor
+        .retrieve_with_ops("acqpo", po_id, flesh)?
+        .ok_or_else(|| editor.die_event())
+}
+
+/// Build an ORDERS interchange for a purchase order.
+///
+/// The provider must have a default EDI account.
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::common::acq::orders;
+///
+/// let client = eg::init().unwrap();
+/// let mut editor = eg::Editor::new(&client);
+///
+/// let orders = orders::po_to_orders(&mut editor, 1).unwrap();
+/// println!("{}", orders.to_edi());
+/// ```
+pub fn po_to_orders(editor: &mut Editor, po_id: i64) -> EgResult<Interchange> {
+    let po = fleshed_po(editor, po_id)?;
+    build_orders(&po)
+}
+
+fn build_orders(po: &EgValue) -> EgResult<Interchange> {
+    let po_id = po.id()?;
+    let provider = &po["provider"];
+    let account = &provider["edi_default"];
+
+    if !account.is_blessed() {
+        return Err(format!("Provider {} has no default EDI account", provider["code"]).into());
+    }
+
+    let buyer = account["san"]
+        .as_str()
+        .or_else(|| account["vendacct"].as_str())
+        .ok_or_else(|| format!("EDI account {} has no SAN", account["id"]))?;
+
+    let vendor = provider["san"]
+        .as_str()
+        .ok_or_else(|| format!("Provider {} has no SAN", provider["code"]))?;
+
+    let po_ref = po_id.to_string();
+
+    let mut msg = Message::new("ORDERS", "1");
+
+    msg.add_segment(
+        Segment::new("BGM")
+            .element(&["220"])
+            .element(&[&po_ref])
+            .element(&["9"]),
+    );
+
+    msg.add_segment(Segment::new("DTM").element(&[
+        "137",
+        &date::now_local().format("%Y%m%d").to_string(),
+        "102",
+    ]));
+
+    msg.add_segment(
+        Segment::new("NAD")
+            .element(&["BY"])
+            .element(&[buyer, "", SAN_QUALIFIER]),
+    );
+
+    msg.add_segment(
+        Segment::new("NAD")
+            .element(&["SU"])
+            .element(&[vendor, "", SAN_QUALIFIER]),
+    );
+
+    if let Some(currency) = provider["currency_type"].as_str() {
+        msg.add_segment(Segment::new("CUX").element(&["2", currency, "9"]));
+    }
+
+    let mut line_count = 0;
+
+    for lineitem in po["lineitems"].members() {
+        let quantity = lineitem["lineitem_details"]
+            .members()
+            .filter(|d| d["cancel_reason"].is_null())
+            .count();
+
+        if quantity == 0 {
+            continue;
+        }
+
+        line_count += 1;
+        let line_num = line_count.to_string();
+
+        let mut lin = Segment::new("LIN").element(&[&line_num]);
+        if let Some(isbn) = lineitem_attr(lineitem, "isbn") {
+            lin = lin.element(&[]).element(&[isbn, "EN"]);
+        }
+        msg.add_segment(lin);
+
+        for (attr, code) in [("title", "BTI"), ("author", "BAU"), ("publisher", "BPU")] {
+            if let Some(value) = lineitem_attr(lineitem, attr) {
+                msg.add_segment(
+                    Segment::new("IMD")
+                        .element(&["F"])
+                        .element(&[code])
+                        .element(&["", "", "", value]),
+                );
+            }
+        }
+
+        msg.add_segment(Segment::new("QTY").element(&["21", &quantity.to_string()]));
+
+        if let Some(price) = lineitem["estimated_unit_price"].as_f64() {
+            msg.add_segment(Segment::new("PRI").element(&["AAB", &format!("{price:.2}")]));
+        }
+
+        // Vendors echo this back in responses and invoices.
+        msg.add_segment(
+            Segment::new("RFF").element(&["LI", &format!("{po_id}/{}", lineitem.id()?)]),
+        );
+    }
+
+    if line_count == 0 {
+        return Err(format!("Purchase order {po_id} has nothing to order").into());
+    }
+
+    msg.add_segment(Segment::new("UNS").element(&["S"]));
+    msg.add_segment(Segment::new("CNT").element(&["2", &line_count.to_string()]));
+
+    let mut interchange = Interchange::new(buyer, SAN_QUALIFIER, vendor, SAN_QUALIFIER, &po_ref);
+    interchange.add_message(msg);
+
+    Ok(interchange)
+}
+
+/// Create a new acq.edi_message containing the ORDERS message for a
+/// purchase order, ready for delivery by eg-edi-transfer.
+///
+/// Uses an externally managed Editor transaction.
+pub fn create_orders_message(editor: &mut Editor, po_id: i64) -> EgResult<EgValue> {
+    let po = fleshed_po(editor, po_id)?;
+    let orders = build_orders(&po)?;
+
+    let message = EgValue::create(
+        "acqedim",
+        eg::hash! {
+            "account": po["provider"]["edi_default"].id()?,
+            "purchase_order": po_id,
+            "message_type": "ORDERS",
+            "status": EDI_STATUS_NEW,
+            "edi": orders.to_edi(),
+        },
+    )?;
+
+    log::info!("ACT:created EDI ORDERS message for purchase order {po_id}");
+
+    editor.create(message)
+}
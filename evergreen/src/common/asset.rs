@@ -0,0 +1,117 @@
+//! Create and modify copies and check copy floating.
+use crate as eg;
+use eg::common::org;
+use eg::common::settings::Settings;
+use eg::constants as C;
+use eg::editor::Editor;
+use eg::result::EgResult;
+use eg::EgValue;
+
+/// Values used to create a new pre-cataloged copy.
+#[derive(Debug, Default)]
+pub struct PrecatCopy<'a> {
+    pub barcode: &'a str,
+    pub circ_lib: i64,
+    pub dummy_title: &'a str,
+    pub dummy_author: &'a str,
+    pub dummy_isbn: &'a str,
+    pub circ_modifier: &'a str,
+}
+
+/// Create a copy, stamping the creator/editor as the requestor.
+///
+/// Returns the newly created copy.
+pub fn create_copy(editor: &mut Editor, mut copy: EgValue) -> EgResult<EgValue> {
+    if copy["call_number"].is_null() {
+        return Err("Copy requires a call number".into());
+    }
+
+    let reqr_id = editor.requestor_id()?;
+
+    copy["creator"] = EgValue::from(reqr_id);
+    copy["editor"] = EgValue::from(reqr_id);
+
+    let copy = EgValue::create("acp", copy)?;
+
+    editor.create(copy)
+}
+
+/// Create a pre-cataloged copy attached to the pre-cat call number.
+///
+/// Honors the circ.pre_cat_copy_circ_lib setting, which overrides
+/// the provided circ lib.
+pub fn create_precat_copy(editor: &mut Editor, values: &PrecatCopy) -> EgResult<EgValue> {
+    let mut copy = eg::hash! {
+        "circ_lib": values.circ_lib,
+        "barcode": values.barcode,
+        "dummy_title": values.dummy_title,
+        "dummy_author": values.dummy_author,
+        "dummy_isbn": values.dummy_isbn,
+        "circ_modifier": values.circ_modifier,
+        "call_number": C::PRECAT_CALL_NUMBER,
+        "loan_duration": C::PRECAT_COPY_LOAN_DURATION,
+        "fine_level": C::PRECAT_COPY_FINE_LEVEL,
+    };
+
+    let pclib = Settings::new(editor)
+        .get_value_at_org("circ.pre_cat_copy_circ_lib", values.circ_lib)?
+        .clone();
+
+    if let Some(sn) = pclib.as_str() {
+        let o = org::by_shortname(editor, sn)?;
+        copy["circ_lib"] = o["id"].clone();
+    }
+
+    log::info!("Creating new pre-cat copy {}", values.barcode);
+
+    create_copy(editor, copy)
+}
+
+/// Apply changes to a copy and save it.
+///
+/// The copy may be fleshed; it is de-fleshed before saving.
+/// Returns the updated copy, unfleshed.
+///
+/// * `changes` - Hash of copy field names and their new values.
+pub fn update_copy(
+    editor: &mut Editor,
+    mut copy: EgValue,
+    mut changes: EgValue,
+) -> EgResult<EgValue> {
+    copy["editor"] = editor.requestor_id()?.into();
+    copy["edit_date"] = "now".into();
+
+    for (k, v) in changes.entries_mut() {
+        copy[k] = v.take();
+    }
+
+    copy.deflesh()?;
+
+    editor.update(copy.clone())?;
+
+    Ok(copy)
+}
+
+/// True if a copy in the given floating group, whose circ lib is
+/// `copy_circ_lib`, may float to `to_lib`.
+pub fn can_float(
+    editor: &mut Editor,
+    float_group_id: i64,
+    copy_circ_lib: i64,
+    to_lib: i64,
+) -> EgResult<bool> {
+    let query = eg::hash! {
+        from: [
+            "evergreen.can_float",
+            float_group_id,
+            copy_circ_lib,
+            to_lib
+        ]
+    };
+
+    if let Some(resp) = editor.json_query(query)?.first() {
+        Ok(resp["evergreen.can_float"].boolish())
+    } else {
+        Ok(false)
+    }
+}
@@ -14,6 +14,15 @@ const LOGIN_TIMEOUT: i32 = 30;
 // Default time for extending a persistent session: ten minutes
 const DEFAULT_RESET_INTERVAL: i32 = 10 * 60;
 
+/// Cache key prefix for failed login counts and lockouts.
+const LOGIN_THROTTLE_PRFX: &str = "login_throttle";
+
+/// Failed logins are counted within this many seconds unless configured.
+pub const DEFAULT_LOGIN_FAILURE_WINDOW: u32 = 5 * 60;
+
+/// Lockouts last this many seconds unless configured.
+pub const DEFAULT_LOGIN_LOCKOUT_TIME: u32 = 15 * 60;
+
 fn cache_key(token: &str) -> String {
     format!("{}{}", C::OILS_AUTH_CACHE_PRFX, token)
 }
@@ -440,3 +449,143 @@ pub fn get_auth_duration(
         Ok(0)
     }
 }
+
+/// Temporarily locks out login identifiers (e.g. a patron barcode or
+/// the device a login arrives from) after repeated failed logins.
+///
+/// Failure counts are stored in the global cache so they are shared
+/// by every service and worker that checks passwords.  Lockouts and
+/// failures which trigger them are written to the activity log.
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    /// Failed logins allowed within the window before locking out.
+    max_failures: u32,
+    /// Seconds within which failures are counted.
+    window: u32,
+    /// Seconds a lockout lasts.
+    lockout: u32,
+}
+
+impl LoginThrottle {
+    pub fn new(max_failures: u32, window: u32, lockout: u32) -> Self {
+        LoginThrottle {
+            max_failures,
+            window: window.max(1),
+            lockout: lockout.max(1),
+        }
+    }
+
+    /// Build a throttle from a settings hash with "max_failures",
+    /// "failure_window", and "lockout_time" values.  Windows and
+    /// lockout times may be seconds or interval strings.
+    ///
+    /// Returns None if "max_failures" is unset or zero.
+    ///
+    /// ```
+    /// use evergreen as eg;
+    /// use eg::common::auth::LoginThrottle;
+    ///
+    /// let conf = eg::hash! {"max_failures": 5, "lockout_time": "1 hour"};
+    /// let throttle = LoginThrottle::from_value(&conf).unwrap();
+    /// assert_eq!(throttle.lockout(), 3600);
+    ///
+    /// assert!(LoginThrottle::from_value(&eg::hash! {"max_failures": 0}).is_none());
+    /// ```
+    pub fn from_value(value: &EgValue) -> Option<LoginThrottle> {
+        let max_failures = value["max_failures"].as_usize().filter(|n| *n > 0)?;
+
+        let window =
+            interval_secs(&value["failure_window"]).unwrap_or(DEFAULT_LOGIN_FAILURE_WINDOW);
+        let lockout = interval_secs(&value["lockout_time"]).unwrap_or(DEFAULT_LOGIN_LOCKOUT_TIME);
+
+        Some(LoginThrottle::new(max_failures as u32, window, lockout))
+    }
+
+    pub fn max_failures(&self) -> u32 {
+        self.max_failures
+    }
+
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    pub fn lockout(&self) -> u32 {
+        self.lockout
+    }
+
+    /// Returns the number of seconds left in the lockout for the
+    /// provided identifier, or None if it is not locked out.
+    pub fn locked_out(&self, ident: &str) -> EgResult<Option<u32>> {
+        let Some(until) = Cache::get_global(&throttle_key("lock", ident))? else {
+            return Ok(None);
+        };
+
+        let now = date::epoch_secs() as i64;
+
+        match until.as_i64() {
+            Some(until) if until > now => Ok(Some((until - now) as u32)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Record a failed login for the identifier.
+    ///
+    /// Failures are counted with an atomic cache increment, so
+    /// concurrent failures are never lost.
+    ///
+    /// Returns true if the identifier is now locked out.
+    pub fn record_failure(&self, ident: &str) -> EgResult<bool> {
+        let count_key = throttle_key("count", ident);
+
+        // The counter expires at the end of the window, which starts
+        // the count over.
+        let failures = Cache::incr_global_for(&count_key, self.window)?;
+
+        if failures < self.max_failures as u64 {
+            log::info!(
+                "ACT:login failure {ident} ({failures} of {})",
+                self.max_failures
+            );
+            return Ok(false);
+        }
+
+        let until = date::epoch_secs() as i64 + self.lockout as i64;
+
+        // Only one of any concurrent failures starts the lockout.
+        if Cache::add_global_for(&throttle_key("lock", ident), until.into(), self.lockout)? {
+            log::warn!(
+                "ACT:login lockout {ident} for {} seconds after {failures} failed logins",
+                self.lockout
+            );
+        }
+
+        // Start counting afresh once the lockout ends.
+        Cache::del_global(&count_key)?;
+
+        Ok(true)
+    }
+
+    /// Clear failures for an identifier after a successful login.
+    ///
+    /// Active lockouts are left in place.
+    pub fn clear(&self, ident: &str) -> EgResult<()> {
+        Cache::del_global(&throttle_key("count", ident))
+    }
+}
+
+/// Identifiers are hashed so cache keys do not contain barcodes.
+fn throttle_key(kind: &str, ident: &str) -> String {
+    format!("{LOGIN_THROTTLE_PRFX}:{kind}:{:x}", md5::compute(ident))
+}
+
+/// Seconds from a number or interval string.
+fn interval_secs(value: &EgValue) -> Option<u32> {
+    if let Some(n) = value.as_usize() {
+        Some(n as u32)
+    } else {
+        value
+            .as_str()
+            .and_then(|s| date::interval_to_seconds(s).ok())
+            .map(|n| n as u32)
+    }
+}
@@ -0,0 +1,234 @@
+//! Render Code39 and Codabar barcode images as SVG or PNG.
+use crate::EgResult;
+
+/// Blank modules on either side of the barcode.
+const QUIET_ZONE: usize = 10;
+/// Wide elements are this many times the width of narrow elements.
+const WIDE_RATIO: usize = 3;
+
+pub const DEFAULT_MODULE_WIDTH: usize = 2;
+pub const DEFAULT_HEIGHT: usize = 60;
+/// Largest module width accepted from API callers.
+pub const MAX_MODULE_WIDTH: usize = 10;
+/// Largest image height in pixels accepted from API callers.
+pub const MAX_HEIGHT: usize = 1000;
+
+const CODE39_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%*";
+
+/// Code39 patterns, 9 elements (bar first), where each set bit is a
+/// wide element.
+const CODE39_PATTERNS: &[u16] = &[
+    0x034, 0x121, 0x061, 0x160, 0x031, 0x130, 0x070, 0x025, 0x124, 0x064, // 0-9
+    0x109, 0x049, 0x148, 0x019, 0x118, 0x058, 0x00D, 0x10C, 0x04C, 0x01C, // A-J
+    0x103, 0x043, 0x142, 0x013, 0x112, 0x052, 0x007, 0x106, 0x046, 0x016, // K-T
+    0x181, 0x0C1, 0x1C0, 0x091, 0x190, 0x0D0, // U-Z
+    0x085, 0x184, 0x0C4, 0x0A8, 0x0A2, 0x08A, 0x02A, 0x094, // -. $/+%*
+];
+
+const CODABAR_ALPHABET: &str = "0123456789-$:/.+ABCD";
+
+/// Codabar patterns, 7 elements (bar first), where each set bit is a
+/// wide element.
+const CODABAR_PATTERNS: &[u16] = &[
+    0x003, 0x006, 0x009, 0x060, 0x012, 0x042, 0x021, 0x024, 0x030, 0x048, // 0-9
+    0x00C, 0x018, 0x045, 0x051, 0x054, 0x015, // -$:/.+
+    0x01A, 0x029, 0x00B, 0x00E, // ABCD
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symbology {
+    Code39,
+    Codabar,
+}
+
+impl TryFrom<&str> for Symbology {
+    type Error = crate::EgError;
+    fn try_from(s: &str) -> EgResult<Self> {
+        match s.to_lowercase().as_str() {
+            "code39" => Ok(Self::Code39),
+            "codabar" => Ok(Self::Codabar),
+            _ => Err(format!("Unsupported barcode symbology: {s}").into()),
+        }
+    }
+}
+
+/// Append the modules for one character, plus a narrow gap.
+fn push_pattern(modules: &mut Vec<bool>, pattern: u16, elements: usize) {
+    for idx in 0..elements {
+        let wide = pattern & (1 << (elements - 1 - idx)) != 0;
+        let width = if wide { WIDE_RATIO } else { 1 };
+        let bar = idx % 2 == 0;
+        modules.extend(std::iter::repeat_n(bar, width));
+    }
+    modules.push(false);
+}
+
+/// Encode a barcode as a list of modules, true for bars, including
+/// quiet zones.
+///
+/// Code39 values are wrapped in '*' start/stop characters.  Codabar
+/// values without their own A-D start/stop characters are wrapped in 'A'.
+///
+/// ```
+/// use evergreen::common::barcode::{self, Symbology};
+///
+/// let modules = barcode::encode("12345", Symbology::Codabar).unwrap();
+/// assert!(modules.iter().any(|m| *m));
+///
+/// assert!(barcode::encode("abc!", Symbology::Code39).is_err());
+/// ```
+pub fn encode(value: &str, symbology: Symbology) -> EgResult<Vec<bool>> {
+    let (alphabet, patterns, elements, value) = match symbology {
+        Symbology::Code39 => {
+            if value.contains('*') {
+                return Err("Code39 values may not contain '*'".into());
+            }
+            (
+                CODE39_ALPHABET,
+                CODE39_PATTERNS,
+                9,
+                format!("*{}*", value.to_uppercase()),
+            )
+        }
+        Symbology::Codabar => {
+            let value = value.to_uppercase();
+            let is_guard = |c: Option<char>| matches!(c, Some('A'..='D'));
+            let value = if is_guard(value.chars().next()) && is_guard(value.chars().last()) {
+                value
+            } else {
+                format!("A{value}A")
+            };
+            (CODABAR_ALPHABET, CODABAR_PATTERNS, 7, value)
+        }
+    };
+
+    let mut modules = vec![false; QUIET_ZONE];
+
+    for c in value.chars() {
+        let idx = alphabet
+            .find(c)
+            .ok_or_else(|| format!("Character '{c}' cannot be encoded as {symbology:?}"))?;
+        push_pattern(&mut modules, patterns[idx], elements);
+    }
+
+    modules.pop(); // no gap after the stop character
+    modules.extend(vec![false; QUIET_ZONE]);
+
+    Ok(modules)
+}
+
+/// Render barcode modules as an SVG document.
+pub fn to_svg(modules: &[bool], module_width: usize, height: usize) -> String {
+    let module_width = module_width.max(1);
+    let height = height.max(1);
+    let width = modules.len() * module_width;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    svg += &format!(r#"<rect width="{width}" height="{height}" fill="white"/>"#);
+
+    let mut idx = 0;
+    while idx < modules.len() {
+        if !modules[idx] {
+            idx += 1;
+            continue;
+        }
+
+        let start = idx;
+        while idx < modules.len() && modules[idx] {
+            idx += 1;
+        }
+
+        svg += &format!(
+            r#"<rect x="{}" width="{}" height="{height}" fill="black"/>"#,
+            start * module_width,
+            (idx - start) * module_width,
+        );
+    }
+
+    svg += "</svg>";
+    svg
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Render barcode modules as a grayscale PNG image.
+///
+/// Image data is stored uncompressed, which is plenty small for
+/// barcode images and avoids requiring a compression library.
+///
+/// ```
+/// use evergreen::common::barcode::{self, Symbology};
+///
+/// let modules = barcode::encode("ABC-123", Symbology::Code39).unwrap();
+/// let png = barcode::to_png(&modules, 2, 40);
+/// assert_eq!(&png[0..8], b"\x89PNG\r\n\x1a\n");
+/// ```
+pub fn to_png(modules: &[bool], module_width: usize, height: usize) -> Vec<u8> {
+    let module_width = module_width.max(1);
+    let height = height.max(1);
+    let width = modules.len() * module_width;
+
+    // Every row is the same: a filter type byte followed by one
+    // byte per pixel.
+    let mut row = vec![0u8];
+    for module in modules {
+        let pixel = if *module { 0 } else { 255 };
+        row.extend(std::iter::repeat_n(pixel, module_width));
+    }
+
+    let raw: Vec<u8> = row.repeat(height);
+
+    // zlib stream of stored deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(65535).collect();
+    for (idx, block) in blocks.iter().enumerate() {
+        zlib.push(if idx == blocks.len() - 1 { 1 } else { 0 });
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    header.extend([8, 0, 0, 0, 0]); // 8-bit grayscale, no interlace
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut png, b"IHDR", &header);
+    png_chunk(&mut png, b"IDAT", &zlib);
+    png_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
@@ -176,6 +176,212 @@ pub fn get_display_attrs(
     Ok(map)
 }
 
+/// Names of the display fields used to build a BibDisplay.
+#[derive(Debug, Clone)]
+pub struct BibDisplayFields {
+    pub title: String,
+    pub author: String,
+    pub edition: String,
+    pub isbn: String,
+    pub physical_description: String,
+
+    /// Fill any values missing from the display fields from the
+    /// MARC record.  This costs a record fetch and parse, so it is
+    /// best left off for lists of items.
+    pub marc_fallback: bool,
+}
+
+impl Default for BibDisplayFields {
+    fn default() -> Self {
+        BibDisplayFields {
+            title: "title".to_string(),
+            author: "author".to_string(),
+            edition: "edition".to_string(),
+            isbn: "isbn".to_string(),
+            physical_description: "physical_description".to_string(),
+            marc_fallback: false,
+        }
+    }
+}
+
+/// Commonly displayed values for a bib record.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BibDisplay {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub edition: Option<String>,
+    pub isbns: Vec<String>,
+    pub physical_description: Option<String>,
+}
+
+impl BibDisplay {
+    /// Build from a record's display attributes.
+    pub fn from_display_attrs(attrs: &DisplayAttrSet, fields: &BibDisplayFields) -> BibDisplay {
+        let value = |name: &str| {
+            attrs
+                .attr(name)
+                .map(|a| a.value().first().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let isbns = match attrs.attr(&fields.isbn).map(|a| a.value()) {
+            Some(DisplayAttrValue::List(list)) => list.clone(),
+            Some(DisplayAttrValue::Value(Some(v))) => vec![v.to_string()],
+            _ => Vec::new(),
+        };
+
+        BibDisplay {
+            title: value(&fields.title),
+            author: value(&fields.author),
+            edition: value(&fields.edition),
+            physical_description: value(&fields.physical_description),
+            isbns,
+        }
+    }
+
+    /// Build from the MARC record.
+    ///
+    /// ```
+    /// use evergreen::common::bib::BibDisplay;
+    ///
+    /// let mut record = marc::Record::new();
+    ///
+    /// let mut field = marc::Field::new("020").unwrap();
+    /// field.add_subfield("a", "9781635575637 (hardcover)").unwrap();
+    /// record.insert_field(field);
+    ///
+    /// let mut field = marc::Field::new("245").unwrap();
+    /// field.add_subfield("a", "Piranesi /").unwrap();
+    /// field.add_subfield("c", "Susanna Clarke.").unwrap();
+    /// record.insert_field(field);
+    ///
+    /// let mut field = marc::Field::new("300").unwrap();
+    /// field.add_subfield("a", "272 pages ;").unwrap();
+    /// field.add_subfield("c", "22 cm").unwrap();
+    /// record.insert_field(field);
+    ///
+    /// let display = BibDisplay::from_marc(&record);
+    ///
+    /// assert_eq!(display.title.as_deref(), Some("Piranesi"));
+    /// assert_eq!(display.author, None);
+    /// assert_eq!(display.isbns, vec!["9781635575637"]);
+    /// assert_eq!(display.physical_description.as_deref(), Some("272 pages ; 22 cm"));
+    /// ```
+    pub fn from_marc(record: &marc::Record) -> BibDisplay {
+        let joined = |tag: &str, codes: &[&str]| {
+            let field = record.get_fields(tag).into_iter().next()?;
+
+            let value = field
+                .subfields()
+                .iter()
+                .filter(|sf| codes.contains(&sf.code()))
+                .map(|sf| sf.content().trim())
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            let value = value.trim_end_matches([' ', '/', ':', ';', ',', '.']);
+
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        };
+
+        // ISBN subfields may include qualifiers, e.g. "(hardcover)".
+        let isbns = record
+            .get_values("020", "a")
+            .iter()
+            .filter_map(|v| v.split_whitespace().next())
+            .map(|v| v.to_string())
+            .collect();
+
+        BibDisplay {
+            title: joined("245", &["a", "b"]),
+            author: joined("100", &["a"])
+                .or_else(|| joined("110", &["a"]))
+                .or_else(|| joined("111", &["a"])),
+            edition: joined("250", &["a"]),
+            isbns,
+            physical_description: joined("300", &["a", "b", "c"]),
+        }
+    }
+
+    /// Build from the dummy values of a pre-cataloged copy.
+    pub fn from_precat_copy(copy: &EgValue) -> BibDisplay {
+        let value = |name: &str| {
+            copy[name]
+                .as_str()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+
+        BibDisplay {
+            title: value("dummy_title"),
+            author: value("dummy_author"),
+            isbns: value("dummy_isbn").into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// True if any value is not yet set.
+    pub fn is_incomplete(&self) -> bool {
+        self.title.is_none()
+            || self.author.is_none()
+            || self.edition.is_none()
+            || self.isbns.is_empty()
+            || self.physical_description.is_none()
+    }
+
+    /// Fill our unset values from another BibDisplay.
+    pub fn fill_from(&mut self, other: BibDisplay) {
+        if self.title.is_none() {
+            self.title = other.title;
+        }
+        if self.author.is_none() {
+            self.author = other.author;
+        }
+        if self.edition.is_none() {
+            self.edition = other.edition;
+        }
+        if self.isbns.is_empty() {
+            self.isbns = other.isbns;
+        }
+        if self.physical_description.is_none() {
+            self.physical_description = other.physical_description;
+        }
+    }
+}
+
+/// Collect the display values for a bib record.
+///
+/// Values come from the metabib display fields, then the MARC record
+/// for anything missing when `fields.marc_fallback` is set.
+pub fn bib_display(
+    editor: &mut Editor,
+    bib_id: i64,
+    fields: &BibDisplayFields,
+) -> EgResult<BibDisplay> {
+    let mut display = match get_display_attrs(editor, &[bib_id])?.remove(&bib_id) {
+        Some(attrs) => BibDisplay::from_display_attrs(&attrs, fields),
+        None => BibDisplay::default(),
+    };
+
+    if !fields.marc_fallback || !display.is_incomplete() {
+        return Ok(display);
+    }
+
+    let bre = editor
+        .retrieve("bre", bib_id)?
+        .ok_or_else(|| editor.die_event())?;
+
+    if let Some(result) = marc::Record::from_xml(bre["marc"].str()?).next() {
+        display.fill_from(BibDisplay::from_marc(&result?));
+    }
+
+    Ok(display)
+}
+
 pub struct RecordSummary {
     id: i64,
     record: EgValue,
@@ -380,13 +380,18 @@ pub fn bill_payment_map_for_xact(
 
         // Find adjustments that apply to this individual billing and
         // has not already been accounted for.
-        let mut my_adjustments: Vec<&mut EgValue> = payments
-            .iter_mut()
-            .filter(|p| p["payment_type"].as_str().unwrap() == "account_adjustment")
-            .filter(|p| used_adjustments.contains(&p["account_adjustment"].id().unwrap()))
-            .filter(|p| p["account_adjustment"]["billing"] == bill["id"])
-            .map(|p| &mut p["account_adjustment"])
-            .collect();
+        let mut my_adjustments: Vec<&mut EgValue> = Vec::new();
+
+        for payment in payments.iter_mut() {
+            if payment.str_at("payment_type")? != "account_adjustment"
+                || !used_adjustments.contains(&payment["account_adjustment"].id()?)
+                || payment["account_adjustment"]["billing"] != bill["id"]
+            {
+                continue;
+            }
+
+            my_adjustments.push(&mut payment["account_adjustment"]);
+        }
 
         if my_adjustments.len() == 0 {
             continue;
@@ -515,7 +520,7 @@ pub fn xact_has_payment_within(
     let payment = &last_payment[0];
 
     // Every payment has a payment_ts value
-    let payment_ts = &payment["payment_ts"].as_str().unwrap();
+    let payment_ts = &payment.str_at("payment_ts")?;
     let payment_dt = date::parse_datetime(payment_ts)?;
 
     let window_start = date::subtract_interval(date::now(), interval)?;
@@ -542,7 +547,7 @@ pub fn generate_fines_for_resv(editor: &mut Editor, resv_id: i64) -> EgResult<()
     generate_fines_for_xact(
         editor,
         resv_id,
-        resv["end_time"].as_str().unwrap(),
+        resv.str_at("end_time")?,
         resv["pickup_lib"].int()?,
         resv["fine_amount"].float()?,
         fine_interval,
@@ -562,7 +567,7 @@ pub fn generate_fines_for_circ(editor: &mut Editor, circ_id: i64) -> EgResult<()
     generate_fines_for_xact(
         editor,
         circ_id,
-        circ["due_date"].as_str().unwrap(),
+        circ.str_at("due_date")?,
         circ["circ_lib"].int()?,
         circ["recurring_fine"].float()?,
         circ["fine_interval"].str()?,
@@ -633,16 +638,19 @@ pub fn generate_fines_for_xact(
     // occurred after the current due date*.  Otherwise, when a
     // due date changes, the fine generator will back-fill billings
     // for a period of time where the item was not technically overdue.
-    let fines: Vec<EgValue> = fines
-        .drain(..)
-        .filter(|f| f["billing_ts"].as_str().unwrap() > due_date)
-        .collect();
+    let mut recent_fines: Vec<EgValue> = Vec::new();
+    for fine in fines.drain(..) {
+        if fine.str_at("billing_ts")? > due_date {
+            recent_fines.push(fine);
+        }
+    }
+    let fines = recent_fines;
 
     let due_date_dt = date::parse_datetime(due_date)?;
 
     // First fine in the list (if we have one) will be the most recent.
     let last_fine_dt = match fines.get(0) {
-        Some(f) => date::parse_datetime(&f["billing_ts"].as_str().unwrap())?,
+        Some(f) => date::parse_datetime(&f.str_at("billing_ts")?)?,
         None => {
             grace_period = extend_grace_period(
                 editor,
@@ -1,8 +1,10 @@
 use crate as eg;
 use chrono::Timelike;
+use eg::common::asset;
 use eg::common::billing;
 use eg::common::circulator::{CircOp, Circulator};
 use eg::common::holds;
+use eg::common::holdshelf;
 use eg::common::penalty;
 use eg::common::targeter;
 use eg::common::transit;
@@ -234,7 +236,7 @@ impl Circulator<'_> {
         let interval = interval.string()?;
 
         // source_send_time is a known non-null string value.
-        let send_time_str = transit["source_send_time"].as_str().unwrap();
+        let send_time_str = transit.str_at("source_send_time")?;
         let send_time = date::parse_datetime(send_time_str)?;
 
         let horizon = date::add_interval(send_time, &interval)?;
@@ -403,36 +405,37 @@ impl Circulator<'_> {
     }
 
     /// Determines of our copy is eligible for floating.
+    ///
+    /// Copies in manual floating groups only float when the caller
+    /// asks for it via the manual_float option.
     fn set_can_float(&mut self) -> EgResult<()> {
-        let float_id = &self.copy()["floating"];
+        let floating = &self.copy()["floating"];
 
-        if float_id.is_null() {
+        if floating.is_null() {
             // Copy is not configured to float
             return Ok(());
         }
 
-        let float_id = float_id.clone();
-
-        // Copy can float.  Can it float here?
+        // Floating group may or may not be fleshed.
+        let group = if floating.is_object() {
+            floating.clone()
+        } else {
+            let float_id = floating.int()?;
+            self.editor()
+                .retrieve("cfg", float_id)?
+                .ok_or_else(|| self.editor().die_event())?
+        };
 
-        let float_group = self
-            .editor()
-            .retrieve("cfg", float_id)?
-            .ok_or_else(|| self.editor().die_event())?;
+        if group["manual"].boolish() && !self.get_option_bool("manual_float") {
+            log::debug!("{self} copy is in a manual floating group; not floating");
+            return Ok(());
+        }
 
-        let query = eg::hash! {
-            from: [
-                "evergreen.can_float",
-                float_group["id"].clone(),
-                self.copy()["circ_lib"].clone(),
-                self.circ_lib
-            ]
-        };
+        let copy_circ_lib = self.copy()["circ_lib"].int()?;
 
-        if let Some(resp) = self.editor().json_query(query)?.first() {
-            if resp["evergreen.can_float"].boolish() {
-                self.set_option_true("can_float");
-            }
+        // Copy can float.  Can it float here?
+        if asset::can_float(self.editor, group.id()?, copy_circ_lib, self.circ_lib)? {
+            self.set_option_true("can_float");
         }
 
         Ok(())
@@ -843,7 +846,7 @@ impl Circulator<'_> {
         let circ_id = circ["id"].clone();
 
         // to_string() early to avoid some mutable borrow issues
-        let due_date = circ["due_date"].as_str().unwrap().to_string();
+        let due_date = circ.str_at("due_date")?.to_string();
 
         let setting = match maybe_setting {
             Some(s) => s,
@@ -1127,7 +1130,7 @@ impl Circulator<'_> {
         let first_od = overdues.first().unwrap();
         let last_od = overdues.last().unwrap();
 
-        let btype_label = first_od["billing_type"].as_str().unwrap(); // required field
+        let btype_label = first_od.str_at("billing_type")?; // required field
         let period_start = first_od["period_start"].as_str();
         let period_end = last_od["period_end"].as_str();
 
@@ -1263,7 +1266,7 @@ impl Circulator<'_> {
             return Ok(());
         }
 
-        if hold["hold_type"].as_str().unwrap() == "R" {
+        if hold.str_at("hold_type")? == "R" {
             // hold_type required
             self.update_copy(eg::hash! {status: C::COPY_STATUS_CATALOGING})?;
             self.clear_option("fake_hold_dest");
@@ -1340,12 +1343,8 @@ impl Circulator<'_> {
         let mut hold = self.hold.take().unwrap();
         let hold_id = hold.id()?;
 
-        hold["shelf_time"] = EgValue::from("now");
-        hold["current_shelf_lib"] = EgValue::from(self.circ_lib);
-
-        if let Some(date) = holds::calc_hold_shelf_expire_time(self.editor(), &hold, None)? {
-            hold["shelf_expire_time"] = EgValue::from(date);
-        }
+        let circ_lib = self.circ_lib;
+        holdshelf::place_on_shelf(self.editor(), &mut hold, circ_lib)?;
 
         self.editor().update(hold)?;
         self.hold = self.editor().retrieve("ahr", hold_id)?;
@@ -1468,11 +1467,10 @@ impl Circulator<'_> {
             params,
         )?;
 
-        let resp = result
-            .ok_or_else(|| EgError::Debug(format!("Booking capture failed to return event")))?;
+        let resp = result.ok_or_else(|| EgError::from("Booking capture failed to return event"))?;
 
         let mut evt = EgEvent::parse(&resp)
-            .ok_or_else(|| EgError::Debug(format!("Booking capture failed to return event")))?;
+            .ok_or_else(|| EgError::from("Booking capture failed to return event"))?;
 
         if evt.textcode() == "RESERVATION_NOT_FOUND" {
             if let Some(cause) = evt.payload()["fail_cause"].as_str() {
@@ -1588,13 +1586,11 @@ impl Circulator<'_> {
             return self.checkin_handle_precat();
         }
 
-        let can_float = self.get_option_bool("can_float");
-        let manual_float =
-            self.get_option_bool("manual_float") || self.copy()["floating"]["manual"].boolish();
-
-        if can_float && manual_float && !has_remote_hold {
+        if self.get_option_bool("can_float") && !has_remote_hold {
             // Copy is floating -- make it stick here
+            log::info!("{self} floating copy to {}", self.circ_lib);
             self.update_copy(eg::hash! {"circ_lib": self.circ_lib})?;
+            self.set_option_true("floated");
             return Ok(());
         }
 
@@ -1723,6 +1719,11 @@ impl Circulator<'_> {
             "volume": volume,
         };
 
+        if self.get_option_bool("floated") {
+            // The copy's circ lib is now the checkin library.
+            payload["floated"] = EgValue::from(true);
+        }
+
         if !self.is_precat_copy() {
             if let Some(rec) = self.editor().retrieve("rmsr", record_id)? {
                 payload["title"] = rec;
@@ -1859,8 +1860,7 @@ impl Circulator<'_> {
 
         // The hold fulfillment time will match the xact_start time of
         // its companion circulation.
-        let xact_date =
-            date::parse_datetime(self.circ.as_ref().unwrap()["xact_start"].as_str().unwrap())?;
+        let xact_date = date::parse_datetime(self.circ.as_ref().unwrap().str_at("xact_start")?)?;
 
         let ff_date = date::parse_datetime(
             self.hold.as_ref().unwrap()["fulfillment_time"]
@@ -1,4 +1,5 @@
 use crate as eg;
+use eg::common::asset;
 use eg::common::bib;
 use eg::common::billing;
 use eg::common::circulator::{CircOp, CircPolicy, Circulator, LEGACY_CIRC_EVENT_MAP};
@@ -34,6 +35,11 @@ impl Circulator<'_> {
 
         self.base_checkout_perms()?;
 
+        // The circ policy test blocks on standing penalties, so make
+        // sure the max items out, max overdue, and max fines penalties
+        // reflect the patron's current circulations and balance.
+        self.calculate_limit_penalties()?;
+
         self.set_circ_policy()?;
         self.inspect_policy_failures()?;
         self.check_copy_alerts()?;
@@ -95,6 +101,22 @@ impl Circulator<'_> {
         Ok(())
     }
 
+    /// Apply or remove the standing penalties generated by group
+    /// penalty thresholds on checkout limits.
+    fn calculate_limit_penalties(&mut self) -> EgResult<()> {
+        let limit_penalties: Vec<EgValue> = penalty::CHECKOUT_LIMIT_PENALTIES
+            .iter()
+            .map(|p| EgValue::from(*p))
+            .collect();
+
+        penalty::calculate_penalties(
+            self.editor,
+            self.patron_id,
+            self.circ_lib,
+            Some(&limit_penalties),
+        )
+    }
+
     fn checkout_noncat(&mut self) -> EgResult<()> {
         let noncat_type = match self.options.get("noncat_type") {
             Some(v) => v,
@@ -151,8 +173,6 @@ impl Circulator<'_> {
             return self.update_existing_precat();
         }
 
-        let reqr_id = self.requestor_id()?;
-
         let dummy_title = self
             .options
             .get("dummy_title")
@@ -186,33 +206,16 @@ impl Circulator<'_> {
 
         log::info!("{self} creating new pre-cat copy {copy_barcode}");
 
-        let copy = eg::hash! {
-            "circ_lib": self.circ_lib,
-            "creator": reqr_id,
-            "editor": reqr_id,
-            "barcode": copy_barcode,
-            "dummy_title": dummy_title,
-            "dummy_author": dummy_author,
-            "dummy_isbn": dummy_isbn,
-            "circ_modifier": circ_modifier,
-            "call_number": C::PRECAT_CALL_NUMBER,
-            "loan_duration": C::PRECAT_COPY_LOAN_DURATION,
-            "fine_level": C::PRECAT_COPY_FINE_LEVEL,
+        let values = asset::PrecatCopy {
+            barcode: copy_barcode,
+            circ_lib: self.circ_lib,
+            dummy_title,
+            dummy_author,
+            dummy_isbn,
+            circ_modifier,
         };
 
-        let mut copy = EgValue::create("acp", copy)?;
-
-        let pclib = self
-            .settings
-            .get_value("circ.pre_cat_copy_circ_lib")?
-            .clone();
-
-        if let Some(sn) = pclib.as_str() {
-            let o = org::by_shortname(self.editor(), sn)?;
-            copy["circ_lib"] = o["id"].clone();
-        }
-
-        let copy = self.editor().create(copy)?;
+        let copy = asset::create_precat_copy(self.editor, &values)?;
 
         self.copy_id = copy.id()?;
 
@@ -407,7 +410,7 @@ impl Circulator<'_> {
                 .as_str()
             {
                 let interval = date::interval_to_seconds(intvl)?;
-                let xact_start = date::parse_datetime(circ["xact_start"].as_str().unwrap())?;
+                let xact_start = date::parse_datetime(circ.str_at("xact_start")?)?;
 
                 let cutoff = xact_start + Duration::from_secs(interval as u64);
 
@@ -587,8 +590,7 @@ impl Circulator<'_> {
 
             let penalty_codes: Vec<&str> = policy_results
                 .iter()
-                .filter(|r| r["fail_part"].is_string())
-                .map(|r| r.as_str().unwrap())
+                .filter_map(|r| r["fail_part"].as_str())
                 .collect();
 
             let query = eg::hash! {
@@ -597,10 +599,10 @@ impl Circulator<'_> {
             };
 
             let block_pens = self.editor().search("csp", query)?;
-            let block_pen_names: Vec<&str> = block_pens
+            let block_pen_names = block_pens
                 .iter()
-                .map(|p| p["name"].as_str().unwrap())
-                .collect();
+                .map(|p| p.str_at("name"))
+                .collect::<EgResult<Vec<&str>>>()?;
 
             let mut keepers = Vec::new();
 
@@ -819,7 +821,7 @@ impl Circulator<'_> {
         let mut due_date = start_date + Duration::from_secs(dur_secs as u64);
 
         if let Some(hdd) = policy.hard_due_date.as_ref() {
-            let cdate_str = hdd["ceiling_date"].as_str().unwrap();
+            let cdate_str = hdd.str_at("ceiling_date")?;
             let cdate = date::parse_datetime(cdate_str)?;
             let force = hdd["forceto"].boolish();
 
@@ -896,7 +898,7 @@ impl Circulator<'_> {
                 .retrieve("bresv", id.clone())?
                 .ok_or_else(|| self.editor().die_event())?;
 
-            let booking_start = date::parse_datetime(booking["start_time"].as_str().unwrap())?;
+            let booking_start = date::parse_datetime(booking.str_at("start_time")?)?;
 
             // Block the circ if a reservation is already active or
             // we're told to prevent new circs on matching resources.
@@ -1022,10 +1024,10 @@ impl Circulator<'_> {
             None => return Err(self.editor().die_event()),
         };
 
-        let start_time_str = prev_circ["xact_start"].as_str().expect("required");
+        let start_time_str = prev_circ.str_at("xact_start")?;
         let start_time = date::parse_datetime(start_time_str)?;
 
-        let prev_due_date_str = prev_circ["due_date"].as_str().expect("required");
+        let prev_due_date_str = prev_circ.str_at("due_date")?;
         let prev_due_date = date::parse_datetime(prev_due_date_str)?;
 
         let now_time = date::now();
@@ -1153,8 +1155,7 @@ impl Circulator<'_> {
             return Ok(());
         }
 
-        // confirmed above
-        let deposit_amount = self.copy()["deposit_amount"].as_f64().unwrap();
+        let deposit_amount = self.copy().float_at("deposit_amount")?;
 
         let skip_deposit_fee = self.settings.get_value("skip_deposit_fee")?.boolish();
         if is_deposit && (skip_deposit_fee || self.is_deposit_exempt()?) {
@@ -1276,6 +1277,11 @@ impl Circulator<'_> {
 
         log::info!("{self} fulfilling hold {hold_id}");
 
+        // Holds found on the shelf have a fleshed user.
+        if hold["usr"].is_object() {
+            hold["usr"] = EgValue::from(hold["usr"].id()?);
+        }
+
         hold["hopeless_date"].take();
         hold["current_copy"] = EgValue::from(self.copy_id);
         hold["fulfillment_time"] = EgValue::from("now");
@@ -1286,7 +1292,7 @@ impl Circulator<'_> {
             hold["capture_time"] = EgValue::from("now");
         }
 
-        self.editor().create(hold)?;
+        self.editor().update(hold)?;
 
         self.fulfilled_hold_ids = Some(vec![hold_id]);
 
@@ -1488,7 +1494,7 @@ impl Circulator<'_> {
 
         let penalties = self.editor().json_query(query)?;
         for pen in penalties {
-            let mut evt = EgEvent::new(pen["name"].as_str().unwrap());
+            let mut evt = EgEvent::new(pen.str_at("name")?);
             if let Some(d) = pen["label"].as_str() {
                 evt.set_desc(d);
             }
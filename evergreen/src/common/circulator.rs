@@ -1,4 +1,5 @@
 use crate as eg;
+use eg::common::asset;
 use eg::common::holds;
 use eg::common::org;
 use eg::common::settings::Settings;
@@ -7,7 +8,7 @@ use eg::constants as C;
 use eg::editor::Editor;
 use eg::event::{EgEvent, Overrides};
 use eg::util;
-use eg::{EgError, EgResult, EgValue};
+use eg::{EgResult, EgValue};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
@@ -118,6 +119,96 @@ impl CircPolicy {
     }
 }
 
+/// Typed view of the outcome of a circulation action.
+///
+/// Circulation APIs return the affected objects in the payload of
+/// their primary event.  CircResult pulls them out so callers don't
+/// have to mine the payload themselves.
+#[derive(Debug, Clone)]
+pub struct CircResult {
+    /// Primary event returned by the action, payload removed.
+    pub event: EgEvent,
+    pub copy: Option<EgValue>,
+    pub circ: Option<EgValue>,
+    /// Hold captured or fulfilled by the action.  For checkins,
+    /// a hold captured for a remote (other library) patron is
+    /// preferred.
+    pub hold: Option<EgValue>,
+    pub transit: Option<EgValue>,
+    pub patron: Option<EgValue>,
+    /// ISO due date of the circulation.
+    pub due_date: Option<String>,
+    /// Amount of any deposit or rental fee billed by the action.
+    pub fee_billed: Option<f64>,
+    /// Balance owed on the circulation, when the circ arrives with
+    /// its billing summary fleshed.
+    pub balance_owed: Option<f64>,
+    /// IDs of holds fulfilled by a checkout.
+    pub holds_fulfilled: Vec<i64>,
+}
+
+impl CircResult {
+    /// Build a result from a circulation event, consuming its payload.
+    pub fn from_event(mut event: EgEvent) -> CircResult {
+        let mut payload = event.payload_mut().take();
+
+        // Payload values may also be non-objects, e.g. copy alert messages.
+        let mut object = |key: &str| {
+            let value = payload[key].take();
+            if value.is_object() {
+                Some(value)
+            } else {
+                None
+            }
+        };
+
+        let copy = object("copy");
+        let circ = object("circ");
+        let hold = object("remote_hold").or_else(|| object("hold"));
+        let transit = object("transit");
+        let patron = object("patron");
+
+        let fee_billed = ["deposit_billing", "rental_billing"]
+            .iter()
+            .find_map(|key| payload[*key]["amount"].as_f64());
+
+        let holds_fulfilled = payload["holds_fulfilled"]
+            .members()
+            .filter_map(|id| id.as_int())
+            .collect();
+
+        let due_date = circ
+            .as_ref()
+            .and_then(|c| c["due_date"].as_str())
+            .map(|d| d.to_string());
+
+        let balance_owed = circ
+            .as_ref()
+            .and_then(|c| c["billable_transaction"]["summary"]["balance_owed"].as_f64());
+
+        CircResult {
+            event,
+            copy,
+            circ,
+            hold,
+            transit,
+            patron,
+            due_date,
+            fee_billed,
+            balance_owed,
+            holds_fulfilled,
+        }
+    }
+
+    pub fn textcode(&self) -> &str {
+        self.event.textcode()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.event.is_success()
+    }
+}
+
 /// Context and shared methods for circulation actions.
 ///
 /// Innards are 'pub' since the impl's are spread across multiple files.
@@ -381,7 +472,7 @@ impl<'a> Circulator<'a> {
     /// a rollback on the main editor.
     pub fn exit_err_on_event(&mut self, evt: EgEvent) -> EgResult<()> {
         self.add_event(evt.clone());
-        Err(EgError::Event(evt))
+        Err(evt.into())
     }
 
     /// Sets a final event and sets the exit_early flag.
@@ -994,29 +1085,31 @@ impl<'a> Circulator<'a> {
     /// be performed after one of our core actions (e.g. checkin) has
     /// completed and produced a response.
     pub fn post_commit_tasks(&mut self) -> EgResult<()> {
-        self.retarget_holds()?;
-        self.make_trigger_events()
+        self.post_commit().run(self.editor)
+    }
+
+    /// Work to perform once our transaction is committed, detached
+    /// from the circulator so it may run after the circulator (and
+    /// its editor borrow) is gone, e.g. after Editor::in_transaction().
+    pub fn post_commit(&self) -> PostCommitTasks {
+        PostCommitTasks {
+            retarget_holds: self.retarget_holds.clone(),
+            circ: self.circ.clone(),
+            circ_op: self.circ_op.clone(),
+            circ_lib: self.circ_lib,
+        }
     }
 
     /// Update our copy with the values provided.
     ///
     /// * `changes` - a JSON Object with key/value copy attributes to update.
-    pub fn update_copy(&mut self, mut changes: EgValue) -> EgResult<&EgValue> {
-        let mut copy = match self.copy.take() {
+    pub fn update_copy(&mut self, changes: EgValue) -> EgResult<&EgValue> {
+        let copy = match self.copy.take() {
             Some(c) => c,
             None => Err(format!("We have no copy to update"))?,
         };
 
-        copy["editor"] = self.requestor_id()?.into();
-        copy["edit_date"] = "now".into();
-
-        for (k, v) in changes.entries_mut() {
-            copy[k] = v.take();
-        }
-
-        copy.deflesh()?;
-
-        self.editor().update(copy)?;
+        asset::update_copy(self.editor, copy, changes)?;
 
         // Load the updated copy with the usual fleshing.
         self.load_copy()?;
@@ -1112,7 +1205,7 @@ impl<'a> Circulator<'a> {
 
         if self.failed_events.len() > 0 {
             log::info!("Exiting early on failed events: {:?}", self.failed_events);
-            Err(EgError::Event(self.failed_events[0].clone()))
+            Err(self.failed_events[0].clone().into())
         } else {
             // If all is well and we encountered a SUCCESS event, keep
             // it in place so it can ultimately be returned to the caller.
@@ -1166,38 +1259,6 @@ impl<'a> Circulator<'a> {
     }
 
     /// Retarget holds in our collected list of holds to retarget.
-    fn retarget_holds(&mut self) -> EgResult<()> {
-        let hold_ids = match self.retarget_holds.as_ref() {
-            Some(list) => list.clone(),
-            None => return Ok(()),
-        };
-        holds::retarget_holds(&self.editor, hold_ids.as_slice())
-    }
-
-    /// Create A/T events for checkout/checkin/renewal actions.
-    fn make_trigger_events(&mut self) -> EgResult<()> {
-        let circ = match self.circ.as_ref() {
-            Some(c) => c,
-            None => return Ok(()),
-        };
-
-        let action: &str = (&self.circ_op).into();
-
-        if action == "other" {
-            return Ok(());
-        }
-
-        trigger::create_events_for_object(
-            &mut self.editor,
-            action,
-            circ,
-            self.circ_lib,
-            None,
-            None,
-            false,
-        )
-    }
-
     /// Remove duplicate events and remove any SUCCESS events if other
     /// event types are present.
     pub fn cleanup_events(&mut self) {
@@ -1233,6 +1294,18 @@ impl<'a> Circulator<'a> {
         &self.events
     }
 
+    /// Typed result for the action, built from our primary (first) event.
+    ///
+    /// Returns Err if no events have been collected.
+    pub fn result(&self) -> EgResult<CircResult> {
+        let evt = self
+            .events
+            .first()
+            .ok_or_else(|| format!("{self} produced no events"))?;
+
+        Ok(CircResult::from_event(evt.clone()))
+    }
+
     /// Clears our list of compiled events and returns them to the caller.
     pub fn take_events(&mut self) -> Vec<EgEvent> {
         std::mem::replace(&mut self.events, Vec::new())
@@ -1256,3 +1329,44 @@ impl<'a> Circulator<'a> {
         }
     }
 }
+
+/// Post-commit work for a circulation action.  See
+/// Circulator::post_commit().
+pub struct PostCommitTasks {
+    retarget_holds: Option<Vec<i64>>,
+    circ: Option<EgValue>,
+    circ_op: CircOp,
+    circ_lib: i64,
+}
+
+impl PostCommitTasks {
+    /// Retarget holds and create A/T events outside of any transaction.
+    pub fn run(self, editor: &mut Editor) -> EgResult<()> {
+        self.retarget_holds(editor)?;
+        self.make_trigger_events(editor)
+    }
+
+    fn retarget_holds(&self, editor: &mut Editor) -> EgResult<()> {
+        let hold_ids = match self.retarget_holds.as_ref() {
+            Some(list) => list.clone(),
+            None => return Ok(()),
+        };
+        holds::retarget_holds(editor, hold_ids.as_slice())
+    }
+
+    /// Create A/T events for checkout/checkin/renewal actions.
+    fn make_trigger_events(&self, editor: &mut Editor) -> EgResult<()> {
+        let circ = match self.circ.as_ref() {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        let action: &str = (&self.circ_op).into();
+
+        if action == "other" {
+            return Ok(());
+        }
+
+        trigger::create_events_for_object(editor, action, circ, self.circ_lib, None, None, false)
+    }
+}
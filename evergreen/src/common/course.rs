@@ -0,0 +1,132 @@
+//! Course reserves: courses, their attached materials, and their users.
+use crate as eg;
+use eg::common::org;
+use eg::editor::Editor;
+use eg::result::EgResult;
+use eg::EgValue;
+
+/// Filtering and paging options for course lookups.
+#[derive(Debug, Default, Clone)]
+pub struct CourseQuery {
+    /// Limit to courses owned by this org unit or its descendants.
+    pub owning_lib: Option<i64>,
+    /// Include archived courses.
+    pub include_archived: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl CourseQuery {
+    /// Build from an API options hash, e.g.
+    /// {"owning_lib": 4, "include_archived": true, "limit": 10, "offset": 20}
+    pub fn from_options(options: &EgValue) -> CourseQuery {
+        CourseQuery {
+            owning_lib: options["owning_lib"].as_int(),
+            include_archived: options["include_archived"].boolish(),
+            limit: options["limit"].as_int(),
+            offset: options["offset"].as_int(),
+        }
+    }
+
+    /// Apply our limit and offset to a set of search options.
+    fn apply_paging(&self, ops: &mut EgValue) {
+        if let Some(limit) = self.limit {
+            ops["limit"] = EgValue::from(limit);
+        }
+        if let Some(offset) = self.offset {
+            ops["offset"] = EgValue::from(offset);
+        }
+    }
+}
+
+/// List courses sorted by name and course number.
+pub fn list_courses(editor: &mut Editor, query: &CourseQuery) -> EgResult<Vec<EgValue>> {
+    let mut filter = eg::hash! {"id": {"!=": EgValue::Null}};
+
+    if !query.include_archived {
+        filter["is_archived"] = EgValue::from("f");
+    }
+
+    if let Some(org_id) = query.owning_lib {
+        filter["owning_lib"] = EgValue::from(org::descendants(editor, org_id)?);
+    }
+
+    let mut ops = eg::hash! {
+        "order_by": {"acmc": ["name", "course_number", "section_number"]}
+    };
+
+    query.apply_paging(&mut ops);
+
+    editor.search_with_ops("acmc", filter, ops)
+}
+
+/// List the materials attached to a course.
+///
+/// Materials are fleshed with their item (call number, location,
+/// and status) and bib record.
+pub fn course_materials(
+    editor: &mut Editor,
+    course_id: i64,
+    query: &CourseQuery,
+) -> EgResult<Vec<EgValue>> {
+    let mut ops = eg::hash! {
+        "flesh": 2,
+        "flesh_fields": {
+            "acmcm": ["item", "record"],
+            "acp": ["call_number", "location", "status"],
+        },
+        "order_by": {"acmcm": "id"},
+    };
+
+    query.apply_paging(&mut ops);
+
+    editor.search_with_ops("acmcm", eg::hash! {"course": course_id}, ops)
+}
+
+/// List the users (e.g. instructors) associated with a course.
+///
+/// Only users whose role is public are included, and only their names
+/// and role are returned, since these are displayed to patrons.
+pub fn course_users(
+    editor: &mut Editor,
+    course_id: i64,
+    query: &CourseQuery,
+) -> EgResult<Vec<EgValue>> {
+    let filter = eg::hash! {
+        "course": course_id,
+        "usr_role": {
+            "in": {
+                "select": {"acmr": ["id"]},
+                "from": "acmr",
+                "where": {"is_public": "t"},
+            }
+        }
+    };
+
+    let mut ops = eg::hash! {
+        "flesh": 1,
+        "flesh_fields": {"acmcu": ["usr", "usr_role"]},
+        "order_by": {"acmcu": "id"},
+    };
+
+    query.apply_paging(&mut ops);
+
+    let mut list = Vec::new();
+
+    for mut cu in editor.search_with_ops("acmcu", filter, ops)? {
+        let mut usr = cu["usr"].take();
+
+        list.push(eg::hash! {
+            "id": cu["id"].take(),
+            "course": cu["course"].take(),
+            "role": cu["usr_role"]["name"].take(),
+            "usr": usr["id"].take(),
+            "first_given_name": usr["first_given_name"].take(),
+            "family_name": usr["family_name"].take(),
+            "pref_first_given_name": usr["pref_first_given_name"].take(),
+            "pref_family_name": usr["pref_family_name"].take(),
+        });
+    }
+
+    Ok(list)
+}
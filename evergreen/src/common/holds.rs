@@ -226,7 +226,7 @@ pub fn find_nearest_permitted_hold(
         );
 
         let hold = editor.retrieve("ahr", hold_id)?.unwrap(); // required
-        let hold_type = hold["hold_type"].as_str().unwrap(); // required
+        let hold_type = hold.str_at("hold_type")?; // required
         if hold_type == "R" || hold_type == "F" {
             // These hold types do not require verification
             best_hold = Some(hold);
@@ -686,7 +686,7 @@ pub fn related_to_copy(
     let mut list = Vec::new();
     for val in editor.json_query(query)? {
         // We know the hold type returned from the database is valid.
-        let hold_type = HoldType::try_from(val["hold_type"].as_str().unwrap()).unwrap();
+        let hold_type = HoldType::try_from(val.str_at("hold_type")?).unwrap();
 
         let h = MinimalHold {
             id: val.id()?,
@@ -773,3 +773,147 @@ pub fn record_has_holdable_copy(editor: &mut Editor, rec_id: i64, is_meta: bool)
 
     Ok(data[&func].boolish())
 }
+
+/// Where a hold sits among the other holds competing for its copies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoldQueueStats {
+    pub hold_id: i64,
+    /// 1-based position of the hold in the queue.
+    pub queue_position: i64,
+    /// Number of open holds sharing at least one potential copy.
+    pub total_holds: i64,
+    /// Number of copies which could fill the hold.
+    pub potential_copies: i64,
+    /// Estimated wait in seconds, if one can be calculated.
+    pub estimated_wait: Option<i64>,
+}
+
+impl HoldQueueStats {
+    pub fn to_eg_value(&self) -> EgValue {
+        eg::hash! {
+            "hold_id": self.hold_id,
+            "queue_position": self.queue_position,
+            "total_holds": self.total_holds,
+            "potential_copies": self.potential_copies,
+            "estimated_wait": self.estimated_wait,
+        }
+    }
+}
+
+/// Calculate the queue position and estimated wait for a hold.
+///
+/// The queue is made up of all open holds which share at least one
+/// potential copy with our hold, in the order they would be targeted.
+///
+/// The estimated wait assumes each potential copy fills one hold per
+/// circulation period, using circ.holds.default_estimated_wait_interval
+/// at the pickup library for the circulation period, bounded below by
+/// circ.holds.min_estimated_wait_interval.
+pub fn queue_stats(editor: &mut Editor, hold_id: i64) -> EgResult<HoldQueueStats> {
+    let hold = editor
+        .retrieve("ahr", hold_id)?
+        .ok_or_else(|| editor.die_event())?;
+
+    let pickup_lib = hold["pickup_lib"].int()?;
+
+    let copies_query = eg::hash! {
+        "select": {"ahcm": ["target_copy"]},
+        "from": "ahcm",
+        "where": {"hold": hold_id},
+    };
+
+    let query = eg::hash! {
+        "select": {
+            "ahcm": [{
+                "column": "target_copy",
+                "transform": "count",
+                "distinct": true,
+                "alias": "count",
+            }]
+        },
+        "from": "ahcm",
+        "where": {"hold": hold_id},
+    };
+
+    let potential_copies = match editor.json_query(query)?.pop() {
+        Some(v) => v["count"].int()?,
+        None => 0,
+    };
+
+    let mut stats = HoldQueueStats {
+        hold_id,
+        queue_position: 1,
+        total_holds: 1,
+        potential_copies,
+        estimated_wait: None,
+    };
+
+    if potential_copies == 0 {
+        // Nothing to compete for.
+        return Ok(stats);
+    }
+
+    let query = eg::hash! {
+        "select": {
+            "ahr": ["id", "cut_in_line", "selection_depth", "request_time"],
+            "pgt": ["hold_priority"],
+        },
+        "from": {
+            "ahr": {
+                "au": {
+                    "field": "id",
+                    "fkey": "usr",
+                    "join": "pgt"
+                }
+            }
+        },
+        "where": {
+            "+ahr": {
+                "id": {
+                    "in": {
+                        "select": {"ahcm": ["hold"]},
+                        "from": "ahcm",
+                        "where": {"target_copy": {"in": copies_query}},
+                    }
+                },
+                "cancel_time": EgValue::Null,
+                "fulfillment_time": EgValue::Null,
+            }
+        },
+        "order_by": json_query_order_by_targetable(),
+    };
+
+    let queue = editor.json_query(query)?;
+
+    stats.total_holds = queue.len() as i64;
+
+    for (idx, h) in queue.iter().enumerate() {
+        if h.id()? == hold_id {
+            stats.queue_position = idx as i64 + 1;
+            break;
+        }
+    }
+
+    let mut settings = Settings::new(editor);
+
+    let default_wait = settings
+        .get_value_at_org("circ.holds.default_estimated_wait_interval", pickup_lib)?
+        .as_str()
+        .map(date::interval_to_seconds)
+        .transpose()?;
+
+    let min_wait = settings
+        .get_value_at_org("circ.holds.min_estimated_wait_interval", pickup_lib)?
+        .as_str()
+        .map(date::interval_to_seconds)
+        .transpose()?
+        .unwrap_or(0);
+
+    if let Some(wait) = default_wait {
+        // Number of full circulation periods before a copy reaches us.
+        let periods = (stats.queue_position - 1) / potential_copies;
+        stats.estimated_wait = Some((periods * wait).max(min_wait));
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,247 @@
+//! Hold shelf management: shelf expire times and clear-shelf processing.
+use crate as eg;
+use eg::common::holds;
+use eg::common::org;
+use eg::common::trigger;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+use std::fmt;
+
+/// actor.hold_cancel_cause for holds which expired on the shelf.
+const CANCEL_CAUSE_SHELF_EXPIRED: i64 = 2;
+
+/// A/T hook fired for each hold canceled by the clear-shelf process.
+const SHELF_EXPIRED_HOOK: &str = "hold_request.cancel.expire_holds_shelf";
+
+/// What staff should do with an item pulled from the hold shelf.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShelfAction {
+    /// Item fills another hold for pickup at the same library.
+    Hold,
+    /// Item must travel to another library, either to fill a hold
+    /// or to return home.
+    Transit,
+    /// Item goes back to the shelves.
+    Reshelve,
+}
+
+impl fmt::Display for ShelfAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Hold => "hold",
+            Self::Transit => "transit",
+            Self::Reshelve => "reshelve",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// One item to pull from the hold shelf.
+#[derive(Debug, Clone)]
+pub struct PickListEntry {
+    pub hold_id: i64,
+    pub copy_id: i64,
+    pub barcode: String,
+    pub title: Option<String>,
+    pub call_number: Option<String>,
+    pub patron_id: i64,
+    pub shelf_lib: i64,
+    pub shelf_expire_time: String,
+    pub action: ShelfAction,
+    /// Where the item goes next, for holds and transits.
+    pub dest_lib: Option<i64>,
+}
+
+impl PickListEntry {
+    pub fn to_eg_value(&self) -> EgValue {
+        eg::hash! {
+            "hold": self.hold_id,
+            "copy": self.copy_id,
+            "barcode": self.barcode.as_str(),
+            "title": self.title.as_deref(),
+            "call_number": self.call_number.as_deref(),
+            "patron": self.patron_id,
+            "shelf_lib": self.shelf_lib,
+            "shelf_expire_time": self.shelf_expire_time.as_str(),
+            "action": self.action.to_string(),
+            "dest_lib": self.dest_lib,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClearShelfOptions {
+    /// Also clear the hold shelves of descendant org units.
+    pub descendants: bool,
+    /// Create A/T events for each canceled hold.
+    pub notify: bool,
+}
+
+/// Set the shelf values on a newly captured hold.
+///
+/// The caller is responsible for updating the hold.
+pub fn place_on_shelf(editor: &mut Editor, hold: &mut EgValue, shelf_lib: i64) -> EgResult<()> {
+    hold["shelf_time"] = EgValue::from("now");
+    hold["current_shelf_lib"] = EgValue::from(shelf_lib);
+
+    // Leave any existing expire time in place when none applies.
+    if let Some(date) = holds::calc_hold_shelf_expire_time(editor, hold, None)? {
+        hold["shelf_expire_time"] = EgValue::from(date);
+    }
+
+    Ok(())
+}
+
+/// Captured, unfulfilled, uncanceled holds on the shelf at the
+/// provided org units whose shelf expire time has passed.
+pub fn expired_shelf_holds(editor: &mut Editor, org_ids: &[i64]) -> EgResult<Vec<EgValue>> {
+    let query = eg::hash! {
+        "current_shelf_lib": org_ids,
+        "capture_time": {"!=": eg::NULL},
+        "fulfillment_time": eg::NULL,
+        "cancel_time": eg::NULL,
+        "shelf_expire_time": {"<": "now"},
+    };
+
+    let ops = eg::hash! {
+        "flesh": 2,
+        "flesh_fields": {
+            "ahr": ["current_copy"],
+            "acp": ["call_number"],
+        },
+        "order_by": {"ahr": ["current_shelf_lib", "shelf_expire_time"]},
+    };
+
+    editor.search_with_ops("ahr", query, ops)
+}
+
+/// Cancel expired holds on the hold shelf at the provided org unit
+/// and return the list of items staff should pull from the shelf.
+///
+/// Uses an externally managed Editor transaction.
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::common::holdshelf::{self, ClearShelfOptions};
+///
+/// let client = eg::init().unwrap();
+/// let mut editor = eg::Editor::new(&client);
+///
+/// let ops = ClearShelfOptions {
+///     descendants: true,
+///     notify: false,
+/// };
+///
+/// let entries = editor
+///     .in_transaction(|e| holdshelf::clear_shelf(e, 1, &ops))
+///     .unwrap();
+///
+/// for entry in entries {
+///     println!("{} => {}", entry.barcode, entry.action);
+/// }
+/// ```
+pub fn clear_shelf(
+    editor: &mut Editor,
+    org_id: i64,
+    options: &ClearShelfOptions,
+) -> EgResult<Vec<PickListEntry>> {
+    let org_ids = if options.descendants {
+        org::descendants(editor, org_id)?
+    } else {
+        vec![org_id]
+    };
+
+    let mut pick_list = Vec::new();
+
+    for hold in expired_shelf_holds(editor, &org_ids)? {
+        pick_list.push(clear_hold(editor, hold, options)?);
+    }
+
+    log::info!(
+        "ACT:clear-shelf org={org_id} descendants={} cleared={}",
+        options.descendants,
+        pick_list.len()
+    );
+
+    Ok(pick_list)
+}
+
+/// Cancel one expired shelf hold and decide what happens to its item.
+fn clear_hold(
+    editor: &mut Editor,
+    mut hold: EgValue,
+    options: &ClearShelfOptions,
+) -> EgResult<PickListEntry> {
+    let hold_id = hold.id()?;
+    let shelf_lib = hold["current_shelf_lib"].int()?;
+
+    let copy = hold["current_copy"].take();
+    let copy_id = copy.id()?;
+
+    // Precat call numbers point to record -1, which has no title.
+    let title = editor
+        .retrieve("rmsr", copy["call_number"]["record"].int()?)?
+        .and_then(|r| r["title"].as_str().map(|s| s.to_string()));
+
+    let mut entry = PickListEntry {
+        hold_id,
+        copy_id,
+        barcode: copy["barcode"].string()?,
+        title,
+        call_number: copy["call_number"]["label"].as_str().map(|s| s.to_string()),
+        patron_id: hold["usr"].int()?,
+        shelf_lib,
+        shelf_expire_time: hold["shelf_expire_time"].string()?,
+        action: ShelfAction::Reshelve,
+        dest_lib: None,
+    };
+
+    log::info!(
+        "ACT:clear-shelf canceling hold {hold_id} for copy {} expired {}",
+        entry.barcode,
+        entry.shelf_expire_time
+    );
+
+    hold["current_copy"] = EgValue::from(copy_id);
+    hold["cancel_time"] = EgValue::from("now");
+    hold["cancel_cause"] = EgValue::from(CANCEL_CAUSE_SHELF_EXPIRED);
+
+    editor.update(hold)?;
+
+    if options.notify {
+        let hold = editor
+            .retrieve("ahr", hold_id)?
+            .ok_or_else(|| editor.die_event())?;
+
+        trigger::create_events_for_object(
+            editor,
+            SHELF_EXPIRED_HOOK,
+            &hold,
+            shelf_lib,
+            None,
+            None,
+            false,
+        )?;
+    }
+
+    if let Some((next_hold, _)) = holds::find_nearest_permitted_hold(editor, copy_id, true)? {
+        let pickup_lib = next_hold["pickup_lib"].int()?;
+
+        entry.dest_lib = Some(pickup_lib);
+        entry.action = if pickup_lib == shelf_lib {
+            ShelfAction::Hold
+        } else {
+            ShelfAction::Transit
+        };
+    } else {
+        let circ_lib = copy["circ_lib"].int()?;
+
+        if circ_lib != shelf_lib {
+            entry.action = ShelfAction::Transit;
+            entry.dest_lib = Some(circ_lib);
+        }
+    }
+
+    Ok(entry)
+}
@@ -0,0 +1,437 @@
+//! Spine and pocket label data, rendered as ZPL or PDF.
+use crate as eg;
+use eg::common::settings::Settings;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Characters per spine label line, unless set via cat.spine.line.width
+pub const DEFAULT_SPINE_WIDTH: usize = 8;
+/// Lines per spine label, unless set via cat.spine.line.height
+pub const DEFAULT_SPINE_LINES: usize = 9;
+/// Characters per pocket label line.
+pub const POCKET_WIDTH: usize = 28;
+
+/// asset.call_number_class for Library of Congress call numbers.
+const LC_LABEL_CLASS: i64 = 3;
+
+/// PDF text size and line spacing in points.
+const PDF_FONT_SIZE: f64 = 10.0;
+const PDF_LEADING: f64 = 11.0;
+/// Courier glyphs are 0.6em wide.
+const PDF_CHAR_WIDTH: f64 = PDF_FONT_SIZE * 0.6;
+const PDF_MARGIN: f64 = 9.0;
+
+/// ZPL dots per text line and per character, at 203 dpi.
+const ZPL_FONT_HEIGHT: usize = 24;
+const ZPL_FONT_WIDTH: usize = 14;
+const ZPL_MARGIN: usize = 20;
+
+static LC_CLASS_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Label layout settings for an org unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelConfig {
+    pub spine_width: usize,
+    pub spine_lines: usize,
+}
+
+impl Default for LabelConfig {
+    fn default() -> Self {
+        LabelConfig {
+            spine_width: DEFAULT_SPINE_WIDTH,
+            spine_lines: DEFAULT_SPINE_LINES,
+        }
+    }
+}
+
+impl LabelConfig {
+    /// Load the label settings which apply at an org unit.
+    pub fn for_org(editor: &mut Editor, org_id: i64) -> EgResult<Self> {
+        let mut settings = Settings::new(editor);
+
+        let spine_width = settings
+            .get_value_at_org("cat.spine.line.width", org_id)?
+            .as_usize()
+            .unwrap_or(DEFAULT_SPINE_WIDTH);
+
+        let spine_lines = settings
+            .get_value_at_org("cat.spine.line.height", org_id)?
+            .as_usize()
+            .unwrap_or(DEFAULT_SPINE_LINES);
+
+        Ok(LabelConfig {
+            spine_width,
+            spine_lines,
+        })
+    }
+}
+
+/// Printable label content for one copy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelData {
+    pub copy_id: i64,
+    pub barcode: String,
+    pub spine: Vec<String>,
+    pub pocket: Vec<String>,
+}
+
+impl LabelData {
+    pub fn to_eg_value(&self) -> EgValue {
+        eg::hash! {
+            "copy": self.copy_id,
+            "barcode": self.barcode.as_str(),
+            "spine": self.spine.clone(),
+            "pocket": self.pocket.clone(),
+        }
+    }
+}
+
+/// Split a call number into spine label lines.
+///
+/// The call number is split on whitespace.  LC call numbers are also
+/// split between the class letters and number and before each cutter.
+/// Lines wider than the spine are wrapped.  Lines beyond the spine
+/// line count are dropped.
+///
+/// ```
+/// use evergreen::common::labels::{self, LabelConfig};
+///
+/// let config = LabelConfig::default();
+///
+/// let lines = labels::spine_lines("QA76.73.R87 B58 2020", true, &config);
+/// assert_eq!(lines, ["QA", "76.73", ".R87", "B58", "2020"]);
+///
+/// let lines = labels::spine_lines("FIC SMITHERSON", false, &config);
+/// assert_eq!(lines, ["FIC", "SMITHERS", "ON"]);
+/// ```
+pub fn spine_lines(label: &str, is_lc: bool, config: &LabelConfig) -> Vec<String> {
+    let mut parts = Vec::new();
+
+    for (idx, token) in label.split_whitespace().enumerate() {
+        if is_lc {
+            parts.extend(split_lc(token, idx == 0));
+        } else {
+            parts.push(token.to_string());
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    for part in parts {
+        lines.extend(wrap(&part, config.spine_width));
+    }
+
+    lines.truncate(config.spine_lines);
+    lines
+}
+
+/// Split an LC token like "QA76.73.R87" into "QA", "76.73", ".R87".
+///
+/// Only the leading token contains the class letters and number.
+fn split_lc(token: &str, leading: bool) -> Vec<String> {
+    let regex =
+        LC_CLASS_REGEX.get_or_init(|| Regex::new(r"^([A-Za-z]{1,3})(\d+(?:\.\d+)?)(.*)$").unwrap());
+
+    let mut parts = Vec::new();
+
+    let captures = if leading { regex.captures(token) } else { None };
+
+    let rest = match captures {
+        Some(caps) => {
+            parts.push(caps[1].to_string());
+            parts.push(caps[2].to_string());
+            caps[3].to_string()
+        }
+        None => token.to_string(),
+    };
+
+    // Cutters start with a period followed by a letter.
+    let mut cutter = String::new();
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let starts_cutter = c == '.' && chars.peek().is_some_and(|n| n.is_alphabetic());
+
+        if starts_cutter && !cutter.is_empty() {
+            parts.push(cutter);
+            cutter = String::new();
+        }
+
+        cutter.push(c);
+    }
+
+    if !cutter.is_empty() {
+        parts.push(cutter);
+    }
+
+    parts
+}
+
+/// Break a string into lines of at most `width` characters.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(width.max(1))
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+/// Pocket label lines: call number, barcode, title, and author.
+fn pocket_lines(call_number: &str, barcode: &str, title: &str, author: &str) -> Vec<String> {
+    [call_number, barcode, title, author]
+        .iter()
+        .filter(|v| !v.is_empty())
+        .map(|v| v.chars().take(POCKET_WIDTH).collect())
+        .collect()
+}
+
+/// Label data for a set of copies, in the order requested.
+///
+/// Copies which do not exist or are deleted are skipped.
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::common::labels::{self, LabelConfig};
+///
+/// let client = eg::init().unwrap();
+/// let mut editor = eg::Editor::new(&client);
+///
+/// let config = LabelConfig::for_org(&mut editor, 4).unwrap();
+///
+/// for label in labels::label_data(&mut editor, &[1, 2, 3], &config).unwrap() {
+///     println!("{}", label.spine.join("\n"));
+/// }
+/// ```
+pub fn label_data(
+    editor: &mut Editor,
+    copy_ids: &[i64],
+    config: &LabelConfig,
+) -> EgResult<Vec<LabelData>> {
+    let query = eg::hash! {"id": copy_ids, "deleted": "f"};
+
+    let flesh = eg::hash! {
+        "flesh": 2,
+        "flesh_fields": {
+            "acp": ["call_number"],
+            "acn": ["prefix", "suffix"],
+        }
+    };
+
+    let copies = editor.search_with_ops("acp", query, flesh)?;
+
+    let mut labels = Vec::new();
+
+    for copy_id in copy_ids {
+        let Some(copy) = copies.iter().find(|c| c["id"].as_int() == Some(*copy_id)) else {
+            continue;
+        };
+
+        let acn = &copy["call_number"];
+
+        let call_number = [
+            acn["prefix"]["label"].as_str().unwrap_or(""),
+            acn["label"].as_str().unwrap_or(""),
+            acn["suffix"]["label"].as_str().unwrap_or(""),
+        ]
+        .iter()
+        .filter(|p| !p.is_empty())
+        .cloned()
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+        let is_lc = acn["label_class"].as_int() == Some(LC_LABEL_CLASS);
+
+        // Precat call numbers point to record -1, which has no summary.
+        let summary = editor.retrieve("rmsr", acn["record"].int()?)?;
+        let (title, author) = match summary.as_ref() {
+            Some(s) => (
+                s["title"].as_str().unwrap_or(""),
+                s["author"].as_str().unwrap_or(""),
+            ),
+            None => (
+                copy["dummy_title"].as_str().unwrap_or(""),
+                copy["dummy_author"].as_str().unwrap_or(""),
+            ),
+        };
+
+        let barcode = copy["barcode"].string()?;
+
+        labels.push(LabelData {
+            copy_id: *copy_id,
+            spine: spine_lines(&call_number, is_lc, config),
+            pocket: pocket_lines(&call_number, &barcode, title, author),
+            barcode,
+        });
+    }
+
+    Ok(labels)
+}
+
+/// Escape a ZPL field value for use with ^FH.
+fn zpl_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '^' | '~' | '_' => escaped += &format!("_{:02X}", c as u32),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Render labels as ZPL, one spine and pocket label pair per format.
+///
+/// ```
+/// use evergreen::common::labels::{self, LabelData};
+///
+/// let label = LabelData {
+///     copy_id: 1,
+///     barcode: "30000".to_string(),
+///     spine: vec!["FIC".to_string(), "SMITH".to_string()],
+///     pocket: vec!["FIC SMITH".to_string(), "30000".to_string()],
+/// };
+///
+/// let zpl = labels::to_zpl(&[label], 8);
+/// assert!(zpl.starts_with("^XA"));
+/// assert!(zpl.contains("^FDSMITH^FS"));
+/// ```
+pub fn to_zpl(labels: &[LabelData], spine_width: usize) -> String {
+    let mut zpl = String::new();
+    let pocket_x = ZPL_MARGIN * 2 + spine_width * ZPL_FONT_WIDTH;
+
+    for label in labels {
+        zpl += "^XA\n";
+
+        for (x, lines) in [(ZPL_MARGIN, &label.spine), (pocket_x, &label.pocket)] {
+            for (idx, line) in lines.iter().enumerate() {
+                zpl += &format!(
+                    "^FO{x},{}^A0N,{ZPL_FONT_HEIGHT},{ZPL_FONT_WIDTH}^FH^FD{}^FS\n",
+                    ZPL_MARGIN + idx * ZPL_FONT_HEIGHT,
+                    zpl_escape(line)
+                );
+            }
+        }
+
+        zpl += "^XZ\n";
+    }
+
+    zpl
+}
+
+/// Escape a PDF string literal, replacing characters outside of the
+/// printable ASCII range, which the standard Courier font lacks.
+fn pdf_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+
+    escaped
+}
+
+/// Render labels as a PDF with one page per spine and pocket label
+/// pair, sized to fit the labels.
+///
+/// ```
+/// use evergreen::common::labels::{self, LabelData};
+///
+/// let label = LabelData {
+///     copy_id: 1,
+///     barcode: "30000".to_string(),
+///     spine: vec!["FIC".to_string(), "SMITH".to_string()],
+///     pocket: vec!["FIC SMITH".to_string(), "30000".to_string()],
+/// };
+///
+/// let pdf = labels::to_pdf(&[label], 8);
+/// assert!(pdf.starts_with(b"%PDF-1.4"));
+/// assert!(pdf.ends_with(b"%%EOF\n"));
+/// ```
+pub fn to_pdf(labels: &[LabelData], spine_width: usize) -> Vec<u8> {
+    let spine_cols = (spine_width as f64) * PDF_CHAR_WIDTH;
+    let page_width = PDF_MARGIN * 3.0 + spine_cols + POCKET_WIDTH as f64 * PDF_CHAR_WIDTH;
+
+    // Object 1 is the catalog, 2 the page tree, 3 the font.  Each
+    // label adds a page object followed by its content stream.
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+
+    let mut kids = Vec::new();
+
+    for label in labels {
+        let lines = label.spine.len().max(label.pocket.len()).max(1);
+        let page_height = PDF_MARGIN * 2.0 + lines as f64 * PDF_LEADING;
+
+        let mut content = String::new();
+
+        for (x, lines) in [
+            (PDF_MARGIN, &label.spine),
+            (PDF_MARGIN * 2.0 + spine_cols, &label.pocket),
+        ] {
+            for (idx, line) in lines.iter().enumerate() {
+                let y = page_height - PDF_MARGIN - PDF_FONT_SIZE - idx as f64 * PDF_LEADING;
+                content += &format!(
+                    "BT /F1 {PDF_FONT_SIZE} Tf {x:.2} {y:.2} Td ({}) Tj ET\n",
+                    pdf_escape(line)
+                );
+            }
+        }
+
+        let page_id = objects.len() + 1;
+        kids.push(format!("{page_id} 0 R"));
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_width:.2} {page_height:.2}] \
+            /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_id + 1
+        ));
+
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ));
+    }
+
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        kids.len()
+    );
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+
+    for (idx, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf += &format!("{} 0 obj\n{object}\nendobj\n", idx + 1);
+    }
+
+    let xref_offset = pdf.len();
+
+    pdf += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        pdf += &format!("{offset:010} 00000 n \n");
+    }
+
+    pdf += &format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+        objects.len() + 1
+    );
+
+    pdf.into_bytes()
+}
@@ -1,21 +1,30 @@
 //! Shared, common utility functions
 
+pub mod acq;
+pub mod asset;
 pub mod auth;
+pub mod barcode;
 pub mod bib;
 pub mod billing;
 pub mod checkin;
 pub mod checkout;
 pub mod circ;
 pub mod circulator;
+pub mod course;
 pub mod holdings;
 pub mod holds;
+pub mod holdshelf;
 pub mod jq;
+pub mod labels;
 pub mod noncat;
 pub mod org;
 pub mod penalty;
 pub mod renew;
+pub mod search;
 pub mod settings;
 pub mod targeter;
+pub mod template;
 pub mod transit;
 pub mod trigger;
 pub mod user;
+pub mod workstation;
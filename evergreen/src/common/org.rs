@@ -92,8 +92,8 @@ pub fn next_open_date(
     let mut closed_days: Vec<i64> = Vec::new();
     if let Some(h) = editor.retrieve("aouhoo", org_id)? {
         for day in 0..7 {
-            let open = h[&format!("dow_{day}_open")].as_str().unwrap();
-            let close = h[&format!("dow_{day}_close")].as_str().unwrap();
+            let open = h.str_at(&format!("dow_{day}_open"))?;
+            let close = h.str_at(&format!("dow_{day}_close"))?;
             if open == "00:00:00" && close == open {
                 closed_days.push(day);
             }
@@ -145,9 +145,9 @@ pub fn next_open_date(
         }
 
         // Find the end of the closed date range and jump ahead to that.
-        let mut range_end = org_closed[0]["close_end"].as_str().unwrap();
+        let mut range_end = org_closed[0].str_at("close_end")?;
         for day in org_closed.iter() {
-            let end = day["close_end"].as_str().unwrap();
+            let end = day.str_at("close_end")?;
             if end > range_end {
                 range_end = end;
             }
@@ -6,6 +6,17 @@ use eg::editor::Editor;
 use eg::result::EgResult;
 use eg::EgValue;
 
+pub const PATRON_EXCEEDS_FINES: i64 = 1;
+pub const PATRON_EXCEEDS_OVERDUE_COUNT: i64 = 2;
+pub const PATRON_EXCEEDS_CHECKOUT_COUNT: i64 = 3;
+
+/// Penalties whose group thresholds limit what a patron may check out.
+pub const CHECKOUT_LIMIT_PENALTIES: &[i64] = &[
+    PATRON_EXCEEDS_FINES,
+    PATRON_EXCEEDS_OVERDUE_COUNT,
+    PATRON_EXCEEDS_CHECKOUT_COUNT,
+];
+
 // Shortcut for unckecked int conversions for values that are known good.
 // We coul compare EgValue's directly, but there's a chance a number may be
 // transferred as a JSON String, so turn them into numbers for conformity.
@@ -184,3 +195,78 @@ fn trim_to_wanted_penalties(
 
     Ok(final_penalties)
 }
+
+/// Checkout limits from the group penalty thresholds which apply to
+/// a permission group at an org unit.  None means no limit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatronLimits {
+    pub max_fines: Option<f64>,
+    pub max_overdue: Option<i64>,
+    pub max_items_out: Option<i64>,
+}
+
+/// Find the checkout limit thresholds for a permission group at a
+/// context org unit.
+///
+/// Like actor.calculate_system_penalties, thresholds defined for the
+/// nearest group ancestor win, then those for the nearest org unit
+/// ancestor.
+pub fn patron_limits(
+    editor: &mut Editor,
+    profile: i64,
+    context_org: i64,
+) -> EgResult<PatronLimits> {
+    let query = eg::hash! {"from": ["permission.grp_ancestors_distance", profile]};
+    let groups = editor.json_query(query)?;
+
+    let query = eg::hash! {"from": ["actor.org_unit_ancestors_distance", context_org]};
+    let orgs = editor.json_query(query)?;
+
+    let distance = |list: &Vec<EgValue>, id: i64| {
+        list.iter()
+            .find(|d| d["id"].int().ok() == Some(id))
+            .and_then(|d| d["distance"].as_i64())
+    };
+
+    let query = eg::hash! {
+        "grp": groups.iter().map(|g| g["id"].clone()).collect::<Vec<EgValue>>(),
+        "org_unit": orgs.iter().map(|o| o["id"].clone()).collect::<Vec<EgValue>>(),
+        "penalty": CHECKOUT_LIMIT_PENALTIES,
+    };
+
+    // Nearest (group, org unit) threshold for each penalty.
+    let mut nearest: Vec<(i64, (i64, i64), f64)> = Vec::new();
+
+    for pgpt in editor.search("pgpt", query)? {
+        let penalty = pgpt["penalty"].int()?;
+        let threshold = pgpt["threshold"].float()?;
+
+        let (Some(grp_dist), Some(org_dist)) = (
+            distance(&groups, pgpt["grp"].int()?),
+            distance(&orgs, pgpt["org_unit"].int()?),
+        ) else {
+            continue;
+        };
+
+        let dist = (grp_dist, org_dist);
+
+        match nearest.iter_mut().find(|(p, _, _)| *p == penalty) {
+            Some(entry) if dist < entry.1 => *entry = (penalty, dist, threshold),
+            Some(_) => {}
+            None => nearest.push((penalty, dist, threshold)),
+        }
+    }
+
+    let mut limits = PatronLimits::default();
+
+    for (penalty, _, threshold) in nearest {
+        match penalty {
+            PATRON_EXCEEDS_FINES => limits.max_fines = Some(threshold),
+            PATRON_EXCEEDS_OVERDUE_COUNT => limits.max_overdue = Some(threshold as i64),
+            PATRON_EXCEEDS_CHECKOUT_COUNT => limits.max_items_out = Some(threshold as i64),
+            _ => {}
+        }
+    }
+
+    Ok(limits)
+}
@@ -0,0 +1,420 @@
+//! Catalog search query building, relevance weighting, and facets.
+//!
+//! Builds the SQL used by open-ils.rs-search to match records against
+//! the metabib field entry tables, ranked by per-class weights.
+use crate as eg;
+use eg::common::settings::Settings;
+use eg::Editor;
+use eg::EgResult;
+use std::collections::HashMap;
+
+/// Search classes.  Each has a metabib.{class}_field_entry table and a
+/// text search configuration of the same name.
+pub const SEARCH_CLASSES: &[&str] = &[
+    "keyword",
+    "title",
+    "author",
+    "subject",
+    "series",
+    "identifier",
+];
+
+/// Relevance multiplier for classes with no weight setting.
+pub const DEFAULT_CLASS_WEIGHT: f64 = 1.0;
+
+/// Maximum number of values returned per facet field.
+pub const DEFAULT_FACET_LIMIT: i64 = 10;
+
+/// Maximum number of records returned per page of results.
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Only the best matching records are counted, paged, and faceted,
+/// like the superpage limits of the Perl search.
+pub const MAX_MATCHES: i64 = 10_000;
+
+/// Org unit setting prefix for per-class weights, e.g.
+/// search.class_weight.title
+const CLASS_WEIGHT_SETTING: &str = "search.class_weight";
+
+/// Maps a class name or QueryParser alias to its search class.
+fn search_class(name: &str) -> Option<&'static str> {
+    let class = match name.to_lowercase().as_str() {
+        "kw" | "keyword" => "keyword",
+        "ti" | "title" => "title",
+        "au" | "author" => "author",
+        "su" | "subject" => "subject",
+        "se" | "series" => "series",
+        "id" | "identifier" => "identifier",
+        _ => return None,
+    };
+    Some(class)
+}
+
+/// Lowercased words from a chunk of query text.
+///
+/// Anything that's not alphanumeric separates words, which also strips
+/// tsquery operators from user input.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Terms for a single search class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassQuery {
+    class: &'static str,
+    terms: Vec<String>,
+    negated: Vec<String>,
+}
+
+impl ClassQuery {
+    pub fn class(&self) -> &str {
+        self.class
+    }
+
+    /// to_tsquery() compatible query string.
+    pub fn tsquery(&self) -> String {
+        self.terms
+            .iter()
+            .chain(self.negated.iter())
+            .map(|t| t.as_str())
+            .collect::<Vec<&str>>()
+            .join(" & ")
+    }
+}
+
+/// Per-class relevance multipliers.
+#[derive(Debug, Clone, Default)]
+pub struct ClassWeights {
+    weights: HashMap<&'static str, f64>,
+}
+
+impl ClassWeights {
+    /// Load class weights from the search.class_weight.{class} org
+    /// unit settings for the provided org unit.
+    pub fn for_org(editor: &mut Editor, org_id: i64) -> EgResult<ClassWeights> {
+        let names: Vec<String> = SEARCH_CLASSES
+            .iter()
+            .map(|c| format!("{CLASS_WEIGHT_SETTING}.{c}"))
+            .collect();
+
+        let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+
+        let mut settings = Settings::new(editor);
+        settings.fetch_values_for_org(org_id, &names)?;
+
+        let mut weights = ClassWeights::default();
+
+        for (class, name) in SEARCH_CLASSES.iter().zip(names) {
+            if let Some(weight) = settings.get_value_at_org(name, org_id)?.as_f64() {
+                weights.set(class, weight);
+            }
+        }
+
+        Ok(weights)
+    }
+
+    /// Set the weight for a class.  Unknown classes are ignored.
+    pub fn set(&mut self, class: &str, weight: f64) {
+        if let Some(class) = search_class(class) {
+            self.weights.insert(class, weight);
+        }
+    }
+
+    pub fn weight(&self, class: &str) -> f64 {
+        self.weights
+            .get(class)
+            .copied()
+            .unwrap_or(DEFAULT_CLASS_WEIGHT)
+    }
+}
+
+/// A parsed catalog search.
+///
+/// ```
+/// use evergreen::common::search::SearchQuery;
+///
+/// let query = SearchQuery::parse("harry potter au:rowling -\"half blood\"").unwrap();
+///
+/// assert_eq!(query.classes().len(), 2);
+/// assert_eq!(query.classes()[0].class(), "keyword");
+/// assert_eq!(query.classes()[0].tsquery(), "harry & potter");
+/// assert_eq!(query.classes()[1].class(), "author");
+/// assert_eq!(query.classes()[1].tsquery(), "rowling & !(half <-> blood)");
+///
+/// assert!(SearchQuery::parse(" -potter ").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    classes: Vec<ClassQuery>,
+}
+
+impl SearchQuery {
+    /// Parse a QueryParser-style search string.
+    ///
+    /// Terms apply to the keyword class until a "class:" prefix (e.g.
+    /// "title:" or "au:") switches classes.  Quoted text is a phrase
+    /// and a leading "-" negates a term or phrase.
+    pub fn parse(query: &str) -> EgResult<SearchQuery> {
+        let chars: Vec<char> = query.chars().collect();
+        let mut search = SearchQuery {
+            classes: Vec::new(),
+        };
+
+        let mut class = "keyword";
+        let mut idx = 0;
+
+        while idx < chars.len() {
+            if chars[idx].is_whitespace() {
+                idx += 1;
+                continue;
+            }
+
+            let negate = chars[idx] == '-';
+            if negate {
+                idx += 1;
+            }
+
+            if idx < chars.len() && chars[idx] == '"' {
+                let start = idx + 1;
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .map(|p| start + p)
+                    .unwrap_or(chars.len());
+
+                let phrase: String = chars[start..end].iter().collect();
+                search.add_term(class, words(&phrase), true, negate);
+
+                idx = end + 1;
+                continue;
+            }
+
+            let start = idx;
+            while idx < chars.len() && !chars[idx].is_whitespace() && chars[idx] != '"' {
+                idx += 1;
+            }
+
+            let token: String = chars[start..idx].iter().collect();
+
+            if !negate {
+                if let Some((prefix, _)) = token.split_once(':') {
+                    if let Some(c) = search_class(prefix) {
+                        // Pick up any term following the prefix on the
+                        // next pass, since it may be a quoted phrase.
+                        class = c;
+                        idx = start + prefix.chars().count() + 1;
+                        continue;
+                    }
+                }
+            }
+
+            search.add_term(class, words(&token), false, negate);
+        }
+
+        if search.classes.is_empty() {
+            return Err(format!("Search query has no search terms: {query}").into());
+        }
+
+        if let Some(cq) = search.classes.iter().find(|c| c.terms.is_empty()) {
+            return Err(format!("Search class {} has only negated terms", cq.class).into());
+        }
+
+        Ok(search)
+    }
+
+    fn add_term(&mut self, class: &'static str, words: Vec<String>, phrase: bool, negate: bool) {
+        if words.is_empty() {
+            return;
+        }
+
+        let mut term = words.join(if phrase { " <-> " } else { " & " });
+
+        if words.len() > 1 {
+            term = format!("({term})");
+        }
+
+        let pos = match self.classes.iter().position(|c| c.class == class) {
+            Some(p) => p,
+            None => {
+                self.classes.push(ClassQuery {
+                    class,
+                    terms: Vec::new(),
+                    negated: Vec::new(),
+                });
+                self.classes.len() - 1
+            }
+        };
+
+        if negate {
+            self.classes[pos].negated.push(format!("!{term}"));
+        } else {
+            self.classes[pos].terms.push(term);
+        }
+    }
+
+    pub fn classes(&self) -> &[ClassQuery] {
+        &self.classes
+    }
+
+    /// SQL selecting the "id" and weighted relevance "rel" of every
+    /// matching record, along with its query parameters.
+    ///
+    /// With an org unit, only records with copies at the org unit or
+    /// its descendants match.
+    pub fn matches_sql(
+        &self,
+        weights: &ClassWeights,
+        org_id: Option<i64>,
+    ) -> (String, Vec<String>) {
+        let mut ctes = Vec::new();
+        let mut joins = String::new();
+        let mut ranks = Vec::new();
+        let mut params = Vec::new();
+
+        for (idx, cq) in self.classes.iter().enumerate() {
+            params.push(cq.tsquery());
+            let param = params.len();
+            let class = cq.class;
+
+            ctes.push(format!(
+                "c{idx} AS (
+                    SELECT source,
+                        MAX(ts_rank_cd(index_vector, to_tsquery('{class}', ${param}))) AS rank
+                    FROM metabib.{class}_field_entry
+                    WHERE index_vector @@ to_tsquery('{class}', ${param})
+                    GROUP BY source
+                )"
+            ));
+
+            joins += &format!(" JOIN c{idx} ON (c{idx}.source = bre.id)");
+
+            ranks.push(format!(
+                "c{idx}.rank::FLOAT8 * {}::FLOAT8",
+                weights.weight(class)
+            ));
+        }
+
+        let mut sql = format!(
+            "WITH {} SELECT bre.id, ({}) AS rel FROM biblio.record_entry bre{joins}
+            WHERE NOT bre.deleted",
+            ctes.join(", "),
+            ranks.join(" + "),
+        );
+
+        if let Some(org_id) = org_id {
+            sql += &format!(
+                " AND EXISTS (
+                    SELECT 1 FROM asset.call_number acn
+                        JOIN asset.copy acp ON (acp.call_number = acn.id)
+                    WHERE acn.record = bre.id
+                        AND NOT acn.deleted
+                        AND NOT acp.deleted
+                        AND acp.circ_lib IN (
+                            SELECT id FROM actor.org_unit_descendants({org_id})
+                        )
+                )"
+            );
+        }
+
+        (sql, params)
+    }
+
+    /// SQL selecting the best MAX_MATCHES matching records.
+    fn top_matches_sql(
+        &self,
+        weights: &ClassWeights,
+        org_id: Option<i64>,
+    ) -> (String, Vec<String>) {
+        let (matches, params) = self.matches_sql(weights, org_id);
+
+        let sql = format!(
+            "SELECT id, rel FROM ({matches}) all_matches
+            ORDER BY rel DESC, id
+            LIMIT {MAX_MATCHES}"
+        );
+
+        (sql, params)
+    }
+
+    /// SQL selecting one page of matching records, best match first,
+    /// with the total number of matches in the "count" column.
+    ///
+    /// The limit is capped at MAX_LIMIT.  A page past the end of the
+    /// results is a single row with a NULL "id" and "rel" so the
+    /// count is still reported.
+    ///
+    /// ```
+    /// use evergreen::common::search::{ClassWeights, SearchQuery};
+    ///
+    /// let query = SearchQuery::parse("potter").unwrap();
+    /// let (sql, params) = query.results_sql(&ClassWeights::default(), None, 1_000_000, -5);
+    ///
+    /// assert!(sql.contains("LIMIT 1000 OFFSET 0"));
+    /// assert_eq!(params, vec!["potter".to_string()]);
+    /// ```
+    pub fn results_sql(
+        &self,
+        weights: &ClassWeights,
+        org_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> (String, Vec<String>) {
+        let (matches, params) = self.top_matches_sql(weights, org_id);
+
+        let limit = limit.clamp(0, MAX_LIMIT);
+        let offset = offset.max(0);
+
+        let sql = format!(
+            "WITH matches AS ({matches}),
+                total AS (SELECT COUNT(*) AS count FROM matches)
+            SELECT page.id, page.rel, total.count
+            FROM total
+                LEFT JOIN (
+                    SELECT id, rel FROM matches
+                    ORDER BY rel DESC, id
+                    LIMIT {limit} OFFSET {offset}
+                ) page ON TRUE
+            ORDER BY page.rel DESC, page.id"
+        );
+
+        (sql, params)
+    }
+
+    /// SQL selecting facet values across the top matching records, up to
+    /// facet_limit values per facet field, most common values first.
+    ///
+    /// Columns are "field" (config.metabib_field ID), "value", and
+    /// "count", the number of matching records with the value.
+    pub fn facets_sql(
+        &self,
+        weights: &ClassWeights,
+        org_id: Option<i64>,
+        facet_limit: i64,
+    ) -> (String, Vec<String>) {
+        let (matches, params) = self.top_matches_sql(weights, org_id);
+
+        let sql = format!(
+            "WITH matches AS ({matches})
+            SELECT field, value, count FROM (
+                SELECT mfe.field, mfe.value,
+                    COUNT(DISTINCT mfe.source) AS count,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY mfe.field
+                        ORDER BY COUNT(DISTINCT mfe.source) DESC, mfe.value
+                    ) AS pos
+                FROM metabib.facet_entry mfe
+                    JOIN config.metabib_field cmf ON (cmf.id = mfe.field)
+                WHERE cmf.facet_field
+                    AND mfe.source IN (SELECT id FROM matches)
+                GROUP BY mfe.field, mfe.value
+            ) facets
+            WHERE pos <= {facet_limit}
+            ORDER BY field, count DESC, value"
+        );
+
+        (sql, params)
+    }
+}
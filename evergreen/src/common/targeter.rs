@@ -4,7 +4,7 @@ use eg::common::settings::Settings;
 use eg::common::trigger;
 use eg::constants as C;
 use eg::date;
-use eg::{Editor, EgError, EgResult, EgValue};
+use eg::{Editor, EgResult, EgValue};
 use rand;
 use rand::seq::SliceRandom;
 use std::collections::{HashMap, HashSet};
@@ -515,7 +515,7 @@ impl<'a> HoldTargeter<'a> {
         let hold = &context.hold;
 
         let hold_target = hold["target"].int()?;
-        let hold_type = hold["hold_type"].as_str().unwrap(); // required.
+        let hold_type = hold.str_at("hold_type")?; // required.
         let org_unit = hold["selection_ou"].int()?;
         let org_depth = hold["selection_depth"].as_int().unwrap_or(0); // not required
 
@@ -868,8 +868,8 @@ impl<'a> HoldTargeter<'a> {
 
         log::info!("{self} recalling circ {}", circ["id"]);
 
-        let old_due_date = date::parse_datetime(circ["due_date"].as_str().unwrap())?;
-        let xact_start_date = date::parse_datetime(circ["xact_start"].as_str().unwrap())?;
+        let old_due_date = date::parse_datetime(circ.str_at("due_date")?)?;
+        let xact_start_date = date::parse_datetime(circ.str_at("xact_start")?)?;
 
         let thresh_date = date::add_interval(xact_start_date, &recall_threshold)?;
         let mut return_date = date::add_interval(date::now(), &return_interval)?;
@@ -1248,7 +1248,7 @@ impl<'a> HoldTargeter<'a> {
         };
 
         // Required, string field
-        let req_time = context.hold["request_time"].as_str().unwrap();
+        let req_time = context.hold.str_at("request_time")?;
         let req_time = date::parse_datetime(&req_time)?;
 
         let hard_stall_time = date::add_interval(req_time, interval)?;
@@ -1556,12 +1556,13 @@ impl<'a> HoldTargeter<'a> {
         // If the caller only provides an error message and the
         // editor has a last-event, return the editor's last event
         // with the message added.
-        if let EgError::Debug(ref msg) = err {
+        if err.event().is_none() {
+            let msg = err.message();
             log::error!("{self} exited early with error message {msg}");
 
             if let Some(mut evt) = self.editor().take_last_event() {
-                evt.set_debug(msg);
-                return Err(EgError::Event(evt));
+                evt.set_debug(&msg);
+                return Err(evt.into());
             }
         }
 
@@ -0,0 +1,289 @@
+//! Render receipts and slips from Tera templates.
+//!
+//! Templates receive their data as a context object and may use these
+//! Evergreen-aware filters in addition to the Tera built-ins:
+//!
+//! * `money` -- Format a number as a currency amount.
+//!   `{{ amount | money }}` => "$1.50".  Accepts `symbol` and `decimals`.
+//! * `date` -- Format an ISO date string.
+//!   `{{ due_date | date(format="%m/%d/%Y") }}`.  Accepts `format` and
+//!   `timezone`.
+//! * `org_name` -- Replace an org unit ID with the org unit's name, or
+//!   its shortname with `org_name(short=true)`.
+use crate as eg;
+use eg::date;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+use tera::{Context, Tera, Value};
+
+pub const CHECKOUT_RECEIPT: &str = "checkout_receipt";
+pub const HOLD_SLIP: &str = "hold_slip";
+pub const TRANSIT_SLIP: &str = "transit_slip";
+
+const DEFAULT_MONEY_SYMBOL: &str = "$";
+const DEFAULT_MONEY_DECIMALS: usize = 2;
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Context: library, patron{name, barcode}, items[{title, barcode, due_date}]
+const CHECKOUT_RECEIPT_TEMPLATE: &str = r#"{{ library | org_name }}
+{{ patron.name }}
+{% for item in items %}
+{{ loop.index }}. {{ item.title }}
+   Barcode: {{ item.barcode }}
+   Due: {{ item.due_date | date }}
+{% endfor %}
+{{ now() | date(format="%Y-%m-%d %H:%M") }}
+"#;
+
+/// Context: pickup_lib, patron{name, barcode}, item{title, barcode,
+/// call_number}, shelf_expire_time
+const HOLD_SLIP_TEMPLATE: &str = r#"HOLD FOR {{ patron.name | upper }}
+{{ patron.barcode }}
+
+{{ item.title }}
+{{ item.call_number | default(value="") }}
+{{ item.barcode }}
+
+Pickup: {{ pickup_lib | org_name }}
+{% if shelf_expire_time %}Hold until {{ shelf_expire_time | date }}
+{% endif %}"#;
+
+/// Context: source, dest, item{title, barcode, call_number}, transit_time
+const TRANSIT_SLIP_TEMPLATE: &str = r#"TRANSIT TO {{ dest | org_name(short=true) }}
+{{ dest | org_name }}
+
+{{ item.title }}
+{{ item.call_number | default(value="") }}
+{{ item.barcode }}
+
+From: {{ source | org_name(short=true) }}
+Sent: {{ transit_time | date }}
+"#;
+
+const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    (CHECKOUT_RECEIPT, CHECKOUT_RECEIPT_TEMPLATE),
+    (HOLD_SLIP, HOLD_SLIP_TEMPLATE),
+    (TRANSIT_SLIP, TRANSIT_SLIP_TEMPLATE),
+];
+
+/// Org unit (name, shortname) keyed on org unit ID.
+type OrgNames = HashMap<i64, (String, String)>;
+
+/// Org unit names, loaded on first use and shared by all renderers.
+static ORG_NAMES: OnceLock<Arc<OrgNames>> = OnceLock::new();
+
+/// Org unit names, loading them from the database on first use.
+fn org_names(editor: &mut Editor) -> EgResult<Arc<OrgNames>> {
+    if let Some(names) = ORG_NAMES.get() {
+        return Ok(names.clone());
+    }
+
+    let mut names = OrgNames::new();
+
+    for org in editor.search("aou", eg::hash! {"id": {"!=": eg::NULL}})? {
+        names.insert(
+            org.id()?,
+            (org["name"].string()?, org["shortname"].string()?),
+        );
+    }
+
+    // Another thread may have beaten us to it, which is fine.
+    ORG_NAMES.set(Arc::new(names)).ok();
+
+    Ok(ORG_NAMES.get().cloned().unwrap_or_default())
+}
+
+fn filter_arg<'a>(args: &'a HashMap<String, Value>, name: &str) -> Option<&'a Value> {
+    args.get(name).filter(|v| !v.is_null())
+}
+
+fn money_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let amount = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("money filter requires a number: {value}"))?;
+
+    let symbol = filter_arg(args, "symbol")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_MONEY_SYMBOL);
+
+    let decimals = filter_arg(args, "decimals")
+        .and_then(|v| v.as_u64())
+        .map(|d| d as usize)
+        .unwrap_or(DEFAULT_MONEY_DECIMALS);
+
+    let sign = if amount < 0.0 { "-" } else { "" };
+    let amount = amount.abs();
+
+    Ok(Value::String(format!("{sign}{symbol}{amount:.decimals$}")))
+}
+
+fn date_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let Some(text) = value.as_str() else {
+        // Nothing to format.  Let null dates render as empty strings.
+        return Ok(Value::String(String::new()));
+    };
+
+    let mut dt = date::parse_datetime(text).map_err(|e| e.to_string())?;
+
+    if let Some(tz) = filter_arg(args, "timezone").and_then(|v| v.as_str()) {
+        dt = date::set_timezone(dt, tz).map_err(|e| e.to_string())?;
+    }
+
+    let format = filter_arg(args, "format")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_DATE_FORMAT);
+
+    // Invalid strftime patterns produce a formatting error, which
+    // would cause to_string() to panic.
+    let mut formatted = String::new();
+    write!(formatted, "{}", dt.format(format))
+        .map_err(|_| format!("Invalid date format: {format}"))?;
+
+    Ok(Value::String(formatted))
+}
+
+fn now_function(_args: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(date::to_iso(&date::now())))
+}
+
+/// Renders named templates with Evergreen-aware helpers.
+///
+/// ```
+/// use evergreen as eg;
+/// use eg::common::template::Renderer;
+///
+/// let mut renderer = Renderer::new().unwrap();
+/// renderer.add_template("fine", "Owed: {{ amount | money }}").unwrap();
+///
+/// let text = renderer.render("fine", &eg::hash! {"amount": 1.5}).unwrap();
+/// assert_eq!(text, "Owed: $1.50");
+///
+/// let text = renderer
+///     .render_str(
+///         "{{ due | date(format='%d/%m/%Y') }} @ {{ lib | org_name }}",
+///         &eg::hash! {"due": "2024-03-01T12:00:00-0500", "lib": 4},
+///     )
+///     .unwrap();
+///
+/// // Without org unit data, org units display as their IDs.
+/// assert_eq!(text, "01/03/2024 @ 4");
+/// ```
+pub struct Renderer {
+    tera: Tera,
+}
+
+impl Renderer {
+    /// Renderer with the stock templates whose org_name filter
+    /// displays org unit IDs as-is.
+    pub fn new() -> EgResult<Self> {
+        Renderer::with_orgs(Arc::new(OrgNames::new()))
+    }
+
+    /// Renderer with the stock templates whose org_name filter
+    /// resolves org units from the database.
+    ///
+    /// Org unit names are loaded once per process.
+    pub fn with_org_names(editor: &mut Editor) -> EgResult<Self> {
+        Renderer::with_orgs(org_names(editor)?)
+    }
+
+    fn with_orgs(names: Arc<OrgNames>) -> EgResult<Self> {
+        let mut tera = Tera::default();
+
+        // Receipts and slips are plain text.
+        tera.autoescape_on(Vec::new());
+
+        tera.register_filter("money", money_filter);
+        tera.register_filter("date", date_filter);
+        tera.register_function("now", now_function);
+
+        tera.register_filter(
+            "org_name",
+            move |value: &Value, args: &HashMap<String, Value>| {
+                let Some(id) = value
+                    .as_i64()
+                    .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+                else {
+                    return Ok(value.clone());
+                };
+
+                let short = filter_arg(args, "short")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                Ok(match names.get(&id) {
+                    Some((name, sn)) => Value::String(if short { sn } else { name }.to_string()),
+                    None => value.clone(),
+                })
+            },
+        );
+
+        let mut renderer = Renderer { tera };
+
+        for (name, body) in DEFAULT_TEMPLATES {
+            renderer.add_template(name, body)?;
+        }
+
+        Ok(renderer)
+    }
+
+    /// Add a template, replacing any existing template by the same name.
+    pub fn add_template(&mut self, name: &str, body: &str) -> EgResult<()> {
+        self.tera
+            .add_raw_template(name, body)
+            .map_err(|e| format!("Invalid template {name}: {}", error_text(&e)).into())
+    }
+
+    pub fn has_template(&self, name: &str) -> bool {
+        self.tera.get_template_names().any(|n| n == name)
+    }
+
+    /// Render a named template.
+    pub fn render(&self, name: &str, context: &EgValue) -> EgResult<String> {
+        self.tera
+            .render(name, &to_context(context)?)
+            .map_err(|e| format!("Cannot render template {name}: {}", error_text(&e)).into())
+    }
+
+    /// Render a one-off template.
+    pub fn render_str(&mut self, body: &str, context: &EgValue) -> EgResult<String> {
+        self.tera
+            .render_str(body, &to_context(context)?)
+            .map_err(|e| format!("Cannot render template: {}", error_text(&e)).into())
+    }
+}
+
+/// Tera errors wrap their root cause, which is usually the part
+/// worth reporting.
+fn error_text(err: &tera::Error) -> String {
+    let mut text = err.to_string();
+    let mut source = std::error::Error::source(err);
+
+    while let Some(e) = source {
+        text += &format!(": {e}");
+        source = e.source();
+    }
+
+    text
+}
+
+/// Translate an object into a template context.
+///
+/// IDL objects are translated to plain hashes keyed on field name.
+fn to_context(value: &EgValue) -> EgResult<Context> {
+    let mut value = value.clone();
+    value.to_classed_hash();
+
+    let json: serde_json::Value = serde_json::from_str(&value.dump())
+        .map_err(|e| format!("Cannot translate template context: {e}"))?;
+
+    Context::from_value(json)
+        .map_err(|e| format!("Template context must be an object: {}", error_text(&e)).into())
+}
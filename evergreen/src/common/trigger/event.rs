@@ -82,7 +82,7 @@ impl Event {
     /// Create an Event from an un-fleshed "atev" object.
     pub fn from_source(source: EgValue) -> EgResult<Event> {
         // required field w/ limited set of values
-        let state: EventState = source["state"].as_str().unwrap().try_into()?;
+        let state: EventState = source.str_at("state")?.try_into()?;
 
         let id = source.id()?;
         let event_def = source["event_def"].int()?;
@@ -9,6 +9,7 @@ use eg::EgValue;
 
 pub mod event;
 pub use event::{Event, EventState};
+pub mod plugin;
 pub mod processor;
 pub use processor::Processor;
 mod reactor;
@@ -36,7 +37,7 @@ pub fn create_events_for_object(
         .classname()
         .ok_or_else(|| format!("Invalid target: {target}"))?;
 
-    if hook_obj["core_type"].as_str().unwrap() != class {
+    if hook_obj.str_at("core_type")? != class {
         // "key" is required.
         log::warn!("A/T hook {hook} does not match object core type: {class}");
         return Ok(());
@@ -235,7 +236,7 @@ pub fn create_passive_events_for_def(
 
     // Determine the date range of the items we want to target.
 
-    let def_delay = event_def["delay"].as_str().unwrap(); // required
+    let def_delay = event_def.str_at("delay")?; // required
     let delay_dt = date::add_interval(date::now(), def_delay)?;
 
     let delay_filter;
@@ -269,8 +270,8 @@ pub fn create_passive_events_for_def(
 
     // Make sure we don't create events that are already represented.
 
-    let core_type = event_def["hook"]["core_type"].as_str().unwrap(); // required
-                                                                      //let idl_class = idl::get_class(core_type)?.clone();
+    let core_type = event_def.str_at("hook.core_type")?; // required
+                                                         //let idl_class = idl::get_class(core_type)?.clone();
     let idl_class = idl::get_class(core_type)?;
 
     let pkey_field = idl_class
@@ -0,0 +1,129 @@
+//! Site-specific A/T reactors and validators.
+//!
+//! Reactors and validators registered here are used for event
+//! definitions whose reactor or validator name does not match one of
+//! the built-in modules.  Register plugins at startup, before any
+//! events are processed.
+//!
+//! ```
+//! use evergreen as eg;
+//! use eg::common::trigger::{plugin, Event, Processor};
+//! use eg::EgResult;
+//!
+//! struct LogPatron;
+//!
+//! impl plugin::Reactor for LogPatron {
+//!     fn react(&self, processor: &mut Processor, events: &mut [&mut Event]) -> EgResult<()> {
+//!         for event in events.iter() {
+//!             log::info!("{processor} notifying patron for {event}");
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! plugin::register_reactor("MySite::LogPatron", LogPatron);
+//!
+//! // Closures work too.
+//! plugin::register_validator("MySite::HasEmail", |_: &mut Processor, event: &Event| {
+//!     Ok(event.target()["usr"]["email"].is_string())
+//! });
+//!
+//! assert!(plugin::reactor("MySite::LogPatron").is_some());
+//! assert!(plugin::validator("MySite::HasEmail").is_some());
+//! ```
+use crate as eg;
+use eg::common::trigger::{Event, Processor};
+use eg::EgResult;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+static REACTORS: OnceLock<Mutex<HashMap<String, Arc<dyn Reactor>>>> = OnceLock::new();
+static VALIDATORS: OnceLock<Mutex<HashMap<String, Arc<dyn Validator>>>> = OnceLock::new();
+
+/// Custom A/T reactor.
+///
+/// Event states are managed by the Processor.  A reactor which
+/// returns Ok leaves its events in the "reacted" state.
+pub trait Reactor: Send + Sync {
+    /// React to one or more events.  Multiple events implies a
+    /// linked event group.
+    fn react(&self, processor: &mut Processor, events: &mut [&mut Event]) -> EgResult<()>;
+}
+
+/// Custom A/T validator.
+pub trait Validator: Send + Sync {
+    /// True if the event is still valid and should proceed to reacting.
+    fn validate(&self, processor: &mut Processor, event: &Event) -> EgResult<bool>;
+}
+
+impl<F> Reactor for F
+where
+    F: Fn(&mut Processor, &mut [&mut Event]) -> EgResult<()> + Send + Sync,
+{
+    fn react(&self, processor: &mut Processor, events: &mut [&mut Event]) -> EgResult<()> {
+        self(processor, events)
+    }
+}
+
+impl<F> Validator for F
+where
+    F: Fn(&mut Processor, &Event) -> EgResult<bool> + Send + Sync,
+{
+    fn validate(&self, processor: &mut Processor, event: &Event) -> EgResult<bool> {
+        self(processor, event)
+    }
+}
+
+fn reactors() -> &'static Mutex<HashMap<String, Arc<dyn Reactor>>> {
+    REACTORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn validators() -> &'static Mutex<HashMap<String, Arc<dyn Validator>>> {
+    VALIDATORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Register a reactor by name, replacing any plugin of the same name.
+///
+/// Names of built-in reactors (e.g. "Circ::AutoRenew") cannot be
+/// overridden.
+pub fn register_reactor(name: &str, reactor: impl Reactor + 'static) {
+    log::info!("Registering A/T reactor plugin {name}");
+
+    // A poisoned lock means a panic while inserting, which
+    // leaves the map itself intact.
+    reactors()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), Arc::new(reactor));
+}
+
+/// Register a validator by name, replacing any plugin of the same name.
+///
+/// Names of built-in validators (e.g. "CircIsOverdue") cannot be
+/// overridden.
+pub fn register_validator(name: &str, validator: impl Validator + 'static) {
+    log::info!("Registering A/T validator plugin {name}");
+
+    validators()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), Arc::new(validator));
+}
+
+/// Registered reactor plugin by name.
+pub fn reactor(name: &str) -> Option<Arc<dyn Reactor>> {
+    reactors()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
+
+/// Registered validator plugin by name.
+pub fn validator(name: &str) -> Option<Arc<dyn Validator>> {
+    validators()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
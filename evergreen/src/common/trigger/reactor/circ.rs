@@ -88,7 +88,7 @@ impl Processor<'_> {
 
         let success = eg_evt.is_success();
         if success && new_circ.is_object() {
-            new_due_date = new_circ["due_date"].as_str().unwrap(); // required
+            new_due_date = new_circ.str_at("due_date")?; // required
             total_remaining = new_circ["renewal_remaining"].int()?;
 
             // nullable / maybe a string
@@ -98,7 +98,7 @@ impl Processor<'_> {
                 0
             };
         } else {
-            old_due_date = source_circ["due_date"].as_str().unwrap(); // required
+            old_due_date = source_circ.str_at("due_date")?; // required
             total_remaining = source_circ["renewal_remaining"].int()?;
             fail_reason = eg_evt.desc().unwrap_or("");
 
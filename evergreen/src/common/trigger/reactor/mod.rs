@@ -1,5 +1,5 @@
 //! Base module for A/T Reactors
-use crate::common::trigger::{Event, EventState, Processor};
+use crate::common::trigger::{plugin, Event, EventState, Processor};
 use crate::result::EgResult;
 
 mod circ;
@@ -37,7 +37,10 @@ impl Processor<'_> {
             "NOOP_True" => Ok(()),
             "NOOP_False" => Err(format!("NOOP_False").into()),
             "Circ::AutoRenew" => self.autorenew(events),
-            _ => Err(format!("No such reactor: {reactor}").into()),
+            _ => match plugin::reactor(reactor) {
+                Some(plugin) => plugin.react(self, events),
+                None => Err(format!("No such reactor: {reactor}").into()),
+            },
         };
 
         if react_result.is_ok() {
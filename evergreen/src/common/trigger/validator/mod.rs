@@ -1,7 +1,7 @@
 //! Base module for A/T Validators
 use crate as eg;
 use eg::common::holdings;
-use eg::common::trigger::{Event, EventState, Processor};
+use eg::common::trigger::{plugin, Event, EventState, Processor};
 use eg::constants as C;
 use eg::date;
 use eg::EgResult;
@@ -13,8 +13,8 @@ impl Processor<'_> {
     /// TODO stacked validators.
     ///
     /// Loading modules dynamically is not as simple in Rust as in Perl.
-    /// Hard-code a module-mapping instead. (*shrug* They all require
-    /// code changes).
+    /// Hard-code a module-mapping instead, falling back to validators
+    /// registered via trigger::plugin.
     pub fn validate(&mut self, event: &mut Event) -> EgResult<bool> {
         log::info!("{self} validating {event}");
 
@@ -34,7 +34,10 @@ impl Processor<'_> {
             "PatronBarred" => self.patron_is_barred(event),
             "PatronNotBarred" => self.patron_is_barred(event).map(|val| !val),
             "ReservationIsAvailable" => self.reservation_is_available(event),
-            _ => Err(format!("No such validator: {validator}").into()),
+            _ => match plugin::validator(validator) {
+                Some(plugin) => plugin.validate(self, event),
+                None => Err(format!("No such validator: {validator}").into()),
+            },
         };
 
         if let Ok(valid) = validate_result {
@@ -113,7 +116,7 @@ impl Processor<'_> {
         }
 
         // due_date is a required string field.
-        let due_date = event.target()["due_date"].as_str().unwrap();
+        let due_date = event.target().str_at("due_date")?;
         let due_date_ts = date::parse_datetime(due_date)?;
 
         Ok(due_date_ts < date::now())
@@ -76,6 +76,25 @@ pub fn verify_password(
     }
 }
 
+/// Replace the user's main password.
+///
+/// The password is provided in plain text and salted and hashed by
+/// the database.
+pub fn change_password(e: &mut Editor, user_id: i64, password: &str) -> EgResult<()> {
+    let query = eg::hash! {
+        from: [
+            "actor.change_password",
+            user_id,
+            password,
+            PW_TYPE_MAIN
+        ]
+    };
+
+    e.json_query_sensitive(query)?;
+
+    Ok(())
+}
+
 /// Returns a list of all org unit IDs where the provided user has
 /// the provided work permission.
 pub fn has_work_perm_at(e: &mut Editor, user_id: i64, perm: &str) -> EgResult<Vec<i64>> {
@@ -163,3 +182,87 @@ pub fn active_hold_counts(e: &mut Editor, user_id: i64) -> EgResult<EgValue> {
 
     Ok(eg::hash! {total: total, ready: ready})
 }
+
+/// Controls how the cards and addresses of merged users are handled.
+///
+/// By default, cards and addresses are moved to the lead user.
+#[derive(Debug, Default, Clone)]
+pub struct MergeOptions {
+    /// Delete the merged users' addresses instead of moving them.
+    pub delete_addresses: bool,
+    /// Delete the merged users' cards instead of moving them.
+    pub delete_cards: bool,
+    /// Deactivate the merged users' cards as they are moved.
+    pub deactivate_cards: bool,
+}
+
+/// Merge one or more users into a lead user.
+///
+/// Circulations, holds, billings, notes, etc. are moved from each
+/// merged user to the lead user via actor.usr_merge(), which also
+/// removes the merged users.  A private note recording the merge
+/// is added to the lead user.
+///
+/// Requires an active transaction.  No permission checks are performed.
+pub fn merge_users(
+    e: &mut Editor,
+    lead_id: i64,
+    user_ids: &[i64],
+    options: &MergeOptions,
+) -> EgResult<()> {
+    if e.retrieve("au", lead_id)?.is_none() {
+        return Err(format!("No such lead user: {lead_id}").into());
+    }
+
+    let mut merged = Vec::new();
+
+    for &user_id in user_ids {
+        if user_id == lead_id {
+            return Err(format!("Cannot merge user {user_id} into itself").into());
+        }
+
+        let flesh = eg::hash! {"flesh": 1, "flesh_fields": {"au": ["card"]}};
+
+        let user = e
+            .retrieve_with_ops("au", user_id, flesh)?
+            .ok_or_else(|| format!("No such user: {user_id}"))?;
+
+        let barcode = user["card"]["barcode"].as_str().unwrap_or("");
+
+        log::info!(
+            "Merging user {user_id} ({barcode}) into user {lead_id} as requestor {}",
+            e.requestor_id()?
+        );
+
+        let query = eg::hash! {
+            "from": [
+                "actor.usr_merge",
+                user_id,
+                lead_id,
+                options.delete_addresses,
+                options.delete_cards,
+                options.deactivate_cards,
+            ]
+        };
+
+        e.json_query(query)?;
+
+        merged.push(format!("{user_id} ({barcode})"));
+    }
+
+    if merged.is_empty() {
+        return Ok(());
+    }
+
+    let note = eg::hash! {
+        "usr": lead_id,
+        "creator": e.requestor_id()?,
+        "pub": false,
+        "title": "Merged Users",
+        "value": format!("Merged users: {}", merged.join(", ")),
+    };
+
+    e.create(EgValue::create("aun", note)?)?;
+
+    Ok(())
+}
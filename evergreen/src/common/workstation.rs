@@ -0,0 +1,51 @@
+//! Workstation lookup and registration.
+use crate as eg;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+
+/// Find a workstation by name.
+pub fn by_name(editor: &mut Editor, name: &str) -> EgResult<Option<EgValue>> {
+    Ok(editor.search("aws", eg::hash! {"name": name})?.pop())
+}
+
+/// Find the workstation with the provided name, registering it at
+/// the provided org unit if it does not yet exist.
+///
+/// An existing workstation is returned as-is, even if it is owned by
+/// a different org unit.
+///
+/// Uses an externally managed Editor transaction.
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::common::workstation;
+///
+/// let client = eg::init().unwrap();
+/// let mut editor = eg::Editor::new(&client);
+///
+/// editor.xact_begin().unwrap();
+///
+/// let ws = workstation::find_or_register(&mut editor, "BR1-sip-kiosk1", 4).unwrap();
+///
+/// editor.commit().unwrap();
+///
+/// println!("Using workstation {}", ws["id"]);
+/// ```
+pub fn find_or_register(editor: &mut Editor, name: &str, owning_lib: i64) -> EgResult<EgValue> {
+    if let Some(ws) = by_name(editor, name)? {
+        return Ok(ws);
+    }
+
+    log::info!("ACT:registering workstation {name} at org unit {owning_lib}");
+
+    let ws = EgValue::create(
+        "aws",
+        eg::hash! {
+            "name": name,
+            "owning_lib": owning_lib,
+        },
+    )?;
+
+    editor.create(ws)
+}
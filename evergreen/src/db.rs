@@ -1,7 +1,8 @@
 //! Create, connect, and manage database connections.
-use crate::result::EgResult;
+use crate::result::{EgError, EgResult, ErrorKind};
 use getopts;
 use log::debug;
+use pg::error::SqlState;
 use postgres as pg;
 use std::cell::RefCell;
 use std::env;
@@ -288,7 +289,10 @@ impl DatabaseConnection {
                 self.client = Some(c);
                 Ok(())
             }
-            Err(e) => Err(format!("Error connecting to database: {e}").into()),
+            Err(e) => Err(EgError::new(
+                ErrorKind::Database,
+                &format!("Error connecting to database: {e}"),
+            )),
         }
     }
 
@@ -344,7 +348,7 @@ impl DatabaseConnection {
         self.in_transaction = true;
         match self.client().execute("BEGIN", &[]) {
             Ok(_) => Ok(()),
-            Err(e) => Err(format!("BEGIN transaction error: {e}").into()),
+            Err(e) => Err(query_error(&e, &format!("BEGIN transaction error: {e}"))),
         }
     }
 
@@ -355,7 +359,7 @@ impl DatabaseConnection {
         self.in_transaction = false;
         match self.client().execute("COMMIT", &[]) {
             Ok(_) => Ok(()),
-            Err(e) => Err(format!("COMMIT transaction error: {e}").into()),
+            Err(e) => Err(query_error(&e, &format!("COMMIT transaction error: {e}"))),
         }
     }
 
@@ -367,7 +371,7 @@ impl DatabaseConnection {
         self.in_transaction = false;
         match self.client().execute("ROLLBACK", &[]) {
             Ok(_) => Ok(()),
-            Err(e) => Err(format!("ROLLBACK transaction error: {e}").into()),
+            Err(e) => Err(query_error(&e, &format!("ROLLBACK transaction error: {e}"))),
         }
     }
 
@@ -376,6 +380,30 @@ impl DatabaseConnection {
     }
 }
 
+/// True if the error is a deadlock or serialization failure, i.e. one
+/// which may succeed if the transaction is run again.
+pub fn is_transient_error(err: &pg::Error) -> bool {
+    err.code().is_some_and(|code| {
+        *code == SqlState::T_R_DEADLOCK_DETECTED || *code == SqlState::T_R_SERIALIZATION_FAILURE
+    })
+}
+
+/// Translate a database error into an EgError with the provided message.
+///
+/// Deadlocks and serialization failures produce ErrorKind::Transient
+/// errors so callers know they may retry.  Everything else is an
+/// ErrorKind::Database error.
+#[track_caller]
+pub fn query_error(err: &pg::Error, msg: &str) -> EgError {
+    let kind = if is_transient_error(err) {
+        ErrorKind::Transient
+    } else {
+        ErrorKind::Database
+    };
+
+    EgError::new(kind, msg)
+}
+
 /// Determine whether a string is potentially a valid SQL identifier.
 pub fn is_identifier(s: &str) -> bool {
     let s = s.trim();
@@ -0,0 +1,311 @@
+//! Compare two versions of an IDL object, including fleshed children,
+//! and describe what changed.
+//!
+//! Linked objects are matched by primary key, so a fleshed has_a
+//! object and its bare ID are treated as the same value, and has_many
+//! children are reported as added, removed, or changed individually.
+//! has_many lists which are not fleshed on both sides are skipped.
+//!
+//! Values of fields with a suppress_controller (e.g. passwords) are
+//! reported as changed without revealing their values.
+use crate as eg;
+use eg::idl::{Class, RelType};
+use eg::{EgResult, EgValue};
+use std::fmt;
+
+/// Displayed in place of suppressed field values.
+const REDACTED: &str = "[REDACTED]";
+
+/// One difference between two object graphs.
+///
+/// Paths are dot-separated field names, with has_many children
+/// identified by their primary key, e.g. "addresses[12].street1".
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A field value changed.
+    Field {
+        path: String,
+        old: EgValue,
+        new: EgValue,
+    },
+    /// A has_many child was added.  The ID is null for objects which
+    /// have not yet been created.
+    Added { path: String, id: EgValue },
+    /// A has_many child was removed.
+    Removed { path: String, id: EgValue },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Field { path, .. } => path,
+            Change::Added { path, .. } => path,
+            Change::Removed { path, .. } => path,
+        }
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        match self {
+            Change::Field { path, old, new } => eg::hash! {
+                "path": path.as_str(),
+                "old": old.clone(),
+                "new": new.clone(),
+            },
+            Change::Added { path, id } => eg::hash! {
+                "path": path.as_str(),
+                "added": id.clone(),
+            },
+            Change::Removed { path, id } => eg::hash! {
+                "path": path.as_str(),
+                "removed": id.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Field { path, old, new } => {
+                write!(f, "{path}: {} => {}", old.dump(), new.dump())
+            }
+            Change::Added { path, id } => write!(f, "{path}: added {id}"),
+            Change::Removed { path, id } => write!(f, "{path}: removed {id}"),
+        }
+    }
+}
+
+/// Differences between two versions of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeSet {
+    classname: String,
+    id: EgValue,
+    changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    pub fn classname(&self) -> &str {
+        &self.classname
+    }
+
+    /// Primary key value of the compared object.
+    pub fn id(&self) -> &EgValue {
+        &self.id
+    }
+
+    pub fn changes(&self) -> &Vec<Change> {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        let mut changes = EgValue::new_array();
+        for change in self.changes.iter() {
+            // Pushing onto an array cannot fail.
+            changes.push(change.to_eg_value()).ok();
+        }
+
+        eg::hash! {
+            "classname": self.classname.as_str(),
+            "id": self.id.clone(),
+            "changes": changes,
+        }
+    }
+}
+
+/// Audit-friendly description, e.g.
+/// `au 42: family_name: "Smith" => "Jones"; addresses: added 7`
+impl fmt::Display for ChangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: ", self.classname, self.id)?;
+
+        if self.changes.is_empty() {
+            return write!(f, "no changes");
+        }
+
+        let changes: Vec<String> = self.changes.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", changes.join("; "))
+    }
+}
+
+/// Compare two versions of an IDL object of the same class.
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::EgValue;
+///
+/// let client = eg::init().unwrap();
+/// let mut editor = eg::Editor::new(&client);
+///
+/// let old = editor.retrieve("ac", 1).unwrap().unwrap();
+/// let mut new = old.clone();
+/// new["active"] = EgValue::from("f");
+///
+/// let changes = eg::diff::diff(&old, &new).unwrap();
+/// assert_eq!(changes.len(), 1);
+///
+/// // e.g. ac 1: active: "t" => "f"
+/// println!("{changes}");
+/// ```
+pub fn diff(old: &EgValue, new: &EgValue) -> EgResult<ChangeSet> {
+    let class = old.idl_class().ok_or("diff() requires IDL objects")?;
+
+    if new.classname() != Some(class.classname()) {
+        return Err(format!(
+            "Cannot compare {} object to {} object",
+            class.classname(),
+            new.classname().unwrap_or("non-IDL")
+        )
+        .into());
+    }
+
+    let mut changes = Vec::new();
+    diff_objects(class, old, new, "", &mut changes);
+
+    Ok(ChangeSet {
+        classname: class.classname().to_string(),
+        id: old.pkey_value().cloned().unwrap_or(EgValue::Null),
+        changes,
+    })
+}
+
+fn diff_objects(
+    class: &Class,
+    old: &EgValue,
+    new: &EgValue,
+    prefix: &str,
+    changes: &mut Vec<Change>,
+) {
+    let mut fields: Vec<_> = class.fields().values().collect();
+    fields.sort_by_key(|f| f.array_pos());
+
+    for field in fields {
+        let name = field.name();
+        let path = format!("{prefix}{name}");
+        let link = class.links().get(name);
+
+        if field.is_virtual() && link.is_none() {
+            continue;
+        }
+
+        let (old_val, new_val) = (&old[name], &new[name]);
+
+        if field.suppress_controller().is_some() {
+            if !same_value(old_val, new_val) {
+                changes.push(Change::Field {
+                    path,
+                    old: EgValue::from(REDACTED),
+                    new: EgValue::from(REDACTED),
+                });
+            }
+            continue;
+        }
+
+        match link.map(|l| l.reltype()) {
+            Some(RelType::HasMany) => diff_children(old_val, new_val, &path, changes),
+            Some(_) => diff_linked(old_val, new_val, &path, changes),
+            None => {
+                if !same_value(old_val, new_val) {
+                    changes.push(Change::Field {
+                        path,
+                        old: old_val.clone(),
+                        new: new_val.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Compare has_a / might_have values, which may be IDs or fleshed objects.
+fn diff_linked(old: &EgValue, new: &EgValue, path: &str, changes: &mut Vec<Change>) {
+    let (old_id, new_id) = (link_id(old), link_id(new));
+
+    if !same_value(old_id, new_id) {
+        changes.push(Change::Field {
+            path: path.to_string(),
+            old: old_id.clone(),
+            new: new_id.clone(),
+        });
+        return;
+    }
+
+    if let Some(class) = same_class(old, new) {
+        diff_objects(class, old, new, &format!("{path}."), changes);
+    }
+}
+
+/// Compare has_many lists, matching children by primary key.
+fn diff_children(old: &EgValue, new: &EgValue, path: &str, changes: &mut Vec<Change>) {
+    if !old.is_array() || !new.is_array() {
+        // Not fleshed on both sides; nothing to compare.
+        return;
+    }
+
+    for old_child in old.members() {
+        let id = link_id(old_child);
+
+        match new
+            .members()
+            .find(|c| !id.is_null() && same_value(link_id(c), id))
+        {
+            Some(new_child) => {
+                if let Some(class) = same_class(old_child, new_child) {
+                    let prefix = format!("{path}[{id}].");
+                    diff_objects(class, old_child, new_child, &prefix, changes);
+                }
+            }
+            None => changes.push(Change::Removed {
+                path: path.to_string(),
+                id: id.clone(),
+            }),
+        }
+    }
+
+    for new_child in new.members() {
+        let id = link_id(new_child);
+
+        if id.is_null() || !old.members().any(|c| same_value(link_id(c), id)) {
+            changes.push(Change::Added {
+                path: path.to_string(),
+                id: id.clone(),
+            });
+        }
+    }
+}
+
+/// The IDL class of two values, when both are objects of the same class.
+fn same_class<'a>(a: &'a EgValue, b: &EgValue) -> Option<&'a Class> {
+    let class = a.idl_class()?;
+
+    if b.classname() == Some(class.classname()) {
+        Some(class)
+    } else {
+        None
+    }
+}
+
+/// ID of a linked value, which is either the ID itself or a fleshed object.
+fn link_id(value: &EgValue) -> &EgValue {
+    if value.is_blessed() {
+        value.pkey_value().unwrap_or(&EgValue::Null)
+    } else {
+        value
+    }
+}
+
+/// Numbers and numeric strings with the same value compare as equal,
+/// since both arrive over the wire.
+fn same_value(a: &EgValue, b: &EgValue) -> bool {
+    match (a.to_string(), b.to_string()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
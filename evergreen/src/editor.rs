@@ -1,15 +1,106 @@
 //! Create, Retrieve, Update, Delete IDL-classed objects via (by default) open-ils.cstore.
 use crate as eg;
+use eg::diff;
 use eg::event::EgEvent;
 use eg::idl;
 use eg::osrf::params::ApiParams;
-use eg::result::{EgError, EgResult};
+use eg::osrf::session::MultiSession;
+use eg::result::{EgError, EgResult, ErrorKind};
 use eg::Client;
 use eg::ClientSession;
 use eg::EgValue;
+use std::env;
+use std::panic;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const DEFAULT_TIMEOUT: i32 = 60;
 
+/// How many times a request or transaction which fails with a
+/// retryable error is retried by default.
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Pause before each retry, multiplied by the attempt number, to give
+/// competing transactions a chance to finish.
+const RETRY_DELAY_MS: u64 = 100;
+
+/// Field compared by update_checked() to detect concurrent changes.
+const VERSION_FIELD: &str = "edit_date";
+
+/// Service which reports query plans for the query log.
+const EXPLAIN_SERVICE: &str = "open-ils.rs-store";
+const EXPLAIN_METHOD: &str = "open-ils.rs-store.json_query.explain";
+
+/// Process-wide slow query log settings.  None if query logging is off.
+static QUERY_LOG: OnceLock<Option<QueryLog>> = OnceLock::new();
+
+/// Slow query logging for all Editors in this process.
+///
+/// Unless set_query_log() is called first, the settings are read from
+/// the environment:
+///
+/// * `EG_EDITOR_SLOW_QUERY_MS` - Log requests which take at least this
+///   many milliseconds, along with their query and row count.  Query
+///   logging is disabled when unset.
+/// * `EG_EDITOR_EXPLAIN` - When set, also log the SQL and query plan
+///   generated for slow json_query calls, as reported by
+///   open-ils.rs-store.
+#[derive(Debug, Clone)]
+pub struct QueryLog {
+    pub threshold: Duration,
+    pub explain: bool,
+}
+
+/// Enable slow query logging for all Editors in this process.
+///
+/// Returns false if query logging was already configured.
+///
+/// ```
+/// use evergreen as eg;
+/// use std::time::Duration;
+///
+/// let query_log = eg::editor::QueryLog {
+///     threshold: Duration::from_millis(500),
+///     explain: false,
+/// };
+///
+/// assert!(eg::editor::set_query_log(query_log));
+/// assert!(eg::editor::query_log().is_some());
+/// ```
+pub fn set_query_log(query_log: QueryLog) -> bool {
+    QUERY_LOG.set(Some(query_log)).is_ok()
+}
+
+/// Active slow query log settings, if any.
+pub fn query_log() -> Option<&'static QueryLog> {
+    QUERY_LOG
+        .get_or_init(|| {
+            let ms = env::var("EG_EDITOR_SLOW_QUERY_MS").ok()?;
+
+            let Ok(ms) = ms.parse::<u64>() else {
+                log::warn!("Invalid EG_EDITOR_SLOW_QUERY_MS value: {ms}");
+                return None;
+            };
+
+            Some(QueryLog {
+                threshold: Duration::from_millis(ms),
+                explain: env::var("EG_EDITOR_EXPLAIN").is_ok(),
+            })
+        })
+        .as_ref()
+}
+
+/// Comparable form of a field value, using the primary key for
+/// fleshed objects.
+fn version_value(v: &EgValue) -> Option<String> {
+    if v.is_blessed() {
+        v.pkey_value().and_then(|p| p.to_string())
+    } else {
+        v.to_string()
+    }
+}
+
 /// Specifies Which service are we communicating with.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Personality {
@@ -67,12 +158,17 @@ pub struct Editor {
     last_event: Option<EgEvent>,
 
     has_pending_changes: bool,
+
+    /// Retries allowed for requests and transactions which fail with
+    /// a retryable error.
+    max_retries: u32,
 }
 
 impl Clone for Editor {
     fn clone(&self) -> Editor {
         let mut e = Editor::new(&self.client);
         e.personality = self.personality().clone();
+        e.max_retries = self.max_retries;
         e.authtoken = self.authtoken().map(str::to_string);
         e.requestor = self.requestor().map(|r| r.clone());
         e
@@ -94,9 +190,19 @@ impl Editor {
             requestor: None,
             last_event: None,
             has_pending_changes: false,
+            max_retries: DEFAULT_MAX_RETRIES,
         }
     }
 
+    /// Set how many times a request or transaction which fails with a
+    /// retryable error (see EgError::is_retryable()) is retried.
+    ///
+    /// Only reads outside of a transaction and whole transactions run
+    /// via in_transaction_retry() are retried.  Use 0 to disable.
+    pub fn set_max_retries(&mut self, retries: u32) {
+        self.max_retries = retries;
+    }
+
     /// Apply a new request timeout value in seconds.
     pub fn set_timeout(&mut self, timeout: i32) {
         self.timeout = timeout;
@@ -290,10 +396,11 @@ impl Editor {
         self.last_event.take()
     }
 
+    #[track_caller]
     pub fn event_as_err(&self) -> EgError {
         match self.last_event() {
-            Some(e) => EgError::Event(e.clone()),
-            None => EgError::Debug("Editor Has No Event".to_string()),
+            Some(e) => e.into(),
+            None => "Editor Has No Event".into(),
         }
     }
 
@@ -314,20 +421,21 @@ impl Editor {
     /// and return an EgError-wrapped variant of the last event.
     ///
     /// The raw event can still be accessed via self.last_event().
+    #[track_caller]
     pub fn die_event(&mut self) -> EgError {
         if let Err(e) = self.rollback() {
             return e;
         }
         match self.last_event() {
-            Some(e) => EgError::Event(e.clone()),
-            None => EgError::Debug("Die-Event Called With No Event".to_string()),
+            Some(e) => e.into(),
+            None => "Die-Event Called With No Event".into(),
         }
     }
 
     /// Rollback the active transaction, disconnect from the worker,
     /// and an EgError using the provided message as either the
-    /// debug text on our last_event or as the string contents
-    /// of an EgError::Debug variant.
+    /// debug text on our last_event or as the error message.
+    #[track_caller]
     pub fn die_event_msg(&mut self, msg: &str) -> EgError {
         if let Err(e) = self.rollback() {
             return e;
@@ -336,9 +444,9 @@ impl Editor {
             Some(e) => {
                 let mut e2 = e.clone();
                 e2.set_debug(msg);
-                EgError::Event(e2)
+                e2.into()
             }
-            None => EgError::Debug(msg.to_string()),
+            None => msg.into(),
         }
     }
 
@@ -360,7 +468,8 @@ impl Editor {
         format!("{p}.{}", part)
     }
 
-    pub fn in_transaction(&self) -> bool {
+    /// True if we are connected to a worker with an active transaction.
+    pub fn xact_active(&self) -> bool {
         if let Some(ref ses) = self.session {
             ses.connected() && self.has_xact_id()
         } else {
@@ -368,11 +477,117 @@ impl Editor {
         }
     }
 
+    /// Run `f` within a new transaction.
+    ///
+    /// The transaction is committed if `f` returns Ok and rolled back
+    /// if `f` returns Err or panics.  Panics are resumed after the
+    /// rollback.
+    ///
+    /// ```no_run
+    /// use evergreen as eg;
+    ///
+    /// fn deactivate_card(editor: &mut eg::Editor, card_id: i64) -> eg::EgResult<()> {
+    ///     editor.in_transaction(|e| {
+    ///         let mut card = e.retrieve("ac", card_id)?.ok_or_else(|| e.die_event())?;
+    ///         card["active"] = "f".into();
+    ///         e.update(card)
+    ///     })
+    /// }
+    /// ```
+    pub fn in_transaction<T, F>(&mut self, f: F) -> EgResult<T>
+    where
+        F: FnOnce(&mut Editor) -> EgResult<T>,
+    {
+        self.run_transaction(f, true)
+    }
+
+    /// Same as in_transaction(), but the transaction is rolled back
+    /// even if `f` succeeds, e.g. for dry runs.
+    pub fn in_transaction_rollback<T, F>(&mut self, f: F) -> EgResult<T>
+    where
+        F: FnOnce(&mut Editor) -> EgResult<T>,
+    {
+        self.run_transaction(f, false)
+    }
+
+    fn run_transaction<T, F>(&mut self, f: F, commit: bool) -> EgResult<T>
+    where
+        F: FnOnce(&mut Editor) -> EgResult<T>,
+    {
+        self.xact_begin()?;
+
+        match panic::catch_unwind(panic::AssertUnwindSafe(|| f(self))) {
+            Ok(Ok(value)) => {
+                if commit {
+                    self.commit()?;
+                } else {
+                    self.rollback()?;
+                }
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                if let Err(e) = self.rollback() {
+                    log::error!("Rollback failed after error '{err}': {e}");
+                }
+                Err(err)
+            }
+            Err(cause) => {
+                if let Err(e) = self.rollback() {
+                    log::error!("Rollback failed after panic: {e}");
+                }
+                panic::resume_unwind(cause)
+            }
+        }
+    }
+
+    /// Same as in_transaction(), but if the transaction fails with a
+    /// retryable error, e.g. a deadlock or serialization failure, it
+    /// is rolled back and `f` is run again in a new transaction, up to
+    /// our max retries.
+    ///
+    /// Errors which are not retryable, or which persist once our
+    /// retries are used up, are returned as-is.  Check
+    /// EgError::is_retryable() to tell them apart.
+    ///
+    /// `f` may run more than once, so it should not have side effects
+    /// outside the transaction.
+    ///
+    /// ```no_run
+    /// use evergreen as eg;
+    ///
+    /// fn touch_card(editor: &mut eg::Editor, card_id: i64) -> eg::EgResult<()> {
+    ///     editor.in_transaction_retry(|e| {
+    ///         let card = e.retrieve("ac", card_id)?.ok_or_else(|| e.die_event())?;
+    ///         e.update(card)
+    ///     })
+    /// }
+    /// ```
+    pub fn in_transaction_retry<T, F>(&mut self, mut f: F) -> EgResult<T>
+    where
+        F: FnMut(&mut Editor) -> EgResult<T>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match self.in_transaction(&mut f) {
+                Err(err) if err.is_retryable() && attempt < self.max_retries => {
+                    attempt += 1;
+                    log::warn!(
+                        "{} retrying transaction (attempt {attempt}) after error: {err}",
+                        self.logtag()
+                    );
+                    thread::sleep(Duration::from_millis(RETRY_DELAY_MS * attempt as u64));
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Rollback a database transaction.
     ///
     /// This variation does not send a DISCONNECT to the connected worker.
     pub fn xact_rollback(&mut self) -> EgResult<()> {
-        if self.in_transaction() {
+        if self.xact_active() {
             self.request_np(&self.app_method("transaction.rollback"))?;
         }
 
@@ -399,7 +614,7 @@ impl Editor {
     ///
     /// This variation does not send a DISCONNECT to the connected worker.
     pub fn xact_commit(&mut self) -> EgResult<()> {
-        if self.in_transaction() {
+        if self.xact_active() {
             // We can take() the xact_id here because we're clearing
             // it below anyway.  This avoids a .to_string() as a way
             // to get around the mutable borrow from self.request().
@@ -438,6 +653,12 @@ impl Editor {
         Ok(())
     }
 
+    /// Create a batch of read-only queries which are sent to our
+    /// service in parallel.  See EditorBatch.
+    pub fn batch(&self) -> EditorBatch {
+        EditorBatch::new(self)
+    }
+
     /// Send an API request without any parameters.
     ///
     /// See request() for more.
@@ -496,7 +717,10 @@ impl Editor {
             self.args_to_string(&params)
         );
 
-        if method.contains("create") || method.contains("update") || method.contains("delete") {
+        let is_write =
+            method.contains("create") || method.contains("update") || method.contains("delete");
+
+        if is_write {
             if !self.has_xact_id() {
                 self.disconnect()?;
                 Err(format!(
@@ -505,9 +729,10 @@ impl Editor {
             }
 
             if params.params().len() == 0 {
-                Err(EgError::Debug(format!(
-                    "Create/update/delete calls require a parameter"
-                )))?;
+                Err(EgError::new(
+                    ErrorKind::InvalidInput,
+                    "Create/update/delete calls require a parameter",
+                ))?;
             }
 
             // Write calls also get logged to the activity log
@@ -519,6 +744,102 @@ impl Editor {
             );
         }
 
+        // A failed statement spoils the whole transaction, so only
+        // standalone reads are safe to retry here.
+        let can_retry = !is_write && !self.has_xact_id();
+        let mut attempt = 0;
+        let start = Instant::now();
+
+        loop {
+            let result = self.send_request(method, params.clone());
+
+            match result {
+                Err(err) if can_retry && err.is_retryable() && attempt < self.max_retries => {
+                    attempt += 1;
+                    log::warn!(
+                        "{} retrying {method} (attempt {attempt}) after error: {err}",
+                        self.logtag()
+                    );
+                    thread::sleep(Duration::from_millis(RETRY_DELAY_MS * attempt as u64));
+                }
+                _ => {
+                    if let Ok(response) = &result {
+                        self.log_slow_query(method, &params, start.elapsed(), response);
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// Log the request if it took longer than the query log threshold.
+    fn log_slow_query(
+        &self,
+        method: &str,
+        params: &ApiParams,
+        duration: Duration,
+        response: &Option<EgValue>,
+    ) {
+        let Some(query_log) = query_log() else {
+            return;
+        };
+
+        if duration < query_log.threshold {
+            return;
+        }
+
+        let rows = match response {
+            Some(EgValue::Array(list)) => list.len(),
+            Some(_) => 1,
+            None => 0,
+        };
+
+        log::warn!(
+            "{} slow query took {:.3}s rows={rows} {method} {}",
+            self.logtag(),
+            duration.as_secs_f64(),
+            self.args_to_string(params)
+        );
+
+        if query_log.explain && method.ends_with(".json_query.atomic") {
+            self.log_query_plan(&params.params()[0]);
+        }
+    }
+
+    /// Log the SQL and query plan for a json_query.
+    ///
+    /// The plan is requested outside of any active transaction.
+    fn log_query_plan(&self, query: &EgValue) {
+        let explain =
+            match self
+                .client
+                .send_recv_one(EXPLAIN_SERVICE, EXPLAIN_METHOD, query.clone())
+            {
+                Ok(Some(e)) => e,
+                Ok(None) => return,
+                Err(e) => {
+                    log::warn!("{} cannot explain query: {e}", self.logtag());
+                    return;
+                }
+            };
+
+        log::warn!(
+            "{} slow query SQL: {} params={}",
+            self.logtag(),
+            explain["sql"].as_str().unwrap_or(""),
+            explain["params"].dump()
+        );
+
+        for line in explain["plan"].members() {
+            log::warn!(
+                "{} slow query plan: {}",
+                self.logtag(),
+                line.as_str().unwrap_or("")
+            );
+        }
+    }
+
+    fn send_request(&mut self, method: &str, params: ApiParams) -> EgResult<Option<EgValue>> {
         let mut req = self.session().request(method, params).or_else(|e| {
             self.rollback()?;
             Err(e)
@@ -561,15 +882,26 @@ impl Editor {
         self.json_query_with_ops(query, EgValue::Null)
     }
 
+    /// Execute an atomic json_query call whose params are redacted
+    /// from the logs, e.g. queries containing passwords.
+    pub fn json_query_sensitive(&mut self, query: EgValue) -> EgResult<Vec<EgValue>> {
+        let params = ApiParams::from(query).into_sensitive();
+        self.json_query_params(params)
+    }
+
     /// Execute an atomic json_query call with additional query params.
     pub fn json_query_with_ops(&mut self, query: EgValue, ops: EgValue) -> EgResult<Vec<EgValue>> {
-        let method = self.app_method(&format!("json_query.atomic"));
-
         let mut params: ApiParams = query.into();
         if !ops.is_null() {
             params.add(ops);
         }
 
+        self.json_query_params(params)
+    }
+
+    fn json_query_params(&mut self, params: ApiParams) -> EgResult<Vec<EgValue>> {
+        let method = self.app_method(&format!("json_query.atomic"));
+
         if let Some(jvec) = self.request(&method, params)? {
             if let EgValue::Array(vec) = jvec {
                 return Ok(vec);
@@ -665,6 +997,68 @@ impl Editor {
         Ok(())
     }
 
+    /// Update an object, first verifying its row has not changed in
+    /// the database since `original` was retrieved.
+    ///
+    /// The row is re-read with "for_update", which locks it until the
+    /// transaction ends, so no other writer can modify it between the
+    /// comparison and the update.
+    ///
+    /// When the class has an edit_date field, only edit_date is
+    /// compared.  Otherwise, all fields of `original` are compared.
+    ///
+    /// Returns an ErrorKind::Conflict error if the row has changed or
+    /// no longer exists.
+    pub fn update_checked(&mut self, object: EgValue, original: &EgValue) -> EgResult<()> {
+        if !self.has_xact_id() {
+            Err("Transaction required for UPDATE")?;
+        }
+
+        let classname = original
+            .classname()
+            .ok_or("update_checked() requires an IDL object")?
+            .to_string();
+
+        let pkey = original
+            .pkey_value()
+            .ok_or_else(|| format!("{classname} object has no primary key value"))?
+            .clone();
+
+        let ops = eg::hash! {"for_update": true};
+
+        let current = match self.retrieve_with_ops(&classname, pkey.clone(), ops)? {
+            Some(c) => c,
+            None => {
+                return Err(EgError::new(
+                    ErrorKind::Conflict,
+                    &format!("{classname} {} was deleted", pkey.dump()),
+                ))
+            }
+        };
+
+        let fields = if original.has_real_field(VERSION_FIELD) {
+            vec![VERSION_FIELD]
+        } else {
+            original.real_fields().iter().map(|f| f.name()).collect()
+        };
+
+        for field in fields {
+            if version_value(&original[field]) != version_value(&current[field]) {
+                return Err(EgError::new(
+                    ErrorKind::Conflict,
+                    &format!("{classname} {} was modified; {field} changed", pkey.dump()),
+                ));
+            }
+        }
+
+        match diff::diff(&current, &object) {
+            Ok(changes) => log::info!("ACT:{} changes {changes}", self.logtag()),
+            Err(e) => log::warn!("{} cannot describe changes: {e}", self.logtag()),
+        }
+
+        self.update(object)
+    }
+
     /// Returns the newly created object.
     pub fn create(&mut self, object: EgValue) -> EgResult<EgValue> {
         if !self.has_xact_id() {
@@ -763,3 +1157,131 @@ impl Editor {
         Ok(has_perm)
     }
 }
+
+/// Collection of read-only queries which are sent in parallel and
+/// whose responses are collected together.
+///
+/// Each query runs in its own stateless session, so batched queries
+/// do not see changes made within an Editor transaction.
+///
+/// ```text
+/// let mut batch = editor.batch();
+/// let user_idx = batch.retrieve("au", 1)?;
+/// let circs_idx = batch.search("circ", eg::hash! {"usr": 1})?;
+/// let mut results = batch.run()?;
+/// let user = results[user_idx].take();
+/// ```
+pub struct EditorBatch {
+    /// Clone of the source editor, used for method names and logging.
+    editor: Editor,
+    multi: MultiSession,
+
+    /// Session thread of each request in the order they were added.
+    threads: Vec<String>,
+}
+
+impl EditorBatch {
+    fn new(source: &Editor) -> Self {
+        let mut editor = source.clone();
+        editor.timeout = source.timeout;
+
+        let service: &str = editor.personality().into();
+        let multi = MultiSession::new(editor.client.clone(), service);
+
+        EditorBatch {
+            editor,
+            multi,
+            threads: Vec::new(),
+        }
+    }
+
+    /// Send a request, returning its index in the results list.
+    fn request(&mut self, method: &str, params: impl Into<ApiParams>) -> EgResult<usize> {
+        let params: ApiParams = params.into();
+
+        log::info!(
+            "{} batch request {} {}",
+            self.editor.logtag(),
+            method,
+            self.editor.args_to_string(&params)
+        );
+
+        let thread = self.multi.request(method, params)?;
+        self.threads.push(thread);
+
+        Ok(self.threads.len() - 1)
+    }
+
+    /// Add an atomic json_query call to the batch.
+    pub fn json_query(&mut self, query: EgValue) -> EgResult<usize> {
+        let method = self.editor.app_method("json_query.atomic");
+        self.request(&method, query)
+    }
+
+    /// Add a retrieve-by-primary-key call to the batch.
+    pub fn retrieve(&mut self, idlclass: &str, id: impl Into<ApiParams>) -> EgResult<usize> {
+        let fmapper = self.editor.get_fieldmapper_from_classname(idlclass)?;
+        let method = self
+            .editor
+            .app_method(&format!("direct.{fmapper}.retrieve"));
+        self.request(&method, id)
+    }
+
+    /// Add an atomic search call to the batch.
+    pub fn search(&mut self, idlclass: &str, query: EgValue) -> EgResult<usize> {
+        self.search_with_ops(idlclass, query, EgValue::Null)
+    }
+
+    /// Add an atomic search call with additional query params to the batch.
+    pub fn search_with_ops(
+        &mut self,
+        idlclass: &str,
+        query: EgValue,
+        ops: EgValue,
+    ) -> EgResult<usize> {
+        let fmapper = self.editor.get_fieldmapper_from_classname(idlclass)?;
+        let method = self
+            .editor
+            .app_method(&format!("direct.{fmapper}.search.atomic"));
+
+        let mut params: ApiParams = query.into();
+        if !ops.is_null() {
+            params.add(ops);
+        }
+
+        self.request(&method, params)
+    }
+
+    /// Wait for all requests to complete.
+    ///
+    /// Returns one entry per request, in the order they were added.
+    /// An entry is None if its request produced no response, e.g. a
+    /// retrieve for an object which does not exist.
+    pub fn run(mut self) -> EgResult<Vec<Option<EgValue>>> {
+        let mut results: Vec<Option<EgValue>> = self.threads.iter().map(|_| None).collect();
+
+        let timeout = self.editor.timeout;
+        let start = Instant::now();
+
+        while !self.multi.complete() {
+            let remaining = timeout - start.elapsed().as_secs() as i32;
+
+            if remaining <= 0 {
+                return Err(format!(
+                    "{} batch timed out waiting for responses",
+                    self.editor.logtag()
+                )
+                .into());
+            }
+
+            if let Some((thread, value)) = self.multi.recv(remaining)? {
+                if let Some(idx) = self.threads.iter().position(|t| t == &thread) {
+                    // All of our calls produce at most one response.
+                    results[idx] = Some(value);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
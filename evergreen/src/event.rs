@@ -1,4 +1,22 @@
 //! Evergreen API Response Events
+//!
+//! # Payload Layouts
+//!
+//! Event payloads vary by textcode and by the API returning the event.
+//! The typed accessors on [`EgEvent`] understand these layouts:
+//!
+//! * Checkin/checkout `SUCCESS` and `ROUTE_ITEM`: a hash which may
+//!   contain "copy", "volume", "title", "circ", "patron", "hold",
+//!   "remote_hold", "transit", and "reservation" objects.
+//! * Transit receive `SUCCESS`: `{"transit": atc, "holdtransit": ahtc}`.
+//! * `HOLD_RESERVATION_CONFLICT`: "hold" and "reservation" are added
+//!   to the event itself instead of the payload.
+//! * `ITEM_DEPOSIT_PAID`: the deposit billing (mb) object.
+//! * Payment `SUCCESS`: `{"payments": [id, ...], "last_xact_id": "..."}`.
+//! * `REFUND_EXCEEDS_DESK_PAYMENTS`: `{"allowed_refund": n, "submitted_refund": n}`.
+//!
+//! A `ROUTE_ITEM` event without a transit payload carries its
+//! destination in [`EgEvent::org()`].
 use crate as eg;
 use eg::date;
 use eg::EgValue;
@@ -234,6 +252,74 @@ impl EgEvent {
         ad_hoc[key] = value;
     }
 
+    /// Hold captured, fulfilled, or in conflict, when present.
+    ///
+    /// A hold captured for another library's patron ("remote_hold")
+    /// is preferred over the local hold.
+    ///
+    /// ```
+    /// use evergreen as eg;
+    /// use eg::EgEvent;
+    ///
+    /// let mut evt = EgEvent::success();
+    /// evt.set_payload(eg::hash! {
+    ///     "copy": {"id": 5, "barcode": "123"},
+    ///     "hold": {"id": 22, "usr": {"id": 1}, "pickup_lib": "4"},
+    ///     "transit": {"id": 9, "source": 4, "dest": 7, "hold": 22},
+    /// });
+    ///
+    /// let hold = evt.hold().unwrap();
+    /// assert_eq!(hold.id, 22);
+    /// assert_eq!(hold.usr, Some(1));
+    /// assert_eq!(hold.pickup_lib, Some(4));
+    ///
+    /// let transit = evt.transit().unwrap();
+    /// assert_eq!(transit.dest, Some(7));
+    /// assert_eq!(transit.hold, Some(22));
+    ///
+    /// assert!(evt.payment().is_none());
+    /// ```
+    pub fn hold(&self) -> Option<EventHold> {
+        let ad_hoc = self.ad_hoc.as_ref().unwrap_or(&eg::NULL);
+
+        ["remote_hold", "hold"]
+            .iter()
+            .map(|key| field(&self.payload, key))
+            .chain([field(ad_hoc, "hold")])
+            .find_map(EventHold::from_value)
+    }
+
+    /// Transit created or received, when present.
+    pub fn transit(&self) -> Option<EventTransit> {
+        ["transit", "holdtransit"]
+            .iter()
+            .find_map(|key| EventTransit::from_value(field(&self.payload, key)))
+    }
+
+    /// Payment details, when present.
+    ///
+    /// ```
+    /// use evergreen as eg;
+    /// use eg::EgEvent;
+    ///
+    /// let mut evt = EgEvent::success();
+    /// evt.set_payload(eg::hash! {"payments": [3, "4"], "last_xact_id": "abc"});
+    ///
+    /// let payment = evt.payment().unwrap();
+    /// assert_eq!(payment.payment_ids, vec![3, 4]);
+    /// assert_eq!(payment.last_xact_id.as_deref(), Some("abc"));
+    ///
+    /// let mut evt = EgEvent::new("ITEM_DEPOSIT_PAID");
+    /// evt.set_payload(eg::hash! {"id": 8, "xact": 100, "amount": "5.00"});
+    ///
+    /// let payment = evt.payment().unwrap();
+    /// assert_eq!(payment.xact, Some(100));
+    /// assert_eq!(payment.amount, Some(5.0));
+    /// ```
+    pub fn payment(&self) -> Option<EventPayment> {
+        EventPayment::from_value(&self.payload)
+    }
+
     /// Parses a EgValue and optionally returns an EgEvent.
     ///
     /// ```
@@ -319,3 +405,134 @@ impl EgEvent {
         Some(evt)
     }
 }
+
+/// Returns the value of a hash or IDL object field, or NULL if the
+/// value has no such field.
+fn field<'a>(obj: &'a EgValue, name: &str) -> &'a EgValue {
+    if obj.has_key(name) {
+        &obj[name]
+    } else {
+        &eg::NULL
+    }
+}
+
+/// ID of a linked object, which may or may not be fleshed.
+fn link_id(obj: &EgValue, name: &str) -> Option<i64> {
+    let value = field(obj, name);
+    value.as_i64().or_else(|| field(value, "id").as_i64())
+}
+
+fn string_field(obj: &EgValue, name: &str) -> Option<String> {
+    field(obj, name).as_str().map(|s| s.to_string())
+}
+
+/// Hold found in an event payload.
+#[derive(Debug, Clone)]
+pub struct EventHold {
+    pub id: i64,
+    pub usr: Option<i64>,
+    pub pickup_lib: Option<i64>,
+    pub current_copy: Option<i64>,
+    pub capture_time: Option<String>,
+    pub shelf_time: Option<String>,
+    /// The full hold object.
+    pub value: EgValue,
+}
+
+impl EventHold {
+    fn from_value(value: &EgValue) -> Option<EventHold> {
+        if !value.is_object() {
+            return None;
+        }
+
+        Some(EventHold {
+            id: link_id(value, "id")?,
+            usr: link_id(value, "usr"),
+            pickup_lib: link_id(value, "pickup_lib"),
+            current_copy: link_id(value, "current_copy"),
+            capture_time: string_field(value, "capture_time"),
+            shelf_time: string_field(value, "shelf_time"),
+            value: value.clone(),
+        })
+    }
+}
+
+/// Transit (copy or hold transit) found in an event payload.
+#[derive(Debug, Clone)]
+pub struct EventTransit {
+    pub id: i64,
+    pub source: Option<i64>,
+    pub dest: Option<i64>,
+    pub target_copy: Option<i64>,
+    pub copy_status: Option<i64>,
+    pub source_send_time: Option<String>,
+    pub dest_recv_time: Option<String>,
+    /// Hold ID for hold transits.
+    pub hold: Option<i64>,
+    /// The full transit object.
+    pub value: EgValue,
+}
+
+impl EventTransit {
+    fn from_value(value: &EgValue) -> Option<EventTransit> {
+        if !value.is_object() {
+            return None;
+        }
+
+        Some(EventTransit {
+            id: link_id(value, "id")?,
+            source: link_id(value, "source"),
+            dest: link_id(value, "dest"),
+            target_copy: link_id(value, "target_copy"),
+            copy_status: link_id(value, "copy_status"),
+            source_send_time: string_field(value, "source_send_time"),
+            dest_recv_time: string_field(value, "dest_recv_time"),
+            hold: link_id(value, "hold"),
+            value: value.clone(),
+        })
+    }
+}
+
+/// Payment, deposit, or refund details found in an event payload.
+#[derive(Debug, Clone, Default)]
+pub struct EventPayment {
+    /// IDs of payments created by a payment call.
+    pub payment_ids: Vec<i64>,
+    /// User's last transaction ID after a payment call.
+    pub last_xact_id: Option<String>,
+    /// Transaction billed or paid, for billing/payment objects.
+    pub xact: Option<i64>,
+    /// Amount billed or paid, for billing/payment objects.
+    pub amount: Option<f64>,
+    /// Maximum refund allowed, for REFUND_EXCEEDS_DESK_PAYMENTS.
+    pub allowed_refund: Option<f64>,
+}
+
+impl EventPayment {
+    fn from_value(value: &EgValue) -> Option<EventPayment> {
+        if !value.is_object() {
+            return None;
+        }
+
+        let payment = EventPayment {
+            payment_ids: field(value, "payments")
+                .members()
+                .filter_map(|p| p.as_i64().or_else(|| field(p, "id").as_i64()))
+                .collect(),
+            last_xact_id: string_field(value, "last_xact_id"),
+            xact: link_id(value, "xact"),
+            amount: field(value, "amount").as_f64(),
+            allowed_refund: field(value, "allowed_refund").as_f64(),
+        };
+
+        if payment.payment_ids.is_empty()
+            && payment.last_xact_id.is_none()
+            && payment.amount.is_none()
+            && payment.allowed_refund.is_none()
+        {
+            return None;
+        }
+
+        Some(payment)
+    }
+}
@@ -29,6 +29,11 @@ pub fn parser() -> &'static Parser {
     }
 }
 
+/// True if the IDL has been loaded.
+pub fn is_loaded() -> bool {
+    GLOBAL_IDL.get().is_some()
+}
+
 /// Returns a ref to an IDL class by classname.
 ///
 /// Err is returned if no such classes exists.
@@ -418,7 +423,14 @@ impl Parser {
             Err(e) => Err(format!("Cannot parse IDL file '{filename}': {e}"))?,
         };
 
-        let p = Parser::parse_string(&xml)?;
+        Parser::load_string(&xml)
+    }
+
+    /// Load the IDL from an XML string.
+    ///
+    /// Returns an Err if the IDL has already been parsed and loaded.
+    pub fn load_string(xml: &str) -> EgResult<()> {
+        let p = Parser::parse_string(xml)?;
 
         if GLOBAL_IDL.set(p).is_err() {
             return Err(format!("Cannot initialize IDL more than once").into());
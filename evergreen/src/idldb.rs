@@ -166,6 +166,8 @@ pub struct IdlClassSearch {
     pub order_by: Option<Vec<OrderBy>>,
     pub pager: Option<Pager>,
     pub flesh: Option<FleshDef>,
+    /// Lock the found rows until the end of the transaction.
+    pub for_update: bool,
 }
 
 impl IdlClassSearch {
@@ -176,6 +178,7 @@ impl IdlClassSearch {
             order_by: None,
             pager: None,
             flesh: None,
+            for_update: false,
         }
     }
 
@@ -210,6 +213,10 @@ impl IdlClassSearch {
     pub fn set_pager(&mut self, pager: Pager) {
         self.pager = Some(pager);
     }
+
+    pub fn set_for_update(&mut self, for_update: bool) {
+        self.for_update = for_update;
+    }
 }
 
 /// Manages the translation to / from IDL objects and database queries.
@@ -246,6 +253,27 @@ impl Translator {
         classname: &str,
         pkey: &EgValue,
         flesh_def: Option<FleshDef>,
+    ) -> EgResult<Option<EgValue>> {
+        self.pkey_search(classname, pkey, flesh_def, false)
+    }
+
+    /// Same as get_idl_object_by_pkey(), but the row is locked until
+    /// the end of the current transaction.
+    pub fn lock_idl_object_by_pkey(
+        &self,
+        classname: &str,
+        pkey: &EgValue,
+        flesh_def: Option<FleshDef>,
+    ) -> EgResult<Option<EgValue>> {
+        self.pkey_search(classname, pkey, flesh_def, true)
+    }
+
+    fn pkey_search(
+        &self,
+        classname: &str,
+        pkey: &EgValue,
+        flesh_def: Option<FleshDef>,
+        for_update: bool,
     ) -> EgResult<Option<EgValue>> {
         let idl_class = idl::get_class(classname)?;
 
@@ -262,6 +290,7 @@ impl Translator {
         let mut search = IdlClassSearch::new(classname);
         search.set_filter(filter);
         search.flesh = flesh_def;
+        search.for_update = for_update;
 
         let mut list = self.idl_class_search(&search)?;
 
@@ -510,7 +539,7 @@ impl Translator {
 
         if let Err(ref e) = query_res {
             log::error!("DB Error: {e} query={query} param={params:?}");
-            Err(format!("DB query failed. See error logs"))?;
+            Err(db::query_error(e, "DB query failed. See error logs"))?;
         }
 
         // Use the primary key values reported by PG to find the
@@ -607,7 +636,7 @@ impl Translator {
             }
             Err(e) => {
                 log::error!("DB Error: {e} query={query} param={params:?}");
-                Err(format!("DB query failed. See error logs").into())
+                Err(db::query_error(&e, "DB query failed. See error logs"))
             }
         }
     }
@@ -689,6 +718,10 @@ impl Translator {
             query += &self.compile_pager(pager);
         }
 
+        if search.for_update {
+            query += " FOR UPDATE";
+        }
+
         log::debug!("search() executing query: {query}");
 
         let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
@@ -700,7 +733,7 @@ impl Translator {
 
         if let Err(ref e) = query_res {
             log::error!("DB Error: {e} query={query} param={params:?}");
-            Err(format!("DB query failed. See error logs"))?;
+            Err(db::query_error(e, "DB query failed. See error logs"))?;
         }
 
         for row in query_res.unwrap() {
@@ -1,4 +1,18 @@
 //! Connect to OpenSRF/Redis, load host settings, and load the IDL.
+//!
+//! These environment variables override the logging settings in
+//! opensrf_core.xml for clients, gateways, and routers alike:
+//!
+//! * `OSRF_LOG_LEVEL` -- 1-5 or error, warn, info, debug, trace.
+//! * `OSRF_LOG_FACILITY` -- Syslog facility, e.g. LOCAL4.
+//! * `OSRF_LOG_FILE` -- syslog, stdout, stderr, or a file path.
+//! * `OSRF_LOG_FORMAT` -- plain or json.  JSON logs contain one object
+//!   per line and apply to all targets except syslog.
+//!
+//! Bus credentials may likewise be overridden with `OSRF_BUS_USERNAME`,
+//! `OSRF_BUS_PASSWORD`, `OSRF_BUS_ACL_USERNAME` (Redis ACL user, when it
+//! differs from the bus username), and `OSRF_BUS_PASSWORD_FILE`, which
+//! is re-read whenever Redis rejects our credentials.
 use crate::idl;
 use crate::osrf::conf;
 use crate::osrf::logging;
@@ -6,138 +20,249 @@ use crate::osrf::sclient::HostSettings;
 use crate::Client;
 use crate::EgResult;
 use std::env;
+use std::sync::Mutex;
 
 const DEFAULT_OSRF_CONFIG: &str = "/openils/conf/opensrf_core.xml";
 const DEFAULT_IDL_PATH: &str = "/openils/conf/fm_IDL.xml";
 
-pub struct InitOptions {
-    /// Skip logging initialization.
-    /// Useful if changes to the logging config first.
-    pub skip_logging: bool,
+/// Serializes initialization and records whether the shared OpenSRF
+/// config and logging have been set up.
+static INIT_STATE: Mutex<bool> = Mutex::new(false);
 
-    /// Skip fetching the host settings from opensrf.settings
-    pub skip_host_settings: bool,
+/// Builds the process-wide Evergreen context -- the OpenSRF config,
+/// logging, host settings, and IDL -- and returns a connected Client.
+///
+/// The context is shared by the whole process.  Calling build() again,
+/// e.g. from a second component embedded in the same binary, reuses
+/// the existing context, loads any parts the earlier builds skipped,
+/// and returns a new Client connection.  Appname and logging options
+/// only apply to the first build.
+///
+/// ```no_run
+/// use evergreen as eg;
+///
+/// let client = eg::init::Builder::new()
+///     .appname("sip2-server")
+///     .skip_host_settings()
+///     .idl_from_cache("/var/cache/eg/fm_IDL.xml")
+///     .build()
+///     .unwrap();
+///
+/// // Another component in the same process gets its own connection.
+/// let client2 = eg::init::Builder::new().skip_host_settings().build().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    appname: Option<String>,
+    skip_logging: bool,
+    skip_host_settings: bool,
+    skip_idl: bool,
+    idl_file: Option<String>,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Default::default()
+    }
 
     /// Application name to use with syslog.
-    pub appname: Option<String>,
-}
+    pub fn appname(mut self, name: &str) -> Builder {
+        self.appname = Some(name.to_string());
+        self
+    }
 
-impl InitOptions {
-    pub fn new() -> InitOptions {
-        InitOptions {
-            skip_logging: false,
-            skip_host_settings: false,
-            appname: None,
-        }
+    /// Skip logging initialization.
+    ///
+    /// Useful if the caller wants to initialize logging from a
+    /// different section of the config, e.g. gateways and routers.
+    pub fn skip_logging(mut self) -> Builder {
+        self.skip_logging = true;
+        self
     }
-}
 
-/// Read environment variables, parse the core config, setup logging.
-///
-/// This does not connect to the bus.
-pub fn init() -> EgResult<Client> {
-    with_options(&InitOptions::new())
-}
+    /// Skip fetching the host settings from opensrf.settings.
+    ///
+    /// Host settings are typically only available on private domains.
+    pub fn skip_host_settings(mut self) -> Builder {
+        self.skip_host_settings = true;
+        self
+    }
 
-/// Parse the OpenSRF config file, connect to the message bus, and
-/// optionally fetch the host settings and initialize logging.
-pub fn osrf_init(options: &InitOptions) -> EgResult<Client> {
-    let builder = if let Ok(fname) = env::var("OSRF_CONFIG") {
-        conf::ConfigBuilder::from_file(&fname)?
-    } else {
-        conf::ConfigBuilder::from_file(DEFAULT_OSRF_CONFIG)?
-    };
-
-    let mut config = builder.build()?;
-
-    if let Ok(_) = env::var("OSRF_LOCALHOST") {
-        config.set_hostname("localhost");
-    } else if let Ok(v) = env::var("OSRF_HOSTNAME") {
-        config.set_hostname(&v);
+    /// Skip loading the IDL.
+    pub fn skip_idl(mut self) -> Builder {
+        self.skip_idl = true;
+        self
     }
 
-    // When custom client connection/logging values are provided via
-    // the ENV, propagate them to all variations of a client connection
-    // supported by the current opensrf_core.xml format.
+    /// Load the IDL from a local copy of the file instead of locating
+    /// it via EG_IDL_FILE or the host settings.
+    pub fn idl_from_cache(mut self, path: &str) -> Builder {
+        self.idl_file = Some(path.to_string());
+        self
+    }
+
+    pub fn build(self) -> EgResult<Client> {
+        let mut initialized = INIT_STATE
+            .lock()
+            .map_err(|e| format!("Init lock poisoned: {e}"))?;
 
-    if let Ok(level) = env::var("OSRF_LOG_LEVEL") {
-        config.client_mut().logging_mut().set_log_level(&level);
-        if let Some(gateway) = config.gateway_mut() {
-            gateway.logging_mut().set_log_level(&level);
+        if !*initialized {
+            self.init_config()?;
+            *initialized = true;
+        } else {
+            log::debug!("Reusing existing Evergreen context");
         }
-        for router in config.routers_mut() {
-            router.client_mut().logging_mut().set_log_level(&level);
+
+        let client = connect()?;
+
+        // We try to get the IDL path from opensrf.settings, but that will
+        // fail if we are not connected to a domain running opensrf.settings
+        // (e.g. a public domain).
+
+        if !self.skip_host_settings && !HostSettings::is_loaded() {
+            HostSettings::load(&client)?;
+        }
+
+        if !self.skip_idl && !idl::is_loaded() {
+            match self.idl_file.as_deref() {
+                Some(path) => idl::Parser::load_file(path)?,
+                None => load_idl()?,
+            }
         }
+
+        Ok(client)
     }
 
-    if let Ok(facility) = env::var("OSRF_LOG_FACILITY") {
-        config
-            .client_mut()
-            .logging_mut()
-            .set_syslog_facility(&facility)?;
-        if let Some(gateway) = config.gateway_mut() {
-            gateway.logging_mut().set_syslog_facility(&facility)?;
+    /// Parse the OpenSRF config file, apply environment overrides,
+    /// and optionally initialize logging.
+    fn init_config(&self) -> EgResult<()> {
+        let builder = if let Ok(fname) = env::var("OSRF_CONFIG") {
+            conf::ConfigBuilder::from_file(&fname)?
+        } else {
+            conf::ConfigBuilder::from_file(DEFAULT_OSRF_CONFIG)?
+        };
+
+        let mut config = builder.build()?;
+        if let Ok(_) = env::var("OSRF_LOCALHOST") {
+            config.set_hostname("localhost");
+        } else if let Ok(v) = env::var("OSRF_HOSTNAME") {
+            config.set_hostname(&v);
         }
-        for router in config.routers_mut() {
-            router
+
+        // When custom client connection/logging values are provided via
+        // the ENV, propagate them to all variations of a client connection
+        // supported by the current opensrf_core.xml format.
+
+        if let Ok(level) = env::var("OSRF_LOG_LEVEL") {
+            config.client_mut().logging_mut().set_log_level(&level);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.logging_mut().set_log_level(&level);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().logging_mut().set_log_level(&level);
+            }
+        }
+
+        // e.g. OSRF_LOG_FILE=stdout for containers without syslog.
+        if let Ok(file) = env::var("OSRF_LOG_FILE") {
+            config.client_mut().logging_mut().set_log_file(&file);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.logging_mut().set_log_file(&file);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().logging_mut().set_log_file(&file);
+            }
+        }
+
+        if let Ok(format) = env::var("OSRF_LOG_FORMAT") {
+            config.client_mut().logging_mut().set_log_format(&format)?;
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.logging_mut().set_log_format(&format)?;
+            }
+            for router in config.routers_mut() {
+                router.client_mut().logging_mut().set_log_format(&format)?;
+            }
+        }
+
+        if let Ok(facility) = env::var("OSRF_LOG_FACILITY") {
+            config
                 .client_mut()
                 .logging_mut()
                 .set_syslog_facility(&facility)?;
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.logging_mut().set_syslog_facility(&facility)?;
+            }
+            for router in config.routers_mut() {
+                router
+                    .client_mut()
+                    .logging_mut()
+                    .set_syslog_facility(&facility)?;
+            }
         }
-    }
 
-    if let Ok(username) = env::var("OSRF_BUS_USERNAME") {
-        config.client_mut().set_username(&username);
-        if let Some(gateway) = config.gateway_mut() {
-            gateway.set_username(&username);
-        }
-        for router in config.routers_mut() {
-            router.client_mut().set_username(&username);
+        if let Ok(username) = env::var("OSRF_BUS_USERNAME") {
+            config.client_mut().set_username(&username);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.set_username(&username);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().set_username(&username);
+            }
         }
-    }
 
-    if let Ok(password) = env::var("OSRF_BUS_PASSWORD") {
-        config.client_mut().set_password(&password);
-        if let Some(gateway) = config.gateway_mut() {
-            gateway.set_password(&password);
-        }
-        for router in config.routers_mut() {
-            router.client_mut().set_password(&password);
+        if let Ok(password) = env::var("OSRF_BUS_PASSWORD") {
+            config.client_mut().set_password(&password);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.set_password(&password);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().set_password(&password);
+            }
         }
-    }
 
-    if !options.skip_logging {
-        let mut logger = logging::Logger::new(config.client().logging())?;
-        if let Some(name) = options.appname.as_ref() {
-            logger.set_application(name);
+        if let Ok(username) = env::var("OSRF_BUS_ACL_USERNAME") {
+            config.client_mut().set_acl_username(&username);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.set_acl_username(&username);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().set_acl_username(&username);
+            }
         }
-        logger
-            .init()
-            .or_else(|e| Err(format!("Error initializing logger: {e}")))?;
-    }
 
-    // Save the config as the one-true-global-osrf-config
-    config.store()?;
+        if let Ok(path) = env::var("OSRF_BUS_PASSWORD_FILE") {
+            config.client_mut().set_password_file(&path);
+            if let Some(gateway) = config.gateway_mut() {
+                gateway.set_password_file(&path);
+            }
+            for router in config.routers_mut() {
+                router.client_mut().set_password_file(&path);
+            }
+        }
 
-    let client = Client::connect().or_else(|e| Err(format!("Cannot connect to OpenSRF: {e}")))?;
+        if !self.skip_logging {
+            let mut logger = logging::Logger::new(config.client().logging())?;
+            if let Some(name) = self.appname.as_ref() {
+                logger.set_application(name);
+            }
+            logger
+                .init()
+                .or_else(|e| Err(format!("Error initializing logger: {e}")))?;
+        }
 
-    // We try to get the IDL path from opensrf.settings, but that will
-    // fail if we are not connected to a domain running opensrf.settings
-    // (e.g. a public domain).
+        // Save the config as the one-true-global-osrf-config
+        config.store()?;
 
-    if !options.skip_host_settings {
-        HostSettings::load(&client)?;
+        Ok(())
     }
-
-    Ok(client)
 }
 
-pub fn with_options(options: &InitOptions) -> EgResult<Client> {
-    let client = osrf_init(&options)?;
-
-    load_idl()?;
-
-    Ok(client)
+/// Read environment variables, parse the core config, setup logging,
+/// connect to the bus, and load the host settings and IDL.
+///
+/// Shorthand for `Builder::new().build()`.
+pub fn init() -> EgResult<Client> {
+    Builder::new().build()
 }
 
 /// Locate and parse the IDL file.
@@ -155,11 +280,15 @@ pub fn load_idl() -> EgResult<()> {
     idl::Parser::load_file(DEFAULT_IDL_PATH)
 }
 
+fn connect() -> EgResult<Client> {
+    Client::connect().or_else(|e| Err(format!("Cannot connect to OpenSRF: {e}").into()))
+}
+
 /// Create a new connection using pre-compiled context components.  Useful
 /// for spawned threads so they can avoid repetitive processing at
 /// connect time.
 ///
 /// The only part that must happen in its own thread is the opensrf connect.
 pub fn init_from_parts() -> EgResult<Client> {
-    Client::connect().or_else(|e| Err(format!("Cannot connect to OpenSRF: {e}").into()))
+    connect()
 }
@@ -16,6 +16,7 @@ pub mod common;
 pub mod constants;
 pub mod date;
 pub mod db;
+pub mod diff;
 pub mod editor;
 pub mod event;
 pub mod idl;
@@ -69,6 +69,14 @@ pub trait ApplicationWorker: Any {
     /// * `connected` - True if we are in the middle of a stateful conversation.
     fn worker_idle_wake(&mut self, connected: bool) -> EgResult<()>;
 
+    /// Called when an operator broadcasts a reload control command.
+    ///
+    /// Offers a chance to re-read any cached settings without
+    /// restarting the service.  Called between sessions only.
+    fn worker_reload(&mut self) -> EgResult<()> {
+        Ok(())
+    }
+
     /// Called after all work is done and the thread is going away.
     ///
     /// Offers a chance to clean up any resources.
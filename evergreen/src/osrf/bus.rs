@@ -1,16 +1,99 @@
+use crate as eg;
 use crate::osrf::addr::BusAddress;
 use crate::osrf::conf;
 use crate::osrf::logging::Logger;
 use crate::osrf::message::TransportMessage;
+use crate::result::{EgError, EgResult, ErrorKind};
 use crate::util;
-use crate::EgResult;
+use crate::EgValue;
 use redis::{Commands, ConnectionAddr, ConnectionInfo, RedisConnectionInfo};
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Per-thread channels for messages pulled by a BusReceiver, keyed on
+/// session thread.
+type Routes = Arc<Mutex<HashMap<String, mpsc::Sender<TransportMessage>>>>;
+
+/// Traffic counters for a single bus connection.
+///
+/// Counters accumulate for the life of the Bus, which may outlive
+/// the Client that uses it.  Use since() to get the activity between
+/// two snapshots.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BusStats {
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Number of times we reconnected to Redis.
+    pub reconnects: u64,
+    /// Number of list pops, including those which timed out empty.
+    pub recv_waits: u64,
+    /// Total time spent in list pops.  For blocking pops, this includes
+    /// time spent waiting on the other end to send us something.
+    pub recv_wait_time: Duration,
+}
+
+impl BusStats {
+    /// Average time spent per list pop.
+    pub fn avg_recv_wait(&self) -> Duration {
+        if self.recv_waits == 0 {
+            return Duration::ZERO;
+        }
+        self.recv_wait_time / self.recv_waits as u32
+    }
+
+    /// Activity which occurred since an earlier snapshot of the same Bus.
+    pub fn since(&self, earlier: &BusStats) -> BusStats {
+        BusStats {
+            messages_sent: self.messages_sent.saturating_sub(earlier.messages_sent),
+            messages_received: self
+                .messages_received
+                .saturating_sub(earlier.messages_received),
+            bytes_sent: self.bytes_sent.saturating_sub(earlier.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(earlier.bytes_received),
+            reconnects: self.reconnects.saturating_sub(earlier.reconnects),
+            recv_waits: self.recv_waits.saturating_sub(earlier.recv_waits),
+            recv_wait_time: self.recv_wait_time.saturating_sub(earlier.recv_wait_time),
+        }
+    }
+
+    /// Add another set of counters to our own, e.g. to total the
+    /// activity of many connections.
+    pub fn add(&mut self, other: &BusStats) {
+        self.messages_sent += other.messages_sent;
+        self.messages_received += other.messages_received;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        self.reconnects += other.reconnects;
+        self.recv_waits += other.recv_waits;
+        self.recv_wait_time += other.recv_wait_time;
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        eg::hash! {
+            "messages_sent": self.messages_sent,
+            "messages_received": self.messages_received,
+            "bytes_sent": self.bytes_sent,
+            "bytes_received": self.bytes_received,
+            "reconnects": self.reconnects,
+            "recv_waits": self.recv_waits,
+            "recv_wait_time": self.recv_wait_time.as_secs_f64(),
+            "avg_recv_wait": self.avg_recv_wait().as_secs_f64(),
+        }
+    }
+}
 
 /// Manages a Redis connection.
 pub struct Bus {
     connection: redis::Connection,
 
+    /// Kept so we can reconnect with fresh credentials.
+    config: conf::BusClient,
+
     /// Every bus connection has a unique client address.
     address: BusAddress,
 
@@ -22,20 +105,13 @@ pub struct Bus {
     /// messages to be parsed and serialized without concern for
     /// IDL-classed information stored in the message.
     raw_data_mode: bool,
+
+    stats: BusStats,
 }
 
 impl Bus {
     pub fn new(config: &conf::BusClient) -> EgResult<Self> {
-        let info = Bus::connection_info(config)?;
-
-        log::trace!("Bus::new() connecting to {:?}", info);
-
-        let client = redis::Client::open(info)
-            .or_else(|e| Err(format!("Error opening Redis connection: {e}")))?;
-
-        let connection = client
-            .get_connection()
-            .or_else(|e| Err(format!("Bus connect error: {e}")))?;
+        let connection = Bus::connect(config)?;
 
         let username = config.username();
         let domain = config.domain().name();
@@ -43,18 +119,127 @@ impl Bus {
 
         let bus = Bus {
             connection,
+            config: config.clone(),
             raw_data_mode: false,
             address: addr,
             router_name: config.router_name().to_string(),
+            stats: BusStats::default(),
         };
 
         Ok(bus)
     }
 
+    fn connect(config: &conf::BusClient) -> EgResult<redis::Connection> {
+        let info = Bus::connection_info(config)?;
+
+        log::trace!("Bus connecting to {:?}", info.addr);
+
+        let client = redis::Client::open(info).map_err(|e| {
+            EgError::new(
+                ErrorKind::Network,
+                &format!("Error opening Redis connection: {e}"),
+            )
+        })?;
+
+        client
+            .get_connection()
+            .map_err(|e| EgError::new(ErrorKind::Network, &format!("Bus connect error: {e}")))
+    }
+
+    /// Replace our Redis connection with one that uses our current
+    /// credentials, re-reading the password file if we have one.
+    ///
+    /// Our bus address is unchanged.
+    pub fn reauthenticate(&mut self) -> EgResult<()> {
+        log::info!("{self} reconnecting with refreshed credentials");
+        self.connection = Bus::connect(&self.config)?;
+        self.stats.reconnects += 1;
+        Ok(())
+    }
+
+    /// Traffic counters for this connection.
+    pub fn stats(&self) -> &BusStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = BusStats::default();
+    }
+
+    /// Run a Redis command, reconnecting and trying once more if the
+    /// server rejects our credentials, e.g. after a password rotation.
+    fn redis_op<T>(
+        &mut self,
+        op: impl Fn(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> redis::RedisResult<T> {
+        match op(&mut self.connection) {
+            Err(e) if is_auth_error(&e) => {
+                log::warn!("{self} bus rejected our credentials: {e}");
+
+                if let Err(re) = self.reauthenticate() {
+                    log::error!("{self} cannot reauthenticate: {re}");
+                    return Err(e);
+                }
+
+                op(&mut self.connection)
+            }
+            result => result,
+        }
+    }
+
     pub fn set_raw_data_mode(&mut self, on: bool) {
         self.raw_data_mode = on;
     }
 
+    /// Split this connection into a sender, which may be cloned and
+    /// shared across threads, and a receiver for a single listener
+    /// thread, so many threads can share one bus address.
+    ///
+    /// The receiver uses a second Redis connection, so a blocking
+    /// recv() never holds up senders.  Use Client::take_bus() to split
+    /// a Client's connection.
+    ///
+    /// ```no_run
+    /// use evergreen::osrf::bus::Bus;
+    /// use evergreen::osrf::conf;
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let bus = Bus::new(conf::config().client()).expect("Connected");
+    /// let (sender, mut receiver) = bus.split().expect("Split");
+    ///
+    /// let shutdown = Arc::new(AtomicBool::new(false));
+    /// let listen_shutdown = shutdown.clone();
+    ///
+    /// thread::spawn(move || receiver.run(&listen_shutdown, 3));
+    ///
+    /// // Each worker thread subscribes to replies for its own
+    /// // session thread, then sends with its clone of the sender.
+    /// let replies = sender.subscribe("my-session-thread");
+    /// ```
+    pub fn split(self) -> EgResult<(BusSender, BusReceiver)> {
+        let mut bus = Bus::new(&self.config)?;
+        bus.set_address(self.address());
+        bus.set_raw_data_mode(self.raw_data_mode);
+
+        let routes: Routes = Arc::new(Mutex::new(HashMap::new()));
+
+        let receiver = BusReceiver {
+            bus,
+            routes: routes.clone(),
+        };
+
+        let sender = BusSender {
+            address: self.address().clone(),
+            router_name: self.router_name().to_string(),
+            bus: Arc::new(Mutex::new(self)),
+            routes,
+        };
+
+        Ok((sender, receiver))
+    }
+
     /// Generates the Redis connection Info
     ///
     /// Builds the connection info by hand because it gives us more
@@ -62,8 +247,8 @@ impl Bus {
     fn connection_info(config: &conf::BusClient) -> EgResult<ConnectionInfo> {
         let redis_con = RedisConnectionInfo {
             db: 0,
-            username: Some(config.username().to_string()),
-            password: Some(config.password().to_string()),
+            username: Some(config.acl_username().to_string()),
+            password: Some(config.current_password()?),
         };
 
         let domain = config.domain();
@@ -123,19 +308,28 @@ impl Bus {
         };
 
         let value: String;
+        let start = Instant::now();
 
         if timeout == 0 {
             // non-blocking
 
             // LPOP returns a scalar response.
-            value = match self.connection().lpop(&recipient, None) {
+            let result = self.redis_op(|c| c.lpop(&recipient, None));
+            self.record_recv_wait(start);
+
+            value = match result {
                 Ok(c) => c,
                 Err(e) => match e.kind() {
                     redis::ErrorKind::TypeError => {
                         // Will read a Nil value on timeout.  That's OK.
                         return Ok(None);
                     }
-                    _ => return Err(format!("recv_one_chunk failed: {e}").into()),
+                    _ => {
+                        return Err(EgError::new(
+                            ErrorKind::Network,
+                            &format!("recv_one_chunk failed: {e}"),
+                        ))
+                    }
                 },
             };
         } else {
@@ -146,10 +340,15 @@ impl Bus {
                 timeout = 0;
             }
 
-            let mut resp: Vec<String> = self
-                .connection()
-                .blpop(&recipient, timeout as usize)
-                .or_else(|e| Err(format!("Redis blpop error recipient={recipient} : {e}")))?;
+            let result = self.redis_op(|c| c.blpop(&recipient, timeout as usize));
+            self.record_recv_wait(start);
+
+            let mut resp: Vec<String> = result.map_err(|e| {
+                EgError::new(
+                    ErrorKind::Network,
+                    &format!("Redis blpop error recipient={recipient} : {e}"),
+                )
+            })?;
 
             if resp.len() > 1 {
                 // BLPOP returns the name of the popped list and the value.
@@ -163,9 +362,17 @@ impl Bus {
 
         log::trace!("recv_one_value() pulled from bus: {}", value);
 
+        self.stats.messages_received += 1;
+        self.stats.bytes_received += value.len() as u64;
+
         Ok(Some(value))
     }
 
+    fn record_recv_wait(&mut self, start: Instant) {
+        self.stats.recv_waits += 1;
+        self.stats.recv_wait_time += start.elapsed();
+    }
+
     /// Returns at most one JSON value pulled from the queue or None if
     /// the list pop times out or the pop is interrupted by a signal.
     fn recv_one_value(
@@ -311,21 +518,30 @@ impl Bus {
 
         log::trace!("send() writing chunk to={}: {}", recipient, json_str);
 
-        let res: Result<i32, _> = self.connection().rpush(recipient, json_str);
+        let res: Result<i32, _> = self.redis_op(|c| c.rpush(recipient, &json_str));
 
         if let Err(e) = res {
-            return Err(format!("Error in send() {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in send() {e}"),
+            ));
         }
 
+        self.stats.messages_sent += 1;
+        self.stats.bytes_sent += json_str.len() as u64;
+
         Ok(())
     }
 
     /// Returns a list of keys that match the provided pattern.
     pub fn keys(&mut self, pattern: &str) -> EgResult<Vec<String>> {
-        let res: Result<Vec<String>, _> = self.connection().keys(pattern);
+        let res: Result<Vec<String>, _> = self.redis_op(|c| c.keys(pattern));
 
         if let Err(e) = res {
-            return Err(format!("Error in keys(): {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in keys(): {e}"),
+            ));
         }
 
         Ok(res.unwrap())
@@ -333,10 +549,13 @@ impl Bus {
 
     /// Returns the length of the array specified by 'key'.
     pub fn llen(&mut self, key: &str) -> EgResult<i32> {
-        let res: Result<i32, _> = self.connection().llen(key);
+        let res: Result<i32, _> = self.redis_op(|c| c.llen(key));
 
         if let Err(e) = res {
-            return Err(format!("Error in llen(): {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in llen(): {e}"),
+            ));
         }
 
         Ok(res.unwrap())
@@ -346,10 +565,13 @@ impl Bus {
     ///
     /// Return -1 if no expire time is set, -2 if no such key exists.
     pub fn ttl(&mut self, key: &str) -> EgResult<i32> {
-        let res: Result<i32, _> = self.connection().ttl(key);
+        let res: Result<i32, _> = self.redis_op(|c| c.ttl(key));
 
         if let Err(e) = res {
-            return Err(format!("Error in ttl(): {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in ttl(): {e}"),
+            ));
         }
 
         Ok(res.unwrap())
@@ -357,10 +579,13 @@ impl Bus {
 
     /// Returns an array slice as a Vec of Strings.
     pub fn lrange(&mut self, key: &str, start: isize, stop: isize) -> EgResult<Vec<String>> {
-        let res: Result<Vec<String>, _> = self.connection().lrange(key, start, stop);
+        let res: Result<Vec<String>, _> = self.redis_op(|c| c.lrange(key, start, stop));
 
         if let Err(e) = res {
-            return Err(format!("Error in lrange(): {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in lrange(): {e}"),
+            ));
         }
 
         Ok(res.unwrap())
@@ -368,10 +593,13 @@ impl Bus {
 
     /// Set the expire time on the specified key to 'timeout' seconds from now.
     pub fn set_key_timeout(&mut self, key: &str, timeout: u64) -> EgResult<i32> {
-        let res: Result<i32, _> = self.connection().expire(key, timeout as usize);
+        let res: Result<i32, _> = self.redis_op(|c| c.expire(key, timeout as usize));
 
         if let Err(ref e) = res {
-            Err(format!("Error in set_key_timeout(): {e}"))?;
+            Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in set_key_timeout(): {e}"),
+            ))?;
         }
 
         let val = res.unwrap();
@@ -381,16 +609,158 @@ impl Bus {
     /// Remove all pending data from the recipient queue.
     pub fn clear_bus(&mut self) -> EgResult<()> {
         let stream = self.address().as_str().to_string(); // mut borrow
-        let res: Result<i32, _> = self.connection().del(stream);
+        let res: Result<i32, _> = self.redis_op(|c| c.del(&stream));
 
         if let Err(e) = res {
-            return Err(format!("Error in queue clear(): {e}").into());
+            return Err(EgError::new(
+                ErrorKind::Network,
+                &format!("Error in queue clear(): {e}"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Sending half of a split Bus.
+///
+/// Clones share one Redis connection and bus address.  Replies to
+/// messages we send arrive at the paired BusReceiver.
+#[derive(Clone)]
+pub struct BusSender {
+    bus: Arc<Mutex<Bus>>,
+    address: BusAddress,
+    router_name: String,
+    routes: Routes,
+}
+
+impl BusSender {
+    /// The bus address shared by both halves of the split Bus.
+    pub fn address(&self) -> &BusAddress {
+        &self.address
+    }
+
+    /// The name of the router running on our primary domain.
+    pub fn router_name(&self) -> &str {
+        &self.router_name
+    }
+
+    fn bus(&self) -> EgResult<MutexGuard<'_, Bus>> {
+        self.bus
+            .lock()
+            .map_err(|e| format!("Cannot lock bus sender: {e}").into())
+    }
+
+    /// Send a TransportMessage to the "to" value in the message.
+    pub fn send(&self, msg: TransportMessage) -> EgResult<()> {
+        self.bus()?.send(msg)
+    }
+
+    /// Send a TransportMessage to the specified BusAddress, regardless
+    /// of what value is in the msg.to() field.
+    pub fn send_to(&self, msg: TransportMessage, recipient: &str) -> EgResult<()> {
+        self.bus()?.send_to(msg, recipient)
+    }
+
+    /// Traffic counters for the sending connection.
+    pub fn stats(&self) -> EgResult<BusStats> {
+        Ok(*self.bus()?.stats())
+    }
+
+    /// Have BusReceiver::run() relay messages for the provided session
+    /// thread to the returned channel.
+    ///
+    /// Replaces any existing subscription for the thread.
+    pub fn subscribe(&self, thread: &str) -> mpsc::Receiver<TransportMessage> {
+        let (tx, rx) = mpsc::channel();
+
+        if let Ok(mut routes) = self.routes.lock() {
+            routes.insert(thread.to_string(), tx);
+        }
+
+        rx
+    }
+
+    pub fn unsubscribe(&self, thread: &str) {
+        if let Ok(mut routes) = self.routes.lock() {
+            routes.remove(thread);
+        }
+    }
+}
+
+impl fmt::Display for BusSender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BusSender {}", self.address.as_str())
+    }
+}
+
+/// Receiving half of a split Bus.
+pub struct BusReceiver {
+    bus: Bus,
+    routes: Routes,
+}
+
+impl BusReceiver {
+    /// The bus address shared by both halves of the split Bus.
+    pub fn address(&self) -> &BusAddress {
+        self.bus.address()
+    }
+
+    /// Returns at most one TransportMessage, ignoring subscriptions.
+    ///
+    /// See Bus::recv().
+    pub fn recv(&mut self, timeout: i32) -> EgResult<Option<TransportMessage>> {
+        self.bus.recv(timeout, None)
+    }
+
+    /// Relay each inbound message to the channel subscribed to its
+    /// session thread until shutdown is set.
+    ///
+    /// Messages for threads with no subscriber are dropped.
+    ///
+    /// * `poll_interval` - Seconds to wait for a message before
+    ///   checking for shutdown.
+    pub fn run(&mut self, shutdown: &AtomicBool, poll_interval: i32) -> EgResult<()> {
+        while !shutdown.load(Ordering::Relaxed) {
+            let msg = match self.recv(poll_interval)? {
+                Some(m) => m,
+                None => continue,
+            };
+
+            let thread = msg.thread().to_string();
+
+            let mut routes = self
+                .routes
+                .lock()
+                .map_err(|e| format!("Cannot lock bus routes: {e}"))?;
+
+            let Some(tx) = routes.get(&thread) else {
+                log::warn!("{self} dropping message for unknown thread {thread}");
+                continue;
+            };
+
+            if tx.send(msg).is_err() {
+                log::debug!("{self} subscriber for thread {thread} has gone away");
+                routes.remove(&thread);
+            }
         }
 
         Ok(())
     }
 }
 
+impl fmt::Display for BusReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BusReceiver {}", self.address().as_str())
+    }
+}
+
+/// True if Redis rejected our credentials or requires us to log in.
+fn is_auth_error(err: &redis::RedisError) -> bool {
+    err.kind() == redis::ErrorKind::AuthenticationFailed
+        || matches!(err.code(), Some("NOAUTH") | Some("WRONGPASS"))
+}
+
 /// Good for debugging / logging
 impl fmt::Display for Bus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -2,6 +2,7 @@ use crate::osrf::sclient::HostSettings;
 use crate::EgResult;
 use crate::EgValue;
 use memcache;
+use redis::Commands;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
@@ -31,11 +32,23 @@ const ANON_CACHE_NAME: &str = "anon";
     <max_cache_size>102400</max_cache_size>
   </anon>
 </cache>
+
+Servers starting with redis:// (e.g. redis://127.0.0.1:6379/1) use
+Redis instead of memcached.  Only the first Redis server is used.
 */
 
+/// Server URL prefixes which select the Redis backend.
+const REDIS_PREFIXES: &[&str] = &["redis://", "rediss://"];
+
+enum CacheBackend {
+    Memcache(memcache::Client),
+    // Redis commands require a mutable connection.
+    Redis(RefCell<redis::Connection>),
+}
+
 pub struct CacheConnection {
     name: String,
-    memcache: memcache::Client,
+    backend: CacheBackend,
     max_cache_time: u32,
     max_cache_size: u32,
 }
@@ -68,13 +81,130 @@ impl CacheConnection {
             timeout = self.max_cache_time;
         }
 
-        self.memcache
-            .set(key, &value, timeout)
-            .map_err(|e| format!("{self} set key={key} failed: {e}").into())
+        let result = match &self.backend {
+            CacheBackend::Memcache(mc) => mc.set(key, &value, timeout).map_err(|e| e.to_string()),
+            CacheBackend::Redis(conn) => conn
+                .borrow_mut()
+                .set_ex(key, value, timeout as usize)
+                .map_err(|e| e.to_string()),
+        };
+
+        result.map_err(|e| format!("{self} set key={key} failed: {e}").into())
+    }
+
+    /// Store a value only if the key is not already in the cache.
+    ///
+    /// Returns true if our value was stored.
+    ///
+    /// Memcache's text protocol does not report whether an "add"
+    /// stored anything, so the stored value is read back and compared
+    /// with ours.  Callers racing to add the same key should use
+    /// values which are unique to the caller.
+    fn add(&self, key: &str, value: EgValue, mut timeout: u32) -> EgResult<bool> {
+        let value = value.into_json_value().dump();
+
+        if value.len() > self.max_cache_size as usize {
+            return Err(format!(
+                "{self} key={key} exceeds the max size of {}",
+                self.max_cache_size
+            )
+            .into());
+        }
+
+        if timeout == 0 {
+            timeout = self.max_cache_time;
+        }
+
+        let result = match &self.backend {
+            CacheBackend::Memcache(mc) => match mc.add(key, &value, timeout) {
+                Ok(()) => mc
+                    .get::<String>(key)
+                    .map(|stored| stored.as_deref() == Some(value.as_str()))
+                    .map_err(|e| e.to_string()),
+                Err(memcache::MemcacheError::CommandError(memcache::CommandError::KeyExists)) => {
+                    Ok(false)
+                }
+                Err(e) => Err(e.to_string()),
+            },
+            CacheBackend::Redis(conn) => redis::cmd("SET")
+                .arg(key)
+                .arg(&value)
+                .arg("NX")
+                .arg("EX")
+                .arg(timeout)
+                .query::<Option<String>>(&mut *conn.borrow_mut())
+                .map(|reply| reply.is_some())
+                .map_err(|e| e.to_string()),
+        };
+
+        result.map_err(|e| format!("{self} add key={key} failed: {e}").into())
+    }
+
+    /// Add 1 to a counter, creating the counter with the provided
+    /// timeout if it does not exist.
+    ///
+    /// Returns the new value of the counter.
+    fn incr(&self, key: &str, mut timeout: u32) -> EgResult<u64> {
+        if timeout == 0 {
+            timeout = self.max_cache_time;
+        }
+
+        let result = match &self.backend {
+            CacheBackend::Memcache(mc) => {
+                let mut result = mc.increment(key, 1);
+
+                // Create the counter if needed, then try again.  Another
+                // caller may create it first, which is fine.
+                for _ in 0..3 {
+                    if !matches!(
+                        result,
+                        Err(memcache::MemcacheError::CommandError(
+                            memcache::CommandError::KeyNotFound
+                        ))
+                    ) {
+                        break;
+                    }
+
+                    match mc.add(key, "0", timeout) {
+                        Ok(())
+                        | Err(memcache::MemcacheError::CommandError(
+                            memcache::CommandError::KeyExists,
+                        )) => {}
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+
+                    result = mc.increment(key, 1);
+                }
+
+                result.map_err(|e| e.to_string())
+            }
+            CacheBackend::Redis(conn) => {
+                let mut conn = conn.borrow_mut();
+                conn.incr::<&str, u64, u64>(key, 1)
+                    .and_then(|n| {
+                        // INCR creates missing keys with no expiry.
+                        if n == 1 {
+                            conn.expire::<&str, bool>(key, timeout as usize)?;
+                        }
+                        Ok(n)
+                    })
+                    .map_err(|e| e.to_string())
+            }
+        };
+
+        result.map_err(|e| format!("{self} incr key={key} failed: {e}").into())
     }
 
     fn get(&self, key: &str) -> EgResult<Option<EgValue>> {
-        let result: Option<String> = match self.memcache.get(key) {
+        let result: Result<Option<String>, String> = match &self.backend {
+            CacheBackend::Memcache(mc) => mc.get(key).map_err(|e| e.to_string()),
+            CacheBackend::Redis(conn) => conn.borrow_mut().get(key).map_err(|e| e.to_string()),
+        };
+
+        let result = match result {
             Ok(r) => r,
             Err(e) => return Err(format!("{self} get key={key} failed: {e}").into()),
         };
@@ -95,10 +225,16 @@ impl CacheConnection {
     }
 
     fn del(&self, key: &str) -> EgResult<()> {
-        self.memcache
-            .delete(key)
-            .map(|_| ())
-            .map_err(|e| format!("{self} del key={key} failed: {e}").into())
+        let result = match &self.backend {
+            CacheBackend::Memcache(mc) => mc.delete(key).map(|_| ()).map_err(|e| e.to_string()),
+            CacheBackend::Redis(conn) => conn
+                .borrow_mut()
+                .del::<&str, i32>(key)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        };
+
+        result.map_err(|e| format!("{self} del key={key} failed: {e}").into())
     }
 }
 
@@ -127,10 +263,12 @@ impl Cache {
 
         let mut servers = Vec::new();
         if let Some(server) = config["servers"]["server"].as_str() {
-            servers.push(format!("memcache://{server}"));
+            servers.push(server.to_string());
         } else {
             for server in config["servers"]["server"].members() {
-                servers.push(format!("memcache://{server}"));
+                if let Some(server) = server.as_str() {
+                    servers.push(server.to_string());
+                }
             }
         }
 
@@ -146,25 +284,39 @@ impl Cache {
 
         log::info!("Connecting to cache servers: {servers:?}");
 
-        let mc = match memcache::connect(servers) {
-            Ok(mc) => mc,
-            Err(e) => {
-                return Err(format!(
-                    "Cannot connect to memcache with config: {} : {e}",
-                    config.clone().into_json_value().dump()
-                )
-                .into());
+        let redis_server = servers
+            .iter()
+            .find(|s| REDIS_PREFIXES.iter().any(|p| s.starts_with(p)));
+
+        let backend = if let Some(server) = redis_server {
+            let connection = redis::Client::open(server.as_str())
+                .and_then(|c| c.get_connection())
+                .map_err(|e| format!("Cannot connect to Redis cache at {server}: {e}"))?;
+
+            CacheBackend::Redis(RefCell::new(connection))
+        } else {
+            let servers: Vec<String> = servers.iter().map(|s| format!("memcache://{s}")).collect();
+
+            match memcache::connect(servers) {
+                Ok(mc) => CacheBackend::Memcache(mc),
+                Err(e) => {
+                    return Err(format!(
+                        "Cannot connect to memcache with config: {} : {e}",
+                        config.clone().into_json_value().dump()
+                    )
+                    .into());
+                }
             }
         };
 
         let cache = CacheConnection {
-            name: GLOBAL_CACHE_NAME.to_string(),
-            memcache: mc,
+            name: cache_name.to_string(),
+            backend,
             max_cache_time: cache_time,
             max_cache_size: cache_size,
         };
 
-        CACHE_CONNECTIONS.with(|c| c.borrow_mut().insert(GLOBAL_CACHE_NAME.to_string(), cache));
+        CACHE_CONNECTIONS.with(|c| c.borrow_mut().insert(cache_name.to_string(), cache));
 
         Ok(())
     }
@@ -227,6 +379,42 @@ impl Cache {
         Cache::set(GLOBAL_CACHE_NAME, key, value, timeout)
     }
 
+    /// Store a value using the specified cache unless the key is
+    /// already present.
+    ///
+    /// Returns true if the value was stored.
+    pub fn add(cache_name: &str, key: &str, value: EgValue, timeout: u32) -> EgResult<bool> {
+        Cache::verify_cache(cache_name)?;
+
+        let mut result = Ok(false);
+        CACHE_CONNECTIONS
+            .with(|c| result = c.borrow().get(cache_name).unwrap().add(key, value, timeout));
+        result
+    }
+
+    /// Shortcut for adding a value to the "global" cache with the
+    /// provided timeout.
+    pub fn add_global_for(key: &str, value: EgValue, timeout: u32) -> EgResult<bool> {
+        Cache::add(GLOBAL_CACHE_NAME, key, value, timeout)
+    }
+
+    /// Add 1 to a counter in the specified cache, creating the counter
+    /// with the provided timeout if needed.
+    ///
+    /// Returns the new value of the counter.
+    pub fn incr(cache_name: &str, key: &str, timeout: u32) -> EgResult<u64> {
+        Cache::verify_cache(cache_name)?;
+
+        let mut result = Ok(0);
+        CACHE_CONNECTIONS.with(|c| result = c.borrow().get(cache_name).unwrap().incr(key, timeout));
+        result
+    }
+
+    /// Shortcut for incrementing a counter in the "global" cache.
+    pub fn incr_global_for(key: &str, timeout: u32) -> EgResult<u64> {
+        Cache::incr(GLOBAL_CACHE_NAME, key, timeout)
+    }
+
     /// Shortcut for storing a value in the "anon" cache with the
     /// default timeout.
     pub fn set_anon(key: &str, value: EgValue) -> EgResult<()> {
@@ -12,6 +12,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 /// Generally speaking, we only need 1 ClientSingleton per thread (hence
 /// the name).  This manages one bus connection per domain and stores
@@ -106,6 +107,21 @@ impl ClientSingleton {
         self.bus = Some(bus);
     }
 
+    /// Traffic counters for each of our bus connections, keyed on domain.
+    pub fn bus_stats(&self) -> HashMap<String, bus::BusStats> {
+        let mut stats: HashMap<String, bus::BusStats> = self
+            .remote_bus_map
+            .iter()
+            .map(|(domain, bus)| (domain.to_string(), *bus.stats()))
+            .collect();
+
+        if let Some(bus) = self.bus.as_ref() {
+            stats.insert(self.domain.to_string(), *bus.stats());
+        }
+
+        stats
+    }
+
     pub fn get_domain_bus(&mut self, domain: &str) -> EgResult<&mut bus::Bus> {
         log::trace!("Loading bus connection for domain: {domain}");
 
@@ -337,6 +353,26 @@ impl Client {
         self.singleton().borrow_mut().bus_mut().clear_bus()
     }
 
+    /// Wrapper for ClientSingleton::bus_stats()
+    ///
+    /// ```no_run
+    /// let client = evergreen::init().unwrap();
+    ///
+    /// client.send_recv_one("opensrf.settings", "opensrf.system.echo", "hello").unwrap();
+    ///
+    /// for (domain, stats) in client.bus_stats() {
+    ///     println!(
+    ///         "{domain}: sent={} received={} avg-wait={:?}",
+    ///         stats.messages_sent,
+    ///         stats.messages_received,
+    ///         stats.avg_recv_wait()
+    ///     );
+    /// }
+    /// ```
+    pub fn bus_stats(&self) -> HashMap<String, bus::BusStats> {
+        self.singleton().borrow().bus_stats()
+    }
+
     /// Wrapper for ClientSingleton::send_router_command()
     pub fn send_router_command(
         &self,
@@ -387,3 +423,275 @@ impl Client {
         req.first()
     }
 }
+
+struct CachedResponse {
+    value: EgValue,
+    expires: Instant,
+}
+
+/// Client wrapper which caches API responses in memory, keyed on
+/// service, method, and parameters.
+///
+/// Intended for lookups of data which rarely changes, e.g. org units,
+/// settings, and other static data.  Only the first response to each
+/// call is cached.  Calls which return no response are not cached.
+///
+/// ```no_run
+/// use evergreen::osrf::client::{CachingClient, Client};
+///
+/// let client = Client::connect().unwrap();
+/// let mut cached = CachingClient::new(client, 300, 1000);
+///
+/// // Only the first call reaches the service.
+/// for _ in 0..3 {
+///     cached.send_recv_one("open-ils.actor", "open-ils.actor.org_tree.retrieve", None).unwrap();
+/// }
+///
+/// // Force the next call to reach the service.
+/// cached.invalidate("open-ils.actor", "open-ils.actor.org_tree.retrieve");
+/// ```
+pub struct CachingClient {
+    client: Client,
+    ttl: Duration,
+    max_entries: usize,
+    entries: HashMap<String, CachedResponse>,
+}
+
+impl CachingClient {
+    /// * `ttl` - Seconds a cached response remains valid.
+    /// * `max_entries` - Once reached, expired responses are purged,
+    ///   followed by those closest to expiring.
+    pub fn new(client: Client, ttl: u64, max_entries: usize) -> Self {
+        CachingClient {
+            client,
+            ttl: Duration::from_secs(ttl),
+            max_entries,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Number of responses currently cached, including expired
+    /// responses which have not yet been purged.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Cache key prefix for all calls to a method.
+    fn method_key(service: &str, method: &str) -> String {
+        format!("{service} {method} ")
+    }
+
+    /// Cache key for a call, with the params serialized as a single
+    /// JSON array so calls with different params never share a key.
+    pub(crate) fn cache_key(service: &str, method: &str, params: &[EgValue]) -> String {
+        let params = EgValue::from(params.to_vec());
+        CachingClient::method_key(service, method) + &params.dump()
+    }
+
+    /// Same as Client::send_recv_one(), but returns the cached
+    /// response when one is available.
+    pub fn send_recv_one(
+        &mut self,
+        service: &str,
+        method: &str,
+        params: impl Into<ApiParams>,
+    ) -> EgResult<Option<EgValue>> {
+        let mut params: ApiParams = params.into();
+        let params = params.take_params();
+
+        let key = CachingClient::cache_key(service, method, &params);
+
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.expires > Instant::now() {
+                log::trace!("Returning cached response for {method}");
+                return Ok(Some(entry.value.clone()));
+            }
+        }
+
+        let value = match self.client.send_recv_one(service, method, params)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        self.make_room();
+
+        let entry = CachedResponse {
+            value: value.clone(),
+            expires: Instant::now() + self.ttl,
+        };
+
+        self.entries.insert(key, entry);
+
+        Ok(Some(value))
+    }
+
+    /// Make room for a new entry if we are at capacity.
+    fn make_room(&mut self) {
+        if self.entries.len() < self.max_entries {
+            return;
+        }
+
+        let now = Instant::now();
+        self.entries.retain(|_, e| e.expires > now);
+
+        while !self.entries.is_empty() && self.entries.len() >= self.max_entries {
+            let key = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.expires)
+                .map(|(k, _)| k.to_string())
+                .unwrap();
+
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Remove cached responses for all calls to a method.
+    pub fn invalidate(&mut self, service: &str, method: &str) {
+        let prefix = CachingClient::method_key(service, method);
+        self.entries.retain(|k, _| !k.starts_with(&prefix));
+    }
+
+    /// Remove cached responses for all calls to a service.
+    pub fn invalidate_service(&mut self, service: &str) {
+        let prefix = format!("{service} ");
+        self.entries.retain(|k, _| !k.starts_with(&prefix));
+    }
+
+    /// Remove all cached responses.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// A change in the availability of a watched service.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceEvent {
+    /// The service has registered with our router.
+    Up(String),
+    /// The service is no longer registered with our router, or the
+    /// router itself could not be reached.
+    Down(String),
+}
+
+impl ServiceEvent {
+    pub fn service(&self) -> &str {
+        match self {
+            ServiceEvent::Up(s) | ServiceEvent::Down(s) => s,
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        matches!(self, ServiceEvent::Up(_))
+    }
+}
+
+/// Tracks whether a set of services is registered with our router and
+/// reports when they appear or disappear.
+///
+/// Watched services are assumed to be up until a poll shows
+/// otherwise, so the first poll only reports services which are down.
+///
+/// ```no_run
+/// use evergreen::osrf::client::{Client, ServiceWatcher};
+/// use std::time::Duration;
+///
+/// let client = Client::connect().unwrap();
+/// let mut watcher = ServiceWatcher::new(&["open-ils.rs-sip2"]);
+///
+/// loop {
+///     watcher.poll_with(&client, |evt| {
+///         if evt.is_up() {
+///             println!("{} is back", evt.service());
+///         } else {
+///             println!("{} went away", evt.service());
+///         }
+///     });
+///
+///     std::thread::sleep(Duration::from_secs(5));
+/// }
+/// ```
+pub struct ServiceWatcher {
+    services: Vec<String>,
+    down: Vec<String>,
+}
+
+impl ServiceWatcher {
+    pub fn new(services: &[&str]) -> Self {
+        ServiceWatcher {
+            services: services.iter().map(|s| s.to_string()).collect(),
+            down: Vec::new(),
+        }
+    }
+
+    /// True if the service was registered with the router as of the
+    /// last poll.
+    pub fn is_up(&self, service: &str) -> bool {
+        !self.down.iter().any(|s| s == service)
+    }
+
+    /// True if every watched service was up as of the last poll.
+    pub fn all_up(&self) -> bool {
+        self.down.is_empty()
+    }
+
+    /// Ask the router which services are registered and return an
+    /// event for each watched service whose state has changed since
+    /// the previous poll.
+    ///
+    /// If the router cannot be reached, all watched services are
+    /// considered down.
+    pub fn poll(&mut self, client: &Client) -> Vec<ServiceEvent> {
+        let registered =
+            match client.send_recv_one("router", "opensrf.router.info.class.list", None) {
+                Ok(Some(list)) => list,
+                Ok(None) => {
+                    log::warn!("Router did not respond to service list request");
+                    EgValue::new_array()
+                }
+                Err(e) => {
+                    log::warn!("Cannot retrieve service list from router: {e}");
+                    EgValue::new_array()
+                }
+            };
+
+        let mut events = Vec::new();
+
+        for service in self.services.iter() {
+            let is_up = registered.members().any(|s| s.as_str() == Some(service));
+            let was_up = !self.down.contains(service);
+
+            if is_up && !was_up {
+                self.down.retain(|s| s != service);
+                events.push(ServiceEvent::Up(service.to_string()));
+            } else if !is_up && was_up {
+                self.down.push(service.to_string());
+                events.push(ServiceEvent::Down(service.to_string()));
+            }
+        }
+
+        for evt in events.iter() {
+            log::info!("Watched service state changed: {evt:?}");
+        }
+
+        events
+    }
+
+    /// Same as poll(), but passes each event to a callback.
+    pub fn poll_with<F>(&mut self, client: &Client, mut callback: F)
+    where
+        F: FnMut(&ServiceEvent),
+    {
+        for evt in self.poll(client).iter() {
+            callback(evt);
+        }
+    }
+}
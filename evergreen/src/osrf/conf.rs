@@ -25,13 +25,51 @@ const DEFAULT_BUS_PORT: u16 = 6379;
 #[derive(Debug, Clone, PartialEq)]
 pub enum LogFile {
     Syslog,
+    Stdout,
+    Stderr,
     Filename(String),
 }
 
+impl From<&str> for LogFile {
+    /// "syslog", "stdout", "stderr", or a file path.
+    fn from(s: &str) -> LogFile {
+        match s {
+            "syslog" => LogFile::Syslog,
+            "stdout" => LogFile::Stdout,
+            "stderr" => LogFile::Stderr,
+            _ => LogFile::Filename(s.to_string()),
+        }
+    }
+}
+
+/// Format of log lines written to files and stdout/stderr.
+///
+/// Syslog messages are always plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat {
+    #[default]
+    Plain,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {s}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LogOptions {
     log_level: Option<log::LevelFilter>,
     log_file: Option<LogFile>,
+    log_format: LogFormat,
     syslog_facility: Option<syslog::Facility>,
     activity_log_facility: Option<syslog::Facility>,
 }
@@ -55,6 +93,16 @@ impl LogOptions {
     pub fn log_file(&self) -> &Option<LogFile> {
         &self.log_file
     }
+    pub fn set_log_file(&mut self, file: &str) {
+        self.log_file = Some(LogFile::from(file));
+    }
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+    pub fn set_log_format(&mut self, format: &str) -> Result<(), String> {
+        self.log_format = format.parse()?;
+        Ok(())
+    }
     pub fn log_level(&self) -> &Option<log::LevelFilter> {
         &self.log_level
     }
@@ -105,11 +153,18 @@ impl fmt::Display for BusDomain {
 pub struct BusClient {
     username: String,
     password: String,
+    /// Redis ACL username, when it differs from the username used
+    /// to build our bus addresses.
+    acl_username: Option<String>,
+    /// File containing the bus password, re-read whenever the bus
+    /// rejects our credentials so passwords can be rotated.
+    password_file: Option<String>,
     router_name: String,
     domain: BusDomain,
     logging: LogOptions,
     settings_config: Option<String>,
     routers: Vec<ClientRouter>,
+    domain_failover: bool,
 }
 
 impl BusClient {
@@ -119,6 +174,24 @@ impl BusClient {
     pub fn password(&self) -> &str {
         &self.password
     }
+    /// Username used to authenticate with Redis.  Defaults to our
+    /// bus username.
+    pub fn acl_username(&self) -> &str {
+        self.acl_username.as_deref().unwrap_or(&self.username)
+    }
+    pub fn password_file(&self) -> Option<&str> {
+        self.password_file.as_deref()
+    }
+    /// Our current password, read from the password file if one
+    /// is configured.
+    pub fn current_password(&self) -> Result<String, String> {
+        match self.password_file.as_deref() {
+            Some(path) => fs::read_to_string(path)
+                .map(|p| p.trim().to_string())
+                .map_err(|e| format!("Cannot read bus password file {path}: {e}")),
+            None => Ok(self.password.clone()),
+        }
+    }
     pub fn domain(&self) -> &BusDomain {
         &self.domain
     }
@@ -138,6 +211,13 @@ impl BusClient {
     pub fn routers(&self) -> &Vec<ClientRouter> {
         &self.routers
     }
+    /// True if routed requests which the router on our primary
+    /// domain refuses, e.g. because no workers for the service are
+    /// registered there, should be retried via the routers on our
+    /// other configured domains.
+    pub fn domain_failover(&self) -> bool {
+        self.domain_failover
+    }
     pub fn set_domain(&mut self, domain: &str) {
         // Assumes other aspects of the domain are identical
         self.domain.name = domain.to_string();
@@ -148,6 +228,12 @@ impl BusClient {
     pub fn set_password(&mut self, password: &str) {
         self.password = password.to_string();
     }
+    pub fn set_acl_username(&mut self, username: &str) {
+        self.acl_username = Some(username.to_string());
+    }
+    pub fn set_password_file(&mut self, path: &str) {
+        self.password_file = Some(path.to_string());
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -404,6 +490,9 @@ impl ConfigBuilder {
         let mut password = "";
         let mut router_name = "router";
         let mut settings_config: Option<String> = None;
+        let mut acl_username: Option<String> = None;
+        let mut password_file: Option<String> = None;
+        let mut domain_failover = false;
 
         for child in node.children() {
             match child.tag_name().name() {
@@ -422,11 +511,20 @@ impl ConfigBuilder {
                         router_name = t;
                     }
                 }
+                "acl_username" => {
+                    acl_username = child.text().map(|t| t.to_string());
+                }
+                "password_file" => {
+                    password_file = child.text().map(|t| t.to_string());
+                }
                 "settings_config" => {
                     if let Some(t) = child.text() {
                         settings_config = Some(t.to_string());
                     }
                 }
+                "domain_failover" => {
+                    domain_failover = child.text() == Some("true");
+                }
                 _ => {}
             }
         }
@@ -435,6 +533,9 @@ impl ConfigBuilder {
             domain,
             logging,
             settings_config,
+            domain_failover,
+            acl_username,
+            password_file,
             routers: Vec::new(),
             username: username.to_string(),
             password: password.to_string(),
@@ -476,6 +577,7 @@ impl ConfigBuilder {
         let mut ops = LogOptions {
             log_level: None,
             log_file: None,
+            log_format: LogFormat::Plain,
             syslog_facility: None,
             activity_log_facility: None,
         };
@@ -484,11 +586,12 @@ impl ConfigBuilder {
             match child.tag_name().name() {
                 "logfile" => {
                     if let Some(filename) = child.text() {
-                        if filename.eq("syslog") {
-                            ops.log_file = Some(LogFile::Syslog);
-                        } else {
-                            ops.log_file = Some(LogFile::Filename(filename.to_string()))
-                        }
+                        ops.log_file = Some(LogFile::from(filename));
+                    }
+                }
+                "logformat" => {
+                    if let Some(f) = child.text() {
+                        ops.log_format = f.parse()?;
                     }
                 }
                 "syslog" => {
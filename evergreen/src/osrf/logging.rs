@@ -24,6 +24,7 @@ thread_local! {
 /// the syslog crate.  This approach gives us much more control.
 pub struct Logger {
     logfile: conf::LogFile,
+    format: conf::LogFormat,
     loglevel: log::LevelFilter,
     facility: syslog::Facility,
     activity_facility: syslog::Facility,
@@ -54,6 +55,7 @@ impl Logger {
 
         Ok(Logger {
             logfile: file.clone(),
+            format: options.log_format(),
             loglevel: level.clone(),
             facility: facility.clone(),
             activity_facility: act_facility.clone(),
@@ -87,11 +89,16 @@ impl Logger {
         self.facility = facility;
     }
 
+    pub fn set_format(&mut self, format: conf::LogFormat) {
+        self.format = format;
+    }
+
     /// Setup our global log handler.
     ///
     /// Attempts to connect to syslog unix socket if possible.
     pub fn init(mut self) -> Result<(), String> {
         match self.logfile {
+            conf::LogFile::Stdout | conf::LogFile::Stderr => {}
             conf::LogFile::Syslog => {
                 self.writer = match Logger::writer() {
                     Ok(w) => Some(w),
@@ -220,8 +227,27 @@ impl log::Log for Logger {
             })
         };
 
-        let mut message = format!(
-            "{}{} [{}:{}:{}:{}",
+        let line = record.line().unwrap_or(0);
+        let trace = Logger::get_log_trace();
+
+        if self.writer.is_none() && self.format == conf::LogFormat::Json {
+            let message = json::object! {
+                "time": date::to_iso(&date::now()),
+                "app": self.application.as_str(),
+                "level": levelname.as_str(),
+                "pid": process::id(),
+                "target": target,
+                "line": line,
+                "trace": trace.as_str(),
+                "msg": logmsg.as_str(),
+            };
+
+            self.write_line(message.dump());
+            return;
+        }
+
+        let message = format!(
+            "{}{} [{}:{}:{}:{}:{trace}] {logmsg}",
             match self.writer.is_some() {
                 true => format!("<{}>", severity),
                 _ => format!("{} ", date::epoch_secs()),
@@ -230,38 +256,47 @@ impl log::Log for Logger {
             levelname,
             process::id(),
             target,
-            match record.line() {
-                Some(l) => l,
-                _ => 0,
-            }
+            line,
         );
 
-        // Add the thread-local log trace
-        THREAD_LOCAL_LOG_TRACE.with(|tr| message += &format!(":{}] ", *tr.borrow()));
-
-        message += &logmsg;
-
         if let Some(ref w) = self.writer {
             if w.send(message.as_bytes()).is_ok() {
                 return;
             }
-        } else if let conf::LogFile::Filename(ref name) = self.logfile {
-            if let Ok(mut file) = fs::File::options()
-                .create(true)
-                .write(true)
-                .append(true)
-                .open(name)
-            {
-                message += "\n";
-                if file.write_all(message.as_bytes()).is_ok() {
-                    return;
+        }
+
+        self.write_line(message);
+    }
+
+    fn flush(&self) {}
+}
+
+impl Logger {
+    /// Write a non-syslog log line to our log file or stdout/stderr.
+    fn write_line(&self, mut message: String) {
+        match self.logfile {
+            conf::LogFile::Stderr => {
+                eprintln!("{message}");
+                return;
+            }
+            conf::LogFile::Filename(ref name) => {
+                if let Ok(mut file) = fs::File::options()
+                    .create(true)
+                    .write(true)
+                    .append(true)
+                    .open(name)
+                {
+                    message += "\n";
+                    if file.write_all(message.as_bytes()).is_ok() {
+                        return;
+                    }
+                    message.pop();
                 }
             }
+            _ => {}
         }
 
         // If all else fails, print the log message.
         println!("{message}");
     }
-
-    fn flush(&self) {}
 }
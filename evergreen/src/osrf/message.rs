@@ -23,6 +23,15 @@ thread_local! {
     static THREAD_INGRESS: RefCell<String> = RefCell::new(DEFAULT_INGRESS.to_string());
 }
 
+/// True if the locale is reasonable enough to pass along, e.g. "fr-CA".
+pub fn is_valid_locale(locale: &str) -> bool {
+    !locale.is_empty()
+        && locale.len() <= MAX_LOCALE_LEN
+        && locale
+            .chars()
+            .all(|b| b.is_ascii_alphabetic() || b == '-' || b == '.')
+}
+
 /// Set the locale for the current thread.
 pub fn set_thread_locale(locale: &str) {
     THREAD_LOCALE.with(|lc| {
@@ -31,17 +40,7 @@ pub fn set_thread_locale(locale: &str) {
             return;
         }
 
-        // Make sure the requested locale is reasonable.
-
-        if locale.len() > MAX_LOCALE_LEN {
-            log::error!("Invalid locale: '{locale}'");
-            return;
-        }
-
-        if locale
-            .chars()
-            .any(|b| !b.is_ascii_alphabetic() && b != '-' && b != '.')
-        {
+        if !is_valid_locale(locale) {
             log::error!("Invalid locale: '{locale}'");
             return;
         }
@@ -50,7 +49,10 @@ pub fn set_thread_locale(locale: &str) {
     });
 }
 
-/// Set the locale for the current thread.
+/// Set the ingress for the current thread.
+///
+/// Outbound messages which have no ingress of their own are stamped
+/// with this value.
 pub fn set_thread_ingress(ingress: &str) {
     THREAD_INGRESS.with(|lc| {
         if lc.borrow().as_str() == ingress {
@@ -74,6 +76,16 @@ pub fn thread_locale() -> String {
     locale.unwrap()
 }
 
+/// Reset the ingress to our default.
+pub fn reset_thread_ingress() {
+    set_thread_ingress(DEFAULT_INGRESS);
+}
+
+/// Returns the ingress for the current thread.
+pub fn thread_ingress() -> String {
+    THREAD_INGRESS.with(|lc| lc.borrow().to_string())
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MessageType {
     Connect,
@@ -439,7 +451,12 @@ pub struct Message {
     thread_trace: usize,
     timezone: Option<String>,
     api_level: u8,
+    /// Overrides the thread locale on outbound messages.
+    locale: Option<String>,
     ingress: Option<String>,
+    /// Set by the caller when the params of a request should not be
+    /// logged anywhere along the way.
+    sensitive: bool,
     payload: Payload,
 }
 
@@ -451,7 +468,9 @@ impl Message {
             payload,
             api_level: DEFAULT_API_LEVEL,
             timezone: None,
+            locale: None,
             ingress: None,
+            sensitive: false,
         }
     }
 
@@ -491,6 +510,22 @@ impl Message {
         self.timezone = Some(timezone.to_string())
     }
 
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Send this message with the provided locale instead of the
+    /// locale of the current thread.
+    ///
+    /// Invalid locales are logged and ignored.
+    pub fn set_locale(&mut self, locale: &str) {
+        if is_valid_locale(locale) {
+            self.locale = Some(locale.to_string());
+        } else {
+            log::error!("Invalid locale: '{locale}'");
+        }
+    }
+
     pub fn ingress(&self) -> Option<&str> {
         self.ingress.as_deref()
     }
@@ -499,6 +534,14 @@ impl Message {
         self.ingress = Some(ingress.to_string())
     }
 
+    pub fn sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
     /// Creates a Message from a JSON value, consuming the JSON value.
     ///
     /// Returns Err if the JSON value cannot be coerced into a Message.
@@ -530,6 +573,9 @@ impl Message {
         // value, adopt that value as our new thread-scoped locale.
         if let Some(lc) = msg_hash["locale"].as_str() {
             set_thread_locale(lc);
+            if is_valid_locale(lc) {
+                msg.locale = Some(lc.to_string());
+            }
         }
 
         if let Some(ing) = msg_hash["ingress"].as_str() {
@@ -541,6 +587,8 @@ impl Message {
             msg.set_api_level(al);
         }
 
+        msg.set_sensitive(msg_hash["sensitive"].as_bool() == Some(true));
+
         Ok(msg)
     }
 
@@ -573,11 +621,12 @@ impl Message {
 
     pub fn into_json_value(self) -> JsonValue {
         let mtype: &str = self.mtype.into();
+        let locale = self.locale.clone().unwrap_or_else(thread_locale);
 
         let mut obj = json::object! {
             threadTrace: self.thread_trace,
             type: mtype,
-            locale: thread_locale(),
+            locale: locale,
             timezone: self.timezone(),
             api_level: self.api_level(),
         };
@@ -590,6 +639,12 @@ impl Message {
             THREAD_INGRESS.with(|lc| obj["ingress"] = lc.borrow().as_str().into());
         }
 
+        // Only present when set, so messages look the same as
+        // always to peers which don't know about the flag.
+        if self.sensitive {
+            obj["sensitive"] = true.into();
+        }
+
         match self.payload {
             // Avoid adding the "payload" key for non-payload messages.
             Payload::NoPayload => {}
@@ -691,6 +746,9 @@ pub struct Status {
     status: MessageStatus,
     status_label: String,
     msg_class: String,
+    /// Optional structured explanation of the status, e.g. the
+    /// parameter problems behind a BadRequest.
+    details: Option<EgValue>,
 }
 
 impl Status {
@@ -699,6 +757,7 @@ impl Status {
             status,
             status_label: status_label.to_string(),
             msg_class: msg_class.to_string(),
+            details: None,
         }
     }
 
@@ -710,10 +769,18 @@ impl Status {
         &self.status_label
     }
 
+    pub fn details(&self) -> Option<&EgValue> {
+        self.details.as_ref()
+    }
+
+    pub fn set_details(&mut self, details: EgValue) {
+        self.details = Some(details);
+    }
+
     pub fn from_json_value(json_obj: JsonValue) -> EgResult<Self> {
         let err = || format!("Invalid Status message");
 
-        let (msg_class, msg_hash) = EgValue::remove_class_wrapper(json_obj).ok_or_else(err)?;
+        let (msg_class, mut msg_hash) = EgValue::remove_class_wrapper(json_obj).ok_or_else(err)?;
 
         let code = util::json_isize(&msg_hash["statusCode"]).ok_or_else(err)?;
         let stat: MessageStatus = code.into();
@@ -722,15 +789,26 @@ impl Status {
         // use the label associated locally with the status code
         let stat_str: &str = msg_hash["status"].as_str().unwrap_or(stat.into());
 
-        Ok(Status::new(stat, stat_str, &msg_class))
+        let mut status = Status::new(stat, stat_str, &msg_class);
+
+        let details = msg_hash["details"].take();
+        if !details.is_null() {
+            status.details = Some(EgValue::from_json_value_plain(details));
+        }
+
+        Ok(status)
     }
 
     pub fn into_json_value(self) -> JsonValue {
-        let obj = json::object! {
+        let mut obj = json::object! {
             "status": self.status_label(),
             "statusCode": self.status as isize,
         };
 
+        if let Some(details) = self.details {
+            obj["details"] = details.into_json_value();
+        }
+
         EgValue::add_class_wrapper(obj, &self.msg_class)
     }
 }
@@ -741,7 +819,103 @@ impl fmt::Display for Status {
             f,
             "stat={} class={} label={}",
             self.status, self.msg_class, self.status_label
-        )
+        )?;
+
+        if let Some(details) = self.details.as_ref() {
+            write!(f, " details={}", details.dump())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Progress of a long-running request.
+///
+/// Servers report progress by sending a Continue (100) status whose
+/// details are {"progress": n, "total": m, "message": "..."}, where
+/// total and message are optional.  Continue statuses also reset the
+/// caller's request timeout, so slow requests which report progress
+/// do not time out.
+///
+/// ```
+/// use evergreen::osrf::message::{MessageStatus, Progress};
+///
+/// let progress = Progress::new(5, Some(20)).with_message("Updating items");
+/// assert_eq!(progress.percent(), Some(25.0));
+///
+/// let status = progress.clone().into_status();
+/// assert_eq!(status.status(), &MessageStatus::Continue);
+/// assert_eq!(Progress::from_status(&status), Some(progress));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Units of work completed so far.
+    pub done: usize,
+    /// Total units of work, when known.
+    pub total: Option<usize>,
+    /// Optional description of the current step.
+    pub message: Option<String>,
+}
+
+impl Progress {
+    pub fn new(done: usize, total: Option<usize>) -> Self {
+        Progress {
+            done,
+            total,
+            message: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Percent complete, when the total is known.
+    pub fn percent(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(100.0),
+            Some(t) => Some(self.done as f64 * 100.0 / t as f64),
+            None => None,
+        }
+    }
+
+    /// Extract the progress from a Continue status, if it has any.
+    pub fn from_status(status: &Status) -> Option<Progress> {
+        if status.status() != &MessageStatus::Continue {
+            return None;
+        }
+
+        let details = status.details()?;
+
+        Some(Progress {
+            done: details["progress"].as_usize()?,
+            total: details["total"].as_usize(),
+            message: details["message"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    pub fn to_eg_value(&self) -> EgValue {
+        let mut value = EgValue::new_object();
+
+        value["progress"] = EgValue::from(self.done);
+
+        if let Some(t) = self.total {
+            value["total"] = EgValue::from(t);
+        }
+
+        if let Some(m) = self.message.as_deref() {
+            value["message"] = EgValue::from(m);
+        }
+
+        value
+    }
+
+    /// Package the progress as a Continue status.
+    pub fn into_status(self) -> Status {
+        let mut status = Status::new(MessageStatus::Continue, "Continue", "osrfContinueStatus");
+        status.set_details(self.to_eg_value());
+        status
     }
 }
 
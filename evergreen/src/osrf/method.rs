@@ -1,3 +1,4 @@
+use crate as eg;
 use crate::osrf::app;
 use crate::osrf::message;
 use crate::osrf::session;
@@ -75,6 +76,8 @@ pub enum ParamDataType {
     Number,
     Array,
     Object, // JsonValue::Object or other object-y thing
+    /// Object which must contain a non-null value for each key.
+    ObjectWithKeys(&'static [&'static str]),
     Boolish,
     Scalar, // Not an Object or Array.
     Any,
@@ -87,6 +90,7 @@ impl fmt::Display for ParamDataType {
             ParamDataType::Number => "Number",
             ParamDataType::Array => "Array",
             ParamDataType::Object => "Object",
+            ParamDataType::ObjectWithKeys(_) => "Object",
             ParamDataType::Boolish => "Boolish",
             ParamDataType::Scalar => "Scalar",
             ParamDataType::Any => "Any",
@@ -106,6 +110,7 @@ impl ParamDataType {
             ParamDataType::Number => param.is_number(),
             ParamDataType::Array => param.is_array(),
             ParamDataType::Object => param.is_object(),
+            ParamDataType::ObjectWithKeys(_) => param.is_object(),
             ParamDataType::Boolish => {
                 param.is_boolean() || param.is_number() || param.is_string() || param.is_null()
             }
@@ -115,6 +120,51 @@ impl ParamDataType {
             ParamDataType::Any => true,
         }
     }
+
+    /// Keys a parameter of this type must contain.
+    pub fn required_keys(&self) -> &[&str] {
+        match *self {
+            ParamDataType::ObjectWithKeys(keys) => keys,
+            _ => &[],
+        }
+    }
+
+    /// Required keys which are missing or null in the provided parameter.
+    ///
+    /// ```
+    /// use evergreen as eg;
+    /// use eg::osrf::method::ParamDataType;
+    ///
+    /// let dtype = ParamDataType::ObjectWithKeys(&["user_id", "login_type"]);
+    /// let param = eg::hash! {"user_id": 1, "login_type": eg::NULL};
+    ///
+    /// assert!(dtype.matches(&param));
+    /// assert_eq!(dtype.missing_keys(&param), vec!["login_type"]);
+    /// ```
+    pub fn missing_keys(&self, param: &EgValue) -> Vec<&str> {
+        self.required_keys()
+            .iter()
+            .filter(|k| !param.has_key(k) || param[**k].is_null())
+            .copied()
+            .collect()
+    }
+}
+
+/// Base type name of a parameter value for error reporting.
+fn value_type_name(param: &EgValue) -> &'static str {
+    if param.is_string() {
+        "String"
+    } else if param.is_number() {
+        "Number"
+    } else if param.is_array() {
+        "Array"
+    } else if param.is_object() {
+        "Object"
+    } else if param.is_boolean() {
+        "Boolean"
+    } else {
+        "Null"
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -133,14 +183,22 @@ pub struct Param {
 
 impl Param {
     pub fn to_eg_value(&self) -> EgValue {
-        EgValue::from_json_value_plain(json::object! {
+        let mut value = EgValue::from_json_value_plain(json::object! {
             "name": self.name.as_str(),
             "datatype": self.datatype.to_string(),
             "desc": match self.desc.as_ref() {
                 Some(d) => d.as_str().into(),
                 _ => JsonValue::Null,
             }
-        })
+        });
+
+        let keys = self.datatype.required_keys();
+        if !keys.is_empty() {
+            value["required_keys"] =
+                EgValue::from(keys.iter().map(|k| k.to_string()).collect::<Vec<String>>());
+        }
+
+        value
     }
 }
 
@@ -259,6 +317,80 @@ impl MethodDef {
         params.push(param);
     }
 
+    /// Verify the caller sent the number and types of parameters this
+    /// method requires.
+    ///
+    /// On failure, returns a description of each problem found,
+    /// suitable for returning to the caller.  Type checks are
+    /// superficial; e.g. array contents are not inspected.
+    pub fn validate_params(&self, params: &[EgValue]) -> Result<(), EgValue> {
+        let mut errors = EgValue::new_array();
+
+        if !ParamCount::matches(&self.param_count, params.len() as u8) {
+            errors
+                .push(eg::hash! {
+                    "message": format!(
+                        "Invalid param count sent: method={} sent={} needed={}",
+                        self.name, params.len(), self.param_count,
+                    ),
+                    "sent": params.len(),
+                    "needed": self.param_count.to_string(),
+                })
+                .expect("Is Array");
+        }
+
+        let param_defs = self.params.as_deref().unwrap_or(&[]);
+        let minimum = self.param_count.minimum() as usize;
+
+        // There may be more param defs than parameters if some params
+        // are optional.
+        for (idx, (param_def, param)) in param_defs.iter().zip(params).enumerate() {
+            if idx >= minimum && param.is_null() {
+                // NULL placeholders for non-required parameters are allowed.
+                continue;
+            }
+
+            if !param_def.datatype.matches(param) {
+                errors
+                    .push(eg::hash! {
+                        "message": format!(
+                            "Invalid parameter type: param={} wanted={} got={}",
+                            param_def.name, param_def.datatype, value_type_name(param),
+                        ),
+                        "index": idx,
+                        "param": param_def.name.as_str(),
+                        "wanted": param_def.datatype.to_string(),
+                        "got": value_type_name(param),
+                    })
+                    .expect("Is Array");
+                continue;
+            }
+
+            let missing = param_def.datatype.missing_keys(param);
+            if !missing.is_empty() {
+                errors
+                    .push(eg::hash! {
+                        "message": format!(
+                            "Missing required keys: param={} keys={}",
+                            param_def.name, missing.join(","),
+                        ),
+                        "index": idx,
+                        "param": param_def.name.as_str(),
+                        "missing_keys": EgValue::from(
+                            missing.iter().map(|k| k.to_string()).collect::<Vec<String>>()
+                        ),
+                    })
+                    .expect("Is Array");
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(eg::hash! {"method": self.name(), "errors": errors})
+        }
+    }
+
     pub fn to_eg_value(&self) -> EgValue {
         let mut pa = EgValue::new_array();
         if let Some(params) = self.params() {
@@ -12,4 +12,5 @@ pub mod params;
 pub mod sclient;
 pub mod server;
 pub mod session;
+pub mod transfer;
 pub mod worker;
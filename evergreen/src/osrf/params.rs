@@ -7,8 +7,13 @@ use json::JsonValue;
 /// list of individual API call parameters.  To pass a single parameter
 /// that is itself a list, pass the value as either a JsonValue::Array
 /// or as a (e.g.) vec![vec![1,2,3]].
+#[derive(Clone)]
 pub struct ApiParams {
     params: Vec<EgValue>,
+
+    /// Ask every hop which handles the request to keep the params
+    /// out of its logs.  See [`ApiParams::into_sensitive()`].
+    sensitive: bool,
 }
 
 impl ApiParams {
@@ -39,6 +44,31 @@ impl ApiParams {
         self.params.push(v)
     }
 
+    /// Flag the request carrying these params as sensitive.
+    ///
+    /// Sensitive requests have their params redacted from the logs of
+    /// the websocket translator, HTTP gateway, and handling service,
+    /// just like methods listed in the log_protect config.  Useful for
+    /// ad-hoc calls whose params contain e.g. passwords.
+    ///
+    /// ```
+    /// use evergreen::osrf::params::ApiParams;
+    ///
+    /// let params = ApiParams::from("secret");
+    /// assert!(!params.sensitive());
+    ///
+    /// let params = params.into_sensitive();
+    /// assert!(params.sensitive());
+    /// ```
+    pub fn into_sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+
+    pub fn sensitive(&self) -> bool {
+        self.sensitive
+    }
+
     pub fn from_json_value(v: JsonValue) -> EgResult<ApiParams> {
         Ok(ApiParams::from(EgValue::from_json_value(v)?))
     }
@@ -46,7 +76,10 @@ impl ApiParams {
 
 impl From<Vec<EgValue>> for ApiParams {
     fn from(v: Vec<EgValue>) -> ApiParams {
-        ApiParams { params: v }
+        ApiParams {
+            params: v,
+            sensitive: false,
+        }
     }
 }
 
@@ -58,11 +91,9 @@ impl From<EgValue> for ApiParams {
 
 impl From<Option<EgValue>> for ApiParams {
     fn from(v: Option<EgValue>) -> ApiParams {
-        ApiParams {
-            params: match v {
-                Some(v) => vec![v],
-                None => Vec::new(),
-            },
+        match v {
+            Some(v) => ApiParams::from(vec![v]),
+            None => ApiParams::from(Vec::<EgValue>::new()),
         }
     }
 }
@@ -123,9 +154,11 @@ impl From<usize> for ApiParams {
 
 impl From<&Vec<&str>> for ApiParams {
     fn from(v: &Vec<&str>) -> ApiParams {
-        ApiParams {
-            params: v.iter().map(|j| EgValue::from(*j)).collect(),
-        }
+        ApiParams::from(
+            v.iter()
+                .map(|j| EgValue::from(*j))
+                .collect::<Vec<EgValue>>(),
+        )
     }
 }
 
@@ -137,9 +170,11 @@ impl From<Vec<&str>> for ApiParams {
 
 impl From<&Vec<u8>> for ApiParams {
     fn from(v: &Vec<u8>) -> ApiParams {
-        ApiParams {
-            params: v.iter().map(|j| EgValue::from(*j)).collect(),
-        }
+        ApiParams::from(
+            v.iter()
+                .map(|j| EgValue::from(*j))
+                .collect::<Vec<EgValue>>(),
+        )
     }
 }
 
@@ -151,9 +186,11 @@ impl From<Vec<u8>> for ApiParams {
 
 impl From<&Vec<i64>> for ApiParams {
     fn from(v: &Vec<i64>) -> ApiParams {
-        ApiParams {
-            params: v.iter().map(|j| EgValue::from(*j)).collect(),
-        }
+        ApiParams::from(
+            v.iter()
+                .map(|j| EgValue::from(*j))
+                .collect::<Vec<EgValue>>(),
+        )
     }
 }
 
@@ -165,9 +202,11 @@ impl From<Vec<i64>> for ApiParams {
 
 impl From<&Vec<u64>> for ApiParams {
     fn from(v: &Vec<u64>) -> ApiParams {
-        ApiParams {
-            params: v.iter().map(|j| EgValue::from(*j)).collect(),
-        }
+        ApiParams::from(
+            v.iter()
+                .map(|j| EgValue::from(*j))
+                .collect::<Vec<EgValue>>(),
+        )
     }
 }
 
@@ -179,9 +218,11 @@ impl From<Vec<u64>> for ApiParams {
 
 impl From<&Vec<String>> for ApiParams {
     fn from(v: &Vec<String>) -> ApiParams {
-        ApiParams {
-            params: v.iter().map(|s| EgValue::from(s.as_str())).collect(),
-        }
+        ApiParams::from(
+            v.iter()
+                .map(|s| EgValue::from(s.as_str()))
+                .collect::<Vec<EgValue>>(),
+        )
     }
 }
 
@@ -1,20 +1,27 @@
+use crate as eg;
 use crate::init;
+use crate::osrf::addr::BusAddress;
 use crate::osrf::app;
 use crate::osrf::client::Client;
 use crate::osrf::conf;
 use crate::osrf::message;
+use crate::osrf::message::{Message, MessageStatus, MessageType, Payload, TransportMessage};
 use crate::osrf::method;
 use crate::osrf::sclient::HostSettings;
 use crate::osrf::session;
-use crate::osrf::worker::{Worker, WorkerState, WorkerStateEvent};
+use crate::osrf::worker::{
+    ControlCommand, ControlRequest, Worker, WorkerCommand, WorkerState, WorkerStateEvent,
+};
 use crate::util;
 use crate::EgResult;
 use mptc::signals::SignalTracker;
+use mptc::usage::ResourceUsage;
 use std::collections::HashMap;
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Warn when there are fewer than this many idle threads
@@ -34,6 +41,25 @@ const LOG_THREAD_STATS_FREQUENCY: i32 = 10;
 pub struct WorkerThread {
     pub state: WorkerState,
     pub join_handle: thread::JoinHandle<()>,
+    /// Delivers control requests and exit requests to the worker.
+    pub to_worker_tx: mpsc::Sender<WorkerCommand>,
+    /// When the worker last reported itself as Idle.
+    pub idle_since: Instant,
+    /// True if we told the worker to exit during scale-down.
+    pub exit_requested: bool,
+}
+
+/// Worker lifecycle counts, reported via the status control command.
+#[derive(Debug, Default)]
+pub struct WorkerStats {
+    /// Workers started.
+    pub spawned: usize,
+    /// Idle workers told to exit during scale-down.
+    pub reaped: usize,
+    /// Workers which exited on their own, e.g. after max_requests.
+    pub exited: usize,
+    /// Workers which exited without notifying us.
+    pub failed: usize,
 }
 
 pub struct Server {
@@ -51,23 +77,28 @@ pub struct Server {
 
     sig_tracker: SignalTracker,
 
-    /// Minimum number of idle workers.  Note we don't support
-    /// max_idle_workers at this time -- it would require adding an
-    /// additional mpsc channel for every thread to deliver the shutdown
-    /// request to individual threads.  Hardly seems worth it -- maybe.
-    /// For comparision, the OSRF C code has no min/max idle support
-    /// either.
+    /// Minimum number of idle workers.
     min_idle_workers: usize,
+
+    /// Workers idle for longer than this are told to exit, so long as
+    /// we remain at or above min_workers and min_idle_workers.
+    ///
+    /// Set via unix_config/idle_timeout (seconds).  Scale-down is
+    /// disabled when the setting is absent or 0.
+    idle_timeout: Option<Duration>,
+
+    stats: WorkerStats,
+
+    /// True if an operator asked us to stop receiving routed requests.
+    quiesced: bool,
 }
 
 impl Server {
     pub fn start(application: Box<dyn app::Application>) -> EgResult<()> {
         let service = application.name();
 
-        let mut options = init::InitOptions::new();
-        options.appname = Some(service.to_string());
-
-        let client = init::osrf_init(&options)?;
+        // Applications load the IDL themselves, if needed.
+        let client = init::Builder::new().appname(service).skip_idl().build()?;
 
         let min_workers = HostSettings::get(&format!("apps/{service}/unix_config/min_children"))?
             .as_usize()
@@ -82,6 +113,11 @@ impl Server {
             .as_usize()
             .unwrap_or(DEFAULT_MAX_WORKERS);
 
+        let idle_timeout = HostSettings::get(&format!("apps/{service}/unix_config/idle_timeout"))?
+            .as_usize()
+            .filter(|t| *t > 0)
+            .map(|t| Duration::from_secs(t as u64));
+
         // We have a single to-parent channel whose trasmitter is cloned
         // per thread.  Communication from worker threads to the parent
         // are synchronous so the parent always knows exactly how many
@@ -99,12 +135,15 @@ impl Server {
             min_workers,
             max_workers,
             min_idle_workers,
+            idle_timeout,
+            stats: WorkerStats::default(),
             methods: None,
             worker_id_gen: 0,
             to_parent_tx: tx,
             to_parent_rx: rx,
             workers: HashMap::new(),
             sig_tracker: SignalTracker::new(),
+            quiesced: false,
         };
 
         server.listen()
@@ -143,6 +182,7 @@ impl Server {
         let service = self.service().to_string();
         let factory = self.app().worker_factory();
         let sig_tracker = self.sig_tracker.clone();
+        let (to_worker_tx, from_parent_rx) = mpsc::channel();
 
         log::trace!("server: spawning a new worker {worker_id}");
 
@@ -154,6 +194,7 @@ impl Server {
                 worker_id,
                 methods,
                 to_parent_tx,
+                from_parent_rx,
             );
         });
 
@@ -162,8 +203,13 @@ impl Server {
             WorkerThread {
                 state: WorkerState::Idle,
                 join_handle: handle,
+                to_worker_tx,
+                idle_since: Instant::now(),
+                exit_requested: false,
             },
         );
+
+        self.stats.spawned += 1;
     }
 
     fn start_worker_thread(
@@ -173,10 +219,18 @@ impl Server {
         worker_id: u64,
         methods: Arc<HashMap<String, method::MethodDef>>,
         to_parent_tx: mpsc::SyncSender<WorkerStateEvent>,
+        from_parent_rx: mpsc::Receiver<WorkerCommand>,
     ) {
         log::trace!("Creating new worker {worker_id}");
 
-        let mut worker = match Worker::new(service, worker_id, sig_tracker, methods, to_parent_tx) {
+        let mut worker = match Worker::new(
+            service,
+            worker_id,
+            sig_tracker,
+            methods,
+            to_parent_tx,
+            from_parent_rx,
+        ) {
             Ok(w) => w,
             Err(e) => {
                 log::error!("Cannot create worker: {e}. Exiting.");
@@ -352,6 +406,8 @@ impl Server {
             // Always check for failed threads.
             work_performed = self.check_failed_threads() || work_performed;
 
+            work_performed = self.handle_control_messages() || work_performed;
+
             if self.sig_tracker.any_shutdown_requested() {
                 log::info!("We received a stop signal, exiting");
                 break;
@@ -366,12 +422,154 @@ impl Server {
             self.log_thread_counts(&mut log_timer);
         }
 
-        self.unregister_routers()?;
+        if !self.quiesced {
+            self.unregister_routers()?;
+        }
         self.shutdown();
 
         Ok(())
     }
 
+    /// Process any control requests sent to our bus address by an
+    /// operator tool.
+    ///
+    /// Returns true if work was done.
+    fn handle_control_messages(&mut self) -> bool {
+        let mut handled = false;
+
+        loop {
+            let recv_result = self.client.singleton().borrow_mut().bus_mut().recv(0, None);
+
+            let tmsg = match recv_result {
+                Ok(Some(tm)) => tm,
+                Ok(None) => break,
+                Err(e) => {
+                    log::error!("server: error receiving control message: {e}");
+                    break;
+                }
+            };
+
+            for msg in tmsg.body().iter() {
+                let Payload::Method(call) = msg.payload() else {
+                    log::warn!("server: ignoring unexpected message {msg:?}");
+                    continue;
+                };
+
+                let Some(command) = ControlCommand::from_method(call.method()) else {
+                    log::warn!("server: unknown control method {}", call.method());
+                    continue;
+                };
+
+                let req = ControlRequest {
+                    command,
+                    reply_to: tmsg.from().to_string(),
+                    thread: tmsg.thread().to_string(),
+                    thread_trace: msg.thread_trace(),
+                };
+
+                if let Err(e) = self.handle_control_request(req) {
+                    log::error!("server: control command {} failed: {e}", command.as_str());
+                }
+
+                handled = true;
+            }
+        }
+
+        handled
+    }
+
+    /// Apply a control command at the server level, relay it to each
+    /// of our workers (except for status requests), then acknowledge it.
+    ///
+    /// Our acknowledgement reports how many workers were sent the
+    /// request so the caller knows how many worker replies to expect.
+    fn handle_control_request(&mut self, req: ControlRequest) -> EgResult<()> {
+        log::info!(
+            "server: received control command {} from {}",
+            req.command.as_str(),
+            req.reply_to
+        );
+
+        match req.command {
+            ControlCommand::Quiesce if !self.quiesced => {
+                self.unregister_routers()?;
+                self.quiesced = true;
+            }
+            ControlCommand::Resume if self.quiesced => {
+                self.register_routers()?;
+                self.quiesced = false;
+            }
+            _ => {}
+        }
+
+        let relayed = match req.command {
+            ControlCommand::Status => 0,
+            _ => self
+                .workers
+                .values()
+                .filter(|w| {
+                    w.to_worker_tx
+                        .send(WorkerCommand::Control(req.clone()))
+                        .is_ok()
+                })
+                .count(),
+        };
+
+        let usage = ResourceUsage::process();
+
+        let ack = eg::hash! {
+            "service": self.service(),
+            "worker_count": self.workers.len(),
+            "address": self.client.address().as_str(),
+            "command": req.command.as_str(),
+            "status": "ok",
+            "workers": relayed,
+            "active": self.active_thread_count(),
+            "idle": self.idle_thread_count(),
+            "quiesced": self.quiesced,
+            "spawned": self.stats.spawned,
+            "reaped": self.stats.reaped,
+            "exited": self.stats.exited,
+            "failed": self.stats.failed,
+            "rss_bytes": usage.map(|u| u.rss_bytes),
+            "cpu_secs": usage.map(|u| u.cpu_secs),
+        };
+
+        let mut tmsg = TransportMessage::with_body(
+            &req.reply_to,
+            self.client.address().as_str(),
+            &req.thread,
+            Message::new(
+                MessageType::Result,
+                req.thread_trace,
+                Payload::Result(message::Result::new(
+                    MessageStatus::Ok,
+                    "OK",
+                    "osrfResult",
+                    ack,
+                )),
+            ),
+        );
+
+        tmsg.body_mut().push(Message::new(
+            MessageType::Status,
+            req.thread_trace,
+            Payload::Status(message::Status::new(
+                MessageStatus::Complete,
+                "Request Complete",
+                "osrfStatus",
+            )),
+        ));
+
+        let domain = BusAddress::from_str(&req.reply_to)?.domain().to_string();
+
+        self.client
+            .singleton()
+            .borrow_mut()
+            .get_domain_bus(&domain)?
+            .send(tmsg)
+    }
+
     /// Periodically report our active/idle thread disposition
     /// so monitoring tools can keep track.
     ///
@@ -390,19 +588,25 @@ impl Server {
         }
 
         log::info!(
-            "Service {} max-threads={} active-threads={} idle-threads={}",
+            "Service {} max-threads={} active-threads={} idle-threads={} \
+            spawned={} reaped={} exited={} failed={}",
             self.application.name(),
             self.max_workers,
             active_count,
             self.idle_thread_count(),
+            self.stats.spawned,
+            self.stats.reaped,
+            self.stats.exited,
+            self.stats.failed,
         );
 
         timer.reset();
     }
 
-    /// Add additional idle workers if needed.
+    /// Add additional idle workers if needed, or scale down workers
+    /// which have been idle for longer than our idle timeout.
     ///
-    /// Spawn at most one worker per maintenance cycle.
+    /// Spawn or reap at most one worker per maintenance cycle.
     fn perform_idle_worker_maint(&mut self) {
         let idle_workers = self.idle_thread_count();
 
@@ -412,6 +616,52 @@ impl Server {
         {
             self.spawn_one_thread();
             log::debug!("Sawned idle worker; idle={idle_workers}");
+            return;
+        }
+
+        self.reap_one_idle_worker();
+    }
+
+    /// Tell the longest-idle worker to exit if it has been idle
+    /// longer than our idle timeout and we have workers to spare.
+    fn reap_one_idle_worker(&mut self) {
+        let Some(timeout) = self.idle_timeout else {
+            return;
+        };
+
+        // Workers already told to exit are in the Exiting state until
+        // they report back, so they don't count toward our totals.
+        let live_workers = self
+            .workers
+            .values()
+            .filter(|w| w.state != WorkerState::Exiting)
+            .count();
+
+        if live_workers <= self.min_workers || self.idle_thread_count() <= self.min_idle_workers {
+            return;
+        }
+
+        let reapable = self
+            .workers
+            .iter_mut()
+            .filter(|(_, w)| w.state == WorkerState::Idle && w.idle_since.elapsed() > timeout)
+            .min_by_key(|(_, w)| w.idle_since);
+
+        let Some((worker_id, worker)) = reapable else {
+            return;
+        };
+
+        log::info!(
+            "server: worker {worker_id} idle for {}s; telling it to exit",
+            worker.idle_since.elapsed().as_secs()
+        );
+
+        // If the send fails, the worker is already gone and will
+        // be cleaned up by check_failed_threads().
+        if worker.to_worker_tx.send(WorkerCommand::Exit).is_ok() {
+            worker.state = WorkerState::Exiting;
+            worker.exit_requested = true;
+            self.stats.reaped += 1;
         }
     }
 
@@ -461,6 +711,7 @@ impl Server {
         for worker_id in failed {
             handled = true;
             log::info!("Found a thread that exited ungracefully: {worker_id}");
+            self.stats.failed += 1;
             self.remove_thread(&worker_id);
         }
 
@@ -490,9 +741,16 @@ impl Server {
 
         if evt.state() == WorkerState::Exiting {
             // Worker is done -- remove it and fire up new ones as needed.
+            // Reaped workers are already counted.
+            if !worker.exit_requested {
+                self.stats.exited += 1;
+            }
             self.remove_thread(&worker_id);
         } else {
             log::trace!("server: updating thread state: {:?}", worker_id);
+            if evt.state() == WorkerState::Idle {
+                worker.idle_since = Instant::now();
+            }
             worker.state = evt.state();
         }
 
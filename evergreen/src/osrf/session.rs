@@ -7,13 +7,16 @@ use crate::osrf::message::MessageStatus;
 use crate::osrf::message::MessageType;
 use crate::osrf::message::MethodCall;
 use crate::osrf::message::Payload;
+use crate::osrf::message::Progress;
 use crate::osrf::message::Status;
 use crate::osrf::message::TransportMessage;
 use crate::osrf::params::ApiParams;
+use crate::result::{EgError, ErrorKind};
 use crate::util;
 use crate::{EgResult, EgValue};
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::fmt;
 use std::rc::Rc;
@@ -21,6 +24,9 @@ use std::rc::Rc;
 const CONNECT_TIMEOUT: i32 = 10;
 pub const DEFAULT_REQUEST_TIMEOUT: i32 = 60;
 
+/// Called with each progress report for a request.
+type ProgressHandler = Box<dyn FnMut(&Progress)>;
+
 /// Response data propagated from a session to the calling Request.
 #[derive(Debug)]
 struct Response {
@@ -87,6 +93,49 @@ impl Request {
         self.complete() && self.session.borrow().backlog.is_empty()
     }
 
+    /// Most recent progress reported by the server for this request.
+    ///
+    /// See [`Progress`].
+    pub fn progress(&self) -> Option<Progress> {
+        self.session
+            .borrow()
+            .progress
+            .get(&self.thread_trace)
+            .cloned()
+    }
+
+    /// Call `handler` each time the server reports progress for this
+    /// request.
+    ///
+    /// Handlers are called while responses are being received, so they
+    /// should not make requests on the same session.
+    ///
+    /// ```no_run
+    /// use evergreen as eg;
+    ///
+    /// let client = eg::Client::connect().unwrap();
+    /// let mut ses = client.session("open-ils.cat");
+    /// let mut req = ses.request("open-ils.cat.some_batch_job", None).unwrap();
+    ///
+    /// req.on_progress(|p| match p.total {
+    ///     Some(total) => println!("{} of {total}", p.done),
+    ///     None => println!("{} done", p.done),
+    /// });
+    ///
+    /// while let Some(resp) = req.recv().unwrap() {
+    ///     println!("{resp}");
+    /// }
+    /// ```
+    pub fn on_progress<F>(&mut self, handler: F)
+    where
+        F: FnMut(&Progress) + 'static,
+    {
+        self.session
+            .borrow_mut()
+            .progress_handlers
+            .insert(self.thread_trace, Box::new(handler));
+    }
+
     /// Pull all responses from the bus and return the first.
     ///
     /// Handy if you are expecting exactly one result, or only care
@@ -156,6 +205,65 @@ impl Request {
     }
 }
 
+/// A routed request retained for resending via routers on other
+/// domains when the router on our primary domain refuses it.
+pub(crate) struct RoutedRequest {
+    thread_trace: usize,
+    tmsg: TransportMessage,
+    /// Routers on other domains we have not yet tried.
+    failover_routers: VecDeque<BusAddress>,
+}
+
+impl RoutedRequest {
+    pub(crate) fn new(
+        thread_trace: usize,
+        tmsg: TransportMessage,
+        failover_routers: VecDeque<BusAddress>,
+    ) -> Self {
+        RoutedRequest {
+            thread_trace,
+            tmsg,
+            failover_routers,
+        }
+    }
+
+    /// Next router to try for our request, given a status reply to
+    /// request `thread_trace`.
+    ///
+    /// Only requests the router refused to deliver, i.e. those which
+    /// never reached a worker, are resent.  Requests which time out
+    /// or fail may have been processed and are not retried.
+    pub(crate) fn next_router(
+        &mut self,
+        thread_trace: usize,
+        status: &MessageStatus,
+    ) -> Option<BusAddress> {
+        if thread_trace != self.thread_trace || status != &MessageStatus::ServiceNotFound {
+            return None;
+        }
+
+        self.failover_routers.pop_front()
+    }
+}
+
+/// Routers on domains other than `primary_domain` which may handle
+/// requests for `service`, in configuration order.
+pub(crate) fn failover_routers_for(
+    routers: &[conf::ClientRouter],
+    primary_domain: &str,
+    service: &str,
+) -> VecDeque<BusAddress> {
+    routers
+        .iter()
+        .filter(|r| r.domain() != primary_domain)
+        .filter(|r| match r.services() {
+            Some(list) => list.iter().any(|s| s == service),
+            None => true,
+        })
+        .map(|r| BusAddress::for_router(r.username(), r.domain()))
+        .collect()
+}
+
 /// Client communication state maintenance.
 struct ClientSessionInternal {
     /// Client so we can ask it to pull data from the Bus for us.
@@ -195,6 +303,16 @@ struct ClientSessionInternal {
 
     /// Staging ground for "partial" messages arriving in chunks.
     partial_buffer: Option<String>,
+
+    /// Most recent routed request, when domain failover is enabled.
+    routed_request: Option<RoutedRequest>,
+
+    /// Most recent progress reported for each request, keyed on
+    /// thread trace.
+    progress: HashMap<usize, Progress>,
+
+    /// Progress callbacks keyed on thread trace.
+    progress_handlers: HashMap<usize, ProgressHandler>,
 }
 
 impl fmt::Display for ClientSessionInternal {
@@ -219,6 +337,9 @@ impl ClientSessionInternal {
             connected: false,
             last_thread_trace: 0,
             partial_buffer: None,
+            routed_request: None,
+            progress: HashMap::new(),
+            progress_handlers: HashMap::new(),
             backlog: VecDeque::new(),
             thread: util::random_number(16),
         }
@@ -240,7 +361,10 @@ impl ClientSessionInternal {
         //log::trace!("{self} resetting...");
         self.worker_addr = None;
         self.connected = false;
+        self.routed_request = None;
         self.backlog.clear();
+        self.progress.clear();
+        self.progress_handlers.clear();
     }
 
     fn router_addr(&self) -> &BusAddress {
@@ -329,6 +453,48 @@ impl ClientSessionInternal {
         }
     }
 
+    /// Resend a routed request which the router refused via the next
+    /// router on another domain.
+    ///
+    /// Returns true if the request was resent.
+    fn failover(&mut self, thread_trace: usize, status: &MessageStatus) -> EgResult<bool> {
+        let Some(rr) = self.routed_request.as_mut() else {
+            return Ok(false);
+        };
+
+        let Some(router) = rr.next_router(thread_trace, status) else {
+            return Ok(false);
+        };
+
+        let tmsg = rr.tmsg.clone();
+
+        log::warn!("{self} request {thread_trace} refused; retrying via {router}");
+
+        self.client_internal_mut()
+            .get_domain_bus(router.domain())?
+            .send_to(tmsg, router.as_str())?;
+
+        Ok(true)
+    }
+
+    /// Routers on domains other than our primary domain which
+    /// may handle requests for our service.
+    ///
+    /// Empty unless domain failover is enabled.
+    fn failover_routers(&self) -> VecDeque<BusAddress> {
+        let client_conf = conf::config().client();
+
+        if !client_conf.domain_failover() {
+            return VecDeque::new();
+        }
+
+        failover_routers_for(
+            client_conf.routers(),
+            self.router_addr().domain(),
+            self.service(),
+        )
+    }
+
     /// Unpack one opensrf message -- there may be multiple opensrf
     /// messages inside a single transport message.
     fn unpack_reply(
@@ -429,16 +595,39 @@ impl ClientSessionInternal {
             }
             MessageStatus::Continue => {
                 timer.reset();
+
+                if let Some(progress) = Progress::from_status(statmsg) {
+                    log::trace!("{self} request {trace} progress: {progress:?}");
+
+                    if let Some(handler) = self.progress_handlers.get_mut(&trace) {
+                        handler(&progress);
+                    }
+
+                    self.progress.insert(trace, progress);
+                }
+
                 Ok(None)
             }
             MessageStatus::Complete => {
                 log::trace!("{self} request {trace} complete");
+                self.progress_handlers.remove(&trace);
                 Ok(Some(Response {
                     value: None,
                     complete: true,
                     partial: false,
                 }))
             }
+            MessageStatus::ServiceNotFound if self.failover(trace, stat)? => {
+                timer.reset();
+                Ok(None)
+            }
+            MessageStatus::ServiceUnavailable => {
+                self.reset();
+                Err(EgError::new(
+                    ErrorKind::Transient,
+                    &format!("{self} request {trace} failed: {}", statmsg),
+                ))
+            }
             _ => {
                 self.reset();
                 return Err(format!("{self} request {trace} failed: {}", statmsg).into());
@@ -458,6 +647,7 @@ impl ClientSessionInternal {
         let trace = self.incr_thread_trace();
 
         let mut params: ApiParams = params.into();
+        let sensitive = params.sensitive();
         let params: Vec<EgValue> = params.take_params();
 
         if !self.connected() {
@@ -466,21 +656,34 @@ impl ClientSessionInternal {
             self.worker_addr = None;
         }
 
+        let mut msg = Message::new(
+            MessageType::Request,
+            trace,
+            Payload::Method(MethodCall::new(method, params)),
+        );
+
+        msg.set_sensitive(sensitive);
+
         let tmsg = TransportMessage::with_body(
             self.destination_addr().as_str(),
             self.client.address().as_str(),
             self.thread(),
-            Message::new(
-                MessageType::Request,
-                trace,
-                Payload::Method(MethodCall::new(method, params)),
-            ),
+            msg,
         );
 
+        self.routed_request = None;
+
         if !self.connected() {
             // Top-level API calls always go through the router on
             // our primary domain
 
+            let failover_routers = self.failover_routers();
+
+            if !failover_routers.is_empty() {
+                self.routed_request =
+                    Some(RoutedRequest::new(trace, tmsg.clone(), failover_routers));
+            }
+
             let router_addr = self.router_addr().as_str();
             self.client_internal_mut()
                 .bus_mut()
@@ -757,6 +960,10 @@ pub struct ServerSession {
 
     /// Responses collected to be packed into an "atomic" response array.
     atomic_resp_queue: Option<Vec<EgValue>>,
+
+    /// Values stashed by method handlers for use by later requests
+    /// within the same session.
+    session_data: HashMap<String, EgValue>,
 }
 
 impl fmt::Display for ServerSession {
@@ -781,9 +988,33 @@ impl ServerSession {
             responded_complete: false,
             thread: thread.to_string(),
             atomic_resp_queue: None,
+            session_data: HashMap::new(),
         }
     }
 
+    /// Stash a value for use by later requests in this session.
+    ///
+    /// Session data is discarded when the session ends: on DISCONNECT,
+    /// on keepalive timeout, or once a stateless request completes.
+    /// This lets stateful conversations (e.g. batch uploads) keep their
+    /// context server-side instead of passing it back with every call.
+    pub fn set_session_data(&mut self, key: &str, value: impl Into<EgValue>) {
+        self.session_data.insert(key.to_string(), value.into());
+    }
+
+    pub fn session_data(&self, key: &str) -> Option<&EgValue> {
+        self.session_data.get(key)
+    }
+
+    pub fn session_data_mut(&mut self, key: &str) -> Option<&mut EgValue> {
+        self.session_data.get_mut(key)
+    }
+
+    /// Remove and return a stashed value.
+    pub fn take_session_data(&mut self, key: &str) -> Option<EgValue> {
+        self.session_data.remove(key)
+    }
+
     pub fn last_thread_trace(&self) -> usize {
         self.last_thread_trace
     }
@@ -934,6 +1165,39 @@ impl ServerSession {
         self.respond_with_parts(None, true)
     }
 
+    /// Tell the caller how far along a long-running request is.
+    ///
+    /// Progress is sent as a Continue status (see [`Progress`]), so it
+    /// is not mixed in with the request's responses, including for
+    /// atomic requests.  Callers may read it via Request::progress()
+    /// or Request::on_progress(); websocket clients receive the status
+    /// message as-is and HTTP gateway event streams receive it as a
+    /// "progress" event.
+    ///
+    /// Progress also resets the caller's request timeout.
+    pub fn send_progress(&mut self, progress: Progress) -> EgResult<()> {
+        if self.responded_complete {
+            return Ok(());
+        }
+
+        let msg = Message::new(
+            MessageType::Status,
+            self.last_thread_trace(),
+            Payload::Status(progress.into_status()),
+        );
+
+        let tmsg = TransportMessage::with_body(
+            self.sender.as_str(),
+            self.client.address().as_str(),
+            self.thread(),
+            msg,
+        );
+
+        self.client_internal_mut()
+            .get_domain_bus(self.sender.domain())?
+            .send(tmsg)
+    }
+
     pub fn respond(&mut self, value: impl Into<EgValue>) -> EgResult<()> {
         self.respond_with_parts(Some(value.into()), false)
     }
@@ -0,0 +1,399 @@
+//! Chunked transfer of large payloads across multiple OpenSRF messages.
+//!
+//! Each chunk is a hash carrying its byte offset, its data (base64
+//! encoded, or raw UTF-8 text), an MD5 checksum of its bytes, and an
+//! MD5 checksum of the full payload.
+//!
+//! Downloads: a method handler responds with [`send_chunks()`], one
+//! chunk per response.  By convention, download methods take the byte
+//! offset to start from as their final parameter, so [`download()`]
+//! can resume an interrupted transfer from where it left off.
+//!
+//! Uploads: [`upload()`] sends each chunk as a separate request to an
+//! upload method, whose handler passes the chunk to [`receive_chunk()`].
+//! Each upload carries a unique "upload_id".  Chunks are appended to a
+//! spool file named for the upload ID and the checksum of the full
+//! payload, so concurrent uploads of the same content do not collide.
+//! If the spool file holds more or less than the client expects, the
+//! server reports how much it has and the client continues from there.
+use crate as eg;
+use crate::osrf::client::Client;
+use crate::osrf::session::ServerSession;
+use crate::util;
+use crate::EgResult;
+use crate::EgValue;
+use base64::Engine;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Default size in bytes of each chunk before encoding.
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Spool directory for uploads, within the system temp directory.
+const SPOOL_DIR: &str = "eg-transfer";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkEncoding {
+    /// Any binary data.
+    Base64,
+    /// UTF-8 text, sent as-is.  Chunks split on character boundaries.
+    Raw,
+}
+
+impl ChunkEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Base64 => "base64",
+            Self::Raw => "raw",
+        }
+    }
+}
+
+impl TryFrom<&str> for ChunkEncoding {
+    type Error = eg::EgError;
+    fn try_from(s: &str) -> EgResult<Self> {
+        match s {
+            "base64" => Ok(Self::Base64),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("Invalid chunk encoding: {s}").into()),
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+/// Split a payload into transfer chunks, starting at byte `offset`.
+///
+/// Chunks for an empty payload (or an offset at the end of the payload)
+/// consist of a single empty final chunk.
+///
+/// ```
+/// use evergreen::osrf::transfer::{self, ChunkEncoding, Download};
+///
+/// let data = "Hello, Wörld".as_bytes();
+///
+/// let chunks = transfer::chunks(data, 0, 5, ChunkEncoding::Raw).unwrap();
+/// assert_eq!(chunks.len(), 3);
+/// assert_eq!(chunks[0]["data"].as_str(), Some("Hello"));
+///
+/// let mut download = Download::new();
+/// for chunk in chunks.iter() {
+///     download.add_chunk(chunk).unwrap();
+/// }
+///
+/// assert!(download.is_complete());
+/// assert_eq!(download.into_data(), data);
+///
+/// // Resume a binary transfer part way through.
+/// let chunks = transfer::chunks(data, 7, 4, ChunkEncoding::Base64).unwrap();
+/// assert_eq!(chunks[0]["offset"].as_usize(), Some(7));
+/// ```
+pub fn chunks(
+    data: &[u8],
+    offset: usize,
+    chunk_size: usize,
+    encoding: ChunkEncoding,
+) -> EgResult<Vec<EgValue>> {
+    if offset > data.len() {
+        return Err(format!("Offset {offset} exceeds payload size {}", data.len()).into());
+    }
+
+    let text = match encoding {
+        ChunkEncoding::Raw => Some(
+            std::str::from_utf8(data)
+                .map_err(|e| format!("Raw transfers require UTF-8 data: {e}"))?,
+        ),
+        ChunkEncoding::Base64 => None,
+    };
+
+    let file_checksum = checksum(data);
+    let chunk_size = chunk_size.max(4); // room for any UTF-8 character
+
+    let mut chunks = Vec::new();
+    let mut start = offset;
+
+    loop {
+        let mut end = (start + chunk_size).min(data.len());
+
+        let encoded = match text {
+            Some(t) => {
+                while !t.is_char_boundary(end) {
+                    end -= 1;
+                }
+                t[start..end].to_string()
+            }
+            None => base64::engine::general_purpose::STANDARD.encode(&data[start..end]),
+        };
+
+        let last = end == data.len();
+
+        let chunk = eg::hash! {
+            "offset": start,
+            "size": end - start,
+            "total_size": data.len(),
+            "encoding": encoding.as_str(),
+            "data": encoded,
+            "checksum": checksum(&data[start..end]),
+            "file_checksum": file_checksum.as_str(),
+            "final": last,
+        };
+
+        chunks.push(chunk);
+
+        if last {
+            return Ok(chunks);
+        }
+
+        start = end;
+    }
+}
+
+/// Decode a chunk and verify its checksum.
+fn decode_chunk(chunk: &EgValue) -> EgResult<Vec<u8>> {
+    let encoding = ChunkEncoding::try_from(chunk["encoding"].str()?)?;
+
+    let data = match encoding {
+        ChunkEncoding::Raw => chunk["data"].str()?.as_bytes().to_vec(),
+        ChunkEncoding::Base64 => base64::engine::general_purpose::STANDARD
+            .decode(chunk["data"].str()?)
+            .map_err(|e| format!("Invalid base64 chunk data: {e}"))?,
+    };
+
+    if checksum(&data) != chunk["checksum"].str()? {
+        return Err(format!("Checksum mismatch on chunk at offset {}", chunk["offset"]).into());
+    }
+
+    Ok(data)
+}
+
+/// Respond to the caller with a payload split into chunks.
+pub fn send_chunks(
+    session: &mut ServerSession,
+    data: &[u8],
+    offset: usize,
+    chunk_size: usize,
+    encoding: ChunkEncoding,
+) -> EgResult<()> {
+    for chunk in chunks(data, offset, chunk_size, encoding)? {
+        session.respond(chunk)?;
+    }
+    Ok(())
+}
+
+/// Collects downloaded chunks, tracking progress for resuming.
+#[derive(Debug, Default)]
+pub struct Download {
+    data: Vec<u8>,
+    complete: bool,
+}
+
+impl Download {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Number of bytes received so far; the offset to resume from.
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Verify and append a chunk.
+    ///
+    /// Chunks must arrive in order.  On the final chunk, the checksum
+    /// of the full payload is verified.
+    pub fn add_chunk(&mut self, chunk: &EgValue) -> EgResult<()> {
+        let offset = chunk["offset"].int()? as usize;
+
+        if offset != self.offset() {
+            return Err(format!(
+                "Chunk offset {offset} does not match received size {}",
+                self.offset()
+            )
+            .into());
+        }
+
+        let data = decode_chunk(chunk)?;
+        self.data.extend(data);
+
+        if chunk["final"].boolish() {
+            if checksum(&self.data) != chunk["file_checksum"].str()? {
+                // The payload is unusable.  Start over next time.
+                self.data.clear();
+                return Err("Checksum mismatch on downloaded payload".into());
+            }
+            self.complete = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Call a download method and collect its chunks.
+///
+/// The download's current offset is appended to `params`.  If the
+/// transfer fails part way, calling this again with the same Download
+/// resumes where it stopped.
+pub fn download(
+    client: &Client,
+    service: &str,
+    method: &str,
+    mut params: Vec<EgValue>,
+    download: &mut Download,
+) -> EgResult<()> {
+    params.push(download.offset().into());
+
+    let mut ses = client.session(service);
+    let mut req = ses.request(method, params)?;
+
+    while let Some(chunk) = req.recv()? {
+        download.add_chunk(&chunk)?;
+    }
+
+    if !download.is_complete() {
+        return Err(format!("{method} ended before the transfer completed").into());
+    }
+
+    Ok(())
+}
+
+/// Send a payload to an upload method in chunks.
+///
+/// Returns the final response from the upload method, which includes
+/// the "path" of the completed spool file on the server.
+pub fn upload(
+    client: &Client,
+    service: &str,
+    method: &str,
+    data: &[u8],
+    chunk_size: usize,
+    encoding: ChunkEncoding,
+) -> EgResult<EgValue> {
+    let upload_id = format!(
+        "{}-{}-{}",
+        std::process::id(),
+        util::thread_id(),
+        util::random_number(12)
+    );
+
+    let mut list = chunks(data, 0, chunk_size, encoding)?;
+    let mut index = 0;
+
+    loop {
+        let mut chunk = list[index].clone();
+        chunk["upload_id"] = EgValue::from(upload_id.as_str());
+
+        let offset = chunk["offset"].int()? as usize;
+
+        let resp = client
+            .send_recv_one(service, method, chunk)?
+            .ok_or_else(|| format!("{method} returned no response"))?;
+
+        if resp["complete"].boolish() {
+            return Ok(resp);
+        }
+
+        let received = resp["received"].int()? as usize;
+
+        if received == offset {
+            return Err(format!("{method} made no progress at offset {offset}").into());
+        }
+
+        // Normally the server wants our next chunk.  Otherwise,
+        // rebuild the chunks from whatever offset it reports.
+        if list.get(index + 1).and_then(|c| c["offset"].as_usize()) == Some(received) {
+            index += 1;
+        } else {
+            list = chunks(data, received, chunk_size, encoding)?;
+            index = 0;
+        }
+    }
+}
+
+/// Location of the spool file for an upload with the provided
+/// upload ID and payload checksum.
+pub fn spool_path(upload_id: &str, file_checksum: &str) -> EgResult<PathBuf> {
+    // Both values become part of a file name.  Make sure they are
+    // just a checksum and an ID.
+    if file_checksum.len() != 32 || !file_checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid transfer checksum: {file_checksum}").into());
+    }
+
+    if upload_id.is_empty()
+        || upload_id.len() > 64
+        || !upload_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid upload ID: {upload_id}").into());
+    }
+
+    Ok(std::env::temp_dir()
+        .join(SPOOL_DIR)
+        .join(format!("{file_checksum}.{upload_id}.part")))
+}
+
+/// Append an uploaded chunk to its spool file.
+///
+/// Chunks which do not start where the spool file ends are ignored,
+/// so the client can resume from the reported "received" size.  Once
+/// the final chunk arrives and the full payload checksum is verified,
+/// the response includes "complete": true and the "path" of the spool
+/// file.  The caller is responsible for removing the file when done.
+pub fn receive_chunk(chunk: &EgValue) -> EgResult<EgValue> {
+    let file_checksum = chunk["file_checksum"].str()?;
+    let path = spool_path(chunk["upload_id"].str()?, file_checksum)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {dir:?}: {e}"))?;
+    }
+
+    let received = match fs::metadata(&path) {
+        Ok(m) => m.len() as usize,
+        Err(_) => 0,
+    };
+
+    let offset = chunk["offset"].int()? as usize;
+
+    if offset != received {
+        return Ok(eg::hash! {"received": received, "complete": false});
+    }
+
+    let data = decode_chunk(chunk)?;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Cannot open {path:?}: {e}"))?;
+
+    file.write_all(&data)
+        .map_err(|e| format!("Cannot write {path:?}: {e}"))?;
+
+    let received = received + data.len();
+
+    if !chunk["final"].boolish() {
+        return Ok(eg::hash! {"received": received, "complete": false});
+    }
+
+    let contents = fs::read(&path).map_err(|e| format!("Cannot read {path:?}: {e}"))?;
+
+    if checksum(&contents) != file_checksum {
+        fs::remove_file(&path).ok();
+        return Err("Checksum mismatch on uploaded payload".into());
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+
+    Ok(eg::hash! {"received": received, "complete": true, "path": path_str})
+}
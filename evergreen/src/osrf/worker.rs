@@ -1,3 +1,5 @@
+use crate as eg;
+use crate::date;
 use crate::osrf::addr::BusAddress;
 use crate::osrf::app;
 use crate::osrf::client::{Client, ClientSingleton};
@@ -10,12 +12,13 @@ use crate::osrf::message::MessageType;
 use crate::osrf::message::Payload;
 use crate::osrf::message::TransportMessage;
 use crate::osrf::method;
-use crate::osrf::method::ParamCount;
 use crate::osrf::sclient::HostSettings;
 use crate::osrf::session::ServerSession;
 use crate::util;
 use crate::EgResult;
+use crate::EgValue;
 use mptc::signals::SignalTracker;
+use mptc::usage::ResourceUsage;
 use std::cell::RefMut;
 use std::collections::HashMap;
 use std::fmt;
@@ -27,6 +30,82 @@ use std::time;
 // How often each worker wakes to check for shutdown signals, etc.
 const IDLE_WAKE_TIME: i32 = 5;
 
+/// Control API calls share this prefix, e.g. opensrf.system.control.reload
+pub const CONTROL_METHOD_PREFIX: &str = "opensrf.system.control.";
+
+/// Operator commands broadcast to every worker of a service.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ControlCommand {
+    /// Ask each worker to reload its settings.
+    Reload,
+    /// Stop receiving routed requests without stopping the service.
+    Quiesce,
+    /// Undo a Quiesce.
+    Resume,
+    /// Report per-worker statistics.
+    Stats,
+    /// Report server-level worker counts.  Not relayed to workers.
+    Status,
+}
+
+impl ControlCommand {
+    /// Translate a control API name into a command.
+    ///
+    /// ```
+    /// use evergreen::osrf::worker::ControlCommand;
+    ///
+    /// let cmd = ControlCommand::from_method("opensrf.system.control.reload");
+    /// assert_eq!(cmd, Some(ControlCommand::Reload));
+    /// assert_eq!(ControlCommand::from_method("opensrf.system.echo"), None);
+    /// ```
+    pub fn from_method(method: &str) -> Option<Self> {
+        match method.strip_prefix(CONTROL_METHOD_PREFIX)? {
+            "reload" => Some(Self::Reload),
+            "quiesce" => Some(Self::Quiesce),
+            "resume" => Some(Self::Resume),
+            "stats" => Some(Self::Stats),
+            "status" => Some(Self::Status),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reload => "reload",
+            Self::Quiesce => "quiesce",
+            Self::Resume => "resume",
+            Self::Stats => "stats",
+            Self::Status => "status",
+        }
+    }
+
+    /// Full API name for this command.
+    pub fn method(&self) -> String {
+        format!("{CONTROL_METHOD_PREFIX}{}", self.as_str())
+    }
+}
+
+/// Control request relayed from the server to each of its workers.
+///
+/// Workers acknowledge by sending a Result directly to the
+/// reply_to address using the original thread and thread trace.
+#[derive(Debug, Clone)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply_to: String,
+    pub thread: String,
+    pub thread_trace: usize,
+}
+
+/// Instructions sent from the server to an individual worker.
+#[derive(Debug, Clone)]
+pub enum WorkerCommand {
+    /// Operator control request to handle and acknowledge.
+    Control(ControlRequest),
+    /// Exit between sessions.  Used to scale down idle workers.
+    Exit,
+}
+
 /// Each worker thread is in one of these states.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum WorkerState {
@@ -76,6 +155,12 @@ pub struct Worker {
 
     /// Channel for sending worker state info to our parent.
     to_parent_tx: mpsc::SyncSender<WorkerStateEvent>,
+
+    /// Channel for receiving commands from our parent.
+    from_parent_rx: mpsc::Receiver<WorkerCommand>,
+
+    /// Epoch seconds when this worker started.
+    start_time: f64,
 }
 
 impl fmt::Display for Worker {
@@ -91,6 +176,7 @@ impl Worker {
         sig_tracker: SignalTracker,
         methods: Arc<HashMap<String, method::MethodDef>>,
         to_parent_tx: mpsc::SyncSender<WorkerStateEvent>,
+        from_parent_rx: mpsc::Receiver<WorkerCommand>,
     ) -> EgResult<Worker> {
         let client = Client::connect()?;
 
@@ -101,6 +187,8 @@ impl Worker {
             methods,
             client,
             to_parent_tx,
+            from_parent_rx,
+            start_time: date::epoch_secs(),
             session: None,
             connected: false,
         })
@@ -203,6 +291,10 @@ impl Worker {
                 continue;
             }
 
+            // Parent commands are only handled between conversations
+            // so a reload or exit never lands in the middle of a session.
+            let exit_requested = self.process_parent_commands(&mut app_worker, requests);
+
             if work_occurred {
                 // also true if msg_handled
 
@@ -224,10 +316,11 @@ impl Worker {
                     requests += 1;
 
                     // An inbound message may have modified our
-                    // thread-scoped locale.  Reset our locale back
-                    // to the default so the previous locale does not
-                    // affect future messages.
+                    // thread-scoped locale and ingress.  Reset them
+                    // back to the defaults so the previous values do
+                    // not affect future messages.
                     message::reset_thread_locale();
+                    message::reset_thread_ingress();
                 }
             } else {
                 // Let the worker know we woke up and nothing interesting
@@ -245,6 +338,11 @@ impl Worker {
                 log::info!("{selfstr} received a stop signal");
                 break;
             }
+
+            if exit_requested {
+                log::info!("{selfstr} exiting at the request of our parent");
+                break;
+            }
         }
 
         log::debug!("{self} exiting listen loop and cleaning up");
@@ -259,6 +357,97 @@ impl Worker {
         self.reset().ok();
     }
 
+    /// Handle any commands sent to us by our parent.
+    ///
+    /// Returns true if our parent asked us to exit.
+    fn process_parent_commands(
+        &mut self,
+        app_worker: &mut Box<dyn app::ApplicationWorker>,
+        requests: usize,
+    ) -> bool {
+        let mut exit_requested = false;
+
+        while let Ok(cmd) = self.from_parent_rx.try_recv() {
+            match cmd {
+                WorkerCommand::Control(req) => {
+                    self.handle_control_request(app_worker, req, requests)
+                }
+                WorkerCommand::Exit => exit_requested = true,
+            }
+        }
+
+        exit_requested
+    }
+
+    /// Handle a control request relayed to us by our parent,
+    /// acknowledging it directly to the requester.
+    fn handle_control_request(
+        &mut self,
+        app_worker: &mut Box<dyn app::ApplicationWorker>,
+        req: ControlRequest,
+        requests: usize,
+    ) {
+        log::info!("{self} received control command {}", req.command.as_str());
+
+        let mut ack = eg::hash! {
+            "service": self.service.as_str(),
+            "worker_id": self.worker_id,
+            "command": req.command.as_str(),
+            "status": "ok",
+        };
+
+        match req.command {
+            ControlCommand::Reload => {
+                if let Err(e) = app_worker.worker_reload() {
+                    log::error!("{self} worker_reload() failed: {e}");
+                    ack["status"] = "error".into();
+                    ack["error"] = e.to_string().into();
+                }
+            }
+            ControlCommand::Stats => {
+                ack["requests"] = requests.into();
+                ack["start_time"] = self.start_time.into();
+
+                // Memory is shared by all workers in the process, so
+                // rss_bytes is the same for each.
+                if let Some(usage) = ResourceUsage::current_thread() {
+                    ack["cpu_secs"] = usage.cpu_secs.into();
+                    ack["rss_bytes"] = usage.rss_bytes.into();
+                }
+            }
+            // Router registration is managed by our parent.
+            ControlCommand::Quiesce | ControlCommand::Resume | ControlCommand::Status => {}
+        }
+
+        if let Err(e) = self.send_control_ack(&req, ack) {
+            log::error!("{self} could not acknowledge control request: {e}");
+        }
+    }
+
+    fn send_control_ack(&mut self, req: &ControlRequest, ack: EgValue) -> EgResult<()> {
+        let reply_to = BusAddress::from_str(&req.reply_to)?;
+
+        let tmsg = TransportMessage::with_body(
+            reply_to.as_str(),
+            self.client.address().as_str(),
+            &req.thread,
+            Message::new(
+                MessageType::Result,
+                req.thread_trace,
+                Payload::Result(message::Result::new(
+                    MessageStatus::Ok,
+                    "OK",
+                    "osrfResult",
+                    ack,
+                )),
+            ),
+        );
+
+        self.client_internal_mut()
+            .get_domain_bus(reply_to.domain())?
+            .send(tmsg)
+    }
+
     /// Call recv() on our message bus and process the response.
     ///
     /// Return value consists of (work_occurred, msg_handled).
@@ -304,6 +493,12 @@ impl Worker {
 
                 self.set_active()?;
 
+                // The conversation is over.  Our session, including any
+                // session data, is discarded when the listen loop
+                // resets for the next request.
+                self.connected = false;
+                app_worker.keepalive_timeout()?;
+
                 return Ok((true, false)); // work occurred
             }
         };
@@ -444,18 +639,20 @@ impl Worker {
         mut msg: message::Message,
         app_worker: &mut Box<dyn app::ApplicationWorker>,
     ) -> EgResult<()> {
+        let sensitive = msg.sensitive();
+
         let method_call = match msg.take_payload() {
             message::Payload::Method(m) => m,
             _ => return self.reply_bad_request("Request sent without a MethoCall payload"),
         };
 
-        let param_count = method_call.params().len();
         let api_name = method_call.method().to_string();
 
         let log_params = util::stringify_params(
             &api_name,
             method_call.params(),
             conf::config().log_protect(),
+            sensitive,
         );
 
         // Log the API call
@@ -498,49 +695,28 @@ impl Worker {
         }
 
         let method_def = method_def.unwrap();
-        let pcount = method_def.param_count();
-
-        // Make sure the number of params sent by the caller matches the
-        // parameter count for the method.
-        if !ParamCount::matches(&pcount, param_count as u8) {
-            return self.reply_bad_request(&format!(
-                "Invalid param count sent: method={} sent={} needed={}",
-                api_name, param_count, &pcount,
-            ));
-        }
 
-        // Verify paramter types are correct, at least superficially.
-        // Do this after deserialization.
-        if let Some(param_defs) = method_def.params() {
-            for (idx, param_def) in param_defs.iter().enumerate() {
-                // There may be more param defs than parameters if
-                // some param are optional.
-                if let Some(param_val) = method_call.params().get(idx) {
-                    if idx >= pcount.minimum().into() && param_val.is_null() {
-                        // NULL placeholders for non-required parameters are
-                        // allowed.
-                        continue;
-                    }
-                    if !param_def.datatype.matches(param_val) {
-                        return self.reply_bad_request(&format!(
-                            "Invalid paramter type: wanted={} got={}",
-                            param_def.datatype,
-                            param_val.clone().dump()
-                        ));
-                    }
-                } else {
-                    // More defs than actual params. Verification complete.
-                    break;
-                }
-            }
+        // Make sure the caller sent the number and types of parameters
+        // the method requires before handing them to the method.
+        if let Err(details) = method_def.validate_params(method_call.params()) {
+            let text = details["errors"]
+                .members()
+                .filter_map(|e| e["message"].as_str())
+                .collect::<Vec<&str>>()
+                .join("; ");
+
+            log::warn!("{self} rejecting {api_name}: {text}");
+
+            return self.reply_bad_request_details(&text, Some(details));
         }
 
         // Call the API
         if let Err(err) = (method_def.handler())(app_worker, self.session_mut(), method_call) {
             let msg = format!("{self} method {api_name} exited: \"{err}\"");
-            log::error!("{msg}");
+            log::error!("{msg} [{}]", err.location());
+            let retryable = err.is_retryable();
             app_worker.api_call_error(&api_name, err);
-            self.reply_server_error(&msg)?;
+            self.reply_server_error(&msg, retryable)?;
             Err(msg)?;
         }
 
@@ -551,15 +727,26 @@ impl Worker {
         }
     }
 
-    fn reply_server_error(&mut self, text: &str) -> EgResult<()> {
+    /// Tell the caller its request failed.
+    ///
+    /// Retryable failures, e.g. deadlocks, are reported as
+    /// ServiceUnavailable so the caller knows the request may
+    /// succeed if it's sent again.
+    fn reply_server_error(&mut self, text: &str, retryable: bool) -> EgResult<()> {
         self.connected = false;
 
+        let (status, label) = if retryable {
+            (MessageStatus::ServiceUnavailable, "Service Unavailable")
+        } else {
+            (MessageStatus::InternalServerError, "Internal Server Error")
+        };
+
         let msg = Message::new(
             MessageType::Status,
             self.session().last_thread_trace(),
             Payload::Status(message::Status::new(
-                MessageStatus::InternalServerError,
-                &format!("Internal Server Error: {text}"),
+                status,
+                &format!("{label}: {text}"),
                 "osrfStatus",
             )),
         );
@@ -577,16 +764,28 @@ impl Worker {
     }
 
     fn reply_bad_request(&mut self, text: &str) -> EgResult<()> {
+        self.reply_bad_request_details(text, None)
+    }
+
+    /// Reply with a BadRequest status, optionally including a
+    /// structured description of the problem.
+    fn reply_bad_request_details(&mut self, text: &str, details: Option<EgValue>) -> EgResult<()> {
         self.connected = false;
 
+        let mut status = message::Status::new(
+            MessageStatus::BadRequest,
+            &format!("Bad Request: {text}"),
+            "osrfStatus",
+        );
+
+        if let Some(details) = details {
+            status.set_details(details);
+        }
+
         let msg = Message::new(
             MessageType::Status,
             self.session().last_thread_trace(),
-            Payload::Status(message::Status::new(
-                MessageStatus::BadRequest,
-                &format!("Bad Request: {text}"),
-                "osrfStatus",
-            )),
+            Payload::Status(status),
         );
 
         let tmsg = TransportMessage::with_body(
@@ -2,6 +2,7 @@
 
 use crate::event::EgEvent;
 use std::fmt;
+use std::panic::Location;
 
 /// This is a convenient way to set the error type to EgError on common
 /// method/function responses to simplify the declaration of return types.
@@ -14,7 +15,7 @@ use std::fmt;
 ///
 /// fn foo1() -> EgResult<()> {
 ///   let evt = EgEvent::new("PROBLEM");
-///   let err = EgError::Event(evt);
+///   let err = EgError::from(evt);
 ///   Err(err)
 /// }
 ///
@@ -29,84 +30,205 @@ use std::fmt;
 ///   Ok(())
 /// }
 ///
-/// if let EgError::Event(e) = foo1().err().unwrap() {
-///     assert_eq!(e.textcode(), "PROBLEM");
-/// } else {
-///     panic!("unexpected response");
+/// for res in [foo1(), foo2(), foo3()] {
+///     let err = res.err().unwrap();
+///     assert_eq!(err.kind(), ErrorKind::Event);
+///     assert_eq!(err.event().unwrap().textcode(), "PROBLEM");
 /// }
 ///
-/// if let EgError::Event(e) = foo2().err().unwrap() {
-///     assert_eq!(e.textcode(), "PROBLEM");
-/// } else {
-///     panic!("unexpected response");
-/// }
+/// ```
+pub type EgResult<T> = std::result::Result<T, EgError>;
+
+/// General category of an error.
+///
+/// Allows callers to react to classes of failures without inspecting
+/// error message strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Unclassified failure.  Plain string errors have this kind.
+    Internal,
+    /// Non-success event returned by the ILS.
+    Event,
+    /// Authentication is missing or invalid.
+    Auth,
+    /// Requestor lacks a required permission.
+    Permission,
+    /// A requested object does not exist.
+    NotFound,
+    /// Invalid data or parameters were provided.
+    InvalidInput,
+    /// Error communicating via the message bus.
+    Network,
+    /// Error communicating with the database.
+    Database,
+    /// The object was modified by someone else since it was retrieved.
+    Conflict,
+    /// Temporary backend failure, i.e. a deadlock or serialization
+    /// failure.  The operation may succeed if retried.
+    Transient,
+}
+
+impl ErrorKind {
+    /// Determine the kind of error represented by an event.
+    fn from_event(evt: &EgEvent) -> ErrorKind {
+        let textcode = evt.textcode();
+
+        if textcode == "PERM_FAILURE" {
+            ErrorKind::Permission
+        } else if textcode == "NO_SESSION" {
+            ErrorKind::Auth
+        } else if textcode.ends_with("_NOT_FOUND") {
+            ErrorKind::NotFound
+        } else {
+            ErrorKind::Event
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Error type with an optional EgEvent and a general error kind.
+///
+/// The details are boxed to keep `EgResult`s small.
+///
+/// ```
+/// use evergreen::result::*;
 ///
-/// if let EgError::Event(e) = foo3().err().unwrap() {
-///     assert_eq!(e.textcode(), "PROBLEM");
-/// } else {
-///     panic!("unexpected response");
+/// fn load() -> EgResult<()> {
+///     Err(EgError::new(ErrorKind::Database, "connection refused"))
 /// }
 ///
+/// let err = load().err().unwrap();
+///
+/// assert_eq!(err.kind(), ErrorKind::Database);
+/// assert_eq!(err.message(), "connection refused");
+/// assert_eq!(err.to_string(), "connection refused");
+/// assert!(err.event().is_none());
 /// ```
-pub type EgResult<T> = std::result::Result<T, EgError>;
+#[derive(Debug, Clone)]
+pub struct EgError(Box<ErrorDetails>);
 
 #[derive(Debug, Clone)]
-pub enum EgError {
-    /// General error/failure messages that is not linked to an EgEvent.
-    ///
-    /// For one thing, this is useful for encapsulating OpenSRF's generic
-    /// fatal error strings.
-    Debug(String),
-    Event(EgEvent),
+struct ErrorDetails {
+    kind: ErrorKind,
+
+    /// Error message for errors which have no event.
+    message: String,
+
+    event: Option<EgEvent>,
+
+    /// Where the error was created.
+    location: &'static Location<'static>,
 }
 
-impl std::error::Error for EgError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match *self {
-            _ => None,
+impl std::error::Error for EgError {}
+
+impl EgError {
+    #[track_caller]
+    pub fn new(kind: ErrorKind, message: &str) -> EgError {
+        EgError(Box::new(ErrorDetails {
+            kind,
+            message: message.to_string(),
+            event: None,
+            location: Location::caller(),
+        }))
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind
+    }
+
+    /// True if the error is a temporary failure which may succeed
+    /// if the operation is retried.
+    ///
+    /// ```
+    /// use evergreen::result::*;
+    ///
+    /// let err = EgError::new(ErrorKind::Transient, "deadlock detected");
+    /// assert!(err.is_retryable());
+    ///
+    /// // Plain string errors are never retryable, whatever they say.
+    /// assert!(!EgError::from("ERROR:  40P01: deadlock detected").is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        self.0.kind == ErrorKind::Transient
+    }
+
+    /// The event which caused the error, if any.
+    pub fn event(&self) -> Option<&EgEvent> {
+        self.0.event.as_ref()
+    }
+
+    pub fn take_event(&mut self) -> Option<EgEvent> {
+        self.0.event.take()
+    }
+
+    /// Error message.
+    ///
+    /// For event errors, this is the event's string form.
+    pub fn message(&self) -> String {
+        match self.0.event {
+            Some(ref e) => e.to_string(),
+            None => self.0.message.to_string(),
         }
     }
-}
 
-impl EgError {
+    /// Source location where the error was created.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.0.location
+    }
+
     /// Coerce the EgError into an EgEvent regardless of its internal
     /// type.
     ///
-    /// If the error is a Debug(string) type, return a new
-    /// INTERNAL_SERVER_ERROR event containing the error string.
-    /// Otherwise, return a copy of the contained event.
+    /// If the error has no event, return a new INTERNAL_SERVER_ERROR
+    /// event containing the error string.  Otherwise, return a copy
+    /// of the contained event.
     pub fn event_or_default(&self) -> EgEvent {
-        match self {
-            EgError::Event(e) => e.clone(),
-            EgError::Debug(s) => {
+        match self.0.event {
+            Some(ref e) => e.clone(),
+            None => {
                 let mut evt = EgEvent::new("INTERNAL_SERVER_ERROR");
                 // This is for debug purposes only -- i18n not needed.
-                evt.set_desc(&format!("Server Error: {s}"));
+                evt.set_desc(&format!("Server Error: {self}"));
                 evt
             }
         }
     }
 }
 
+/// The alternate form ({:#}) also includes the error's source location.
 impl fmt::Display for EgError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Self::Debug(ref m) => write!(f, "{m}"),
-            Self::Event(ref e) => write!(f, "{e}"),
+        match self.0.event {
+            Some(ref e) => write!(f, "{e}")?,
+            None => write!(f, "{}", self.0.message)?,
+        }
+
+        if f.alternate() {
+            write!(f, " [{}]", self.0.location)?;
         }
+
+        Ok(())
     }
 }
 
 /// Useful for translating generic OSRF Err(String)'s into EgError's
 impl From<String> for EgError {
+    #[track_caller]
     fn from(msg: String) -> Self {
-        EgError::Debug(msg)
+        EgError::from(msg.as_str())
     }
 }
 
 impl From<&str> for EgError {
+    #[track_caller]
     fn from(msg: &str) -> Self {
-        EgError::Debug(msg.to_string())
+        EgError::new(ErrorKind::Internal, msg)
     }
 }
 
@@ -115,18 +237,21 @@ impl From<&str> for EgError {
 /// OpenSRF published APIs
 impl From<EgError> for String {
     fn from(err: EgError) -> Self {
-        match err {
-            EgError::Debug(m) => m.to_string(),
-            EgError::Event(e) => e.to_string(),
-        }
+        err.to_string()
     }
 }
 
 /// Useful for translating EgEvents that are returned as Err's into
 /// fully-fledged Err(EgError) responses.
 impl From<EgEvent> for EgError {
+    #[track_caller]
     fn from(evt: EgEvent) -> Self {
-        EgError::Event(evt)
+        EgError(Box::new(ErrorDetails {
+            kind: ErrorKind::from_event(&evt),
+            message: String::new(),
+            event: Some(evt),
+            location: Location::caller(),
+        }))
     }
 }
 
@@ -135,22 +260,17 @@ impl From<EgEvent> for EgError {
 /// use evergreen::result::*;
 ///
 /// fn foo() -> Result<(), EgError> {
-///     let evt = EgEvent::new("PROBLEM");
-///     Err(evt.into())
+///     let evt = EgEvent::new("ACTOR_USER_NOT_FOUND");
+///     Err((&evt).into())
 /// }
 ///
-/// if let Err(e) = foo() {
-///     if let EgError::Event(ee) = e {
-///         assert_eq!(ee.textcode(), "PROBLEM");
-///     } else {
-///         panic!("Unexpected EgError type: {}", e);
-///     }
-/// } else {
-///     panic!("Unexpected result type");
-/// }
+/// let err = foo().err().unwrap();
+/// assert_eq!(err.kind(), ErrorKind::NotFound);
+/// assert_eq!(err.event().unwrap().textcode(), "ACTOR_USER_NOT_FOUND");
 /// ```
 impl From<&EgEvent> for EgError {
+    #[track_caller]
     fn from(evt: &EgEvent) -> Self {
-        EgError::Event(evt.clone())
+        EgError::from(evt.clone())
     }
 }
@@ -1,19 +1,32 @@
+use base64::Engine;
+use eg::common::barcode::{self, Symbology};
+use eg::common::course::{self, CourseQuery};
 use eg::common::penalty;
 use eg::common::settings::Settings;
 use eg::common::user;
 use eg::osrf::app::ApplicationWorker;
 use eg::osrf::message;
 use eg::osrf::method::{ParamCount, ParamDataType, StaticMethodDef, StaticParam};
+use eg::osrf::sclient::HostSettings;
 use eg::osrf::session::ServerSession;
 use eg::Editor;
 use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
 use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 
 // Import our local app module
 use crate::app;
 
+/// Where patron photos are stored when the patron_photo_dir app
+/// setting is not set.
+const DEFAULT_PHOTO_DIR: &str = "/openils/var/data/patron-photos";
+
+/// Patron photos larger than this many bytes are rejected.
+const MAX_PHOTO_SIZE: usize = 2 * 1024 * 1024;
+
 /// List of method definitions we know at compile time.
 ///
 /// These will form the basis (and possibly all) of our published methods.
@@ -181,6 +194,148 @@ pub static METHODS: &[StaticMethodDef] = &[
             },
         ],
     },
+    StaticMethodDef {
+        name: "user.merge",
+        desc: "Merge Users Into a Lead User",
+        param_count: ParamCount::Range(3, 4),
+        handler: merge_users,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Lead User ID",
+                datatype: ParamDataType::Number,
+                desc: "User which absorbs the merged users",
+            },
+            StaticParam {
+                name: "User IDs",
+                datatype: ParamDataType::Array,
+                desc: "Users to merge into the lead user",
+            },
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "Optional delete_addresses, delete_cards, and
+                    deactivate_cards flags",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "course.list",
+        desc: "List Course Reserves Courses",
+        param_count: ParamCount::Range(0, 1),
+        handler: list_courses,
+        params: &[StaticParam {
+            name: "Options",
+            datatype: ParamDataType::Object,
+            desc: "Optional owning_lib, include_archived, limit, and offset",
+        }],
+    },
+    StaticMethodDef {
+        name: "course.materials.list",
+        desc: "List Materials Attached to a Course",
+        param_count: ParamCount::Range(1, 2),
+        handler: list_course_materials,
+        params: &[
+            StaticParam {
+                name: "Course ID",
+                datatype: ParamDataType::Number,
+                desc: "",
+            },
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "Optional limit and offset",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "course.users.list",
+        desc: "List Public Users (e.g. Instructors) of a Course",
+        param_count: ParamCount::Range(1, 2),
+        handler: list_course_users,
+        params: &[
+            StaticParam {
+                name: "Course ID",
+                datatype: ParamDataType::Number,
+                desc: "",
+            },
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "Optional limit and offset",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "user.photo.store",
+        desc: "Store or remove a patron photo",
+        param_count: ParamCount::Exactly(3),
+        handler: store_user_photo,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "User ID",
+                datatype: ParamDataType::Number,
+                desc: "",
+            },
+            StaticParam {
+                name: "Photo",
+                datatype: ParamDataType::String,
+                desc: "Base64 encoded PNG, JPEG, GIF, or WebP image. Null removes the photo",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "user.photo.retrieve",
+        desc: "Retrieve a patron photo as {content_type, data (base64)}",
+        param_count: ParamCount::Exactly(2),
+        handler: retrieve_user_photo,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "User ID",
+                datatype: ParamDataType::Number,
+                desc: "",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "barcode.render",
+        desc: "Render a barcode image as {content_type, data}",
+        param_count: ParamCount::Range(2, 3),
+        handler: render_barcode,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Barcode",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "symbology (codabar or code39), format (svg or png), \
+                    height (max 1000), and module_width (max 10).  \
+                    PNG data is base64 encoded",
+            },
+        ],
+    },
 ];
 
 pub fn get_barcodes(
@@ -515,11 +670,294 @@ pub fn update_penalties(
         None => None,
     };
 
-    editor.xact_begin()?;
+    editor.in_transaction(|e| {
+        penalty::calculate_penalties(e, user_id, context_org, only_penalties)
+    })?;
+
+    session.respond(1)
+}
+
+pub fn merge_users(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let lead_id = method.param(1).int()?;
+
+    let mut user_ids = Vec::new();
+    for id in method.param(2).members() {
+        user_ids.push(id.int()?);
+    }
+
+    let options = match method.params().get(3) {
+        Some(ops) => user::MergeOptions {
+            delete_addresses: ops["delete_addresses"].boolish(),
+            delete_cards: ops["delete_cards"].boolish(),
+            deactivate_cards: ops["deactivate_cards"].boolish(),
+        },
+        None => user::MergeOptions::default(),
+    };
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    // Merging requires permission at the home org of every user involved.
+    for user_id in user_ids.iter().chain([lead_id].iter()) {
+        let user = match editor.retrieve("au", *user_id)? {
+            Some(u) => u,
+            None => return session.respond(editor.event()),
+        };
+
+        if !editor.allowed_at("MERGE_USERS", user["home_ou"].int()?)? {
+            return session.respond(editor.event());
+        }
+    }
+
+    editor.in_transaction(|e| user::merge_users(e, lead_id, &user_ids, &options))?;
+
+    session.respond(1)
+}
+
+pub fn list_courses(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let query = CourseQuery::from_options(method.param(0));
+
+    let mut editor = Editor::new(worker.client());
+
+    for c in course::list_courses(&mut editor, &query)? {
+        session.respond(c)?;
+    }
+
+    Ok(())
+}
+
+pub fn list_course_materials(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let course_id = method.param(0).int()?;
+    let query = CourseQuery::from_options(method.param(1));
+
+    let mut editor = Editor::new(worker.client());
+
+    for m in course::course_materials(&mut editor, course_id, &query)? {
+        session.respond(m)?;
+    }
+
+    Ok(())
+}
+
+pub fn list_course_users(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let course_id = method.param(0).int()?;
+    let query = CourseQuery::from_options(method.param(1));
+
+    let mut editor = Editor::new(worker.client());
+
+    for u in course::course_users(&mut editor, course_id, &query)? {
+        session.respond(u)?;
+    }
+
+    Ok(())
+}
+
+/// Path to the photo file for a user.
+fn photo_path(user_id: i64) -> EgResult<PathBuf> {
+    let dir = HostSettings::get("apps/open-ils.rs-actor/app_settings/patron_photo_dir")?
+        .as_str()
+        .unwrap_or(DEFAULT_PHOTO_DIR);
+
+    Ok(PathBuf::from(dir).join(user_id.to_string()))
+}
+
+/// Image content type derived from the leading bytes of the image.
+fn photo_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() > 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Load a user and verify the requestor is the user or has the
+/// provided permission at the user's home org unit.
+///
+/// Returns None if the user was not found or the permission check
+/// failed, leaving the reason in the editor's last event.
+fn user_with_perm(editor: &mut Editor, user_id: i64, perm: &str) -> EgResult<Option<EgValue>> {
+    let user = match editor.retrieve("au", user_id)? {
+        Some(u) => u,
+        None => return Ok(None),
+    };
+
+    if user_id != editor.requestor_id()? && !editor.allowed_at(perm, user["home_ou"].int()?)? {
+        return Ok(None);
+    }
 
-    penalty::calculate_penalties(&mut editor, user_id, context_org, only_penalties)?;
+    Ok(Some(user))
+}
 
-    editor.commit()?;
+pub fn store_user_photo(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let user_id = method.param(1).int()?;
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    let user = match editor.retrieve("au", user_id)? {
+        Some(u) => u,
+        None => return session.respond(editor.event()),
+    };
+
+    // Patrons may not replace their own photos, so no self-exemption.
+    if !editor.allowed_at("UPDATE_USER", user["home_ou"].int()?)? {
+        return session.respond(editor.event());
+    }
+
+    let path = photo_path(user_id)?;
+
+    let Some(encoded) = method.param(2).as_str() else {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Cannot remove {path:?}: {e}"))?;
+        }
+        return session.respond(1);
+    };
+
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid base64 photo data: {e}"))?;
+
+    if data.len() > MAX_PHOTO_SIZE {
+        return Err(format!("Photo exceeds maximum size of {MAX_PHOTO_SIZE} bytes").into());
+    }
+
+    if photo_content_type(&data).is_none() {
+        return Err("Photo must be a PNG, JPEG, GIF, or WebP image".into());
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {dir:?}: {e}"))?;
+    }
+
+    fs::write(&path, data).map_err(|e| format!("Cannot write {path:?}: {e}"))?;
 
     session.respond(1)
 }
+
+pub fn retrieve_user_photo(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let user_id = method.param(1).int()?;
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    if user_with_perm(&mut editor, user_id, "VIEW_USER")?.is_none() {
+        return session.respond(editor.event());
+    }
+
+    let path = photo_path(user_id)?;
+
+    if !path.exists() {
+        return session.respond(EgValue::Null);
+    }
+
+    let data = fs::read(&path).map_err(|e| format!("Cannot read {path:?}: {e}"))?;
+
+    session.respond(eg::hash! {
+        content_type: photo_content_type(&data).unwrap_or("application/octet-stream"),
+        data: base64::engine::general_purpose::STANDARD.encode(&data),
+    })
+}
+
+pub fn render_barcode(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsActorWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let value = method.param(1).str()?;
+    let options = method.param(2);
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    let symbology = Symbology::try_from(options["symbology"].as_str().unwrap_or("codabar"))?;
+    let height = options["height"]
+        .as_usize()
+        .unwrap_or(barcode::DEFAULT_HEIGHT);
+    let module_width = options["module_width"]
+        .as_usize()
+        .unwrap_or(barcode::DEFAULT_MODULE_WIDTH);
+
+    if height > barcode::MAX_HEIGHT {
+        return Err(format!(
+            "Barcode height {height} exceeds the maximum of {}",
+            barcode::MAX_HEIGHT
+        )
+        .into());
+    }
+
+    if module_width > barcode::MAX_MODULE_WIDTH {
+        return Err(format!(
+            "Barcode module width {module_width} exceeds the maximum of {}",
+            barcode::MAX_MODULE_WIDTH
+        )
+        .into());
+    }
+
+    let modules = barcode::encode(value, symbology)?;
+
+    match options["format"].as_str().unwrap_or("svg") {
+        "svg" => session.respond(eg::hash! {
+            content_type: "image/svg+xml",
+            data: barcode::to_svg(&modules, module_width, height),
+        }),
+        "png" => session.respond(eg::hash! {
+            content_type: "image/png",
+            data: base64::engine::general_purpose::STANDARD
+                .encode(barcode::to_png(&modules, module_width, height)),
+        }),
+        f => Err(format!("Unsupported barcode image format: {f}").into()),
+    }
+}
@@ -21,7 +21,7 @@ pub static METHODS: &[StaticMethodDef] = &[
         handler: create_auth_session,
         params: &[StaticParam {
             name: "Options",
-            datatype: ParamDataType::Object,
+            datatype: ParamDataType::ObjectWithKeys(&["user_id", "login_type"]),
             desc: "Hash of Login Options and Values",
         }],
     },
@@ -32,7 +32,7 @@ pub static METHODS: &[StaticMethodDef] = &[
         handler: validate_user,
         params: &[StaticParam {
             name: "Options",
-            datatype: ParamDataType::Object,
+            datatype: ParamDataType::ObjectWithKeys(&["user_id", "login_type"]),
             desc: "Hash of Login Options and Values",
         }],
     },
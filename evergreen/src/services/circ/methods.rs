@@ -1,8 +1,15 @@
+use base64::Engine;
+use eg::common::asset;
 use eg::common::circ;
-use eg::common::circulator::Circulator;
+use eg::common::circulator::{CircOp, Circulator};
+use eg::common::holds;
+use eg::common::labels::{self, LabelConfig};
+use eg::common::template::Renderer;
+use eg::constants as C;
 use eg::editor::Editor;
+use eg::event::EgEvent;
 use eg::osrf::app::ApplicationWorker;
-use eg::osrf::message;
+use eg::osrf::message::{self, Progress};
 use eg::osrf::method::{ParamCount, ParamDataType, StaticMethodDef, StaticParam};
 use eg::osrf::session::ServerSession;
 use eg::EgResult;
@@ -13,6 +20,9 @@ use std::collections::HashMap;
 // Import our local app module
 use crate::app;
 
+/// Number of copies updated per transaction by copy.status.update.batch
+const COPY_UPDATE_BATCH_SIZE: usize = 50;
+
 /// List of method definitions we know at compile time.
 ///
 /// These will form the basis (and possibly all) of our published methods.
@@ -179,6 +189,101 @@ pub static METHODS: &[StaticMethodDef] = &[
             },
         ],
     },
+    StaticMethodDef {
+        name: "hold.queue_stats.retrieve",
+        desc: "Hold queue position and estimated wait",
+        param_count: ParamCount::Exactly(2),
+        handler: hold_queue_stats,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Hold ID",
+                datatype: ParamDataType::Number,
+                desc: "Hold ID to lookup",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "slip.render",
+        desc: "Render a receipt or slip as plain text",
+        param_count: ParamCount::Range(3, 4),
+        handler: render_slip,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Template Name",
+                datatype: ParamDataType::String,
+                desc: "checkout_receipt, hold_slip, or transit_slip",
+            },
+            StaticParam {
+                name: "Context",
+                datatype: ParamDataType::Object,
+                desc: "Values made available to the template",
+            },
+            StaticParam {
+                name: "Template",
+                datatype: ParamDataType::String,
+                desc: "Optional template body which replaces the stock template",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "copy.status.update.batch",
+        desc: "Set the status and/or location of a list of copies.  Progress is reported after each batch",
+        param_count: ParamCount::Exactly(3),
+        handler: batch_update_copies,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Barcodes",
+                datatype: ParamDataType::Array,
+                desc: "Copy barcodes",
+            },
+            StaticParam {
+                name: "Changes",
+                datatype: ParamDataType::Object,
+                desc: "New status and/or location, e.g. {status: 7, location: 1}",
+            },
+        ],
+    },
+    StaticMethodDef {
+        name: "copy.labels.render",
+        desc: "Spine and pocket label data or printable output for a list of copies",
+        param_count: ParamCount::Range(2, 3),
+        handler: render_copy_labels,
+        params: &[
+            StaticParam {
+                name: "Authtoken",
+                datatype: ParamDataType::String,
+                desc: "",
+            },
+            StaticParam {
+                name: "Copy IDs",
+                datatype: ParamDataType::Array,
+                desc: "",
+            },
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "format (data, zpl, or pdf) and org, whose label settings \
+                    apply.  The data format responds with one {copy, barcode, \
+                    spine, pocket} per copy.  Other formats respond with \
+                    {content_type, data}.  PDF data is base64 encoded",
+            },
+        ],
+    },
 ];
 
 pub fn checkout_renew_checkin(
@@ -203,47 +308,63 @@ pub fn checkout_renew_checkin(
         return session.respond(editor.event());
     }
 
-    let mut circulator = Circulator::new(&mut editor, options)?;
-    circulator.is_inspect = method.method().contains(".inspect");
-    circulator.is_override = method.method().contains(".override");
-
-    circulator.begin()?;
+    let is_inspect = method.method().contains(".inspect");
+    let is_override = method.method().contains(".override");
 
-    let result = if method.method().contains("checkout") {
-        circulator.checkout()
+    let op = if method.method().contains("checkout") {
+        CircOp::Checkout
     } else if method.method().contains("checkin") {
-        circulator.checkin()
+        CircOp::Checkin
     } else if method.method().contains("renew") {
-        circulator.renew()
+        CircOp::Renew
     } else {
         return Err(format!("Unhandled method {}", method.method()).into());
     };
 
-    if let Err(err) = result {
-        circulator.rollback()?;
-        // Return the error event to the caller.
-        session.respond(&err.event_or_default())?;
-        return Ok(());
-    }
+    // Responds with the policy data for inspect calls and the
+    // compiled events otherwise.
+    let run = |e: &mut Editor| {
+        let mut circulator = Circulator::new(e, options)?;
+        circulator.is_inspect = is_inspect;
+        circulator.is_override = is_override;
 
-    if circulator.is_inspect() {
-        session.respond(circulator.policy_to_eg_value())?;
-        circulator.rollback()?;
-        return Ok(());
-    }
+        match op {
+            CircOp::Checkout => circulator.checkout()?,
+            CircOp::Checkin => circulator.checkin()?,
+            _ => circulator.renew()?,
+        }
+
+        if circulator.is_inspect() {
+            return Ok((circulator.policy_to_eg_value(), None));
+        }
 
-    let events: Vec<EgValue> = circulator.events().iter().map(|e| e.into()).collect();
+        let events: Vec<EgValue> = circulator.events().iter().map(|e| e.into()).collect();
 
-    // Checkin call completed
-    circulator.commit()?;
+        Ok((EgValue::from(events), Some(circulator.post_commit())))
+    };
+
+    // Inspect calls never change anything.
+    let result = if is_inspect {
+        editor.in_transaction_rollback(run)
+    } else {
+        editor.in_transaction(run)
+    };
+
+    let (response, post_commit) = match result {
+        Ok(r) => r,
+        // Return the error event to the caller.
+        Err(err) => return session.respond(&err.event_or_default()),
+    };
+
+    let Some(post_commit) = post_commit else {
+        return session.respond(response);
+    };
 
     // Send the compiled events to the caller and let them know we're done.
-    session.respond_complete(events)?;
+    session.respond_complete(response)?;
 
     // Work that the caller does not care about.
-    circulator.post_commit_tasks()?;
-
-    Ok(())
+    post_commit.run(&mut editor)
 }
 
 pub fn renewal_chain_summary(
@@ -297,7 +418,7 @@ pub fn prev_renewal_chain_summary(
 
     let query = eg::hash! {
         target_copy: first_circ["target_copy"].int()?,
-        xact_start: {"<": first_circ["xact_start"].as_str().unwrap()}, // xact_tart required
+        xact_start: {"<": first_circ.str_at("xact_start")?}, // xact_tart required
     };
 
     let flesh = eg::hash! {
@@ -321,3 +442,245 @@ pub fn prev_renewal_chain_summary(
 
     session.respond(circ::summarize_circ_chain(&mut editor, prev_circ[0].id()?)?)
 }
+
+pub fn hold_queue_stats(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsCircWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let hold_id = method.param(1).int()?;
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    let hold = match editor.retrieve("ahr", hold_id)? {
+        Some(h) => h,
+        None => return session.respond(editor.event()),
+    };
+
+    // Patrons may always view the queue stats for their own holds.
+    if hold["usr"].int()? != editor.requestor_id()? && !editor.allowed("VIEW_HOLD")? {
+        return session.respond(editor.event());
+    }
+
+    let stats = holds::queue_stats(&mut editor, hold_id)?;
+
+    session.respond(stats.to_eg_value())
+}
+
+pub fn render_slip(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsCircWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let name = method.param(1).str()?;
+    let context = method.param(2);
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    let mut renderer = Renderer::with_org_names(&mut editor)?;
+
+    if let Some(body) = method.param(3).as_str() {
+        renderer.add_template(name, body)?;
+    } else if !renderer.has_template(name) {
+        return Err(format!("No such template: {name}").into());
+    }
+
+    session.respond(renderer.render(name, context)?)
+}
+
+/// Apply a status and/or location change to a list of copies.
+///
+/// Copies are updated in transactions of COPY_UPDATE_BATCH_SIZE.
+/// Responds with one {barcode, copy_id, success, event} result per
+/// barcode once the transaction containing it is committed.  Copies
+/// which cannot be found, which the requestor lacks permission to
+/// edit, or which are checked out are reported and skipped.
+pub fn batch_update_copies(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsCircWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let changes = method.param(2);
+
+    let status = changes["status"].as_i64();
+    let location = changes["location"].as_i64();
+
+    if status.is_none() && location.is_none() {
+        return Err("A new status or location is required".into());
+    }
+
+    if status == Some(C::COPY_STATUS_CHECKED_OUT) {
+        return Err("Copies are only checked out via checkout".into());
+    }
+
+    let barcodes: Vec<&str> = method
+        .param(1)
+        .members()
+        .filter_map(|b| b.as_str())
+        .collect();
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    if let Some(id) = status {
+        if editor.retrieve("ccs", id)?.is_none() {
+            return session.respond(editor.event());
+        }
+    }
+
+    if let Some(id) = location {
+        if editor.retrieve("acpl", id)?.is_none() {
+            return session.respond(editor.event());
+        }
+    }
+
+    let mut done = 0;
+
+    for batch in barcodes.chunks(COPY_UPDATE_BATCH_SIZE) {
+        let results = editor.in_transaction(|e| {
+            batch
+                .iter()
+                .map(|barcode| update_copy_status_location(e, barcode, status, location))
+                .collect::<EgResult<Vec<EgValue>>>()
+        })?;
+
+        for result in results {
+            session.respond(result)?;
+        }
+
+        done += batch.len();
+        session.send_progress(Progress::new(done, Some(barcodes.len())))?;
+    }
+
+    Ok(())
+}
+
+/// Update a single copy within the active transaction.
+///
+/// Returns the result to report to the caller.
+fn update_copy_status_location(
+    editor: &mut Editor,
+    barcode: &str,
+    status: Option<i64>,
+    location: Option<i64>,
+) -> EgResult<EgValue> {
+    let failed = |copy_id: EgValue, event: EgValue| {
+        eg::hash! {
+            barcode: barcode,
+            copy_id: copy_id,
+            success: false,
+            event: event,
+        }
+    };
+
+    let query = eg::hash! {barcode: barcode, deleted: "f"};
+
+    let Some(copy) = editor.search("acp", query)?.pop() else {
+        return Ok(failed(
+            eg::NULL,
+            EgEvent::new("ASSET_COPY_NOT_FOUND").into(),
+        ));
+    };
+
+    let copy_id = copy.id()?;
+
+    if !editor.allowed_at("UPDATE_COPY", copy["circ_lib"].int()?)? {
+        return Ok(failed(copy_id.into(), editor.event()));
+    }
+
+    if status.is_some() && copy["status"].int()? == C::COPY_STATUS_CHECKED_OUT {
+        // Checked out copies leave that status via checkin.
+        return Ok(failed(
+            copy_id.into(),
+            EgEvent::new("OPEN_CIRCULATION_EXISTS").into(),
+        ));
+    }
+
+    let mut changes = EgValue::new_object();
+
+    if let Some(id) = status {
+        changes["status"] = id.into();
+    }
+
+    if let Some(id) = location {
+        changes["location"] = id.into();
+    }
+
+    log::info!("Batch updating copy {copy_id} with {}", changes.dump());
+
+    asset::update_copy(editor, copy, changes)?;
+
+    Ok(eg::hash! {
+        barcode: barcode,
+        copy_id: copy_id,
+        success: true,
+        event: eg::NULL,
+    })
+}
+
+/// Spine and pocket labels for a list of copies.
+///
+/// Label settings are read at the requested org unit, defaulting to
+/// the requestor's workstation org unit.
+pub fn render_copy_labels(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsCircWorker::downcast(worker)?;
+    let authtoken = method.param(0).str()?;
+    let options = method.param(2);
+
+    let copy_ids: Vec<i64> = method
+        .param(1)
+        .members()
+        .filter_map(|id| id.as_int())
+        .collect();
+
+    let mut editor = Editor::with_auth(worker.client(), authtoken);
+
+    if !editor.checkauth()? {
+        return session.respond(editor.event());
+    }
+
+    let org_id = options["org"].as_int().unwrap_or(editor.perm_org());
+    let config = LabelConfig::for_org(&mut editor, org_id)?;
+
+    let labels = labels::label_data(&mut editor, &copy_ids, &config)?;
+
+    match options["format"].as_str().unwrap_or("data") {
+        "data" => {
+            for label in labels {
+                session.respond(label.to_eg_value())?;
+            }
+            Ok(())
+        }
+        "zpl" => session.respond(eg::hash! {
+            content_type: "application/zpl",
+            data: labels::to_zpl(&labels, config.spine_width),
+        }),
+        "pdf" => session.respond(eg::hash! {
+            content_type: "application/pdf",
+            data: base64::engine::general_purpose::STANDARD
+                .encode(labels::to_pdf(&labels, config.spine_width)),
+        }),
+        f => Err(format!("Unsupported label format: {f}").into()),
+    }
+}
@@ -1,11 +1,16 @@
+use eg::db::{DatabaseConnection, DatabaseConnectionBuilder};
 use eg::osrf::app::{Application, ApplicationWorker, ApplicationWorkerFactory};
+use eg::osrf::conf;
 use eg::osrf::method::MethodDef;
+use eg::osrf::sclient::HostSettings;
 use eg::Client;
 use eg::EgError;
 use eg::EgResult;
 use evergreen as eg;
 use std::any::Any;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 
 // Import our local methods module.
@@ -61,6 +66,7 @@ impl Application for RsSearchApplication {
 pub struct RsSearchWorker {
     client: Option<Client>,
     methods: Option<Arc<HashMap<String, MethodDef>>>,
+    database: Option<Rc<RefCell<DatabaseConnection>>>,
 }
 
 impl Default for RsSearchWorker {
@@ -74,6 +80,7 @@ impl RsSearchWorker {
         RsSearchWorker {
             client: None,
             methods: None,
+            database: None,
         }
     }
 
@@ -97,6 +104,63 @@ impl RsSearchWorker {
     pub fn client_mut(&mut self) -> &mut Client {
         self.client.as_mut().unwrap()
     }
+
+    /// Get a reference to our database connection, connecting first
+    /// if needed.
+    pub fn database(&mut self) -> EgResult<&Rc<RefCell<DatabaseConnection>>> {
+        if self.database.is_none() {
+            self.setup_database()?;
+        }
+
+        Ok(self.database.as_ref().unwrap())
+    }
+
+    fn setup_database(&mut self) -> EgResult<()> {
+        // Our builder will apply default values where none exist in
+        // settings or environment variables.
+        let mut builder = DatabaseConnectionBuilder::new();
+
+        let path = format!("apps/{APPNAME}/app_settings/database");
+        let settings = HostSettings::get(&path)?;
+
+        if let Some(user) = settings["user"].as_str() {
+            builder.set_user(user);
+        }
+
+        if let Some(host) = settings["host"].as_str() {
+            builder.set_host(host);
+        }
+
+        if let Some(port) = settings["port"].as_u16() {
+            builder.set_port(port);
+        }
+
+        if let Some(db) = settings["db"].as_str() {
+            builder.set_database(db);
+        } else if let Some(db) = settings["database"].as_str() {
+            builder.set_database(db);
+        }
+
+        if let Some(pw) = settings["pw"].as_str() {
+            builder.set_password(pw);
+        } else if let Some(pw) = settings["password"].as_str() {
+            builder.set_password(pw);
+        }
+
+        builder.set_application(&format!(
+            "{APPNAME}@{}(thread_{})",
+            conf::config().hostname(),
+            eg::util::thread_id()
+        ));
+
+        log::debug!("{APPNAME} connecting to database");
+
+        let mut db = builder.build();
+        db.connect()?;
+        self.database = Some(db.into_shared());
+
+        Ok(())
+    }
 }
 
 impl ApplicationWorker for RsSearchWorker {
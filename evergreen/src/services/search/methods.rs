@@ -1,11 +1,17 @@
 use eg::common::bib;
+use eg::common::search::{self, ClassWeights, SearchQuery};
+use eg::db::DatabaseConnection;
 use eg::osrf::app::ApplicationWorker;
 use eg::osrf::message;
 use eg::osrf::method::{ParamCount, ParamDataType, StaticMethodDef, StaticParam};
 use eg::osrf::session::ServerSession;
 use eg::Editor;
 use eg::EgResult;
+use eg::EgValue;
 use evergreen as eg;
+use postgres::types::ToSql;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 // Import our local app module
 use crate::app;
@@ -58,6 +64,24 @@ pub static METHODS: &[StaticMethodDef] = &[
             },
         ],
     },
+    StaticMethodDef {
+        name: "biblio.multiclass.query",
+        desc: "Catalog Search with Facets",
+        param_count: ParamCount::Exactly(2),
+        handler: multiclass_query,
+        params: &[
+            StaticParam {
+                name: "Options",
+                datatype: ParamDataType::Object,
+                desc: "Options Hash: org_unit, limit, offset, facet_limit",
+            },
+            StaticParam {
+                name: "Query",
+                datatype: ParamDataType::String,
+                desc: "Search query, e.g. 'potter au:rowling'",
+            },
+        ],
+    },
 ];
 
 pub fn catalog_record_summary(
@@ -88,3 +112,87 @@ pub fn catalog_record_summary(
 
     Ok(())
 }
+
+/// Run a catalog search, responding with the matching record IDs and
+/// relevance, the total match count, and facet counts, which are keyed
+/// by config.metabib_field ID then facet value.
+pub fn multiclass_query(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsSearchWorker::downcast(worker)?;
+
+    let options = method.param(0);
+    let query = SearchQuery::parse(method.param(1).str()?)?;
+
+    let org_id = options["org_unit"].as_int();
+    let limit = options["limit"].as_int().unwrap_or(10);
+    let offset = options["offset"].as_int().unwrap_or(0);
+    let facet_limit = options["facet_limit"]
+        .as_int()
+        .unwrap_or(search::DEFAULT_FACET_LIMIT);
+
+    let weights = match org_id {
+        Some(id) => ClassWeights::for_org(&mut Editor::new(worker.client()), id)?,
+        None => ClassWeights::default(),
+    };
+
+    let db = worker.database()?.clone();
+
+    let mut count = 0;
+    let mut ids = EgValue::new_array();
+
+    let (sql, params) = query.results_sql(&weights, org_id, limit, offset);
+
+    for row in run_query(&db, &sql, &params)? {
+        count = row.get::<&str, i64>("count");
+
+        // NULL when the page is past the end of the results.
+        if let Some(id) = row.get::<&str, Option<i64>>("id") {
+            ids.push(EgValue::from(vec![
+                EgValue::from(id),
+                EgValue::from(row.get::<&str, f64>("rel")),
+            ]))?;
+        }
+    }
+
+    let mut facets = EgValue::new_object();
+
+    if facet_limit > 0 {
+        let (sql, params) = query.facets_sql(&weights, org_id, facet_limit);
+
+        for row in run_query(&db, &sql, &params)? {
+            let field = row.get::<&str, i32>("field").to_string();
+            let value = row.get::<&str, String>("value");
+
+            if !facets.has_key(&field) {
+                facets[&field] = EgValue::new_object();
+            }
+
+            facets[&field][&value] = EgValue::from(row.get::<&str, i64>("count"));
+        }
+    }
+
+    session.respond(eg::hash! {
+        "count": count,
+        "ids": ids,
+        "facets": facets,
+    })
+}
+
+fn run_query(
+    db: &Rc<RefCell<DatabaseConnection>>,
+    sql: &str,
+    qparams: &[String],
+) -> EgResult<Vec<postgres::Row>> {
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+    for p in qparams.iter() {
+        params.push(p);
+    }
+
+    db.borrow_mut().client().query(sql, &params).map_err(|e| {
+        log::error!("DB Error: {e} query={sql} param={params:?}");
+        eg::db::query_error(&e, &format!("DB query failed: {e}"))
+    })
+}
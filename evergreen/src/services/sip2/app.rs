@@ -2,6 +2,7 @@ use eg::osrf::app::{Application, ApplicationWorker, ApplicationWorkerFactory};
 use eg::osrf::cache::Cache;
 use eg::osrf::method::MethodDef;
 use eg::Client;
+use eg::Editor;
 use eg::EgError;
 use eg::EgResult;
 use evergreen as eg;
@@ -11,6 +12,8 @@ use std::sync::Arc;
 
 // Import our local methods module.
 use crate::methods;
+use crate::session::Session;
+use crate::shared;
 
 const APPNAME: &str = "open-ils.rs-sip2";
 
@@ -35,9 +38,16 @@ impl Application for Sip2Application {
     }
 
     /// Load the IDL and perform any other needed global startup work.
-    fn init(&mut self, _client: Client) -> EgResult<()> {
+    ///
+    /// Startup fails if any SIP setting group has unknown settings
+    /// or references a missing profile.
+    fn init(&mut self, client: Client) -> EgResult<()> {
         eg::init::load_idl()?;
-        Ok(())
+
+        let mut editor = Editor::new(&client);
+
+        Session::validate_setting_groups(&mut editor)
+            .map_err(|e| format!("Invalid SIP settings: {e}").into())
     }
 
     fn register_methods(&self, _client: Client) -> EgResult<Vec<MethodDef>> {
@@ -110,6 +120,12 @@ impl ApplicationWorker for Sip2Worker {
         methods: Arc<HashMap<String, MethodDef>>,
     ) -> EgResult<()> {
         Cache::init_cache("global")?;
+
+        // Failing to preload only means slower first requests.
+        if let Err(e) = shared::warm_up(&client) {
+            log::warn!("SIP warm-up failed: {e}");
+        }
+
         self.client = Some(client);
         self.methods = Some(methods);
         Ok(())
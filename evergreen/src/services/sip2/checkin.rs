@@ -1,13 +1,18 @@
 use super::item;
-use super::session::Session;
+use super::session::{Session, SortBinItem};
 use chrono::NaiveDateTime;
-use eg::common::circulator::Circulator;
+use eg::common::circulator::{CircOp, CircResult};
+use eg::common::trigger;
 use eg::constants as C;
 use eg::result::EgResult;
 use eg::EgValue;
 use evergreen as eg;
+use sip2::spec::CheckinAlert;
 use std::collections::HashMap;
 
+/// A/T hook for holds which are ready for pickup.
+const HOLD_READY_HOOK: &str = "hold.available";
+
 pub struct CheckinResult {
     ok: bool,
     permanent_loc: String,
@@ -79,6 +84,9 @@ impl Session {
         )
         .unwrap();
 
+        if let Some(bin) = self.sort_bin(&item, &result) {
+            resp.add_field(self.config().sort_bin_field(), &bin);
+        }
         if let Some(ref bc) = result.patron_barcode {
             resp.add_field("AA", bc);
         }
@@ -94,8 +102,37 @@ impl Session {
         if let Some(ref n) = result.hold_patron_name {
             resp.add_field("DA", n);
         }
-        if blocked_on_co {
-            resp.add_field("AF", "Item Is Currently Checked Out");
+        let outcome = if blocked_on_co {
+            "checkin.checked_out"
+        } else if result.ok {
+            "checkin.success"
+        } else {
+            "checkin.failure"
+        };
+
+        let template = self.screen_message(
+            &[outcome],
+            &[
+                ("title", &item.title),
+                ("barcode", barcode),
+                (
+                    "destination",
+                    result.destination_loc.as_deref().unwrap_or(""),
+                ),
+            ],
+        );
+
+        let default_screen = if blocked_on_co {
+            Some("Item Is Currently Checked Out")
+        } else {
+            None
+        };
+
+        if let Some(t) = template {
+            resp.maybe_add_field("AF", t.screen().or(default_screen));
+            resp.maybe_add_field("AG", t.print());
+        } else {
+            resp.maybe_add_field("AF", default_screen);
         }
 
         Ok(resp)
@@ -130,6 +167,34 @@ impl Session {
         })
     }
 
+    /// Sort bin for a checked in item, if any sort bin rules apply.
+    fn sort_bin(&self, item: &item::Item, result: &CheckinResult) -> Option<String> {
+        let bins = self.config().sort_bins();
+
+        if bins.is_empty() || !result.ok {
+            return None;
+        }
+
+        let sort_item = SortBinItem {
+            hold: matches!(
+                result.alert_type,
+                Some(CheckinAlert::LocalHold) | Some(CheckinAlert::RemoteHold)
+            ),
+            transit: matches!(
+                result.alert_type,
+                Some(CheckinAlert::Transit) | Some(CheckinAlert::RemoteHold)
+            ),
+            destination: result.destination_loc.as_deref(),
+            location: &item.collection_code,
+        };
+
+        let bin = bins.find(&sort_item)?;
+
+        log::info!("{self} Item {} sorted to bin {bin}", item.barcode);
+
+        Some(bin.to_string())
+    }
+
     fn return_checkin_item_not_found(&self, barcode: &str) -> sip2::Message {
         sip2::Message::from_values(
             "10",
@@ -157,187 +222,49 @@ impl Session {
         cancel: bool,
         ovride: bool,
     ) -> EgResult<CheckinResult> {
-        // There is no seed data for use_native_checkin, so this will
-        // always be false unless locally modified.
-        if self.config().setting_is_true("use_native_checkin") {
-            self.checkin_native(item, checkin_loc_op, return_date, cancel, ovride)
-        } else {
-            self.checkin_api(item, checkin_loc_op, return_date, cancel, ovride)
-        }
-    }
-
-    /// Checkin variant that calls the traditional open-ils.circ APIs.
-    fn checkin_api(
-        &mut self,
-        item: &item::Item,
-        checkin_loc_op: Option<&str>,
-        return_date: &str,
-        cancel: bool,
-        ovride: bool,
-    ) -> EgResult<CheckinResult> {
-        let mut args = eg::hash! {
-            "copy_barcode": item.barcode.as_str(),
-            "hold_as_transit": self.config().setting_is_true("checkin_holds_as_transits"),
-        };
-
-        if cancel {
-            args["revert_hold_fulfillment"] = EgValue::from(cancel);
-        }
-
-        if return_date.trim().len() == 18 {
-            let fmt = sip2::spec::SIP_DATE_FORMAT;
-
-            // Use NaiveDate since SIP dates don't typically include a
-            // time zone value.
-            if let Ok(sip_date) = NaiveDateTime::parse_from_str(return_date, fmt) {
-                let iso_date = sip_date.format("%Y-%m-%d").to_string();
-                log::info!("{self} Checking in with backdate: {iso_date}");
-
-                args["backdate"] = EgValue::from(iso_date);
-            } else {
-                log::warn!("{self} Invalid checkin return date: {return_date}");
-            }
-        }
+        // The copy status is about to change.
+        self.uncache_lookup("acp", &item.barcode)?;
 
-        if let Some(sn) = checkin_loc_op {
-            if let Some(org) = self.org_from_sn(sn)? {
-                args["circ_lib"] = org["id"].clone();
-            }
-        }
-
-        if !args.has_key("circ_lib") {
-            args["circ_lib"] = EgValue::from(self.editor().perm_org());
-        }
-
-        let method = match ovride {
-            true => "open-ils.circ.checkin.override",
-            false => "open-ils.circ.checkin",
-        };
-
-        log::info!("{self} checking in items with args: {}", args.dump());
-
-        let params = vec![EgValue::from(self.editor().authtoken().unwrap()), args];
-
-        let mut resp =
-            match self
-                .editor()
-                .client_mut()
-                .send_recv_one("open-ils.circ", method, params)?
-            {
-                Some(r) => r,
-                None => Err(format!("API call {method} failed to return a response"))?,
-            };
-
-        log::info!(
-            "{self} Checkin of {} returned: {}",
-            item.barcode,
-            resp.dump()
-        );
+        let options = self.checkin_options(item, checkin_loc_op, return_date, cancel)?;
 
-        let evt_json = if resp.is_array() {
-            resp[0].take()
-        } else {
-            resp
-        };
+        // There is no seed data for use_native_checkin, so this will
+        // always be the API unless locally modified.
+        let backend = self.circ_backend("use_native_checkin");
 
-        let evt = eg::event::EgEvent::parse(&evt_json)
-            .ok_or(format!("API call {method} failed to return an event"))?;
+        let circ_result = self.run_circ_action(
+            backend,
+            CircOp::Checkin,
+            "open-ils.circ.checkin",
+            options,
+            ovride,
+        )?;
 
         let can_override = self
             .config()
-            .setting_is_true(&format!("checkin.override.{}", evt.textcode()));
+            .setting_is_true(&format!("checkin.override.{}", circ_result.textcode()));
 
         if !ovride && can_override {
             return self.checkin(item, checkin_loc_op, return_date, cancel, true);
         }
 
-        let mut permanent_loc = item.permanent_loc.to_string(); // item.circ_lib
-        let mut destination_loc = None;
-        if let Some(org_id) = evt.org() {
-            if let Some(org) = self.org_from_id(*org_id)? {
-                if let Some(sn) = org["shortname"].as_str() {
-                    destination_loc = Some(sn.to_string());
-                }
-            }
-        }
-
-        let copy = &evt.payload()["copy"];
-        if copy.is_object() {
-            // If the API returned a copy, collect data about the copy
-            // for our response.  It could mean the copy's circ lib
-            // changed because it floats.
-
-            log::debug!("{self} Checkin of {} returned a copy object", item.barcode);
-
-            if let Ok(circ_lib) = copy["circ_lib"].int() {
-                if circ_lib != item.circ_lib {
-                    if let Some(org) = self.org_from_id(circ_lib)? {
-                        let loc = org["shortname"].as_str().unwrap();
-                        permanent_loc = loc.to_string();
-                    }
-                }
-            }
-        }
-
-        let mut result = CheckinResult {
-            ok: false,
-            permanent_loc,
-            destination_loc,
-            patron_barcode: None,
-            alert_type: None,
-            hold_patron_name: None,
-            hold_patron_barcode: None,
-        };
-
-        let circ = &evt.payload()["circ"];
-        if circ.is_object() {
-            log::debug!(
-                "{self} Checkin of {} returned a circulation object",
-                item.barcode
-            );
-
-            if let Some(user) = self.get_user_and_card(circ["usr"].int()?)? {
-                if let Some(bc) = user["card"]["barcode"].as_str() {
-                    result.patron_barcode = Some(bc.to_string());
-                }
-            }
-        }
-
-        self.handle_checkin_hold(&evt, &mut result)?;
-
-        if evt.textcode().eq("SUCCESS") || evt.textcode().eq("NO_CHANGE") {
-            result.ok = true;
-        } else if evt.textcode().eq("ROUTE_ITEM") {
-            result.ok = true;
-            if result.alert_type.is_none() {
-                result.alert_type = Some(sip2::spec::CheckinAlert::Transit);
-            }
-        } else {
-            result.ok = false;
-            if result.alert_type.is_none() {
-                result.alert_type = Some(sip2::spec::CheckinAlert::Unknown);
-            }
-        }
-
-        Ok(result)
+        self.compile_checkin_result(item, &circ_result)
     }
 
-    /// Checkoin that runs within the current thread as a direct
-    /// Rust call.
-    fn checkin_native(
+    /// Checkin options shared by all circulation backends.
+    fn checkin_options(
         &mut self,
         item: &item::Item,
         checkin_loc_op: Option<&str>,
         return_date: &str,
         cancel: bool,
-        ovride: bool,
-    ) -> EgResult<CheckinResult> {
+    ) -> EgResult<HashMap<String, EgValue>> {
         let mut options: HashMap<String, EgValue> = HashMap::new();
         options.insert("copy_barcode".to_string(), item.barcode.as_str().into());
 
-        if self.config().setting_is_true("checkin_holds_as_transits") {
-            options.insert("hold_as_transit".to_string(), EgValue::from(true));
-        }
+        options.insert(
+            "hold_as_transit".to_string(),
+            EgValue::from(self.config().setting_is_true("checkin_holds_as_transits")),
+        );
 
         if cancel {
             options.insert("revert_hold_fulfillment".to_string(), EgValue::from(cancel));
@@ -362,7 +289,7 @@ impl Session {
             if let Some(org) = self.org_from_sn(sn)? {
                 options.insert("circ_lib".to_string(), org["id"].clone());
             } else {
-                log::warn!("Unknown org unit provided for current location: {sn}");
+                log::warn!("{self} Unknown org unit provided for current location: {sn}");
             }
         }
 
@@ -373,42 +300,16 @@ impl Session {
             );
         }
 
-        log::info!("{self} checkin with params: {:?}", options);
-
-        let mut editor = self.editor().clone();
-
-        let mut circulator = Circulator::new(&mut editor, options)?;
-        circulator.begin()?;
-        circulator.is_override = ovride;
-
-        // Collect needed data then kickoff the checkin process.
-        let result = circulator.checkin();
-
-        log::info!("{self} Checkin of {} returned: {result:?}", item.barcode);
-
-        let err_bind;
-        let evt = match result {
-            Ok(()) => {
-                circulator.commit()?;
-                circulator
-                    .events()
-                    .first()
-                    .ok_or_else(|| "API call failed to return an event".to_string())?
-            }
-            Err(err) => {
-                circulator.rollback()?;
-                err_bind = Some(err.event_or_default());
-                err_bind.as_ref().unwrap()
-            }
-        };
-
-        let can_override = self
-            .config()
-            .setting_is_true(&format!("checkin.override.{}", evt.textcode()));
+        Ok(options)
+    }
 
-        if !ovride && can_override {
-            return self.checkin(item, checkin_loc_op, return_date, cancel, true);
-        }
+    /// Translate the outcome of a checkin into a CheckinResult.
+    fn compile_checkin_result(
+        &mut self,
+        item: &item::Item,
+        circ_result: &CircResult,
+    ) -> EgResult<CheckinResult> {
+        let evt = &circ_result.event;
 
         let mut permanent_loc = item.permanent_loc.to_string(); // item.circ_lib
 
@@ -421,9 +322,8 @@ impl Session {
             }
         }
 
-        let copy = &evt.payload()["copy"];
-        if copy.is_object() {
-            // If the API returned a copy, collect data about the copy
+        if let Some(copy) = circ_result.copy.as_ref() {
+            // If the checkin returned a copy, collect data about the copy
             // for our response.  It could mean the copy's circ lib
             // changed because it floats.
 
@@ -432,7 +332,7 @@ impl Session {
             if let Ok(circ_lib) = copy["circ_lib"].int() {
                 if circ_lib != item.circ_lib {
                     if let Some(org) = self.org_from_id(circ_lib)? {
-                        let loc = org["shortname"].as_str().unwrap();
+                        let loc = org.str_at("shortname")?;
                         permanent_loc = loc.to_string();
                     }
                 }
@@ -449,8 +349,10 @@ impl Session {
             hold_patron_barcode: None,
         };
 
-        let circ = &evt.payload()["circ"];
-        if circ.is_object() {
+        // Sorters and similar have no use for patron details.
+        let skip_patrons = self.config().setting_is_true("checkin_skip_patron_lookup");
+
+        if let Some(circ) = circ_result.circ.as_ref().filter(|_| !skip_patrons) {
             log::debug!(
                 "{self} Checkin of {} returned a circulation object",
                 item.barcode
@@ -463,7 +365,7 @@ impl Session {
             }
         }
 
-        self.handle_checkin_hold(evt, &mut result)?;
+        self.handle_checkin_hold(circ_result, &mut result)?;
 
         if evt.textcode().eq("SUCCESS") || evt.textcode().eq("NO_CHANGE") {
             result.ok = true;
@@ -486,23 +388,25 @@ impl Session {
     /// related info.
     fn handle_checkin_hold(
         &mut self,
-        evt: &eg::event::EgEvent,
+        circ_result: &CircResult,
         result: &mut CheckinResult,
     ) -> EgResult<()> {
-        let rh = &evt.payload()["remote_hold"];
-        let lh = &evt.payload()["hold"];
-
-        let hold = if rh.is_object() {
-            rh
-        } else if lh.is_object() {
-            lh
-        } else {
-            return Ok(());
+        let hold = match circ_result.hold.as_ref() {
+            Some(h) => h,
+            None => return Ok(()),
         };
 
         log::debug!("{self} Checkin returned a hold object id={}", hold["id"]);
 
-        if let Some(user) = self.get_user_and_card(hold["usr"].int()?)? {
+        let skip_patrons = self.config().setting_is_true("checkin_skip_patron_lookup");
+
+        let user = if skip_patrons {
+            None
+        } else {
+            self.get_user_and_card(hold["usr"].int()?)?
+        };
+
+        if let Some(user) = user {
             result.hold_patron_name = Some(self.format_user_name(&user));
             if let Some(bc) = user["card"]["barcode"].as_str() {
                 result.hold_patron_barcode = Some(bc.to_string());
@@ -514,7 +418,7 @@ impl Session {
 
         // hold pickup lib may or may not be fleshed here.
         if pickup_lib.is_object() {
-            result.destination_loc = Some(pickup_lib["shortname"].as_str().unwrap().to_string());
+            result.destination_loc = Some(pickup_lib.str_at("shortname")?.to_string());
             pickup_lib_id = pickup_lib.id()?;
         } else {
             pickup_lib_id = pickup_lib.int()?;
@@ -531,6 +435,73 @@ impl Session {
             result.alert_type = Some(sip2::spec::CheckinAlert::RemoteHold);
         }
 
+        // Holds headed elsewhere are not ready until their transit
+        // is received.
+        let on_shelf = hold["current_shelf_lib"].as_int() == Some(pickup_lib_id);
+
+        if on_shelf && self.config().setting_is_true("checkin_notify_hold_ready") {
+            let hold_id = hold.id()?;
+
+            // The checkin succeeded regardless.
+            if let Err(e) = self.notify_hold_ready(hold_id, pickup_lib_id) {
+                log::error!("{self} cannot create hold ready events for hold {hold_id}: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create hold pickup notification events for a hold which was
+    /// just placed on the hold shelf.
+    ///
+    /// Holds which already have hold ready events, e.g. because the
+    /// item was checked in again while on the shelf, are skipped so
+    /// the patron is not notified twice.
+    fn notify_hold_ready(&mut self, hold_id: i64, pickup_lib: i64) -> EgResult<()> {
+        let granularity = self
+            .config()
+            .hold_ready_granularity()
+            .map(|g| g.to_string());
+
+        let created = self.editor().in_transaction(|e| {
+            let hold = e
+                .retrieve("ahr", hold_id)?
+                .ok_or_else(|| format!("No such hold: {hold_id}"))?;
+
+            let query = eg::hash! {
+                "select": {"atev": ["id"]},
+                "from": {"atev": "atevdef"},
+                "where": {
+                    "target": hold_id,
+                    "+atevdef": {"hook": HOLD_READY_HOOK},
+                },
+                "limit": 1,
+            };
+
+            if !e.json_query(query)?.is_empty() {
+                return Ok(false);
+            }
+
+            trigger::create_events_for_object(
+                e,
+                HOLD_READY_HOOK,
+                &hold,
+                pickup_lib,
+                granularity.as_deref(),
+                None,
+                false,
+            )?;
+
+            Ok(true)
+        })?;
+
+        if !created {
+            log::info!("{self} hold {hold_id} already has hold ready events");
+            return Ok(());
+        }
+
+        log::info!("ACT:{self} created hold ready events for hold {hold_id}");
+
         Ok(())
     }
 }
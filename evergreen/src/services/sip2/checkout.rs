@@ -1,8 +1,9 @@
-use super::session::DEFAULT_DUE_DATE_FORMAT;
+use crate::circ::CircBackend;
 use crate::item::Item;
 use crate::patron::Patron;
 use crate::session::Session;
-use eg::common::circulator::Circulator;
+use eg::common::circulator::CircOp;
+use eg::constants as C;
 use eg::date;
 use eg::result::EgResult;
 use eg::EgValue;
@@ -10,9 +11,11 @@ use evergreen as eg;
 use std::collections::HashMap;
 
 const RENEW_METHOD: &str = "open-ils.circ.renew";
-const RENEW_OVERRIDE_METHOD: &str = "open-ils.circ.renew.override";
 const CHECKOUT_METHOD: &str = "open-ils.circ.checkout.full";
-const CHECKOUT_OVERRIDE_METHOD: &str = "open-ils.circ.checkout.full.override";
+
+/// Title applied to pre-cataloged items when neither the SIP client
+/// nor the account settings provide one.
+const DEFAULT_PRECAT_TITLE: &str = "Pre-cataloged item";
 
 pub struct CheckoutResult {
     /// Presence of a circ_id implies success.
@@ -21,6 +24,14 @@ pub struct CheckoutResult {
     renewal_remaining: i64,
     screen_msg: Option<String>,
     was_renewal: bool,
+    /// Textcode of the event returned by a failed checkout.
+    textcode: Option<String>,
+    /// Deposit or rental fee billed as part of the checkout.
+    fee_billed: Option<f64>,
+    /// True if the item was checked out as a new pre-cataloged item.
+    was_precat: bool,
+    /// True if the checkout fulfilled one of the patron's holds.
+    hold_fulfilled: bool,
 }
 
 impl Default for CheckoutResult {
@@ -37,67 +48,110 @@ impl CheckoutResult {
             renewal_remaining: 0,
             screen_msg: None,
             was_renewal: false,
+            textcode: None,
+            fee_billed: None,
+            was_precat: false,
+            hold_fulfilled: false,
         }
     }
 }
 
+/// A single checkout or renewal attempt.
+struct CheckoutRequest<'a> {
+    item_barcode: &'a str,
+    patron_barcode: &'a str,
+    fee_ack: bool,
+    is_renewal: bool,
+    ovride: bool,
+    /// Additional circulation options, e.g. for pre-cataloged items.
+    extra_options: HashMap<String, EgValue>,
+}
+
+impl CheckoutResult {
+    fn is_fee_event(textcode: &str) -> bool {
+        textcode == "ITEM_DEPOSIT_FEE_REQUIRED" || textcode == "ITEM_RENTAL_FEE_REQUIRED"
+    }
+}
+
 impl Session {
+    /// Renew every open circulation for a patron.
+    ///
+    /// Failures are per-item: each item lands in either the renewed
+    /// (BM) or unrenewed (BN) list of the response.
     pub fn handle_renew_all(&mut self, sip_msg: &sip2::Message) -> EgResult<sip2::Message> {
         let patron_barcode = sip_msg.get_field_value("AA").unwrap_or("");
         let password_op = sip_msg.get_field_value("AD"); // optional
-        let fee_ack_op = sip_msg.get_field_value("BO");
+
+        // Fee acknowledged
+        let fee_ack = sip_msg.get_field_value("BO") == Some("Y");
 
         let patron = match self.get_patron_details(patron_barcode, password_op, None)? {
-            Some(c) => c,
-            None => {
-                // Stub response
-                let response = sip2::Message::from_values(
-                    "66",
-                    &[
-                        "0",
-                        &sip2::util::sip_count4(0), // renewed count
-                        &sip2::util::sip_count4(0), // unrenewed count
-                        &sip2::util::sip_date_now(),
-                    ],
-                    &[("AA", patron_barcode), ("AO", self.config().institution())],
-                )
-                .unwrap();
-
-                return Ok(response);
-            }
+            Some(p) => p,
+            None => return Ok(self.renew_all_failed(patron_barcode, None)),
+        };
+
+        if password_op.is_some() && !patron.password_verified {
+            log::info!("{self} renew all refused; invalid password for {patron_barcode}");
+            return Ok(self.renew_all_failed(patron_barcode, Some("Invalid patron password")));
+        }
+
+        let flesh = eg::hash! {
+            "flesh": 1,
+            "flesh_fields": {"circ": ["target_copy"]},
         };
 
         let mut items_renewed = Vec::new();
         let mut items_unrenewed = Vec::new();
 
-        for item_id in patron
+        // Both lists contain circulation IDs.
+        for circ_id in patron
             .items_overdue_ids
             .iter()
             .chain(patron.items_out_ids.iter())
         {
-            let item = self
+            let circ = match self
                 .editor()
-                .retrieve("acp", *item_id)?
-                .ok_or_else(|| self.editor().die_event())?;
+                .retrieve_with_ops("circ", *circ_id, flesh.clone())?
+            {
+                Some(c) => c,
+                None => {
+                    log::warn!("{self} renew all skipping missing circulation {circ_id}");
+                    continue;
+                }
+            };
 
-            let item_barcode = item["barcode"].str()?;
+            let item_barcode = circ["target_copy"]["barcode"].string()?;
 
-            let result = self.checkout(
-                item_barcode,
+            let renewed = match self.checkout(
+                &item_barcode,
                 patron_barcode,
-                fee_ack_op.is_some(),
+                fee_ack,
                 true, // is_explicit_renewal
                 self.config().setting_is_true("checkout_override_all"),
-            )?;
+            ) {
+                // Presence of circ id indicates success
+                Ok(result) => result.circ_id.is_some(),
+                Err(e) => {
+                    // Keep going so one bad item does not prevent
+                    // renewing the rest.
+                    log::error!("{self} renew all failed to renew {item_barcode}: {e}");
+                    false
+                }
+            };
 
-            // Presence of circ id indicates success
-            if result.circ_id.is_some() {
-                items_renewed.push(item_barcode.to_string());
+            if renewed {
+                items_renewed.push(item_barcode);
             } else {
-                items_unrenewed.push(item_barcode.to_string());
+                items_unrenewed.push(item_barcode);
             }
         }
 
+        log::info!(
+            "ACT:{self} renew all for {patron_barcode} renewed {} of {} items",
+            items_renewed.len(),
+            items_renewed.len() + items_unrenewed.len()
+        );
+
         let mut response = sip2::Message::from_values(
             "66",
             &[
@@ -121,6 +175,25 @@ impl Session {
         Ok(response)
     }
 
+    /// Renew All response for a patron whose items we cannot renew.
+    fn renew_all_failed(&self, patron_barcode: &str, screen_msg: Option<&str>) -> sip2::Message {
+        let mut response = sip2::Message::from_values(
+            "66",
+            &[
+                "0",
+                &sip2::util::sip_count4(0), // renewed count
+                &sip2::util::sip_count4(0), // unrenewed count
+                &sip2::util::sip_date_now(),
+            ],
+            &[("AA", patron_barcode), ("AO", self.config().institution())],
+        )
+        .unwrap();
+
+        response.maybe_add_field("AF", screen_msg);
+
+        response
+    }
+
     fn checkout_renew_common(
         &mut self,
         msg: &sip2::Message,
@@ -144,16 +217,21 @@ impl Session {
 
         log::info!("{self} Checking out item {item_barcode} to patron {patron_barcode}");
 
-        let fee_ack_op = msg.get_field_value("BO");
+        // Fee acknowledged
+        let fee_ack = msg.get_field_value("BO") == Some("Y");
 
         let item = match self.get_item_details(item_barcode)? {
             Some(c) => c,
             None => {
+                if !is_explicit_renewal && self.config().setting_is_true("checkout_allow_precat") {
+                    return self.checkout_precat(msg, item_barcode, patron_barcode, fee_ack);
+                }
+
                 return Ok(self.checkout_item_not_found(
                     item_barcode,
                     patron_barcode,
                     is_explicit_renewal,
-                ))
+                ));
             }
         };
 
@@ -168,13 +246,25 @@ impl Session {
             }
         };
 
+        if !is_explicit_renewal && self.item_held_for_other_patron(&item, &patron) {
+            // Never capture another patron's hold from a SIP client,
+            // regardless of any override settings.
+            log::info!("{self} item {item_barcode} is on the holds shelf for another patron");
+
+            let mut result = CheckoutResult::new();
+            result.textcode = Some("ITEM_ON_HOLDS_SHELF".to_string());
+            result.screen_msg = Some("This item is on hold for another patron".to_string());
+
+            return self.compile_checkout_response(&item, &patron, &result, false);
+        }
+
         let same_patron = item.circ_patron_id == Some(patron.id);
         let renew_ok = msg.fixed_fields()[0].value().eq("Y");
 
         let result = self.checkout(
             item_barcode,
             patron_barcode,
-            fee_ack_op.is_some(),
+            fee_ack,
             is_explicit_renewal || (renew_ok && same_patron), // is_renewal
             self.config().setting_is_true("checkout_override_all"),
         )?;
@@ -182,6 +272,111 @@ impl Session {
         self.compile_checkout_response(&item, &patron, &result, is_explicit_renewal)
     }
 
+    /// True if the item sits on the holds shelf for a patron other
+    /// than the one checking it out.
+    fn item_held_for_other_patron(&self, item: &Item, patron: &Patron) -> bool {
+        item.copy_status == C::COPY_STATUS_ON_HOLDS_SHELF
+            && item.hold_patron_id.is_some()
+            && item.hold_patron_id != Some(patron.id)
+    }
+
+    /// Checkout an item which is not in the catalog by creating a
+    /// pre-cataloged copy for it.
+    ///
+    /// The title comes from the request (AJ), then the
+    /// "precat_dummy_title" setting.  SIP has no author field, so the
+    /// author comes from the "precat_dummy_author" setting.
+    fn checkout_precat(
+        &mut self,
+        msg: &sip2::Message,
+        item_barcode: &str,
+        patron_barcode: &str,
+        fee_ack: bool,
+    ) -> EgResult<sip2::Message> {
+        let password_op = msg.get_field_value("AD"); // optional
+
+        let patron = match self.get_patron_details(patron_barcode, password_op, None)? {
+            Some(p) => p,
+            None => return Ok(self.checkout_item_not_found(item_barcode, patron_barcode, false)),
+        };
+
+        if password_op.is_some() && !patron.password_verified {
+            log::info!("{self} pre-cat checkout refused; invalid password for {patron_barcode}");
+            let mut resp = self.checkout_item_not_found(item_barcode, patron_barcode, false);
+            resp.add_field("AF", "Invalid patron password");
+            return Ok(resp);
+        }
+
+        let settings = self.config().settings();
+
+        let title = msg
+            .get_field_value("AJ")
+            .filter(|t| !t.is_empty())
+            .or(settings.get("precat_dummy_title").and_then(|v| v.as_str()))
+            .unwrap_or(DEFAULT_PRECAT_TITLE)
+            .to_string();
+
+        let author = settings
+            .get("precat_dummy_author")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        log::info!("{self} checking out pre-cat item {item_barcode} with title '{title}'");
+
+        let mut options = HashMap::new();
+        options.insert("is_precat".to_string(), EgValue::from(true));
+        options.insert("dummy_title".to_string(), EgValue::from(title.as_str()));
+        options.insert("dummy_author".to_string(), EgValue::from(author));
+
+        // Pre-cat copies are created by the Circulator, so this always
+        // uses the native checkout.
+        let request = CheckoutRequest {
+            item_barcode,
+            patron_barcode,
+            fee_ack,
+            is_renewal: false,
+            ovride: self.config().setting_is_true("checkout_override_all"),
+            extra_options: options,
+        };
+
+        let mut result = self.checkout_with_backend(CircBackend::Native, request)?;
+
+        if result.circ_id.is_none() {
+            // No copy was created, so report the failure without one.
+            let textcode = result.textcode.as_deref().unwrap_or("");
+            log::info!("{self} pre-cat checkout of {item_barcode} failed: {textcode}");
+
+            let mut resp = self.checkout_item_not_found(item_barcode, patron_barcode, false);
+            resp.add_field("AJ", &title);
+
+            let template = self.screen_message(
+                &[&format!("checkout.{textcode}"), "checkout.failure"],
+                &[
+                    ("title", &title),
+                    ("barcode", item_barcode),
+                    ("patron_name", &patron.name),
+                ],
+            );
+
+            let screen = template.as_ref().and_then(|t| t.screen());
+            resp.maybe_add_field("AF", screen.or(result.screen_msg.as_deref()));
+            resp.maybe_add_field("AG", template.as_ref().and_then(|t| t.print()));
+
+            return Ok(resp);
+        }
+
+        // The copy exists now that the checkout succeeded.
+        let item = match self.get_item_details(item_barcode)? {
+            Some(i) => i,
+            None => return Ok(self.checkout_item_not_found(item_barcode, patron_barcode, false)),
+        };
+
+        result.was_precat = true;
+
+        self.compile_checkout_response(&item, &patron, &result, false)
+    }
+
     pub fn handle_checkout(&mut self, msg: &sip2::Message) -> EgResult<sip2::Message> {
         self.checkout_renew_common(msg, false)
     }
@@ -214,22 +409,76 @@ impl Session {
                 ("AB", &item.barcode),
                 ("AJ", &item.title),
                 ("AO", self.config().institution()),
-                ("BT", (item.fee_type)),
+                ("BT", item.fee_type.into()),
                 //("CI", "N"), // security inhibit / not supported
                 ("CK", &item.media_type),
             ],
         )
         .unwrap();
 
-        resp.maybe_add_field("AF", result.screen_msg.as_deref());
+        let action = if result.was_renewal {
+            "renew"
+        } else {
+            "checkout"
+        };
+
+        let specific_key;
+        let generic_key;
+
+        if result.circ_id.is_some() {
+            specific_key = if result.was_precat {
+                format!("{action}.precat")
+            } else if result.hold_fulfilled {
+                format!("{action}.hold_fulfilled")
+            } else {
+                format!("{action}.success")
+            };
+            generic_key = format!("{action}.success");
+        } else {
+            specific_key = format!("{action}.{}", result.textcode.as_deref().unwrap_or(""));
+            generic_key = format!("{action}.failure");
+        }
+
+        let template = self.screen_message(
+            &[&specific_key, &generic_key],
+            &[
+                ("title", &item.title),
+                ("barcode", &item.barcode),
+                ("due_date", result.due_date.as_deref().unwrap_or("")),
+                ("amount", &self.config().format_amount(item.deposit_amount)),
+                ("patron_name", &patron.name),
+            ],
+        );
+
+        let default_screen = if result.was_precat && result.circ_id.is_some() {
+            Some("Checked out as a pre-cataloged item")
+        } else if result.hold_fulfilled && result.circ_id.is_some() {
+            Some("Hold fulfilled")
+        } else {
+            result.screen_msg.as_deref()
+        };
+
+        if let Some(t) = template {
+            resp.maybe_add_field("AF", t.screen().or(default_screen));
+            resp.maybe_add_field("AG", t.print());
+        } else {
+            resp.maybe_add_field("AF", default_screen);
+        }
+
         resp.maybe_add_field("AH", result.due_date.as_deref());
 
         if let Some(id) = result.circ_id {
             resp.add_field("BK", &format!("{id}"));
         }
 
-        if item.deposit_amount > 0.0 {
-            resp.add_field("BV", &format!("{:.2}", item.deposit_amount));
+        // Report the fee which was billed or, when the client has yet
+        // to acknowledge the fee, the fee which would be billed.
+        let fee_amount = result.fee_billed.unwrap_or(item.deposit_amount);
+
+        if fee_amount > 0.0 {
+            let amount = self.config().format_amount(fee_amount);
+            resp.add_field("BV", &amount);
+            resp.add_field("BH", self.config().currency());
         }
 
         Ok(resp)
@@ -269,202 +518,59 @@ impl Session {
         is_renewal: bool,
         ovride: bool,
     ) -> EgResult<CheckoutResult> {
-        if self.config().setting_is_true("use_native_checkout") {
-            self.checkout_native(item_barcode, patron_barcode, fee_ack, is_renewal, ovride)
-        } else {
-            self.checkout_api(item_barcode, patron_barcode, fee_ack, is_renewal, ovride)
-        }
-    }
-
-    /// Checkout variant that calls the traditional open-ils.circ APIs.
-    fn checkout_api(
-        &mut self,
-        item_barcode: &str,
-        patron_barcode: &str,
-        fee_ack: bool,
-        is_renewal: bool,
-        ovride: bool,
-    ) -> EgResult<CheckoutResult> {
-        let params = vec![
-            EgValue::from(self.editor().authtoken().unwrap()),
-            eg::hash! {
-                "copy_barcode": item_barcode,
-                "patron_barcode": patron_barcode,
-            },
-        ];
-
-        let method = match is_renewal {
-            true => match ovride {
-                true => RENEW_OVERRIDE_METHOD,
-                false => RENEW_METHOD,
-            },
-            false => match ovride {
-                true => CHECKOUT_OVERRIDE_METHOD,
-                false => CHECKOUT_METHOD,
-            },
-        };
+        let backend = self.circ_backend("use_native_checkout");
 
-        let mut resp =
-            match self
-                .editor()
-                .client_mut()
-                .send_recv_one("open-ils.circ", method, params)?
-            {
-                Some(r) => r,
-                None => Err(format!("API call {method} failed to return a response"))?,
-            };
-
-        log::info!(
-            "{self} Checkout of {item_barcode} returned: {}",
-            resp.dump()
-        );
-
-        let event = if resp.is_array() {
-            resp[0].take()
-        } else {
-            resp
+        let request = CheckoutRequest {
+            item_barcode,
+            patron_barcode,
+            fee_ack,
+            is_renewal,
+            ovride,
+            extra_options: HashMap::new(),
         };
 
-        let mut result = CheckoutResult::new();
-        result.was_renewal = is_renewal;
-
-        let evt = eg::event::EgEvent::parse(&event)
-            .ok_or_else(|| format!("API call {method} failed to return an event"))?;
-
-        if evt.is_success() {
-            let circ = &evt.payload()["circ"];
-
-            if circ.is_object() {
-                result.circ_id = Some(circ.id()?);
-                result.renewal_remaining = circ["renewal_remaining"].int()?;
-
-                let iso_date = circ["due_date"].as_str().unwrap(); // required
-                let due_dt = date::parse_datetime(iso_date)?;
-
-                if self
-                    .config()
-                    .setting_is_true("due_date_use_sip_date_format")
-                {
-                    result.due_date = Some(sip2::util::sip_date_from_dt(&due_dt));
-                } else {
-                    // YYYY-MM-DD HH:MM:SS
-                    result.due_date = Some(due_dt.format(DEFAULT_DUE_DATE_FORMAT).to_string());
-                }
-
-                return Ok(result);
-            } else {
-                log::error!("{self} checked out, but did not receive a circ object");
-            }
-        }
-
-        let can_override = self
-            .config()
-            .setting_is_true(&format!("checkout.override.{}", evt.textcode()));
-
-        if !ovride && can_override {
-            return self.checkout(item_barcode, patron_barcode, fee_ack, is_renewal, true);
-        }
-
-        if !ovride && fee_ack {
-            // Caller acknowledges a fee is required.
-            if evt.textcode().eq("ITEM_DEPOSIT_FEE_REQUIRED")
-                || evt.textcode().eq("ITEM_RENTAL_FEE_REQUIRED")
-            {
-                return self.checkout(item_barcode, patron_barcode, fee_ack, is_renewal, true);
-            }
-        }
-
-        if evt.textcode().eq("OPEN_CIRCULATION_EXISTS") {
-            let msg = self
-                .editor()
-                .retrieve("sipsm", "checkout.open_circ_exists")?
-                .ok_or_else(|| self.editor().die_event())?;
-
-            result.screen_msg = Some(msg["message"].string()?)
-        } else {
-            let msg = self
-                .editor()
-                .retrieve("sipsm", "checkout.patron_not_allowed")?
-                .ok_or_else(|| self.editor().die_event())?;
-
-            result.screen_msg = Some(msg["message"].string()?);
-        }
-
-        Ok(result)
+        self.checkout_with_backend(backend, request)
     }
 
-    /// Checkout that runs within the current thread as a direct
-    /// Rust call.
-    fn checkout_native(
+    /// Checkout or renew an item using the requested backend.
+    fn checkout_with_backend(
         &mut self,
-        item_barcode: &str,
-        patron_barcode: &str,
-        fee_ack: bool,
-        is_renewal: bool,
-        ovride: bool,
+        backend: CircBackend,
+        mut request: CheckoutRequest,
     ) -> EgResult<CheckoutResult> {
-        let mut options: HashMap<String, EgValue> = HashMap::new();
+        let item_barcode = request.item_barcode;
+        let patron_barcode = request.patron_barcode;
+        let is_renewal = request.is_renewal;
+
+        // The copy status is about to change.
+        self.uncache_lookup("acp", item_barcode)?;
 
+        let mut options = request.extra_options.clone();
         options.insert("copy_barcode".to_string(), item_barcode.into());
         options.insert("patron_barcode".to_string(), patron_barcode.into());
 
-        // Standalone transaction; cloning is just easier here.
-        let mut editor = self.editor().clone();
-
-        let mut circulator = Circulator::new(&mut editor, options)?;
-        circulator.begin()?;
-        circulator.is_override = ovride;
-
-        // Collect needed data then kickoff the checkin process.
-        let api_result = if is_renewal {
-            circulator.renew()
-        } else {
-            circulator.checkout()
+        let (op, method) = match is_renewal {
+            true => (CircOp::Renew, RENEW_METHOD),
+            false => (CircOp::Checkout, CHECKOUT_METHOD),
         };
 
-        let err_bind;
-        let evt = match api_result {
-            Ok(()) => {
-                circulator.commit()?;
-                circulator
-                    .events()
-                    .first()
-                    .ok_or_else(|| "API call failed to return an event".to_string())?
-            }
-            Err(err) => {
-                circulator.rollback()?;
-                err_bind = Some(err.event_or_default());
-                err_bind.as_ref().unwrap()
-            }
-        };
-
-        log::debug!(
-            "{self} Checkout of {item_barcode} returned: {}",
-            evt.to_value().dump()
-        );
+        let circ_result = self.run_circ_action(backend, op, method, options, request.ovride)?;
+        let evt = &circ_result.event;
 
         let mut result = CheckoutResult::new();
         result.was_renewal = is_renewal;
 
         if evt.is_success() {
-            let circ = &evt.payload()["circ"];
-
-            if circ.is_object() {
+            if let Some(circ) = circ_result.circ.as_ref() {
                 result.circ_id = Some(circ.id()?);
                 result.renewal_remaining = circ["renewal_remaining"].int()?;
 
-                let iso_date = circ["due_date"].as_str().unwrap(); // required
+                let iso_date = circ_result.due_date.as_deref().unwrap(); // required
                 let due_dt = date::parse_datetime(iso_date)?;
 
-                if self
-                    .config()
-                    .setting_is_true("due_date_use_sip_date_format")
-                {
-                    result.due_date = Some(sip2::util::sip_date_from_dt(&due_dt));
-                } else {
-                    // YYYY-MM-DD HH:MM:SS
-                    result.due_date = Some(due_dt.format(DEFAULT_DUE_DATE_FORMAT).to_string());
-                }
+                result.due_date = Some(self.format_due_date(&due_dt));
+                result.fee_billed = circ_result.fee_billed;
+                result.hold_fulfilled = !circ_result.holds_fulfilled.is_empty();
 
                 return Ok(result);
             } else {
@@ -476,20 +582,21 @@ impl Session {
             .config()
             .setting_is_true(&format!("checkout.override.{}", evt.textcode()));
 
-        if !ovride && can_override {
-            return self.checkout(item_barcode, patron_barcode, fee_ack, is_renewal, true);
+        // Retry with override if the account allows overriding this
+        // event or the caller acknowledges a fee is required.
+        if !request.ovride
+            && (can_override || (request.fee_ack && CheckoutResult::is_fee_event(evt.textcode())))
+        {
+            request.ovride = true;
+            return self.checkout_with_backend(backend, request);
         }
 
-        if !ovride && fee_ack {
-            // Caller acknowledges a fee is required.
-            if evt.textcode().eq("ITEM_DEPOSIT_FEE_REQUIRED")
-                || evt.textcode().eq("ITEM_RENTAL_FEE_REQUIRED")
-            {
-                return self.checkout(item_barcode, patron_barcode, fee_ack, is_renewal, true);
-            }
-        }
+        result.textcode = Some(evt.textcode().to_string());
 
-        if evt.textcode().eq("OPEN_CIRCULATION_EXISTS") {
+        if CheckoutResult::is_fee_event(evt.textcode()) {
+            // Client must acknowledge the fee (BO=Y) and try again.
+            result.screen_msg = Some("Fee acknowledgement required".to_string());
+        } else if evt.textcode().eq("OPEN_CIRCULATION_EXISTS") {
             let msg = self
                 .editor()
                 .retrieve("sipsm", "checkout.open_circ_exists")?
@@ -0,0 +1,144 @@
+use crate::session::Session;
+use eg::common::circulator::{CircOp, CircResult, Circulator};
+use eg::result::EgResult;
+use eg::EgValue;
+use evergreen as eg;
+use std::collections::HashMap;
+
+/// Where circulation actions are carried out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircBackend {
+    /// Traditional open-ils.circ API calls.
+    Api,
+    /// Direct calls to the Circulator within the current thread.
+    Native,
+}
+
+impl Session {
+    /// Backend to use for a circulation action.
+    ///
+    /// * `native_setting` - Name of the account setting which, when
+    ///   true, selects the native backend.
+    pub fn circ_backend(&self, native_setting: &str) -> CircBackend {
+        if self.config().setting_is_true(native_setting) {
+            CircBackend::Native
+        } else {
+            CircBackend::Api
+        }
+    }
+
+    /// Run a circulation action with the requested backend.
+    ///
+    /// * `api_method` - open-ils.circ method name, used with the API
+    ///   backend.  The override variant is called when `ovride` is set.
+    pub fn run_circ_action(
+        &mut self,
+        backend: CircBackend,
+        op: CircOp,
+        api_method: &str,
+        options: HashMap<String, EgValue>,
+        ovride: bool,
+    ) -> EgResult<CircResult> {
+        log::info!("{self} running {op} with options: {options:?}");
+
+        let result = match backend {
+            CircBackend::Api => {
+                let method = match ovride {
+                    true => format!("{api_method}.override"),
+                    false => api_method.to_string(),
+                };
+                self.run_circ_api(&method, options)?
+            }
+            CircBackend::Native => self.run_circulator(&op, options, ovride)?,
+        };
+
+        log::info!("{self} {op} returned {}", result.textcode());
+
+        Ok(result)
+    }
+
+    /// Call an open-ils.circ API and collect its primary event.
+    fn run_circ_api(
+        &mut self,
+        method: &str,
+        options: HashMap<String, EgValue>,
+    ) -> EgResult<CircResult> {
+        let params = vec![
+            EgValue::from(self.editor().authtoken().unwrap()),
+            EgValue::Hash(options),
+        ];
+
+        let mut resp =
+            match self
+                .editor()
+                .client_mut()
+                .send_recv_one("open-ils.circ", method, params)?
+            {
+                Some(r) => r,
+                None => Err(format!("API call {method} failed to return a response"))?,
+            };
+
+        log::debug!("{self} {method} returned: {}", resp.dump());
+
+        let evt_json = if resp.is_array() {
+            resp[0].take()
+        } else {
+            resp
+        };
+
+        let evt = eg::event::EgEvent::parse(&evt_json)
+            .ok_or_else(|| format!("API call {method} failed to return an event"))?;
+
+        Ok(CircResult::from_event(evt))
+    }
+
+    /// Run a circulation action as a standalone transaction in the
+    /// current thread.
+    fn run_circulator(
+        &mut self,
+        op: &CircOp,
+        options: HashMap<String, EgValue>,
+        ovride: bool,
+    ) -> EgResult<CircResult> {
+        // Standalone transaction; cloning is just easier here.
+        let mut editor = self.editor().clone();
+
+        // Busy systems may see occasional deadlocks, etc. which
+        // succeed on a second try.
+        let result = editor.in_transaction_retry(|e| {
+            let mut circulator = Circulator::new(e, options.clone())?;
+            circulator.is_override = ovride;
+
+            match op {
+                CircOp::Checkout => circulator.checkout()?,
+                CircOp::Checkin => circulator.checkin()?,
+                CircOp::Renew => circulator.renew()?,
+                CircOp::Unset => return Err("Circulation action required".into()),
+            }
+
+            Ok((circulator.result()?, circulator.post_commit()))
+        });
+
+        // Failed circulations are reported to the caller as events.
+        // Other errors, e.g. database or bus failures, are not
+        // circulation results.
+        let (result, post_commit) = match result {
+            Ok(r) => r,
+            Err(mut err) => {
+                return match err.take_event() {
+                    Some(evt) => Ok(CircResult::from_event(evt)),
+                    None => Err(err),
+                }
+            }
+        };
+
+        // Hold retargeting, A/T events, etc.  The circulation is
+        // committed at this point, so failures here do not change
+        // the response.
+        if let Err(e) = post_commit.run(&mut editor) {
+            log::error!("{self} post-commit tasks failed: {e}");
+        }
+
+        Ok(result)
+    }
+}
@@ -1,8 +1,10 @@
 use crate::session::Session;
+use eg::common::holds;
 use eg::result::EgResult;
 use eg::EgEvent;
 use eg::EgValue;
 use evergreen as eg;
+use sip2::spec::{HoldMode, HoldType};
 
 use crate::item::Item;
 use crate::patron::Patron;
@@ -26,17 +28,24 @@ impl Session {
         )
         .unwrap();
 
-        // At present, hold cancelation is the only supported operation.
-        if sip_msg.fixed_fields().first().map(|f| f.value()) != Some("-") {
-            log::warn!("{self} unsupported hold operation");
-            return Ok(response);
-        }
+        // "+" places, "-" cancels, and "*" modifies a hold.
+        let mode_code = sip_msg
+            .fixed_fields()
+            .first()
+            .map(|f| f.value())
+            .unwrap_or("");
+
+        let mode = HoldMode::try_from(mode_code);
 
         let patron = match self.get_patron_details(patron_barcode, None, None)? {
             Some(p) => p,
             None => return Ok(response),
         };
 
+        if mode == Ok(HoldMode::Add) {
+            return self.place_hold(&sip_msg, &patron, response);
+        }
+
         let item = match self.get_item_details(item_barcode)? {
             Some(i) => i,
             None => return Ok(response),
@@ -47,8 +56,28 @@ impl Session {
             None => return Ok(response),
         };
 
-        if !self.cancel_hold(hold.id()?)? {
-            return Ok(response);
+        match mode {
+            Ok(HoldMode::Delete) => {
+                if !self.cancel_hold(hold.id()?)? {
+                    return Ok(response);
+                }
+            }
+            Ok(HoldMode::Change) => {
+                if !self.modify_hold(&sip_msg, hold.id()?)? {
+                    return Ok(response);
+                }
+
+                self.add_hold_details(&mut response, hold.id()?)?;
+            }
+            _ => {
+                log::warn!("{self} unsupported hold mode '{mode_code}'");
+
+                // We can still tell the caller where the hold sits.
+                let stats = holds::queue_stats(self.editor(), hold.id()?)?;
+                response.add_field("BR", &stats.queue_position.to_string());
+
+                return Ok(response);
+            }
         }
 
         // Set the "OK" flag
@@ -68,6 +97,175 @@ impl Session {
         Ok(response)
     }
 
+    /// Place a title hold, or a copy hold when the hold type (BY) is
+    /// "3" (specific copy).
+    ///
+    /// The hold targets the record of the item (AB) or, lacking an
+    /// item, the title identifier (AJ) as a record ID.  Holds are
+    /// picked up at the requested pickup location (BS) or the
+    /// patron's home library.
+    fn place_hold(
+        &mut self,
+        sip_msg: &sip2::Message,
+        patron: &Patron,
+        mut response: sip2::Message,
+    ) -> EgResult<sip2::Message> {
+        if patron.holds_denied {
+            log::info!("{self} patron {} may not place holds", patron.barcode);
+            return Ok(response);
+        }
+
+        let item_barcode = sip_msg.get_field_value("AB").unwrap_or("");
+        let copy_hold = sip_msg
+            .get_field_value("BY")
+            .and_then(|t| HoldType::try_from(t).ok())
+            == Some(HoldType::SpecificCopy);
+
+        let (hold_type, target, record_id) = match self.get_item_details(item_barcode)? {
+            Some(item) if copy_hold => ("C", item.id, item.record_id),
+            Some(item) => ("T", item.record_id, item.record_id),
+            None => match sip_msg
+                .get_field_value("AJ")
+                .and_then(|t| t.parse::<i64>().ok())
+            {
+                Some(rec_id) if !copy_hold => ("T", rec_id, rec_id),
+                _ => return Ok(response),
+            },
+        };
+
+        let pickup_sn = sip_msg
+            .get_field_value("BS")
+            .or(patron.home_lib.as_deref())
+            .unwrap_or("");
+
+        let pickup_lib = match self.org_from_sn(pickup_sn)? {
+            Some(org) => org.id()?,
+            None => {
+                log::warn!("{self} invalid hold pickup location: '{pickup_sn}'");
+                return Ok(response);
+            }
+        };
+
+        let args = eg::hash! {
+            "patronid": patron.id,
+            "pickup_lib": pickup_lib,
+            "hold_type": hold_type,
+            "expire_time": expire_date(sip_msg),
+        };
+
+        let params = vec![
+            EgValue::from(self.editor().authtoken().unwrap()),
+            args,
+            EgValue::from(vec![target]),
+        ];
+
+        // One response per target.
+        let resp = self.editor().client_mut().send_recv_one(
+            "open-ils.circ",
+            "open-ils.circ.holds.test_and_create.batch",
+            params,
+        )?;
+
+        let Some(hold_id) = resp.as_ref().and_then(|r| r["result"].as_int()) else {
+            log::info!(
+                "{self} hold placement failed for patron {}: {}",
+                patron.barcode,
+                resp.map(|r| r["result"].dump()).unwrap_or_default()
+            );
+            return Ok(response);
+        };
+
+        log::info!(
+            "ACT:{self} placed {hold_type} hold {hold_id} for patron {}",
+            patron.barcode
+        );
+
+        response.fixed_fields_mut()[0].set_value("1").unwrap();
+        response.add_field("AJ", &record_id.to_string());
+
+        self.add_hold_details(&mut response, hold_id)?;
+
+        Ok(response)
+    }
+
+    /// Apply a new pickup location (BS) and/or expire date (BW) to a hold.
+    fn modify_hold(&mut self, sip_msg: &sip2::Message, hold_id: i64) -> EgResult<bool> {
+        let mut hold = self
+            .editor()
+            .retrieve("ahr", hold_id)?
+            .ok_or_else(|| self.editor().die_event())?;
+
+        if let Some(sn) = sip_msg.get_field_value("BS") {
+            match self.org_from_sn(sn)? {
+                Some(org) => hold["pickup_lib"] = org["id"].clone(),
+                None => {
+                    log::warn!("{self} invalid hold pickup location: '{sn}'");
+                    return Ok(false);
+                }
+            }
+        }
+
+        if let Some(date) = expire_date(sip_msg) {
+            hold["expire_time"] = EgValue::from(date);
+        }
+
+        let params = vec![EgValue::from(self.editor().authtoken().unwrap()), hold];
+
+        let resp = self.editor().client_mut().send_recv_one(
+            "open-ils.circ",
+            "open-ils.circ.hold.update",
+            params,
+        )?;
+
+        match resp {
+            Some(r) if EgEvent::parse(&r).is_none() => {
+                log::info!("ACT:{self} modified hold {hold_id}");
+                Ok(true)
+            }
+            r => {
+                log::info!(
+                    "{self} hold {hold_id} update failed: {}",
+                    r.map(|r| r.dump()).unwrap_or_default()
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Add the hold's availability, queue position (BR), pickup
+    /// location (BS), and expire date (BW) to a Hold Response.
+    fn add_hold_details(&mut self, response: &mut sip2::Message, hold_id: i64) -> EgResult<()> {
+        let flesh = eg::hash! {"flesh": 1, "flesh_fields": {"ahr": ["pickup_lib"]}};
+
+        let hold = self
+            .editor()
+            .retrieve_with_ops("ahr", hold_id, flesh)?
+            .ok_or_else(|| self.editor().die_event())?;
+
+        let pickup_lib = hold["pickup_lib"].id()?;
+
+        let available = hold["shelf_time"].is_string()
+            && hold["current_shelf_lib"].as_int() == Some(pickup_lib);
+
+        response.fixed_fields_mut()[1]
+            .set_value(sip2::util::sip_bool(available))
+            .unwrap();
+
+        let stats = holds::queue_stats(self.editor(), hold_id)?;
+        response.add_field("BR", &stats.queue_position.to_string());
+
+        if let Some(sn) = hold["pickup_lib"]["shortname"].as_str() {
+            response.add_field("BS", sn);
+        }
+
+        if let Some(expire) = hold["expire_time"].as_str() {
+            let expire = eg::date::parse_datetime(expire)?;
+            response.add_field("BW", &sip2::util::sip_date_from_dt(&expire));
+        }
+
+        Ok(())
+    }
+
     fn cancel_hold(&mut self, hold_id: i64) -> EgResult<bool> {
         let params = vec![
             EgValue::from(self.editor().authtoken().unwrap()),
@@ -168,3 +366,18 @@ impl Session {
         Ok(None)
     }
 }
+
+/// Hold expire date (BW) as YYYY-MM-DD.
+///
+/// SIP dates are formatted YYYYMMDDZZZZHHMMSS.
+fn expire_date(sip_msg: &sip2::Message) -> Option<String> {
+    // get() avoids panicking on a multi-byte character at the
+    // slice boundary.
+    let date = sip_msg.get_field_value("BW")?.get(..8)?;
+
+    if !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..8]))
+}
@@ -1,10 +1,10 @@
-use super::session::DEFAULT_DUE_DATE_FORMAT;
 use crate::session::Session;
 use eg::constants as C;
 use eg::date;
 use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
+use sip2::spec::{CirculationStatus, FeeType};
 use std::fmt;
 
 pub struct Item {
@@ -16,13 +16,17 @@ pub struct Item {
     pub call_number_id: i64,
     pub due_date: Option<String>,
     pub copy_status: i64,
-    pub circ_status: &'static str,
-    pub fee_type: &'static str,
+    pub circ_status: CirculationStatus,
+    pub fee_type: FeeType,
     pub title: String,
     pub current_loc: String,
     pub permanent_loc: String,
     pub destination_loc: String,
     pub owning_loc: String,
+    /// Full name of the owning library.
+    pub owning_loc_name: String,
+    /// Full name of the circulating library.
+    pub current_loc_name: String,
     pub collection_code: String,
     pub deposit_amount: f64,
     pub magnetic_media: bool,
@@ -30,6 +34,8 @@ pub struct Item {
     pub media_type: String,
     pub hold_pickup_date: Option<String>,
     pub hold_patron_barcode: Option<String>,
+    /// User ID of the patron whose hold the item is captured for.
+    pub hold_patron_id: Option<i64>,
     pub circ_patron_id: Option<i64>,
 }
 
@@ -58,9 +64,15 @@ impl Session {
             }
         };
 
-        let copy = match self.editor().search_with_ops("acp", search, flesh)?.pop() {
+        let copy = match self.cached_lookup("acp", barcode)? {
             Some(c) => c,
-            None => return Ok(None),
+            None => match self.editor().search_with_ops("acp", search, flesh)?.pop() {
+                Some(c) => {
+                    self.cache_lookup("acp", barcode, &c)?;
+                    c
+                }
+                None => return Ok(None),
+            },
         };
 
         let copy_status = copy["status"].int()?;
@@ -73,15 +85,7 @@ impl Session {
 
             if let Some(iso_date) = circ["due_date"].as_str() {
                 let due_dt = date::parse_datetime(iso_date)?;
-                if self
-                    .config()
-                    .setting_is_true("due_date_use_sip_date_format")
-                {
-                    due_date = Some(sip2::util::sip_date_from_dt(&due_dt));
-                } else {
-                    // YYYY-MM-DD HH:MM:SS
-                    due_date = Some(due_dt.format(DEFAULT_DUE_DATE_FORMAT).to_string());
-                }
+                due_date = Some(self.format_due_date(&due_dt));
             }
         }
 
@@ -98,6 +102,7 @@ impl Session {
 
         let mut hold_pickup_date_op: Option<String> = None;
         let mut hold_patron_barcode_op: Option<String> = None;
+        let mut hold_patron_id_op: Option<i64> = None;
         let mut hold_queue_length = 0;
 
         if let Some(hold) = self.get_copy_hold(&copy, &transit_op, copy_status)? {
@@ -113,23 +118,21 @@ impl Session {
             if let Some(bc) = hold["usr"]["card"]["barcode"].as_str() {
                 hold_patron_barcode_op = Some(bc.to_string());
             }
+
+            hold_patron_id_op = hold["usr"]["id"].as_i64();
         }
 
         let deposit_amount = copy["deposit_amount"].float()?;
 
-        let mut fee_type = "01";
-        if copy["deposit"].as_str().unwrap().eq("f") && deposit_amount > 0.0 {
-            fee_type = "06";
+        let mut fee_type = FeeType::OtherUnknown;
+        if !copy.bool_at("deposit")? && deposit_amount > 0.0 {
+            fee_type = FeeType::Rental;
         }
 
         let circ_status = self.circ_status(copy_status);
-        let media_type = copy["circ_modifier"]["sip2_media_type"]
-            .as_str()
-            .unwrap_or("001");
-        let magnetic_media = copy["circ_modifier"]["magnetic_media"].boolish();
 
-        let (title, _) = self.get_copy_title_author(&copy)?;
-        let title = title.unwrap_or(String::new());
+        let display = self.get_copy_display(&copy, true)?;
+        let title = display.title.unwrap_or_default();
 
         let call_number = format!(
             "{}{}{}",
@@ -150,6 +153,39 @@ impl Session {
 
         let collection_code = collection_code["name"].take_string().unwrap();
 
+        let (media_type, magnetic_media) = self
+            .config()
+            .media_types()
+            .resolve(&copy["circ_modifier"], &collection_code);
+
+        self.set_response_var("item.barcode", barcode);
+        self.set_response_var("item.title", &title);
+        self.set_response_var("item.author", display.author.as_deref().unwrap_or(""));
+        self.set_response_var("item.edition", display.edition.as_deref().unwrap_or(""));
+        self.set_response_var("item.isbn", display.isbns.first().map_or("", |v| v));
+        self.set_response_var(
+            "item.physical_description",
+            display.physical_description.as_deref().unwrap_or(""),
+        );
+        self.set_response_var("item.call_number", &call_number);
+        self.set_response_var("item.collection_code", &collection_code);
+        self.set_response_var("item.media_type", &media_type);
+        self.set_response_var("item.circ_lib", &circ_lib);
+        self.set_response_var("item.owning_lib", owning_lib);
+        self.set_response_var(
+            "item.circ_modifier",
+            copy["circ_modifier"]["code"].as_str().unwrap_or(""),
+        );
+
+        for map in copy["stat_cat_entry_copy_maps"].members() {
+            if let (Some(name), Some(value)) = (
+                map["stat_cat"]["name"].as_str(),
+                map["stat_cat_entry"]["value"].as_str(),
+            ) {
+                self.set_response_var(&format!("item.stat_cat.{name}"), value);
+            }
+        }
+
         Ok(Some(Item {
             id: copy.id()?,
             barcode: barcode.to_string(),
@@ -168,15 +204,54 @@ impl Session {
             permanent_loc: circ_lib.to_string(),
             destination_loc: dest_location,
             owning_loc: owning_lib.to_string(),
-            media_type: media_type.to_string(),
+            owning_loc_name: copy["call_number"]["owning_lib"]["name"].string()?,
+            current_loc_name: copy["circ_lib"]["name"].string()?,
+            media_type,
             hold_pickup_date: hold_pickup_date_op,
             hold_patron_barcode: hold_patron_barcode_op,
+            hold_patron_id: hold_patron_id_op,
             circ_patron_id,
             record_id: copy["call_number"]["record"].int()?,
             call_number_id: copy["call_number"].id()?,
         }))
     }
 
+    /// Number of open holds which could be filled by this item's
+    /// bib record.
+    pub fn item_hold_queue_length(&mut self, item: &Item) -> EgResult<usize> {
+        let count = eg::common::holds::record_hold_counts(self.editor(), item.record_id, None)?;
+        Ok(count as usize)
+    }
+
+    /// Notes from unacknowledged copy alerts on the item.
+    pub fn item_alert_messages(&mut self, item: &Item) -> EgResult<Vec<String>> {
+        let query = eg::hash! {
+            "copy": item.id,
+            "ack_time": EgValue::Null,
+        };
+
+        let flesh = eg::hash! {
+            "flesh": 1,
+            "flesh_fields": {"aca": ["alert_type"]}
+        };
+
+        let mut messages = Vec::new();
+
+        for alert in self.editor().search_with_ops("aca", query, flesh)? {
+            if !alert["alert_type"]["active"].boolish() {
+                continue;
+            }
+
+            match alert["note"].as_str() {
+                Some(note) if !note.is_empty() => messages.push(note.to_string()),
+                // Fall back to the alert type name when no note exists.
+                _ => messages.push(alert["alert_type"]["name"].string()?),
+            }
+        }
+
+        Ok(messages)
+    }
+
     /// Find an active hold linked to the copy.  The copy must be on
     /// the holds shelf or in transit to the holds shelf.
     fn get_copy_hold(
@@ -242,18 +317,18 @@ impl Session {
         Ok(transits.pop())
     }
 
-    fn circ_status(&self, copy_status: i64) -> &'static str {
+    fn circ_status(&self, copy_status: i64) -> CirculationStatus {
         match copy_status {
-            C::COPY_STATUS_ON_ORDER => "02",
-            C::COPY_STATUS_AVAILABLE => "03",
-            C::COPY_STATUS_CHECKED_OUT => "04",
-            C::COPY_STATUS_IN_PROCESS => "06",
-            C::COPY_STATUS_ON_HOLDS_SHELF => "08",
-            C::COPY_STATUS_RESHELVING => "09",
-            C::COPY_STATUS_IN_TRANSIT => "10",
-            C::COPY_STATUS_LOST | C::COPY_STATUS_LOST_AND_PAID => "12",
-            C::COPY_STATUS_MISSING => "13",
-            _ => "01", // unknown
+            C::COPY_STATUS_ON_ORDER => CirculationStatus::OnOrder,
+            C::COPY_STATUS_AVAILABLE => CirculationStatus::Available,
+            C::COPY_STATUS_CHECKED_OUT => CirculationStatus::Charged,
+            C::COPY_STATUS_IN_PROCESS => CirculationStatus::InProcess,
+            C::COPY_STATUS_ON_HOLDS_SHELF => CirculationStatus::WaitingOnHoldShelf,
+            C::COPY_STATUS_RESHELVING => CirculationStatus::WaitingToBeReshelved,
+            C::COPY_STATUS_IN_TRANSIT => CirculationStatus::InTransit,
+            C::COPY_STATUS_LOST | C::COPY_STATUS_LOST_AND_PAID => CirculationStatus::Lost,
+            C::COPY_STATUS_MISSING => CirculationStatus::Missing,
+            _ => CirculationStatus::Other,
         }
     }
 
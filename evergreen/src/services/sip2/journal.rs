@@ -0,0 +1,126 @@
+//! Replay of responses to retransmitted SIP requests.
+//!
+//! Self-check units which time out waiting on a Checkout or Fee Paid
+//! response often send the same request again, which could circulate
+//! the item twice or charge the patron twice.  When the
+//! "duplicate_request_window" setting is non-zero, responses to these
+//! messages are journaled in the cache for that many seconds, keyed on
+//! the SIP account and the fields which identify the request.  Repeats
+//! of a journaled request receive the journaled response instead of
+//! being processed again.
+use super::session::Session;
+use eg::osrf::cache::Cache;
+use eg::result::EgResult;
+use eg::util;
+use eg::EgValue;
+use evergreen as eg;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const JOURNAL_PFX: &str = "sip2:journal";
+
+/// Messages which change patron or item state.
+const JOURNALED_MESSAGES: &[&str] = &["11", "37"];
+
+/// Patron barcode, item barcode, fee amount, fee identifier, and
+/// transaction identifier.
+///
+/// The transaction date fixed field is also part of the key, so
+/// repeats of a request made at a later time, e.g. a second payment
+/// of the same amount, are processed normally.
+const KEY_FIELDS: &[&str] = &["AA", "AB", "BV", "CG", "BK"];
+
+/// How often we check for the response to a duplicate request whose
+/// original is still being processed.
+const PENDING_POLL_INTERVAL: u64 = 250;
+
+impl Session {
+    /// Journal key for a request, if the request is journaled.
+    pub fn journal_key(&self, msg: &sip2::Message) -> Option<String> {
+        if self.config().duplicate_request_window() == 0 {
+            return None;
+        }
+
+        let code = msg.spec().code;
+
+        if !JOURNALED_MESSAGES.contains(&code) {
+            return None;
+        }
+
+        let date = msg
+            .fixed_fields()
+            .iter()
+            .find(|ff| ff.spec() == &sip2::spec::FF_DATE)
+            .map(|ff| ff.value())
+            .unwrap_or("");
+
+        let mut key = format!(
+            "{}|{code}|{date}",
+            self.sip_account()["sip_username"].str().ok()?
+        );
+
+        for field in KEY_FIELDS {
+            key += &format!("|{}", msg.get_field_value(field).unwrap_or(""));
+        }
+
+        // Barcodes et al. are not cache-key-safe.
+        Some(format!("{JOURNAL_PFX}:{:x}", md5::compute(key)))
+    }
+
+    /// Returns the journaled response to an earlier copy of a request.
+    ///
+    /// If the earlier request is still being processed, wait for its
+    /// response.  Otherwise, mark the request as in progress so any
+    /// copies which follow will wait on us.  The marker is added
+    /// atomically, so only one copy of a request is processed.
+    pub fn journaled_response(&self, key: &str) -> EgResult<Option<sip2::Message>> {
+        let window = self.config().duplicate_request_window();
+        let started = Instant::now();
+
+        // Unique to this request, so we can tell whether our marker won.
+        let marker = eg::hash! {
+            "pending": true,
+            "owner": format!("{}-{}", util::thread_id(), util::random_number(16)),
+        };
+
+        loop {
+            if Cache::add_global_for(key, marker.clone(), window)? {
+                return Ok(None);
+            }
+
+            let Some(entry) = Cache::get_global(key)? else {
+                // Expired or removed since our add; try again.
+                continue;
+            };
+
+            if !entry["pending"].boolish() {
+                log::info!("ACT:{self} replaying journaled response to duplicate request");
+
+                let msg = sip2::Message::from_json_value(entry["response"].clone().into())
+                    .map_err(|e| format!("{self} invalid journaled response: {e}"))?;
+
+                return Ok(Some(msg));
+            }
+
+            if started.elapsed().as_secs() >= window as u64 {
+                return Err(format!("{self} duplicate request is still in progress").into());
+            }
+
+            thread::sleep(Duration::from_millis(PENDING_POLL_INTERVAL));
+        }
+    }
+
+    /// Store the response to a journaled request.
+    pub fn journal_response(&self, key: &str, response: &sip2::Message) -> EgResult<()> {
+        let entry = eg::hash! {
+            "response": EgValue::from_json_value(response.to_json_value())?,
+        };
+
+        Cache::set_global_for(key, entry, self.config().duplicate_request_window())
+    }
+
+    /// Forget a journaled request which failed, so it may be retried.
+    pub fn unjournal(&self, key: &str) -> EgResult<()> {
+        Cache::del_global(key)
+    }
+}
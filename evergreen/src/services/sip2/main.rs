@@ -3,12 +3,15 @@ use evergreen as eg;
 pub mod app;
 pub mod checkin;
 pub mod checkout;
+pub mod circ;
 pub mod holds;
 pub mod item;
+pub mod journal;
 pub mod methods;
 pub mod patron;
 pub mod payment;
 pub mod session;
+pub mod shared;
 pub mod util;
 
 fn main() {
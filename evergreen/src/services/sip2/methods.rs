@@ -8,11 +8,13 @@ use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
 use sip2;
+use sip2::spec::{CirculationStatus, FeeType, SecurityMarker};
 
 // Import our local app module
 use crate::app;
 use crate::session::Config;
 use crate::session::Session;
+use crate::shared;
 
 /// List of method definitions we know at compile time.
 pub static METHODS: &[StaticMethodDef] = &[StaticMethodDef {
@@ -86,27 +88,119 @@ pub fn dispatch_sip_request(
         }
     };
 
-    let response = match msg_code {
-        "01" => handle_block_patron(&mut sip_ses, sip_msg)?,
-        "09" => handle_checkin(&mut sip_ses, sip_msg)?,
-        "11" => handle_checkout(&mut sip_ses, sip_msg)?,
-        "15" => handle_hold(&mut sip_ses, sip_msg)?,
-        "17" => handle_item_info(&mut sip_ses, sip_msg)?,
-        "23" => handle_patron_status(&mut sip_ses, sip_msg)?,
-        "29" => handle_renew(&mut sip_ses, sip_msg)?,
-        "35" => handle_end_patron_session(&mut sip_ses, sip_msg)?,
-        "37" => handle_payment(&mut sip_ses, sip_msg)?,
-        "63" => handle_patron_info(&mut sip_ses, sip_msg)?,
-        "65" => handle_renew_all(&mut sip_ses, sip_msg)?,
-        "XS" => handle_end_session(&mut sip_ses, sip_msg)?,
-        _ => return Err(format!("SIP message '{msg_code}' not implemented").into()),
+    if let Some(ff) = sip_msg
+        .fixed_fields()
+        .iter()
+        .find(|ff| ff.spec() == &sip2::spec::FF_LANGUAGE)
+    {
+        // Remember the patron's language for the rest of the session.
+        if sip_ses.set_language(ff.value()) {
+            sip_ses.to_cache()?;
+        }
+    }
+
+    if !sip_ses.config().allows_message(msg_code) {
+        log::warn!("{sip_ses} SIP message '{msg_code}' not permitted");
+
+        let mut response = refused_response(&mut sip_ses, &sip_msg)?;
+        sip_ses.apply_filters(&mut response);
+
+        let value = EgValue::from_json_value(response.to_json_value())?;
+        return session.respond_complete(value);
+    }
+
+    let journal_key = sip_ses.journal_key(&sip_msg);
+
+    if let Some(key) = journal_key.as_deref() {
+        if let Some(response) = sip_ses.journaled_response(key)? {
+            let value = EgValue::from_json_value(response.to_json_value())?;
+            return session.respond_complete(value);
+        }
+    }
+
+    let result = match msg_code {
+        "01" => handle_block_patron(&mut sip_ses, sip_msg),
+        "09" => handle_checkin(&mut sip_ses, sip_msg),
+        "11" => handle_checkout(&mut sip_ses, sip_msg),
+        "15" => handle_hold(&mut sip_ses, sip_msg),
+        "17" => handle_item_info(&mut sip_ses, sip_msg),
+        "23" => handle_patron_status(&mut sip_ses, sip_msg),
+        "25" => handle_patron_enable(&mut sip_ses, sip_msg),
+        "29" => handle_renew(&mut sip_ses, sip_msg),
+        "35" => handle_end_patron_session(&mut sip_ses, sip_msg),
+        "37" => handle_payment(&mut sip_ses, sip_msg),
+        "63" => handle_patron_info(&mut sip_ses, sip_msg),
+        "65" => handle_renew_all(&mut sip_ses, sip_msg),
+        "81" => handle_patron_password_change(&mut sip_ses, sip_msg),
+        "XS" => handle_end_session(&mut sip_ses, sip_msg),
+        _ => Err(format!("SIP message '{msg_code}' not implemented").into()),
     };
 
+    let mut response = match result {
+        Ok(r) => r,
+        Err(e) => {
+            // Let the client retry a request which failed.
+            if let Some(key) = journal_key.as_deref() {
+                sip_ses.unjournal(key)?;
+            }
+            return Err(e);
+        }
+    };
+
+    sip_ses.apply_filters(&mut response);
+
+    if let Some(key) = journal_key.as_deref() {
+        sip_ses.journal_response(key, &response)?;
+    }
+
     let value = EgValue::from_json_value(response.to_json_value())?;
 
     session.respond_complete(value)
 }
 
+/// Negative response to a message the SIP account may not send.
+///
+/// Includes the "message.refused" screen message, when configured.
+fn refused_response(sip_ses: &mut Session, sip_msg: &sip2::Message) -> EgResult<sip2::Message> {
+    let msg_code = sip_msg.spec().code;
+    let patron_barcode = sip_msg.get_field_value("AA").unwrap_or("");
+    let item_barcode = sip_msg.get_field_value("AB").unwrap_or("");
+    let institution = sip_ses.config().institution().to_string();
+    let sipdate = sip2::util::sip_date_now();
+
+    let (code, ff): (&str, Vec<&str>) = match msg_code {
+        // Patron lookups and updates respond as if the patron was
+        // not found.
+        "01" | "23" => return sip_ses.patron_response_common("24", patron_barcode, None),
+        "25" => return sip_ses.patron_response_common("26", patron_barcode, None),
+        "63" => return sip_ses.patron_response_common("64", patron_barcode, None),
+        "11" => ("12", vec!["0", "N", "N", "N", &sipdate]),
+        "29" => ("30", vec!["0", "N", "N", "N", &sipdate]),
+        "15" => ("16", vec!["0", "N", &sipdate]),
+        "35" => ("36", vec!["N", &sipdate]),
+        "37" => ("38", vec!["N", &sipdate]),
+        "65" => ("66", vec!["0", "0000", "0000", &sipdate]),
+        "81" => ("82", vec!["0", &sipdate]),
+        _ => return Err(format!("SIP message '{msg_code}' not permitted for {sip_ses}").into()),
+    };
+
+    let mut fields = vec![("AO", institution.as_str()), ("AA", patron_barcode)];
+
+    if matches!(msg_code, "11" | "29" | "15") {
+        fields.push(("AB", item_barcode));
+    }
+
+    let mut resp = sip2::Message::from_values(code, &ff, &fields)
+        .map_err(|e| format!("Cannot build refused response: {e}"))?;
+
+    if let Some(t) = sip_ses.screen_message(&["message.refused"], &[]) {
+        resp.maybe_add_field("AF", t.screen());
+        resp.maybe_add_field("AG", t.print());
+    }
+
+    Ok(resp)
+}
+
 fn handle_login(
     editor: &mut Editor,
     seskey: &str,
@@ -135,6 +229,11 @@ fn handle_login(
         "enabled": "t",
     };
 
+    // A login on an established connection replaces the active
+    // account along with its settings and ILS auth session.  The
+    // previous login is ended even if the new login fails.
+    let language = Session::end_cached(editor, seskey)?;
+
     let sip_account = match editor.search_with_ops("sipacc", query, flesh)?.pop() {
         Some(a) => a,
         None => {
@@ -144,7 +243,15 @@ fn handle_login(
     };
 
     if user::verify_password(editor, sip_account["usr"].int()?, sip_password, "sip2")? {
+        // Pick up any settings changes made since the shared config
+        // was loaded.
+        shared::reload_config(editor, sip_account["setting_group"].int()?)?;
+
         let mut session = Session::new(editor, seskey, sip_account)?;
+        if let Some(lang) = language {
+            session.set_language(&lang);
+        }
+        session.auto_register_workstation(sip_msg.get_field_value("CP"))?;
         session.refresh_auth_token()?;
         session.to_cache()?;
 
@@ -180,6 +287,12 @@ fn handle_sc_status(
     .unwrap();
 
     if let Some(mut session) = Session::from_cache(editor, seskey)? {
+        if session.config().setting_is_true("checkin_only") {
+            let ff = response.fixed_fields_mut();
+            ff[2].set_value(sip2::util::sip_bool(false)).unwrap(); // checkout_ok
+            ff[3].set_value(sip2::util::sip_bool(false)).unwrap(); // acs_renewal_policy
+        }
+
         response.add_field("AO", session.config().institution());
         response.add_field("BX", session.config().supports());
 
@@ -225,9 +338,9 @@ fn handle_item_info(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResult<s
             return Ok(sip2::Message::from_values(
                 "18",
                 &[
-                    "01",                        // circ status: other/Unknown
-                    "01",                        // security marker: other/unknown
-                    "01",                        // fee type: other/unknown
+                    CirculationStatus::Other.into(),
+                    SecurityMarker::None.into(),
+                    FeeType::OtherUnknown.into(),
                     &sip2::util::sip_date_now(), // transaction date
                 ],
                 &[("AB", barcode), ("AJ", "")],
@@ -236,31 +349,49 @@ fn handle_item_info(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResult<s
         }
     };
 
-    let cur_set = sip_ses.config().settings().get("currency");
-    let currency = if let Some(cur) = cur_set {
-        cur.str()?
+    let hold_queue_length = if sip_ses
+        .config()
+        .setting_is_true("item_info_hold_queue_length")
+    {
+        sip_ses.item_hold_queue_length(&item)?
+    } else {
+        item.hold_queue_length
+    };
+
+    // Full org unit names instead of shortnames.
+    let use_labels = sip_ses
+        .config()
+        .setting_is_true("item_info_location_labels");
+
+    // Our permanent location is always the circ lib.
+    let (owning_loc, current_loc, permanent_loc) = if use_labels {
+        (
+            &item.owning_loc_name,
+            &item.current_loc_name,
+            &item.current_loc_name,
+        )
     } else {
-        "USD"
+        (&item.owning_loc, &item.current_loc, &item.permanent_loc)
     };
 
     let mut resp = sip2::Message::from_values(
         "18",
         &[
-            item.circ_status,
-            "02", // security marker
-            (item.fee_type),
+            item.circ_status.into(),
+            SecurityMarker::TattleTape.into(),
+            item.fee_type.into(),
             &sip2::util::sip_date_now(),
         ],
         &[
             ("AB", &item.barcode),
-            ("BG", &item.owning_loc),
+            ("BG", owning_loc),
             ("AJ", &item.title),
-            ("AP", &item.current_loc),
-            ("AQ", &item.permanent_loc),
-            ("BH", currency),
-            ("BV", &format!("{:.2}", item.deposit_amount)),
+            ("AP", current_loc),
+            ("AQ", permanent_loc),
+            ("BH", sip_ses.config().currency()),
+            ("BV", &sip_ses.config().format_amount(item.deposit_amount)),
             //("CI", "N"), // security inhibit / not supported
-            ("CF", &format!("{}", item.hold_queue_length)),
+            ("CF", &format!("{hold_queue_length}")),
             ("CK", &item.media_type),
             ("CS", &item.call_number),
             ("CT", &item.destination_loc),
@@ -272,6 +403,16 @@ fn handle_item_info(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResult<s
     resp.maybe_add_field("CY", item.hold_patron_barcode.as_deref());
     resp.maybe_add_field("AH", item.due_date.as_deref());
 
+    if sip_ses.config().setting_is_true("item_info_copy_location") {
+        resp.add_field("CR", &item.collection_code);
+    }
+
+    if sip_ses.config().setting_is_true("item_info_alert_messages") {
+        for msg in sip_ses.item_alert_messages(&item)? {
+            resp.add_field("AF", &msg);
+        }
+    }
+
     Ok(resp)
 }
 
@@ -333,6 +474,17 @@ fn handle_block_patron(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResul
     sip_ses.handle_block_patron(sip_msg)
 }
 
+fn handle_patron_enable(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResult<sip2::Message> {
+    sip_ses.handle_patron_enable(sip_msg)
+}
+
+fn handle_patron_password_change(
+    sip_ses: &mut Session,
+    sip_msg: sip2::Message,
+) -> EgResult<sip2::Message> {
+    sip_ses.handle_patron_password_change(sip_msg)
+}
+
 fn handle_hold(sip_ses: &mut Session, sip_msg: sip2::Message) -> EgResult<sip2::Message> {
     sip_ses.handle_hold(sip_msg)
 }
@@ -1,10 +1,13 @@
 use crate::session::Session;
+use eg::common::penalty::{self, PatronLimits};
 use eg::constants as C;
 use eg::date;
+use eg::osrf::message;
 use eg::result::EgResult;
 use eg::EgEvent;
 use eg::EgValue;
 use evergreen as eg;
+use sip2::spec::{Language, SummaryIndex};
 
 const EG_NULL: EgValue = EgValue::Null;
 const DEFAULT_LIST_ITEM_SIZE: usize = 10;
@@ -101,6 +104,7 @@ pub struct Patron {
     pub recall_denied: bool,
     pub holds_denied: bool,
     pub card_lost: bool,
+    pub max_charged: bool,
     pub max_overdue: bool,
     pub max_fines: bool,
     pub recall_overdue: bool,
@@ -132,6 +136,8 @@ pub struct Patron {
     pub profile: Option<String>,
     pub phone: Option<String>,
     pub screen_msg: Option<String>,
+    /// Checkout limits reported in Patron Information responses.
+    pub limits: PatronLimits,
 }
 
 impl Patron {
@@ -145,6 +151,7 @@ impl Patron {
             recall_denied: false,
             holds_denied: false,
             card_lost: false,
+            max_charged: false,
             max_overdue: false,
             max_fines: false,
             recall_overdue: false,
@@ -173,6 +180,7 @@ impl Patron {
             profile: None,
             phone: None,
             screen_msg: None,
+            limits: PatronLimits::default(),
         }
     }
 }
@@ -199,16 +207,71 @@ impl Session {
             }
         };
 
-        let mut patron = Patron::new(barcode, self.format_user_name(&user));
+        let mut patron = self.build_patron(barcode, &user, password_op)?;
+
+        let penalties = self.fetch_patron_data(&mut patron, true)?;
+        self.set_patron_privileges(&user, &mut patron, &penalties)?;
+
+        let org_id = self.editor().perm_org();
+        patron.limits = penalty::patron_limits(self.editor(), user["profile"].id()?, org_id)?;
+
+        if let Some(ops) = summary_list_options {
+            self.set_patron_summary_list_items(&mut patron, ops)?;
+        }
+
+        self.log_activity(patron.id)?;
+
+        Ok(Some(patron))
+    }
+
+    /// Collect the subset of patron data needed for a Patron Status
+    /// response: standing, blocks, and fine total.
+    ///
+    /// Skips the item and hold summary queries performed for Patron
+    /// Information, since clients like security gates may send a
+    /// Patron Status request for every card swipe.
+    pub fn get_patron_status(
+        &mut self,
+        barcode: &str,
+        password_op: Option<&str>,
+    ) -> EgResult<Option<Patron>> {
+        log::info!("{self} SIP patron status for {barcode}");
+
+        if barcode.is_empty() {
+            return Ok(None);
+        }
+
+        let user = match self.get_user(barcode)? {
+            Some(u) => u,
+            None => {
+                log::warn!("{self} No such patron: {barcode}");
+                return Ok(None);
+            }
+        };
+
+        let mut patron = self.build_patron(barcode, &user, password_op)?;
+
+        let penalties = self.fetch_patron_data(&mut patron, false)?;
+        self.set_patron_privileges(&user, &mut patron, &penalties)?;
+        self.log_activity(patron.id)?;
+
+        Ok(Some(patron))
+    }
+
+    /// Create a Patron from a fleshed user with the values that come
+    /// directly from the user account, plus the password check.
+    fn build_patron(
+        &mut self,
+        barcode: &str,
+        user: &EgValue,
+        password_op: Option<&str>,
+    ) -> EgResult<Patron> {
+        let mut patron = Patron::new(barcode, self.format_user_name(user));
 
         patron.id = user.id()?;
         // Patron password is an optional field.  Is an undefined password
         // valid?  This code says no. EG SIPServer says yes.
-        patron.password_verified = self.check_password(patron.id, password_op)?;
-
-        if let Some(summary) = self.editor().retrieve("mous", patron.id)? {
-            patron.balance_owed = summary["balance_owed"].float()?;
-        }
+        patron.password_verified = self.check_password(barcode, patron.id, password_op)?;
 
         if user["billing_address"].is_object() {
             patron.address = Some(self.format_address(&user["billing_address"]));
@@ -251,22 +314,19 @@ impl Session {
             patron.phone = Some(phone.to_string());
         }
 
+        self.set_response_var("patron.barcode", barcode);
+        self.set_response_var("patron.name", &patron.name);
+        self.set_response_var("patron.home_lib", patron.home_lib.as_deref().unwrap_or(""));
+        self.set_response_var("patron.profile", patron.profile.as_deref().unwrap_or(""));
+        self.set_response_var("patron.email", patron.email.as_deref().unwrap_or(""));
+
         if let Some(expire) = user["expire_date"].as_str() {
             if let Ok(date) = date::parse_datetime(expire) {
                 patron.expire_date = Some(date.format("%Y%m%d").to_string());
             }
         }
 
-        self.set_patron_privileges(&user, &mut patron)?;
-        self.set_patron_summary_items(&mut patron)?;
-
-        if let Some(ops) = summary_list_options {
-            self.set_patron_summary_list_items(&mut patron, ops)?;
-        }
-
-        self.log_activity(patron.id)?;
-
-        Ok(Some(patron))
+        Ok(patron)
     }
 
     fn log_activity(&mut self, patron_id: i64) -> EgResult<()> {
@@ -280,23 +340,17 @@ impl Session {
                 patron_id,
                 who,
                 "verify",
-                "sip2", // ingress
+                message::thread_ingress(),
             ]
         };
 
-        self.editor().xact_begin()?;
-
-        let resp = self.editor().json_query(query)?;
-
-        if !resp.is_empty() {
-            self.editor().commit()?;
-            Ok(())
-        } else {
-            self.editor().rollback()?;
-            Err("Patron activity logging returned no response"
-                .to_string()
-                .into())
-        }
+        self.editor().in_transaction(|e| {
+            if e.json_query(query)?.is_empty() {
+                Err("Patron activity logging returned no response".into())
+            } else {
+                Ok(())
+            }
+        })
     }
 
     /// Caller wants to see specific values of a given type, e.g. list
@@ -388,7 +442,11 @@ impl Session {
 
         match av_format {
             AvFormat::Legacy => {
-                line = format!("{:.2} {}", balance_owed, last_billing_type);
+                line = format!(
+                    "{} {}",
+                    self.config().format_amount(balance_owed),
+                    last_billing_type
+                );
                 if is_circ {
                     line += &format!(" {} / {}", title, author);
                 }
@@ -406,8 +464,10 @@ impl Session {
 
             AvFormat::SwyerB => {
                 line = format!(
-                    "Charge-Number: {}, Amount-Due: {:.2}, Fine-Type: {}",
-                    xact_id, balance_owed, fee_type
+                    "Charge-Number: {}, Amount-Due: {}, Fine-Type: {}",
+                    xact_id,
+                    self.config().format_amount(balance_owed),
+                    fee_type
                 );
 
                 if is_circ {
@@ -432,7 +492,9 @@ impl Session {
 
         let circ = self.editor().retrieve_with_ops("circ", id, flesh)?.unwrap();
 
-        self.get_copy_title_author(&circ["target_copy"])
+        let display = self.get_copy_display(&circ["target_copy"], false)?;
+
+        Ok((display.title, display.author))
     }
 
     fn add_items_out(
@@ -502,7 +564,7 @@ impl Session {
             let circ = self.editor().retrieve_with_ops("circ", id, flesh)?.unwrap();
 
             // If we have a circ, we have to have copy barcode.
-            let bc = circ["target_copy"]["barcode"].as_str().unwrap();
+            let bc = circ.str_at("target_copy.barcode")?;
 
             return Ok(bc.to_string());
         }
@@ -552,7 +614,7 @@ impl Session {
             if let Some(hold) = self.editor().retrieve("ahr", *hold_id)? {
                 if format == Msg64HoldDatatype::Barcode {
                     if let Some(copy) = self.find_copy_for_hold(&hold)? {
-                        hold_items.push(copy["barcode"].as_str().unwrap().to_string());
+                        hold_items.push(copy.str_at("barcode")?.to_string());
                     }
                 } else if let Some(title) = self.find_title_for_hold(&hold)? {
                     hold_items.push(title);
@@ -596,7 +658,7 @@ impl Session {
             return self.editor().retrieve("acp", copy_id);
         }
 
-        let hold_type = hold["hold_type"].as_str().unwrap(); // required
+        let hold_type = hold.str_at("hold_type")?; // required
         let hold_target = hold["target"].int()?;
 
         if hold_type.eq("C") || hold_type.eq("R") || hold_type.eq("F") {
@@ -653,36 +715,131 @@ impl Session {
         Ok(copies.pop())
     }
 
-    fn set_patron_summary_items(&mut self, patron: &mut Patron) -> EgResult<()> {
-        self.set_patron_hold_ids(patron, false, None, None)?;
-        self.set_patron_hold_ids(patron, true, None, None)?;
+    /// Collect the patron's balance owed and standing penalties, plus
+    /// the hold, circulation, and transaction summary counts when
+    /// `with_summary` is set.
+    ///
+    /// The queries do not depend on one another, so they are sent in
+    /// parallel.  Returns the patron's penalties.
+    fn fetch_patron_data(
+        &mut self,
+        patron: &mut Patron,
+        with_summary: bool,
+    ) -> EgResult<Vec<EgValue>> {
+        let penalty_query = self.patron_penalties_query(patron.id);
 
-        if let Some(summary) = self.editor().retrieve("ocirclist", patron.id)? {
-            // overdue and out are packaged as comma-separated ID values.
-            let overdue: Vec<i64> = summary["overdue"]
-                .as_str()
-                .unwrap()
-                .split(',')
-                .map(|id| id.parse::<i64>().unwrap())
-                .filter(|id| id > &0)
-                .collect();
+        let mut batch = self.editor().batch();
 
-            let outs: Vec<i64> = summary["out"]
-                .as_str()
-                .unwrap()
-                .split(',')
-                .map(|id| id.parse::<i64>().unwrap())
-                .filter(|id| id > &0)
-                .collect();
+        let balance_idx = batch.retrieve("mous", patron.id)?;
+        let penalty_idx = batch.json_query(penalty_query)?;
+
+        let summary_idx = if with_summary {
+            Some(batch.json_query(self.patron_summary_query(patron))?)
+        } else {
+            None
+        };
+
+        let mut results = batch.run()?;
+
+        if let Some(summary) = results[balance_idx].take() {
+            patron.balance_owed = summary["balance_owed"].float()?;
+        }
+
+        let penalties = Session::batch_list(&mut results, penalty_idx)?;
+
+        if let Some(idx) = summary_idx {
+            let rows = Session::batch_list(&mut results, idx)?;
+            Session::set_patron_summary(patron, rows)?;
+        }
+
+        Ok(penalties)
+    }
+
+    /// Take the list response for an atomic query from a set of
+    /// batch results.
+    fn batch_list(results: &mut [Option<EgValue>], idx: usize) -> EgResult<Vec<EgValue>> {
+        results[idx]
+            .take()
+            .and_then(|mut v| v.take_vec())
+            .ok_or_else(|| "Unexpected response to batched query".into())
+    }
+
+    /// Query for the IDs behind every Patron Information summary count
+    /// (holds, unavailable holds, items out, overdue items, and fines)
+    /// in a single UNION ALL pass.
+    ///
+    /// JSON queries cannot select literal values, so each branch tags
+    /// its IDs with concat(), producing one "ids" value per row of the
+    /// form "<comma-separated IDs>:<kind>".
+    fn patron_summary_query(&self, patron: &Patron) -> EgValue {
+        let tagged = |class: &str, column: &str, kind: &str, filter: EgValue| {
+            let mut query = eg::hash! {
+                "select": {},
+                "from": class,
+                "where": {},
+            };
+
+            query["select"][class] = eg::array! [{
+                "column": column,
+                "transform": "concat",
+                "params": [format!(":{kind}")],
+                "alias": "ids",
+            }];
+
+            query["where"][&format!("+{class}")] = filter;
+            query
+        };
 
-            patron.items_overdue_count = overdue.len();
-            patron.items_out_count = outs.len() + overdue.len();
-            patron.items_overdue_ids = overdue;
-            patron.items_out_ids = outs;
+        let (xact_search, _) = self.patron_xacts_query(patron, None);
+        let circ_filter = eg::hash! {"usr": patron.id};
+
+        let queries = vec![
+            tagged("ahr", "id", "hold", self.patron_holds_filter(patron, false)),
+            tagged(
+                "ahr",
+                "id",
+                "unavail",
+                self.patron_holds_filter(patron, true),
+            ),
+            tagged("ocirclist", "out", "out", circ_filter.clone()),
+            tagged("ocirclist", "overdue", "overdue", circ_filter),
+            tagged("mbts", "id", "fine", xact_search),
+        ];
+
+        eg::hash! {
+            "union": queries,
+            "all": true,
         }
+    }
+
+    /// Apply the rows returned by patron_summary_query().
+    fn set_patron_summary(patron: &mut Patron, rows: Vec<EgValue>) -> EgResult<()> {
+        for row in rows {
+            let value = row["ids"].str()?;
 
-        let summaries = self.get_patron_xacts(patron, None)?;
-        patron.fine_count = summaries.len();
+            let (ids, kind) = value
+                .rsplit_once(':')
+                .ok_or_else(|| format!("Invalid patron summary value: {value}"))?;
+
+            let ids = ids
+                .split(',')
+                .filter_map(|id| id.parse::<i64>().ok())
+                .filter(|id| *id > 0);
+
+            match kind {
+                "hold" => patron.hold_ids.extend(ids),
+                "unavail" => patron.unavail_hold_ids.extend(ids),
+                "out" => patron.items_out_ids.extend(ids),
+                "overdue" => patron.items_overdue_ids.extend(ids),
+                "fine" => patron.fine_count += ids.count(),
+                _ => return Err(format!("Invalid patron summary value: {value}").into()),
+            }
+        }
+
+        patron.holds_count = patron.hold_ids.len();
+        patron.unavail_holds_count = patron.unavail_hold_ids.len();
+        patron.items_overdue_count = patron.items_overdue_ids.len();
+        patron.items_out_count = patron.items_out_ids.len() + patron.items_overdue_ids.len();
 
         Ok(())
     }
@@ -692,6 +849,16 @@ impl Session {
         patron: &Patron,
         summary_ops: Option<&SummaryListOptions>,
     ) -> EgResult<Vec<EgValue>> {
+        let (search, ops) = self.patron_xacts_query(patron, summary_ops);
+        self.editor().search_with_ops("mbts", search, ops)
+    }
+
+    /// Search and query ops for the patron's open transactions.
+    fn patron_xacts_query(
+        &self,
+        patron: &Patron,
+        summary_ops: Option<&SummaryListOptions>,
+    ) -> (EgValue, EgValue) {
         let search = eg::hash! {
             "usr": patron.id,
             "balance_owed": {"<>": 0},
@@ -707,16 +874,11 @@ impl Session {
             ops["offset"] = EgValue::from(sum_ops.offset());
         }
 
-        self.editor().search_with_ops("mbts", search, ops)
+        (search, ops)
     }
 
-    fn set_patron_hold_ids(
-        &mut self,
-        patron: &mut Patron,
-        unavail: bool,
-        limit: Option<usize>,
-        offset: Option<usize>,
-    ) -> EgResult<()> {
+    /// Search filter for the patron's open holds.
+    fn patron_holds_filter(&self, patron: &Patron, unavail: bool) -> EgValue {
         let mut search = eg::hash! {
             "usr": patron.id,
             "fulfillment_time": EG_NULL,
@@ -732,41 +894,16 @@ impl Session {
             search["current_shelf_lib"] = eg::hash! {"=": {"+ahr": "pickup_lib"}};
         }
 
-        let mut query = eg::hash! {
-            "select": {"ahr": ["id"]},
-            "from": "ahr",
-            "where": {"+ahr": search},
-        };
-
-        if let Some(l) = limit {
-            query["limit"] = EgValue::from(l);
-        }
-        if let Some(o) = offset {
-            query["offset"] = EgValue::from(o);
-        }
-
-        let id_hash_list = self.editor().json_query(query)?;
-
-        for hash in id_hash_list {
-            let hold_id = hash.id()?;
-            if unavail {
-                patron.unavail_hold_ids.push(hold_id);
-            } else {
-                patron.hold_ids.push(hold_id);
-            }
-        }
-
-        if unavail {
-            patron.unavail_holds_count = patron.unavail_hold_ids.len();
-        } else {
-            patron.holds_count = patron.hold_ids.len();
-        }
-
-        Ok(())
+        search
     }
 
-    fn set_patron_privileges(&mut self, user: &EgValue, patron: &mut Patron) -> EgResult<()> {
-        let expire_date_str = user["expire_date"].as_str().unwrap(); // required
+    fn set_patron_privileges(
+        &mut self,
+        user: &EgValue,
+        patron: &mut Patron,
+        penalties: &Vec<EgValue>,
+    ) -> EgResult<()> {
+        let expire_date_str = user.str_at("expire_date")?; // required
         let expire_date = date::parse_datetime(expire_date_str)?;
 
         if expire_date < eg::date::now() {
@@ -786,10 +923,11 @@ impl Session {
             return Ok(());
         }
 
-        let penalties = self.get_patron_penalties(patron.id)?;
-
-        patron.max_fines = self.penalties_contain(1, &penalties)?; // PATRON_EXCEEDS_FINES
-        patron.max_overdue = self.penalties_contain(2, &penalties)?; // PATRON_EXCEEDS_OVERDUE_COUNT
+        patron.max_fines = self.penalties_contain(penalty::PATRON_EXCEEDS_FINES, penalties)?;
+        patron.max_overdue =
+            self.penalties_contain(penalty::PATRON_EXCEEDS_OVERDUE_COUNT, penalties)?;
+        patron.max_charged =
+            self.penalties_contain(penalty::PATRON_EXCEEDS_CHECKOUT_COUNT, penalties)?;
         patron.card_active = user["card"]["active"].boolish();
 
         let blocked = user["barred"].boolish() || !user["active"].boolish() || !patron.card_active;
@@ -839,10 +977,11 @@ impl Session {
         Ok(false)
     }
 
-    fn get_patron_penalties(&mut self, user_id: i64) -> EgResult<Vec<EgValue>> {
+    /// Query for the patron's penalties which apply at our workstation org.
+    fn patron_penalties_query(&mut self, user_id: i64) -> EgValue {
         let ws_org = self.editor().perm_org();
 
-        let search = eg::hash! {
+        eg::hash! {
             select: {csp: ["id", "block_list"]},
             from: {ausp: "csp"},
             where: {
@@ -867,12 +1006,14 @@ impl Session {
                     }
                 }
             }
-        };
-
-        self.editor().json_query(search)
+        }
     }
 
     fn get_user(&mut self, barcode: &str) -> EgResult<Option<EgValue>> {
+        if let Some(user) = self.cached_lookup("au", barcode)? {
+            return Ok(Some(user));
+        }
+
         let search = eg::hash! {"barcode": barcode};
 
         let flesh = eg::hash! {
@@ -894,24 +1035,72 @@ impl Session {
         let mut user = cards[0]["usr"].take();
         user["card"] = cards.remove(0);
 
+        self.cache_lookup("au", barcode, &user)?;
+
         Ok(Some(user))
     }
 
-    fn check_password(&mut self, user_id: i64, password_op: Option<&str>) -> EgResult<bool> {
+    /// Verify the patron's password, honoring any failed login
+    /// throttling.
+    ///
+    /// Failures are counted per patron barcode and per SIP account,
+    /// since each self-check device logs in with its own account.
+    /// Locked out patrons and accounts fail verification without the
+    /// password being checked.
+    fn check_password(
+        &mut self,
+        barcode: &str,
+        user_id: i64,
+        password_op: Option<&str>,
+    ) -> EgResult<bool> {
         let password = match password_op {
             Some(p) => p,
             None => return Ok(false),
         };
 
+        let patron_ident = format!("sip2:patron:{barcode}");
+        let source_ident = format!("sip2:account:{}", self.sip_account()["sip_username"].str()?);
+
+        // A successful login clears the patron's failures, but says
+        // nothing about other patrons using the same device.
+        let throttles = [
+            (self.config().patron_login_throttle(), patron_ident, true),
+            (self.config().source_login_throttle(), source_ident, false),
+        ];
+
+        for (throttle, ident, _) in throttles.iter() {
+            let Some(throttle) = throttle else { continue };
+
+            if let Some(secs) = throttle.locked_out(ident)? {
+                log::warn!(
+                    "ACT:{self} password check refused for {ident}; locked out for {secs} more seconds"
+                );
+                return Ok(false);
+            }
+        }
+
         log::debug!("{self} verifying password for user ID {user_id}");
-        eg::common::user::verify_migrated_password(self.editor(), user_id, password, false)
+        let verified =
+            eg::common::user::verify_migrated_password(self.editor(), user_id, password, false)?;
+
+        for (throttle, ident, clear_on_success) in throttles.iter() {
+            let Some(throttle) = throttle else { continue };
+
+            if !verified {
+                throttle.record_failure(ident)?;
+            } else if *clear_on_success {
+                throttle.clear(ident)?;
+            }
+        }
+
+        Ok(verified)
     }
 
     pub fn handle_patron_status(&mut self, sip_msg: sip2::Message) -> EgResult<sip2::Message> {
         let barcode = sip_msg.get_field_value("AA").unwrap_or("");
         let password_op = sip_msg.get_field_value("AD"); // optional
 
-        let patron_op = self.get_patron_details(barcode, password_op, None)?;
+        let patron_op = self.get_patron_status(barcode, password_op)?;
 
         self.patron_response_common("24", barcode, patron_op.as_ref())
     }
@@ -940,16 +1129,13 @@ impl Session {
 
         // Position of the "Y" value, of which there should only be 1,
         // indicates which type of extra summary data to include.
-        let list_type = match summary_ff.value().find('Y') {
-            Some(idx) => match idx {
-                0 => SummaryListType::HoldItems,
-                1 => SummaryListType::OverdueItems,
-                2 => SummaryListType::ChargedItems,
-                3 => SummaryListType::FineItems,
-                5 => SummaryListType::UnavailHoldItems,
-                _ => SummaryListType::Unsupported,
-            },
-            None => SummaryListType::Unsupported,
+        let list_type = match SummaryIndex::try_from(summary_ff.value()) {
+            Ok(SummaryIndex::HoldItems) => SummaryListType::HoldItems,
+            Ok(SummaryIndex::OverdueItems) => SummaryListType::OverdueItems,
+            Ok(SummaryIndex::ChargedItems) => SummaryListType::ChargedItems,
+            Ok(SummaryIndex::FineItems) => SummaryListType::FineItems,
+            Ok(SummaryIndex::UnavailableHolds) => SummaryListType::UnavailHoldItems,
+            _ => SummaryListType::Unsupported,
         };
 
         let list_ops = SummaryListOptions {
@@ -974,6 +1160,18 @@ impl Session {
         resp.maybe_add_field("PI", patron.net_access.as_deref());
         resp.maybe_add_field("PC", patron.profile.as_deref());
 
+        if let Some(max) = patron.limits.max_overdue {
+            resp.add_field("CA", &sip2::util::sip_count4(max as usize));
+        }
+
+        if let Some(max) = patron.limits.max_items_out {
+            resp.add_field("CB", &sip2::util::sip_count4(max as usize));
+        }
+
+        if let Some(max) = patron.limits.max_fines {
+            resp.add_field("CC", &self.config().format_amount(max));
+        }
+
         if let Some(detail_items) = patron.detail_items {
             let code = match list_type {
                 SummaryListType::HoldItems => "AS",
@@ -990,7 +1188,7 @@ impl Session {
         Ok(resp)
     }
 
-    fn patron_response_common(
+    pub fn patron_response_common(
         &mut self,
         msg_code: &str,
         barcode: &str,
@@ -1006,7 +1204,7 @@ impl Session {
                 msg_code,
                 &[
                     "YYYY          ", // patron status
-                    "000",            // language
+                    Language::Unknown.into(),
                     &sipdate,
                     "0000", // holds count
                     "0000", // overdue count
@@ -1037,7 +1235,7 @@ impl Session {
             sbool(patron.recall_denied),
             sbool(patron.holds_denied),
             sbool(!patron.card_active),
-            " ", // max charged
+            sbool(patron.max_charged),
             sbool(patron.max_overdue),
             " ", // max renewals
             " ", // max claims returned
@@ -1048,18 +1246,11 @@ impl Session {
             sbool(patron.max_fines)
         );
 
-        let cur_set = self.config().settings().get("currency");
-        let currency = if let Some(cur) = cur_set {
-            cur.str()?
-        } else {
-            "USD"
-        };
-
         let mut resp = sip2::Message::from_values(
             msg_code,
             &[
                 &summary,
-                "000", // language
+                self.language(),
                 &sipdate,
                 &sip2::util::sip_count4(patron.holds_count),
                 &sip2::util::sip_count4(patron.items_overdue_count),
@@ -1072,22 +1263,45 @@ impl Session {
                 ("AO", self.config().institution()),
                 ("AA", barcode),
                 ("AE", &patron.name),
-                ("BH", currency),
+                ("BH", self.config().currency()),
                 ("BL", sip2::util::sip_bool(true)), // valid patron
-                ("BV", &format!("{:.2}", patron.balance_owed)),
+                ("BV", &self.config().format_amount(patron.balance_owed)),
                 ("CQ", sip2::util::sip_bool(patron.password_verified)),
                 ("XI", &format!("{}", patron.id)),
             ],
         )
         .unwrap();
 
-        resp.maybe_add_field("AF", patron.screen_msg.as_deref());
+        let key = if patron.screen_msg.is_some() {
+            "patron.blocked"
+        } else {
+            "patron.ok"
+        };
+
+        let template = self.screen_message(
+            &[key],
+            &[
+                ("patron_name", &patron.name),
+                ("amount", &self.config().format_amount(patron.balance_owed)),
+            ],
+        );
+
+        if let Some(t) = template {
+            resp.maybe_add_field("AF", t.screen().or(patron.screen_msg.as_deref()));
+            resp.maybe_add_field("AG", t.print());
+        } else {
+            resp.maybe_add_field("AF", patron.screen_msg.as_deref());
+        }
+
         resp.maybe_add_field("BD", patron.address.as_deref());
         resp.maybe_add_field("BE", patron.email.as_deref());
 
         Ok(resp)
     }
 
+    /// Mark a patron's card inactive and apply the "patron_block_penalty"
+    /// standing penalty, noting the blocked card message (AL), e.g. when
+    /// a security gate detects a card which should not be in use.
     pub fn handle_block_patron(&mut self, sip_msg: sip2::Message) -> EgResult<sip2::Message> {
         let barcode = sip_msg.get_field_value("AA").unwrap_or("");
         let block_msg_op = sip_msg.get_field_value("AL");
@@ -1097,6 +1311,8 @@ impl Session {
             None => return self.patron_response_common("24", barcode, None),
         };
 
+        let penalty_type = self.config().patron_block_penalty();
+
         if !patron.card_active {
             log::info!("{self} patron {barcode} is already inactive");
             return self.patron_response_common("24", barcode, Some(&patron));
@@ -1111,7 +1327,14 @@ impl Session {
 
         self.editor().xact_begin()?;
 
+        let original = card.clone();
         card["active"] = "f".into();
+
+        match eg::diff::diff(&original, &card) {
+            Ok(changes) => log::info!("ACT:{self} blocking patron: {changes}"),
+            Err(e) => log::warn!("{self} cannot describe patron block changes: {e}"),
+        }
+
         self.editor().update(card)?;
 
         let penalty = eg::blessed! {
@@ -1120,7 +1343,7 @@ impl Session {
             "org_unit": self.editor().perm_org(),
             "set_date": "now",
             "staff": self.editor().requestor_id().unwrap(),
-            "standing_penalty": 20, // ALERT_NOTE
+            "standing_penalty": penalty_type,
         }?;
 
         let msg = self
@@ -1171,9 +1394,187 @@ impl Session {
 
         // Update our patron so the response data can indicate the
         // card is now inactive.
+        self.uncache_lookup("au", barcode)?;
         let patron = self.get_patron_details(barcode, None, None)?.unwrap();
 
         // SIP message 01 wants a message 24 (patron status) response.
         self.patron_response_common("24", barcode, Some(&patron))
     }
+
+    /// Reactivate a patron's card and archive the standing penalties
+    /// listed in the "patron_enable_penalties" setting, e.g. once the
+    /// conditions behind a Block Patron have been cleared.
+    ///
+    /// Only accounts with the "patron_enable" setting may send this
+    /// message; see Config::allows_message().
+    pub fn handle_patron_enable(&mut self, sip_msg: sip2::Message) -> EgResult<sip2::Message> {
+        let barcode = sip_msg.get_field_value("AA").unwrap_or("");
+        let password_op = sip_msg.get_field_value("AD"); // optional
+
+        let patron = match self.get_patron_details(barcode, password_op, None)? {
+            Some(p) => p,
+            None => return self.patron_response_common("26", barcode, None),
+        };
+
+        if password_op.is_some() && !patron.password_verified {
+            log::info!("{self} patron enable refused; invalid password for {barcode}");
+            return self.patron_response_common("26", barcode, Some(&patron));
+        }
+
+        let penalty_types = self.config().patron_enable_penalties();
+        let logtag = self.to_string();
+
+        self.editor().in_transaction(|e| {
+            if !patron.card_active {
+                let mut card = e
+                    .search("ac", eg::hash! {"barcode": barcode})?
+                    .pop()
+                    // should not be able to get here.
+                    .ok_or_else(|| "Patron card search returned nothing".to_string())?;
+
+                let original = card.clone();
+                card["active"] = "t".into();
+
+                match eg::diff::diff(&original, &card) {
+                    Ok(changes) => log::info!("ACT:{logtag} enabling patron: {changes}"),
+                    Err(e) => log::warn!("{logtag} cannot describe patron enable changes: {e}"),
+                }
+
+                e.update(card)?;
+            }
+
+            if penalty_types.is_empty() {
+                return Ok(());
+            }
+
+            let query = eg::hash! {
+                "usr": patron.id,
+                "standing_penalty": penalty_types,
+                "-or": [
+                    {"stop_date": EG_NULL},
+                    {"stop_date": {">": "now"}},
+                ],
+            };
+
+            for mut penalty in e.search("ausp", query)? {
+                log::info!(
+                    "ACT:{logtag} archiving penalty {} for patron {barcode}",
+                    penalty.id()?
+                );
+
+                penalty["stop_date"] = "now".into();
+                e.update(penalty)?;
+            }
+
+            Ok(())
+        })?;
+
+        // Reload our patron so the response reflects the cleared blocks.
+        self.uncache_lookup("au", barcode)?;
+
+        let mut patron = self
+            .get_patron_details(barcode, None, None)?
+            .ok_or_else(|| format!("Patron {barcode} disappeared"))?;
+
+        // Avoid checking the password twice.
+        patron.password_verified = password_op.is_some();
+
+        // SIP message 25 wants a message 26 (patron enable) response.
+        self.patron_response_common("26", barcode, Some(&patron))
+    }
+
+    /// Change a patron's password via the custom Patron Password Change
+    /// (81) message, once the patron's current password (AD) verifies.
+    ///
+    /// The new password (XP) must match the "patron_password_regex"
+    /// setting, when set.
+    pub fn handle_patron_password_change(
+        &mut self,
+        sip_msg: sip2::Message,
+    ) -> EgResult<sip2::Message> {
+        let barcode = sip_msg.get_field_value("AA").unwrap_or("");
+        let password_op = sip_msg.get_field_value("AD");
+        let new_password = sip_msg.get_field_value("XP").unwrap_or("");
+
+        let patron = match self.get_patron_details(barcode, password_op, None)? {
+            Some(p) => p,
+            None => return Ok(self.password_change_response(barcode, Some("invalid_patron"))),
+        };
+
+        if !patron.password_verified {
+            log::info!("ACT:{self} password change refused; invalid password for {barcode}");
+            return Ok(self.password_change_response(barcode, Some("invalid_password")));
+        }
+
+        if !self.new_password_allowed(new_password) {
+            log::info!(
+                "ACT:{self} password change refused; new password not allowed for {barcode}"
+            );
+            return Ok(self.password_change_response(barcode, Some("invalid_new_password")));
+        }
+
+        self.editor()
+            .in_transaction(|e| eg::common::user::change_password(e, patron.id, new_password))?;
+
+        log::info!("ACT:{self} changed password for patron {barcode}");
+
+        Ok(self.password_change_response(barcode, None))
+    }
+
+    /// True if the new password is non-empty and matches any
+    /// configured "patron_password_regex".
+    ///
+    /// No password is allowed when the configured pattern is invalid.
+    fn new_password_allowed(&self, password: &str) -> bool {
+        if password.is_empty() {
+            return false;
+        }
+
+        match self.config().patron_password_regex() {
+            None => true,
+            Some(Ok(regex)) => regex.is_match(password),
+            Some(Err(e)) => {
+                log::error!("{self} {e}");
+                false
+            }
+        }
+    }
+
+    /// Patron Password Change Response (82), with any screen message
+    /// configured for the failure reason, e.g.
+    /// "patron_password_change.invalid_password".
+    fn password_change_response(&self, barcode: &str, failure: Option<&str>) -> sip2::Message {
+        let mut resp = sip2::Message::from_values(
+            "82",
+            &[
+                sip2::util::num_bool(failure.is_none()),
+                &sip2::util::sip_date_now(),
+            ],
+            &[("AA", barcode), ("AO", self.config().institution())],
+        )
+        .unwrap();
+
+        let (specific_key, generic_key) = match failure {
+            Some(f) => (
+                format!("patron_password_change.{f}"),
+                "patron_password_change.failure",
+            ),
+            None => (
+                "patron_password_change.success".to_string(),
+                "patron_password_change.success",
+            ),
+        };
+
+        let template = self.screen_message(
+            &[&specific_key, generic_key],
+            &[("patron_barcode", barcode)],
+        );
+
+        if let Some(t) = template {
+            resp.maybe_add_field("AF", t.screen());
+            resp.maybe_add_field("AG", t.print());
+        }
+
+        resp
+    }
 }
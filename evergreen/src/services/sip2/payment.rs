@@ -9,6 +9,9 @@ pub struct PaymentResult {
     success: bool,
     patron_barcode: String,
     screen_msg: Option<String>,
+    /// Screen message key suffix describing why the payment failed.
+    outcome: Option<String>,
+    amount: Option<String>,
 }
 
 impl PaymentResult {
@@ -16,6 +19,8 @@ impl PaymentResult {
         PaymentResult {
             success: false,
             screen_msg: None,
+            outcome: None,
+            amount: None,
             patron_barcode: patron_barcode.to_string(),
         }
     }
@@ -49,6 +54,8 @@ impl Session {
             }
         };
 
+        result.amount = Some(self.config().format_amount(pay_amount));
+
         // msg.fixed_fields()[1] contains the FeeType code, but we do
         // not support making payments toward transactions of specific
         // types.  Payments are made toward specific transactions (by
@@ -124,7 +131,31 @@ impl Session {
         )
         .unwrap();
 
-        resp.maybe_add_field("AF", result.screen_msg.as_deref());
+        let specific_key;
+        let generic_key;
+
+        if result.success {
+            specific_key = "payment.success".to_string();
+            generic_key = "payment.success";
+        } else {
+            specific_key = format!("payment.{}", result.outcome.as_deref().unwrap_or(""));
+            generic_key = "payment.failure";
+        }
+
+        let template = self.screen_message(
+            &[&specific_key, generic_key],
+            &[
+                ("amount", result.amount.as_deref().unwrap_or("")),
+                ("patron_barcode", &result.patron_barcode),
+            ],
+        );
+
+        if let Some(t) = template {
+            resp.maybe_add_field("AF", t.screen().or(result.screen_msg.as_deref()));
+            resp.maybe_add_field("AG", t.print());
+        } else {
+            resp.maybe_add_field("AF", result.screen_msg.as_deref());
+        }
 
         resp
     }
@@ -153,6 +184,7 @@ impl Session {
 
         if pay_amount > sum["balance_owed"].float()? {
             result.screen_msg = Some("Overpayment not allowed".to_string());
+            result.outcome = Some("overpayment".to_string());
             return Ok(Vec::new());
         }
 
@@ -175,6 +207,7 @@ impl Session {
 
         if xacts.is_empty() {
             result.screen_msg = Some("No transactions to pay".to_string());
+            result.outcome = Some("no_transactions".to_string());
             return Ok(payments);
         }
 
@@ -219,6 +252,7 @@ impl Session {
 
         if amount_remaining > 0.0 {
             result.screen_msg = Some("Overpayment not allowed".to_string());
+            result.outcome = Some("overpayment".to_string());
             // An overpayment results in no payments at all.
             return Ok(Vec::new());
         }
@@ -316,6 +350,7 @@ impl Session {
         let resp = resp.ok_or_else(|| "Payment API returned no response".to_string())?;
 
         if let Some(evt) = eg::event::EgEvent::parse(&resp) {
+            result.outcome = Some(evt.textcode().to_string());
             if let Some(d) = evt.desc() {
                 result.screen_msg = Some(d.to_string());
             } else {
@@ -1,11 +1,19 @@
+use crate::shared::{self, GroupConfig, OrgUnits};
+use chrono::{DateTime, FixedOffset};
 use eg::common::auth;
+use eg::common::template::Renderer;
+use eg::common::workstation;
 use eg::osrf::cache::Cache;
 use eg::Editor;
 use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
+use regex::Regex;
+use sip2::{FilterAction, SipFilter};
 use std::collections::HashMap;
 use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
 
 // TODO session auth caching and storage.
 //
@@ -32,6 +40,78 @@ use std::fmt;
 
 const CACHE_PFX: &str = "sip2";
 
+/// Standing penalty type applied by Block Patron when no
+/// "patron_block_penalty" is configured.  20 is ALERT_NOTE.
+const DEFAULT_PATRON_BLOCK_PENALTY: i64 = 20;
+
+/// Setting whose value is the label of another setting group (a
+/// "profile", e.g. "selfcheck" or "sorter") whose settings are applied
+/// first.  Any settings in the referring group override the profile's.
+const PROFILE_SETTING: &str = "profile";
+
+/// Profiles may themselves reference a profile up to this depth.
+const MAX_PROFILE_DEPTH: usize = 5;
+
+/// Every setting name understood by the SIP service.
+const KNOWN_SETTINGS: &[&str] = &[
+    PROFILE_SETTING,
+    "author_display_field",
+    "av_format",
+    "checkin_block_on_checked_out",
+    "checkin_holds_as_transits",
+    "checkin_notify_hold_ready",
+    "checkin_notify_hold_ready_granularity",
+    "checkin_only",
+    "checkin_override_all",
+    "checkin_skip_patron_lookup",
+    "checkout_allow_precat",
+    "checkout_override_all",
+    "currency",
+    "currency_decimal_places",
+    "currency_rounding",
+    "duplicate_request_window",
+    "due_date_format",
+    "due_date_formats",
+    "due_date_timezone",
+    "due_date_use_sip_date_format",
+    "item_info_alert_messages",
+    "item_info_copy_location",
+    "item_info_hold_queue_length",
+    "item_info_location_labels",
+    "language",
+    "lookup_cache_ttl",
+    "media_type_map",
+    "msg64_hold_datatype",
+    "msg64_hold_items_available",
+    "msg64_summary_datatype",
+    "patron_block_penalty",
+    "patron_enable",
+    "patron_enable_penalties",
+    "patron_inverse_pref_names",
+    "patron_login_failure_window",
+    "patron_login_lockout_time",
+    "patron_login_max_failures",
+    "patron_login_max_source_failures",
+    "patron_password_change",
+    "patron_password_regex",
+    "patron_status_permit_all",
+    "patron_status_permit_loans",
+    "precat_dummy_author",
+    "precat_dummy_title",
+    "print_templates",
+    "response_templates",
+    "screen_messages",
+    "sort_bin_field",
+    "sort_bin_rules",
+    "title_display_field",
+    "use_native_checkin",
+    "use_native_checkout",
+    "workstation_auto_register",
+];
+
+/// Prefixes of per-event settings, e.g. "checkout.override.COPY_IN_TRANSIT".
+const KNOWN_SETTING_PREFIXES: &[&str] = &["checkin.override.", "checkout.override."];
+
 /// Supported Messages (BX)
 ///
 /// By order of appearance in the INSTITUTION_SUPPORTS string:
@@ -53,30 +133,484 @@ const CACHE_PFX: &str = "sip2";
 /// renew all
 const INSTITUTION_SUPPORTS: &str = "YYYYYNYYYYYNNNYY";
 
+/// Supported Messages (BX) for accounts with the "patron_enable"
+/// setting: INSTITUTION_SUPPORTS plus patron enable.
+const PATRON_ENABLE_SUPPORTS: &str = "YYYYYNYYYYYNYNYY";
+
+/// Supported Messages (BX) for accounts with the "checkin_only"
+/// setting: checkin, acs status, login, and item information.
+const CHECKIN_ONLY_SUPPORTS: &str = "NNYNYNYNNNYNNNNN";
+
+/// Messages accepted from "checkin_only" accounts, in addition to
+/// login and SC status.
+const CHECKIN_ONLY_MESSAGES: &[&str] = &["09", "17", "XS"];
+
+/// Field that carries the sort bin in checkin responses unless
+/// overridden by the "sort_bin_field" setting.
+const DEFAULT_SORT_BIN_FIELD: &str = "CL";
+
 pub const DEFAULT_DUE_DATE_FORMAT: &str = "%F %T";
 
+/// Due date format value which selects 18-character SIP dates.
+pub const SIP_DUE_DATE_FORMAT: &str = "sip";
+
+/// Currency type (BH) used when none is configured.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Number of decimal places used for fee amounts when none is configured.
+const DEFAULT_CURRENCY_DECIMALS: usize = 2;
+
+/// How fee amounts are rounded to the configured number of decimal
+/// places before they are added to a SIP message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurrencyRounding {
+    /// Round half away from zero
+    HalfUp,
+    /// Round half to the nearest even value (banker's rounding)
+    HalfEven,
+    Floor,
+    Ceiling,
+    Truncate,
+}
+
+impl From<&str> for CurrencyRounding {
+    fn from(s: &str) -> CurrencyRounding {
+        match s.to_lowercase().as_str() {
+            "half_even" => Self::HalfEven,
+            "floor" => Self::Floor,
+            "ceiling" | "ceil" => Self::Ceiling,
+            "truncate" => Self::Truncate,
+            _ => Self::HalfUp,
+        }
+    }
+}
+
+impl CurrencyRounding {
+    /// Round a value to the requested number of decimal places.
+    pub fn round(&self, amount: f64, decimals: usize) -> f64 {
+        let factor = 10f64.powi(decimals as i32);
+
+        // Avoid float noise (e.g. 1.005 * 100 = 100.49999...) by first
+        // collapsing the shifted value to a sane precision.
+        let shifted = ((amount * factor) * 1e6).round() / 1e6;
+
+        let rounded = match self {
+            Self::HalfUp => shifted.round(),
+            Self::Floor => shifted.floor(),
+            Self::Ceiling => shifted.ceil(),
+            Self::Truncate => shifted.trunc(),
+            Self::HalfEven => {
+                let floor = shifted.floor();
+                let diff = shifted - floor;
+                if diff > 0.5 {
+                    floor + 1.0
+                } else if diff < 0.5 || floor % 2.0 == 0.0 {
+                    floor
+                } else {
+                    floor + 1.0
+                }
+            }
+        };
+
+        rounded / factor
+    }
+}
+
+/// Media type (CK) used when no mapping applies to an item.
+pub const DEFAULT_MEDIA_TYPE: &str = "001";
+
+/// SIP media type and magnetic media values to apply to items which
+/// match a configured circ modifier or copy location.
+///
+/// Values left unset fall through to the next matching source.
+#[derive(Debug, Clone, Default)]
+pub struct MediaMapping {
+    /// 3-character SIP media type code, e.g. "006" for video.
+    media_type: Option<String>,
+    magnetic_media: Option<bool>,
+}
+
+impl MediaMapping {
+    pub fn media_type(&self) -> Option<&str> {
+        self.media_type.as_deref()
+    }
+    pub fn magnetic_media(&self) -> Option<bool> {
+        self.magnetic_media
+    }
+
+    fn from_value(key: &str, value: &EgValue) -> MediaMapping {
+        let mut mapping = MediaMapping::default();
+
+        if let Some(mt) = value["media_type"].to_string() {
+            if mt.len() == 3 {
+                mapping.media_type = Some(mt);
+            } else {
+                log::warn!("Ignoring invalid SIP media type for '{key}': '{mt}'");
+            }
+        }
+
+        if !value["magnetic_media"].is_null() {
+            mapping.magnetic_media = Some(value["magnetic_media"].boolish());
+        }
+
+        mapping
+    }
+}
+
+/// Maps Evergreen copy attributes to SIP media type and magnetic
+/// media values.
+///
+/// Loaded from the "media_type_map" setting, e.g.
+///
+/// {
+///   "default": {"media_type": "001", "magnetic_media": false},
+///   "circ_modifier": {"DVD": {"media_type": "006", "magnetic_media": true}},
+///   "copy_location": {"Audio Books": {"media_type": "004"}}
+/// }
+///
+/// Copy location mappings take precedence over circ modifier mappings,
+/// which take precedence over the sip2_media_type and magnetic_media
+/// values stored on the circ modifier itself.
+#[derive(Debug, Default)]
+pub struct MediaTypeMap {
+    default: MediaMapping,
+    circ_modifiers: HashMap<String, MediaMapping>,
+    copy_locations: HashMap<String, MediaMapping>,
+}
+
+impl MediaTypeMap {
+    fn from_value(value: &EgValue) -> MediaTypeMap {
+        let mut map = MediaTypeMap::default();
+
+        if value["default"].is_object() {
+            map.default = MediaMapping::from_value("default", &value["default"]);
+        }
+
+        for (code, v) in value["circ_modifier"].entries() {
+            map.circ_modifiers
+                .insert(code.to_string(), MediaMapping::from_value(code, v));
+        }
+
+        for (name, v) in value["copy_location"].entries() {
+            map.copy_locations
+                .insert(name.to_string(), MediaMapping::from_value(name, v));
+        }
+
+        map
+    }
+
+    /// Returns the (media type, magnetic media) values for an item.
+    ///
+    /// `circ_modifier` is the fleshed circ modifier object, if any.
+    pub fn resolve(&self, circ_modifier: &EgValue, copy_location: &str) -> (String, bool) {
+        let mut media_type = None;
+        let mut magnetic = None;
+
+        let mut sources = Vec::new();
+
+        if let Some(m) = self.copy_locations.get(copy_location) {
+            sources.push(m);
+        }
+
+        if let Some(code) = circ_modifier["code"].as_str() {
+            if let Some(m) = self.circ_modifiers.get(code) {
+                sources.push(m);
+            }
+        }
+
+        for source in sources {
+            if media_type.is_none() {
+                media_type = source.media_type().map(|m| m.to_string());
+            }
+            if magnetic.is_none() {
+                magnetic = source.magnetic_media();
+            }
+        }
+
+        // Fall back to the values stored on the circ modifier.
+        if media_type.is_none() {
+            media_type = circ_modifier["sip2_media_type"]
+                .as_str()
+                .map(|m| m.to_string());
+        }
+
+        if magnetic.is_none() && circ_modifier.is_object() {
+            magnetic = Some(circ_modifier["magnetic_media"].boolish());
+        }
+
+        (
+            media_type
+                .or_else(|| self.default.media_type().map(|m| m.to_string()))
+                .unwrap_or(DEFAULT_MEDIA_TYPE.to_string()),
+            magnetic.or(self.default.magnetic_media()).unwrap_or(false),
+        )
+    }
+}
+
+/// SIP language code for "unknown", used to store screen message
+/// templates which apply to all languages.
+pub const DEFAULT_LANGUAGE: &str = "000";
+
+/// One rule for choosing the sort bin of a checked in item.
+///
+/// Every criterion present must match.  A rule with no criteria
+/// matches every item.
 #[derive(Debug)]
-pub struct SipFilter {
-    /// 2-character SIP field code.
-    identifier: String,
+pub struct SortBinRule {
+    bin: String,
+    /// Item was captured for a hold.
+    hold: Option<bool>,
+    /// Item is headed elsewhere, either in transit or for a hold
+    /// at another library.
+    transit: Option<bool>,
+    /// Shortname of the library the item is headed to.
+    destination: Option<String>,
+    /// Name of the item's shelving location.
+    location: Option<String>,
+}
+
+impl SortBinRule {
+    fn from_value(value: &EgValue) -> Option<SortBinRule> {
+        let Some(bin) = value["bin"].to_string() else {
+            log::warn!("SIP sort bin rule has no bin: {value}");
+            return None;
+        };
+
+        Some(SortBinRule {
+            bin,
+            hold: value["hold"].as_bool(),
+            transit: value["transit"].as_bool(),
+            destination: value["destination"].as_str().map(|v| v.to_string()),
+            location: value["location"].as_str().map(|v| v.to_string()),
+        })
+    }
+
+    fn matches(&self, item: &SortBinItem) -> bool {
+        self.hold.is_none_or(|h| h == item.hold)
+            && self.transit.is_none_or(|t| t == item.transit)
+            && self
+                .destination
+                .as_deref()
+                .is_none_or(|d| Some(d) == item.destination)
+            && self.location.as_deref().is_none_or(|l| l == item.location)
+    }
+}
+
+/// What we know about a checked in item for sort bin purposes.
+pub struct SortBinItem<'a> {
+    pub hold: bool,
+    pub transit: bool,
+    pub destination: Option<&'a str>,
+    pub location: &'a str,
+}
+
+/// Chooses the sort bin for checked in items, for automated
+/// material handling (AMH) sorters.
+///
+/// Loaded from the "sort_bin_rules" setting, a list of rules checked
+/// in order, where the first match wins, e.g.
+///
+/// [
+///   {"hold": true, "transit": false, "bin": "1"},
+///   {"destination": "BR2", "bin": "2"},
+///   {"transit": true, "bin": "3"},
+///   {"location": "Reference", "bin": "4"},
+///   {"bin": "9"}
+/// ]
+///
+/// The bin is returned in the "sort_bin_field" field, which defaults
+/// to CL.
+#[derive(Debug, Default)]
+pub struct SortBins {
+    rules: Vec<SortBinRule>,
+}
+
+impl SortBins {
+    fn from_value(value: &EgValue) -> SortBins {
+        SortBins {
+            rules: value
+                .members()
+                .filter_map(SortBinRule::from_value)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn find(&self, item: &SortBinItem) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|r| r.matches(item))
+            .map(|r| r.bin.as_str())
+    }
+}
+
+/// Patron-facing screen (AF) and print (AG) message text.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenMessage {
+    screen: Option<String>,
+    print: Option<String>,
+}
+
+impl ScreenMessage {
+    pub fn screen(&self) -> Option<&str> {
+        self.screen.as_deref()
+    }
+    pub fn print(&self) -> Option<&str> {
+        self.print.as_deref()
+    }
 
-    /// Remove the entire field
-    strip: bool,
+    /// A template may be a plain string, used for the screen message,
+    /// or an object with "AF" and/or "AG" values.
+    fn from_value(value: &EgValue) -> ScreenMessage {
+        if let Some(s) = value.as_str() {
+            return ScreenMessage {
+                screen: Some(s.to_string()),
+                print: None,
+            };
+        }
+
+        ScreenMessage {
+            screen: value["AF"].as_str().map(|s| s.to_string()),
+            print: value["AG"].as_str().map(|s| s.to_string()),
+        }
+    }
+
+    /// Replace {name} placeholders with their values.
+    fn render(&self, vars: &[(&str, &str)]) -> ScreenMessage {
+        let fill = |template: &String| {
+            let mut text = template.to_string();
+            for (name, value) in vars {
+                text = text.replace(&format!("{{{name}}}"), value);
+            }
+            text
+        };
+
+        ScreenMessage {
+            screen: self.screen.as_ref().map(fill),
+            print: self.print.as_ref().map(fill),
+        }
+    }
+}
 
-    /// Replace the content of the field with this value.
-    replace_with: Option<String>,
+/// Screen message templates keyed on SIP language code, then on
+/// message key.
+///
+/// Loaded from the "screen_messages" setting, e.g.
+///
+/// {
+///   "000": {
+///     "checkout.success": "Due {due_date}",
+///     "checkout.OPEN_CIRCULATION_EXISTS": {
+///       "AF": "{title} is already checked out",
+///       "AG": "Please see staff"
+///     }
+///   },
+///   "002": {"checkout.success": "A rendre le {due_date}"}
+/// }
+///
+/// Keys take the form "<action>.<outcome>", where outcome is either
+/// an Evergreen event textcode or one of the generic outcomes used
+/// by each handler, e.g. "success" or "failure".
+#[derive(Debug, Default)]
+pub struct ScreenMessages {
+    templates: HashMap<String, HashMap<String, ScreenMessage>>,
 }
 
-impl SipFilter {
-    pub fn identifier(&self) -> &str {
-        &self.identifier
+impl ScreenMessages {
+    fn from_value(value: &EgValue) -> ScreenMessages {
+        let mut messages = ScreenMessages::default();
+
+        for (lang, entries) in value.entries() {
+            let mut map = HashMap::new();
+            for (key, template) in entries.entries() {
+                map.insert(key.to_string(), ScreenMessage::from_value(template));
+            }
+            messages.templates.insert(lang.to_string(), map);
+        }
+
+        messages
+    }
+
+    fn find(&self, lang: &str, key: &str) -> Option<&ScreenMessage> {
+        self.templates.get(lang).and_then(|m| m.get(key))
     }
-    pub fn strip(&self) -> bool {
-        self.strip
+
+    /// Returns the rendered message for the first key which has a
+    /// template.
+    ///
+    /// Keys are tried in order, and for each key the requested language
+    /// is checked before the default language.  A specific key in the
+    /// default language therefore wins over a later, more generic key
+    /// in the requested language.
+    pub fn render(
+        &self,
+        keys: &[&str],
+        lang: Option<&str>,
+        vars: &[(&str, &str)],
+    ) -> Option<ScreenMessage> {
+        for key in keys {
+            if let Some(lang) = lang {
+                if let Some(m) = self.find(lang, key) {
+                    return Some(m.render(vars));
+                }
+            }
+            if let Some(m) = self.find(DEFAULT_LANGUAGE, key) {
+                return Some(m.render(vars));
+            }
+        }
+        None
     }
-    pub fn replace_with(&self) -> Option<&str> {
-        self.replace_with.as_deref()
+}
+
+/// Build filters from the "response_templates" setting, which maps
+/// response message codes to field templates.  Templates under "*"
+/// apply to all messages.  E.g.
+///
+/// {
+///   "18": [
+///     {"field": "CR", "set": "{item.collection_code}"},
+///     {"field": "ZV", "add": "{item.stat_cat.Vendor Code}"}
+///   ],
+///   "*": [{"field": "AG", "suppress": true}]
+/// }
+///
+/// Besides {value} and {field.XX}, values may refer to any item or
+/// patron values collected while handling the request, e.g.
+/// {item.call_number} or {patron.profile}.
+fn template_filters(value: &EgValue) -> Vec<SipFilter> {
+    let mut filters = Vec::new();
+
+    for (code, list) in value.entries() {
+        for template in list.members() {
+            let Some(field) = template["field"].as_str() else {
+                continue;
+            };
+
+            let action = if template["suppress"].boolish() {
+                FilterAction::Strip
+            } else if let Some(v) = template["set"].as_str() {
+                FilterAction::Set(v.to_string())
+            } else if let Some(v) = template["add"].as_str() {
+                FilterAction::Add(v.to_string())
+            } else {
+                log::warn!("Ignoring response template for {field} with no action");
+                continue;
+            };
+
+            let filter = SipFilter::new(field, action);
+
+            if code == "*" {
+                filters.push(filter);
+            } else {
+                filters.push(filter.for_message(code));
+            }
+        }
     }
+
+    filters
 }
 
 #[derive(Debug)]
@@ -85,6 +619,12 @@ pub struct Config {
     supports: &'static str,
     settings: HashMap<String, EgValue>,
     filters: Vec<SipFilter>,
+    media_types: MediaTypeMap,
+    screen_messages: ScreenMessages,
+    sort_bins: SortBins,
+    /// Compiled "patron_password_regex" setting, or the reason it
+    /// failed to compile.
+    patron_password_regex: Option<Result<Regex, String>>,
 }
 
 impl Config {
@@ -103,6 +643,74 @@ impl Config {
     pub fn filters(&self) -> &Vec<SipFilter> {
         &self.filters
     }
+    pub fn media_types(&self) -> &MediaTypeMap {
+        &self.media_types
+    }
+    pub fn screen_messages(&self) -> &ScreenMessages {
+        &self.screen_messages
+    }
+    pub fn sort_bins(&self) -> &SortBins {
+        &self.sort_bins
+    }
+
+    /// Field which carries the sort bin in checkin responses.
+    pub fn sort_bin_field(&self) -> &str {
+        self.settings
+            .get("sort_bin_field")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_SORT_BIN_FIELD)
+    }
+
+    /// True if this account may send the provided message type.
+    ///
+    /// Accounts with the "checkin_only" setting, e.g. sorters, may
+    /// only check items in and look them up.  Only accounts with the
+    /// "patron_enable" setting may reactivate patrons, and only
+    /// accounts with the "patron_password_change" setting may change
+    /// patron passwords.
+    pub fn allows_message(&self, code: &str) -> bool {
+        if code == sip2::spec::M_PATRON_ENABLE.code && !self.setting_is_true("patron_enable") {
+            return false;
+        }
+
+        if code == sip2::spec::M_PATRON_PWD_CHANGE.code
+            && !self.setting_is_true("patron_password_change")
+        {
+            return false;
+        }
+
+        !self.setting_is_true("checkin_only") || CHECKIN_ONLY_MESSAGES.contains(&code)
+    }
+
+    /// Pattern new patron passwords must match when changed via SIP,
+    /// from the "patron_password_regex" setting, e.g. "^\\d{4,8}$"
+    /// for a 4 to 8 digit PIN.
+    ///
+    /// The pattern is compiled when the config is loaded.  Returns
+    /// Err with the reason if the configured pattern is invalid.
+    pub fn patron_password_regex(&self) -> Option<Result<&Regex, &str>> {
+        self.patron_password_regex
+            .as_ref()
+            .map(|r| r.as_ref().map_err(|e| e.as_str()))
+    }
+
+    /// Compile the "patron_password_regex" setting, if any.
+    fn compile_password_regex(
+        settings: &HashMap<String, EgValue>,
+    ) -> Option<Result<Regex, String>> {
+        let value = settings.get("patron_password_regex")?;
+
+        let Some(pattern) = value.as_str() else {
+            return Some(Err(format!(
+                "patron_password_regex must be a string: {value}"
+            )));
+        };
+
+        Some(
+            Regex::new(pattern)
+                .map_err(|e| format!("Invalid patron_password_regex '{pattern}': {e}")),
+        )
+    }
 
     pub fn setting_is_true(&self, name: &str) -> bool {
         if let Some(val) = self.settings.get(name) {
@@ -111,16 +719,196 @@ impl Config {
             false
         }
     }
+
+    /// A/T granularity of the hold pickup notification events created
+    /// when a checkin puts a hold on the shelf, when the
+    /// "checkin_notify_hold_ready" setting is true.
+    ///
+    /// With a granularity, only matching event definitions are used,
+    /// so a dedicated definition may be processed by a frequently
+    /// running A/T runner without duplicating the regular notices.
+    pub fn hold_ready_granularity(&self) -> Option<&str> {
+        self.settings
+            .get("checkin_notify_hold_ready_granularity")
+            .and_then(|v| v.as_str())
+    }
+
+    /// Currency type code (BH) for this institution.
+    pub fn currency(&self) -> &str {
+        self.settings
+            .get("currency")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    /// Number of decimal places used in fee amount fields.
+    pub fn currency_decimals(&self) -> usize {
+        self.settings
+            .get("currency_decimal_places")
+            .and_then(|v| v.as_usize())
+            .unwrap_or(DEFAULT_CURRENCY_DECIMALS)
+    }
+
+    pub fn currency_rounding(&self) -> CurrencyRounding {
+        match self
+            .settings
+            .get("currency_rounding")
+            .and_then(|v| v.as_str())
+        {
+            Some(r) => r.into(),
+            None => CurrencyRounding::HalfUp,
+        }
+    }
+
+    /// SIP language code used when the client does not specify one.
+    pub fn default_language(&self) -> &str {
+        self.settings
+            .get("language")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    /// Due date strftime pattern for a SIP language code.
+    ///
+    /// Language-specific formats come from the "due_date_formats"
+    /// setting, e.g. {"002": "%d/%m/%Y"}, where formats for the default
+    /// language ("000") apply to all languages without their own format.
+    /// Otherwise, the "due_date_format" setting applies.
+    ///
+    /// The special format "sip" produces 18-character SIP dates.
+    pub fn due_date_format(&self, lang: &str) -> &str {
+        if let Some(formats) = self.settings.get("due_date_formats") {
+            if let Some(f) = formats[lang]
+                .as_str()
+                .or(formats[DEFAULT_LANGUAGE].as_str())
+            {
+                return f;
+            }
+        }
+
+        if self.setting_is_true("due_date_use_sip_date_format") {
+            return SIP_DUE_DATE_FORMAT;
+        }
+
+        self.settings
+            .get("due_date_format")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_DUE_DATE_FORMAT)
+    }
+
+    /// Time zone due dates are displayed in, e.g. "America/New_York".
+    ///
+    /// When unset, due dates use the time zone returned by the ILS.
+    pub fn due_date_timezone(&self) -> Option<&str> {
+        self.settings
+            .get("due_date_timezone")
+            .and_then(|v| v.as_str())
+    }
+
+    /// Seconds to cache patron, org unit, and item lookups within a
+    /// SIP session.  Zero, the default, disables caching.
+    ///
+    /// Cached patrons are only refreshed by the session's own checkouts,
+    /// checkins, and blocks, so fines, holds, and penalties applied
+    /// elsewhere may be stale for up to this many seconds.
+    pub fn lookup_cache_ttl(&self) -> u32 {
+        self.settings
+            .get("lookup_cache_ttl")
+            .and_then(|v| v.as_usize())
+            .and_then(|v| u32::try_from(v).ok())
+            .unwrap_or(0)
+    }
+
+    /// Seconds to remember the response to each Checkout and Fee Paid
+    /// request, so a retransmitted request gets the same response
+    /// instead of being processed again.  Zero disables the journal.
+    pub fn duplicate_request_window(&self) -> u32 {
+        let Some(value) = self.settings.get("duplicate_request_window") else {
+            return 0;
+        };
+
+        match value.as_usize().and_then(|v| u32::try_from(v).ok()) {
+            Some(v) => v,
+            None => {
+                log::warn!("Ignoring invalid duplicate_request_window: {value}");
+                0
+            }
+        }
+    }
+
+    /// Standing penalty type applied by Block Patron, with the
+    /// blocked card message stored as the penalty note.
+    pub fn patron_block_penalty(&self) -> i64 {
+        self.settings
+            .get("patron_block_penalty")
+            .and_then(|v| v.as_int())
+            .unwrap_or(DEFAULT_PATRON_BLOCK_PENALTY)
+    }
+
+    /// Standing penalty types archived by Patron Enable, from the
+    /// "patron_enable_penalties" setting, e.g. [20] to clear the
+    /// ALERT_NOTE penalties applied by Block Patron.
+    pub fn patron_enable_penalties(&self) -> Vec<i64> {
+        match self.settings.get("patron_enable_penalties") {
+            Some(v) if v.is_array() => v.members().filter_map(|p| p.as_int()).collect(),
+            Some(v) => v.as_int().into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Failed patron password throttling per patron barcode, when the
+    /// "patron_login_max_failures" setting is non-zero.
+    pub fn patron_login_throttle(&self) -> Option<auth::LoginThrottle> {
+        self.login_throttle("patron_login_max_failures")
+    }
+
+    /// Failed patron password throttling per SIP account (i.e. per
+    /// device), when the "patron_login_max_source_failures" setting
+    /// is non-zero.
+    pub fn source_login_throttle(&self) -> Option<auth::LoginThrottle> {
+        self.login_throttle("patron_login_max_source_failures")
+    }
+
+    fn login_throttle(&self, max_setting: &str) -> Option<auth::LoginThrottle> {
+        let value = |name: &str| self.settings.get(name).cloned().unwrap_or(EgValue::Null);
+
+        auth::LoginThrottle::from_value(&eg::hash! {
+            "max_failures": value(max_setting),
+            "failure_window": value("patron_login_failure_window"),
+            "lockout_time": value("patron_login_lockout_time"),
+        })
+    }
+
+    /// Format a monetary amount for a SIP fee amount field (e.g. BV)
+    /// using the configured decimal places and rounding mode.
+    pub fn format_amount(&self, amount: f64) -> String {
+        let decimals = self.currency_decimals();
+        let rounded = self.currency_rounding().round(amount, decimals);
+        format!("{rounded:.decimals$}")
+    }
 }
 
 pub struct Session {
     editor: Editor,
     seskey: String,
     sip_account: EgValue,
-    config: Config,
+
+    /// Config for our setting group, shared with other sessions.
+    group: Arc<GroupConfig>,
+
+    /// Org units shared with other sessions, current as of the last
+    /// config reload.
+    org_units: Arc<OrgUnits>,
 
     /// Any time we encounter a new org unit, add it here.
     org_cache: HashMap<i64, EgValue>,
+
+    /// SIP language code most recently sent by the client.
+    language: Option<String>,
+
+    /// Values collected while handling the current request for use
+    /// by response templates.
+    response_vars: HashMap<String, String>,
 }
 
 impl fmt::Display for Session {
@@ -137,19 +925,55 @@ impl fmt::Display for Session {
 impl Session {
     pub fn new(editor: &Editor, seskey: &str, sip_account: EgValue) -> EgResult<Self> {
         let mut editor = editor.clone();
-        let config = Session::load_config(&mut editor, sip_account["setting_group"].int()?)?;
-
-        log::debug!("Session {seskey} loaded config: {:?}", config);
+        let group = shared::config(&mut editor, sip_account["setting_group"].int()?)?;
+        let org_units = shared::org_units(&mut editor)?;
 
         Ok(Session {
             seskey: seskey.to_string(),
             editor,
             sip_account,
-            config,
+            group,
+            org_units,
             org_cache: HashMap::new(),
+            language: None,
+            response_vars: HashMap::new(),
         })
     }
 
+    /// Compile the templates from the "print_templates" setting,
+    /// keyed on screen message key, e.g.
+    ///
+    /// {"checkout.success": "{{ title }}\nDue: {{ due_date }}"}
+    ///
+    /// Invalid templates are logged and skipped.
+    pub fn load_print_templates(
+        editor: &mut Editor,
+        config: &Config,
+    ) -> EgResult<Option<Renderer>> {
+        let Some(templates) = config.settings().get("print_templates") else {
+            return Ok(None);
+        };
+
+        let mut renderer = Renderer::with_org_names(editor)?;
+
+        for (key, body) in templates.entries() {
+            let Some(body) = body.as_str() else {
+                log::warn!("SIP print template {key} is not a string");
+                continue;
+            };
+
+            if let Err(e) = renderer.add_template(key, body) {
+                log::warn!("Skipping SIP print template: {e}");
+            }
+        }
+
+        Ok(Some(renderer))
+    }
+
+    pub fn org_units(&self) -> &OrgUnits {
+        &self.org_units
+    }
+
     pub fn org_cache(&self) -> &HashMap<i64, EgValue> {
         &self.org_cache
     }
@@ -158,6 +982,120 @@ impl Session {
         &mut self.org_cache
     }
 
+    /// Make a value available to response filters as {name}.
+    ///
+    /// No-op when no filters are configured.
+    pub fn set_response_var(&mut self, name: &str, value: &str) {
+        if !self.config().filters().is_empty() {
+            self.response_vars
+                .insert(name.to_string(), value.to_string());
+        }
+    }
+
+    /// Apply the account's filters to an outgoing message.
+    pub fn apply_filters(&self, msg: &mut sip2::Message) {
+        for filter in self.config().filters() {
+            filter.apply(msg, |name| self.response_vars.get(name).cloned());
+        }
+    }
+
+    /// SIP language code for responses.
+    ///
+    /// This is the last known language sent by the client, falling
+    /// back to the account's default language.
+    pub fn language(&self) -> &str {
+        self.language
+            .as_deref()
+            .unwrap_or(self.config().default_language())
+    }
+
+    /// Apply a language code sent by the client.
+    ///
+    /// Returns true if the session language changed.  Unknown ("000")
+    /// and malformed codes are ignored.
+    pub fn set_language(&mut self, language: &str) -> bool {
+        if language.len() != 3 || language == DEFAULT_LANGUAGE {
+            return false;
+        }
+
+        if self.language.as_deref() == Some(language) {
+            return false;
+        }
+
+        self.language = Some(language.to_string());
+        true
+    }
+
+    /// Render the configured screen message for the first matching key
+    /// in the session language.
+    ///
+    /// When a print template exists for any of the keys, it replaces
+    /// the print line (AG) of the screen message.  Print templates
+    /// see `vars` plus "institution" and "language" as template values.
+    pub fn screen_message(&self, keys: &[&str], vars: &[(&str, &str)]) -> Option<ScreenMessage> {
+        let mut message = self
+            .config()
+            .screen_messages()
+            .render(keys, Some(self.language()), vars);
+
+        if let Some(print) = self.render_print_line(keys, vars) {
+            message.get_or_insert_with(ScreenMessage::default).print = Some(print);
+        }
+
+        message
+    }
+
+    /// Render the print template for the first matching key.
+    fn render_print_line(&self, keys: &[&str], vars: &[(&str, &str)]) -> Option<String> {
+        let renderer = self.group.print_templates.as_ref()?;
+        let key = keys.iter().find(|k| renderer.has_template(k))?;
+
+        let mut context = eg::hash! {
+            "institution": self.config().institution(),
+            "language": self.language(),
+        };
+
+        for (name, value) in vars {
+            context[*name] = EgValue::from(*value);
+        }
+
+        match renderer.render(key, &context) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                log::warn!("{self} {e}");
+                None
+            }
+        }
+    }
+
+    /// Format a due date for a SIP response using the account's due
+    /// date format and time zone.
+    pub fn format_due_date(&self, due_dt: &DateTime<FixedOffset>) -> String {
+        let mut due_dt = *due_dt;
+
+        if let Some(tz) = self.config().due_date_timezone() {
+            match eg::date::set_timezone(due_dt, tz) {
+                Ok(dt) => due_dt = dt,
+                Err(e) => log::warn!("{self} invalid due_date_timezone: {e}"),
+            }
+        }
+
+        let format = self.config().due_date_format(self.language());
+
+        if format == SIP_DUE_DATE_FORMAT {
+            sip2::util::sip_date_from_dt(&due_dt)
+        } else {
+            // Invalid strftime patterns produce a formatting error,
+            // which would cause to_string() to panic.
+            let mut formatted = String::new();
+            if write!(formatted, "{}", due_dt.format(format)).is_err() {
+                log::warn!("{self} invalid due date format: {format}");
+                return due_dt.format(DEFAULT_DUE_DATE_FORMAT).to_string();
+            }
+            formatted
+        }
+    }
+
     pub fn editor(&mut self) -> &mut Editor {
         &mut self.editor
     }
@@ -171,10 +1109,10 @@ impl Session {
     }
 
     pub fn config(&self) -> &Config {
-        &self.config
+        &self.group.config
     }
 
-    fn load_config(editor: &mut Editor, setting_group: i64) -> EgResult<Config> {
+    pub fn load_config(editor: &mut Editor, setting_group: i64) -> EgResult<Config> {
         let flesh = eg::hash! {
             "flesh": 1,
             "flesh_fields": {
@@ -191,30 +1129,158 @@ impl Session {
             supports: INSTITUTION_SUPPORTS,
             settings: HashMap::new(),
             filters: Vec::new(),
+            media_types: MediaTypeMap::default(),
+            screen_messages: ScreenMessages::default(),
+            sort_bins: SortBins::default(),
+            patron_password_regex: None,
         };
 
-        for setting in group["settings"].members() {
-            config.settings.insert(
-                setting["name"].string()?,
-                EgValue::parse(setting["value"].str()?)?,
-            );
+        Session::load_settings(editor, &group, &mut config.settings, 0)?;
+
+        config.patron_password_regex = Config::compile_password_regex(&config.settings);
+
+        if let Some(Err(e)) = config.patron_password_regex.as_ref() {
+            log::error!("SIP setting group {setting_group}: {e}; refusing password changes");
+        }
+
+        if let Some(map) = config.settings.get("media_type_map") {
+            config.media_types = MediaTypeMap::from_value(map);
+        }
+
+        if let Some(msgs) = config.settings.get("screen_messages") {
+            config.screen_messages = ScreenMessages::from_value(msgs);
+        }
+
+        if let Some(rules) = config.settings.get("sort_bin_rules") {
+            config.sort_bins = SortBins::from_value(rules);
+        }
+
+        if config.setting_is_true("checkin_only") {
+            config.supports = CHECKIN_ONLY_SUPPORTS;
+        } else if config.setting_is_true("patron_enable") {
+            config.supports = PATRON_ENABLE_SUPPORTS;
         }
 
         for filter in group["filters"].members() {
             if filter["enabled"].boolish() {
-                let f = SipFilter {
-                    identifier: filter["identifier"].string()?,
-                    strip: filter["strip"].boolish(),
-                    replace_with: filter["replace_with"].as_str().map(|v| v.to_string()),
+                let action = if filter["strip"].boolish() {
+                    FilterAction::Strip
+                } else if let Some(v) = filter["replace_with"].as_str() {
+                    FilterAction::Replace(v.to_string())
+                } else {
+                    continue;
                 };
 
-                config.filters.push(f);
+                let identifier = filter["identifier"].string()?;
+                config.filters.push(SipFilter::new(&identifier, action));
             }
         }
 
+        // Templates run after the setting group filters.
+        if let Some(templates) = config.settings.get("response_templates") {
+            config.filters.extend(template_filters(templates));
+        }
+
         Ok(config)
     }
 
+    /// Add the settings for a setting group to `settings`, starting
+    /// with the settings of its profile group, if any.
+    ///
+    /// * `group` - Setting group fleshed with its settings.
+    fn load_settings(
+        editor: &mut Editor,
+        group: &EgValue,
+        settings: &mut HashMap<String, EgValue>,
+        depth: usize,
+    ) -> EgResult<()> {
+        let mut local = HashMap::new();
+
+        for setting in group["settings"].members() {
+            local.insert(
+                setting["name"].string()?,
+                EgValue::parse(setting["value"].str()?)?,
+            );
+        }
+
+        if let Some(label) = local.remove(PROFILE_SETTING) {
+            if depth >= MAX_PROFILE_DEPTH {
+                return Err(format!(
+                    "SIP setting group {} exceeds the max profile depth of {MAX_PROFILE_DEPTH}",
+                    group["label"]
+                )
+                .into());
+            }
+
+            let profile = Session::find_profile(editor, &label)?;
+            Session::load_settings(editor, &profile, settings, depth + 1)?;
+        }
+
+        settings.extend(local);
+
+        Ok(())
+    }
+
+    /// Find a profile setting group by label, fleshed with its settings.
+    fn find_profile(editor: &mut Editor, label: &EgValue) -> EgResult<EgValue> {
+        let label = label
+            .as_str()
+            .ok_or_else(|| format!("SIP '{PROFILE_SETTING}' setting must be a string: {label}"))?;
+
+        let flesh = eg::hash! {
+            "flesh": 1,
+            "flesh_fields": {"sipsetg": ["settings"]}
+        };
+
+        editor
+            .search_with_ops("sipsetg", eg::hash! {"label": label}, flesh)?
+            .pop()
+            .ok_or_else(|| format!("No SIP setting group (profile) with label '{label}'").into())
+    }
+
+    /// Verify every SIP setting group uses only known setting names
+    /// and references profiles that exist.
+    ///
+    /// Returns an Err describing every problem found.
+    pub fn validate_setting_groups(editor: &mut Editor) -> EgResult<()> {
+        let flesh = eg::hash! {
+            "flesh": 1,
+            "flesh_fields": {"sipsetg": ["settings"]}
+        };
+
+        let groups =
+            editor.search_with_ops("sipsetg", eg::hash! {"id": {"!=": EgValue::Null}}, flesh)?;
+
+        let mut problems = Vec::new();
+
+        for group in groups.iter() {
+            let label = &group["label"];
+
+            for setting in group["settings"].members() {
+                let name = setting["name"].str()?;
+
+                if let Err(e) = validate_setting_name(name) {
+                    problems.push(format!("Setting group '{label}': {e}"));
+                }
+            }
+
+            let mut settings = HashMap::new();
+            if let Err(e) = Session::load_settings(editor, group, &mut settings, 0) {
+                problems.push(format!("Setting group '{label}': {e}"));
+            }
+
+            if let Some(Err(e)) = Config::compile_password_regex(&settings) {
+                problems.push(format!("Setting group '{label}': {e}"));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; ").into())
+        }
+    }
+
     /// Load the session from the cache by session key.
     pub fn from_cache(editor: &mut Editor, seskey: &str) -> EgResult<Option<Session>> {
         let mut cached = match Cache::get_global(&format!("{CACHE_PFX}:{seskey}"))? {
@@ -233,6 +1299,7 @@ impl Session {
 
         let mut session = Session::new(editor, seskey, sip_account)?;
         session.editor.set_authtoken(auth_token);
+        session.language = cached["language"].as_str().map(|l| l.to_string());
 
         // Make sure our auth session is still valid and set the 'requestor'
         // value on our editor.
@@ -253,6 +1320,7 @@ impl Session {
         let cache_val = eg::hash! {
             "sip_account": self.sip_account.clone(),
             "ils_token": authtoken,
+            "language": self.language.as_deref(),
         };
 
         // Cache the session using the default max cache time.
@@ -263,6 +1331,136 @@ impl Session {
         Cache::del_global(&format!("{CACHE_PFX}:{}", self.seskey))
     }
 
+    /// Remove a previously cached session and end its ILS auth session.
+    ///
+    /// Used when a client logs in again on an established connection.
+    /// Returns the language of the removed session, if any, so it
+    /// may be carried over to the new session.
+    pub fn end_cached(editor: &Editor, seskey: &str) -> EgResult<Option<String>> {
+        let key = format!("{CACHE_PFX}:{seskey}");
+
+        let cached = match Cache::get_global(&key)? {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        log::info!(
+            "Session {seskey} replacing login for {}",
+            cached["sip_account"]["sip_username"].as_str().unwrap_or("")
+        );
+
+        if let Some(token) = cached["ils_token"].as_str() {
+            let mut editor = editor.clone();
+            editor.set_authtoken(token);
+            editor.clear_auth()?;
+        }
+
+        Cache::del_global(&key)?;
+
+        Ok(cached["language"].as_str().map(|l| l.to_string()))
+    }
+
+    /// Lookup keys are hashed so barcodes are not stored in the clear.
+    fn lookup_cache_key(&self, kind: &str, key: &str) -> String {
+        format!("{CACHE_PFX}:{}:{kind}:{:x}", self.seskey, md5::compute(key))
+    }
+
+    /// Returns a value previously stored via cache_lookup(), provided
+    /// its time-to-live has not yet expired.
+    ///
+    /// Lookups are cached in the global cache instead of the Session
+    /// itself, since each SIP message may be handled by a different
+    /// worker.
+    pub fn cached_lookup(&self, kind: &str, key: &str) -> EgResult<Option<EgValue>> {
+        if self.config().lookup_cache_ttl() == 0 {
+            return Ok(None);
+        }
+
+        let value = Cache::get_global(&self.lookup_cache_key(kind, key))?;
+
+        if value.is_some() {
+            log::debug!("{self} using cached {kind} {key}");
+        }
+
+        Ok(value)
+    }
+
+    /// Cache a looked-up value for this SIP session.
+    pub fn cache_lookup(&self, kind: &str, key: &str, value: &EgValue) -> EgResult<()> {
+        let ttl = self.config().lookup_cache_ttl();
+
+        if ttl == 0 {
+            return Ok(());
+        }
+
+        Cache::set_global_for(&self.lookup_cache_key(kind, key), value.clone(), ttl)
+    }
+
+    /// Remove a cached value, e.g. after the underlying data changes.
+    pub fn uncache_lookup(&self, kind: &str, key: &str) -> EgResult<()> {
+        if self.config().lookup_cache_ttl() == 0 {
+            return Ok(());
+        }
+
+        Cache::del_global(&self.lookup_cache_key(kind, key))
+    }
+
+    /// Find or register a workstation for this account and terminal,
+    /// when the account has no workstation and the
+    /// "workstation_auto_register" setting is enabled.
+    ///
+    /// Workstations are named after the SIP user's home org unit,
+    /// the SIP username, and the terminal location code, if any,
+    /// e.g. "BR1-sip-kiosk-LOBBY".  Call before refresh_auth_token()
+    /// so the ILS auth session uses the workstation.
+    pub fn auto_register_workstation(&mut self, location: Option<&str>) -> EgResult<()> {
+        if self.sip_account["workstation"].is_blessed()
+            || !self.config().setting_is_true("workstation_auto_register")
+        {
+            return Ok(());
+        }
+
+        let user_id = self.sip_account["usr"].int()?;
+
+        let user = self
+            .editor
+            .retrieve("au", user_id)?
+            .ok_or_else(|| self.editor.die_event())?;
+
+        let org_id = user["home_ou"].int()?;
+
+        let shortname = self
+            .org_from_id(org_id)?
+            .ok_or_else(|| format!("No such org unit: {org_id}"))?["shortname"]
+            .string()?;
+
+        let mut name = format!(
+            "{shortname}-sip-{}",
+            self.sip_account["sip_username"].str()?
+        );
+
+        if let Some(loc) = location.filter(|l| !l.is_empty()) {
+            name += &format!("-{loc}");
+        }
+
+        let result = self
+            .editor
+            .in_transaction(|e| workstation::find_or_register(e, &name, org_id));
+
+        let ws = match result {
+            Ok(ws) => ws,
+            Err(e) => {
+                // A concurrent login for the same terminal may have
+                // registered the workstation first.
+                workstation::by_name(&mut self.editor, &name)?.ok_or(e)?
+            }
+        };
+
+        self.sip_account["workstation"] = ws;
+
+        Ok(())
+    }
+
     /// Get a new authtoken from the ILS.
     ///
     /// This is necessary when creating a new session or when a session
@@ -289,3 +1487,90 @@ impl Session {
         }
     }
 }
+
+/// Returns Err with a suggested replacement if the setting name is
+/// not one we know about.
+fn validate_setting_name(name: &str) -> Result<(), String> {
+    if KNOWN_SETTINGS.contains(&name) || KNOWN_SETTING_PREFIXES.iter().any(|p| name.starts_with(p))
+    {
+        return Ok(());
+    }
+
+    let closest = KNOWN_SETTINGS
+        .iter()
+        .map(|known| (edit_distance(name, known), known))
+        .min_by_key(|(dist, _)| *dist);
+
+    match closest {
+        // Only suggest names which are a plausible typo.
+        Some((dist, known)) if dist <= 3 => {
+            Err(format!("Unknown setting '{name}'; did you mean '{known}'?"))
+        }
+        _ => Err(format!("Unknown setting '{name}'")),
+    }
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+
+        prev = cur;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item<'a>(hold: bool, transit: bool, destination: Option<&'a str>) -> SortBinItem<'a> {
+        SortBinItem {
+            hold,
+            transit,
+            destination,
+            location: "Stacks",
+        }
+    }
+
+    #[test]
+    fn sort_bins() {
+        let rules = eg::array! [
+            {"hold": true, "transit": false, "bin": "1"},
+            {"destination": "BR2", "bin": 2},
+            {"transit": true, "bin": "3"},
+            {"location": "Reference", "bin": "4"},
+            {"hold": true},
+        ];
+
+        let bins = SortBins::from_value(&rules);
+
+        // Rules without a bin are skipped.
+        assert!(!bins.is_empty());
+        assert_eq!(bins.rules.len(), 4);
+
+        assert_eq!(bins.find(&item(true, false, None)), Some("1"));
+        assert_eq!(bins.find(&item(true, true, Some("BR2"))), Some("2"));
+        assert_eq!(bins.find(&item(false, true, Some("BR3"))), Some("3"));
+        assert_eq!(bins.find(&item(false, false, None)), None);
+
+        let mut reference = item(false, false, None);
+        reference.location = "Reference";
+        assert_eq!(bins.find(&reference), Some("4"));
+
+        let catch_all = eg::array! [{"bin": "9"}];
+        let bins = SortBins::from_value(&catch_all);
+        assert_eq!(bins.find(&item(false, false, None)), Some("9"));
+
+        assert!(SortBins::from_value(&EgValue::Null).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! SIP data shared by all workers in a process.
+//!
+//! Org units and setting group configs rarely change, so they are
+//! loaded once per process and shared by every worker instead of being
+//! fetched for each request.  A setting group's config is reloaded when
+//! a SIP client logs in with one of its accounts, and the org units are
+//! reloaded on next use, so changes apply without a restart.
+use crate::session::{Config, Session};
+use eg::common::template::Renderer;
+use eg::Client;
+use eg::Editor;
+use eg::EgResult;
+use eg::EgValue;
+use evergreen as eg;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// Org units keyed on ID.
+pub type OrgUnits = HashMap<i64, EgValue>;
+
+/// Org units shared by all sessions.  Cleared by reload_config() so
+/// sessions pick up org unit changes along with settings changes.
+static ORG_UNITS: Mutex<Option<Arc<OrgUnits>>> = Mutex::new(None);
+
+/// Setting group configs keyed on setting group ID.
+static GROUP_CONFIGS: OnceLock<Mutex<HashMap<i64, Arc<GroupConfig>>>> = OnceLock::new();
+
+/// Keeps workers which start together from each loading the same data.
+static WARM_UP_LOCK: Mutex<()> = Mutex::new(());
+
+/// A setting group's config along with its compiled print templates.
+pub struct GroupConfig {
+    pub config: Config,
+    pub print_templates: Option<Renderer>,
+}
+
+fn group_configs() -> &'static Mutex<HashMap<i64, Arc<GroupConfig>>> {
+    GROUP_CONFIGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Load the org units and every setting group config, unless another
+/// worker has already done so.
+///
+/// Called as each worker starts so the first SIP request handled by
+/// the worker does not pay the cost of loading them.
+pub fn warm_up(client: &Client) -> EgResult<()> {
+    let _guard = WARM_UP_LOCK
+        .lock()
+        .map_err(|e| format!("SIP warm-up lock poisoned: {e}"))?;
+
+    if org_units_lock()?.is_some() {
+        return Ok(());
+    }
+
+    let mut editor = Editor::new(client);
+
+    for group in editor.search("sipsetg", eg::hash! {"id": {"!=": EgValue::Null}})? {
+        reload_config(&mut editor, group.id()?)?;
+    }
+
+    // After the configs, since each reload clears the org units.
+    org_units(&mut editor)?;
+
+    Ok(())
+}
+
+fn org_units_lock() -> EgResult<MutexGuard<'static, Option<Arc<OrgUnits>>>> {
+    ORG_UNITS
+        .lock()
+        .map_err(|e| format!("SIP org unit lock poisoned: {e}").into())
+}
+
+/// Org units, loaded on first use and after each reload_config().
+pub fn org_units(editor: &mut Editor) -> EgResult<Arc<OrgUnits>> {
+    if let Some(orgs) = org_units_lock()?.as_ref() {
+        return Ok(orgs.clone());
+    }
+
+    let mut orgs = HashMap::new();
+    for org in editor.search("aou", eg::hash! {"id": {"!=": EgValue::Null}})? {
+        orgs.insert(org.id()?, org);
+    }
+
+    log::info!("SIP loaded {} org units", orgs.len());
+
+    let orgs = Arc::new(orgs);
+    *org_units_lock()? = Some(orgs.clone());
+
+    Ok(orgs)
+}
+
+/// Config for a setting group, loaded on first use.
+pub fn config(editor: &mut Editor, setting_group: i64) -> EgResult<Arc<GroupConfig>> {
+    let cached = group_configs()
+        .lock()
+        .map_err(|e| format!("SIP config lock poisoned: {e}"))?
+        .get(&setting_group)
+        .cloned();
+
+    match cached {
+        Some(group) => Ok(group),
+        None => reload_config(editor, setting_group),
+    }
+}
+
+/// Load a setting group's config from the database, replacing any
+/// shared copy.
+pub fn reload_config(editor: &mut Editor, setting_group: i64) -> EgResult<Arc<GroupConfig>> {
+    let config = Session::load_config(editor, setting_group)?;
+
+    log::debug!("SIP setting group {setting_group} loaded config: {config:?}");
+
+    let print_templates = Session::load_print_templates(editor, &config)?;
+
+    let group = Arc::new(GroupConfig {
+        config,
+        print_templates,
+    });
+
+    group_configs()
+        .lock()
+        .map_err(|e| format!("SIP config lock poisoned: {e}"))?
+        .insert(setting_group, group.clone());
+
+    // Org units are reloaded on next use.
+    *org_units_lock()? = None;
+
+    Ok(group)
+}
@@ -1,4 +1,6 @@
 use crate::session::Session;
+use eg::common::bib::{self, BibDisplay, BibDisplayFields};
+use eg::constants as C;
 use eg::result::EgResult;
 use eg::EgValue;
 use evergreen as eg;
@@ -6,97 +8,96 @@ use evergreen as eg;
 const PATRON_NAME_PARTS: [&str; 3] = ["first_given_name", "second_given_name", "family_name"];
 
 impl Session {
-    /// Extract the title and author info from a copy object.
+    /// Collect the title, author, etc. for a copy.
     ///
-    /// Assumes copy is fleshed to the bib with flat_display_entries.
-    pub fn get_copy_title_author(
+    /// With `marc_fallback`, values missing from the display fields
+    /// are read from the MARC record.
+    ///
+    /// Assumes copy is fleshed to its call number.
+    pub fn get_copy_display(
         &mut self,
         copy: &EgValue,
-    ) -> EgResult<(Option<String>, Option<String>)> {
-        let mut resp = (None, None);
-
-        if copy["call_number"].id()? == -1 {
-            if let Some(title) = copy["dummy_title"].as_str() {
-                resp.0 = Some(title.to_string());
-            }
-            if let Some(author) = copy["dummy_author"].as_str() {
-                resp.1 = Some(author.to_string());
-            }
-
-            return Ok(resp);
-        }
-
-        let title_setting = "title_display_field";
-        let author_setting = "author_display_field";
-
-        let mut title_field_setting = None;
-        let mut author_field_setting = None;
-
-        if let Some(Some(field)) = self
-            .config()
-            .settings()
-            .get(title_setting)
-            .map(|v| v.as_str())
-        {
-            title_field_setting = Some(field.to_string());
-        }
-
-        if let Some(Some(field)) = self
-            .config()
-            .settings()
-            .get(author_setting)
-            .map(|v| v.as_str())
-        {
-            author_field_setting = Some(field.to_string());
+        marc_fallback: bool,
+    ) -> EgResult<BibDisplay> {
+        if copy["call_number"].id()? == C::PRECAT_CALL_NUMBER {
+            return Ok(BibDisplay::from_precat_copy(copy));
         }
 
-        let title_field = title_field_setting.as_deref().unwrap_or("title");
-        let author_field = author_field_setting.as_deref().unwrap_or("author");
-
-        let query = eg::hash! {
-            "source": copy["call_number"]["record"].int()?,
-            "name": [title_field, author_field],
+        let mut fields = BibDisplayFields {
+            marc_fallback,
+            ..Default::default()
         };
 
-        let mut entries = self.editor().search("mfde", query)?;
+        if let Some(field) = self.config().settings().get("title_display_field") {
+            if let Some(f) = field.as_str() {
+                fields.title = f.to_string();
+            }
+        }
 
-        for entry in entries.iter_mut() {
-            if entry["name"].str()? == title_field {
-                resp.0 = entry["value"].take_string();
-            } else if entry["name"].str()? == author_field {
-                resp.1 = entry["value"].take_string();
+        if let Some(field) = self.config().settings().get("author_display_field") {
+            if let Some(f) = field.as_str() {
+                fields.author = f.to_string();
             }
         }
 
-        Ok(resp)
+        let bib_id = copy["call_number"]["record"].int()?;
+
+        bib::bib_display(self.editor(), bib_id, &fields)
     }
 
     /// Get an org unit (by cache or net) via its ID.
     pub fn org_from_id(&mut self, id: i64) -> EgResult<Option<&EgValue>> {
-        if self.org_cache().contains_key(&id) {
-            return Ok(self.org_cache().get(&id));
+        if self.org_units().contains_key(&id) {
+            return Ok(self.org_units().get(&id));
         }
 
-        if let Some(org) = self.editor().retrieve("aou", id)? {
-            self.org_cache_mut().insert(id, org);
+        if self.org_cache().contains_key(&id) {
             return Ok(self.org_cache().get(&id));
         }
 
-        Ok(None)
+        let org = match self.cached_lookup("aou", &id.to_string())? {
+            Some(o) => o,
+            None => match self.editor().retrieve("aou", id)? {
+                Some(o) => {
+                    self.cache_lookup("aou", &id.to_string(), &o)?;
+                    o
+                }
+                None => return Ok(None),
+            },
+        };
+
+        self.org_cache_mut().insert(id, org);
+        Ok(self.org_cache().get(&id))
     }
 
     /// Get an org unit (by cache or net) via its shortname.
     pub fn org_from_sn(&mut self, sn: &str) -> EgResult<Option<&EgValue>> {
+        let shared_id = self
+            .org_units()
+            .iter()
+            .find(|(_, org)| org["shortname"].as_str() == Some(sn))
+            .map(|(id, _)| *id);
+
+        if let Some(id) = shared_id {
+            return Ok(self.org_units().get(&id));
+        }
+
         for (id, org) in self.org_cache() {
-            if org["shortname"].as_str().unwrap().eq(sn) {
+            if org.str_at("shortname")?.eq(sn) {
                 return Ok(self.org_cache().get(id));
             }
         }
 
+        if let Some(id) = self.cached_lookup("aou_sn", sn)? {
+            return self.org_from_id(id.int()?);
+        }
+
         let mut orgs = self.editor().search("aou", eg::hash! {"shortname": sn})?;
 
         if let Some(org) = orgs.pop() {
             let id = org.id()?;
+            self.cache_lookup("aou_sn", sn, &EgValue::from(id))?;
+            self.cache_lookup("aou", &id.to_string(), &org)?;
             self.org_cache_mut().insert(id, org);
             return Ok(self.org_cache().get(&id));
         }
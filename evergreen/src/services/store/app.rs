@@ -132,6 +132,13 @@ impl Application for RsStoreApplication {
 
         methods.push(json_query.into_method(APPNAME));
 
+        let explain = methods::METHODS
+            .iter()
+            .find(|m| m.name.eq("json_query.explain"))
+            .unwrap();
+
+        methods.push(explain.into_method(APPNAME));
+
         log::info!("{APPNAME} registered {} total methods", methods.len());
 
         Ok(methods)
@@ -1,4 +1,5 @@
 use eg::common::jq::JsonQueryCompiler;
+use eg::db;
 use eg::idl;
 use eg::idldb::{FleshDef, IdlClassSearch, Translator};
 use eg::osrf::app::ApplicationWorker;
@@ -6,6 +7,7 @@ use eg::osrf::message;
 use eg::osrf::method::{ParamCount, ParamDataType, StaticMethodDef, StaticParam};
 use eg::osrf::session::ServerSession;
 use eg::EgResult;
+use eg::EgValue;
 use evergreen as eg;
 use pg::types::ToSql;
 use postgres as pg;
@@ -124,6 +126,17 @@ pub static METHODS: &[StaticMethodDef] = &[
             desc: "JSON Query Object/Hash",
         }],
     },
+    StaticMethodDef {
+        name: "json_query.explain",
+        desc: "Returns the SQL and query plan generated for a JSON Query",
+        param_count: ParamCount::Exactly(1),
+        handler: json_query_explain,
+        params: &[StaticParam {
+            name: "query-object",
+            datatype: ParamDataType::Object,
+            desc: "JSON Query Object/Hash",
+        }],
+    },
 ];
 
 /// Get the IDL class info from the API call split into parts by ".".
@@ -168,11 +181,19 @@ pub fn retrieve(
     let translator = Translator::new(db);
 
     let mut flesh_def = None;
+    let mut for_update = false;
     if let Some(j) = method.params().get(1) {
         flesh_def = Some(FleshDef::from_eg_value(j)?);
+        for_update = j["for_update"].boolish();
     }
 
-    if let Some(obj) = translator.get_idl_object_by_pkey(&classname, pkey, flesh_def)? {
+    let obj = if for_update {
+        translator.lock_idl_object_by_pkey(&classname, pkey, flesh_def)?
+    } else {
+        translator.get_idl_object_by_pkey(&classname, pkey, flesh_def)?
+    };
+
+    if let Some(obj) = obj {
         session.respond(obj)
     } else {
         Ok(())
@@ -197,6 +218,7 @@ pub fn search(
 
     if let Some(j) = method.params().get(1) {
         search.flesh = Some(FleshDef::from_eg_value(j)?);
+        search.set_for_update(j["for_update"].boolish());
     }
 
     for value in translator.idl_class_search(&search)? {
@@ -303,30 +325,20 @@ pub fn json_query(
 
     let db = worker.database().clone();
 
-    let mut jq_compiler = JsonQueryCompiler::new();
-    jq_compiler.compile(query)?;
-
-    let sql = jq_compiler
-        .query_string()
-        .ok_or_else(|| format!("JSON query failed to produce valid SQL: {}", query.dump()))?;
+    let (sql, qparams) = compile_json_query(query)?;
 
     // Do a little translation dance here to get the param values
     // into a container our DB API can accept.
     let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
-    let qparams: Vec<String> = jq_compiler
-        .query_params()
-        .iter()
-        .map(|s| s.to_string())
-        .collect();
     for p in qparams.iter() {
         params.push(p);
     }
 
-    let query_res = db.borrow_mut().client().query(sql, &params);
+    let query_res = db.borrow_mut().client().query(&sql, &params);
 
     if let Err(ref e) = query_res {
         log::error!("DB Error: {e} query={query} param={params:?}");
-        Err("DB query failed. See error logs".to_string())?;
+        Err(db::query_error(e, "DB query failed. See error logs"))?;
     }
 
     for row in query_res.unwrap() {
@@ -341,3 +353,64 @@ pub fn json_query(
 
     Ok(())
 }
+
+/// Compile a JSON query into SQL and its parameter values.
+fn compile_json_query(query: &EgValue) -> EgResult<(String, Vec<String>)> {
+    let mut jq_compiler = JsonQueryCompiler::new();
+    jq_compiler.compile(query)?;
+
+    let sql = jq_compiler
+        .query_string()
+        .ok_or_else(|| format!("JSON query failed to produce valid SQL: {}", query.dump()))?;
+
+    let params = jq_compiler
+        .query_params()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    Ok((sql.to_string(), params))
+}
+
+/// Responds with the SQL generated for a JSON query along with the
+/// database's plan for running it.  The query itself is not run.
+pub fn json_query_explain(
+    worker: &mut Box<dyn ApplicationWorker>,
+    session: &mut ServerSession,
+    method: message::MethodCall,
+) -> EgResult<()> {
+    let worker = app::RsStoreWorker::downcast(worker)?;
+    let query = method.param(0);
+
+    let db = worker.database().clone();
+
+    let (sql, qparams) = compile_json_query(query)?;
+
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+    for p in qparams.iter() {
+        params.push(p);
+    }
+
+    let rows = db
+        .borrow_mut()
+        .client()
+        .query(&format!("EXPLAIN {sql}"), &params)
+        .map_err(|e| {
+            log::error!("DB Error: {e} query={query} param={params:?}");
+            db::query_error(&e, "DB query failed. See error logs")
+        })?;
+
+    let mut plan = Vec::new();
+    for row in rows {
+        plan.push(
+            row.try_get::<usize, String>(0)
+                .map_err(|e| format!("{e}"))?,
+        );
+    }
+
+    session.respond(eg::hash! {
+        "sql": sql,
+        "params": qparams,
+        "plan": plan,
+    })
+}
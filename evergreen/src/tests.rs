@@ -1,6 +1,12 @@
+use crate as eg;
+use crate::osrf::addr::BusAddress;
+use crate::osrf::conf::ConfigBuilder;
 use crate::osrf::message::Message;
+use crate::osrf::message::MessageStatus;
 use crate::osrf::message::Payload;
 use crate::osrf::message::TransportMessage;
+use crate::osrf::session::{self, RoutedRequest};
+use crate::EgValue;
 use json;
 
 const TRANSPORT_MSG_JSON: &str = r#"{
@@ -54,3 +60,336 @@ fn parse_opensrf_message() {
     let msg = msg_op.unwrap();
     assert_eq!(msg.ingress(), Some("opensrf"));
 }
+
+const FAILOVER_CONFIG_XML: &str = r#"<?xml version="1.0"?>
+<config>
+  <opensrf>
+    <domain>private.localhost</domain>
+    <username>opensrf</username>
+    <domain_failover>true</domain_failover>
+    <routers>
+      <router>
+        <name>router</name>
+        <domain>private.localhost</domain>
+      </router>
+      <router>
+        <name>router</name>
+        <domain>public.west</domain>
+        <services>
+          <service>open-ils.search</service>
+        </services>
+      </router>
+      <router>
+        <name>router</name>
+        <domain>private.east</domain>
+      </router>
+      <router>
+        <name>router</name>
+        <domain>private.north</domain>
+        <services>
+          <service>open-ils.actor</service>
+          <service>open-ils.circ</service>
+        </services>
+      </router>
+    </routers>
+  </opensrf>
+</config>
+"#;
+
+fn failover_domains(service: &str) -> Vec<String> {
+    let config = ConfigBuilder::from_xml_string(FAILOVER_CONFIG_XML)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    assert!(config.client().domain_failover());
+
+    session::failover_routers_for(config.client().routers(), "private.localhost", service)
+        .iter()
+        .map(|r| r.domain().to_string())
+        .collect()
+}
+
+#[test]
+fn failover_routers_order() {
+    // Config order, skipping our primary domain and routers which
+    // do not handle the service.
+    assert_eq!(
+        failover_domains("open-ils.circ"),
+        ["private.east", "private.north"]
+    );
+    assert_eq!(
+        failover_domains("open-ils.search"),
+        ["public.west", "private.east"]
+    );
+    assert_eq!(failover_domains("open-ils.pcrud"), ["private.east"]);
+}
+
+#[test]
+fn failover_retry_decision() {
+    let routers = [
+        BusAddress::for_router("router", "private.east"),
+        BusAddress::for_router("router", "private.north"),
+    ];
+
+    let tmsg = TransportMessage::new("to", "from", "thread");
+    let mut rr = RoutedRequest::new(3, tmsg, routers.iter().cloned().collect());
+
+    // Timeouts and failures may have reached a worker.  Not retried.
+    assert!(rr.next_router(3, &MessageStatus::Timeout).is_none());
+    assert!(rr
+        .next_router(3, &MessageStatus::InternalServerError)
+        .is_none());
+    assert!(rr.next_router(3, &MessageStatus::MethodNotFound).is_none());
+
+    // Refusals for other requests are not ours to retry.
+    assert!(rr.next_router(4, &MessageStatus::ServiceNotFound).is_none());
+
+    let next = rr.next_router(3, &MessageStatus::ServiceNotFound).unwrap();
+    assert_eq!(next.as_str(), routers[0].as_str());
+
+    let next = rr.next_router(3, &MessageStatus::ServiceNotFound).unwrap();
+    assert_eq!(next.as_str(), routers[1].as_str());
+
+    assert!(rr.next_router(3, &MessageStatus::ServiceNotFound).is_none());
+}
+
+#[test]
+fn caching_client_keys() {
+    use crate::osrf::client::CachingClient;
+
+    let key = |params: Vec<EgValue>| CachingClient::cache_key("open-ils.actor", "m", &params);
+
+    assert_ne!(
+        key(vec![EgValue::from(1), EgValue::from(23)]),
+        key(vec![EgValue::from(12), EgValue::from(3)])
+    );
+    assert_ne!(
+        key(vec![EgValue::from("a"), EgValue::from("b")]),
+        key(vec![EgValue::from("ab")])
+    );
+    assert_eq!(
+        key(vec![EgValue::from(1), EgValue::from(23)]),
+        key(vec![EgValue::from(1), EgValue::from(23)])
+    );
+    assert!(key(vec![]).starts_with("open-ils.actor m "));
+}
+
+#[test]
+fn transfer_concurrent_uploads() {
+    use crate::osrf::transfer::{self, ChunkEncoding};
+
+    let data = "Same payload, two uploads. ".repeat(20);
+    let list = transfer::chunks(data.as_bytes(), 0, 64, ChunkEncoding::Base64).unwrap();
+    assert!(list.len() > 1);
+
+    let ids = [
+        format!("test-{}-a", std::process::id()),
+        format!("test-{}-b", std::process::id()),
+    ];
+
+    let mut paths = Vec::new();
+
+    // Interleave the chunks of both uploads.
+    for chunk in list.iter() {
+        for id in ids.iter() {
+            let mut chunk = chunk.clone();
+            chunk["upload_id"] = EgValue::from(id.as_str());
+
+            let resp = transfer::receive_chunk(&chunk).unwrap();
+            let end = chunk["offset"].int().unwrap() + chunk["size"].int().unwrap();
+
+            assert_eq!(resp["received"].int().unwrap(), end);
+
+            if resp["complete"].boolish() {
+                paths.push(resp["path"].string().unwrap());
+            }
+        }
+    }
+
+    assert_eq!(paths.len(), 2);
+    assert_ne!(paths[0], paths[1]);
+
+    for path in paths {
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), data);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    let checksum = list[0]["file_checksum"].str().unwrap();
+    assert!(transfer::spool_path("../etc", checksum).is_err());
+    assert!(transfer::spool_path("", checksum).is_err());
+}
+
+const TEST_IDL_XML: &str = r#"<IDL xmlns="http://opensrf.org/spec/IDL/base/v1"
+    xmlns:oils_obj="http://open-ils.org/spec/opensrf/IDL/objects/v1"
+    xmlns:oils_persist="http://open-ils.org/spec/opensrf/IDL/persistence/v1">
+  <class id="au" oils_obj:fieldmapper="actor::user">
+    <fields oils_persist:primary="id">
+      <field name="addresses" oils_persist:virtual="true"/>
+      <field name="family_name"/>
+      <field name="home_ou"/>
+      <field name="id"/>
+      <field name="passwd" oils_persist:suppress_controller="open-ils.pcrud"/>
+    </fields>
+    <links>
+      <link field="addresses" reltype="has_many" key="usr" map="" class="aua"/>
+      <link field="home_ou" reltype="has_a" key="id" map="" class="aou"/>
+    </links>
+  </class>
+  <class id="aou" oils_obj:fieldmapper="actor::org_unit">
+    <fields oils_persist:primary="id">
+      <field name="id"/>
+      <field name="shortname"/>
+    </fields>
+  </class>
+  <class id="aua" oils_obj:fieldmapper="actor::user_address">
+    <fields oils_persist:primary="id">
+      <field name="id"/>
+      <field name="street1"/>
+      <field name="usr"/>
+    </fields>
+  </class>
+</IDL>"#;
+
+/// Load the test IDL once for all tests in this process.
+fn load_test_idl() {
+    static LOAD: std::sync::Once = std::sync::Once::new();
+    LOAD.call_once(|| crate::idl::Parser::load_string(TEST_IDL_XML).unwrap());
+}
+
+fn test_user() -> EgValue {
+    let org = EgValue::create("aou", eg::hash! {"id": 4, "shortname": "BR1"}).unwrap();
+
+    let addr = |id: i64, street: &str| {
+        EgValue::create("aua", eg::hash! {"id": id, "street1": street, "usr": 1}).unwrap()
+    };
+
+    EgValue::create(
+        "au",
+        eg::hash! {
+            "id": 1,
+            "family_name": "Smith",
+            "passwd": "secret",
+            "home_ou": org,
+            "addresses": eg::array! [addr(10, "1 Main St"), addr(11, "2 Main St")],
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn diff_fields() {
+    use crate::diff::{self, Change};
+
+    load_test_idl();
+
+    let old = test_user();
+    assert!(diff::diff(&old, &old.clone()).unwrap().is_empty());
+
+    let mut new = old.clone();
+    new["family_name"] = EgValue::from("Jones");
+    new["passwd"] = EgValue::from("hunter2");
+
+    let changes = diff::diff(&old, &new).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes.id(), &EgValue::from(1));
+
+    assert_eq!(
+        changes.changes()[0],
+        Change::Field {
+            path: "family_name".to_string(),
+            old: EgValue::from("Smith"),
+            new: EgValue::from("Jones"),
+        }
+    );
+
+    // Suppressed values are never revealed.
+    let text = changes.to_string();
+    assert!(text.starts_with("au 1: family_name: \"Smith\" => \"Jones\"; passwd: "));
+    assert!(!text.contains("secret"));
+    assert!(!text.contains("hunter2"));
+
+    // A number and a numeric string compare as equal.
+    let mut new = old.clone();
+    new["id"] = EgValue::from("1");
+    assert!(diff::diff(&old, &new).unwrap().is_empty());
+
+    let org = EgValue::create("aou", eg::hash! {"id": 4}).unwrap();
+    assert!(diff::diff(&old, &org).is_err());
+    assert!(diff::diff(&EgValue::from(1), &old).is_err());
+}
+
+#[test]
+fn diff_linked() {
+    use crate::diff::{self, Change};
+
+    load_test_idl();
+
+    let old = test_user();
+
+    // A bare ID matches the fleshed object with that ID.
+    let mut new = old.clone();
+    new["home_ou"] = EgValue::from(4);
+    assert!(diff::diff(&old, &new).unwrap().is_empty());
+
+    new["home_ou"] = EgValue::from(5);
+    assert_eq!(
+        diff::diff(&old, &new).unwrap().changes(),
+        &vec![Change::Field {
+            path: "home_ou".to_string(),
+            old: EgValue::from(4),
+            new: EgValue::from(5),
+        }]
+    );
+
+    // Fleshed objects with the same ID are compared field by field.
+    let mut new = old.clone();
+    new["home_ou"]["shortname"] = EgValue::from("BR2");
+    assert_eq!(
+        diff::diff(&old, &new).unwrap().changes()[0].path(),
+        "home_ou.shortname"
+    );
+}
+
+#[test]
+fn diff_children() {
+    use crate::diff::{self, Change};
+
+    load_test_idl();
+
+    let old = test_user();
+
+    let mut new = old.clone();
+    let added = EgValue::create("aua", eg::hash! {"street1": "3 Main St", "usr": 1}).unwrap();
+
+    let mut addrs = EgValue::new_array();
+    addrs.push(old["addresses"][1].clone()).unwrap();
+    addrs.push(added).unwrap();
+    addrs[0]["street1"] = EgValue::from("22 Main St");
+    new["addresses"] = addrs;
+
+    let changes = diff::diff(&old, &new).unwrap();
+
+    assert_eq!(
+        changes.changes(),
+        &vec![
+            Change::Removed {
+                path: "addresses".to_string(),
+                id: EgValue::from(10),
+            },
+            Change::Field {
+                path: "addresses[11].street1".to_string(),
+                old: EgValue::from("2 Main St"),
+                new: EgValue::from("22 Main St"),
+            },
+            Change::Added {
+                path: "addresses".to_string(),
+                id: EgValue::Null,
+            },
+        ]
+    );
+
+    // Unfleshed has_many lists are skipped.
+    new["addresses"] = EgValue::Null;
+    assert!(diff::diff(&old, &new).unwrap().is_empty());
+}
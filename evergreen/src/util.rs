@@ -1,11 +1,12 @@
 use crate::EgResult;
 use crate::EgValue;
+use ipnet::IpNet;
 use json::JsonValue;
 use rand::Rng;
 use socket2::{Domain, Socket, Type};
 use std::collections::HashSet;
 use std::fs;
-use std::net::{SocketAddr, TcpListener};
+use std::net::{IpAddr, SocketAddr, TcpListener};
 use std::path::Path;
 use std::thread;
 use std::time::Duration;
@@ -144,7 +145,7 @@ impl Timer {
 
 /// Creates a (JSON) String verion of a list of method parameters,
 /// replacing params with a generic REDACTED message for log-protected
-/// methods.
+/// methods and for requests the caller flagged as sensitive.
 ///
 /// ```
 /// use evergreen::util;
@@ -152,12 +153,21 @@ impl Timer {
 /// let log_protect = vec!["opensrf.system.private".to_string()];
 /// let params = vec![];
 ///
-/// let s = util::stringify_params(method, &params, &log_protect);
+/// let s = util::stringify_params(method, &params, &log_protect, false);
+/// assert_eq!(s.as_str(), util::REDACTED_PARAMS_STR);
+///
+/// let params = vec![evergreen::EgValue::from("secret")];
+/// let s = util::stringify_params("opensrf.public", &params, &log_protect, true);
 /// assert_eq!(s.as_str(), util::REDACTED_PARAMS_STR);
 /// ```
-pub fn stringify_params(method: &str, params: &Vec<EgValue>, log_protect: &Vec<String>) -> String {
+pub fn stringify_params(
+    method: &str,
+    params: &Vec<EgValue>,
+    log_protect: &Vec<String>,
+    sensitive: bool,
+) -> String {
     // Check if the method should be protected
-    let is_protected = log_protect.iter().any(|m| method.starts_with(m));
+    let is_protected = sensitive || log_protect.iter().any(|m| method.starts_with(m));
 
     if is_protected {
         REDACTED_PARAMS_STR.to_string()
@@ -331,3 +341,96 @@ pub fn tcp_listener(address: &str, port: u16, read_timeout: u64) -> EgResult<Tcp
 
     Ok(socket.into())
 }
+
+/// Parse a comma-separated list of IP addresses and CIDR ranges.
+///
+/// ```
+/// use evergreen::util;
+///
+/// let ranges = util::parse_ip_ranges("127.0.0.1, 10.0.0.0/8,::1").unwrap();
+/// assert_eq!(ranges.len(), 3);
+///
+/// assert!(util::parse_ip_ranges("10.0.0.0/99").is_err());
+/// ```
+pub fn parse_ip_ranges(list: &str) -> EgResult<Vec<IpNet>> {
+    let mut ranges = Vec::new();
+
+    for part in list.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let range = match part.parse::<IpNet>() {
+            Ok(r) => r,
+            Err(_) => part
+                .parse::<IpAddr>()
+                .map(IpNet::from)
+                .map_err(|e| format!("Invalid IP address or range '{part}': {e}"))?,
+        };
+
+        ranges.push(range);
+    }
+
+    Ok(ranges)
+}
+
+/// Determine the address of a client connecting through trusted proxies.
+///
+/// Forwarding headers are only believed when the peer is one of the
+/// `trusted` proxies.  The X-Forwarded-For chain is read right to
+/// left, skipping trusted proxies, and the first address which is not
+/// a trusted proxy is the client.  X-Real-IP is used when there is no
+/// X-Forwarded-For header.  Otherwise, the client is the peer.
+///
+/// ```
+/// use evergreen::util;
+/// use std::net::IpAddr;
+///
+/// let trusted = util::parse_ip_ranges("127.0.0.1,10.0.0.0/8").unwrap();
+/// let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+///
+/// // Spoofed entries to the left of the real client are ignored.
+/// let ip = util::forwarded_client_ip(proxy, Some("6.6.6.6, 1.2.3.4, 10.0.0.5"), None, &trusted);
+/// assert_eq!(ip.to_string(), "1.2.3.4");
+///
+/// let ip = util::forwarded_client_ip(proxy, None, Some("1.2.3.4"), &trusted);
+/// assert_eq!(ip.to_string(), "1.2.3.4");
+///
+/// // Headers from untrusted peers are ignored.
+/// let peer: IpAddr = "5.5.5.5".parse().unwrap();
+/// let ip = util::forwarded_client_ip(peer, Some("1.2.3.4"), None, &trusted);
+/// assert_eq!(ip, peer);
+/// ```
+pub fn forwarded_client_ip(
+    peer: IpAddr,
+    forwarded_for: Option<&str>,
+    real_ip: Option<&str>,
+    trusted: &[IpNet],
+) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|r| r.contains(ip));
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    if let Some(chain) = forwarded_for {
+        let mut client = peer;
+
+        for hop in chain.rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    client = ip;
+                    if !is_trusted(&ip) {
+                        break;
+                    }
+                }
+                Err(_) => {
+                    log::warn!("Invalid X-Forwarded-For entry: {hop}");
+                    break;
+                }
+            }
+        }
+
+        return client;
+    }
+
+    real_ip
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        .unwrap_or(peer)
+}
@@ -97,6 +97,39 @@ macro_rules! array {
         }
     }
 }
+
+/// Macro for building any EgValue from JSON-like syntax.
+///
+/// Objects and arrays are built as with eg::hash! and eg::array!,
+/// `null` becomes EgValue::Null, and any other expression is
+/// converted with EgValue::from().
+///
+/// ```
+/// use evergreen as eg;
+///
+/// let v = eg::value!({"name": "Branch", "ids": [1, 2, 3], "active": true});
+/// assert_eq!(v["name"].as_str(), Some("Branch"));
+/// assert_eq!(v["ids"].len(), 3);
+///
+/// assert!(eg::value!(null).is_null());
+/// assert_eq!(eg::value!([1, "two"]).len(), 2);
+/// assert_eq!(eg::value!(5 + 5).int().unwrap(), 10);
+/// ```
+#[macro_export]
+macro_rules! value {
+    (null) => {
+        eg::value::EgValue::Null
+    };
+    ({$($tts:tt)*}) => {
+        eg::hash! {$($tts)*}
+    };
+    ([$($tts:tt)*]) => {
+        eg::array! [$($tts)*]
+    };
+    ($other:expr) => {
+        eg::value::EgValue::from($other)
+    };
+}
 // ---
 
 #[test]
@@ -126,6 +159,57 @@ impl BlessedValue {
     }
 }
 
+/// Builds an EgValue::Blessed one field at a time.
+///
+/// The class name and each field name are verified against the IDL.
+/// The first problem encountered is reported by build().
+///
+/// ```no_run
+/// use evergreen as eg;
+/// use eg::EgValue;
+///
+/// fn new_org() -> eg::EgResult<EgValue> {
+///     EgValue::builder("aou")
+///         .set("name", "Branch")
+///         .set("shortname", "BR1")
+///         .set("parent_ou", 1)
+///         .build()
+/// }
+/// ```
+pub struct ObjectBuilder {
+    value: EgResult<EgValue>,
+}
+
+impl ObjectBuilder {
+    /// Set a field value.
+    pub fn set(mut self, field: &str, value: impl Into<EgValue>) -> Self {
+        if let Ok(EgValue::Blessed(ref mut o)) = self.value {
+            if o.idl_class.has_field(field) {
+                o.values.insert(field.to_string(), value.into());
+            } else {
+                self.value = Err(format!(
+                    "IDL class '{}' has no field named '{field}'",
+                    o.idl_class.classname()
+                )
+                .into());
+            }
+        }
+        self
+    }
+
+    /// Set a field value only if one is provided.
+    pub fn set_opt(self, field: &str, value: Option<impl Into<EgValue>>) -> Self {
+        match value {
+            Some(v) => self.set(field, v),
+            None => self,
+        }
+    }
+
+    pub fn build(self) -> EgResult<EgValue> {
+        self.value
+    }
+}
+
 /// Wrapper class which stores JSON-style values with one special
 /// value type which maps to IDL objects.
 #[derive(Debug, PartialEq, Clone)]
@@ -169,6 +253,13 @@ impl EgValue {
         }))
     }
 
+    /// Start building a new blessed value of the provided class.
+    pub fn builder(classname: &str) -> ObjectBuilder {
+        ObjectBuilder {
+            value: EgValue::stub(classname),
+        }
+    }
+
     /// Create a new blessed value from an existing Hash value using
     /// the provided class name.
     pub fn create(classname: &str, mut v: EgValue) -> EgResult<EgValue> {
@@ -251,20 +342,29 @@ impl EgValue {
     /// exist in the class definition for the value, are included in the
     /// generated Hash as Null values.
     pub fn to_classed_hash(&mut self) {
+        self.to_classed_hash_suppressed(&HashMap::new());
+    }
+
+    /// Same as to_classed_hash(), but fields listed in `suppress`,
+    /// keyed on IDL classname, are removed from the generated hashes.
+    pub fn to_classed_hash_suppressed(&mut self, suppress: &HashMap<String, Vec<String>>) {
         let (idl_class, mut map) = match self {
             Self::Array(ref mut list) => {
-                list.iter_mut().for_each(|v| v.to_classed_hash());
+                list.iter_mut()
+                    .for_each(|v| v.to_classed_hash_suppressed(suppress));
                 return;
             }
             Self::Hash(ref mut h) => {
-                h.values_mut().for_each(|v| v.to_classed_hash());
+                h.values_mut()
+                    .for_each(|v| v.to_classed_hash_suppressed(suppress));
                 return;
             }
             Self::Blessed(ref mut o) => (&o.idl_class, std::mem::take(&mut o.values)),
             _ => return,
         };
 
-        map.values_mut().for_each(|v| v.to_classed_hash());
+        map.values_mut()
+            .for_each(|v| v.to_classed_hash_suppressed(suppress));
 
         // Null's are not stored in Blessed values by default, but we do
         // want all of the real fields to be present in the plain Hash that's
@@ -275,6 +375,12 @@ impl EgValue {
             }
         }
 
+        if let Some(fields) = suppress.get(idl_class.classname()) {
+            for field in fields {
+                map.remove(field);
+            }
+        }
+
         // Add the _classname entry
         map.insert(
             HASH_CLASSNAME_KEY.to_string(),
@@ -992,6 +1098,151 @@ impl EgValue {
             .ok_or_else(|| format!("{self} has no valid ID").into())
     }
 
+    /// Coerce a boolean-ish value into a bool.
+    ///
+    /// Accepts JSON booleans, the "t"/"f" and "true"/"false" strings
+    /// used by the database layer, and the numbers 1 and 0.
+    ///
+    /// ```
+    /// use evergreen::EgValue;
+    ///
+    /// assert!(EgValue::from("t").bool().unwrap());
+    /// assert!(!EgValue::from(false).bool().unwrap());
+    /// assert!(EgValue::from(1).bool().unwrap());
+    /// assert!(EgValue::from("yes").bool().is_err());
+    /// assert!(EgValue::Null.bool().is_err());
+    /// ```
+    pub fn bool(&self) -> EgResult<bool> {
+        self.coerce_bool()
+            .ok_or_else(|| format!("{self} is not a boolean").into())
+    }
+
+    fn coerce_bool(&self) -> Option<bool> {
+        match self {
+            EgValue::Boolean(b) => Some(*b),
+            EgValue::Number(n) if *n == 1 => Some(true),
+            EgValue::Number(n) if *n == 0 => Some(false),
+            EgValue::String(ref s) => match s.as_str() {
+                "t" | "true" => Some(true),
+                "f" | "false" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the value found by following a dot-separated path of
+    /// hash keys, IDL field names, and array indexes.
+    ///
+    /// Missing hash keys and array indexes produce EgValue::Null.
+    /// Unlike indexing, a name which is not a field on an IDL object
+    /// produces an Err instead of a panic.
+    ///
+    /// ```
+    /// use evergreen as eg;
+    /// use eg::EgValue;
+    ///
+    /// let v = eg::hash! {"copy": {"circ_lib": {"id": "4"}}, "list": [1, 2]};
+    ///
+    /// assert_eq!(v.path("copy.circ_lib.id").unwrap().int().unwrap(), 4);
+    /// assert_eq!(v.path("list.1").unwrap().int().unwrap(), 2);
+    /// assert!(v.path("copy.nope").unwrap().is_null());
+    /// ```
+    pub fn path(&self, path: &str) -> EgResult<&EgValue> {
+        let mut value = self;
+
+        for part in path.split('.') {
+            value = match value {
+                EgValue::Blessed(ref o) => {
+                    if !part.starts_with('_') && !o.idl_class.has_field(part) {
+                        return Err(format!(
+                            "{}: class '{}' has no field '{part}'",
+                            self.path_label(path),
+                            o.idl_class.classname()
+                        )
+                        .into());
+                    }
+                    o.values.get(part).unwrap_or(&eg::NULL)
+                }
+                EgValue::Array(ref list) => match part.parse::<usize>() {
+                    Ok(idx) => list.get(idx).unwrap_or(&eg::NULL),
+                    Err(_) => &eg::NULL,
+                },
+                _ => &value[part],
+            };
+        }
+
+        Ok(value)
+    }
+
+    /// Path prefixed with our IDL class name, if we have one, for
+    /// error messages.
+    fn path_label(&self, path: &str) -> String {
+        match self.classname() {
+            Some(c) => format!("{c}.{path}"),
+            None => path.to_string(),
+        }
+    }
+
+    /// Apply a coercion to the value at the provided path, producing
+    /// an error which describes the path on failure.
+    fn coerce_at<T, F>(&self, path: &str, what: &str, coerce: F) -> EgResult<T>
+    where
+        F: Fn(&EgValue) -> Option<T>,
+    {
+        let value = self.path(path)?;
+
+        coerce(value)
+            .ok_or_else(|| format!("{}: {value} is not {what}", self.path_label(path)).into())
+    }
+
+    /// Integer value found at the provided path.  See path().
+    ///
+    /// ```
+    /// use evergreen as eg;
+    ///
+    /// let v = eg::hash! {"circ": {"usr": "17", "note": "hi"}};
+    ///
+    /// assert_eq!(v.int_at("circ.usr").unwrap(), 17);
+    ///
+    /// let err = v.int_at("circ.note").unwrap_err();
+    /// assert_eq!(err.to_string(), "circ.note: hi is not an integer");
+    /// ```
+    pub fn int_at(&self, path: &str) -> EgResult<i64> {
+        self.coerce_at(path, "an integer", |v| v.as_int())
+    }
+
+    /// Float value found at the provided path.  See path().
+    pub fn float_at(&self, path: &str) -> EgResult<f64> {
+        self.coerce_at(path, "a float", |v| v.as_float())
+    }
+
+    /// Boolean value found at the provided path.  See path() and bool().
+    pub fn bool_at(&self, path: &str) -> EgResult<bool> {
+        self.coerce_at(path, "a boolean", |v| v.coerce_bool())
+    }
+
+    /// String value found at the provided path.  See path().
+    pub fn str_at(&self, path: &str) -> EgResult<&str> {
+        let value = self.path(path)?;
+
+        value
+            .as_str()
+            .ok_or_else(|| format!("{}: {value} is not a string", self.path_label(path)).into())
+    }
+
+    /// ID of the object found at the provided path, which may be
+    /// either a fleshed object or the ID value itself.  See path().
+    pub fn id_at(&self, path: &str) -> EgResult<i64> {
+        self.coerce_at(path, "an object or ID", |v| {
+            if v.is_object() {
+                v["id"].as_int()
+            } else {
+                v.as_int()
+            }
+        })
+    }
+
     /// Returns the idl::Field for the primary key if present.
     pub fn pkey_field(&self) -> Option<&idl::Field> {
         if let EgValue::Blessed(b) = self {
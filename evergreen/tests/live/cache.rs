@@ -31,6 +31,13 @@ pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
 
     tester.timer.log("Deleted Something");
 
+    Cache::del_global("counter").expect("Del OK");
+    assert_eq!(Cache::incr_global_for("counter", 60).expect("Incr OK"), 1);
+    assert_eq!(Cache::incr_global_for("counter", 60).expect("Incr OK"), 2);
+    Cache::del_global("counter").expect("Del OK");
+
+    tester.timer.log("Incremented Something");
+
     // We have not initialized the anon cache, so this should produce an error.
     assert!(Cache::get_anon("foo").is_err());
 
@@ -1,10 +1,13 @@
 use crate::util;
 use eg::common::circulator::Circulator;
 use eg::constants as C;
+use eg::osrf::message::Progress;
 use eg::result::EgResult;
 use eg::EgValue;
 use evergreen as eg;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
     util::login(tester)?;
@@ -16,6 +19,9 @@ pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
     create_test_assets(tester)?;
     tester.timer.log("Created circ assets");
 
+    batch_update_copies(tester)?;
+    tester.timer.log("batch_update_copies()");
+
     checkout(tester)?;
     tester.timer.log("checkout()");
 
@@ -53,6 +59,47 @@ fn delete_test_assets(tester: &mut util::Tester) -> EgResult<()> {
     e.commit()
 }
 
+fn batch_update_copies(tester: &mut util::Tester) -> EgResult<()> {
+    let authtoken = tester.editor.authtoken().ok_or("Not logged in")?;
+
+    let params = vec![
+        EgValue::from(authtoken),
+        eg::array![
+            tester.samples.acp_barcode.as_str(),
+            "_EG_TEST_NO_SUCH_COPY_"
+        ],
+        eg::hash! {"status": C::COPY_STATUS_AVAILABLE},
+    ];
+
+    let updates: Rc<RefCell<Vec<Progress>>> = Rc::new(RefCell::new(Vec::new()));
+    let seen = updates.clone();
+
+    let mut ses = tester.client.session("open-ils.rs-circ");
+    let mut req = ses.request("open-ils.rs-circ.copy.status.update.batch", params)?;
+
+    req.on_progress(move |p| seen.borrow_mut().push(p.clone()));
+
+    let mut results = Vec::new();
+    while let Some(resp) = req.recv()? {
+        results.push(resp);
+    }
+
+    assert_eq!(results.len(), 2);
+    assert!(results[0]["success"].boolish());
+    assert!(!results[1]["success"].boolish());
+
+    let last = updates
+        .borrow()
+        .last()
+        .cloned()
+        .ok_or("No progress reported")?;
+
+    assert_eq!(last, Progress::new(2, Some(2)));
+    assert_eq!(req.progress(), Some(last));
+
+    Ok(())
+}
+
 fn checkout(tester: &mut util::Tester) -> EgResult<()> {
     let mut options: HashMap<String, EgValue> = HashMap::new();
     options.insert(
@@ -0,0 +1,92 @@
+use crate::util;
+use eg::db::{self, DatabaseConnection};
+use eg::result::ErrorKind;
+use eg::EgResult;
+use evergreen as eg;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
+    tester.timer.start();
+
+    // Two transactions each take one advisory lock, then wait on
+    // the other's.  PG aborts one of them with a deadlock error.
+    let barrier = Arc::new(Barrier::new(2));
+
+    let handles = [(1, 2), (2, 1)].map(|(first, second)| {
+        let barrier = barrier.clone();
+        thread::spawn(move || lock_pair(first, second, &barrier))
+    });
+
+    let errors: Vec<_> = handles
+        .into_iter()
+        .filter_map(|h| h.join().expect("Lock thread should not panic").err())
+        .collect();
+
+    assert_eq!(errors.len(), 1, "Exactly one transaction should deadlock");
+    assert_eq!(errors[0].kind(), ErrorKind::Transient);
+    assert!(errors[0].is_retryable());
+
+    tester.timer.log("Deadlock Is Retryable");
+
+    let mut conn = DatabaseConnection::builder().build();
+    conn.connect()?;
+
+    let err = conn
+        .client()
+        .execute(
+            "DO $$ BEGIN RAISE EXCEPTION 'test' USING ERRCODE = 'serialization_failure'; END $$",
+            &[],
+        )
+        .expect_err("Query should fail");
+
+    assert_eq!(db::query_error(&err, "test").kind(), ErrorKind::Transient);
+
+    tester.timer.log("Serialization Failure Is Retryable");
+
+    // The SQLSTATE decides, not the message text.
+    let err = conn
+        .client()
+        .execute(
+            "DO $$ BEGIN RAISE EXCEPTION '40P01 deadlock detected'; END $$",
+            &[],
+        )
+        .expect_err("Query should fail");
+
+    let err = db::query_error(&err, &err.to_string());
+    assert_eq!(err.kind(), ErrorKind::Database);
+    assert!(!err.is_retryable());
+
+    tester.timer.log("Other Failures Are Not Retryable");
+
+    Ok(())
+}
+
+/// Lock `first` then `second` within a single transaction.
+fn lock_pair(first: i64, second: i64, barrier: &Barrier) -> EgResult<()> {
+    let mut conn = DatabaseConnection::builder().build();
+    conn.connect()?;
+    conn.xact_begin()?;
+
+    let mut result = lock(&mut conn, first);
+
+    // Both threads hold their first lock before either asks for
+    // its second.
+    barrier.wait();
+
+    if result.is_ok() {
+        result = lock(&mut conn, second);
+    }
+
+    conn.xact_rollback()?;
+
+    result
+}
+
+fn lock(conn: &mut DatabaseConnection, key: i64) -> EgResult<()> {
+    conn.client()
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&key])
+        .map_err(|e| db::query_error(&e, &format!("Lock {key} failed: {e}")))?;
+
+    Ok(())
+}
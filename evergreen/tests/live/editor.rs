@@ -0,0 +1,43 @@
+use crate::util;
+use eg::result::ErrorKind;
+use eg::EgResult;
+use evergreen as eg;
+
+pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
+    tester.timer.start();
+
+    // Changes are always rolled back.
+    tester.editor.in_transaction_rollback(|e| {
+        let original = e.retrieve("aou", 1)?.expect("Org unit 1 exists");
+
+        let mut org = original.clone();
+        org["name"] = eg::EgValue::from("update_checked test");
+
+        e.update_checked(org.clone(), &original)?;
+
+        // Compare against a copy which no longer matches the row.
+        let mut stale = original.clone();
+        stale["shortname"] = eg::EgValue::from("STALE");
+
+        let err = e
+            .update_checked(org, &stale)
+            .expect_err("Stale object should not be updated");
+
+        assert_eq!(err.kind(), ErrorKind::Conflict);
+
+        Ok(())
+    })?;
+
+    tester.timer.log("Checked Updates");
+
+    // A transaction is required to lock the row.
+    let org = tester
+        .editor
+        .retrieve("aou", 1)?
+        .expect("Org unit 1 exists");
+    assert!(tester.editor.update_checked(org.clone(), &org).is_err());
+
+    tester.timer.log("Checked Update Requires Transaction");
+
+    Ok(())
+}
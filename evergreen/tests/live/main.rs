@@ -2,7 +2,10 @@ use evergreen as eg;
 mod auth;
 mod cache;
 mod circ;
+mod db;
+mod editor;
 mod json_query;
+mod search;
 mod store;
 mod util;
 
@@ -36,10 +39,16 @@ fn main() -> eg::EgResult<()> {
 
     circ::run_live_tests(&mut tester)?;
 
+    editor::run_live_tests(&mut tester)?;
+
+    db::run_live_tests(&mut tester)?;
+
     // open-ils.rs-store tester
     //store::run_live_tests(&mut tester)?;
 
     json_query::run_live_tests(&mut tester)?;
 
+    search::run_live_tests(&mut tester)?;
+
     Ok(())
 }
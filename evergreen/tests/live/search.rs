@@ -0,0 +1,61 @@
+//! open-ils.rs-search tests.
+//!
+//! Results depend on the loaded bib records, so these verify the
+//! shape of the response and the paging rules instead of specific
+//! records.
+use crate::util;
+use eg::result::EgResult;
+use eg::EgValue;
+use evergreen as eg;
+
+const SERVICE: &str = "open-ils.rs-search";
+const QUERY: &str = "history";
+
+pub fn run_live_tests(tester: &mut util::Tester) -> EgResult<()> {
+    tester.timer.start();
+
+    multiclass_query(tester)?;
+    tester.timer.log("biblio.multiclass.query");
+
+    Ok(())
+}
+
+fn search(tester: &mut util::Tester, limit: i64, offset: i64) -> EgResult<EgValue> {
+    let options = eg::hash! {
+        "org_unit": 1,
+        "limit": limit,
+        "offset": offset,
+    };
+
+    tester
+        .client
+        .send_recv_one(
+            SERVICE,
+            &format!("{SERVICE}.biblio.multiclass.query"),
+            vec![options, EgValue::from(QUERY)],
+        )?
+        .ok_or_else(|| "multiclass query returned no response".into())
+}
+
+fn multiclass_query(tester: &mut util::Tester) -> EgResult<()> {
+    let first = search(tester, 5, 0)?;
+    let count = first["count"].int()?;
+
+    assert!(first["ids"].len() <= 5);
+    assert!(first["ids"].len() as i64 <= count);
+
+    for pair in first["ids"].members() {
+        assert!(pair[0].int()? > 0);
+        assert!(pair[1].as_f64().is_some());
+    }
+
+    assert!(first["facets"].is_object());
+
+    // Paging past the end still reports the full count.
+    let past_end = search(tester, 5, count + 10)?;
+
+    assert_eq!(past_end["count"].int()?, count);
+    assert_eq!(past_end["ids"].len(), 0);
+
+    Ok(())
+}
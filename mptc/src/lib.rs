@@ -1,5 +1,6 @@
 pub mod server;
 pub mod signals;
+pub mod usage;
 pub mod worker;
 
 pub use server::Server;
@@ -1,8 +1,12 @@
 use super::signals::SignalTracker;
+use super::usage::ResourceUsage;
 use super::worker::{Worker, WorkerInstance, WorkerState, WorkerStateEvent};
 use super::{Request, RequestStream};
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
@@ -29,8 +33,20 @@ pub struct Server {
 
     sig_tracker: SignalTracker,
 
+    /// Maximum amount of time to wait for active workers to finish
+    /// their requests once a shutdown has been initiated.
+    ///
+    /// None means wait indefinitely.
+    shutdown_timeout: Option<Duration>,
+
     /// All inbound requests arrive via this stream.
     stream: Box<dyn RequestStream>,
+
+    /// Workers still awaiting replacement during a rolling restart.
+    restart_queue: VecDeque<u64>,
+
+    /// Worker currently draining during a rolling restart.
+    retiring_worker: Option<u64>,
 }
 
 impl Server {
@@ -47,6 +63,9 @@ impl Server {
             min_workers: super::DEFAULT_MIN_WORKERS,
             max_workers: super::DEFAULT_MAX_WORKERS,
             max_worker_reqs: super::DEFAULT_MAX_WORKER_REQS,
+            shutdown_timeout: None,
+            restart_queue: VecDeque::new(),
+            retiring_worker: None,
         }
     }
 
@@ -60,6 +79,13 @@ impl Server {
         self.max_worker_reqs = v;
     }
 
+    /// Wait at most this many seconds for active workers to complete
+    /// once a shutdown is requested.  Workers still running after the
+    /// timeout are abandoned.
+    pub fn set_shutdown_timeout(&mut self, secs: u64) {
+        self.shutdown_timeout = Some(Duration::from_secs(secs));
+    }
+
     fn next_worker_id(&mut self) -> u64 {
         self.worker_id_gen += 1;
         self.worker_id_gen
@@ -72,12 +98,52 @@ impl Server {
     }
 
     fn stop_workers(&mut self) {
+        if let Some(timeout) = self.shutdown_timeout {
+            self.wait_for_workers(timeout);
+        }
+
         while let Some(id) = self.workers.keys().next().copied() {
             log::debug!("Server cleaning up worker {}", id);
             self.remove_worker(&id, false);
         }
     }
 
+    /// Wait up to `timeout` for all worker threads to exit.
+    ///
+    /// Any workers still running after the timeout are dropped
+    /// from our list without being joined.
+    fn wait_for_workers(&mut self, timeout: Duration) {
+        let start = Instant::now();
+
+        while self.workers.values().any(|w| !w.join_handle.is_finished()) {
+            if start.elapsed() >= timeout {
+                let running: Vec<u64> = self
+                    .workers
+                    .iter()
+                    .filter(|(_, w)| !w.join_handle.is_finished())
+                    .map(|(k, _)| *k)
+                    .collect();
+
+                log::warn!(
+                    "Shutdown timeout reached with {} active worker(s); abandoning",
+                    running.len()
+                );
+
+                for id in running {
+                    self.workers.remove(&id);
+                }
+
+                return;
+            }
+
+            // Drain state events so the channel does not grow
+            // while we wait.
+            while self.to_parent_rx.try_recv().is_ok() {}
+
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
     fn start_one_worker(&mut self) -> u64 {
         let worker_id = self.next_worker_id();
         let to_parent_tx = self.to_parent_tx.clone();
@@ -91,10 +157,21 @@ impl Server {
             self.active_worker_count(),
         );
 
+        let retire = Arc::new(AtomicBool::new(false));
+        let worker_retire = retire.clone();
+
         let (tx, rx): (RequestSendChannel, RequestReceiveChannel) = mpsc::channel();
 
         let handle = thread::spawn(move || {
-            let mut w = Worker::new(worker_id, max_reqs, sig_tracker, to_parent_tx, rx, handler);
+            let mut w = Worker::new(
+                worker_id,
+                max_reqs,
+                sig_tracker,
+                worker_retire,
+                to_parent_tx,
+                rx,
+                handler,
+            );
             w.run();
         });
 
@@ -103,6 +180,8 @@ impl Server {
             state: WorkerState::Idle,
             join_handle: handle,
             to_worker_tx: tx,
+            usage: None,
+            retire,
         };
 
         self.workers.insert(worker_id, instance);
@@ -120,7 +199,7 @@ impl Server {
     fn idle_worker_count(&self) -> usize {
         self.workers
             .values()
-            .filter(|v| v.state == WorkerState::Idle)
+            .filter(|v| v.state == WorkerState::Idle && !v.retiring())
             .count()
     }
 
@@ -158,6 +237,9 @@ impl Server {
         } else {
             log::trace!("Updating thread state for worker: {}", worker_id);
             worker.state = evt.state().clone();
+            if let Some(usage) = evt.usage() {
+                worker.usage = Some(*usage);
+            }
         }
 
         let idle = self.idle_worker_count();
@@ -207,6 +289,18 @@ impl Server {
                 }
             }
 
+            if self.sig_tracker.restart_requested() {
+                self.sig_tracker.handle_restart_requested();
+                self.start_restart();
+            }
+
+            if self.sig_tracker.dump_state_requested() {
+                self.sig_tracker.handle_dump_state_requested();
+                self.dump_state();
+            }
+
+            self.continue_restart();
+
             if self.sig_tracker.any_shutdown_requested() {
                 log::info!("Shutdown request received.");
                 self.stream.shutdown();
@@ -243,6 +337,8 @@ impl Server {
         self.sig_tracker.track_graceful_shutdown();
         self.sig_tracker.track_fast_shutdown();
         self.sig_tracker.track_reload();
+        self.sig_tracker.track_restart();
+        self.sig_tracker.track_dump_state();
 
         self.start_workers();
 
@@ -271,6 +367,99 @@ impl Server {
         self.stop_workers();
     }
 
+    /// Queue every current worker for replacement.
+    ///
+    /// Workers are retired one at a time as they become idle, each
+    /// after its replacement has been started, so we never drop below
+    /// our current capacity.  Useful for picking up new code or
+    /// configuration without refusing any requests.
+    fn start_restart(&mut self) {
+        let mut ids: Vec<u64> = self.workers.keys().copied().collect();
+        ids.sort();
+
+        log::info!("Restart request received; replacing {} workers", ids.len());
+
+        self.restart_queue = ids.into();
+    }
+
+    /// Retire the next idle worker in the restart queue once the
+    /// previously retired worker has exited.
+    fn continue_restart(&mut self) {
+        if let Some(id) = self.retiring_worker {
+            if self.workers.contains_key(&id) {
+                return;
+            }
+            self.retiring_worker = None;
+
+            if self.restart_queue.is_empty() {
+                log::info!("Restart complete");
+            }
+        }
+
+        // Workers may have exited on their own since the restart began.
+        self.restart_queue
+            .retain(|id| self.workers.contains_key(id));
+
+        let pos = self.restart_queue.iter().position(|id| {
+            self.workers
+                .get(id)
+                .map(|w| w.state() == &WorkerState::Idle)
+                .unwrap_or(false)
+        });
+
+        let Some(id) = pos.and_then(|p| self.restart_queue.remove(p)) else {
+            return;
+        };
+
+        if self.workers.len() < self.max_workers {
+            self.start_one_worker();
+        }
+
+        log::info!(
+            "Retiring worker {id}; {} left to restart",
+            self.restart_queue.len()
+        );
+
+        if let Some(worker) = self.workers.get_mut(&id) {
+            worker.retire();
+        }
+
+        self.retiring_worker = Some(id);
+    }
+
+    /// Log the state of every worker.
+    fn dump_state(&self) {
+        let usage = ResourceUsage::process().unwrap_or_default();
+
+        log::info!(
+            "MPTC state: workers={} min-workers={} max-workers={} active={} idle={} \
+            restart-pending={} rss-mb={} cpu-secs={:.2}",
+            self.workers.len(),
+            self.min_workers,
+            self.max_workers,
+            self.active_worker_count(),
+            self.idle_worker_count(),
+            self.restart_queue.len() + self.retiring_worker.iter().count(),
+            usage.rss_bytes / (1024 * 1024),
+            usage.cpu_secs,
+        );
+
+        let mut ids: Vec<&u64> = self.workers.keys().collect();
+        ids.sort();
+
+        for id in ids {
+            let worker = &self.workers[id];
+            let cpu = worker.usage().map(|u| u.cpu_secs).unwrap_or(0.0);
+
+            log::info!(
+                "MPTC worker={id} state={} retiring={} finished={} cpu-secs={cpu:.2}",
+                worker.state(),
+                worker.retiring(),
+                worker.join_handle().is_finished(),
+            );
+        }
+    }
+
     /// Periodically report our active/idle thread disposition
     /// so monitoring tools can keep track.
     ///
@@ -290,11 +479,23 @@ impl Server {
             return;
         }
 
+        let usage = ResourceUsage::process().unwrap_or_default();
+
+        // CPU time of the busiest current worker.
+        let max_worker_cpu = self
+            .workers
+            .values()
+            .filter_map(|w| w.usage().map(|u| u.cpu_secs))
+            .fold(0.0, f64::max);
+
         log::info!(
-            "MPTC max-threads={} active-threads={} idle-threads={}",
+            "MPTC max-threads={} active-threads={} idle-threads={} rss-mb={} cpu-secs={:.2} max-worker-cpu-secs={:.2}",
             self.max_workers,
             active_count,
             self.idle_worker_count(),
+            usage.rss_bytes / (1024 * 1024),
+            usage.cpu_secs,
+            max_worker_cpu,
         );
 
         *timer = Instant::now();
@@ -321,7 +522,7 @@ impl Server {
         if let Some((k, _)) = self
             .workers
             .iter()
-            .find(|(_, w)| w.state() == &WorkerState::Idle)
+            .find(|(_, w)| w.state() == &WorkerState::Idle && !w.retiring())
         {
             return *k; // &u64
         }
@@ -340,7 +541,7 @@ impl Server {
             if let Some((k, _)) = self
                 .workers
                 .iter()
-                .find(|(_, w)| w.state() == &WorkerState::Idle)
+                .find(|(_, w)| w.state() == &WorkerState::Idle && !w.retiring())
             {
                 return *k; // &u64
             }
@@ -7,6 +7,8 @@ use std::time::SystemTime;
 pub const SIG_FAST_SHUTDOWN: i32 = sigs::consts::SIGTERM;
 pub const SIG_GRACEFUL_SHUTDOWN: i32 = sigs::consts::SIGINT;
 pub const SIG_RELOAD: i32 = sigs::consts::SIGHUP;
+pub const SIG_RESTART: i32 = sigs::consts::SIGUSR1;
+pub const SIG_DUMP_STATE: i32 = sigs::consts::SIGUSR2;
 
 /// Tracks various signals so threaded, etc. applications can
 /// easily respond to received signals.
@@ -24,11 +26,15 @@ pub struct SignalTracker {
     fast_shutdown: Arc<AtomicBool>,
     reload: Arc<AtomicBool>,
     reload_request_time: Arc<AtomicU64>,
+    restart: Arc<AtomicBool>,
+    dump_state: Arc<AtomicBool>,
 
     /// Avoid duplicate signal handlers
     graceful_shutdown_tracked: bool,
     fast_shutdown_tracked: bool,
     reload_tracked: bool,
+    restart_tracked: bool,
+    dump_state_tracked: bool,
 }
 
 impl Default for SignalTracker {
@@ -44,9 +50,13 @@ impl SignalTracker {
             fast_shutdown: Arc::new(AtomicBool::new(false)),
             reload: Arc::new(AtomicBool::new(false)),
             reload_request_time: Arc::new(AtomicU64::new(0)),
+            restart: Arc::new(AtomicBool::new(false)),
+            dump_state: Arc::new(AtomicBool::new(false)),
             graceful_shutdown_tracked: false,
             fast_shutdown_tracked: false,
             reload_tracked: false,
+            restart_tracked: false,
+            dump_state_tracked: false,
         }
     }
 
@@ -65,6 +75,16 @@ impl SignalTracker {
         self.reload.store(true, Ordering::Relaxed);
     }
 
+    /// Directly initiate a worker restart request.
+    pub fn request_restart(&self) {
+        self.restart.store(true, Ordering::Relaxed);
+    }
+
+    /// Directly initiate a worker state dump request.
+    pub fn request_dump_state(&self) {
+        self.dump_state.store(true, Ordering::Relaxed);
+    }
+
     /// True if any shutdown signals have been received.
     pub fn any_shutdown_requested(&self) -> bool {
         self.graceful_shutdown_requested() || self.fast_shutdown_requested()
@@ -199,4 +219,87 @@ impl SignalTracker {
     pub fn reload_request_time(&self) -> u64 {
         self.reload_request_time.load(Ordering::Relaxed)
     }
+
+    /// Activate worker restart signal tracking.
+    ///
+    /// ```
+    /// use mptc::signals::SignalTracker;
+    /// use signal_hook::low_level::raise;
+    ///
+    /// let mut tracker = SignalTracker::new();
+    /// tracker.track_restart();
+    ///
+    /// raise(mptc::signals::SIG_RESTART).expect("Signal Sent");
+    ///
+    /// assert!(tracker.restart_requested());
+    /// assert!(!tracker.any_shutdown_requested());
+    ///
+    /// tracker.handle_restart_requested();
+    ///
+    /// assert!(!tracker.restart_requested());
+    /// ```
+    pub fn track_restart(&mut self) {
+        if self.restart_tracked {
+            log::warn!("Already tracking restart signals");
+            return;
+        }
+
+        let result = sigs::flag::register(SIG_RESTART, self.restart.clone());
+
+        if let Err(e) = result {
+            panic!("Cannot register restart handler: {}", e);
+        }
+
+        self.restart_tracked = true;
+    }
+
+    pub fn restart_requested(&self) -> bool {
+        self.restart.load(Ordering::Relaxed)
+    }
+
+    /// Reset the restart request flag so it may be used again later.
+    pub fn handle_restart_requested(&mut self) {
+        self.restart.store(false, Ordering::Relaxed);
+    }
+
+    /// Activate worker state dump signal tracking.
+    ///
+    /// ```
+    /// use mptc::signals::SignalTracker;
+    /// use signal_hook::low_level::raise;
+    ///
+    /// let mut tracker = SignalTracker::new();
+    /// tracker.track_dump_state();
+    ///
+    /// raise(mptc::signals::SIG_DUMP_STATE).expect("Signal Sent");
+    ///
+    /// assert!(tracker.dump_state_requested());
+    ///
+    /// tracker.handle_dump_state_requested();
+    ///
+    /// assert!(!tracker.dump_state_requested());
+    /// ```
+    pub fn track_dump_state(&mut self) {
+        if self.dump_state_tracked {
+            log::warn!("Already tracking dump state signals");
+            return;
+        }
+
+        let result = sigs::flag::register(SIG_DUMP_STATE, self.dump_state.clone());
+
+        if let Err(e) = result {
+            panic!("Cannot register dump state handler: {}", e);
+        }
+
+        self.dump_state_tracked = true;
+    }
+
+    pub fn dump_state_requested(&self) -> bool {
+        self.dump_state.load(Ordering::Relaxed)
+    }
+
+    /// Reset the dump state request flag so it may be used again later.
+    pub fn handle_dump_state_requested(&mut self) {
+        self.dump_state.store(false, Ordering::Relaxed);
+    }
 }
@@ -0,0 +1,85 @@
+//! CPU and memory usage of the current process and worker threads.
+//!
+//! Values are read from /proc and are only available on Linux.
+use std::fs;
+use std::sync::OnceLock;
+
+/// Kernel clock ticks per second used for /proc CPU times.
+static CLOCK_TICKS_PER_SEC: OnceLock<f64> = OnceLock::new();
+
+fn clock_ticks_per_sec() -> f64 {
+    *CLOCK_TICKS_PER_SEC.get_or_init(|| {
+        // SAFETY: sysconf() only reads a system configuration value.
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+
+        if ticks > 0 {
+            ticks as f64
+        } else {
+            // USER_HZ is 100 on all mainstream Linux platforms.
+            100.0
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// User + system CPU time in seconds.
+    pub cpu_secs: f64,
+    /// Resident set size of the whole process in bytes.  Threads
+    /// share their process's memory, so there is no per-thread value.
+    pub rss_bytes: u64,
+}
+
+impl ResourceUsage {
+    /// CPU time consumed by the calling thread, along with the
+    /// process RSS.
+    ///
+    /// ```
+    /// use mptc::usage::ResourceUsage;
+    ///
+    /// if let Some(usage) = ResourceUsage::current_thread() {
+    ///     assert!(usage.rss_bytes > 0);
+    /// }
+    /// ```
+    pub fn current_thread() -> Option<ResourceUsage> {
+        Some(ResourceUsage {
+            cpu_secs: cpu_secs("/proc/thread-self/stat")?,
+            rss_bytes: rss_bytes()?,
+        })
+    }
+
+    /// CPU time consumed by all threads of this process, along with
+    /// the process RSS.
+    pub fn process() -> Option<ResourceUsage> {
+        Some(ResourceUsage {
+            cpu_secs: cpu_secs("/proc/self/stat")?,
+            rss_bytes: rss_bytes()?,
+        })
+    }
+}
+
+/// Extract utime + stime from a /proc stat file.
+fn cpu_secs(path: &str) -> Option<f64> {
+    let stat = fs::read_to_string(path).ok()?;
+
+    // The command name (field 2) may contain spaces, so start
+    // counting fields after its closing paren.
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 2..)?.split(' ').collect();
+
+    // utime and stime are fields 14 and 15 of the full line.
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+
+    Some((utime + stime) as f64 / clock_ticks_per_sec())
+}
+
+fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+
+    // e.g. "VmRSS:	   10240 kB"
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(kb * 1024)
+}
@@ -1,13 +1,20 @@
 use super::signals::SignalTracker;
+use super::usage::ResourceUsage;
 use super::{Request, RequestHandler};
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 
 const SHUTDOWN_POLL_INTERVAL: u64 = 5;
 
+/// Minimum time between reads of our resource usage from /proc.
+const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum WorkerState {
     Idle,
@@ -43,6 +50,7 @@ impl fmt::Display for WorkerState {
 pub struct WorkerStateEvent {
     worker_id: u64,
     state: WorkerState,
+    usage: Option<ResourceUsage>,
 }
 
 impl fmt::Display for WorkerStateEvent {
@@ -62,6 +70,9 @@ impl WorkerStateEvent {
     pub fn state(&self) -> &WorkerState {
         &self.state
     }
+    pub fn usage(&self) -> Option<&ResourceUsage> {
+        self.usage.as_ref()
+    }
 }
 
 /// Data for tracking a specific worker thread.
@@ -70,6 +81,11 @@ pub struct WorkerInstance {
     pub state: WorkerState,
     pub join_handle: thread::JoinHandle<()>,
     pub to_worker_tx: mpsc::Sender<Box<dyn Request>>,
+    /// Most recent resource usage reported by the worker.
+    pub usage: Option<ResourceUsage>,
+    /// Set when the server wants this worker to exit so it can be
+    /// replaced, e.g. during a rolling restart.
+    pub retire: Arc<AtomicBool>,
 }
 
 impl WorkerInstance {
@@ -82,6 +98,22 @@ impl WorkerInstance {
     pub fn join_handle(&self) -> &thread::JoinHandle<()> {
         &self.join_handle
     }
+    pub fn usage(&self) -> Option<&ResourceUsage> {
+        self.usage.as_ref()
+    }
+    pub fn retiring(&self) -> bool {
+        self.retire.load(Ordering::Relaxed)
+    }
+
+    /// Tell the worker to exit once it's done with any active request.
+    ///
+    /// Our request channel is replaced so the worker wakes right
+    /// away instead of waiting out its receive timeout.
+    pub fn retire(&mut self) {
+        self.retire.store(true, Ordering::Relaxed);
+        let (tx, _) = mpsc::channel();
+        self.to_worker_tx = tx;
+    }
 }
 
 impl fmt::Display for WorkerInstance {
@@ -103,6 +135,9 @@ pub struct Worker {
     to_worker_rx: mpsc::Receiver<Box<dyn Request>>,
     handler: Box<dyn RequestHandler>,
     sig_tracker: SignalTracker,
+    retire: Arc<AtomicBool>,
+    /// When we last sampled our resource usage.
+    usage_sampled: Option<Instant>,
 }
 
 impl Worker {
@@ -110,6 +145,7 @@ impl Worker {
         worker_id: u64,
         max_requests: usize,
         sig_tracker: SignalTracker,
+        retire: Arc<AtomicBool>,
         to_parent_tx: mpsc::Sender<WorkerStateEvent>,
         to_worker_rx: mpsc::Receiver<Box<dyn Request>>,
         handler: Box<dyn RequestHandler>,
@@ -123,11 +159,13 @@ impl Worker {
             worker_id,
             max_requests,
             sig_tracker,
+            retire,
             start_time_epoch: epoch,
             to_parent_tx,
             to_worker_rx,
             request_count: 0,
             handler,
+            usage_sampled: None,
         }
     }
 
@@ -143,6 +181,7 @@ impl Worker {
         let evt = WorkerStateEvent {
             worker_id: self.worker_id,
             state,
+            usage: self.sample_usage(),
         };
 
         if let Err(e) = self.to_parent_tx.send(evt) {
@@ -157,6 +196,22 @@ impl Worker {
         }
     }
 
+    /// Read our resource usage if the last sample is old enough.
+    ///
+    /// Returns None between samples, in which case the server keeps
+    /// the last values we reported.
+    fn sample_usage(&mut self) -> Option<ResourceUsage> {
+        if let Some(sampled) = self.usage_sampled {
+            if sampled.elapsed() < USAGE_SAMPLE_INTERVAL {
+                return None;
+            }
+        }
+
+        self.usage_sampled = Some(Instant::now());
+
+        ResourceUsage::current_thread()
+    }
+
     fn should_shut_down(&self) -> bool {
         if self.sig_tracker.any_shutdown_requested() {
             log::debug!("{self} received shutdown, exiting run loop");
@@ -164,6 +219,11 @@ impl Worker {
             return true;
         }
 
+        if self.retire.load(Ordering::Relaxed) {
+            log::info!("{self} retired by server, exiting run loop");
+            return true;
+        }
+
         let reload_time = self.sig_tracker.reload_request_time();
         if reload_time > self.start_time_epoch {
             log::info!("{self} shutdown_before of {reload_time} issued.  That includes us");
@@ -241,6 +301,12 @@ impl Worker {
                 match e {
                     // Timeouts are expected.
                     std::sync::mpsc::RecvTimeoutError::Timeout => return Ok(false),
+                    // As is a closed channel once we've been retired.
+                    std::sync::mpsc::RecvTimeoutError::Disconnected
+                        if self.retire.load(Ordering::Relaxed) =>
+                    {
+                        return Ok(false)
+                    }
                     // Other errors are not.
                     _ => return Err(format!("Error receiving request from parent: {e}")),
                 }
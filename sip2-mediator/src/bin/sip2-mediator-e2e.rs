@@ -5,6 +5,7 @@ use eg::EgValue;
 use evergreen as eg;
 use getopts;
 use sip2;
+use sip2::spec::CirculationStatus;
 use std::time::SystemTime;
 
 fn is_zero(n: &str) -> bool {
@@ -337,7 +338,7 @@ fn test_invalid_item_info(tester: &mut Tester) -> Result<(), String> {
         .or_else(|e| Err(format!("SIP sendrecv error: {e}")))?;
     t.done("test_invalid_item_info");
 
-    let circ_status = resp.fixed_fields()[0].value();
+    let circ_status = CirculationStatus::try_from(resp.fixed_fields()[0].value())?;
     let barcode = resp.get_field_value("AB");
     let title = resp.get_field_value("AJ");
 
@@ -347,7 +348,7 @@ fn test_invalid_item_info(tester: &mut Tester) -> Result<(), String> {
 
     assert_eq!(barcode.unwrap(), dummy);
     assert_eq!(title.unwrap(), "");
-    assert_eq!(circ_status, "01");
+    assert_eq!(circ_status, CirculationStatus::Other);
 
     Ok(())
 }
@@ -370,7 +371,7 @@ fn test_item_info(tester: &mut Tester, charged: bool) -> Result<(), String> {
         .or_else(|e| Err(format!("SIP sendrecv error: {e}")))?;
     t.done("test_item_info");
 
-    let circ_status = resp.fixed_fields()[0].value();
+    let circ_status = CirculationStatus::try_from(resp.fixed_fields()[0].value())?;
     let barcode = resp.get_field_value("AB");
     let title = resp.get_field_value("AJ");
 
@@ -381,10 +382,13 @@ fn test_item_info(tester: &mut Tester, charged: bool) -> Result<(), String> {
     assert_eq!(barcode.unwrap(), tester.samples.acp_barcode);
     assert_ne!(title.unwrap(), "");
     if charged {
-        assert_eq!(circ_status, "04");
+        assert_eq!(circ_status, CirculationStatus::Charged);
     } else {
         // May be available or reshelving
-        assert!(circ_status.eq("03") || circ_status.eq("09"));
+        assert!(matches!(
+            circ_status,
+            CirculationStatus::Available | CirculationStatus::WaitingToBeReshelved
+        ));
     }
 
     if let Some(dest) = resp.get_field_value("CT") {
@@ -0,0 +1,400 @@
+use getopts;
+use sip2;
+use std::env;
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use yaml_rust::{Yaml, YamlLoader};
+
+const DEFAULT_HOST: &str = "localhost:6001";
+
+const HELP_TEXT: &str = r#"
+Run scripted SIP scenarios against a SIP server, validating the
+responses and reporting timing information.
+
+Synopsis:
+
+sip2-server-test --scenario sip2-mediator/conf/sip2-server-test.example.yml
+
+Parameters:
+
+    --scenario <file>
+        YAML scenario file.  See the example file for details.
+
+    --sip-host <host:port>
+        Overrides the scenario sip-host value.
+
+    --parallel <count>
+        Overrides the scenario parallel value.
+
+    --repeat <count>
+        Overrides the scenario repeat value.
+
+    --quiet
+        Print only summary information
+"#;
+
+/// One message to send as part of a scenario along with the
+/// values we expect to find in the response.
+struct Step {
+    message: String,
+    params: sip2::ParamSet,
+
+    /// Send this step this many times in a row per scenario pass.
+    repeat: usize,
+
+    /// If set, the response ok() value must match.
+    expect_ok: Option<bool>,
+
+    /// Fields which must be present in the response with these values.
+    expect_fields: Vec<(String, String)>,
+
+    /// Fields which must be present in the response with any value.
+    expect_present: Vec<String>,
+}
+
+struct Scenario {
+    host: String,
+    parallel: usize,
+    repeat: usize,
+    steps: Vec<Step>,
+}
+
+/// Results collected for a single Step across all threads.
+#[derive(Default, Clone)]
+struct StepStats {
+    passed: usize,
+    failed: usize,
+    total_time: Duration,
+    max_time: Duration,
+}
+
+fn main() -> Result<(), String> {
+    let options = read_options();
+
+    if options.opt_present("help") {
+        println!("{HELP_TEXT}");
+        return Ok(());
+    }
+
+    let filename = options
+        .opt_str("scenario")
+        .ok_or_else(|| "--scenario required".to_string())?;
+
+    let mut scenario = load_scenario(&filename)?;
+
+    if let Some(host) = options.opt_str("sip-host") {
+        scenario.host = host;
+    }
+
+    if let Some(p) = options.opt_str("parallel") {
+        scenario.parallel = p.parse().map_err(|e| format!("Invalid --parallel: {e}"))?;
+    }
+
+    if let Some(r) = options.opt_str("repeat") {
+        scenario.repeat = r.parse().map_err(|e| format!("Invalid --repeat: {e}"))?;
+    }
+
+    let quiet = options.opt_present("quiet");
+    let scenario = Arc::new(scenario);
+
+    let mut handles = Vec::new();
+    let start = SystemTime::now();
+
+    for idx in 0..scenario.parallel {
+        let s = scenario.clone();
+        handles.push(thread::spawn(move || run_one_thread(idx, s, quiet)));
+    }
+
+    let mut stats = vec![StepStats::default(); scenario.steps.len()];
+
+    for h in handles {
+        let thread_stats = h.join().map_err(|_| "Test thread panicked".to_string())?;
+
+        for (total, part) in stats.iter_mut().zip(thread_stats.iter()) {
+            total.passed += part.passed;
+            total.failed += part.failed;
+            total.total_time += part.total_time;
+            total.max_time = total.max_time.max(part.max_time);
+        }
+    }
+
+    let seconds = start.elapsed().unwrap().as_secs_f64();
+
+    println!(
+        "\n{:<4} {:<20} {:>8} {:>8} {:>12} {:>12}",
+        "#", "message", "passed", "failed", "avg ms", "max ms"
+    );
+
+    let mut count = 0;
+    let mut failures = 0;
+
+    for (idx, (step, s)) in scenario.steps.iter().zip(stats.iter()).enumerate() {
+        let sent = s.passed + s.failed;
+        count += sent;
+        failures += s.failed;
+
+        let avg = if sent > 0 {
+            s.total_time.as_secs_f64() * 1000.0 / sent as f64
+        } else {
+            0.0
+        };
+
+        println!(
+            "{:<4} {:<20} {:>8} {:>8} {:>12.3} {:>12.3}",
+            idx + 1,
+            step.message,
+            s.passed,
+            s.failed,
+            avg,
+            s.max_time.as_secs_f64() * 1000.0
+        );
+    }
+
+    let thput = count as f64 / seconds;
+
+    println!("\n{count} messages processed in {seconds:.3} seconds; ~{thput:.3} reqs / second");
+
+    if failures > 0 {
+        Err(format!("{failures} message(s) failed validation"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Run all scenario steps the requested number of times within a
+/// single SIP connection.
+fn run_one_thread(thread_idx: usize, scenario: Arc<Scenario>, quiet: bool) -> Vec<StepStats> {
+    let mut stats = vec![StepStats::default(); scenario.steps.len()];
+
+    let mut client = match sip2::Client::new(&scenario.host) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("[{thread_idx}] Cannot connect to {}: {e}", scenario.host);
+            // Count every message as failed.
+            for (step, s) in scenario.steps.iter().zip(stats.iter_mut()) {
+                s.failed = step.repeat * scenario.repeat;
+            }
+            return stats;
+        }
+    };
+
+    for _ in 0..scenario.repeat {
+        for (idx, step) in scenario.steps.iter().enumerate() {
+            for _ in 0..step.repeat {
+                let start = SystemTime::now();
+                let result = send_step(&mut client, step);
+                let duration = start.elapsed().unwrap();
+
+                let s = &mut stats[idx];
+                s.total_time += duration;
+                s.max_time = s.max_time.max(duration);
+
+                let errors = match result {
+                    Ok(resp) => {
+                        if !quiet {
+                            println!("[{thread_idx}] {}", resp.msg());
+                        }
+                        validate(step, &resp)
+                    }
+                    Err(e) => vec![format!("request failed: {e}")],
+                };
+
+                if errors.is_empty() {
+                    s.passed += 1;
+                } else {
+                    s.failed += 1;
+                    for e in errors {
+                        eprintln!("[{thread_idx}] step {} ({}): {e}", idx + 1, step.message);
+                    }
+                }
+            }
+        }
+    }
+
+    client.disconnect().ok();
+
+    stats
+}
+
+fn send_step(client: &mut sip2::Client, step: &Step) -> Result<sip2::SipResponse, sip2::Error> {
+    let p = &step.params;
+    match step.message.as_str() {
+        "login" => client.login(p),
+        "sc-status" => client.sc_status(),
+        "patron-status" => client.patron_status(p),
+        "patron-information" => client.patron_info(p),
+        "item-information" => client.item_info(p),
+        "checkout" => client.checkout(p),
+        "checkin" => client.checkin(p),
+        "fee-paid" => client.fee_paid(p),
+        // Validated in load_scenario()
+        _ => unreachable!(),
+    }
+}
+
+/// Returns a list of validation failures for a response.
+fn validate(step: &Step, resp: &sip2::SipResponse) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if let Some(ok) = step.expect_ok {
+        if resp.ok() != ok {
+            errors.push(format!("expected ok={ok}, got ok={}", resp.ok()));
+        }
+    }
+
+    for (code, value) in step.expect_fields.iter() {
+        match resp.value(code) {
+            Some(v) if v == value => {}
+            Some(v) => errors.push(format!("expected {code}='{value}', got '{v}'")),
+            None => errors.push(format!("expected {code}='{value}', field missing")),
+        }
+    }
+
+    for code in step.expect_present.iter() {
+        if resp.value(code).is_none() {
+            errors.push(format!("expected field {code} to be present"));
+        }
+    }
+
+    errors
+}
+
+/// Yaml string or number as a String.
+fn yaml_string(y: &Yaml) -> Option<String> {
+    match y {
+        Yaml::String(s) => Some(s.to_string()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Real(r) => Some(r.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Apply any SIP parameters found in a YAML hash to the ParamSet.
+fn apply_params(params: &mut sip2::ParamSet, y: &Yaml) {
+    if let Some(v) = yaml_string(&y["sip-user"]) {
+        params.set_sip_user(&v);
+    }
+    if let Some(v) = yaml_string(&y["sip-pass"]) {
+        params.set_sip_pass(&v);
+    }
+    if let Some(v) = yaml_string(&y["institution"]) {
+        params.set_institution(&v);
+    }
+    if let Some(v) = yaml_string(&y["terminal-password"]) {
+        params.set_terminal_pwd(&v);
+    }
+    if let Some(v) = yaml_string(&y["location-code"]) {
+        params.set_location(&v);
+    }
+    if let Some(v) = yaml_string(&y["patron-barcode"]) {
+        params.set_patron_id(&v);
+    }
+    if let Some(v) = yaml_string(&y["patron-password"]) {
+        params.set_patron_pwd(&v);
+    }
+    if let Some(v) = yaml_string(&y["item-barcode"]) {
+        params.set_item_id(&v);
+    }
+    if let Some(v) = yaml_string(&y["pay-amount"]) {
+        params.set_pay_amount(&v);
+    }
+    if let Some(v) = yaml_string(&y["fee-id"]) {
+        params.set_fee_id(&v);
+    }
+    if let Some(v) = yaml_string(&y["transaction-id"]) {
+        params.set_transaction_id(&v);
+    }
+}
+
+fn load_scenario(filename: &str) -> Result<Scenario, String> {
+    let yaml_text =
+        fs::read_to_string(filename).map_err(|e| format!("Error reading {filename}: {e}"))?;
+
+    let yaml_docs = YamlLoader::load_from_str(&yaml_text)
+        .map_err(|e| format!("Error parsing {filename}: {e}"))?;
+
+    let root = yaml_docs
+        .first()
+        .ok_or_else(|| format!("Invalid scenario file: {filename}"))?;
+
+    let mut base_params = sip2::ParamSet::new();
+    apply_params(&mut base_params, root);
+
+    let mut scenario = Scenario {
+        host: root["sip-host"]
+            .as_str()
+            .unwrap_or(DEFAULT_HOST)
+            .to_string(),
+        parallel: root["parallel"].as_i64().unwrap_or(1) as usize,
+        repeat: root["repeat"].as_i64().unwrap_or(1) as usize,
+        steps: Vec::new(),
+    };
+
+    let steps = root["steps"]
+        .as_vec()
+        .ok_or_else(|| "Scenario requires a list of steps".to_string())?;
+
+    for y in steps {
+        let message = y["message"]
+            .as_str()
+            .ok_or_else(|| "Each step requires a message".to_string())?;
+
+        match message {
+            "login" | "sc-status" | "patron-status" | "patron-information" | "item-information"
+            | "checkout" | "checkin" | "fee-paid" => {}
+            _ => return Err(format!("Unsupported message type: {message}")),
+        }
+
+        let mut params = base_params.clone();
+        apply_params(&mut params, y);
+
+        let expect = &y["expect"];
+
+        let mut step = Step {
+            message: message.to_string(),
+            params,
+            repeat: y["repeat"].as_i64().unwrap_or(1) as usize,
+            expect_ok: expect["ok"].as_bool(),
+            expect_fields: Vec::new(),
+            expect_present: Vec::new(),
+        };
+
+        if let Some(hash) = expect["fields"].as_hash() {
+            for (k, v) in hash {
+                if let (Some(code), Some(value)) = (k.as_str(), yaml_string(v)) {
+                    step.expect_fields.push((code.to_string(), value));
+                }
+            }
+        }
+
+        if let Some(list) = expect["present"].as_vec() {
+            for code in list.iter().filter_map(|c| c.as_str()) {
+                step.expect_present.push(code.to_string());
+            }
+        }
+
+        scenario.steps.push(step);
+    }
+
+    Ok(scenario)
+}
+
+/// Read the command line arguments
+fn read_options() -> getopts::Matches {
+    let args: Vec<String> = env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.optopt("", "scenario", "Scenario File", "");
+    opts.optopt("", "sip-host", "SIP Host", "");
+    opts.optopt("", "parallel", "Parallel Count", "");
+    opts.optopt("", "repeat", "Repeat Count", "");
+
+    opts.optflag("h", "help", "");
+    opts.optflag("q", "quiet", "");
+
+    opts.parse(&args[1..]) // skip the command name
+        .expect("Error parsing command line options")
+}
@@ -1,7 +1,137 @@
 use eg::EgResult;
 use evergreen as eg;
+use sip2::{FilterAction, SipFilter};
+use std::collections::HashMap;
 use std::fs;
-use yaml_rust::YamlLoader;
+use yaml_rust::parser::{MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+use yaml_rust::{Event, Yaml, YamlLoader};
+
+/// Upstream value which tells the proxy to send an account's traffic
+/// to the ILS instead of an upstream SIP server.
+pub const PROXY_LOCAL: &str = "local";
+
+/// Expected type of a configuration value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    Str,
+    Int,
+    Bool,
+    Hash,
+    /// A TCP port number.
+    Port,
+}
+
+/// Keys allowed in the "sip2-mediator" section and their types.
+const CONFIG_KEYS: &[(&str, ValueType)] = &[
+    ("sip-address", ValueType::Str),
+    ("sip-port", ValueType::Port),
+    ("max-clients", ValueType::Int),
+    ("min-workers", ValueType::Int),
+    ("ascii", ValueType::Bool),
+    ("shutdown-timeout", ValueType::Int),
+    ("metrics-address", ValueType::Str),
+    ("metrics-port", ValueType::Port),
+    ("keepalive-time", ValueType::Int),
+    ("keepalive-interval", ValueType::Int),
+    ("keepalive-retries", ValueType::Int),
+    ("keep-warm-interval", ValueType::Int),
+    ("ils-check-interval", ValueType::Int),
+    ("proxy", ValueType::Hash),
+];
+
+/// Keys allowed in the "proxy" section.
+const PROXY_KEYS: &[&str] = &["default-upstream", "accounts", "local-messages", "rewrite"];
+
+/// Keys allowed in each proxy "rewrite" entry.
+const REWRITE_KEYS: &[&str] = &["field", "direction", "strip", "replace-with"];
+
+/// Relay SIP traffic to upstream SIP servers instead of the ILS.
+#[derive(Debug, Clone, Default)]
+pub struct ProxyConfig {
+    /// Upstream "host:port" for accounts with no specific mapping.
+    pub default_upstream: Option<String>,
+    /// SIP username => upstream "host:port" or "local".
+    pub accounts: HashMap<String, String>,
+    /// Message codes which are always handled by the ILS.
+    pub local_messages: Vec<String>,
+    /// Applied to messages sent by the SIP client.
+    pub request_filters: Vec<SipFilter>,
+    /// Applied to messages returned by the upstream server.
+    pub response_filters: Vec<SipFilter>,
+}
+
+impl ProxyConfig {
+    fn from_yaml(y: &Yaml) -> ProxyConfig {
+        let mut proxy = ProxyConfig::default();
+
+        if let Some(v) = y["default-upstream"].as_str() {
+            proxy.default_upstream = Some(v.to_string());
+        }
+
+        if let Some(hash) = y["accounts"].as_hash() {
+            for (k, v) in hash {
+                if let (Some(user), Some(host)) = (k.as_str(), v.as_str()) {
+                    proxy.accounts.insert(user.to_string(), host.to_string());
+                }
+            }
+        }
+
+        if let Some(list) = y["local-messages"].as_vec() {
+            for code in list.iter().filter_map(|c| c.as_str()) {
+                proxy.local_messages.push(code.to_string());
+            }
+        }
+
+        if let Some(list) = y["rewrite"].as_vec() {
+            for rw in list {
+                let field = match rw["field"].as_str() {
+                    Some(f) => f.to_string(),
+                    None => {
+                        log::warn!("Ignoring proxy rewrite with no field");
+                        continue;
+                    }
+                };
+
+                let action = if rw["strip"].as_bool().unwrap_or(false) {
+                    FilterAction::Strip
+                } else if let Some(v) = rw["replace-with"].as_str() {
+                    FilterAction::Replace(v.to_string())
+                } else {
+                    continue;
+                };
+
+                let filter = SipFilter::new(&field, action);
+                let direction = rw["direction"].as_str().unwrap_or("both");
+
+                if direction != "response" {
+                    proxy.request_filters.push(filter.clone());
+                }
+                if direction != "request" {
+                    proxy.response_filters.push(filter);
+                }
+            }
+        }
+
+        proxy
+    }
+
+    /// Returns the upstream "host:port" for a SIP account, or None if
+    /// the account's traffic is handled by the ILS.
+    pub fn upstream_for(&self, sip_user: &str) -> Option<&str> {
+        let upstream = self
+            .accounts
+            .get(sip_user)
+            .map(|u| u.as_str())
+            .or(self.default_upstream.as_deref())?;
+
+        if upstream == PROXY_LOCAL {
+            None
+        } else {
+            Some(upstream)
+        }
+    }
+}
 
 /// SIP configuration
 #[derive(Debug, Clone)]
@@ -11,6 +141,29 @@ pub struct Config {
     pub max_clients: usize,
     pub min_workers: usize,
     pub ascii: bool,
+    /// Seconds active sessions may continue relaying messages after a
+    /// shutdown request is received.
+    pub shutdown_timeout: u64,
+    pub metrics_address: String,
+    /// Prometheus metrics are only served if a port is configured.
+    pub metrics_port: Option<u16>,
+    /// Proxy mode is enabled when set.
+    pub proxy: Option<ProxyConfig>,
+    /// Seconds a SIP client connection may be idle before TCP keepalive
+    /// probes are sent.  Keepalive is disabled when not set.
+    pub keepalive_time: Option<u64>,
+    /// Seconds between unanswered keepalive probes.
+    pub keepalive_interval: u64,
+    /// Unanswered probes before the connection is considered dead.
+    pub keepalive_retries: u32,
+    /// Seconds a logged in SIP session may be idle before an SC Status
+    /// request is sent to the ILS to keep its auth session and bus
+    /// connection warm.  Disabled when not set.
+    pub keep_warm_interval: Option<u64>,
+    /// Seconds between checks that the ILS SIP service is registered
+    /// with the router.  While it is not, SIP clients are told the ILS
+    /// is offline.  Disabled when not set.
+    pub ils_check_interval: Option<u64>,
 }
 
 impl Config {
@@ -21,6 +174,15 @@ impl Config {
             max_clients: 64,
             min_workers: 1,
             ascii: true,
+            shutdown_timeout: 30,
+            metrics_address: String::from("localhost"),
+            metrics_port: None,
+            proxy: None,
+            keepalive_time: None,
+            keepalive_interval: 60,
+            keepalive_retries: 5,
+            keep_warm_interval: None,
+            ils_check_interval: None,
         }
     }
 
@@ -63,6 +225,277 @@ impl Config {
             conf.ascii = v;
         }
 
+        let v = &root["shutdown-timeout"];
+        if !v.is_badvalue() {
+            conf.shutdown_timeout = v
+                .as_i64()
+                .and_then(|t| u64::try_from(t).ok())
+                .ok_or_else(|| format!("Invalid shutdown-timeout: {v:?}"))?;
+        }
+
+        if let Some(v) = root["metrics-address"].as_str() {
+            conf.metrics_address = String::from(v);
+        }
+
+        let v = &root["metrics-port"];
+        if !v.is_badvalue() {
+            let port = v
+                .as_i64()
+                .and_then(|p| u16::try_from(p).ok())
+                .filter(|p| *p > 0)
+                .ok_or_else(|| format!("Invalid metrics-port: {v:?}"))?;
+
+            conf.metrics_port = Some(port);
+        }
+
+        if let Some(v) = root["keepalive-time"].as_i64() {
+            if v > 0 {
+                conf.keepalive_time = Some(v as u64);
+            }
+        }
+
+        if let Some(v) = root["keepalive-interval"].as_i64() {
+            conf.keepalive_interval = v as u64;
+        }
+
+        if let Some(v) = root["keepalive-retries"].as_i64() {
+            conf.keepalive_retries = v as u32;
+        }
+
+        if let Some(v) = root["keep-warm-interval"].as_i64() {
+            if v > 0 {
+                conf.keep_warm_interval = Some(v as u64);
+            }
+        }
+
+        if let Some(v) = root["ils-check-interval"].as_i64() {
+            if v > 0 {
+                conf.ils_check_interval = Some(v as u64);
+            }
+        }
+
+        if !root["proxy"].is_badvalue() {
+            conf.proxy = Some(ProxyConfig::from_yaml(&root["proxy"]));
+        }
+
         Ok(conf)
     }
 }
+
+/// Problems found while validating a configuration file.
+///
+/// Unlike Config::from_yaml(), which ignores values it does not
+/// understand, this reports every unknown key, badly typed value,
+/// and duplicate key.
+pub fn check_yaml(filename: &str) -> EgResult<Vec<String>> {
+    let yaml_text = fs::read_to_string(filename)
+        .map_err(|e| format!("Error reading SIP config {filename}: {e}"))?;
+
+    let yaml_docs = YamlLoader::load_from_str(&yaml_text)
+        .map_err(|e| format!("Error parsing SIP config {filename}: {e}"))?;
+
+    let mut problems = Vec::new();
+
+    // YamlLoader keeps only the last of any duplicate keys.
+    let mut finder = DuplicateKeyFinder::default();
+    Parser::new(yaml_text.chars())
+        .load(&mut finder, true)
+        .map_err(|e| format!("Error parsing SIP config {filename}: {e}"))?;
+
+    problems.append(&mut finder.duplicates);
+
+    let root = match yaml_docs.first() {
+        Some(doc) => &doc["sip2-mediator"],
+        None => {
+            problems.push("Config file is empty".to_string());
+            return Ok(problems);
+        }
+    };
+
+    let Some(hash) = root.as_hash() else {
+        problems.push("Config has no 'sip2-mediator' section".to_string());
+        return Ok(problems);
+    };
+
+    for key in hash.keys() {
+        let key = key.as_str().unwrap_or("");
+
+        match CONFIG_KEYS.iter().find(|(k, _)| *k == key) {
+            Some((_, vtype)) => check_value(&mut problems, key, &root[key], *vtype),
+            None => problems.push(format!(
+                "Unknown setting '{key}'; expected one of: {}",
+                CONFIG_KEYS
+                    .iter()
+                    .map(|(k, _)| *k)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    if let (Some(min), Some(max)) = (root["min-workers"].as_i64(), root["max-clients"].as_i64()) {
+        if min > max {
+            problems.push(format!(
+                "min-workers ({min}) must not exceed max-clients ({max})"
+            ));
+        }
+    }
+
+    if root["proxy"].as_hash().is_some() {
+        check_proxy(&mut problems, &root["proxy"]);
+    }
+
+    Ok(problems)
+}
+
+fn check_value(problems: &mut Vec<String>, key: &str, value: &Yaml, vtype: ValueType) {
+    let valid = match vtype {
+        ValueType::Str => value.as_str().is_some(),
+        ValueType::Int => value.as_i64().map(|v| v >= 0).unwrap_or(false),
+        ValueType::Bool => value.as_bool().is_some(),
+        ValueType::Hash => value.as_hash().is_some(),
+        ValueType::Port => value
+            .as_i64()
+            .map(|v| v > 0 && v <= u16::MAX as i64)
+            .unwrap_or(false),
+    };
+
+    if !valid {
+        let expected = match vtype {
+            ValueType::Str => "a string",
+            ValueType::Int => "a non-negative integer",
+            ValueType::Bool => "true or false",
+            ValueType::Hash => "a set of key/value pairs",
+            ValueType::Port => "a port number between 1 and 65535",
+        };
+
+        problems.push(format!("'{key}' must be {expected}; found {value:?}"));
+    }
+}
+
+fn check_proxy(problems: &mut Vec<String>, proxy: &Yaml) {
+    for (key, _) in proxy.as_hash().into_iter().flatten() {
+        let key = key.as_str().unwrap_or("");
+        if !PROXY_KEYS.contains(&key) {
+            problems.push(format!(
+                "Unknown proxy setting '{key}'; expected one of: {}",
+                PROXY_KEYS.join(", ")
+            ));
+        }
+    }
+
+    if !proxy["default-upstream"].is_badvalue() {
+        check_upstream(
+            problems,
+            "proxy default-upstream",
+            &proxy["default-upstream"],
+        );
+    }
+
+    for (user, upstream) in proxy["accounts"].as_hash().into_iter().flatten() {
+        let user = match user {
+            Yaml::String(s) => s.to_string(),
+            other => format!("{other:?}"),
+        };
+
+        check_upstream(problems, &format!("proxy account '{user}'"), upstream);
+    }
+
+    for code in proxy["local-messages"].as_vec().into_iter().flatten() {
+        if code.as_str().map(|c| c.len() != 2).unwrap_or(true) {
+            problems.push(format!(
+                "proxy local-messages must be 2-character message codes; found {code:?}"
+            ));
+        }
+    }
+
+    for rw in proxy["rewrite"].as_vec().into_iter().flatten() {
+        for (key, _) in rw.as_hash().into_iter().flatten() {
+            let key = key.as_str().unwrap_or("");
+            if !REWRITE_KEYS.contains(&key) {
+                problems.push(format!(
+                    "Unknown proxy rewrite setting '{key}'; expected one of: {}",
+                    REWRITE_KEYS.join(", ")
+                ));
+            }
+        }
+
+        if rw["field"].as_str().map(|f| f.len() != 2).unwrap_or(true) {
+            problems.push(format!(
+                "proxy rewrite requires a 2-character 'field'; found {:?}",
+                rw["field"]
+            ));
+        }
+
+        if let Some(dir) = rw["direction"].as_str() {
+            if !["request", "response", "both"].contains(&dir) {
+                problems.push(format!(
+                    "proxy rewrite direction must be request, response, or both; found '{dir}'"
+                ));
+            }
+        }
+    }
+}
+
+/// Upstreams are "host:port" or "local".
+fn check_upstream(problems: &mut Vec<String>, label: &str, value: &Yaml) {
+    let valid = match value.as_str() {
+        Some(PROXY_LOCAL) => true,
+        Some(v) => match v.rsplit_once(':') {
+            Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+            None => false,
+        },
+        None => false,
+    };
+
+    if !valid {
+        problems.push(format!(
+            "{label} must be \"host:port\" or \"{PROXY_LOCAL}\"; found {value:?}"
+        ));
+    }
+}
+
+/// Collects duplicate mapping keys from the YAML event stream.
+#[derive(Default)]
+struct DuplicateKeyFinder {
+    /// One entry per open collection.  Mappings track the keys seen so
+    /// far and whether the next node is a key.  Sequences are None.
+    stack: Vec<Option<(Vec<String>, bool)>>,
+    duplicates: Vec<String>,
+}
+
+impl DuplicateKeyFinder {
+    /// A value node completed within the current collection.
+    fn value_done(&mut self) {
+        if let Some(Some((_, expect_key))) = self.stack.last_mut() {
+            *expect_key = true;
+        }
+    }
+}
+
+impl MarkedEventReceiver for DuplicateKeyFinder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::MappingStart(_) => self.stack.push(Some((Vec::new(), true))),
+            Event::SequenceStart(_) => self.stack.push(None),
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.value_done();
+            }
+            Event::Scalar(value, ..) => match self.stack.last_mut() {
+                Some(Some((keys, expect_key))) if *expect_key => {
+                    if keys.contains(&value) {
+                        self.duplicates
+                            .push(format!("Duplicate key '{value}' at line {}", mark.line()));
+                    } else {
+                        keys.push(value);
+                    }
+                    *expect_key = false;
+                }
+                _ => self.value_done(),
+            },
+            Event::Alias(_) => self.value_done(),
+            _ => {}
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::env;
 use std::path::Path;
 
 mod conf;
+mod metrics;
 mod server;
 mod session;
 
@@ -13,34 +14,63 @@ const DEFAULT_CONFIG_2: &str = "./sip2-mediator/conf/eg-sip2-mediator.yml";
 const DEFAULT_CONFIG_3: &str = "/usr/local/etc/eg-sip2-mediator.example.yml";
 const DEFAULT_CONFIG_4: &str = "./sip2-mediator/conf/eg-sip2-mediator.example.yml";
 
-fn load_config() -> EgResult<conf::Config> {
-    if let Ok(ref file) = env::var("EG_SIP2_MEDIATOR_CONFIG") {
-        conf::Config::from_yaml(&file)
-    } else if Path::new(DEFAULT_CONFIG_1).exists() {
-        conf::Config::from_yaml(DEFAULT_CONFIG_1)
-    } else if Path::new(DEFAULT_CONFIG_2).exists() {
-        conf::Config::from_yaml(DEFAULT_CONFIG_2)
-    } else if Path::new(DEFAULT_CONFIG_3).exists() {
-        conf::Config::from_yaml(DEFAULT_CONFIG_3)
-    } else if Path::new(DEFAULT_CONFIG_4).exists() {
-        conf::Config::from_yaml(DEFAULT_CONFIG_4)
-    } else {
-        Err(format!("sip2-mediator requires a configuration file").into())
+/// Extra time given to sessions beyond the configured shutdown timeout
+/// so they can send their final End Session message to the ILS.
+const SHUTDOWN_GRACE_PERIOD: u64 = 10;
+
+fn config_file() -> EgResult<String> {
+    if let Ok(file) = env::var("EG_SIP2_MEDIATOR_CONFIG") {
+        return Ok(file);
+    }
+
+    for file in [
+        DEFAULT_CONFIG_1,
+        DEFAULT_CONFIG_2,
+        DEFAULT_CONFIG_3,
+        DEFAULT_CONFIG_4,
+    ] {
+        if Path::new(file).exists() {
+            return Ok(file.to_string());
+        }
     }
+
+    Err("sip2-mediator requires a configuration file".into())
+}
+
+/// Validate the configuration file, report any problems, and exit
+/// non-zero if any were found.
+fn check_config() -> EgResult<()> {
+    let file = config_file()?;
+    let problems = conf::check_yaml(&file)?;
+
+    if problems.is_empty() {
+        println!("{file}: OK");
+        return Ok(());
+    }
+
+    for problem in problems.iter() {
+        eprintln!("{file}: {problem}");
+    }
+
+    eprintln!("{file}: {} problem(s) found", problems.len());
+
+    std::process::exit(1);
 }
 
 fn main() -> EgResult<()> {
-    let conf = load_config()?;
+    if env::args().any(|a| a == "--check-config") {
+        return check_config();
+    }
+
+    let conf = conf::Config::from_yaml(&config_file()?)?;
     let max_workers = conf.max_clients;
     let min_workers = conf.min_workers;
+    let shutdown_timeout = conf.shutdown_timeout;
 
-    let options = eg::init::InitOptions {
-        skip_logging: false,
-        skip_host_settings: true,
-        appname: Some("sip2-mediator".to_string()),
-    };
-
-    let ctx = eg::init::with_options(&options)?;
+    let ctx = eg::init::Builder::new()
+        .appname("sip2-mediator")
+        .skip_host_settings()
+        .build()?;
 
     let stream = server::Server::setup(conf, ctx)?;
 
@@ -48,6 +78,7 @@ fn main() -> EgResult<()> {
 
     s.set_max_workers(max_workers);
     s.set_min_workers(min_workers);
+    s.set_shutdown_timeout(shutdown_timeout + SHUTDOWN_GRACE_PERIOD);
 
     // Each SIP sessions counts as one request to MPTC.
     // Use the default value for max worker requests.
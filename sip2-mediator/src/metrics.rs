@@ -0,0 +1,415 @@
+//! Prometheus metrics for SIP traffic relayed by the mediator.
+//!
+//! Metrics are collected in memory and served in the Prometheus text
+//! exposition format from a small HTTP listener running in its own
+//! thread.  Any request path returns the full set of metrics.
+use eg::osrf::bus::BusStats;
+use evergreen as eg;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds in seconds of our latency histogram buckets.
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Account label applied to messages which arrive before a SIP login.
+const UNKNOWN_ACCOUNT: &str = "unknown";
+
+/// How long we wait on a scraper to send its request.
+const SCRAPE_READ_TIMEOUT: u64 = 5;
+
+/// Translate a SIP message code into a metrics-friendly message name.
+fn message_name(code: &str) -> &str {
+    match code {
+        "99" => "sc_status",
+        "93" => "login",
+        "17" => "item_info",
+        "23" => "patron_status",
+        "63" => "patron_info",
+        "11" => "checkout",
+        "29" => "renew",
+        "65" => "renew_all",
+        "09" => "checkin",
+        "15" => "hold",
+        "35" => "end_patron_session",
+        "37" => "fee_paid",
+        "97" => "request_acs_resend",
+        "01" => "block_patron",
+        "XS" => "end_session",
+        _ => code,
+    }
+}
+
+/// Escape a label value per the Prometheus text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[derive(Default)]
+struct MessageStats {
+    requests: u64,
+    backend_errors: u64,
+    /// Per-bucket observation counts, parallel to LATENCY_BUCKETS.
+    /// Counts are not cumulative until rendered.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+/// Bus counter name, help text, and how to read its value.
+type BusCounter = (&'static str, &'static str, fn(&BusStats) -> String);
+
+/// Counters and latency histograms keyed on message name and SIP account.
+#[derive(Default)]
+pub struct Metrics {
+    stats: Mutex<HashMap<(String, String), MessageStats>>,
+    active_sessions: AtomicI64,
+    /// OpenSRF bus traffic totals keyed on bus domain.
+    bus: Mutex<HashMap<String, BusStats>>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Default::default()
+    }
+
+    fn with_stats<F>(&self, code: &str, account: Option<&str>, f: F)
+    where
+        F: FnOnce(&mut MessageStats),
+    {
+        let key = (
+            message_name(code).to_string(),
+            account.unwrap_or(UNKNOWN_ACCOUNT).to_string(),
+        );
+
+        // A poisoned lock just means another thread panicked while
+        // updating a counter.  Keep counting.
+        let mut stats = match self.stats.lock() {
+            Ok(s) => s,
+            Err(e) => e.into_inner(),
+        };
+
+        f(stats.entry(key).or_default());
+    }
+
+    /// Record a completed ILS round trip for a SIP message.
+    pub fn observe(&self, code: &str, account: Option<&str>, duration: Duration) {
+        let secs = duration.as_secs_f64();
+
+        self.with_stats(code, account, |s| {
+            s.requests += 1;
+            s.latency_sum += secs;
+            s.latency_count += 1;
+            if let Some(idx) = LATENCY_BUCKETS.iter().position(|b| secs <= *b) {
+                s.buckets[idx] += 1;
+            }
+        });
+    }
+
+    /// Record a failed ILS round trip for a SIP message.
+    pub fn backend_error(&self, code: &str, account: Option<&str>) {
+        self.with_stats(code, account, |s| {
+            s.requests += 1;
+            s.backend_errors += 1;
+        });
+    }
+
+    /// Add bus activity for a domain to our running totals.
+    pub fn observe_bus(&self, domain: &str, stats: &BusStats) {
+        let mut bus = match self.bus.lock() {
+            Ok(b) => b,
+            Err(e) => e.into_inner(),
+        };
+
+        bus.entry(domain.to_string()).or_default().add(stats);
+    }
+
+    pub fn session_started(&self) {
+        self.active_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn session_ended(&self) {
+        self.active_sessions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let stats = match self.stats.lock() {
+            Ok(s) => s,
+            Err(e) => e.into_inner(),
+        };
+
+        // Sort for stable output.
+        let mut keys: Vec<&(String, String)> = stats.keys().collect();
+        keys.sort();
+
+        let mut out = String::new();
+
+        out += "# HELP sip2_mediator_active_sessions Number of connected SIP clients.\n";
+        out += "# TYPE sip2_mediator_active_sessions gauge\n";
+        let _ = writeln!(
+            out,
+            "sip2_mediator_active_sessions {}",
+            self.active_sessions.load(Ordering::Relaxed)
+        );
+
+        out += "# HELP sip2_mediator_requests_total SIP requests relayed to the ILS.\n";
+        out += "# TYPE sip2_mediator_requests_total counter\n";
+        for key in keys.iter() {
+            let labels = Metrics::labels(key);
+            let _ = writeln!(
+                out,
+                "sip2_mediator_requests_total{{{labels}}} {}",
+                stats[*key].requests
+            );
+        }
+
+        out += "# HELP sip2_mediator_backend_errors_total SIP requests the ILS failed to answer.\n";
+        out += "# TYPE sip2_mediator_backend_errors_total counter\n";
+        for key in keys.iter() {
+            let labels = Metrics::labels(key);
+            let _ = writeln!(
+                out,
+                "sip2_mediator_backend_errors_total{{{labels}}} {}",
+                stats[*key].backend_errors
+            );
+        }
+
+        out += "# HELP sip2_mediator_request_duration_seconds ILS round trip time.\n";
+        out += "# TYPE sip2_mediator_request_duration_seconds histogram\n";
+        for key in keys.iter() {
+            let labels = Metrics::labels(key);
+            let s = &stats[*key];
+
+            let mut cumulative = 0;
+            for (idx, bound) in LATENCY_BUCKETS.iter().enumerate() {
+                cumulative += s.buckets[idx];
+                let _ = writeln!(
+                    out,
+                    "sip2_mediator_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {cumulative}"
+                );
+            }
+
+            let _ = writeln!(
+                out,
+                "sip2_mediator_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                s.latency_count
+            );
+            let _ = writeln!(
+                out,
+                "sip2_mediator_request_duration_seconds_sum{{{labels}}} {}",
+                s.latency_sum
+            );
+            let _ = writeln!(
+                out,
+                "sip2_mediator_request_duration_seconds_count{{{labels}}} {}",
+                s.latency_count
+            );
+        }
+
+        self.render_bus(&mut out);
+
+        out
+    }
+
+    fn render_bus(&self, out: &mut String) {
+        let bus = match self.bus.lock() {
+            Ok(b) => b,
+            Err(e) => e.into_inner(),
+        };
+
+        let mut domains: Vec<&String> = bus.keys().collect();
+        domains.sort();
+
+        let counters: &[BusCounter] = &[
+            (
+                "bus_messages_sent_total",
+                "Messages sent to the OpenSRF bus.",
+                |s| s.messages_sent.to_string(),
+            ),
+            (
+                "bus_messages_received_total",
+                "Messages received from the OpenSRF bus.",
+                |s| s.messages_received.to_string(),
+            ),
+            (
+                "bus_bytes_sent_total",
+                "Bytes sent to the OpenSRF bus.",
+                |s| s.bytes_sent.to_string(),
+            ),
+            (
+                "bus_bytes_received_total",
+                "Bytes received from the OpenSRF bus.",
+                |s| s.bytes_received.to_string(),
+            ),
+            (
+                "bus_reconnects_total",
+                "Reconnects to the OpenSRF bus.",
+                |s| s.reconnects.to_string(),
+            ),
+            (
+                "bus_recv_waits_total",
+                "Reads from the OpenSRF bus, including those which timed out.",
+                |s| s.recv_waits.to_string(),
+            ),
+            (
+                "bus_recv_wait_seconds_total",
+                "Time spent waiting on reads from the OpenSRF bus.",
+                |s| s.recv_wait_time.as_secs_f64().to_string(),
+            ),
+        ];
+
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP sip2_mediator_{name} {help}");
+            let _ = writeln!(out, "# TYPE sip2_mediator_{name} counter");
+
+            for domain in domains.iter() {
+                let _ = writeln!(
+                    out,
+                    "sip2_mediator_{name}{{domain=\"{}\"}} {}",
+                    escape_label(domain),
+                    value(&bus[*domain])
+                );
+            }
+        }
+    }
+
+    fn labels(key: &(String, String)) -> String {
+        format!(
+            "message=\"{}\",account=\"{}\"",
+            escape_label(&key.0),
+            escape_label(&key.1)
+        )
+    }
+}
+
+/// Serve metrics over HTTP from a background thread.
+pub fn serve(metrics: std::sync::Arc<Metrics>, address: &str, port: u16) -> Result<(), String> {
+    let bind = format!("{address}:{port}");
+
+    let listener =
+        TcpListener::bind(&bind).map_err(|e| format!("Cannot bind metrics to {bind}: {e}"))?;
+
+    log::info!("Serving metrics at {bind}");
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(s) => {
+                    if let Err(e) = respond(&metrics, s) {
+                        log::warn!("Error responding to metrics request: {e}");
+                    }
+                }
+                Err(e) => log::warn!("Metrics accept() failed: {e}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn respond(metrics: &Metrics, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(SCRAPE_READ_TIMEOUT)))?;
+
+    // We don't care what was requested, but read the request so the
+    // client isn't left with unread data when we close the socket.
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf)?;
+
+    let body = metrics.render();
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::new();
+
+        metrics.session_started();
+        metrics.session_started();
+        metrics.session_ended();
+
+        metrics.observe("11", Some("sc1"), Duration::from_millis(200));
+        metrics.observe("11", Some("sc1"), Duration::from_secs(60));
+        metrics.backend_error("11", Some("sc1"));
+        metrics.observe("93", None, Duration::from_millis(10));
+        metrics.observe("ZZ", Some("a\"b"), Duration::from_millis(10));
+
+        let text = metrics.render();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(lines.contains(&"sip2_mediator_active_sessions 1"));
+
+        let labels = r#"message="checkout",account="sc1""#;
+
+        assert!(lines.contains(&format!("sip2_mediator_requests_total{{{labels}}} 3").as_str()));
+        assert!(
+            lines.contains(&format!("sip2_mediator_backend_errors_total{{{labels}}} 1").as_str())
+        );
+
+        // Buckets are cumulative and observations past the last bound
+        // only appear in +Inf.
+        let bucket = |le: &str| {
+            format!("sip2_mediator_request_duration_seconds_bucket{{{labels},le=\"{le}\"}}")
+        };
+        assert!(lines.contains(&format!("{} 0", bucket("0.1")).as_str()));
+        assert!(lines.contains(&format!("{} 1", bucket("0.25")).as_str()));
+        assert!(lines.contains(&format!("{} 1", bucket("30")).as_str()));
+        assert!(lines.contains(&format!("{} 2", bucket("+Inf")).as_str()));
+        assert!(lines.contains(
+            &format!("sip2_mediator_request_duration_seconds_count{{{labels}}} 2").as_str()
+        ));
+
+        assert!(
+            lines.contains(&r#"sip2_mediator_requests_total{message="login",account="unknown"} 1"#)
+        );
+        assert!(lines.contains(&r#"sip2_mediator_requests_total{message="ZZ",account="a\"b"} 1"#));
+
+        // Output is sorted by message name, then account.
+        let checkout = text.find(r#"requests_total{message="checkout""#).unwrap();
+        let login = text.find(r#"requests_total{message="login""#).unwrap();
+        assert!(checkout < login);
+    }
+
+    #[test]
+    fn render_bus() {
+        let metrics = Metrics::new();
+
+        let stats = BusStats {
+            messages_sent: 2,
+            bytes_sent: 100,
+            ..Default::default()
+        };
+
+        metrics.observe_bus("private.localhost", &stats);
+        metrics.observe_bus("private.localhost", &stats);
+
+        let text = metrics.render();
+
+        assert!(
+            text.lines()
+                .any(|l| l
+                    == r#"sip2_mediator_bus_messages_sent_total{domain="private.localhost"} 4"#)
+        );
+        assert!(text
+            .lines()
+            .any(|l| l == r#"sip2_mediator_bus_bytes_sent_total{domain="private.localhost"} 200"#));
+    }
+}
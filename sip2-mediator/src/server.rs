@@ -1,6 +1,8 @@
 use super::conf::Config;
-use super::session::Session;
+use super::metrics::{self, Metrics};
+use super::session::{self, Session};
 use eg::osrf;
+use eg::osrf::client::ServiceWatcher;
 use eg::Client;
 use evergreen as eg;
 use mptc;
@@ -9,6 +11,8 @@ use std::net::TcpListener;
 use std::net::TcpStream;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 /// How often do we wake to check for shutdown signals
 const SIP_SHUTDOWN_POLL_INTERVAL: u64 = 5;
@@ -40,6 +44,11 @@ pub struct SessionFactory {
 
     /// OpenSRF bus.
     osrf_bus: Option<eg::osrf::bus::Bus>,
+
+    metrics: Arc<Metrics>,
+
+    /// False while the ILS SIP service is unavailable.
+    ils_online: Arc<AtomicBool>,
 }
 
 impl mptc::RequestHandler for SessionFactory {
@@ -75,7 +84,16 @@ impl mptc::RequestHandler for SessionFactory {
         // this request.
         let stream = request.stream.take().unwrap();
 
-        let mut session = Session::new(sip_config, osrf_bus, stream, shutdown)?;
+        let metrics = self.metrics.clone();
+
+        let mut session = Session::new(
+            sip_config,
+            osrf_bus,
+            stream,
+            shutdown,
+            metrics,
+            self.ils_online.clone(),
+        )?;
 
         if let Err(e) = session.start() {
             // This is not necessarily an error.  The client may simply
@@ -117,6 +135,12 @@ pub struct Server {
 
     /// Inbound SIP connections start here.
     tcp_listener: TcpListener,
+
+    /// Shared by all of our Sessions.
+    metrics: Arc<Metrics>,
+
+    /// Maintained by our ILS watcher thread, when enabled.
+    ils_online: Arc<AtomicBool>,
 }
 
 impl mptc::RequestStream for Server {
@@ -149,6 +173,8 @@ impl mptc::RequestStream for Server {
             shutdown: self.shutdown.clone(),
             sip_config: self.sip_config.clone(),
             osrf_bus: None, // set in worker_start
+            metrics: self.metrics.clone(),
+            ils_online: self.ils_online.clone(),
         };
 
         Box::new(sf)
@@ -178,13 +204,63 @@ impl Server {
             SIP_SHUTDOWN_POLL_INTERVAL,
         )?;
 
+        let metrics = Arc::new(Metrics::new());
+
+        if let Some(port) = config.metrics_port {
+            metrics::serve(metrics.clone(), &config.metrics_address, port)?;
+        }
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let ils_online = Arc::new(AtomicBool::new(true));
+
+        if let Some(secs) = config.ils_check_interval {
+            let shutdown = shutdown.clone();
+            let ils_online = ils_online.clone();
+            thread::spawn(move || Server::watch_ils(secs, shutdown, ils_online));
+        }
+
         let server = Server {
             client,
+            metrics,
             tcp_listener,
+            shutdown,
+            ils_online,
             sip_config: Arc::new(config),
-            shutdown: Arc::new(AtomicBool::new(false)),
         };
 
         Ok(server)
     }
+
+    /// Track whether the ILS SIP service is registered with the router
+    /// so our Sessions can switch to offline mode and back.
+    fn watch_ils(interval: u64, shutdown: Arc<AtomicBool>, ils_online: Arc<AtomicBool>) {
+        let client = match Client::connect() {
+            Ok(c) => c,
+            Err(e) => {
+                log::error!("ILS watcher cannot connect to opensrf: {e}");
+                return;
+            }
+        };
+
+        let mut watcher = ServiceWatcher::new(&[session::EG_SERVICE]);
+
+        while !shutdown.load(Ordering::Relaxed) {
+            watcher.poll_with(&client, |evt| {
+                if evt.is_up() {
+                    log::info!(
+                        "ILS service {} is back; leaving offline mode",
+                        evt.service()
+                    );
+                } else {
+                    log::warn!(
+                        "ILS service {} is gone; entering offline mode",
+                        evt.service()
+                    );
+                }
+                ils_online.store(evt.is_up(), Ordering::Relaxed);
+            });
+
+            thread::sleep(Duration::from_secs(interval));
+        }
+    }
 }
@@ -1,14 +1,20 @@
 use super::conf;
+use super::metrics::Metrics;
+use eg::osrf::bus::BusStats;
 use eg::osrf::logging;
+use eg::osrf::params::ApiParams;
 use eg::EgEvent;
 use eg::EgResult;
 use eg::EgValue;
 use evergreen as eg;
 use sip2;
+use socket2::{SockRef, TcpKeepalive};
+use std::collections::HashMap;
 use std::fmt;
 use std::net;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// How often do we wake up from blocking on our sip socket socket to check
 /// for shutdown, etc. signals.
@@ -17,9 +23,15 @@ const SIG_POLL_INTERVAL: u64 = 5;
 // TODO make configurable?
 //const EG_SERVICE: &str = "open-ils.sip2";
 //const EG_METHOD: &str = "open-ils.sip2.request";
-const EG_SERVICE: &str = "open-ils.rs-sip2";
+pub const EG_SERVICE: &str = "open-ils.rs-sip2";
 const EG_METHOD: &str = "open-ils.rs-sip2.request";
 
+/// Connection to an upstream SIP server used in proxy mode.
+struct Upstream {
+    host: String,
+    connection: sip2::Connection,
+}
+
 /// Manages the connection between a SIP client and the Evergreen backend.
 pub struct Session {
     sip_connection: sip2::Connection,
@@ -35,6 +47,31 @@ pub struct Session {
 
     /// If true, we're shutting down.
     shutdown: Arc<AtomicBool>,
+
+    /// How long we may continue relaying messages after a shutdown
+    /// request is received.
+    shutdown_timeout: Duration,
+
+    /// Set when we first notice a shutdown request.
+    drain_start: Option<Instant>,
+
+    metrics: Arc<Metrics>,
+
+    /// Bus counters as of our last report to the metrics collector.
+    /// Our Bus is reused across sessions, so we report the difference.
+    bus_stats: HashMap<String, BusStats>,
+
+    sip_config: Arc<conf::Config>,
+
+    /// Set in proxy mode once the SIP client logs in with an account
+    /// whose traffic is relayed to an upstream SIP server.
+    upstream: Option<Upstream>,
+
+    /// When we last exchanged a message with the ILS.
+    last_ils_activity: Instant,
+
+    /// False while the ILS SIP service is unavailable.
+    ils_online: Arc<AtomicBool>,
 }
 
 impl Session {
@@ -46,12 +83,27 @@ impl Session {
         osrf_bus: eg::osrf::bus::Bus,
         stream: net::TcpStream,
         shutdown: Arc<AtomicBool>,
+        metrics: Arc<Metrics>,
+        ils_online: Arc<AtomicBool>,
     ) -> EgResult<Session> {
         match stream.peer_addr() {
             Ok(a) => log::info!("New SIP connection from {a}"),
             Err(e) => return Err(format!("SIP connection has no peer addr? {e}").into()),
         }
 
+        if let Some(secs) = sip_config.keepalive_time {
+            let keepalive = TcpKeepalive::new()
+                .with_time(Duration::from_secs(secs))
+                .with_interval(Duration::from_secs(sip_config.keepalive_interval))
+                .with_retries(sip_config.keepalive_retries);
+
+            // Dead peers are detected by the kernel, which causes our
+            // next read on the socket to fail, ending the session.
+            if let Err(e) = SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+                log::warn!("Cannot enable TCP keepalive on SIP connection: {e}");
+            }
+        }
+
         // Random session key string
         let key = eg::util::random_number(16);
 
@@ -59,6 +111,7 @@ impl Session {
         con.set_ascii(sip_config.ascii);
 
         let client = eg::Client::from_bus(osrf_bus);
+        let bus_stats = client.bus_stats();
 
         let ses = Session {
             key,
@@ -66,6 +119,14 @@ impl Session {
             client,
             sip_connection: con,
             sip_user: None,
+            shutdown_timeout: Duration::from_secs(sip_config.shutdown_timeout),
+            drain_start: None,
+            metrics,
+            bus_stats,
+            sip_config,
+            upstream: None,
+            last_ils_activity: Instant::now(),
+            ils_online,
         };
 
         Ok(ses)
@@ -76,6 +137,14 @@ impl Session {
     pub fn start(&mut self) -> EgResult<()> {
         log::debug!("{self} starting");
 
+        self.metrics.session_started();
+        let result = self.listen();
+        self.metrics.session_ended();
+
+        result
+    }
+
+    fn listen(&mut self) -> EgResult<()> {
         loop {
             // Blocks waiting for a SIP request to arrive or for the
             // poll interval to timeout.
@@ -98,6 +167,11 @@ impl Session {
                         break;
                     }
 
+                    if let Err(e) = self.keep_warm() {
+                        log::error!("{self} keep-warm request failed: {e}");
+                        break;
+                    }
+
                     // Go back and start listenting again.
                     continue;
                 }
@@ -109,6 +183,9 @@ impl Session {
                 // If this is a login request, capture the SIP username
                 // for improved session logging.
                 if let Some(sip_user) = sip_req.get_field_value("CN") {
+                    if self.sip_user.is_some() {
+                        log::info!("{self} client logging in again as {sip_user}");
+                    }
                     self.sip_user = Some(sip_user.to_string());
                 }
             }
@@ -116,14 +193,21 @@ impl Session {
             // Relay the request to the Evergreen backend and wait for a
             // response.  If an error occurs, all we can do is exit and
             // cleanup, since SIP has no concept of an error response.
-            let sip_resp = match self.osrf_round_trip(sip_req) {
+            let code = sip_req.spec().code;
+            let started = Instant::now();
+
+            let sip_resp = match self.route_request(sip_req) {
                 Ok(r) => r,
                 Err(e) => {
                     log::error!("{self} error routing ILS message: {e}");
+                    self.metrics.backend_error(code, self.sip_user.as_deref());
                     break;
                 }
             };
 
+            self.metrics
+                .observe(code, self.sip_user.as_deref(), started.elapsed());
+
             log::trace!("{self} EG server replied with {sip_resp:?}");
 
             // Send the response back to the SIP client as a SIP message.
@@ -135,8 +219,8 @@ impl Session {
 
             log::debug!("{self} Successfully relayed response back to SIP client");
 
-            if self.shutdown.load(Ordering::Relaxed) {
-                log::debug!("{self} Shutdown signal received, exiting listen loop");
+            if self.drain_expired() {
+                log::info!("{self} shutdown timeout reached, exiting listen loop");
                 break;
             }
         }
@@ -146,37 +230,274 @@ impl Session {
         // Might already be disconnected
         self.sip_connection.disconnect().ok();
 
+        if let Some(upstream) = self.upstream.take() {
+            upstream.connection.disconnect().ok();
+        }
+
         // Tell the Evergreen server our session is done.
         self.send_end_session()
     }
 
+    /// Returns true if a shutdown has been requested and we have used
+    /// up our allotted time for relaying in-flight messages.
+    ///
+    /// Sessions remain open during shutdown as long as the SIP client
+    /// is actively sending messages, so a patron is not disconnected
+    /// in the middle of a transaction.  Idle sessions exit the next
+    /// time they wake to check for signals.
+    fn drain_expired(&mut self) -> bool {
+        if !self.shutdown.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let start = match self.drain_start {
+            Some(s) => s,
+            None => {
+                log::info!("{self} shutdown requested; draining session");
+                let now = Instant::now();
+                self.drain_start = Some(now);
+                now
+            }
+        };
+
+        start.elapsed() >= self.shutdown_timeout
+    }
+
+    /// Send an SC Status request to the ILS if we have been idle for
+    /// longer than the keep-warm interval.
+    ///
+    /// Loading the session in the ILS verifies, and if needed renews,
+    /// its auth session, which also keeps our bus connection active.
+    /// Only applies to logged in sessions handled by the ILS.
+    fn keep_warm(&mut self) -> EgResult<()> {
+        let interval = match self.sip_config.keep_warm_interval {
+            Some(i) => Duration::from_secs(i),
+            None => return Ok(()),
+        };
+
+        if self.sip_user.is_none() || self.upstream.is_some() || !self.ils_is_online() {
+            return Ok(());
+        }
+
+        if self.last_ils_activity.elapsed() < interval {
+            return Ok(());
+        }
+
+        log::debug!("{self} sending keep-warm SC Status to the ILS");
+
+        let msg = sip2::Message::from_ff_values(
+            "99",
+            &[
+                "0",    // status code
+                "999",  // max print width
+                "2.00", // protocol version
+            ],
+        )
+        .map_err(|e| format!("{self} cannot build SC Status: {e}"))?;
+
+        self.osrf_round_trip(&msg).map(|_| ())
+    }
+
+    fn ils_is_online(&self) -> bool {
+        self.ils_online.load(Ordering::Relaxed)
+    }
+
+    /// Handle a request destined for the ILS while the ILS is offline.
+    ///
+    /// SC Status requests are answered with the online status set to
+    /// "N" so the SIP client may switch to its own offline mode.  We
+    /// have no way to process anything else.
+    fn offline_response(&self, msg: &sip2::Message) -> EgResult<sip2::Message> {
+        if msg.spec() != &sip2::spec::M_SC_STATUS {
+            return Err(format!(
+                "{self} ILS is offline; cannot handle message {}",
+                msg.spec().code
+            )
+            .into());
+        }
+
+        log::info!("{self} ILS is offline; reporting offline status");
+
+        sip2::Message::from_ff_values(
+            "98",
+            &[
+                sip2::util::sip_bool(false), // online_status
+                sip2::util::sip_bool(false), // checkin_ok
+                sip2::util::sip_bool(false), // checkout_ok
+                sip2::util::sip_bool(false), // acs_renewal_policy
+                sip2::util::sip_bool(false), // status_update_ok
+                sip2::util::sip_bool(false), // offline_ok
+                "999",                       // timeout_period
+                "999",                       // retries_allowed
+                &sip2::util::sip_date_now(), // transaction date
+                "2.00",                      // protocol_version
+            ],
+        )
+        .map_err(|e| format!("{self} cannot build ACS Status: {e}").into())
+    }
+
     /// Send the final End Session (XS) message to the ILS.
     ///
     /// Response and errors are ignored since this is the final step
     /// in the session shuting down.
     fn send_end_session(&mut self) -> EgResult<()> {
+        if !self.ils_is_online() {
+            // Nobody is listening.  The ILS session will expire.
+            return Ok(());
+        }
+
         log::debug!("{self} sending end of session message to the ILS");
 
         let msg_spec = sip2::spec::Message::from_code("XS").unwrap();
 
         let msg = sip2::Message::new(&msg_spec, vec![], vec![]);
 
-        self.osrf_round_trip(msg).map(|_| ())
+        self.osrf_round_trip(&msg).map(|_| ())
+    }
+
+    /// Send a SIP client request to the ILS or, in proxy mode, to
+    /// the upstream SIP server for this session.
+    fn route_request(&mut self, msg: sip2::Message) -> EgResult<sip2::Message> {
+        let sip_config = self.sip_config.clone();
+
+        let proxy = match sip_config.proxy.as_ref() {
+            Some(p) => p,
+            None => return self.ils_round_trip(&msg),
+        };
+
+        if msg.spec() == &sip2::spec::M_LOGIN {
+            // (Re)select our upstream based on the login account.
+            if let Some(upstream) = self.upstream.take() {
+                upstream.connection.disconnect().ok();
+            }
+
+            let host = self
+                .sip_user
+                .as_deref()
+                .and_then(|u| proxy.upstream_for(u))
+                .map(|h| h.to_string());
+
+            if let Some(host) = host {
+                log::info!("{self} proxying session to upstream {host}");
+
+                let mut connection = sip2::Connection::new(&host)
+                    .map_err(|e| format!("{self} cannot connect to upstream {host}: {e}"))?;
+
+                connection.set_ascii(self.sip_config.ascii);
+
+                self.upstream = Some(Upstream { host, connection });
+
+                if !proxy.local_messages.is_empty() {
+                    // Locally handled messages require an ILS session.
+                    if let Err(e) = self.ils_round_trip(&msg) {
+                        log::warn!("{self} ILS login failed in proxy mode: {e}");
+                    }
+                }
+            }
+        }
+
+        let is_local = proxy.local_messages.iter().any(|c| c == msg.spec().code);
+
+        if self.upstream.is_none() || is_local {
+            return self.ils_round_trip(&msg);
+        }
+
+        self.upstream_round_trip(msg)
+    }
+
+    /// Relay a SIP client request to our upstream SIP server, applying
+    /// any configured field rewrites in both directions.
+    ///
+    /// Blocks waiting for a response.
+    fn upstream_round_trip(&mut self, mut msg: sip2::Message) -> EgResult<sip2::Message> {
+        let sip_config = self.sip_config.clone();
+
+        // Only called in proxy mode.
+        let proxy = match sip_config.proxy.as_ref() {
+            Some(p) => p,
+            None => return Err(format!("{self} proxy mode is not enabled").into()),
+        };
+
+        for filter in proxy.request_filters.iter() {
+            filter.apply(&mut msg, |_| None);
+        }
+
+        let key = self.key.as_str();
+
+        let upstream = self
+            .upstream
+            .as_mut()
+            .ok_or_else(|| format!("Ses {key} has no upstream connection"))?;
+
+        log::info!(
+            "Ses {key} relaying to {}: {}",
+            upstream.host,
+            msg.to_sip_redacted()
+        );
+
+        let mut resp = upstream
+            .connection
+            .sendrecv(&msg)
+            .map_err(|e| format!("Ses {key} upstream {} failed: {e}", upstream.host))?;
+
+        for filter in proxy.response_filters.iter() {
+            filter.apply(&mut resp, |_| None);
+        }
+
+        Ok(resp)
+    }
+
+    /// Send a SIP client request to the ILS, unless the ILS is offline.
+    fn ils_round_trip(&mut self, msg: &sip2::Message) -> EgResult<sip2::Message> {
+        if self.ils_is_online() {
+            let result = self.osrf_round_trip(msg);
+            self.report_bus_stats();
+            result
+        } else {
+            self.offline_response(msg)
+        }
+    }
+
+    /// Pass along any bus activity since our last report.
+    fn report_bus_stats(&mut self) {
+        for (domain, stats) in self.client.bus_stats() {
+            let delta = match self.bus_stats.get(&domain) {
+                Some(prev) => stats.since(prev),
+                None => stats,
+            };
+
+            self.metrics.observe_bus(&domain, &delta);
+            self.bus_stats.insert(domain, stats);
+        }
     }
 
     /// Send a SIP client request to the ILS backend for processing.
     ///
     /// Blocks waiting for a response.
-    fn osrf_round_trip(&mut self, msg: sip2::Message) -> EgResult<sip2::Message> {
+    fn osrf_round_trip(&mut self, msg: &sip2::Message) -> EgResult<sip2::Message> {
         logging::Logger::mk_log_trace();
 
         let msg_json = msg.to_json_value();
 
-        log::debug!("{self} posting message: {msg_json}");
+        // Messages carrying passwords are flagged as sensitive so the
+        // ILS keeps their params out of its logs too.
+        let sensitive = msg.has_password();
+
+        if sensitive {
+            log::debug!("{self} posting message: {}", msg.to_sip_redacted());
+        } else {
+            log::debug!("{self} posting message: {msg_json}");
+        }
 
         let msg_val = EgValue::from_json_value(msg_json)?;
 
-        let params = vec![EgValue::from(self.key.as_str()), msg_val];
+        let mut params = ApiParams::from(vec![EgValue::from(self.key.as_str()), msg_val]);
+
+        if sensitive {
+            params = params.into_sensitive();
+        }
+
+        self.last_ils_activity = Instant::now();
 
         // Uses the default request timeout (probably 60 seconds).
         let response = self
@@ -40,6 +40,12 @@ impl Client {
         self.connection.disconnect()
     }
 
+    /// Send a message and wait for the response, for messages which
+    /// have no canned action.
+    pub fn sendrecv(&mut self, msg: &Message) -> Result<Message, Error> {
+        self.connection.sendrecv(msg)
+    }
+
     /// Login to the SIP server
     ///
     /// Sets ok=true if the OK fixed field is true.
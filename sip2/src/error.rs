@@ -11,6 +11,8 @@ pub enum Error {
     NetworkError,
     NoResponseError,
     MissingParamsError,
+    LoginError,
+    PoolTimeoutError,
 }
 
 use self::Error::*;
@@ -31,6 +33,8 @@ impl fmt::Display for Error {
             UnknownMessageError => write!(f, "unknown sip message type"),
             NoResponseError => write!(f, "no message was received"),
             MissingParamsError => write!(f, "missing needed parameter values"),
+            LoginError => write!(f, "sip login failed"),
+            PoolTimeoutError => write!(f, "timed out waiting for a pooled connection"),
         }
     }
 }
@@ -0,0 +1,143 @@
+use super::message::Message;
+
+/// What a filter does to its field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterAction {
+    /// Remove all instances of the field.
+    Strip,
+    /// Replace the value of the field when present.
+    Replace(String),
+    /// Replace the value of the field, adding it if not present.
+    Set(String),
+    /// Append a new instance of the field.
+    Add(String),
+}
+
+/// Adds, replaces, or removes one field on a SIP message.
+///
+/// Values may contain {name} placeholders.  {value} is the current
+/// value of the field, {field.XX} is the value of another field in
+/// the same message, and other names are resolved by the caller.
+/// Unknown names are removed.  Values which render as empty strings
+/// are not added.
+#[derive(Debug, Clone)]
+pub struct SipFilter {
+    /// 2-character SIP field code.
+    identifier: String,
+
+    /// Message code this filter applies to; None for all messages.
+    message: Option<String>,
+
+    action: FilterAction,
+}
+
+impl SipFilter {
+    pub fn new(identifier: &str, action: FilterAction) -> SipFilter {
+        SipFilter {
+            identifier: identifier.to_string(),
+            message: None,
+            action,
+        }
+    }
+
+    /// Limit this filter to messages with the provided code.
+    pub fn for_message(mut self, code: &str) -> SipFilter {
+        self.message = Some(code.to_string());
+        self
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+    pub fn action(&self) -> &FilterAction {
+        &self.action
+    }
+
+    /// True if this filter applies to the message.
+    pub fn applies_to(&self, msg: &Message) -> bool {
+        match self.message.as_deref() {
+            Some(code) => code == msg.spec().code,
+            None => true,
+        }
+    }
+
+    /// Apply this filter to the message, resolving placeholders other
+    /// than {value} and {field.XX} via `lookup`.
+    pub fn apply<F>(&self, msg: &mut Message, lookup: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if !self.applies_to(msg) {
+            return;
+        }
+
+        let template = match &self.action {
+            FilterAction::Strip => {
+                msg.remove_field(&self.identifier, true);
+                return;
+            }
+            FilterAction::Replace(v) | FilterAction::Set(v) | FilterAction::Add(v) => v,
+        };
+
+        let current = msg.get_field_value(&self.identifier).map(|v| v.to_string());
+
+        if current.is_none() && matches!(self.action, FilterAction::Replace(_)) {
+            return;
+        }
+
+        // Fields are rendered against the message as it was before
+        // this filter was applied.
+        let value = SipFilter::render(template, current.as_deref(), |name| {
+            if let Some(code) = name.strip_prefix("field.") {
+                msg.get_field_value(code).map(|v| v.to_string())
+            } else {
+                lookup(name)
+            }
+        });
+
+        if matches!(self.action, FilterAction::Add(_)) || current.is_none() {
+            if !value.is_empty() {
+                msg.add_field(&self.identifier, &value);
+            }
+            return;
+        }
+
+        for field in msg.fields_mut().iter_mut() {
+            if field.code() == self.identifier {
+                field.set_value(&value);
+            }
+        }
+    }
+
+    /// Replace {name} placeholders in `template`.
+    fn render<F>(template: &str, current: Option<&str>, lookup: F) -> String
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut text = String::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(e) => start + e,
+                None => break,
+            };
+
+            text += &rest[..start];
+
+            let name = &rest[start + 1..end];
+            if name == "value" {
+                text += current.unwrap_or("");
+            } else if let Some(v) = lookup(name) {
+                text += &v;
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        text + rest
+    }
+}
@@ -1,12 +1,18 @@
 pub use self::connection::Connection;
 pub use self::error::Error;
+pub use self::filter::FilterAction;
+pub use self::filter::SipFilter;
 pub use self::message::Field;
 pub use self::message::FixedField;
 pub use self::message::Message;
 
 pub use self::client::Client;
+pub use self::client::SipResponse;
 pub use self::params::ParamSet;
+pub use self::pool::Pool;
+pub use self::pool::PooledClient;
 
+pub mod filter;
 pub mod spec;
 pub mod util;
 
@@ -15,6 +21,7 @@ mod connection;
 mod error;
 mod message;
 mod params;
+mod pool;
 
 #[cfg(feature = "json")]
 mod message_json;
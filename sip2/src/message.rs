@@ -6,6 +6,14 @@ use std::fmt;
 
 const PASSWORD_REDACTED: &str = "REDACTED";
 
+/// Fields whose values are redacted from logs.
+const PASSWORD_FIELDS: &[&str] = &[
+    spec::F_LOGIN_PWD.code,
+    spec::F_TERMINAL_PWD.code,
+    spec::F_PATRON_PWD.code,
+    spec::F_NEW_PATRON_PWD.code,
+];
+
 /// Fixed field with spec and value.
 ///
 /// Since fixed fields have specific length requirements, a well-known
@@ -313,7 +321,27 @@ impl Message {
         s
     }
 
-    /// Same as to_sip() but replaces the patron password 'AD' value
+    /// True if the message contains a non-empty login, terminal, or
+    /// patron password.
+    ///
+    /// ```
+    /// use sip2::{Message, Field};
+    /// use sip2::spec;
+    ///
+    /// let mut msg = Message::new(&spec::M_PATRON_STATUS, vec![], vec![]);
+    /// assert!(!msg.has_password());
+    ///
+    /// msg.add_field(spec::F_PATRON_PWD.code, "secret");
+    /// assert!(msg.has_password());
+    /// assert!(!msg.to_sip_redacted().contains("secret"));
+    /// ```
+    pub fn has_password(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|f| PASSWORD_FIELDS.contains(&f.code()) && !f.value().is_empty())
+    }
+
+    /// Same as to_sip() but replaces password values (CO, AC, AD, XP)
     /// with redacted text.
     ///
     /// Useful for logging.
@@ -325,7 +353,7 @@ impl Message {
         }
 
         for f in self.fields.iter() {
-            if f.code() == spec::F_PATRON_PWD.code {
+            if PASSWORD_FIELDS.contains(&f.code()) {
                 s += f.code();
                 s += PASSWORD_REDACTED;
                 s += "|";
@@ -0,0 +1,354 @@
+use super::client::Client;
+use super::error::Error;
+use super::params::ParamSet;
+use super::Message;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Connections idle for at least this many seconds are verified with
+/// an SC Status message before they are handed out.
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: u64 = 60;
+
+struct PoolConnection {
+    client: Client,
+    last_used: Instant,
+}
+
+impl PoolConnection {
+    fn new(client: Client) -> Self {
+        PoolConnection {
+            client,
+            last_used: Instant::now(),
+        }
+    }
+}
+
+struct PoolState {
+    idle: Vec<PoolConnection>,
+
+    /// Number of open connections, idle or checked out, including
+    /// connections in the process of being opened.
+    open: usize,
+}
+
+/// Pool of persistent, logged-in SIP connections which may be shared
+/// by many threads.
+///
+/// Dead connections are discarded and replaced as needed.
+///
+/// ```no_run
+/// use sip2::{ParamSet, Pool};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let mut params = ParamSet::new();
+/// params.set_sip_user("sip-server-login");
+/// params.set_sip_pass("sip-server-password");
+///
+/// let pool = Arc::new(Pool::new("127.0.0.1:6001", &params, 4).expect("Pool Connected"));
+///
+/// let handles: Vec<_> = (0..8)
+///     .map(|_| {
+///         let pool = pool.clone();
+///         thread::spawn(move || {
+///             // Returned to the pool when it goes out of scope.
+///             let mut client = pool.checkout().expect("Connection Available");
+///             client.sc_status().expect("SC Status").ok()
+///         })
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     assert!(handle.join().unwrap());
+/// }
+/// ```
+pub struct Pool {
+    host: String,
+    params: ParamSet,
+    size: usize,
+    checkout_timeout: Option<Duration>,
+    health_check_interval: Duration,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+impl Pool {
+    /// Opens and logs in `size` connections to the SIP server.
+    ///
+    /// * `params` - Login parameters: sip_user, sip_pass, and
+    ///   optionally location.
+    pub fn new(host: &str, params: &ParamSet, size: usize) -> Result<Self, Error> {
+        let pool = Pool {
+            host: host.to_string(),
+            params: params.clone(),
+            size,
+            checkout_timeout: None,
+            health_check_interval: Duration::from_secs(DEFAULT_HEALTH_CHECK_INTERVAL),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                open: 0,
+            }),
+            available: Condvar::new(),
+        };
+
+        for _ in 0..size {
+            let client = pool.connect()?;
+            let mut state = pool.state();
+            state.idle.push(PoolConnection::new(client));
+            state.open += 1;
+        }
+
+        Ok(pool)
+    }
+
+    /// Wait at most this many seconds in checkout() for a connection
+    /// to become available.  By default, checkout() waits indefinitely.
+    pub fn set_checkout_timeout(&mut self, secs: u64) {
+        self.checkout_timeout = Some(Duration::from_secs(secs));
+    }
+
+    /// Verify connections idle at least this many seconds before
+    /// handing them out.
+    pub fn set_health_check_interval(&mut self, secs: u64) {
+        self.health_check_interval = Duration::from_secs(secs);
+    }
+
+    /// Maximum number of connections.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of connections waiting to be checked out.
+    pub fn idle_count(&self) -> usize {
+        self.state().idle.len()
+    }
+
+    /// Number of open connections, idle or checked out.
+    pub fn open_count(&self) -> usize {
+        self.state().open
+    }
+
+    /// A panic while holding the lock leaves our state intact, so
+    /// ignore any poisoning.
+    fn state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Opens a new connection and logs in.
+    fn connect(&self) -> Result<Client, Error> {
+        let mut client = Client::new(&self.host)?;
+
+        if !client.login(&self.params)?.ok() {
+            log::error!("Pool login to {} failed", self.host);
+            client.disconnect().ok();
+            return Err(Error::LoginError);
+        }
+
+        Ok(client)
+    }
+
+    /// Opens a connection in a slot previously reserved by
+    /// incrementing our open count, releasing the slot on failure.
+    fn connect_reserved(&self) -> Result<Client, Error> {
+        self.connect().inspect_err(|_| self.release_slot())
+    }
+
+    /// Give up a connection slot so another may be opened in its place.
+    fn release_slot(&self) {
+        self.state().open -= 1;
+        self.available.notify_one();
+    }
+
+    /// True if the server answers an SC Status message.
+    fn is_healthy(client: &mut Client) -> bool {
+        matches!(client.sc_status(), Ok(r) if r.ok())
+    }
+
+    /// Returns the connection if it's still usable.
+    ///
+    /// Dead connections are closed and their slots released.
+    fn verify(&self, mut conn: PoolConnection) -> Option<PoolConnection> {
+        if conn.last_used.elapsed() < self.health_check_interval
+            || Pool::is_healthy(&mut conn.client)
+        {
+            return Some(conn);
+        }
+
+        log::warn!("Pool discarding dead connection to {}", self.host);
+
+        conn.client.disconnect().ok();
+        self.release_slot();
+
+        None
+    }
+
+    /// Check out a connection, waiting for one to become available
+    /// if all are in use.
+    ///
+    /// The connection is checked back in when the PooledClient is
+    /// dropped.
+    pub fn checkout(&self) -> Result<PooledClient<'_>, Error> {
+        let start = Instant::now();
+        let mut state = self.state();
+
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                drop(state);
+
+                if let Some(conn) = self.verify(conn) {
+                    return Ok(PooledClient::new(self, conn.client));
+                }
+
+                state = self.state();
+                continue;
+            }
+
+            if state.open < self.size {
+                // Reserve the slot so we can connect without holding
+                // the lock.
+                state.open += 1;
+                drop(state);
+
+                let client = self.connect_reserved()?;
+                return Ok(PooledClient::new(self, client));
+            }
+
+            state = match self.checkout_timeout {
+                Some(timeout) => {
+                    let remaining = timeout
+                        .checked_sub(start.elapsed())
+                        .ok_or(Error::PoolTimeoutError)?;
+
+                    self.available
+                        .wait_timeout(state, remaining)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+
+    /// Return a connection to the pool.
+    ///
+    /// Broken connections are closed.  A replacement is opened the
+    /// next time one is needed.
+    fn checkin(&self, client: Client, broken: bool) {
+        if broken {
+            log::debug!("Pool closing broken connection to {}", self.host);
+            client.disconnect().ok();
+            self.release_slot();
+            return;
+        }
+
+        self.state().idle.push(PoolConnection::new(client));
+        self.available.notify_one();
+    }
+
+    /// Send a message on a pooled connection and return the response.
+    ///
+    /// Connections which fail at the network level are replaced.
+    pub fn sendrecv(&self, msg: &Message) -> Result<Message, Error> {
+        let mut client = self.checkout()?;
+
+        client.sendrecv(msg).inspect_err(|e| {
+            if matches!(e, Error::NetworkError | Error::NoResponseError) {
+                client.set_broken();
+            }
+        })
+    }
+
+    /// Verify every idle connection, replacing any that are dead, and
+    /// reopen connections lost since the pool was created.
+    ///
+    /// Returns the number of connections opened.
+    pub fn health_check(&self) -> usize {
+        let idle = std::mem::take(&mut self.state().idle);
+        let mut opened = 0;
+
+        for mut conn in idle {
+            if Pool::is_healthy(&mut conn.client) {
+                self.checkin(conn.client, false);
+            } else {
+                log::warn!("Pool health check found dead connection to {}", self.host);
+                conn.client.disconnect().ok();
+                self.release_slot();
+            }
+        }
+
+        loop {
+            let mut state = self.state();
+
+            if state.open >= self.size {
+                break;
+            }
+
+            state.open += 1;
+            drop(state);
+
+            match self.connect_reserved() {
+                Ok(client) => {
+                    self.checkin(client, false);
+                    opened += 1;
+                }
+                Err(e) => {
+                    log::error!("Pool cannot reconnect to {}: {e}", self.host);
+                    break;
+                }
+            }
+        }
+
+        opened
+    }
+}
+
+/// A connection checked out from a Pool.
+///
+/// Derefs to a Client and returns to the pool when dropped.
+pub struct PooledClient<'a> {
+    pool: &'a Pool,
+    client: Option<Client>,
+    broken: bool,
+}
+
+impl<'a> PooledClient<'a> {
+    fn new(pool: &'a Pool, client: Client) -> Self {
+        PooledClient {
+            pool,
+            client: Some(client),
+            broken: false,
+        }
+    }
+
+    /// Close this connection instead of returning it to the pool,
+    /// e.g. after a network error.
+    pub fn set_broken(&mut self) {
+        self.broken = true;
+    }
+}
+
+impl Deref for PooledClient<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().expect("Client is set until dropped")
+    }
+}
+
+impl DerefMut for PooledClient<'_> {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().expect("Client is set until dropped")
+    }
+}
+
+impl Drop for PooledClient<'_> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.checkin(client, self.broken);
+        }
+    }
+}
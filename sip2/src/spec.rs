@@ -88,6 +88,382 @@ impl From<FeeType> for &'static str {
     }
 }
 
+/// Item Circulation Status
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CirculationStatus {
+    Other,
+    OnOrder,
+    Available,
+    Charged,
+    ChargedNotToBeRecalled,
+    InProcess,
+    Recalled,
+    WaitingOnHoldShelf,
+    WaitingToBeReshelved,
+    InTransit,
+    ClaimedReturned,
+    Lost,
+    Missing,
+}
+
+impl TryFrom<&str> for CirculationStatus {
+    type Error = String;
+
+    fn try_from(cs: &str) -> Result<CirculationStatus, Self::Error> {
+        match cs {
+            "01" => Ok(Self::Other),
+            "02" => Ok(Self::OnOrder),
+            "03" => Ok(Self::Available),
+            "04" => Ok(Self::Charged),
+            "05" => Ok(Self::ChargedNotToBeRecalled),
+            "06" => Ok(Self::InProcess),
+            "07" => Ok(Self::Recalled),
+            "08" => Ok(Self::WaitingOnHoldShelf),
+            "09" => Ok(Self::WaitingToBeReshelved),
+            "10" => Ok(Self::InTransit),
+            "11" => Ok(Self::ClaimedReturned),
+            "12" => Ok(Self::Lost),
+            "13" => Ok(Self::Missing),
+            _ => Err(format!("Unknown circulation status: {cs}")),
+        }
+    }
+}
+
+impl From<CirculationStatus> for &'static str {
+    fn from(cs: CirculationStatus) -> &'static str {
+        match cs {
+            CirculationStatus::Other => "01",
+            CirculationStatus::OnOrder => "02",
+            CirculationStatus::Available => "03",
+            CirculationStatus::Charged => "04",
+            CirculationStatus::ChargedNotToBeRecalled => "05",
+            CirculationStatus::InProcess => "06",
+            CirculationStatus::Recalled => "07",
+            CirculationStatus::WaitingOnHoldShelf => "08",
+            CirculationStatus::WaitingToBeReshelved => "09",
+            CirculationStatus::InTransit => "10",
+            CirculationStatus::ClaimedReturned => "11",
+            CirculationStatus::Lost => "12",
+            CirculationStatus::Missing => "13",
+        }
+    }
+}
+
+/// Item Security Marker
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SecurityMarker {
+    Other,
+    None,
+    TattleTape,
+    WhisperTape,
+}
+
+impl TryFrom<&str> for SecurityMarker {
+    type Error = String;
+
+    fn try_from(sm: &str) -> Result<SecurityMarker, Self::Error> {
+        match sm {
+            "00" => Ok(Self::Other),
+            "01" => Ok(Self::None),
+            "02" => Ok(Self::TattleTape),
+            "03" => Ok(Self::WhisperTape),
+            _ => Err(format!("Unknown security marker: {sm}")),
+        }
+    }
+}
+
+impl From<SecurityMarker> for &'static str {
+    fn from(sm: SecurityMarker) -> &'static str {
+        match sm {
+            SecurityMarker::Other => "00",
+            SecurityMarker::None => "01",
+            SecurityMarker::TattleTape => "02",
+            SecurityMarker::WhisperTape => "03",
+        }
+    }
+}
+
+/// Patron and SC Languages
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Language {
+    Unknown,
+    English,
+    French,
+    German,
+    Italian,
+    Dutch,
+    Swedish,
+    Finnish,
+    Spanish,
+    Danish,
+    Portuguese,
+    CanadianFrench,
+    Norwegian,
+    Hebrew,
+    Japanese,
+    Russian,
+    Arabic,
+    Polish,
+    Greek,
+    Chinese,
+    Korean,
+    NorthAmericanSpanish,
+    Tamil,
+    Malay,
+    UnitedKingdom,
+    Icelandic,
+    Belgian,
+    Taiwanese,
+}
+
+impl TryFrom<&str> for Language {
+    type Error = String;
+
+    fn try_from(lang: &str) -> Result<Language, Self::Error> {
+        match lang {
+            "000" => Ok(Self::Unknown),
+            "001" => Ok(Self::English),
+            "002" => Ok(Self::French),
+            "003" => Ok(Self::German),
+            "004" => Ok(Self::Italian),
+            "005" => Ok(Self::Dutch),
+            "006" => Ok(Self::Swedish),
+            "007" => Ok(Self::Finnish),
+            "008" => Ok(Self::Spanish),
+            "009" => Ok(Self::Danish),
+            "010" => Ok(Self::Portuguese),
+            "011" => Ok(Self::CanadianFrench),
+            "012" => Ok(Self::Norwegian),
+            "013" => Ok(Self::Hebrew),
+            "014" => Ok(Self::Japanese),
+            "015" => Ok(Self::Russian),
+            "016" => Ok(Self::Arabic),
+            "017" => Ok(Self::Polish),
+            "018" => Ok(Self::Greek),
+            "019" => Ok(Self::Chinese),
+            "020" => Ok(Self::Korean),
+            "021" => Ok(Self::NorthAmericanSpanish),
+            "022" => Ok(Self::Tamil),
+            "023" => Ok(Self::Malay),
+            "024" => Ok(Self::UnitedKingdom),
+            "025" => Ok(Self::Icelandic),
+            "026" => Ok(Self::Belgian),
+            "027" => Ok(Self::Taiwanese),
+            _ => Err(format!("Unknown language code: {lang}")),
+        }
+    }
+}
+
+impl From<Language> for &'static str {
+    fn from(lang: Language) -> &'static str {
+        match lang {
+            Language::Unknown => "000",
+            Language::English => "001",
+            Language::French => "002",
+            Language::German => "003",
+            Language::Italian => "004",
+            Language::Dutch => "005",
+            Language::Swedish => "006",
+            Language::Finnish => "007",
+            Language::Spanish => "008",
+            Language::Danish => "009",
+            Language::Portuguese => "010",
+            Language::CanadianFrench => "011",
+            Language::Norwegian => "012",
+            Language::Hebrew => "013",
+            Language::Japanese => "014",
+            Language::Russian => "015",
+            Language::Arabic => "016",
+            Language::Polish => "017",
+            Language::Greek => "018",
+            Language::Chinese => "019",
+            Language::Korean => "020",
+            Language::NorthAmericanSpanish => "021",
+            Language::Tamil => "022",
+            Language::Malay => "023",
+            Language::UnitedKingdom => "024",
+            Language::Icelandic => "025",
+            Language::Belgian => "026",
+            Language::Taiwanese => "027",
+        }
+    }
+}
+
+/// Patron Information Summary
+///
+/// The position of the single "Y" in the summary fixed field selects
+/// which list of items to return.
+///
+/// ```
+/// use sip2::spec::SummaryIndex;
+///
+/// let index = SummaryIndex::try_from(" Y        ").unwrap();
+/// assert_eq!(index, SummaryIndex::OverdueItems);
+///
+/// let summary: &str = SummaryIndex::FineItems.into();
+/// assert_eq!(summary, "   Y      ");
+///
+/// assert!(SummaryIndex::try_from("          ").is_err());
+/// ```
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SummaryIndex {
+    HoldItems,
+    OverdueItems,
+    ChargedItems,
+    FineItems,
+    RecallItems,
+    UnavailableHolds,
+}
+
+impl TryFrom<&str> for SummaryIndex {
+    type Error = String;
+
+    fn try_from(summary: &str) -> Result<SummaryIndex, Self::Error> {
+        match summary.find('Y') {
+            Some(0) => Ok(Self::HoldItems),
+            Some(1) => Ok(Self::OverdueItems),
+            Some(2) => Ok(Self::ChargedItems),
+            Some(3) => Ok(Self::FineItems),
+            Some(4) => Ok(Self::RecallItems),
+            Some(5) => Ok(Self::UnavailableHolds),
+            _ => Err(format!("Unknown summary value: '{summary}'")),
+        }
+    }
+}
+
+impl From<SummaryIndex> for &'static str {
+    fn from(si: SummaryIndex) -> &'static str {
+        match si {
+            SummaryIndex::HoldItems => "Y         ",
+            SummaryIndex::OverdueItems => " Y        ",
+            SummaryIndex::ChargedItems => "  Y       ",
+            SummaryIndex::FineItems => "   Y      ",
+            SummaryIndex::RecallItems => "    Y     ",
+            SummaryIndex::UnavailableHolds => "     Y    ",
+        }
+    }
+}
+
+/// Hold Request Hold Modes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HoldMode {
+    Add,
+    Delete,
+    Change,
+}
+
+impl TryFrom<&str> for HoldMode {
+    type Error = String;
+
+    fn try_from(hm: &str) -> Result<HoldMode, Self::Error> {
+        match hm {
+            "+" => Ok(Self::Add),
+            "-" => Ok(Self::Delete),
+            "*" => Ok(Self::Change),
+            _ => Err(format!("Unknown hold mode: {hm}")),
+        }
+    }
+}
+
+impl From<HoldMode> for &'static str {
+    fn from(hm: HoldMode) -> &'static str {
+        match hm {
+            HoldMode::Add => "+",
+            HoldMode::Delete => "-",
+            HoldMode::Change => "*",
+        }
+    }
+}
+
+/// Hold Request Hold Types
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HoldType {
+    Other,
+    AnyCopyOfTitle,
+    SpecificCopy,
+    AnyCopyAtBranch,
+}
+
+impl TryFrom<&str> for HoldType {
+    type Error = String;
+
+    fn try_from(ht: &str) -> Result<HoldType, Self::Error> {
+        match ht {
+            "1" => Ok(Self::Other),
+            "2" => Ok(Self::AnyCopyOfTitle),
+            "3" => Ok(Self::SpecificCopy),
+            "4" => Ok(Self::AnyCopyAtBranch),
+            _ => Err(format!("Unknown hold type: {ht}")),
+        }
+    }
+}
+
+impl From<HoldType> for &'static str {
+    fn from(ht: HoldType) -> &'static str {
+        match ht {
+            HoldType::Other => "1",
+            HoldType::AnyCopyOfTitle => "2",
+            HoldType::SpecificCopy => "3",
+            HoldType::AnyCopyAtBranch => "4",
+        }
+    }
+}
+
+/// Item Media Types
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MediaType {
+    Other,
+    Book,
+    Magazine,
+    BoundJournal,
+    AudioTape,
+    VideoTape,
+    CdOrCdRom,
+    Diskette,
+    BookWithDiskette,
+    BookWithCd,
+    BookWithAudioTape,
+}
+
+impl TryFrom<&str> for MediaType {
+    type Error = String;
+
+    fn try_from(mt: &str) -> Result<MediaType, Self::Error> {
+        match mt {
+            "000" => Ok(Self::Other),
+            "001" => Ok(Self::Book),
+            "002" => Ok(Self::Magazine),
+            "003" => Ok(Self::BoundJournal),
+            "004" => Ok(Self::AudioTape),
+            "005" => Ok(Self::VideoTape),
+            "006" => Ok(Self::CdOrCdRom),
+            "007" => Ok(Self::Diskette),
+            "008" => Ok(Self::BookWithDiskette),
+            "009" => Ok(Self::BookWithCd),
+            "010" => Ok(Self::BookWithAudioTape),
+            _ => Err(format!("Unknown media type: {mt}")),
+        }
+    }
+}
+
+impl From<MediaType> for &'static str {
+    fn from(mt: MediaType) -> &'static str {
+        match mt {
+            MediaType::Other => "000",
+            MediaType::Book => "001",
+            MediaType::Magazine => "002",
+            MediaType::BoundJournal => "003",
+            MediaType::AudioTape => "004",
+            MediaType::VideoTape => "005",
+            MediaType::CdOrCdRom => "006",
+            MediaType::Diskette => "007",
+            MediaType::BookWithDiskette => "008",
+            MediaType::BookWithCd => "009",
+            MediaType::BookWithAudioTape => "010",
+        }
+    }
+}
+
 /// Fixed field definition with label and field length
 #[derive(PartialEq, Debug)]
 pub struct FixedField {
@@ -211,6 +587,7 @@ impl Field {
             f if f == F_PATRON_CLASS.code => Some(&F_PATRON_CLASS),
             f if f == F_REGISTER_LOGIN.code => Some(&F_REGISTER_LOGIN),
             f if f == F_CHECK_NUMBER.code => Some(&F_CHECK_NUMBER),
+            f if f == F_NEW_PATRON_PWD.code => Some(&F_NEW_PATRON_PWD),
             _ => None,
         }
     }
@@ -273,7 +650,11 @@ impl Message {
             m if m == M_END_PATRON_SESSION_RESP.code => Some(&M_END_PATRON_SESSION_RESP),
             m if m == M_END_SESSION.code => Some(&M_END_SESSION),
             m if m == M_END_SESSION_RESP.code => Some(&M_END_SESSION_RESP),
+            m if m == M_PATRON_PWD_CHANGE.code => Some(&M_PATRON_PWD_CHANGE),
+            m if m == M_PATRON_PWD_CHANGE_RESP.code => Some(&M_PATRON_PWD_CHANGE_RESP),
             m if m == M_BLOCK_PATRON.code => Some(&M_BLOCK_PATRON),
+            m if m == M_PATRON_ENABLE.code => Some(&M_PATRON_ENABLE),
+            m if m == M_PATRON_ENABLE_RESP.code => Some(&M_PATRON_ENABLE_RESP),
             m if m == M_REQUEST_ACS_RESEND.code => Some(&M_REQUEST_ACS_RESEND),
             _ => None,
         }
@@ -776,6 +1157,13 @@ pub const F_CHECK_NUMBER: F = F {
     label: "check number",
 };
 
+/// New patron password for the custom Patron Password Change (81)
+/// message.
+pub const F_NEW_PATRON_PWD: F = F {
+    code: "XP",
+    label: "new patron password",
+};
+
 // NOTE: when adding new fields, be sure to also add the new
 // to Field::from_code()
 
@@ -1021,6 +1409,20 @@ pub const M_BLOCK_PATRON: Message = Message {
     fixed_fields: &[&FF_CARD_RETAINED, &FF_DATE],
 };
 
+/// Message 25
+pub const M_PATRON_ENABLE: Message = Message {
+    code: "25",
+    label: "Patron Enable",
+    fixed_fields: &[&FF_DATE],
+};
+
+/// Message 26
+pub const M_PATRON_ENABLE_RESP: Message = Message {
+    code: "26",
+    label: "Patron Enable Response",
+    fixed_fields: &[&FF_PATRON_STATUS, &FF_LANGUAGE, &FF_DATE],
+};
+
 // Custom "end session" messages for SIP2Mediator.
 // This differs from the "End Patron Session" (35) message in that it's
 // not about a patron but about a SIP client session, which can involve
@@ -1040,10 +1442,30 @@ pub const M_END_SESSION_RESP: Message = Message {
     fixed_fields: &[],
 };
 
+// Custom patron password change messages.
+// Lets self-service kiosks change a patron's password after verifying
+// the current password (AD).  The new password travels in the custom
+// XP field.
+
+/// Custom 81 (Patron Password Change) Message
+pub const M_PATRON_PWD_CHANGE: Message = Message {
+    code: "81",
+    label: "Patron Password Change",
+    fixed_fields: &[&FF_DATE],
+};
+
+/// Custom 82 (Patron Password Change Response) Message
+pub const M_PATRON_PWD_CHANGE_RESP: Message = Message {
+    code: "82",
+    label: "Patron Password Change Response",
+    fixed_fields: &[&FF_OK, &FF_DATE],
+};
+
 // NOTE: when adding new message types, be sure to also add the new
 // message to Message::from_code()
 
-#[derive(Debug, PartialEq)]
+/// Checkin Alert Types
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum CheckinAlert {
     Unknown,
     LocalHold,
@@ -1072,6 +1494,9 @@ impl TryFrom<&str> for CheckinAlert {
     }
 }
 
+/// Name used by sip2-server.
+pub type AlertType = CheckinAlert;
+
 impl From<CheckinAlert> for &str {
     fn from(a: CheckinAlert) -> &'static str {
         match a {
@@ -51,3 +51,193 @@ fn fixed_field_to_str() {
     let ff = FixedField::new(&spec::FF_MAX_PRINT_WIDTH, "999").unwrap();
     assert_eq!(ff.to_sip(), "999");
 }
+
+/// Starts a SIP server on a local port which accepts any login.
+///
+/// Returns the server address and a count of connections accepted.
+fn start_login_server() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            let mut con = crate::Connection::from_stream(stream.unwrap());
+
+            std::thread::spawn(move || {
+                while let Ok(msg) = con.recv() {
+                    if msg.spec().code == spec::M_LOGIN.code {
+                        let resp = Message::from_values("94", &["1"], &[]).unwrap();
+                        if con.send(&resp).is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    (addr, accepted)
+}
+
+#[test]
+fn pool_checkout_checkin() {
+    use std::sync::atomic::Ordering;
+
+    let (addr, accepted) = start_login_server();
+
+    let mut params = crate::ParamSet::new();
+    params.set_sip_user("sip-user");
+    params.set_sip_pass("sip-pass");
+
+    let mut pool = crate::Pool::new(&addr, &params, 2).unwrap();
+    pool.set_checkout_timeout(1);
+
+    assert_eq!(pool.idle_count(), 2);
+
+    let first = pool.checkout().unwrap();
+    let mut second = pool.checkout().unwrap();
+
+    assert_eq!(pool.idle_count(), 0);
+    assert!(matches!(
+        pool.checkout(),
+        Err(crate::Error::PoolTimeoutError)
+    ));
+
+    drop(first);
+    assert_eq!(pool.idle_count(), 1);
+
+    // Broken connections are closed and replaced on demand.
+    second.set_broken();
+    drop(second);
+
+    assert_eq!(pool.open_count(), 1);
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+
+    let _first = pool.checkout().unwrap();
+    let _second = pool.checkout().unwrap();
+
+    assert_eq!(pool.open_count(), 2);
+    assert_eq!(accepted.load(Ordering::SeqCst), 3);
+}
+
+/// Every code which parses must convert back to the same code.
+///
+/// Returns the number of codes which parsed.
+fn assert_code_round_trips<T, E>(width: usize) -> usize
+where
+    T: for<'a> TryFrom<&'a str, Error = E> + Into<&'static str>,
+{
+    let mut count = 0;
+
+    for num in 0..10_usize.pow(width as u32) {
+        let code = format!("{num:0width$}");
+
+        if let Ok(value) = T::try_from(&code) {
+            let back: &str = value.into();
+            assert_eq!(back, code);
+            count += 1;
+        }
+    }
+
+    count
+}
+
+#[test]
+fn fixed_field_code_round_trips() {
+    assert_eq!(assert_code_round_trips::<spec::CirculationStatus, _>(2), 13);
+    assert_eq!(assert_code_round_trips::<spec::SecurityMarker, _>(2), 4);
+    assert_eq!(assert_code_round_trips::<spec::FeeType, _>(2), 9);
+    assert_eq!(assert_code_round_trips::<spec::PayType, _>(2), 4);
+    assert_eq!(assert_code_round_trips::<spec::Language, _>(3), 28);
+    assert_eq!(assert_code_round_trips::<spec::CheckinAlert, _>(2), 6);
+    assert_eq!(assert_code_round_trips::<spec::HoldType, _>(1), 4);
+    assert_eq!(assert_code_round_trips::<spec::MediaType, _>(3), 11);
+}
+
+#[test]
+fn hold_mode_round_trips() {
+    for mode in ["+", "-", "*"] {
+        let back: &str = spec::HoldMode::try_from(mode).unwrap().into();
+        assert_eq!(back, mode);
+    }
+
+    assert!(spec::HoldMode::try_from("X").is_err());
+}
+
+#[test]
+fn summary_index_round_trips() {
+    let mut count = 0;
+
+    for pos in 0..spec::FF_SUMMARY.length {
+        let summary = format!("{}Y{}", " ".repeat(pos), " ".repeat(9 - pos));
+
+        if let Ok(index) = spec::SummaryIndex::try_from(summary.as_str()) {
+            let back: &str = index.into();
+            assert_eq!(back, summary);
+            assert_eq!(back.len(), spec::FF_SUMMARY.length);
+            count += 1;
+        }
+    }
+
+    assert_eq!(count, 6);
+}
+
+#[test]
+fn password_change_message() {
+    let msg = Message::from_sip("8120240102    030405AAbarcode|ADold-pw|XPnew-pw|").unwrap();
+
+    assert_eq!(msg.spec().code, spec::M_PATRON_PWD_CHANGE.code);
+    assert_eq!(msg.get_field_value("XP"), Some("new-pw"));
+    assert!(msg.has_password());
+
+    let redacted = msg.to_sip_redacted();
+    assert!(!redacted.contains("old-pw"));
+    assert!(!redacted.contains("new-pw"));
+}
+
+#[test]
+fn sip_filters() {
+    use super::filter::{FilterAction, SipFilter};
+
+    let mut msg = Message::from_sip("1803010020240102    030405ABitem-1|AJTitle|AQmain|").unwrap();
+    let lookup = |name: &str| (name == "item.circ_lib").then(|| "BR1".to_string());
+
+    SipFilter::new(
+        "AJ",
+        FilterAction::Replace("{value} ({field.AB})".to_string()),
+    )
+    .apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("AJ"), Some("Title (item-1)"));
+
+    // Replace only modifies fields which are present.
+    SipFilter::new("CR", FilterAction::Replace("x".to_string())).apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("CR"), None);
+
+    SipFilter::new("AQ", FilterAction::Set("{item.circ_lib}".to_string())).apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("AQ"), Some("BR1"));
+
+    SipFilter::new("CR", FilterAction::Set("{item.unknown}".to_string())).apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("CR"), None);
+
+    SipFilter::new("AF", FilterAction::Add("one".to_string())).apply(&mut msg, lookup);
+    SipFilter::new("AF", FilterAction::Add("two".to_string())).apply(&mut msg, lookup);
+    assert_eq!(msg.fields().iter().filter(|f| f.code() == "AF").count(), 2);
+
+    // Filters scoped to another message code are ignored.
+    SipFilter::new("AB", FilterAction::Strip)
+        .for_message("12")
+        .apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("AB"), Some("item-1"));
+
+    SipFilter::new("AF", FilterAction::Strip)
+        .for_message("18")
+        .apply(&mut msg, lookup);
+    assert_eq!(msg.get_field_value("AF"), None);
+}